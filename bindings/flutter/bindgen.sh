@@ -0,0 +1,5 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+cbindgen --config cbindgen.toml --crate stronghold_flutter --output stronghold_flutter.h
+dart run ffigen --config ffigen.yaml
@@ -0,0 +1,180 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Dart and Flutter bindings of the client interface.
+//!
+//! The functions are exported with a C interface, whose header is generated with cbindgen and consumed by
+//! `ffigen`. They are built on the native bindings, whose instances are referenced by handles of the
+//! [`registry`] here, so they can be used from any isolate. Every fallible function returns a
+//! [`StrongholdErrorCode`], the description of the last error of the calling thread is returned by
+//! [`stronghold_flutter_get_last_error`].
+
+mod registry;
+
+use std::{os::raw::c_char, slice};
+
+use iota_stronghold::Argon2Params;
+use stronghold_native::{
+    c_str, check_out, stronghold_destroy_byte_buffer, stronghold_destroy_error, stronghold_get_last_error, try_ffi,
+    write_out, StrongholdByteBuffer, StrongholdWrapper, WrapperError,
+};
+
+pub use stronghold_native::StrongholdErrorCode;
+
+unsafe fn bytes<'a>(ptr: *const u8, len: libc::size_t, name: &'static str) -> Result<&'a [u8], WrapperError> {
+    if ptr.is_null() {
+        return Err(WrapperError::NullPointer(name));
+    }
+    Ok(slice::from_raw_parts(ptr, len))
+}
+
+/// Returns the description of the last error of the calling thread, or null. The string has to be freed
+/// with [`stronghold_flutter_destroy_error`].
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_flutter_get_last_error() -> *const c_char {
+    stronghold_get_last_error()
+}
+
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_flutter_destroy_error(s: *mut c_char) {
+    stronghold_destroy_error(s)
+}
+
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_flutter_destroy_buffer(buffer: StrongholdByteBuffer) {
+    stronghold_destroy_byte_buffer(buffer)
+}
+
+/// Creates a new snapshot at `snapshot_path_c`, whose key is derived from `password_c`, and writes the
+/// handle of the instance to `out`.
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_flutter_create(
+    snapshot_path_c: *const c_char,
+    password_c: *const c_char,
+    out: *mut u64,
+) -> StrongholdErrorCode {
+    try_ffi!(check_out(out));
+
+    let snapshot_path = try_ffi!(c_str(snapshot_path_c, "snapshot_path_c")).to_string();
+    let password = try_ffi!(c_str(password_c, "password_c"));
+
+    let instance = try_ffi!(StrongholdWrapper::create_new(
+        snapshot_path,
+        password,
+        Argon2Params::default()
+    ));
+
+    write_out(out, try_ffi!(registry::insert(instance)))
+}
+
+/// Loads the snapshot at `snapshot_path_c`, whose key is derived from `password_c`, and writes the handle
+/// of the instance to `out`.
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_flutter_load(
+    snapshot_path_c: *const c_char,
+    password_c: *const c_char,
+    out: *mut u64,
+) -> StrongholdErrorCode {
+    try_ffi!(check_out(out));
+
+    let snapshot_path = try_ffi!(c_str(snapshot_path_c, "snapshot_path_c")).to_string();
+    let password = try_ffi!(c_str(password_c, "password_c"));
+
+    let instance = try_ffi!(StrongholdWrapper::from_file(snapshot_path, password));
+
+    write_out(out, try_ffi!(registry::insert(instance)))
+}
+
+/// Removes the instance of `handle`. Calls on other isolates, that are using it, complete.
+#[no_mangle]
+pub extern "C" fn stronghold_flutter_destroy(handle: u64) -> StrongholdErrorCode {
+    try_ffi!(registry::remove(handle));
+
+    StrongholdErrorCode::Ok
+}
+
+/// Writes `data_length` bytes of `data_c` to the record at `record_path_c`, and commits the snapshot.
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_flutter_write_secret(
+    handle: u64,
+    record_path_c: *const c_char,
+    data_c: *const u8,
+    data_length: libc::size_t,
+) -> StrongholdErrorCode {
+    let record_path = try_ffi!(c_str(record_path_c, "record_path_c")).to_string();
+    let data = try_ffi!(bytes(data_c, data_length, "data_c"));
+
+    let instance = try_ffi!(registry::get(handle));
+    try_ffi!(instance.write_vault(record_path, data.to_vec()));
+
+    StrongholdErrorCode::Ok
+}
+
+/// Generates an Ed25519 key in the record at `record_path_c`, and commits the snapshot.
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_flutter_generate_ed25519(
+    handle: u64,
+    record_path_c: *const c_char,
+) -> StrongholdErrorCode {
+    let record_path = try_ffi!(c_str(record_path_c, "record_path_c")).to_string();
+
+    let instance = try_ffi!(registry::get(handle));
+    try_ffi!(instance.generate_ed25519_keypair(record_path));
+
+    StrongholdErrorCode::Ok
+}
+
+/// Writes the public key of the Ed25519 key in the record at `record_path_c` to `out`. The buffer has to be
+/// freed with [`stronghold_flutter_destroy_buffer`].
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_flutter_ed25519_public_key(
+    handle: u64,
+    record_path_c: *const c_char,
+    out: *mut StrongholdByteBuffer,
+) -> StrongholdErrorCode {
+    try_ffi!(check_out(out));
+
+    let record_path = try_ffi!(c_str(record_path_c, "record_path_c")).to_string();
+
+    let instance = try_ffi!(registry::get(handle));
+    let public_key = try_ffi!(instance.get_public_key(record_path));
+
+    write_out(out, public_key.into())
+}
+
+/// Writes the signature of `msg_length` bytes of `msg_c` with the Ed25519 key in the record at
+/// `record_path_c` to `out`. The buffer has to be freed with [`stronghold_flutter_destroy_buffer`].
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_flutter_ed25519_sign(
+    handle: u64,
+    record_path_c: *const c_char,
+    msg_c: *const u8,
+    msg_length: libc::size_t,
+    out: *mut StrongholdByteBuffer,
+) -> StrongholdErrorCode {
+    try_ffi!(check_out(out));
+
+    let record_path = try_ffi!(c_str(record_path_c, "record_path_c")).to_string();
+    let msg = try_ffi!(bytes(msg_c, msg_length, "msg_c"));
+
+    let instance = try_ffi!(registry::get(handle));
+    let signature = try_ffi!(instance.sign(record_path, msg.to_vec()));
+
+    write_out(out, signature.into())
+}
@@ -0,0 +1,56 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Instances are not handed to Dart as pointers, but as opaque handles into a registry. The registry and
+//! the instances are `Send` and `Sync`, so a handle can be passed between isolates, which run on different
+//! threads, and be used from any of them. Unknown or destroyed handles are rejected instead of being
+//! dereferenced.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use lazy_static::lazy_static;
+use stronghold_native::{StrongholdWrapper, WrapperError};
+
+lazy_static! {
+    static ref INSTANCES: RwLock<HashMap<u64, Arc<StrongholdWrapper>>> = RwLock::new(HashMap::new());
+}
+
+// handles start at 1, so that 0 is never a valid handle
+static NEXT_HANDLE: AtomicU64 = AtomicU64::new(1);
+
+/// Registers `instance` and returns its handle
+pub fn insert(instance: StrongholdWrapper) -> Result<u64, WrapperError> {
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    INSTANCES
+        .write()
+        .map_err(|_| WrapperError::LockAcquireFailed)?
+        .insert(handle, Arc::new(instance));
+    Ok(handle)
+}
+
+/// Returns the instance of `handle`. It stays valid until the returned reference is dropped, even if the
+/// handle is removed concurrently.
+pub fn get(handle: u64) -> Result<Arc<StrongholdWrapper>, WrapperError> {
+    INSTANCES
+        .read()
+        .map_err(|_| WrapperError::LockAcquireFailed)?
+        .get(&handle)
+        .cloned()
+        .ok_or(WrapperError::InvalidHandle(handle))
+}
+
+/// Removes the instance of `handle`
+pub fn remove(handle: u64) -> Result<(), WrapperError> {
+    INSTANCES
+        .write()
+        .map_err(|_| WrapperError::LockAcquireFailed)?
+        .remove(&handle)
+        .map(|_| ())
+        .ok_or(WrapperError::InvalidHandle(handle))
+}
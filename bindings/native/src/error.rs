@@ -0,0 +1,124 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use iota_stronghold::{procedures::ProcedureError, ClientError, MemoryError};
+
+use crate::wrapper::WrapperError;
+
+/// The result of every `stronghold_*` call, that can fail.
+///
+/// The values are stable and will not be reassigned, new codes are only appended to their group. On
+/// failure, `stronghold_get_last_error` returns a description of the error, that is meant for logs and not
+/// for branching on.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrongholdErrorCode {
+    Ok = 0,
+
+    // invalid arguments
+    NullPointer = 1,
+    InvalidString = 2,
+    InvalidHandle = 3,
+    InvalidArgument = 4,
+
+    // failed operations, if there is no more specific cause
+    OpenSnapshot = 100,
+    CommitToSnapshot = 101,
+    CreateClient = 102,
+    WriteClient = 103,
+    ExecuteProcedure = 104,
+    KeyDerivation = 105,
+    MigrateSnapshot = 106,
+    ImportSnapshot = 107,
+
+    // client errors
+    LockAcquireFailed = 200,
+    SnapshotFileMissing = 201,
+    ClientDataNotPresent = 202,
+    IllegalKeySize = 203,
+    QuotaExceeded = 204,
+    UnlockThrottled = 205,
+    UnlockFailed = 206,
+    Locked = 207,
+    WrongUnlockKey = 208,
+    KeyBackend = 209,
+    PcrMismatch = 210,
+
+    // procedure errors
+    NotApproved = 300,
+    Aborted = 301,
+    ShuttingDown = 302,
+
+    // memory errors
+    Encryption = 400,
+    Decryption = 401,
+    MemoryLockNotAvailable = 402,
+    Allocation = 403,
+    IllegalMemorySize = 404,
+    ZeroizedMemory = 405,
+    Memory = 406,
+}
+
+impl From<&WrapperError> for StrongholdErrorCode {
+    fn from(e: &WrapperError) -> Self {
+        match e {
+            WrapperError::NullPointer(_) => StrongholdErrorCode::NullPointer,
+            WrapperError::InvalidString(_) => StrongholdErrorCode::InvalidString,
+            WrapperError::InvalidHandle(_) => StrongholdErrorCode::InvalidHandle,
+            WrapperError::InvalidArgument(..) => StrongholdErrorCode::InvalidArgument,
+            WrapperError::OpenSnapshot(e) => client_error(e, StrongholdErrorCode::OpenSnapshot),
+            WrapperError::CommitToSnapshot(e) => client_error(e, StrongholdErrorCode::CommitToSnapshot),
+            WrapperError::CreateClient(e) => client_error(e, StrongholdErrorCode::CreateClient),
+            WrapperError::WriteClient(e) => client_error(e, StrongholdErrorCode::WriteClient),
+            WrapperError::ExecuteProcedure(e) => procedure_error(e),
+            WrapperError::KeyDerivation(_) => StrongholdErrorCode::KeyDerivation,
+            WrapperError::MigrateSnapshot(e) => client_error(e, StrongholdErrorCode::MigrateSnapshot),
+            WrapperError::ImportSnapshot(e) => client_error(e, StrongholdErrorCode::ImportSnapshot),
+            WrapperError::Memory(e) => memory_error(e),
+            WrapperError::LockAcquireFailed => StrongholdErrorCode::LockAcquireFailed,
+        }
+    }
+}
+
+/// Returns the code of the cause of `e`, or `operation`, if the cause has no code of its own
+fn client_error(e: &ClientError, operation: StrongholdErrorCode) -> StrongholdErrorCode {
+    match e {
+        ClientError::LockAcquireFailed => StrongholdErrorCode::LockAcquireFailed,
+        ClientError::SnapshotFileMissing(_) => StrongholdErrorCode::SnapshotFileMissing,
+        ClientError::ClientDataNotPresent => StrongholdErrorCode::ClientDataNotPresent,
+        ClientError::IllegalKeySize(_) => StrongholdErrorCode::IllegalKeySize,
+        ClientError::StoreEntryTooLarge { .. }
+        | ClientError::StoreQuotaExceeded { .. }
+        | ClientError::VaultQuotaExceeded(_) => StrongholdErrorCode::QuotaExceeded,
+        ClientError::UnlockThrottled { .. } => StrongholdErrorCode::UnlockThrottled,
+        ClientError::UnlockFailed { .. } => StrongholdErrorCode::UnlockFailed,
+        ClientError::SnapshotDecryption(_) => StrongholdErrorCode::Decryption,
+        ClientError::Locked => StrongholdErrorCode::Locked,
+        ClientError::WrongUnlockKey => StrongholdErrorCode::WrongUnlockKey,
+        ClientError::KeyBackend(_) => StrongholdErrorCode::KeyBackend,
+        ClientError::PcrMismatch => StrongholdErrorCode::PcrMismatch,
+        _ => operation,
+    }
+}
+
+fn procedure_error(e: &ProcedureError) -> StrongholdErrorCode {
+    match e {
+        ProcedureError::NotApproved => StrongholdErrorCode::NotApproved,
+        ProcedureError::Aborted => StrongholdErrorCode::Aborted,
+        ProcedureError::Locked => StrongholdErrorCode::Locked,
+        ProcedureError::ShuttingDown => StrongholdErrorCode::ShuttingDown,
+        ProcedureError::Engine(_) | ProcedureError::Procedure(_) => StrongholdErrorCode::ExecuteProcedure,
+    }
+}
+
+fn memory_error(e: &MemoryError) -> StrongholdErrorCode {
+    match e {
+        MemoryError::EncryptionError => StrongholdErrorCode::Encryption,
+        MemoryError::DecryptionError => StrongholdErrorCode::Decryption,
+        MemoryError::LockNotAvailable => StrongholdErrorCode::MemoryLockNotAvailable,
+        MemoryError::Allocation(_) => StrongholdErrorCode::Allocation,
+        MemoryError::NCSizeNotAllowed | MemoryError::ZeroSizedNotAllowed => StrongholdErrorCode::IllegalMemorySize,
+        MemoryError::IllegalZeroizedUsage => StrongholdErrorCode::ZeroizedMemory,
+        _ => StrongholdErrorCode::Memory,
+    }
+}
@@ -2,52 +2,138 @@
 // SPDX-License-Identifier: Apache-2.0
 extern crate core;
 
+mod error;
+mod logging;
 mod shared;
 mod wrapper;
 
-use log::{LevelFilter, *};
+use log::*;
 
 use std::{
     cell::RefCell,
-    error::Error,
     ffi::{CStr, CString},
     os::raw::c_char,
     ptr, slice,
 };
 
-use crate::{
-    shared::hash_blake2b,
+use iota_stronghold::{sync::MergePolicy, Argon2Params};
+
+pub use crate::{
+    error::StrongholdErrorCode,
+    logging::StrongholdLogCallback,
     wrapper::{StrongholdWrapper, WrapperError},
 };
 
 thread_local! {
-    static LAST_ERROR: RefCell<Option<Box<dyn Error>>> = RefCell::new(None);
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
 }
 
-fn set_last_error(err: WrapperError) {
+/// Stores the description of `err` for [`stronghold_get_last_error`] and returns its code. Bindings, that are
+/// built on this crate, report their errors through it as well.
+pub fn set_last_error(err: WrapperError) -> StrongholdErrorCode {
+    let code = StrongholdErrorCode::from(&err);
     LAST_ERROR.with(|prev| {
-        *prev.borrow_mut() = Some(Box::new(err));
+        *prev.borrow_mut() = Some(err.to_string());
     });
+    code
 }
 
-#[no_mangle]
-pub extern "C" fn stronghold_set_log_level(log_level: libc::size_t) {
-    let filter = match log_level {
-        0 => Some(LevelFilter::Off),
-        1 => Some(LevelFilter::Error),
-        2 => Some(LevelFilter::Warn),
-        3 => Some(LevelFilter::Info),
-        4 => Some(LevelFilter::Debug),
-        5 => Some(LevelFilter::Trace),
-        _ => Some(LevelFilter::Off),
+/// Returns the value of a `Result<_, WrapperError>`, or returns the code of its error from the calling
+/// function
+#[macro_export]
+macro_rules! try_ffi {
+    ($result:expr) => {
+        match $result {
+            Ok(value) => value,
+            Err(err) => return $crate::set_last_error(err.into()),
+        }
     };
+}
+
+mod tasks;
+
+pub use crate::tasks::{StrongholdCallback, StrongholdInstanceCallback};
+
+/// Returns the string behind `ptr`, that is named `name` in errors
+///
+/// # Safety
+///
+/// `ptr` has to be null, or point to a nul-terminated string, that is valid for `'a`.
+pub unsafe fn c_str<'a>(ptr: *const c_char, name: &'static str) -> Result<&'a str, WrapperError> {
+    if ptr.is_null() {
+        return Err(WrapperError::NullPointer(name));
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| WrapperError::InvalidString(name))
+}
+
+/// Returns the instance behind `stronghold_ptr`. The instance is only borrowed shared, since the asynchronous
+/// calls may use it from several threads at once.
+unsafe fn stronghold<'a>(stronghold_ptr: *mut StrongholdWrapper) -> Result<&'a StrongholdWrapper, WrapperError> {
+    info!("[Rust] Getting Stronghold instance from Box");
+
+    if stronghold_ptr.is_null() {
+        return Err(WrapperError::NullPointer("stronghold_ptr"));
+    }
+    Ok(&*stronghold_ptr)
+}
+
+/// Bytes that are returned by the bindings, together with their length. The buffer has to be freed with
+/// [`stronghold_destroy_byte_buffer`].
+#[repr(C)]
+pub struct StrongholdByteBuffer {
+    pub data: *mut u8,
+    pub len: libc::size_t,
+}
+
+impl StrongholdByteBuffer {
+    fn empty() -> Self {
+        Self {
+            data: ptr::null_mut(),
+            len: 0,
+        }
+    }
+}
+
+impl From<Vec<u8>> for StrongholdByteBuffer {
+    fn from(bytes: Vec<u8>) -> Self {
+        let len = bytes.len();
+        let data = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+        Self { data, len }
+    }
+}
+
+/// Returns an error, if the output parameter `out` is null. It is checked before the value of `out` is
+/// created, since the value couldn't be freed by the caller otherwise.
+pub fn check_out<T>(out: *mut T) -> Result<(), WrapperError> {
+    if out.is_null() {
+        return Err(WrapperError::NullPointer("out"));
+    }
+    Ok(())
+}
+
+/// Writes `value` to the output parameter `out`, that has been checked with [`check_out`]
+///
+/// # Safety
+///
+/// `out` has to be valid for writes.
+pub unsafe fn write_out<T>(out: *mut T, value: T) -> StrongholdErrorCode {
+    out.write(value);
+    StrongholdErrorCode::Ok
+}
 
-    let _logger = env_logger::builder()
-        .is_test(false)
-        .filter_level(filter.unwrap())
-        .try_init();
+/// Writes the logs up to `log_level` to stderr, from `0` for no logs to `5` for traces. Use
+/// [`logging::stronghold_set_log_callback`] to route them to the host instead.
+#[no_mangle]
+pub extern "C" fn stronghold_set_log_level(log_level: libc::size_t) {
+    logging::set_sink_stderr(logging::level_filter(log_level));
 }
 
+/// Returns the description of the error of the last failed call on this thread, or null. Branch on the
+/// [`StrongholdErrorCode`] returned by the call instead of this text. The returned string has to be freed
+/// with [`stronghold_destroy_error`].
+///
 /// # Safety
 #[no_mangle]
 pub unsafe extern "C" fn stronghold_get_last_error() -> *const c_char {
@@ -58,7 +144,7 @@ pub unsafe extern "C" fn stronghold_get_last_error() -> *const c_char {
         None => return ptr::null_mut(),
     };
 
-    let s = CString::new(last_error.to_string()).unwrap();
+    let s = CString::new(last_error).unwrap_or_default();
     s.into_raw()
 }
 
@@ -72,50 +158,74 @@ pub unsafe extern "C" fn stronghold_destroy_error(s: *mut c_char) {
     let _ = CString::from_raw(s);
 }
 
+/// Creates a new snapshot and writes the instance to `out`.
+///
 /// # Safety
 #[no_mangle]
 pub unsafe extern "C" fn stronghold_create(
     snapshot_path_c: *const libc::c_char,
     key_c: *const libc::c_char,
-) -> *mut StrongholdWrapper {
-    let snapshot_path = CStr::from_ptr(snapshot_path_c);
-    let snapshot_path = snapshot_path.to_str().unwrap().to_string();
-    let key = CStr::from_ptr(key_c);
-    let key_as_hash = hash_blake2b(key.to_str().unwrap().to_string());
-
-    let stronghold_wrapper = match StrongholdWrapper::create_new(snapshot_path, key_as_hash) {
-        Ok(res) => res,
-        Err(err) => {
-            set_last_error(err);
-            return ptr::null_mut();
-        }
+    out: *mut *mut StrongholdWrapper,
+) -> StrongholdErrorCode {
+    let params = Argon2Params::default();
+    stronghold_create_with_kdf(
+        snapshot_path_c,
+        key_c,
+        params.memory_kib,
+        params.iterations,
+        params.parallelism,
+        out,
+    )
+}
+
+/// Creates a new snapshot, whose key is derived from the password with Argon2id and the given
+/// parameters. The parameters and the salt are stored in the header of the snapshot. The instance is
+/// written to `out`.
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_create_with_kdf(
+    snapshot_path_c: *const libc::c_char,
+    key_c: *const libc::c_char,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    out: *mut *mut StrongholdWrapper,
+) -> StrongholdErrorCode {
+    try_ffi!(check_out(out));
+
+    let params = Argon2Params {
+        memory_kib,
+        iterations,
+        parallelism,
     };
+    let snapshot_path = try_ffi!(c_str(snapshot_path_c, "snapshot_path_c")).to_string();
+    let key = try_ffi!(c_str(key_c, "key_c"));
 
-    Box::into_raw(Box::new(stronghold_wrapper))
+    let stronghold_wrapper = try_ffi!(StrongholdWrapper::create_new(snapshot_path, key, params));
+
+    write_out(out, Box::into_raw(Box::new(stronghold_wrapper)))
 }
 
+/// Loads the snapshot and writes the instance to `out`.
+///
 /// # Safety
 #[no_mangle]
 pub unsafe extern "C" fn stronghold_load(
     snapshot_path_c: *const libc::c_char,
     key_c: *const libc::c_char,
-) -> *mut StrongholdWrapper {
-    let snapshot_path = CStr::from_ptr(snapshot_path_c);
-    let snapshot_path = snapshot_path.to_str().unwrap().to_string();
-    let key = CStr::from_ptr(key_c);
-    let key_as_hash = hash_blake2b(key.to_str().unwrap().to_string());
-
-    let stronghold_wrapper = match StrongholdWrapper::from_file(snapshot_path, key_as_hash) {
-        Ok(res) => res,
-        Err(err) => {
-            set_last_error(err);
-            return ptr::null_mut();
-        }
-    };
+    out: *mut *mut StrongholdWrapper,
+) -> StrongholdErrorCode {
+    try_ffi!(check_out(out));
+
+    let snapshot_path = try_ffi!(c_str(snapshot_path_c, "snapshot_path_c")).to_string();
+    let key = try_ffi!(c_str(key_c, "key_c"));
+
+    let stronghold_wrapper = try_ffi!(StrongholdWrapper::from_file(snapshot_path, key));
 
     info!("[Rust] Snapshot loaded");
 
-    Box::into_raw(Box::new(stronghold_wrapper))
+    write_out(out, Box::into_raw(Box::new(stronghold_wrapper)))
 }
 
 /// # Safety
@@ -134,181 +244,254 @@ pub unsafe extern "C" fn stronghold_destroy_stronghold(stronghold_ptr: *mut Stro
     info!("[Rust] Destroyed instance");
 }
 
-// TODO: Find better way to generalize destroy into one function
+/// Re-encrypts the loaded snapshot with a key that is derived from the password with Argon2id and the
+/// given parameters. Snapshots that have been created with the Blake2b key derivation are migrated this
+/// way, and the parameters of Argon2id snapshots can be raised.
+///
 /// # Safety
 #[no_mangle]
-pub unsafe extern "C" fn stronghold_destroy_data_pointer(ptr: *mut u8) {
+pub unsafe extern "C" fn stronghold_migrate_kdf(
+    stronghold_ptr: *mut StrongholdWrapper,
+    key_c: *const libc::c_char,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+) -> StrongholdErrorCode {
+    info!("[Rust] Migrate KDF started");
+
+    let key = try_ffi!(c_str(key_c, "key_c"));
+    let params = Argon2Params {
+        memory_kib,
+        iterations,
+        parallelism,
+    };
+
+    let stronghold_wrapper = try_ffi!(stronghold(stronghold_ptr));
+
+    try_ffi!(stronghold_wrapper.migrate_kdf(key, params));
+
+    StrongholdErrorCode::Ok
+}
+
+/// Imports all records of the snapshot at `source_path_c`, that is encrypted with `source_key_c`, into
+/// the loaded snapshot. A `merge_policy` of `0` keeps existing records, `1` replaces them. Other values are
+/// rejected with [`StrongholdErrorCode::InvalidArgument`].
+///
+/// `_key_c` is ignored, the snapshot key is derived once by [`stronghold_create`] or [`stronghold_load`]. It is
+/// kept for compatibility with existing callers.
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_import_snapshot(
+    stronghold_ptr: *mut StrongholdWrapper,
+    _key_c: *const libc::c_char,
+    source_path_c: *const libc::c_char,
+    source_key_c: *const libc::c_char,
+    merge_policy: u8,
+) -> StrongholdErrorCode {
+    stronghold_import_snapshot_records(
+        stronghold_ptr,
+        _key_c,
+        source_path_c,
+        source_key_c,
+        ptr::null(),
+        0,
+        merge_policy,
+    )
+}
+
+/// Same as [`stronghold_import_snapshot`], but only imports the records at the `record_paths_length`
+/// paths of `record_paths_c`. All records are imported, if `record_paths_c` is null.
+///
+/// `_key_c` is ignored, the snapshot key is derived once by [`stronghold_create`] or [`stronghold_load`]. It is
+/// kept for compatibility with existing callers.
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_import_snapshot_records(
+    stronghold_ptr: *mut StrongholdWrapper,
+    _key_c: *const libc::c_char,
+    source_path_c: *const libc::c_char,
+    source_key_c: *const libc::c_char,
+    record_paths_c: *const *const libc::c_char,
+    record_paths_length: libc::size_t,
+    merge_policy: u8,
+) -> StrongholdErrorCode {
+    info!("[Rust] Import snapshot started");
+
+    let source_path = try_ffi!(c_str(source_path_c, "source_path_c")).to_string();
+    let source_key = try_ffi!(c_str(source_key_c, "source_key_c"));
+
+    let record_paths = if record_paths_c.is_null() {
+        None
+    } else {
+        let record_paths = slice::from_raw_parts(record_paths_c, record_paths_length);
+        Some(try_ffi!(record_paths
+            .iter()
+            .map(|path| c_str(*path, "record_paths_c").map(|path| path.to_string()))
+            .collect::<Result<Vec<_>, _>>()))
+    };
+
+    let merge_policy = match merge_policy {
+        0 => MergePolicy::KeepOld,
+        1 => MergePolicy::Replace,
+        _ => try_ffi!(Err(WrapperError::InvalidArgument("merge_policy", merge_policy.into()))),
+    };
+
+    let stronghold_wrapper = try_ffi!(stronghold(stronghold_ptr));
+
+    info!("[Rust] Got Stronghold instance from Box");
+
+    try_ffi!(stronghold_wrapper.import_snapshot(source_path, source_key, record_paths, merge_policy));
+
+    StrongholdErrorCode::Ok
+}
+
+/// Frees a buffer, that has been returned by the bindings.
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_destroy_byte_buffer(buffer: StrongholdByteBuffer) {
     info!("[Rust] Destroy started");
 
-    if ptr.is_null() {
+    if buffer.data.is_null() {
         error!("[Rust] Data pointer was null!");
 
         return;
     }
 
-    let _ = Box::from_raw(ptr);
+    let _ = Box::from_raw(ptr::slice_from_raw_parts_mut(buffer.data, buffer.len));
 
     info!("[Rust] Destroyed instance");
 }
 
+/// `_key_c` is ignored, the snapshot key is derived once by [`stronghold_create`] or [`stronghold_load`]. It is
+/// kept for compatibility with existing callers.
+///
 /// # Safety
 #[no_mangle]
 pub unsafe extern "C" fn stronghold_generate_ed25519_keypair(
     stronghold_ptr: *mut StrongholdWrapper,
-    key_c: *const libc::c_char,
+    _key_c: *const libc::c_char,
     record_path_c: *const libc::c_char,
-) -> *mut u8 {
+) -> StrongholdErrorCode {
     info!("[Rust] Generate ED25519 Keypair started");
 
-    let key = CStr::from_ptr(key_c);
-    let key_as_hash = hash_blake2b(key.to_str().unwrap().to_string());
-
-    let record_path = CStr::from_ptr(record_path_c);
-    let record_path = record_path.to_str().unwrap().to_string();
-
-    info!("[Rust] Getting Stronghold instance from Box");
+    let record_path = try_ffi!(c_str(record_path_c, "record_path_c")).to_string();
 
-    let stronghold_wrapper = {
-        assert!(!stronghold_ptr.is_null());
-        &mut *stronghold_ptr
-    };
+    let stronghold_wrapper = try_ffi!(stronghold(stronghold_ptr));
 
     info!("[Rust] Got Stronghold instance from Box");
 
-    let chain_code = match stronghold_wrapper.generate_ed25519_keypair(key_as_hash, record_path) {
-        Ok(res) => res,
-        Err(err) => {
-            set_last_error(err);
-            return ptr::null_mut();
-        }
-    };
+    try_ffi!(stronghold_wrapper.generate_ed25519_keypair(record_path));
 
-    Box::into_raw(Box::new(chain_code)) as *mut _
+    StrongholdErrorCode::Ok
 }
 
+/// `_key_c` is ignored, the snapshot key is derived once by [`stronghold_create`] or [`stronghold_load`]. It is
+/// kept for compatibility with existing callers.
+///
 /// # Safety
 #[no_mangle]
 pub unsafe extern "C" fn stronghold_write_vault(
     stronghold_ptr: *mut StrongholdWrapper,
-    key_c: *const libc::c_char,
+    _key_c: *const libc::c_char,
     record_path_c: *const libc::c_char,
     data_c: *const libc::c_uchar,
     data_length: libc::size_t,
-) -> bool {
+) -> StrongholdErrorCode {
     info!("[Rust] Writing Vault started");
 
-    let key = CStr::from_ptr(key_c);
-    let key_as_hash = hash_blake2b(key.to_str().unwrap().to_string());
-
-    let record_path = CStr::from_ptr(record_path_c);
-    let record_path = record_path.to_str().unwrap().to_string();
+    let record_path = try_ffi!(c_str(record_path_c, "record_path_c")).to_string();
+    if data_c.is_null() {
+        return set_last_error(WrapperError::NullPointer("data_c"));
+    }
     let data = slice::from_raw_parts(data_c, data_length);
 
-    info!("[Rust] Getting Stronghold instance from Box");
-
-    let stronghold_wrapper = {
-        assert!(!stronghold_ptr.is_null());
-        &mut *stronghold_ptr
-    };
+    let stronghold_wrapper = try_ffi!(stronghold(stronghold_ptr));
 
     info!("[Rust] Got Stronghold instance from Box");
 
-    if let Err(err) = stronghold_wrapper.write_vault(key_as_hash, record_path, data.to_vec()) {
-        set_last_error(err);
-        return false;
-    }
+    try_ffi!(stronghold_wrapper.write_vault(record_path, data.to_vec()));
 
-    true
+    StrongholdErrorCode::Ok
 }
 
+/// `_key_c` is ignored, the snapshot key is derived once by [`stronghold_create`] or [`stronghold_load`]. It is
+/// kept for compatibility with existing callers.
+///
 /// # Safety
 #[no_mangle]
 pub unsafe extern "C" fn stronghold_generate_seed(
     stronghold_ptr: *mut StrongholdWrapper,
-    key_c: *const libc::c_char,
-) -> bool {
+    _key_c: *const libc::c_char,
+) -> StrongholdErrorCode {
     info!("[Rust] Generate Seed started");
 
-    let key = CStr::from_ptr(key_c);
-    let key_as_hash = hash_blake2b(key.to_str().unwrap().to_string());
-
-    info!("[Rust] Getting Stronghold instance from Box");
-
-    let stronghold_wrapper = {
-        assert!(!stronghold_ptr.is_null());
-        &mut *stronghold_ptr
-    };
+    let stronghold_wrapper = try_ffi!(stronghold(stronghold_ptr));
 
     info!("[Rust] Got Stronghold instance from Box");
 
-    if let Err(err) = stronghold_wrapper.generate_seed(key_as_hash) {
-        set_last_error(err);
-        return false;
-    }
+    try_ffi!(stronghold_wrapper.generate_seed());
 
-    true
+    StrongholdErrorCode::Ok
 }
 
+/// Derives the key of `address_index` from the seed, and writes its chain code to `out`. The buffer has to
+/// be freed with [`stronghold_destroy_byte_buffer`].
+///
+/// `_key_c` is ignored, the snapshot key is derived once by [`stronghold_create`] or [`stronghold_load`]. It is
+/// kept for compatibility with existing callers.
+///
 /// # Safety
 #[no_mangle]
 pub unsafe extern "C" fn stronghold_derive_seed(
     stronghold_ptr: *mut StrongholdWrapper,
-    key_c: *const libc::c_char,
+    _key_c: *const libc::c_char,
     address_index: u32,
-) -> bool {
-    info!("[Rust] Derive Seed started");
-
-    let key = CStr::from_ptr(key_c);
-    let key_as_hash = hash_blake2b(key.to_str().unwrap().to_string());
+    out: *mut StrongholdByteBuffer,
+) -> StrongholdErrorCode {
+    try_ffi!(check_out(out));
 
-    info!("[Rust] Getting Stronghold instance from Box");
+    info!("[Rust] Derive Seed started");
 
-    let stronghold_wrapper = {
-        assert!(!stronghold_ptr.is_null());
-        &mut *stronghold_ptr
-    };
+    let stronghold_wrapper = try_ffi!(stronghold(stronghold_ptr));
 
     info!("[Rust] Got Stronghold instance from Box");
 
-    if let Err(err) = stronghold_wrapper.derive_seed(key_as_hash, address_index) {
-        set_last_error(err);
-        return false;
-    }
+    let chain_code = try_ffi!(stronghold_wrapper.derive_seed(address_index));
 
-    true
+    write_out(out, chain_code.to_vec().into())
 }
 
+/// Writes the public key of the record at `record_path_c` to `out`. The buffer has to be freed with
+/// [`stronghold_destroy_byte_buffer`].
+///
 /// # Safety
 #[no_mangle]
 pub unsafe extern "C" fn stronghold_get_public_key(
     stronghold_ptr: *mut StrongholdWrapper,
     record_path_c: *const libc::c_char,
-) -> *mut u8 {
-    info!("[Rust] Get public key started");
+    out: *mut StrongholdByteBuffer,
+) -> StrongholdErrorCode {
+    try_ffi!(check_out(out));
 
-    let record_path = CStr::from_ptr(record_path_c);
-    let record_path = record_path.to_str().unwrap().to_string();
+    info!("[Rust] Get public key started");
 
-    info!("[Rust] Getting Stronghold instance from Box");
+    let record_path = try_ffi!(c_str(record_path_c, "record_path_c")).to_string();
 
-    let stronghold_wrapper = {
-        assert!(!stronghold_ptr.is_null());
-        &mut *stronghold_ptr
-    };
+    let stronghold_wrapper = try_ffi!(stronghold(stronghold_ptr));
 
     info!("[Rust] Got Stronghold instance from Box");
 
-    let public_key = match stronghold_wrapper.get_public_key(record_path) {
-        Ok(res) => res,
-        Err(err) => {
-            set_last_error(err);
-            return ptr::null_mut();
-        }
-    };
+    let public_key = try_ffi!(stronghold_wrapper.get_public_key(record_path));
 
-    Box::into_raw(Box::new(public_key)) as *mut _
+    write_out(out, public_key.into())
 }
 
+/// Writes the signature of the data with the key of the record at `record_path_c` to `out`. The buffer has
+/// to be freed with [`stronghold_destroy_byte_buffer`].
+///
 /// # Safety
 #[no_mangle]
 pub unsafe extern "C" fn stronghold_sign(
@@ -316,27 +499,47 @@ pub unsafe extern "C" fn stronghold_sign(
     record_path_c: *const libc::c_char,
     data_c: *const libc::c_uchar,
     data_length: libc::size_t,
-) -> *mut u8 {
-    let record_path = CStr::from_ptr(record_path_c);
-    let record_path = record_path.to_str().unwrap().to_string();
-    let data = slice::from_raw_parts(data_c, data_length);
+    out: *mut StrongholdByteBuffer,
+) -> StrongholdErrorCode {
+    try_ffi!(check_out(out));
 
-    info!("[Rust] Getting Stronghold instance from Box");
+    let record_path = try_ffi!(c_str(record_path_c, "record_path_c")).to_string();
+    if data_c.is_null() {
+        return set_last_error(WrapperError::NullPointer("data_c"));
+    }
+    let data = slice::from_raw_parts(data_c, data_length);
 
-    let stronghold_wrapper = {
-        assert!(!stronghold_ptr.is_null());
-        &mut *stronghold_ptr
-    };
+    let stronghold_wrapper = try_ffi!(stronghold(stronghold_ptr));
 
     info!("[Rust] Got Stronghold instance from Box");
 
-    let signature = match stronghold_wrapper.sign(record_path, data.to_vec()) {
-        Ok(res) => res,
-        Err(err) => {
-            set_last_error(err);
-            return ptr::null_mut();
-        }
-    };
+    let signature = try_ffi!(stronghold_wrapper.sign(record_path, data.to_vec()));
+
+    write_out(out, signature.into())
+}
+
+/// Writes the 24 bytes of the record id of `record_path_c` in the vault at `vault_path_c` to `out`, without
+/// loading a snapshot. The buffer has to be freed with [`stronghold_destroy_byte_buffer`].
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_derive_record_id(
+    vault_path_c: *const libc::c_char,
+    record_path_c: *const libc::c_char,
+    out: *mut StrongholdByteBuffer,
+) -> StrongholdErrorCode {
+    try_ffi!(check_out(out));
+
+    if vault_path_c.is_null() {
+        return set_last_error(WrapperError::NullPointer("vault_path_c"));
+    }
+    if record_path_c.is_null() {
+        return set_last_error(WrapperError::NullPointer("record_path_c"));
+    }
+    let vault_path = CStr::from_ptr(vault_path_c);
+    let record_path = CStr::from_ptr(record_path_c);
+
+    let record_id = engine::vault::RecordId::deterministic(vault_path.to_bytes(), record_path.to_bytes());
 
-    Box::into_raw(Box::new(signature)) as *mut _
+    write_out(out, record_id.as_ref().to_vec().into())
 }
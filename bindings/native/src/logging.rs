@@ -0,0 +1,132 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Routes the logs of the bindings either to stderr, or to a callback of the host application.
+
+use std::{
+    ffi::{c_void, CString},
+    os::raw::c_char,
+    sync::RwLock,
+};
+
+use lazy_static::lazy_static;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Called with the level, from `1` for errors to `5` for traces, and the message of every log entry,
+/// together with the `user_data` of [`stronghold_set_log_callback`]. The message is only valid during the
+/// call. The callback may be called from any thread.
+pub type StrongholdLogCallback = extern "C" fn(libc::size_t, *const c_char, *mut c_void);
+
+#[derive(Clone, Copy)]
+struct Callback {
+    callback: StrongholdLogCallback,
+    user_data: *mut c_void,
+}
+
+// the host guarantees, that `user_data` can be used from any thread
+unsafe impl Send for Callback {}
+unsafe impl Sync for Callback {}
+
+enum Sink {
+    Stderr(env_logger::Logger),
+    Callback(Callback),
+}
+
+lazy_static! {
+    static ref SINK: RwLock<Option<Sink>> = RwLock::new(None);
+}
+
+/// The logger, that is installed once and dispatches to the current sink
+struct Dispatch;
+
+static DISPATCH: Dispatch = Dispatch;
+
+impl Log for Dispatch {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let Callback { callback, user_data } = match SINK.read().as_deref() {
+            Ok(Some(Sink::Stderr(logger))) => return logger.log(record),
+            Ok(Some(Sink::Callback(callback))) => *callback,
+            _ => return,
+        };
+        // the lock is released before calling into the host, so that the callback may log or replace the sink
+        if let Ok(message) = CString::new(record.args().to_string()) {
+            callback(level_index(record.level()), message.as_ptr(), user_data);
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(sink) = SINK.read() {
+            if let Some(Sink::Stderr(logger)) = &*sink {
+                logger.flush();
+            }
+        }
+    }
+}
+
+pub(crate) fn level_filter(log_level: libc::size_t) -> LevelFilter {
+    match log_level {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        5 => LevelFilter::Trace,
+        _ => LevelFilter::Off,
+    }
+}
+
+fn level_index(level: Level) -> libc::size_t {
+    match level {
+        Level::Error => 1,
+        Level::Warn => 2,
+        Level::Info => 3,
+        Level::Debug => 4,
+        Level::Trace => 5,
+    }
+}
+
+/// Replaces the current sink, and installs the logger on first use
+pub(crate) fn set_sink_stderr(filter: LevelFilter) {
+    let logger = env_logger::builder().is_test(false).filter_level(filter).build();
+    set_sink(Sink::Stderr(logger), filter);
+}
+
+fn set_sink(sink: Sink, filter: LevelFilter) {
+    if let Ok(mut current) = SINK.write() {
+        *current = Some(sink);
+    }
+    let _ = log::set_logger(&DISPATCH);
+    log::set_max_level(filter);
+}
+
+/// Routes the logs up to `log_level`, see [`stronghold_set_log_level`](crate::stronghold_set_log_level), to
+/// `callback` instead of stderr, so that the host can process them with its own logging system. Passing
+/// null as `callback` disables logging.
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_set_log_callback(
+    callback: Option<StrongholdLogCallback>,
+    user_data: *mut c_void,
+    log_level: libc::size_t,
+) {
+    match callback {
+        Some(callback) => set_sink(
+            Sink::Callback(Callback { callback, user_data }),
+            level_filter(log_level),
+        ),
+        None => {
+            if let Ok(mut current) = SINK.write() {
+                *current = None;
+            }
+            log::set_max_level(LevelFilter::Off);
+        }
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Asynchronous variants of the operations, that derive the snapshot key or commit the snapshot, so that
+//! the UI thread of the host isn't blocked by the Argon2 key derivation or a large snapshot.
+//!
+//! The arguments are copied before the call returns, the copies of passwords and secrets are zeroized once
+//! the operation is done. The operation then runs on a thread of an internal executor, which calls
+//! `callback` with the result and `user_data`. `stronghold_get_last_error` returns the description of a
+//! failure, if it is called from within the callback. Operations on the same instance, that commit its
+//! snapshot, run one after another. The instance must not be destroyed before all of its callbacks have
+//! been called.
+
+use std::{
+    ffi::{c_void, CStr, CString},
+    os::raw::c_char,
+    ptr, slice,
+};
+
+use lazy_static::lazy_static;
+use tokio::runtime::{Builder, Runtime};
+use zeroize::Zeroizing;
+
+use crate::{
+    stronghold_create, stronghold_derive_seed, stronghold_generate_ed25519_keypair, stronghold_generate_seed,
+    stronghold_load, stronghold_sign, stronghold_write_vault, StrongholdByteBuffer, StrongholdErrorCode,
+    StrongholdWrapper, WrapperError,
+};
+
+lazy_static! {
+    static ref RUNTIME: Runtime = Builder::new_multi_thread()
+        .thread_name("stronghold-native")
+        .build()
+        .expect("Failed to start the executor of the native bindings");
+}
+
+/// Called with the result of an operation. `result` is empty, if the operation has no output or failed,
+/// and has to be freed with [`stronghold_destroy_byte_buffer`](crate::stronghold_destroy_byte_buffer)
+/// otherwise.
+pub type StrongholdCallback = extern "C" fn(StrongholdErrorCode, StrongholdByteBuffer, *mut c_void);
+
+/// Called with the instance of [`stronghold_create_async`] or [`stronghold_load_async`], or null if the
+/// operation failed.
+pub type StrongholdInstanceCallback = extern "C" fn(StrongholdErrorCode, *mut StrongholdWrapper, *mut c_void);
+
+/// Pointers, that are handed to the executor. The host guarantees, that they stay valid until the callback
+/// has been called.
+struct Unchecked<T>(T);
+
+unsafe impl<T> Send for Unchecked<T> {}
+
+impl<T> Unchecked<T> {
+    fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+fn spawn(task: impl FnOnce() + Send + 'static) -> StrongholdErrorCode {
+    RUNTIME.spawn_blocking(task);
+    StrongholdErrorCode::Ok
+}
+
+unsafe fn c_string(ptr: *const c_char, name: &'static str) -> Result<Zeroizing<CString>, WrapperError> {
+    if ptr.is_null() {
+        return Err(WrapperError::NullPointer(name));
+    }
+    Ok(Zeroizing::new(CStr::from_ptr(ptr).to_owned()))
+}
+
+unsafe fn bytes(
+    ptr: *const libc::c_uchar,
+    len: libc::size_t,
+    name: &'static str,
+) -> Result<Zeroizing<Vec<u8>>, WrapperError> {
+    if ptr.is_null() {
+        return Err(WrapperError::NullPointer(name));
+    }
+    Ok(Zeroizing::new(slice::from_raw_parts(ptr, len).to_vec()))
+}
+
+/// Asynchronous variant of [`stronghold_create`].
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_create_async(
+    snapshot_path_c: *const libc::c_char,
+    key_c: *const libc::c_char,
+    callback: StrongholdInstanceCallback,
+    user_data: *mut c_void,
+) -> StrongholdErrorCode {
+    let snapshot_path = try_ffi!(c_string(snapshot_path_c, "snapshot_path_c"));
+    let key = try_ffi!(c_string(key_c, "key_c"));
+    let user_data = Unchecked(user_data);
+
+    spawn(move || {
+        let mut out = ptr::null_mut();
+        let code = stronghold_create(snapshot_path.as_ptr(), key.as_ptr(), &mut out);
+        callback(code, out, user_data.into_inner());
+    })
+}
+
+/// Asynchronous variant of [`stronghold_load`].
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_load_async(
+    snapshot_path_c: *const libc::c_char,
+    key_c: *const libc::c_char,
+    callback: StrongholdInstanceCallback,
+    user_data: *mut c_void,
+) -> StrongholdErrorCode {
+    let snapshot_path = try_ffi!(c_string(snapshot_path_c, "snapshot_path_c"));
+    let key = try_ffi!(c_string(key_c, "key_c"));
+    let user_data = Unchecked(user_data);
+
+    spawn(move || {
+        let mut out = ptr::null_mut();
+        let code = stronghold_load(snapshot_path.as_ptr(), key.as_ptr(), &mut out);
+        callback(code, out, user_data.into_inner());
+    })
+}
+
+/// Asynchronous variant of [`stronghold_generate_ed25519_keypair`].
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_generate_ed25519_keypair_async(
+    stronghold_ptr: *mut StrongholdWrapper,
+    _key_c: *const libc::c_char,
+    record_path_c: *const libc::c_char,
+    callback: StrongholdCallback,
+    user_data: *mut c_void,
+) -> StrongholdErrorCode {
+    let record_path = try_ffi!(c_string(record_path_c, "record_path_c"));
+    let (stronghold_ptr, user_data) = (Unchecked(stronghold_ptr), Unchecked(user_data));
+
+    spawn(move || {
+        let code = stronghold_generate_ed25519_keypair(stronghold_ptr.into_inner(), ptr::null(), record_path.as_ptr());
+        callback(code, StrongholdByteBuffer::empty(), user_data.into_inner());
+    })
+}
+
+/// Asynchronous variant of [`stronghold_write_vault`].
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_write_vault_async(
+    stronghold_ptr: *mut StrongholdWrapper,
+    _key_c: *const libc::c_char,
+    record_path_c: *const libc::c_char,
+    data_c: *const libc::c_uchar,
+    data_length: libc::size_t,
+    callback: StrongholdCallback,
+    user_data: *mut c_void,
+) -> StrongholdErrorCode {
+    let record_path = try_ffi!(c_string(record_path_c, "record_path_c"));
+    let data = try_ffi!(bytes(data_c, data_length, "data_c"));
+    let (stronghold_ptr, user_data) = (Unchecked(stronghold_ptr), Unchecked(user_data));
+
+    spawn(move || {
+        let code = stronghold_write_vault(
+            stronghold_ptr.into_inner(),
+            ptr::null(),
+            record_path.as_ptr(),
+            data.as_ptr(),
+            data.len(),
+        );
+        callback(code, StrongholdByteBuffer::empty(), user_data.into_inner());
+    })
+}
+
+/// Asynchronous variant of [`stronghold_generate_seed`].
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_generate_seed_async(
+    stronghold_ptr: *mut StrongholdWrapper,
+    _key_c: *const libc::c_char,
+    callback: StrongholdCallback,
+    user_data: *mut c_void,
+) -> StrongholdErrorCode {
+    let (stronghold_ptr, user_data) = (Unchecked(stronghold_ptr), Unchecked(user_data));
+
+    spawn(move || {
+        let code = stronghold_generate_seed(stronghold_ptr.into_inner(), ptr::null());
+        callback(code, StrongholdByteBuffer::empty(), user_data.into_inner());
+    })
+}
+
+/// Asynchronous variant of [`stronghold_derive_seed`]. The chain code is passed to the callback.
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_derive_seed_async(
+    stronghold_ptr: *mut StrongholdWrapper,
+    _key_c: *const libc::c_char,
+    address_index: u32,
+    callback: StrongholdCallback,
+    user_data: *mut c_void,
+) -> StrongholdErrorCode {
+    let (stronghold_ptr, user_data) = (Unchecked(stronghold_ptr), Unchecked(user_data));
+
+    spawn(move || {
+        let mut out = StrongholdByteBuffer::empty();
+        let code = stronghold_derive_seed(stronghold_ptr.into_inner(), ptr::null(), address_index, &mut out);
+        callback(code, out, user_data.into_inner());
+    })
+}
+
+/// Asynchronous variant of [`stronghold_sign`]. The signature is passed to the callback.
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn stronghold_sign_async(
+    stronghold_ptr: *mut StrongholdWrapper,
+    record_path_c: *const libc::c_char,
+    data_c: *const libc::c_uchar,
+    data_length: libc::size_t,
+    callback: StrongholdCallback,
+    user_data: *mut c_void,
+) -> StrongholdErrorCode {
+    let record_path = try_ffi!(c_string(record_path_c, "record_path_c"));
+    let data = try_ffi!(bytes(data_c, data_length, "data_c"));
+    let (stronghold_ptr, user_data) = (Unchecked(stronghold_ptr), Unchecked(user_data));
+
+    spawn(move || {
+        let mut out = StrongholdByteBuffer::empty();
+        let code = stronghold_sign(
+            stronghold_ptr.into_inner(),
+            record_path.as_ptr(),
+            data.as_ptr(),
+            data.len(),
+            &mut out,
+        );
+        callback(code, out, user_data.into_inner());
+    })
+}
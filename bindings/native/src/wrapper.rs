@@ -2,117 +2,264 @@
 // SPDX-License-Identifier: Apache-2.0
 
 //#![allow(unused_imports)]
+use std::sync::{Mutex, MutexGuard};
+
 use crypto::keys::slip10::ChainCode;
 use iota_stronghold::{
-    procedures::{Chain, Ed25519Sign, GenerateKey, KeyType, PublicKey, Slip10Derive, Slip10Generate, WriteVault},
-    Client, KeyProvider, Location, SnapshotPath, Stronghold,
+    procedures::{
+        Bip44Path, Curve, Ed25519Sign, GenerateKey, KeyType, ProcedureError, PublicKey, Slip10Derive, Slip10Generate,
+        WriteVault,
+    },
+    sync::{MergePolicy, SyncClientsConfig},
+    Argon2Params, Client, ClientError, KeyProvider, Location, MemoryError, PasswordKey, Snapshot, SnapshotFormat,
+    SnapshotPath, Stronghold,
 };
-use log::*;
 use thiserror::Error as DeriveError;
 
+use crate::shared::hash_blake2b;
+
 const CLIENT_PATH: &str = "wasp";
 const VAULT_PATH: &str = "wasp";
 const KEY_TYPE: KeyType = KeyType::Ed25519;
 const SEED_LENGTH: usize = 32;
 const RECORD_PATH_SEED: &str = "seed";
 
+/// A snapshot with its client, that is loaded through the FFI.
+///
+/// The calls, that commit the snapshot, hold the lock of its key until they are done, so that the
+/// asynchronous variants of the calls on one instance are executed one after another.
 pub struct StrongholdWrapper {
-    snapshot_path: String,
+    snapshot_path: SnapshotPath,
     stronghold: Stronghold,
     client: Client,
+    key: Mutex<SnapshotKey>,
+}
+
+/// The key of a snapshot, that is derived from the password once, when the snapshot is created or loaded.
+///
+/// Snapshots without a key derivation header have been encrypted with a single Blake2b pass over the
+/// password. New snapshots use Argon2id, with the parameters and the salt in the header of the snapshot.
+enum SnapshotKey {
+    Blake2b(KeyProvider),
+    Argon2id(PasswordKey),
+}
+
+impl SnapshotKey {
+    /// Derives the key of the snapshot at `snapshot_path` from `password`
+    fn derive(snapshot_path: &SnapshotPath, password: &str) -> Result<Self, WrapperError> {
+        let info = Snapshot::peek_metadata(snapshot_path).map_err(|e| WrapperError::KeyDerivation(e.to_string()))?;
+        match info.format {
+            SnapshotFormat::Password => PasswordKey::from_header(snapshot_path.as_path(), password.as_bytes())
+                .map(SnapshotKey::Argon2id)
+                .map_err(|e| WrapperError::KeyDerivation(e.to_string())),
+            _ => Ok(SnapshotKey::Blake2b(KeyProvider::try_from(hash_blake2b(
+                password.to_string(),
+            ))?)),
+        }
+    }
+
+    /// Derives a new Argon2id key from `password` with `params` and a fresh salt
+    fn argon2id(password: &str, params: Argon2Params) -> Result<Self, WrapperError> {
+        PasswordKey::new(password.as_bytes(), &params)
+            .map(SnapshotKey::Argon2id)
+            .map_err(|e| WrapperError::KeyDerivation(e.to_string()))
+    }
+
+    /// Returns `true`, if both keys have been derived in the same way from the same password
+    fn matches(&self, other: &Self) -> Result<bool, WrapperError> {
+        match (self, other) {
+            (SnapshotKey::Blake2b(a), SnapshotKey::Blake2b(b)) => {
+                Ok(*a.try_unlock()?.borrow() == *b.try_unlock()?.borrow())
+            }
+            (SnapshotKey::Argon2id(a), SnapshotKey::Argon2id(b)) => Ok(a.key() == b.key()),
+            _ => Ok(false),
+        }
+    }
+
+    /// Loads the client from the snapshot at `snapshot_path` into `stronghold`
+    fn load(&self, stronghold: &Stronghold, snapshot_path: &SnapshotPath) -> Result<Client, ClientError> {
+        match self {
+            SnapshotKey::Blake2b(key) => stronghold.load_client_from_snapshot(CLIENT_PATH, key, snapshot_path),
+            SnapshotKey::Argon2id(key) => {
+                stronghold.load_snapshot_with_password_key(key, snapshot_path)?;
+                stronghold.load_client(CLIENT_PATH)
+            }
+        }
+    }
+
+    /// Atomically writes the state of `stronghold` to the snapshot at `snapshot_path`
+    fn commit(&self, stronghold: &Stronghold, snapshot_path: &SnapshotPath) -> Result<(), ClientError> {
+        match self {
+            SnapshotKey::Blake2b(key) => stronghold.commit_with_keyprovider(snapshot_path, key),
+            SnapshotKey::Argon2id(key) => stronghold.commit_with_password_key(snapshot_path, key),
+        }
+    }
 }
 
 #[derive(Debug, DeriveError)]
 #[non_exhaustive]
 pub enum WrapperError {
-    #[error("Failed to open snapshot")]
-    OpenSnapshot,
+    #[error("Argument {0} is a null pointer")]
+    NullPointer(&'static str),
+
+    #[error("Argument {0} is not valid UTF-8")]
+    InvalidString(&'static str),
 
-    #[error("Failed to commit to snapshot")]
-    CommitToSnapshot,
+    #[error("No instance with handle {0}")]
+    InvalidHandle(u64),
 
-    #[error("Failed to create client")]
-    CreateClient,
+    #[error("Argument {0} has the unknown value {1}")]
+    InvalidArgument(&'static str, u64),
 
-    #[error("Failed to write client")]
-    WriteClient,
+    #[error("Failed to open snapshot: ({0})")]
+    OpenSnapshot(#[source] ClientError),
+
+    #[error("Failed to commit to snapshot: ({0})")]
+    CommitToSnapshot(#[source] ClientError),
+
+    #[error("Failed to create client: ({0})")]
+    CreateClient(#[source] ClientError),
+
+    #[error("Failed to write client: ({0})")]
+    WriteClient(#[source] ClientError),
 
     #[error("Failed to execute procedure: ({0})")]
-    ExecuteProcedure(String),
+    ExecuteProcedure(#[from] ProcedureError),
+
+    #[error("Failed to derive key: ({0})")]
+    KeyDerivation(String),
+
+    #[error("Failed to migrate snapshot: ({0})")]
+    MigrateSnapshot(#[source] ClientError),
+
+    #[error("Failed to import snapshot: ({0})")]
+    ImportSnapshot(#[source] ClientError),
+
+    #[error("Failed to protect the key: ({0})")]
+    Memory(#[from] MemoryError),
+
+    #[error("Failed to acquire the lock of the instance")]
+    LockAcquireFailed,
 }
 
 impl StrongholdWrapper {
-    pub fn from_file<R>(snapshot_path: String, key_as_hash: R) -> Result<Self, WrapperError>
-    where
-        R: AsRef<[u8]>,
-    {
+    pub fn from_file(snapshot_path: String, password: &str) -> Result<Self, WrapperError> {
         let stronghold = Stronghold::default();
 
         log::info!("[Rust] Loading snapshot => {}", snapshot_path);
 
-        let commit_snapshot_path = &SnapshotPath::from_path(snapshot_path.clone());
-        let key_provider = &KeyProvider::try_from(key_as_hash.as_ref().to_vec()).unwrap();
+        let snapshot_path = SnapshotPath::from_path(snapshot_path);
+        let key = SnapshotKey::derive(&snapshot_path, password)?;
 
-        let client = stronghold.load_client_from_snapshot(CLIENT_PATH, key_provider, commit_snapshot_path);
-
-        let client = match client {
-            Ok(res) => res,
-            Err(_err) => return Err(WrapperError::OpenSnapshot),
-        };
+        let client = key
+            .load(&stronghold, &snapshot_path)
+            .map_err(WrapperError::OpenSnapshot)?;
 
         Ok(Self {
             snapshot_path,
             stronghold,
             client,
+            key: Mutex::new(key),
         })
     }
 
-    pub fn create_new<R>(snapshot_path: String, key_as_hash: R) -> Result<Self, WrapperError>
-    where
-        R: AsRef<[u8]>,
-    {
+    pub fn create_new(snapshot_path: String, password: &str, params: Argon2Params) -> Result<Self, WrapperError> {
         let stronghold = Stronghold::default();
 
-        let client = match stronghold.create_client(CLIENT_PATH) {
-            Ok(res) => res,
-            Err(_err) => return Err(WrapperError::CreateClient),
-        };
+        let key = SnapshotKey::argon2id(password, params)?;
+
+        let client = stronghold
+            .create_client(CLIENT_PATH)
+            .map_err(WrapperError::CreateClient)?;
 
         let result = Self {
-            snapshot_path,
+            snapshot_path: SnapshotPath::from_path(snapshot_path),
             stronghold,
             client,
+            key: Mutex::new(key),
         };
 
         log::info!("[Rust] Client created");
 
-        if let Err(_err) = result.stronghold.write_client(CLIENT_PATH) {
-            return Err(WrapperError::WriteClient);
-        }
+        result
+            .stronghold
+            .write_client(CLIENT_PATH)
+            .map_err(WrapperError::WriteClient)?;
 
         log::info!("[Rust] Client written");
 
-        result.commit_with_key(key_as_hash)?;
+        result.commit(&*result.lock_key()?)?;
 
         Ok(result)
     }
 
-    fn commit_with_key<R>(&self, key_as_hash: R) -> Result<bool, WrapperError>
-    where
-        R: AsRef<[u8]>,
-    {
-        log::info!("[Rust] Committing to snapshot");
+    /// Re-encrypts the snapshot with a key that is derived from `password` with Argon2id and `params`. This
+    /// upgrades snapshots, whose key has been derived with Blake2b, and raises the parameters of snapshots
+    /// that already use Argon2id.
+    ///
+    /// `password` has to be the password of the snapshot. The snapshot file is replaced atomically, so it
+    /// can be loaded with either the old or the new key derivation, if the migration is interrupted.
+    pub fn migrate_kdf(&self, password: &str, params: Argon2Params) -> Result<bool, WrapperError> {
+        log::info!("[Rust] Migrating snapshot key derivation");
+
+        let mut current = self.lock_key()?;
+        if !SnapshotKey::derive(&self.snapshot_path, password)?.matches(&current)? {
+            return Err(WrapperError::KeyDerivation("Invalid password".to_string()));
+        }
 
-        let commit_snapshot_path = &SnapshotPath::from_path(self.snapshot_path.clone());
-        let key_provider = &KeyProvider::try_from(key_as_hash.as_ref().to_vec()).unwrap();
+        let key = SnapshotKey::argon2id(password, params)?;
+        key.commit(&self.stronghold, &self.snapshot_path)
+            .map_err(WrapperError::MigrateSnapshot)?;
+        *current = key;
 
-        match self
-            .stronghold
-            .commit_with_keyprovider(commit_snapshot_path, key_provider)
-        {
-            Err(_err) => Err(WrapperError::CommitToSnapshot),
-            _ => Ok(true),
+        Ok(true)
+    }
+
+    /// Imports the records of the snapshot at `source_path`, e.g. a backup from another device, and
+    /// commits the merged state. If `record_paths` is `Some`, only the given records are imported.
+    /// Records that exist in both snapshots are resolved with `merge_policy`.
+    pub fn import_snapshot(
+        &self,
+        source_path: String,
+        source_password: &str,
+        record_paths: Option<Vec<String>>,
+        merge_policy: MergePolicy,
+    ) -> Result<bool, WrapperError> {
+        log::info!("[Rust] Importing snapshot => {}", source_path);
+
+        let key = self.lock_key()?;
+        let source_path = SnapshotPath::from_path(source_path);
+        let source = SnapshotKey::derive(&source_path, source_password)?
+            .load(&Stronghold::default(), &source_path)
+            .map_err(WrapperError::ImportSnapshot)?;
+
+        let mut config = SyncClientsConfig::new(merge_policy);
+        if let Some(record_paths) = record_paths {
+            config.sync_selected_record(VAULT_PATH, record_paths);
         }
+        self.client
+            .sync_with(&source, config)
+            .map_err(WrapperError::ImportSnapshot)?;
+
+        log::info!("[Rust] Snapshot imported");
+
+        self.stronghold
+            .write_client(CLIENT_PATH)
+            .map_err(WrapperError::WriteClient)?;
+
+        self.commit(&key)
+    }
+
+    fn lock_key(&self) -> Result<MutexGuard<'_, SnapshotKey>, WrapperError> {
+        self.key.lock().map_err(|_| WrapperError::LockAcquireFailed)
+    }
+
+    fn commit(&self, key: &SnapshotKey) -> Result<bool, WrapperError> {
+        log::info!("[Rust] Committing to snapshot");
+
+        key.commit(&self.stronghold, &self.snapshot_path)
+            .map_err(WrapperError::CommitToSnapshot)?;
+        Ok(true)
     }
 
     pub fn get_public_key(&self, record_path: String) -> Result<Vec<u8>, WrapperError> {
@@ -126,20 +273,14 @@ impl StrongholdWrapper {
             private_key,
         };
 
-        let procedure_result = match self.client.execute_procedure(public_key_procedure) {
-            Ok(res) => res,
-            Err(_err) => return Err(WrapperError::ExecuteProcedure(format!("{:?}", _err))),
-        };
-
-        let output: Vec<u8> = procedure_result.into();
+        let output = self.client.execute_procedure(public_key_procedure)?;
 
         Ok(output)
     }
 
-    pub fn write_vault<R>(&self, key_as_hash: R, record_path: String, data: Vec<u8>) -> Result<bool, WrapperError>
-    where
-        R: AsRef<[u8]>,
-    {
+    pub fn write_vault(&self, record_path: String, data: Vec<u8>) -> Result<bool, WrapperError> {
+        let key = self.lock_key()?;
+
         let location = Location::Generic {
             record_path: record_path.as_bytes().to_vec(),
             vault_path: VAULT_PATH.as_bytes().to_vec(),
@@ -147,11 +288,9 @@ impl StrongholdWrapper {
 
         let sign_procedure = WriteVault { data, location };
 
-        if let Err(_err) = self.client.execute_procedure(sign_procedure) {
-            return Err(WrapperError::ExecuteProcedure(format!("{:?}", _err)));
-        }
+        self.client.execute_procedure(sign_procedure)?;
 
-        self.commit_with_key(key_as_hash)
+        self.commit(&key)
     }
 
     pub fn sign(&self, record_path: String, data: Vec<u8>) -> Result<Vec<u8>, WrapperError> {
@@ -162,20 +301,16 @@ impl StrongholdWrapper {
 
         let sign_procedure = Ed25519Sign { private_key, msg: data };
 
-        let procedure_result = match self.client.execute_procedure(sign_procedure) {
-            Ok(res) => res,
-            Err(_err) => return Err(WrapperError::ExecuteProcedure(format!("{:?}", _err))),
-        };
+        let procedure_result = self.client.execute_procedure(sign_procedure)?;
 
         let signature: Vec<u8> = procedure_result.into();
 
         Ok(signature)
     }
 
-    pub fn derive_seed<R>(&self, key_as_hash: R, address_index: u32) -> Result<ChainCode, WrapperError>
-    where
-        R: AsRef<[u8]>,
-    {
+    pub fn derive_seed(&self, address_index: u32) -> Result<ChainCode, WrapperError> {
+        let key = self.lock_key()?;
+
         let seed_derived_path = format!("{RECORD_PATH_SEED}.{address_index}");
 
         let seed_location = Location::Generic {
@@ -188,46 +323,38 @@ impl StrongholdWrapper {
             vault_path: VAULT_PATH.as_bytes().to_vec(),
         };
 
-        let chain = Chain::from_u32_hardened(vec![
-            44,   // BIP-0044
-            4218, // IOTA coin type
-            0,    // zero account id
-            0,    // public
-            address_index,
-        ]);
+        // IOTA coin type, zero account id, public addresses
+        let path = Bip44Path::new(4218, 0, 0, address_index);
 
         log::info!("[Rust] Deriving Seed procedure started");
 
-        let slip10_derive = Slip10Derive {
-            chain,
-            input: iota_stronghold::procedures::Slip10DeriveInput::Seed(seed_location),
-            output: seed_derived_location,
-        };
+        let slip10_derive = Slip10Derive::bip44(
+            Curve::Ed25519,
+            &path,
+            iota_stronghold::procedures::Slip10DeriveInput::Seed(seed_location),
+            seed_derived_location,
+        );
 
-        let chain_code = match self.client.execute_procedure(slip10_derive) {
-            Ok(res) => res,
-            Err(_err) => return Err(WrapperError::ExecuteProcedure(format!("{:?}", _err))),
-        };
+        let chain_code = self.client.execute_procedure(slip10_derive)?;
 
         log::info!("[Rust] Derive generated");
         log::info!("[Rust] Storing client");
 
-        if let Err(_err) = self.stronghold.write_client(CLIENT_PATH) {
-            return Err(WrapperError::WriteClient);
-        }
+        self.stronghold
+            .write_client(CLIENT_PATH)
+            .map_err(WrapperError::WriteClient)?;
 
         log::info!("[Rust] client stored");
 
-        match self.commit_with_key(key_as_hash) {
+        match self.commit(&key) {
             Err(err) => Err(err),
             _ => Ok(chain_code),
         }
     }
 
-    pub fn generate_seed<R>(&self, key_as_hash: R) -> Result<bool, WrapperError>
-    where
-        R: AsRef<[u8]>,
-    {
+    pub fn generate_seed(&self) -> Result<bool, WrapperError> {
+        let key = self.lock_key()?;
+
         let output = Location::Generic {
             record_path: RECORD_PATH_SEED.as_bytes().to_vec(),
             vault_path: VAULT_PATH.as_bytes().to_vec(),
@@ -240,26 +367,23 @@ impl StrongholdWrapper {
             output,
         };
 
-        if let Err(_err) = self.client.execute_procedure(slip10_generate) {
-            return Err(WrapperError::ExecuteProcedure(format!("{:?}", _err)));
-        }
+        self.client.execute_procedure(slip10_generate)?;
 
         log::info!("[Rust] Key generated");
         log::info!("[Rust] Storing client");
 
-        if let Err(_err) = self.stronghold.write_client(CLIENT_PATH) {
-            return Err(WrapperError::WriteClient);
-        }
+        self.stronghold
+            .write_client(CLIENT_PATH)
+            .map_err(WrapperError::WriteClient)?;
 
         log::info!("[Rust] client stored");
 
-        self.commit_with_key(key_as_hash)
+        self.commit(&key)
     }
 
-    pub fn generate_ed25519_keypair<R>(&self, key_as_hash: R, record_path: String) -> Result<bool, WrapperError>
-    where
-        R: AsRef<[u8]>,
-    {
+    pub fn generate_ed25519_keypair(&self, record_path: String) -> Result<bool, WrapperError> {
+        let key = self.lock_key()?;
+
         let output = Location::Generic {
             record_path: record_path.as_bytes().to_vec(),
             vault_path: VAULT_PATH.as_bytes().to_vec(),
@@ -269,19 +393,17 @@ impl StrongholdWrapper {
 
         log::info!("[Rust] Generating Key procedure started");
 
-        if let Err(_err) = self.client.execute_procedure(generate_key_procedure) {
-            return Err(WrapperError::ExecuteProcedure(format!("{:?}", _err)));
-        }
+        self.client.execute_procedure(generate_key_procedure)?;
 
         log::info!("[Rust] Key generated");
         log::info!("[Rust] Storing client");
 
-        if let Err(_err) = self.stronghold.write_client(CLIENT_PATH) {
-            return Err(WrapperError::WriteClient);
-        }
+        self.stronghold
+            .write_client(CLIENT_PATH)
+            .map_err(WrapperError::WriteClient)?;
 
         log::info!("[Rust] client stored");
 
-        self.commit_with_key(key_as_hash)
+        self.commit(&key)
     }
 }
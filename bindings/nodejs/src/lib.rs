@@ -0,0 +1,161 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Node.js bindings of the client interface, built with napi-rs.
+//!
+//! Secrets, e.g. passwords, are passed as `Buffer`s and zeroized as soon as they have been copied, so
+//! that they don't linger in the JavaScript heap until the next garbage collection. Snapshot operations
+//! and procedures are computed on the libuv thread pool and return a promise.
+
+#[macro_use]
+extern crate napi_derive;
+
+mod task;
+
+use iota_stronghold::{Location, SnapshotPath};
+use napi::{
+    bindgen_prelude::{AsyncTask, Buffer},
+    Error,
+};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::task::{Commit, Ed25519PublicKey, Ed25519Signature, GenerateEd25519, LoadSnapshot};
+
+pub(crate) fn napi_error(e: impl ToString) -> Error {
+    Error::from_reason(e.to_string())
+}
+
+/// Copies the secret out of `buffer` and zeroizes the buffer
+fn take_secret(mut buffer: Buffer) -> Zeroizing<Vec<u8>> {
+    let secret = Zeroizing::new(buffer.to_vec());
+    let bytes: &mut [u8] = &mut buffer;
+    bytes.zeroize();
+    secret
+}
+
+#[napi]
+pub struct Stronghold {
+    inner: iota_stronghold::Stronghold,
+}
+
+#[napi]
+impl Stronghold {
+    #[napi(constructor)]
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            inner: iota_stronghold::Stronghold::default(),
+        }
+    }
+
+    /// Creates a new client with `clientPath`
+    #[napi]
+    pub fn create_client(&self, client_path: Buffer) -> napi::Result<Client> {
+        self.inner
+            .create_client(client_path.as_ref())
+            .map(|inner| Client { inner })
+            .map_err(napi_error)
+    }
+
+    /// Loads the client with `clientPath` from the loaded snapshot
+    #[napi]
+    pub fn load_client(&self, client_path: Buffer) -> napi::Result<Client> {
+        self.inner
+            .load_client(client_path.as_ref())
+            .map(|inner| Client { inner })
+            .map_err(napi_error)
+    }
+
+    /// Loads the snapshot at `snapshotPath`, whose key is derived from `password`. The password buffer is
+    /// zeroized.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn load_snapshot(&self, snapshot_path: String, password: Buffer) -> AsyncTask<LoadSnapshot> {
+        AsyncTask::new(LoadSnapshot {
+            stronghold: self.inner.clone(),
+            snapshot_path: SnapshotPath::from_path(snapshot_path),
+            password: take_secret(password),
+        })
+    }
+
+    /// Writes all clients to the snapshot at `snapshotPath`, whose key is derived from `password`. The
+    /// password buffer is zeroized.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn commit(&self, snapshot_path: String, password: Buffer) -> AsyncTask<Commit> {
+        AsyncTask::new(Commit {
+            stronghold: self.inner.clone(),
+            snapshot_path: SnapshotPath::from_path(snapshot_path),
+            password: take_secret(password),
+        })
+    }
+}
+
+#[napi]
+pub struct Client {
+    inner: iota_stronghold::Client,
+}
+
+#[napi]
+impl Client {
+    /// Writes `secret` to the record at `recordPath` in the vault at `vaultPath`. The secret buffer is
+    /// zeroized.
+    #[napi]
+    pub fn write_secret(&self, vault_path: Buffer, record_path: Buffer, secret: Buffer) -> napi::Result<()> {
+        let secret = take_secret(secret);
+        self.inner
+            .vault(vault_path.as_ref())
+            .write_secret(location(&vault_path, &record_path), secret.to_vec())
+            .map_err(napi_error)
+    }
+
+    /// Generates an Ed25519 key in the record at `recordPath` in the vault at `vaultPath`
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn generate_ed25519(&self, vault_path: Buffer, record_path: Buffer) -> AsyncTask<GenerateEd25519> {
+        AsyncTask::new(GenerateEd25519 {
+            client: self.inner.clone(),
+            location: location(&vault_path, &record_path),
+        })
+    }
+
+    /// Returns the public key of the Ed25519 key in the record at `recordPath`
+    #[napi(ts_return_type = "Promise<Buffer>")]
+    pub fn ed25519_public_key(&self, vault_path: Buffer, record_path: Buffer) -> AsyncTask<Ed25519PublicKey> {
+        AsyncTask::new(Ed25519PublicKey {
+            client: self.inner.clone(),
+            location: location(&vault_path, &record_path),
+        })
+    }
+
+    /// Signs `msg` with the Ed25519 key in the record at `recordPath`
+    #[napi(ts_return_type = "Promise<Buffer>")]
+    pub fn ed25519_sign(&self, vault_path: Buffer, record_path: Buffer, msg: Buffer) -> AsyncTask<Ed25519Signature> {
+        AsyncTask::new(Ed25519Signature {
+            client: self.inner.clone(),
+            location: location(&vault_path, &record_path),
+            msg: msg.to_vec(),
+        })
+    }
+
+    /// Inserts `value` under `key` into the store of the client
+    #[napi]
+    pub fn store_insert(&self, key: Buffer, value: Buffer) -> napi::Result<()> {
+        self.inner
+            .store()
+            .insert(key.to_vec(), value.to_vec(), None)
+            .map(|_| ())
+            .map_err(napi_error)
+    }
+
+    /// Returns the value under `key` in the store of the client
+    #[napi]
+    pub fn store_get(&self, key: Buffer) -> napi::Result<Option<Buffer>> {
+        self.inner
+            .store()
+            .get(&key)
+            .map(|value| value.map(Buffer::from))
+            .map_err(napi_error)
+    }
+}
+
+fn location(vault_path: &[u8], record_path: &[u8]) -> Location {
+    Location::generic(vault_path, record_path)
+}
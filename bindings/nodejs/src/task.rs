@@ -0,0 +1,128 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Operations, that are too expensive for the JavaScript thread, e.g. the key derivation of a snapshot
+//! or a procedure. They are computed on the libuv thread pool and resolve the returned promise.
+
+use iota_stronghold::{
+    procedures::{Ed25519Sign, GenerateKey, KeyType, PublicKey},
+    Argon2Params, Client, Location, SnapshotPath, Stronghold,
+};
+use napi::{bindgen_prelude::Buffer, Env, Task};
+use zeroize::Zeroizing;
+
+use crate::napi_error;
+
+pub struct LoadSnapshot {
+    pub(crate) stronghold: Stronghold,
+    pub(crate) snapshot_path: SnapshotPath,
+    pub(crate) password: Zeroizing<Vec<u8>>,
+}
+
+impl Task for LoadSnapshot {
+    type Output = ();
+    type JsValue = ();
+
+    fn compute(&mut self) -> napi::Result<()> {
+        self.stronghold
+            .load_snapshot_with_password(self.password.to_vec(), &self.snapshot_path)
+            .map_err(napi_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: ()) -> napi::Result<()> {
+        Ok(output)
+    }
+}
+
+pub struct Commit {
+    pub(crate) stronghold: Stronghold,
+    pub(crate) snapshot_path: SnapshotPath,
+    pub(crate) password: Zeroizing<Vec<u8>>,
+}
+
+impl Task for Commit {
+    type Output = ();
+    type JsValue = ();
+
+    fn compute(&mut self) -> napi::Result<()> {
+        self.stronghold
+            .commit_with_password(&self.snapshot_path, self.password.to_vec(), &Argon2Params::default())
+            .map_err(napi_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: ()) -> napi::Result<()> {
+        Ok(output)
+    }
+}
+
+pub struct GenerateEd25519 {
+    pub(crate) client: Client,
+    pub(crate) location: Location,
+}
+
+impl Task for GenerateEd25519 {
+    type Output = ();
+    type JsValue = ();
+
+    fn compute(&mut self) -> napi::Result<()> {
+        self.client
+            .execute_procedure(GenerateKey {
+                ty: KeyType::Ed25519,
+                output: self.location.clone(),
+            })
+            .map_err(napi_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: ()) -> napi::Result<()> {
+        Ok(output)
+    }
+}
+
+pub struct Ed25519PublicKey {
+    pub(crate) client: Client,
+    pub(crate) location: Location,
+}
+
+impl Task for Ed25519PublicKey {
+    type Output = Vec<u8>;
+    type JsValue = Buffer;
+
+    fn compute(&mut self) -> napi::Result<Vec<u8>> {
+        self.client
+            .execute_procedure(PublicKey {
+                ty: KeyType::Ed25519,
+                private_key: self.location.clone(),
+            })
+            .map(|public_key| public_key.to_vec())
+            .map_err(napi_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Vec<u8>) -> napi::Result<Buffer> {
+        Ok(output.into())
+    }
+}
+
+pub struct Ed25519Signature {
+    pub(crate) client: Client,
+    pub(crate) location: Location,
+    pub(crate) msg: Vec<u8>,
+}
+
+impl Task for Ed25519Signature {
+    type Output = Vec<u8>;
+    type JsValue = Buffer;
+
+    fn compute(&mut self) -> napi::Result<Vec<u8>> {
+        self.client
+            .execute_procedure(Ed25519Sign {
+                msg: std::mem::take(&mut self.msg),
+                private_key: self.location.clone(),
+            })
+            .map(|signature| signature.to_vec())
+            .map_err(napi_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Vec<u8>) -> napi::Result<Buffer> {
+        Ok(output.into())
+    }
+}
@@ -0,0 +1,5 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+cargo run --bin uniffi-bindgen generate src/stronghold.udl --language swift --out-dir bindings/swift
+cargo run --bin uniffi-bindgen generate src/stronghold.udl --language kotlin --out-dir bindings/kotlin
@@ -0,0 +1,129 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Swift and Kotlin bindings of the client interface, generated with UniFFI from `stronghold.udl`.
+//!
+//! Both languages are generated from the same interface definition, so they expose the same API. The
+//! definition has to be updated together with this file, the scaffolding fails to compile otherwise.
+
+use std::sync::Arc;
+
+use iota_stronghold::{
+    procedures::{Ed25519Sign, GenerateKey, KeyType, ProcedureError, PublicKey},
+    Argon2Params, ClientError, Location, SnapshotPath,
+};
+use thiserror::Error as DeriveError;
+
+uniffi::include_scaffolding!("stronghold");
+
+#[derive(Debug, DeriveError)]
+pub enum StrongholdError {
+    #[error("Client error: ({message})")]
+    Client { message: String },
+
+    #[error("Procedure error: ({message})")]
+    Procedure { message: String },
+}
+
+impl From<ClientError> for StrongholdError {
+    fn from(e: ClientError) -> Self {
+        StrongholdError::Client { message: e.to_string() }
+    }
+}
+
+impl From<ProcedureError> for StrongholdError {
+    fn from(e: ProcedureError) -> Self {
+        StrongholdError::Procedure { message: e.to_string() }
+    }
+}
+
+pub struct Stronghold {
+    inner: iota_stronghold::Stronghold,
+}
+
+impl Stronghold {
+    pub fn new() -> Self {
+        Self {
+            inner: iota_stronghold::Stronghold::default(),
+        }
+    }
+
+    pub fn create_client(&self, client_path: Vec<u8>) -> Result<Arc<Client>, StrongholdError> {
+        let inner = self.inner.create_client(client_path)?;
+        Ok(Arc::new(Client { inner }))
+    }
+
+    pub fn load_client(&self, client_path: Vec<u8>) -> Result<Arc<Client>, StrongholdError> {
+        let inner = self.inner.load_client(client_path)?;
+        Ok(Arc::new(Client { inner }))
+    }
+
+    /// Loads the snapshot at `snapshot_path`, whose key is derived from `password`
+    pub fn load_snapshot(&self, snapshot_path: String, password: Vec<u8>) -> Result<(), StrongholdError> {
+        self.inner
+            .load_snapshot_with_password(password, &SnapshotPath::from_path(snapshot_path))?;
+        Ok(())
+    }
+
+    /// Writes all clients to the snapshot at `snapshot_path`, whose key is derived from `password`
+    pub fn commit(&self, snapshot_path: String, password: Vec<u8>) -> Result<(), StrongholdError> {
+        self.inner.commit_with_password(
+            &SnapshotPath::from_path(snapshot_path),
+            password,
+            &Argon2Params::default(),
+        )?;
+        Ok(())
+    }
+}
+
+impl Default for Stronghold {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Client {
+    inner: iota_stronghold::Client,
+}
+
+impl Client {
+    pub fn write_secret(
+        &self,
+        vault_path: Vec<u8>,
+        record_path: Vec<u8>,
+        secret: Vec<u8>,
+    ) -> Result<(), StrongholdError> {
+        let location = Location::generic(vault_path.clone(), record_path);
+        self.inner.vault(vault_path).write_secret(location, secret)?;
+        Ok(())
+    }
+
+    pub fn generate_ed25519(&self, vault_path: Vec<u8>, record_path: Vec<u8>) -> Result<(), StrongholdError> {
+        self.inner.execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: Location::generic(vault_path, record_path),
+        })?;
+        Ok(())
+    }
+
+    pub fn ed25519_public_key(&self, vault_path: Vec<u8>, record_path: Vec<u8>) -> Result<Vec<u8>, StrongholdError> {
+        let public_key = self.inner.execute_procedure(PublicKey {
+            ty: KeyType::Ed25519,
+            private_key: Location::generic(vault_path, record_path),
+        })?;
+        Ok(public_key.to_vec())
+    }
+
+    pub fn ed25519_sign(
+        &self,
+        vault_path: Vec<u8>,
+        record_path: Vec<u8>,
+        msg: Vec<u8>,
+    ) -> Result<Vec<u8>, StrongholdError> {
+        let signature = self.inner.execute_procedure(Ed25519Sign {
+            msg,
+            private_key: Location::generic(vault_path, record_path),
+        })?;
+        Ok(signature.to_vec())
+    }
+}
@@ -0,0 +1,6 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+fn main() {
+    uniffi::uniffi_bindgen_main()
+}
@@ -0,0 +1,63 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+use criterion::{criterion_group, criterion_main, Criterion};
+use iota_stronghold::{KeyProvider, Location, SnapshotPath, Stronghold};
+use stronghold_utils::random::fixed_bytestring;
+
+const CLIENTS: usize = 16;
+const SECRETS_PER_CLIENT: usize = 64;
+
+/// Creates a [`Stronghold`] with [`CLIENTS`] clients, each holding [`SECRETS_PER_CLIENT`] secrets
+fn setup() -> (Stronghold, Vec<Vec<u8>>) {
+    let stronghold = Stronghold::default();
+    let client_paths: Vec<Vec<u8>> = (0..CLIENTS).map(|_| fixed_bytestring(32)).collect();
+    for path in &client_paths {
+        let client = stronghold.create_client(path).unwrap();
+        let vault = client.vault(b"vault");
+        for _ in 0..SECRETS_PER_CLIENT {
+            let location = Location::generic(b"vault".to_vec(), fixed_bytestring(32));
+            vault.write_secret(location, fixed_bytestring(64)).unwrap();
+        }
+    }
+    (stronghold, client_paths)
+}
+
+fn snapshot_path(name: &str) -> SnapshotPath {
+    let mut path = std::env::temp_dir();
+    path.push(format!("stronghold-bench-{}-{}", name, std::process::id()));
+    SnapshotPath::from_path(path)
+}
+
+/// Compares a full commit with incremental commits of a single modified client and of no changes at all
+pub fn bench_persistence(c: &mut Criterion) {
+    let keyprovider = KeyProvider::try_from(fixed_bytestring(32)).unwrap();
+    let (stronghold, client_paths) = setup();
+    let client = stronghold.get_client(&client_paths[0]).unwrap();
+    let location = Location::generic(b"vault".to_vec(), b"record".to_vec());
+
+    let full = snapshot_path("full");
+    c.bench_function("bench_commit_full", |b| {
+        b.iter(|| stronghold.commit_with_keyprovider(&full, &keyprovider).unwrap())
+    });
+
+    let incremental = snapshot_path("incremental");
+    stronghold.commit_incremental(&incremental, &keyprovider).unwrap();
+    c.bench_function("bench_commit_incremental_one_dirty", |b| {
+        b.iter(|| {
+            client
+                .vault(b"vault")
+                .write_secret(location.clone(), fixed_bytestring(64))
+                .unwrap();
+            stronghold.commit_incremental(&incremental, &keyprovider).unwrap()
+        })
+    });
+    c.bench_function("bench_commit_incremental_clean", |b| {
+        b.iter(|| stronghold.commit_incremental(&incremental, &keyprovider).unwrap())
+    });
+
+    let _ = std::fs::remove_file(full.as_path());
+    let _ = std::fs::remove_file(incremental.as_path());
+}
+
+criterion_group!(benches, bench_persistence);
+criterion_main!(benches);
@@ -11,8 +11,8 @@ use iota_stronghold as stronghold;
 use log::*;
 use stronghold::{
     procedures::{
-        BIP39Generate, Chain, GenerateKey, KeyType, MnemonicLanguage, Slip10Derive, Slip10DeriveInput, Slip10Generate,
-        StrongholdProcedure,
+        BIP39Generate, Chain, Curve, GenerateKey, KeyType, MnemonicLanguage, MnemonicLength, Slip10Derive,
+        Slip10DeriveInput, Slip10Generate, StrongholdProcedure,
     },
     Client, ClientError, ClientVault, KeyProvider, Location, SnapshotPath, Store, Stronghold,
 };
@@ -287,8 +287,9 @@ async fn command_generate_bip39(passphrase: Option<String>, language: MnemonicLa
         stronghold::Location::generic(vault_path.as_bytes().to_vec(), record_path.as_bytes().to_vec());
 
     let bip39_procedure = BIP39Generate {
-        passphrase,
+        passphrase: passphrase.map(Into::into),
         language,
+        length: MnemonicLength::default(),
         output: output_location,
     };
 
@@ -330,6 +331,7 @@ async fn command_slip10_derive(chain: ChainInput, input: VaultLocation, output:
 
     info!("Deriving SLIP10 Child Secret");
     let slip10_derive = Slip10Derive {
+        curve: Curve::Ed25519,
         chain: chain.chain,
         input: Slip10DeriveInput::Seed(output_location),
         output: output.to_location(),
@@ -429,8 +431,8 @@ async fn command_bip39_recover(
 
     // get the public key
     let procedure_bip39_recover = stronghold::procedures::BIP39Recover {
-        passphrase,
-        mnemonic,
+        passphrase: passphrase.map(Into::into),
+        mnemonic: mnemonic.into(),
         output: output.to_location(),
     };
 
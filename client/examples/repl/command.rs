@@ -9,7 +9,10 @@ use crate::{
     Command, State, TermAction, HELP_MESSAGE,
 };
 use iota_stronghold::{
-    procedures::{BIP39Generate, BIP39Recover, Chain, GenerateKey, Slip10Derive, Slip10DeriveInput, Slip10Generate},
+    procedures::{
+        BIP39Generate, BIP39Recover, Chain, Curve, GenerateKey, MnemonicLength, Slip10Derive, Slip10DeriveInput,
+        Slip10Generate,
+    },
     KeyProvider, Location, SnapshotPath, Stronghold,
 };
 
@@ -285,6 +288,7 @@ impl Command for Slip10DeriveCommand {
         let record_path_new = &parameters[4];
 
         client.execute_procedure(Slip10Derive {
+            curve: Curve::Ed25519,
             chain: Chain::from_u32_hardened(chain_code.parse()),
             input: Slip10DeriveInput::Seed(Location::const_generic(
                 vault_path_old.clone().into_bytes(),
@@ -337,8 +341,9 @@ impl Command for Bip39GenerateCommand {
         let record_path = &parameters[3];
 
         let result = client.execute_procedure(BIP39Generate {
-            passphrase: Some(password.clone()),
+            passphrase: Some(password.as_str().into()),
             language: parse_lang(language)?,
+            length: MnemonicLength::default(),
             output: Location::const_generic(vault_path.clone().into_bytes(), record_path.clone().into_bytes()),
         })?;
 
@@ -374,8 +379,8 @@ impl Command for Bip39RestoreCommand {
         let record_path = &parameters[3];
 
         client.execute_procedure(BIP39Recover {
-            passphrase: Some(password.clone()),
-            mnemonic: mnemonic.clone(),
+            passphrase: Some(password.as_str().into()),
+            mnemonic: mnemonic.as_str().into(),
             output: Location::const_generic(vault_path.clone().into_bytes(), record_path.clone().into_bytes()),
         })?;
 
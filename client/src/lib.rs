@@ -12,7 +12,24 @@
 pub use crate::{internal::Provider, security::*, types::*, utils::*};
 
 #[cfg(feature = "std")]
-pub use engine::runtime::MemoryError;
+pub use engine::runtime::{MemoryError, ZeroizingString, ZeroizingVec};
+
+#[cfg(feature = "std")]
+pub use engine::snapshot::kdf::{Argon2Params, PasswordKey};
+
+#[cfg(feature = "std")]
+pub use engine::snapshot::Compression;
+
+#[cfg(feature = "std")]
+pub use engine::snapshot::info::{SnapshotFormat, SnapshotInfo};
+
+#[cfg(feature = "std")]
+pub use engine::snapshot::throttle::{Attempts, ThrottlePolicy};
+
+#[cfg(feature = "std")]
+pub use engine::vault::{
+    AuditEntry, AuditEvent, GcStats, ListOptions, Quota, RecordEntry, RecordOrder, RecordPage, Tags,
+};
 
 #[cfg(feature = "std")]
 pub(crate) use crate::sync::SnapshotHierarchy;
@@ -32,6 +49,9 @@ pub mod procedures;
 #[cfg(feature = "std")]
 pub mod sync;
 
+#[cfg(all(feature = "std", feature = "wasm", target_arch = "wasm32"))]
+pub mod wasm;
+
 // is this std?
 #[cfg(feature = "std")]
 pub mod utils;
@@ -1,22 +1,48 @@
 // Copyright 2020-2021 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
+mod bip32;
+#[cfg(feature = "bls")]
+pub(crate) mod bls;
 mod clientrunner;
+mod custom;
+mod ed25519ph;
+#[cfg(feature = "ml-dsa")]
+pub(crate) mod ml_dsa;
+mod paper_backup;
+mod pipeline;
 mod primitives;
+mod shamir;
 mod types;
+mod wrapped_key;
+mod x509;
 
+#[cfg(feature = "bls")]
+pub use bls::{
+    BlsAggregate, BlsGenerateKey, BlsProofOfPossession, BlsPublicKey, BlsSign, BLS_PUBLIC_KEY_LENGTH,
+    BLS_SIGNATURE_LENGTH,
+};
 pub use clientrunner::*;
+pub use custom::{CustomProcedure, DynProcedure, MAX_DYN_SOURCES};
+#[cfg(feature = "ml-dsa")]
+pub use ml_dsa::{MlDsaGenerateKey, MlDsaLevel, MlDsaPublicKey, MlDsaSign};
+pub use paper_backup::{PaperBackupEncoding, PaperBackupShards};
+pub use pipeline::Pipeline;
+pub use x509::{CertificateEncoding, DistinguishedName};
 
 #[cfg(feature = "insecure")]
 pub use primitives::CompareSecret;
 
 pub use primitives::{
     AeadCipher, AeadDecrypt, AeadEncrypt, AesKeyWrapCipher, AesKeyWrapDecrypt, AesKeyWrapEncrypt, BIP39Generate,
-    BIP39Recover, Chain, ChainCode, ConcatKdf, ConcatSecret, CopyRecord, Ed25519Sign, GarbageCollect, GenerateKey,
-    Hkdf, Hmac, KeyType, MnemonicLanguage, Pbkdf2Hmac, PublicKey, RevokeData, Sha2Hash, Slip10Derive,
-    Slip10DeriveInput, Slip10Generate, StrongholdProcedure, WriteVault, X25519DiffieHellman,
+    BIP39Recover, BIP39Validate, Bip44Path, Bip44PathError, Chain, ChainCode, ConcatKdf, ConcatSecret, CopyRecord,
+    Curve, EcdsaSignPrehashed, Ed25519Sign, Ed25519SignPrehashed, ExportWrappedKey, GarbageCollect, GenerateCsr,
+    GenerateKey, Hkdf, Hmac, ImportWrappedKey, KeyType, MnemonicLanguage, MnemonicLength, PaperBackupExport,
+    PaperBackupImport, Pbkdf2Hmac, PublicKey, RevokeData, SelfSignedCertificate, Sha2Hash, Slip10Derive,
+    Slip10DeriveInput, Slip10Generate, SssRecover, SssSplit, StrongholdProcedure, WriteVault, X25519DiffieHellman,
 };
 pub use types::{
-    DeriveSecret, FatalProcedureError, GenerateSecret, Procedure, ProcedureError, ProcedureOutput, UseSecret,
+    DeriveSecret, FatalProcedureError, GenerateSecret, OsRng, OutputTarget, Procedure, ProcedureError, ProcedureOutput,
+    Products, SecureRng, UseSecret,
 };
-pub(crate) use types::{Products, Runner};
+pub(crate) use types::{MixedRng, Runner};
@@ -0,0 +1,169 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! SLIP-10 derivation of private keys on the ECDSA curves secp256k1 and NIST P-256. Ed25519 keys are derived by
+//! [`crypto::keys::slip10`].
+//!
+//! Extended keys are stored like the ones of [`crypto::keys::slip10`], as the private key followed by the chain
+//! code:
+//!
+//! ```text
+//! | key: [u8; 32] | chain code: [u8; 32] |
+//! ```
+//!
+//! Unlike Ed25519, both curves support non-hardened derivation. For non-hardened segments, the chain code is
+//! mixed with the compressed public key of the parent, as specified by BIP-32.
+
+use super::FatalProcedureError;
+use crypto::keys::slip10::{Chain, Segment};
+use zeroize::Zeroize;
+
+pub(crate) const EXTENDED_KEY_LENGTH: usize = 64;
+
+macro_rules! ecdsa_curve {
+    ($name:ident, $curve:ident, $seed_key:literal) => {
+        pub(crate) mod $name {
+            use super::{FatalProcedureError, Segment, EXTENDED_KEY_LENGTH};
+            use crypto::macs::hmac::HMAC_SHA512;
+            use $curve::{
+                elliptic_curve::{
+                    ff::{Field, PrimeField},
+                    sec1::ToEncodedPoint,
+                },
+                FieldBytes, NonZeroScalar, PublicKey, Scalar,
+            };
+            use zeroize::Zeroize;
+
+            /// Parses a big endian scalar, that is smaller than the order of the curve
+            fn parse(bytes: &[u8]) -> Option<Scalar> {
+                Option::from(Scalar::from_repr(FieldBytes::clone_from_slice(bytes)))
+            }
+
+            pub(crate) fn master_key(seed: &[u8]) -> [u8; EXTENDED_KEY_LENGTH] {
+                let mut key = [0u8; EXTENDED_KEY_LENGTH];
+                HMAC_SHA512(seed, $seed_key, &mut key);
+
+                // invalid keys are hashed again, until they are valid
+                while parse(&key[..32]).map_or(true, |scalar| bool::from(scalar.is_zero())) {
+                    let mut data = key;
+                    HMAC_SHA512(&data, $seed_key, &mut key);
+                    data.zeroize();
+                }
+                key
+            }
+
+            pub(crate) fn child_key(
+                parent: &[u8],
+                segment: &Segment,
+            ) -> Result<[u8; EXTENDED_KEY_LENGTH], FatalProcedureError> {
+                if parent.len() != EXTENDED_KEY_LENGTH {
+                    return Err(format!("Invalid length {} of the extended key", parent.len()).into());
+                }
+                let (parent_key, chain_code) = parent.split_at(32);
+                let parent_scalar = parse(parent_key)
+                    .and_then(|scalar| Option::from(NonZeroScalar::new(scalar)))
+                    .ok_or_else(|| FatalProcedureError::from("Invalid private key".to_string()))?;
+
+                let mut data = Vec::with_capacity(37);
+                if segment.hardened() {
+                    data.push(0);
+                    data.extend_from_slice(parent_key);
+                } else {
+                    let public_key = PublicKey::from_secret_scalar(&parent_scalar);
+                    data.extend_from_slice(public_key.to_encoded_point(true).as_bytes());
+                }
+                data.extend_from_slice(&segment.bs());
+
+                loop {
+                    let mut i = [0u8; EXTENDED_KEY_LENGTH];
+                    HMAC_SHA512(&data, chain_code, &mut i);
+                    data.zeroize();
+
+                    if let Some(tweak) = parse(&i[..32]) {
+                        let child = tweak + *parent_scalar;
+                        if !bool::from(child.is_zero()) {
+                            let mut key = [0u8; EXTENDED_KEY_LENGTH];
+                            key[..32].copy_from_slice(&child.to_repr());
+                            key[32..].copy_from_slice(&i[32..]);
+                            i.zeroize();
+                            return Ok(key);
+                        }
+                    }
+
+                    // the probability of an invalid child is below 2^-127, it is skipped as specified by SLIP-10
+                    data = Vec::with_capacity(37);
+                    data.push(1);
+                    data.extend_from_slice(&i[32..]);
+                    data.extend_from_slice(&segment.bs());
+                    i.zeroize();
+                }
+            }
+        }
+    };
+}
+
+ecdsa_curve!(secp256k1, k256, b"Bitcoin seed");
+ecdsa_curve!(nist_p256, p256, b"Nist256p1 seed");
+
+/// Derives the extended key at `chain` from the extended key `parent` with `child_key`.
+pub(crate) fn derive<F>(parent: &[u8], chain: &Chain, child_key: F) -> Result<Vec<u8>, FatalProcedureError>
+where
+    F: Fn(&[u8], &Segment) -> Result<[u8; EXTENDED_KEY_LENGTH], FatalProcedureError>,
+{
+    let mut key = parent.to_vec();
+    for segment in chain.segments() {
+        let mut child = child_key(&key, &segment)?;
+        key.zeroize();
+        key = child.to_vec();
+        child.zeroize();
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // test vector 1 for nist256p1 of SLIP-10
+    #[test]
+    fn test_nist_p256_vector() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = nist_p256::master_key(&seed);
+        assert_eq!(
+            hex::encode(&master[32..]),
+            "beeb672fe4621673f722f38529c07392fecaa61015c80c34f29ce8b41b3cb6ea"
+        );
+        assert_eq!(
+            hex::encode(&master[..32]),
+            "612091aaa12e22dd2abef664f8a01a82cae99ad7441b7ef8110424915c268bc2"
+        );
+
+        let child = derive(&master, &Chain::from_u32_hardened(vec![0]), nist_p256::child_key).unwrap();
+        assert_eq!(
+            hex::encode(&child[32..]),
+            "3460cea53e6a6bb5fb391eeef3237ffd8724bf0a40e94943c98b83825342ee11"
+        );
+        assert_eq!(
+            hex::encode(&child[..32]),
+            "6939694369114c67917a182c59ddb8cafc3004e63ca5d3b84403ba8613debc0c"
+        );
+    }
+
+    // test vector 1 for secp256k1 of SLIP-10, which matches BIP-32
+    #[test]
+    fn test_secp256k1_vector() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = secp256k1::master_key(&seed);
+        assert_eq!(
+            hex::encode(&master[..32]),
+            "e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35"
+        );
+
+        // m/0'/1
+        let child = derive(&master, &Chain::from_u32(vec![1 << 31, 1]), secp256k1::child_key).unwrap();
+        assert_eq!(
+            hex::encode(&child[..32]),
+            "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! BLS signatures on BLS12-381 in the minimal-pubkey-size variant: public keys are compressed G1 points of 48
+//! bytes, signatures are compressed G2 points of 96 bytes. Messages are signed with the ciphersuite of the
+//! proof-of-possession scheme of the IETF BLS draft, which is also used by Ethereum validators.
+
+use super::{
+    types::{GenerateSecret, Procedure, ProcedureError, Products, Runner, SecureRng, UseSecret},
+    FatalProcedureError, OsRng,
+};
+use crate::Location;
+use blst::{
+    min_pk::{AggregateSignature, SecretKey, Signature},
+    BLST_ERROR,
+};
+use engine::runtime::memories::buffer::{Buffer, Ref};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+pub const BLS_PUBLIC_KEY_LENGTH: usize = 48;
+pub const BLS_SIGNATURE_LENGTH: usize = 96;
+
+const SIGNATURE_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+const POP_DST: &[u8] = b"BLS_POP_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+fn bls_error(e: BLST_ERROR) -> FatalProcedureError {
+    FatalProcedureError::from(format!("BLS error: {:?}", e))
+}
+
+fn bls_secret_key(raw: Ref<u8>) -> Result<SecretKey, FatalProcedureError> {
+    SecretKey::from_bytes(&raw).map_err(bls_error)
+}
+
+/// Generate a BLS12-381 private key from 32 bytes of randomness, and store it in the `output` location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlsGenerateKey {
+    pub output: Location,
+}
+
+impl GenerateSecret for BlsGenerateKey {
+    type Output = ();
+
+    fn generate(self) -> Result<Products<Self::Output>, FatalProcedureError> {
+        self.generate_with_rng(&OsRng)
+    }
+
+    fn generate_with_rng(self, rng: &dyn SecureRng) -> Result<Products<Self::Output>, FatalProcedureError> {
+        let mut ikm = [0u8; 32];
+        rng.fill(&mut ikm)?;
+        let sk = SecretKey::key_gen(&ikm, &[]);
+        ikm.zeroize();
+        Ok(Products {
+            secret: sk.map_err(bls_error)?.to_bytes().to_vec(),
+            output: (),
+        })
+    }
+
+    fn target(&self) -> &Location {
+        &self.output
+    }
+}
+
+/// Get the compressed public key of the BLS12-381 private key at `private_key`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlsPublicKey {
+    pub private_key: Location,
+}
+
+impl UseSecret<1> for BlsPublicKey {
+    type Output = [u8; BLS_PUBLIC_KEY_LENGTH];
+
+    fn use_secret(self, guards: [Buffer<u8>; 1]) -> Result<Self::Output, FatalProcedureError> {
+        let sk = bls_secret_key(guards[0].borrow())?;
+        Ok(sk.sk_to_pk().to_bytes())
+    }
+
+    fn source(&self) -> [Location; 1] {
+        [self.private_key.clone()]
+    }
+}
+
+/// Sign `msg` with the BLS12-381 private key at `private_key`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlsSign {
+    pub msg: Vec<u8>,
+
+    pub private_key: Location,
+}
+
+impl UseSecret<1> for BlsSign {
+    type Output = [u8; BLS_SIGNATURE_LENGTH];
+
+    fn use_secret(self, guards: [Buffer<u8>; 1]) -> Result<Self::Output, FatalProcedureError> {
+        let sk = bls_secret_key(guards[0].borrow())?;
+        Ok(sk.sign(&self.msg, SIGNATURE_DST, &[]).to_bytes())
+    }
+
+    fn source(&self) -> [Location; 1] {
+        [self.private_key.clone()]
+    }
+}
+
+/// Create a proof of possession of the BLS12-381 private key at `private_key`, i.e. a signature over its public
+/// key, that is required to register the key for aggregated signatures
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlsProofOfPossession {
+    pub private_key: Location,
+}
+
+impl UseSecret<1> for BlsProofOfPossession {
+    type Output = [u8; BLS_SIGNATURE_LENGTH];
+
+    fn use_secret(self, guards: [Buffer<u8>; 1]) -> Result<Self::Output, FatalProcedureError> {
+        let sk = bls_secret_key(guards[0].borrow())?;
+        let pk = sk.sk_to_pk().to_bytes();
+        Ok(sk.sign(&pk, POP_DST, &[]).to_bytes())
+    }
+
+    fn source(&self) -> [Location; 1] {
+        [self.private_key.clone()]
+    }
+}
+
+/// Aggregate BLS12-381 signatures into a single signature. Signatures, that are not valid points of the
+/// subgroup, are rejected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlsAggregate {
+    pub signatures: Vec<Vec<u8>>,
+}
+
+impl Procedure for BlsAggregate {
+    type Output = [u8; BLS_SIGNATURE_LENGTH];
+
+    fn execute<R: Runner>(self, _runner: &R) -> Result<Self::Output, ProcedureError> {
+        let signatures = self
+            .signatures
+            .iter()
+            .map(|signature| Signature::from_bytes(signature))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(bls_error)?;
+        let signatures: Vec<&Signature> = signatures.iter().collect();
+        let aggregate = AggregateSignature::aggregate(&signatures, true).map_err(bls_error)?;
+        Ok(aggregate.to_signature().to_bytes())
+    }
+}
+
+/// Verifies a signature of [`BlsSign`] or an aggregated signature of the same message. Exposed for the tests of
+/// the procedures, verification doesn't need secrets.
+#[cfg(test)]
+pub(crate) fn verify(signature: &[u8], msg: &[u8], public_keys: &[[u8; BLS_PUBLIC_KEY_LENGTH]], pop: bool) -> bool {
+    use blst::min_pk::{AggregatePublicKey, PublicKey};
+
+    let public_keys: Result<Vec<PublicKey>, _> = public_keys.iter().map(|pk| PublicKey::from_bytes(pk)).collect();
+    let (signature, public_keys) = match (Signature::from_bytes(signature), public_keys) {
+        (Ok(signature), Ok(public_keys)) => (signature, public_keys),
+        _ => return false,
+    };
+    let public_keys: Vec<&PublicKey> = public_keys.iter().collect();
+    let aggregate = match AggregatePublicKey::aggregate(&public_keys, true) {
+        Ok(aggregate) => aggregate.to_public_key(),
+        Err(_) => return false,
+    };
+    let dst = if pop { POP_DST } else { SIGNATURE_DST };
+    signature.verify(true, msg, dst, &[], &aggregate, true) == BLST_ERROR::BLST_SUCCESS
+}
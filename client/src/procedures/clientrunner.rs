@@ -1,9 +1,10 @@
 // Copyright 2020-2022 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
+use engine::time::Instant;
 use std::{
     error::Error,
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, RwLock},
 };
 
 use engine::{
@@ -14,19 +15,32 @@ use engine::{
 use crate::{
     derive_vault_id,
     procedures::{
-        FatalProcedureError, Procedure, ProcedureError, ProcedureOutput, Products, Runner, StrongholdProcedure,
+        FatalProcedureError, Procedure, ProcedureError, ProcedureOutput, Products, Runner, SecureRng,
+        StrongholdProcedure,
     },
-    Client, ClientError, ClientVault, KeyStore, Location, Provider, RecordError, Store, VaultError,
+    Client, ClientError, ClientVault, DenyReason, Event, GcPolicy, KeyStore, Location, Provider, RecordError, Store,
+    UsageReservation, VaultError,
 };
 use stronghold_utils::random as rand;
+use zeroize::Zeroizing;
 pub const DEFAULT_RANDOM_HINT_SIZE: usize = 24;
 type ResolvedLocation = (Key<Provider>, VaultId, RecordId);
 
-/// Resolve the given locations into their corresponding vault keys and vault and record ids.
+/// Maps the error of [`Client::read_keystore`] and [`Client::write_keystore`]. A client, that has been locked
+/// after the procedure started, fails it.
+fn keystore_error(e: ClientError) -> VaultError<FatalProcedureError> {
+    match e {
+        ClientError::Locked => VaultError::Procedure(FatalProcedureError::from(e.to_string())),
+        _ => VaultError::LockPoisoned,
+    }
+}
+
+/// Resolve the given locations into their corresponding vault keys and vault and record ids, and reserve
+/// their use in `$reservation`.
 /// We use a macro instead of a function to avoid data races due to locks being
 /// dropped at the end of a function
 macro_rules! resolve_locations {
-    ($client:expr, $locations:expr, $keystore:expr) => {{
+    ($client:expr, $locations:expr, $keystore:expr, $reservation:expr) => {{
         let mut ids: Vec<(Key<Provider>, VaultId, RecordId)> = Vec::with_capacity(N);
 
         for location in ($locations) {
@@ -34,6 +48,14 @@ macro_rules! resolve_locations {
             let key: Key<Provider> = ($keystore)
                 .get_key(vault_id)
                 .ok_or(VaultError::VaultNotFound(vault_id))?;
+            ($reservation).reserve(vault_id, record_id).map_err(|e| {
+                ($client).events.emit(Event::AccessDenied {
+                    client: ($client).id,
+                    kind: None,
+                    reason: DenyReason::UsageLimit,
+                });
+                VaultError::Procedure(FatalProcedureError::from(e))
+            })?;
             ids.push((key, vault_id, record_id));
         }
         let ids: [(Key<Provider>, VaultId, RecordId); N] =
@@ -53,19 +75,28 @@ impl Runner for Client {
         F: FnOnce([Buffer<u8>; N]) -> Result<T, FatalProcedureError>,
     {
         let mut ret = None;
+        let start = Instant::now();
         let execute_procedure = |guard: [Buffer<u8>; N]| {
+            // the guards are ready, once the records have been decrypted
+            self.metrics.vault_read(start.elapsed());
             ret = Some(f(guard)?);
             Ok(())
         };
 
-        let keystore = self.keystore.read().map_err(|_| VaultError::LockPoisoned)?;
+        let keystore = self.read_keystore().map_err(keystore_error)?;
         let db = self.db.read().map_err(|_| VaultError::LockPoisoned)?;
-        let ids: [(Key<Provider>, VaultId, RecordId); N] = resolve_locations!(self, locations, keystore)?;
+        let mut reservation = UsageReservation::new(&self.usage);
+        let ids: [(Key<Provider>, VaultId, RecordId); N] = resolve_locations!(self, locations, keystore, reservation)?;
 
         let res = db.get_guards(ids, execute_procedure);
 
         match res {
-            Ok(()) => Ok(ret.unwrap()),
+            Ok(()) => {
+                // the uses of the records are only counted, once the procedure has succeeded
+                reservation.complete();
+                self.mark_dirty();
+                Ok(ret.unwrap())
+            }
             Err(e) => Err(e),
         }
     }
@@ -81,76 +112,124 @@ impl Runner for Client {
     {
         let (target_vid, target_rid) = target_location.resolve();
 
+        let random_hint = RecordHint::new(rand::variable_bytestring(DEFAULT_RANDOM_HINT_SIZE)).unwrap();
+
+        // Procedures that write into the same vault are executed one after the other. The sources are read
+        // and the procedure is executed with shared locks, so that procedures on different vaults, and all
+        // read-only procedures, run concurrently. Only the write of the result is exclusive.
+        let target_lock = self.vault_lock(target_vid)?;
+        let _target_guard = target_lock.lock().map_err(|_| VaultError::LockPoisoned)?;
+
         let mut ret = None;
+        let start = Instant::now();
         let execute_procedure = |guards: [Buffer<u8>; N]| {
+            self.metrics.vault_read(start.elapsed());
             let Products { output: plain, secret } = f(guards)?;
             ret = Some(plain);
             Ok(secret)
         };
 
-        let random_hint = RecordHint::new(rand::variable_bytestring(DEFAULT_RANDOM_HINT_SIZE)).unwrap();
+        let mut reservation = UsageReservation::new(&self.usage);
+        let data = {
+            let keystore = self.read_keystore().map_err(keystore_error)?;
+            let db = self.db.read().map_err(|_| VaultError::LockPoisoned)?;
+            let sources: [(Key<Provider>, VaultId, RecordId); N] =
+                resolve_locations!(self, source_locations, keystore, reservation)?;
+            Zeroizing::new(db.run_procedure(sources, execute_procedure)?)
+        };
 
-        let mut keystore = self.keystore.write().map_err(|_| VaultError::LockPoisoned)?;
+        let mut keystore = self.write_keystore().map_err(keystore_error)?;
         let mut db = self.db.write().map_err(|_| VaultError::LockPoisoned)?;
+        self.mark_dirty();
 
-        let sources: [(Key<Provider>, VaultId, RecordId); N] = resolve_locations!(self, source_locations, keystore)?;
-
-        if !keystore.vault_exists(target_vid) {
-            let key1 = keystore
+        let target_key = if keystore.vault_exists(target_vid) {
+            keystore
+                .get_key(target_vid)
+                .ok_or(VaultError::VaultNotFound(target_vid))
+        } else {
+            keystore
                 .create_key(target_vid)
-                .map_err(|_| VaultError::Procedure("failed to generate key from keystore".to_string().into()))?;
-            db.init_vault(&key1, target_vid);
-        }
-
-        let target_key = keystore
-            .get_key(target_vid)
-            .ok_or(VaultError::VaultNotFound(target_vid))?;
-
-        let res = db.exec_procedure(
-            sources,
-            &target_key,
-            target_vid,
-            target_rid,
-            random_hint,
-            execute_procedure,
-        );
+                .inspect(|key| db.init_vault(key, target_vid))
+                .map_err(|_| VaultError::Procedure("failed to generate key from keystore".to_string().into()))
+        };
+        let res = target_key.and_then(|key| {
+            let start = Instant::now();
+            let res = db.write(&key, target_vid, target_rid, &data, random_hint);
+            self.metrics.vault_write(start.elapsed());
+            res.map_err(|e| e.with_procedure_error())
+        });
+        drop((keystore, db));
 
         match res {
-            Ok(()) => Ok(ret.unwrap()),
+            Ok(()) => {
+                reservation.complete();
+                self.events.emit(Event::RecordWritten {
+                    client: self.id,
+                    location: target_location.clone(),
+                });
+                Ok(ret.unwrap())
+            }
             Err(e) => Err(e),
         }
     }
 
-    fn write_to_vault(&self, location: &Location, value: Vec<u8>) -> Result<(), RecordError> {
+    fn write_to_vault(&self, location: &Location, value: Vec<u8>) -> Result<(), VaultError<FatalProcedureError>> {
+        let value = Zeroizing::new(value);
         let (vault_id, record_id) = location.resolve();
 
-        let mut keystore = self.keystore.write().map_err(|_| RecordError::LockPoisoned)?;
-        let mut db = self.db.write().map_err(|_| RecordError::LockPoisoned)?;
+        let mut keystore = self.write_keystore().map_err(keystore_error)?;
+        let mut db = self.db.write().map_err(|_| VaultError::LockPoisoned)?;
+        self.mark_dirty();
 
         if !keystore.vault_exists(vault_id) {
             // The error type mapped to the possible key creation error is semantically incorrect
-            let key = keystore.create_key(vault_id).map_err(|_| RecordError::InvalidKey)?;
+            let key = keystore
+                .create_key(vault_id)
+                .map_err(|_| VaultError::Record(RecordError::InvalidKey))?;
             db.init_vault(&key, vault_id);
         }
         let random_hint = RecordHint::new(rand::variable_bytestring(DEFAULT_RANDOM_HINT_SIZE)).unwrap();
         let key = keystore.take_key(vault_id).unwrap();
+        let start = Instant::now();
         let res = db.write(&key, vault_id, record_id, &value, random_hint);
+        self.metrics.vault_write(start.elapsed());
 
         // this should return an error
         keystore
             .get_or_insert_key(vault_id, key)
             .expect("Inserting key into vault failed");
-        res
+        res.map_err(|e| e.with_procedure_error())?;
+        drop((keystore, db));
+
+        self.events.emit(Event::RecordWritten {
+            client: self.id,
+            location: location.clone(),
+        });
+        Ok(())
     }
 
     fn revoke_data(&self, location: &Location) -> Result<(), RecordError> {
         let (vault_id, record_id) = location.resolve();
 
-        let mut keystore = self.keystore.write().map_err(|_| RecordError::LockPoisoned)?;
+        let policy = *self.gc_policy.read().map_err(|_| RecordError::LockPoisoned)?;
+        let retention = *self.trash_retention.read().map_err(|_| RecordError::LockPoisoned)?;
+
+        let mut keystore = self.write_keystore().map_err(|e| match e {
+            ClientError::Locked => RecordError::InvalidKey,
+            _ => RecordError::LockPoisoned,
+        })?;
         let mut db = self.db.write().map_err(|_| RecordError::LockPoisoned)?;
+        self.mark_dirty();
 
         if let Some(key) = keystore.take_key(vault_id) {
-            let res = db.revoke_record(&key, vault_id, record_id);
+            let res = db.revoke_record(&key, vault_id, record_id).and_then(|()| match policy {
+                GcPolicy::AfterNRevocations(n)
+                    if db.vault_gc_stats(vault_id).is_some_and(|stats| stats.revoked >= n) =>
+                {
+                    db.garbage_collect_vault_expired(&key, vault_id, retention).map(|_| ())
+                }
+                _ => Ok(()),
+            });
 
             // this should return an error
             keystore
@@ -162,19 +241,30 @@ impl Runner for Client {
     }
 
     fn garbage_collect(&self, vault_id: VaultId) -> Result<bool, VaultError<FatalProcedureError>> {
-        let mut keystore = self.keystore.write().map_err(|_| VaultError::LockPoisoned)?;
+        let retention = *self.trash_retention.read().map_err(|_| VaultError::LockPoisoned)?;
+        let mut keystore = self.write_keystore().map_err(keystore_error)?;
         let mut db = self.db.write().map_err(|_| VaultError::LockPoisoned)?;
 
         let key = match keystore.take_key(vault_id) {
             Some(key) => key,
             None => return Ok(false),
         };
-        db.garbage_collect_vault(&key, vault_id);
+        self.mark_dirty();
+        let res = db.garbage_collect_vault_expired(&key, vault_id, retention);
         keystore
             .get_or_insert_key(vault_id, key)
             .expect("Inserting key into vault failed");
+        res?;
         Ok(true)
     }
+
+    fn rng(&self) -> Result<Arc<dyn SecureRng>, FatalProcedureError> {
+        let rng = self
+            .rng
+            .read()
+            .map_err(|_| FatalProcedureError::from("Lock is poisoned".to_string()))?;
+        Ok(rng.clone())
+    }
 }
 
 impl Client {
@@ -185,10 +275,10 @@ impl Client {
     {
         let (vault_id, record_id) = location.resolve();
 
-        let mut keystore = self.keystore.write().map_err(|_| VaultError::LockPoisoned)?;
+        let keystore = self.read_keystore().map_err(keystore_error)?;
         let db = self.db.read().map_err(|_| VaultError::LockPoisoned)?;
 
-        let key = keystore.take_key(vault_id).ok_or(VaultError::VaultNotFound(vault_id))?;
+        let key = keystore.get_key(vault_id).ok_or(VaultError::VaultNotFound(vault_id))?;
 
         let mut ret = None;
         let execute_procedure = |guard: Buffer<u8>| {
@@ -198,14 +288,17 @@ impl Client {
 
         let res = db.get_guard(&key, vault_id, record_id, execute_procedure);
 
-        // this should return an error
-        keystore
-            .get_or_insert_key(vault_id, key)
-            .expect("Inserting key into vault failed");
-
         match res {
             Ok(()) => Ok(ret.unwrap()),
             Err(e) => Err(e),
         }
     }
+
+    /// Returns the lock, that serializes the procedures writing into the vault `vault_id`. The locks, that are
+    /// not held by any procedure anymore, are removed, so that only vaults with procedures in flight keep one.
+    pub(crate) fn vault_lock(&self, vault_id: VaultId) -> Result<Arc<Mutex<()>>, VaultError<FatalProcedureError>> {
+        let mut locks = self.vault_locks.lock().map_err(|_| VaultError::LockPoisoned)?;
+        locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+        Ok(locks.entry(vault_id).or_default().clone())
+    }
 }
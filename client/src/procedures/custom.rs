@@ -0,0 +1,177 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::Arc;
+
+use engine::runtime::memories::buffer::Buffer;
+
+use super::types::{FatalProcedureError, Procedure, ProcedureError, Products, Runner, SecureRng};
+use crate::Location;
+
+/// The maximum number of secrets, that are passed into a [`DynProcedure`]
+pub const MAX_DYN_SOURCES: usize = 4;
+
+/// A procedure, that is implemented outside of this crate, but runs like the built-in procedures: the secrets are
+/// only unlocked while [`DynProcedure::execute`] runs, and a new secret is written straight into the vault.
+///
+/// Execute it with [`crate::Client::execute_procedure`] after wrapping it into a [`CustomProcedure`]. Custom
+/// procedures take part in approvals, the history and events under their [`DynProcedure::name`], but they can't
+/// be serialized.
+///
+/// # Example
+/// ```
+/// use iota_stronghold::{
+///     procedures::{CustomProcedure, DynProcedure, FatalProcedureError, Products, SecureRng},
+///     Location, Stronghold,
+/// };
+///
+/// // xors two secrets into a new one
+/// struct Xor {
+///     a: Location,
+///     b: Location,
+///     output: Location,
+/// }
+///
+/// impl DynProcedure for Xor {
+///     fn name(&self) -> &'static str {
+///         "Xor"
+///     }
+///
+///     fn source(&self) -> Vec<Location> {
+///         vec![self.a.clone(), self.b.clone()]
+///     }
+///
+///     fn target(&self) -> Option<Location> {
+///         Some(self.output.clone())
+///     }
+///
+///     fn execute(
+///         &self,
+///         secrets: &[&[u8]],
+///         _: &dyn SecureRng,
+///     ) -> Result<Products<Vec<u8>>, FatalProcedureError> {
+///         let secret = secrets[0]
+///             .iter()
+///             .zip(secrets[1])
+///             .map(|(a, b)| a ^ b)
+///             .collect();
+///         Ok(Products {
+///             secret,
+///             output: Vec::new(),
+///         })
+///     }
+/// }
+///
+/// let stronghold = Stronghold::default();
+/// let client = stronghold.create_client(b"client").unwrap();
+/// let a = Location::const_generic(b"v".to_vec(), b"a".to_vec());
+/// let b = Location::const_generic(b"v".to_vec(), b"b".to_vec());
+/// client
+///     .vault(b"v")
+///     .write_secret(a.clone(), vec![1, 2])
+///     .unwrap();
+/// client
+///     .vault(b"v")
+///     .write_secret(b.clone(), vec![3, 4])
+///     .unwrap();
+///
+/// let output = Location::const_generic(b"v".to_vec(), b"xor".to_vec());
+/// client
+///     .execute_procedure(CustomProcedure::new(Xor {
+///         a,
+///         b,
+///         output: output.clone(),
+///     }))
+///     .unwrap();
+/// assert!(client.record_exists(&output).unwrap());
+/// ```
+pub trait DynProcedure: Send + Sync {
+    /// The name of the procedure, that is reported e.g. in [`crate::Event::ProcedureExecuted`]
+    fn name(&self) -> &'static str;
+
+    /// The records, whose secrets are passed into [`DynProcedure::execute`] in the same order. At most
+    /// [`MAX_DYN_SOURCES`] records are supported.
+    fn source(&self) -> Vec<Location>;
+
+    /// The location, where the secret of the [`Products`] is written to. Procedures without target must return
+    /// an empty secret.
+    fn target(&self) -> Option<Location> {
+        None
+    }
+
+    /// Computes the new secret and the non-secret output from the `secrets` of [`DynProcedure::source`]. All
+    /// randomness must be drawn from `rng`, so that the entropy source of the client is respected.
+    fn execute(&self, secrets: &[&[u8]], rng: &dyn SecureRng) -> Result<Products<Vec<u8>>, FatalProcedureError>;
+}
+
+/// A [`DynProcedure`], that can be executed like the procedures of this crate
+#[derive(Clone)]
+pub struct CustomProcedure(pub(crate) Arc<dyn DynProcedure>);
+
+impl CustomProcedure {
+    pub fn new<P: DynProcedure + 'static>(procedure: P) -> Self {
+        CustomProcedure(Arc::new(procedure))
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    pub(crate) fn source(&self) -> Vec<Location> {
+        self.0.source()
+    }
+
+    pub(crate) fn target(&self) -> Option<Location> {
+        self.0.target()
+    }
+}
+
+impl Procedure for CustomProcedure {
+    type Output = Vec<u8>;
+
+    fn execute<R: Runner>(self, runner: &R) -> Result<Self::Output, ProcedureError> {
+        let rng = runner.rng()?;
+        let source = self.0.source();
+        let target = self.0.target();
+
+        let f = |guards: &[Buffer<u8>]| {
+            let borrowed: Vec<_> = guards.iter().map(|guard| guard.borrow()).collect();
+            let secrets: Vec<&[u8]> = borrowed.iter().map(|secret| &**secret).collect();
+            let products = self.0.execute(&secrets, &*rng)?;
+            if target.is_none() && !products.secret.is_empty() {
+                return Err(FatalProcedureError::from(format!(
+                    "Procedure {} returned a secret without target",
+                    self.0.name()
+                )));
+            }
+            Ok(products)
+        };
+
+        // the runner takes the sources as arrays, so each supported length is dispatched separately
+        macro_rules! dispatch {
+            ($($n:literal),*) => {
+                match source.len() {
+                    0 => {
+                        let Products { secret, output } = f(&[])?;
+                        if let Some(target) = &target {
+                            runner.write_to_vault(target, secret)?;
+                        }
+                        Ok(output)
+                    }
+                    $($n => {
+                        let sources: [Location; $n] = source.clone().try_into().unwrap();
+                        let output = match &target {
+                            Some(target) => runner.exec_proc(sources, target, |guards| f(&guards))?,
+                            None => runner.get_guards(sources, |guards| f(&guards).map(|products| products.output))?,
+                        };
+                        Ok(output)
+                    })*
+                    n => Err(ProcedureError::Procedure(
+                        format!("{} sources exceed the limit of {}", n, MAX_DYN_SOURCES).into(),
+                    )),
+                }
+            };
+        }
+        dispatch!(1, 2, 3, 4)
+    }
+}
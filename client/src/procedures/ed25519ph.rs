@@ -0,0 +1,98 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Ed25519ph of RFC 8032, which signs the SHA-512 hash of a message instead of the message itself. The keys are
+//! the same as for Ed25519, but the signatures are domain separated from Ed25519 signatures by the `dom2` prefix.
+
+use super::FatalProcedureError;
+use crypto::signatures::ed25519::{SECRET_KEY_LENGTH, SIGNATURE_LENGTH};
+use ed25519_dalek::{ExpandedSecretKey, PublicKey, SecretKey};
+use sha2::digest::{consts::U64, generic_array::GenericArray, FixedOutput, Reset, Update};
+
+pub(crate) const PREHASH_LENGTH: usize = 64;
+pub(crate) const MAX_CONTEXT_LENGTH: usize = 255;
+
+/// A digest, that finalizes to a hash of the message, which has been computed before. Ed25519ph of
+/// ed25519-dalek only accepts the prehash as a digest, that is yet to be finalized.
+#[derive(Clone, Default)]
+struct Prehashed(GenericArray<u8, U64>);
+
+impl Update for Prehashed {
+    fn update(&mut self, _data: impl AsRef<[u8]>) {}
+}
+
+impl FixedOutput for Prehashed {
+    type OutputSize = U64;
+
+    fn finalize_into(self, out: &mut GenericArray<u8, Self::OutputSize>) {
+        *out = self.0;
+    }
+
+    fn finalize_into_reset(&mut self, out: &mut GenericArray<u8, Self::OutputSize>) {
+        *out = self.0;
+    }
+}
+
+impl Reset for Prehashed {
+    fn reset(&mut self) {}
+}
+
+/// Signs `prehash`, the SHA-512 hash of the message, with the Ed25519 secret key `secret_key`.
+pub(crate) fn sign(
+    secret_key: &[u8; SECRET_KEY_LENGTH],
+    prehash: &[u8],
+    context: &[u8],
+) -> Result<[u8; SIGNATURE_LENGTH], FatalProcedureError> {
+    if prehash.len() != PREHASH_LENGTH {
+        return Err(format!("Invalid length {} of the SHA-512 prehash", prehash.len()).into());
+    }
+    if context.len() > MAX_CONTEXT_LENGTH {
+        return Err(format!(
+            "Context of {} bytes exceeds {} bytes",
+            context.len(),
+            MAX_CONTEXT_LENGTH
+        )
+        .into());
+    }
+
+    // the secret keys of ed25519-dalek are zeroized on drop
+    let secret_key = SecretKey::from_bytes(secret_key).map_err(|e| FatalProcedureError::from(e.to_string()))?;
+    let expanded = ExpandedSecretKey::from(&secret_key);
+    let public_key = PublicKey::from(&expanded);
+    let signature = expanded
+        .sign_prehashed(
+            Prehashed(GenericArray::clone_from_slice(prehash)),
+            &public_key,
+            Some(context),
+        )
+        .map_err(|e| FatalProcedureError::from(e.to_string()))?;
+    Ok(signature.to_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crypto::hashes::{sha::Sha512, Digest};
+
+    // test vector of Ed25519ph in section 7.3 of RFC 8032
+    #[test]
+    fn test_rfc8032_vector() {
+        let mut secret_key = [0u8; SECRET_KEY_LENGTH];
+        hex::decode_to_slice(
+            "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+            &mut secret_key,
+        )
+        .unwrap();
+        let prehash = Sha512::digest(b"abc");
+
+        let signature = sign(&secret_key, &prehash, &[]).unwrap();
+        assert_eq!(
+            hex::encode(signature),
+            "98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae41\
+             31f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406"
+        );
+
+        assert!(sign(&secret_key, &prehash[..32], &[]).is_err());
+        assert!(sign(&secret_key, &prehash, &[0; 256]).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Experimental post-quantum signatures with CRYSTALS-Dilithium, the basis of ML-DSA of FIPS 204.
+//!
+//! The signatures are not compatible with the final ML-DSA standard, they are only meant for testing hybrid
+//! signing flows. A Dilithium private key doesn't allow to compute the public key cheaply, so the record holds
+//! both:
+//!
+//! ```text
+//! | secret key | public key |
+//! ```
+
+use super::{
+    types::{GenerateSecret, Products, UseSecret},
+    FatalProcedureError,
+};
+use crate::Location;
+use engine::runtime::memories::buffer::Buffer;
+use pqcrypto_dilithium::{dilithium2, dilithium3, dilithium5};
+use pqcrypto_traits::sign::{DetachedSignature, PublicKey, SecretKey};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+/// The parameter set of Dilithium, named after the NIST security level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MlDsaLevel {
+    Dilithium2,
+    Dilithium3,
+    Dilithium5,
+}
+
+macro_rules! dispatch {
+    ($level:expr, $m:ident => $body:expr) => {
+        match $level {
+            MlDsaLevel::Dilithium2 => {
+                use dilithium2 as $m;
+                $body
+            }
+            MlDsaLevel::Dilithium3 => {
+                use dilithium3 as $m;
+                $body
+            }
+            MlDsaLevel::Dilithium5 => {
+                use dilithium5 as $m;
+                $body
+            }
+        }
+    };
+}
+
+impl MlDsaLevel {
+    fn secret_key_len(&self) -> usize {
+        dispatch!(self, m => m::secret_key_bytes())
+    }
+
+    fn public_key_len(&self) -> usize {
+        dispatch!(self, m => m::public_key_bytes())
+    }
+
+    /// Splits a record of [`MlDsaGenerateKey`] into the secret key and the public key
+    fn split<'a>(&self, keypair: &'a [u8]) -> Result<(&'a [u8], &'a [u8]), FatalProcedureError> {
+        if keypair.len() != self.secret_key_len() + self.public_key_len() {
+            return Err(format!("Invalid length {} of the {:?} key pair", keypair.len(), self).into());
+        }
+        Ok(keypair.split_at(self.secret_key_len()))
+    }
+}
+
+/// Overwrites the secret key `sk` with zeros. The key types of pqcrypto are plain byte arrays, that are
+/// neither zeroized on drop nor expose their bytes mutably.
+fn wipe<K: SecretKey>(sk: &mut K) {
+    debug_assert_eq!(std::mem::size_of::<K>(), sk.as_bytes().len());
+    // SAFETY: the secret keys of pqcrypto only consist of an array of `size_of::<K>()` bytes, for which all
+    // zeros is a valid value, and `sk` is borrowed exclusively.
+    let bytes = unsafe { std::slice::from_raw_parts_mut(sk as *mut K as *mut u8, std::mem::size_of::<K>()) };
+    bytes.zeroize();
+}
+
+/// Generate a Dilithium key pair, and store it in the `output` location.
+///
+/// The key is generated with the randomness of the operating system, a custom entropy source of the client is
+/// not used.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MlDsaGenerateKey {
+    pub level: MlDsaLevel,
+    pub output: Location,
+}
+
+impl GenerateSecret for MlDsaGenerateKey {
+    type Output = ();
+
+    fn generate(self) -> Result<Products<Self::Output>, FatalProcedureError> {
+        let secret = dispatch!(self.level, m => {
+            let (pk, mut sk) = m::keypair();
+            let keypair = [sk.as_bytes(), pk.as_bytes()].concat();
+            wipe(&mut sk);
+            keypair
+        });
+        Ok(Products { secret, output: () })
+    }
+
+    fn target(&self) -> &Location {
+        &self.output
+    }
+}
+
+/// Get the public key of the Dilithium key pair at `private_key`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MlDsaPublicKey {
+    pub level: MlDsaLevel,
+    pub private_key: Location,
+}
+
+impl UseSecret<1> for MlDsaPublicKey {
+    type Output = Vec<u8>;
+
+    fn use_secret(self, guards: [Buffer<u8>; 1]) -> Result<Self::Output, FatalProcedureError> {
+        let keypair = guards[0].borrow();
+        let (_, pk) = self.level.split(&keypair)?;
+        Ok(pk.to_vec())
+    }
+
+    fn source(&self) -> [Location; 1] {
+        [self.private_key.clone()]
+    }
+}
+
+/// Sign `msg` with the Dilithium key pair at `private_key`, and return the detached signature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MlDsaSign {
+    pub level: MlDsaLevel,
+    pub msg: Vec<u8>,
+    pub private_key: Location,
+}
+
+impl UseSecret<1> for MlDsaSign {
+    type Output = Vec<u8>;
+
+    fn use_secret(self, guards: [Buffer<u8>; 1]) -> Result<Self::Output, FatalProcedureError> {
+        let keypair = guards[0].borrow();
+        let (sk, _) = self.level.split(&keypair)?;
+        let signature = dispatch!(self.level, m => {
+            let mut sk = m::SecretKey::from_bytes(sk).map_err(|e| FatalProcedureError::from(format!("{:?}", e)))?;
+            let signature = m::detached_sign(&self.msg, &sk).as_bytes().to_vec();
+            wipe(&mut sk);
+            signature
+        });
+        Ok(signature)
+    }
+
+    fn source(&self) -> [Location; 1] {
+        [self.private_key.clone()]
+    }
+}
+
+/// Verifies a signature of [`MlDsaSign`]. Exposed for the tests of the procedures, verification doesn't need
+/// secrets.
+#[cfg(test)]
+pub(crate) fn verify(level: MlDsaLevel, signature: &[u8], msg: &[u8], public_key: &[u8]) -> bool {
+    dispatch!(level, m => {
+        match (m::DetachedSignature::from_bytes(signature), m::PublicKey::from_bytes(public_key)) {
+            (Ok(signature), Ok(public_key)) => m::verify_detached_signature(&signature, msg, &public_key).is_ok(),
+            _ => false,
+        }
+    })
+}
@@ -0,0 +1,299 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Encoding of secrets into shards, that can be written down or printed as QR codes.
+//!
+//! The secret is split into chunks of [`CHUNK_SIZE`] bytes. Each chunk is prefixed with a header and
+//! padded to a shard of [`SHARD_SIZE`] bytes:
+//!
+//! ```text
+//! | set: u8 | index: u8 | count: u8 | length: u8 | chunk | padding |
+//! ```
+//!
+//! `set` is the first byte of the SHA-256 digest of the secret. It ties the shards of one backup together,
+//! and is checked after the secret has been reassembled. Shards can be imported in any order.
+//!
+//! A shard is either encoded as a BIP39 mnemonic of 24 English words, which includes the BIP39 checksum,
+//! or as an unpadded RFC 4648 base32 string of the shard followed by the first [`CHECKSUM_SIZE`] bytes of
+//! its SHA-256 digest. Base32 only uses characters of the QR alphanumeric mode.
+
+use crypto::{
+    hashes::{sha::Sha256, Digest},
+    keys::bip39,
+};
+use serde::{Deserialize, Serialize};
+use stronghold_utils::GuardDebug;
+use zeroize::Zeroize;
+
+use super::{FatalProcedureError, ProcedureOutput};
+
+const SHARD_SIZE: usize = 32;
+
+const HEADER_SIZE: usize = 4;
+
+const CHUNK_SIZE: usize = SHARD_SIZE - HEADER_SIZE;
+
+const CHECKSUM_SIZE: usize = 4;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Human-transcribable representation of a paper backup shard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaperBackupEncoding {
+    /// 24 words of the English BIP39 wordlist
+    Mnemonic,
+
+    /// Uppercase base32, that can be encoded in the alphanumeric mode of QR codes
+    Base32,
+}
+
+/// The encoded shards of a paper backup. All shards are needed to restore the secret.
+#[derive(Clone, GuardDebug, Serialize, Deserialize)]
+pub struct PaperBackupShards(pub Vec<String>);
+
+impl Drop for PaperBackupShards {
+    fn drop(&mut self) {
+        self.0.iter_mut().for_each(|shard| shard.zeroize());
+    }
+}
+
+impl From<PaperBackupShards> for ProcedureOutput {
+    fn from(shards: PaperBackupShards) -> Self {
+        bincode::serialize(&shards.0)
+            .expect("Serializing strings can't fail")
+            .into()
+    }
+}
+
+impl TryFrom<ProcedureOutput> for PaperBackupShards {
+    type Error = bincode::Error;
+
+    fn try_from(value: ProcedureOutput) -> Result<Self, Self::Error> {
+        let mut bytes: Vec<u8> = value.into();
+        let shards = bincode::deserialize(&bytes);
+        bytes.zeroize();
+        shards.map(PaperBackupShards)
+    }
+}
+
+/// Splits `secret` into shards and encodes them
+pub(crate) fn encode(secret: &[u8], encoding: PaperBackupEncoding) -> Result<PaperBackupShards, FatalProcedureError> {
+    if secret.is_empty() {
+        return Err(FatalProcedureError::from("Secret is empty".to_string()));
+    }
+    let count = secret.len().div_ceil(CHUNK_SIZE);
+    if count > u8::MAX as usize {
+        return Err(FatalProcedureError::from(
+            "Secret is too large for a paper backup".to_string(),
+        ));
+    }
+    let set = Sha256::digest(secret)[0];
+
+    let mut shards = Vec::with_capacity(count);
+    for (index, chunk) in secret.chunks(CHUNK_SIZE).enumerate() {
+        let mut shard = [0u8; SHARD_SIZE];
+        shard[..HEADER_SIZE].copy_from_slice(&[set, index as u8, count as u8, chunk.len() as u8]);
+        shard[HEADER_SIZE..HEADER_SIZE + chunk.len()].copy_from_slice(chunk);
+
+        let encoded = match encoding {
+            PaperBackupEncoding::Mnemonic => bip39::wordlist::encode(&shard, &bip39::wordlist::ENGLISH)
+                .map_err(|e| FatalProcedureError::from(format!("{:?}", e))),
+            PaperBackupEncoding::Base32 => {
+                let mut checked = shard.to_vec();
+                checked.extend_from_slice(&Sha256::digest(shard)[..CHECKSUM_SIZE]);
+                let encoded = base32_encode(&checked);
+                checked.zeroize();
+                Ok(encoded)
+            }
+        };
+        shard.zeroize();
+        shards.push(encoded?);
+    }
+    Ok(PaperBackupShards(shards))
+}
+
+/// Decodes `shards` and reassembles the secret
+pub(crate) fn decode(shards: &[String], encoding: PaperBackupEncoding) -> Result<Vec<u8>, FatalProcedureError> {
+    let mut chunks: Vec<Option<Vec<u8>>> = Vec::new();
+    let mut header: Option<(u8, u8)> = None;
+
+    for encoded in shards {
+        let mut shard = match encoding {
+            PaperBackupEncoding::Mnemonic => {
+                let mut decoded = bip39::wordlist::decode(encoded.trim(), &bip39::wordlist::ENGLISH)
+                    .map_err(|e| FatalProcedureError::from(format!("Invalid mnemonic shard: {:?}", e)))?;
+                let shard = decoded.to_vec();
+                decoded.zeroize();
+                shard
+            }
+            PaperBackupEncoding::Base32 => {
+                let mut checked = base32_decode(encoded.trim())?;
+                if checked.len() != SHARD_SIZE + CHECKSUM_SIZE
+                    || Sha256::digest(&checked[..SHARD_SIZE])[..CHECKSUM_SIZE] != checked[SHARD_SIZE..]
+                {
+                    checked.zeroize();
+                    return Err(FatalProcedureError::from(
+                        "Invalid checksum of base32 shard".to_string(),
+                    ));
+                }
+                checked.truncate(SHARD_SIZE);
+                checked
+            }
+        };
+        let parsed = parse_shard(&shard, &mut header, &mut chunks);
+        shard.zeroize();
+        parsed?;
+    }
+
+    let (set, _) = header.ok_or_else(|| FatalProcedureError::from("No shards".to_string()))?;
+    let mut secret = Vec::new();
+    for (index, chunk) in chunks.iter_mut().enumerate() {
+        match chunk {
+            Some(chunk) => {
+                secret.extend_from_slice(chunk);
+                chunk.zeroize();
+            }
+            None => {
+                secret.zeroize();
+                return Err(FatalProcedureError::from(format!("Missing shard {}", index + 1)));
+            }
+        }
+    }
+    if Sha256::digest(&secret)[0] != set {
+        secret.zeroize();
+        return Err(FatalProcedureError::from(
+            "Shards don't belong to the same backup".to_string(),
+        ));
+    }
+    Ok(secret)
+}
+
+/// Checks the header of `shard` against the previous shards, and adds its chunk
+fn parse_shard(
+    shard: &[u8],
+    header: &mut Option<(u8, u8)>,
+    chunks: &mut Vec<Option<Vec<u8>>>,
+) -> Result<(), FatalProcedureError> {
+    if shard.len() != SHARD_SIZE {
+        return Err(FatalProcedureError::from("Invalid shard size".to_string()));
+    }
+    let (set, index, count, length) = (shard[0], shard[1] as usize, shard[2], shard[3] as usize);
+    if count == 0 || index >= count as usize || length > CHUNK_SIZE {
+        return Err(FatalProcedureError::from("Invalid shard header".to_string()));
+    }
+    match header {
+        Some(expected) if *expected != (set, count) => {
+            return Err(FatalProcedureError::from(
+                "Shards don't belong to the same backup".to_string(),
+            ))
+        }
+        Some(_) => {}
+        None => {
+            *header = Some((set, count));
+            chunks.resize(count as usize, None);
+        }
+    }
+    if chunks[index].is_some() {
+        return Err(FatalProcedureError::from(format!("Duplicate shard {}", index + 1)));
+    }
+    chunks[index] = Some(shard[HEADER_SIZE..HEADER_SIZE + length].to_vec());
+    Ok(())
+}
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() * 8).div_ceil(5));
+    let (mut buffer, mut bits) = (0u16, 0);
+    for byte in data {
+        buffer = (buffer << 8) | *byte as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+fn base32_decode(encoded: &str) -> Result<Vec<u8>, FatalProcedureError> {
+    let mut decoded = Vec::with_capacity(encoded.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for c in encoded.bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a == c.to_ascii_uppercase())
+            .ok_or_else(|| FatalProcedureError::from(format!("Invalid base32 character {:?}", c as char)))?;
+        buffer = (buffer << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use stronghold_utils::random;
+
+    #[test]
+    fn test_base32() {
+        // test vectors of RFC 4648
+        for (data, encoded) in [
+            ("", ""),
+            ("f", "MY"),
+            ("fo", "MZXQ"),
+            ("foo", "MZXW6"),
+            ("foob", "MZXW6YQ"),
+            ("fooba", "MZXW6YTB"),
+            ("foobar", "MZXW6YTBOI"),
+        ] {
+            assert_eq!(base32_encode(data.as_bytes()), encoded);
+            assert_eq!(base32_decode(encoded).unwrap(), data.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        for encoding in [PaperBackupEncoding::Mnemonic, PaperBackupEncoding::Base32] {
+            for size in [1, CHUNK_SIZE, CHUNK_SIZE + 1, 64, 100] {
+                let secret = random::fixed_bytestring(size);
+                let mut shards = encode(&secret, encoding).unwrap().0.clone();
+                assert_eq!(shards.len(), size.div_ceil(CHUNK_SIZE));
+
+                shards.reverse();
+                assert_eq!(decode(&shards, encoding).unwrap(), secret);
+
+                if shards.len() > 1 {
+                    assert!(decode(&shards[1..], encoding).is_err());
+                    let duplicate = vec![shards[0].clone(), shards[0].clone()];
+                    assert!(decode(&duplicate, encoding).is_err());
+                }
+            }
+        }
+
+        // shards of different backups can not be mixed
+        let a = encode(&random::fixed_bytestring(64), PaperBackupEncoding::Base32)
+            .unwrap()
+            .0
+            .clone();
+        let b = encode(&random::fixed_bytestring(64), PaperBackupEncoding::Base32)
+            .unwrap()
+            .0
+            .clone();
+        assert!(decode(&[a[0].clone(), b[1].clone(), a[2].clone()], PaperBackupEncoding::Base32).is_err());
+
+        // typos are detected
+        let mut shards = encode(&random::fixed_bytestring(16), PaperBackupEncoding::Base32)
+            .unwrap()
+            .0
+            .clone();
+        let typo = if shards[0].starts_with('A') { "B" } else { "A" };
+        shards[0].replace_range(..1, typo);
+        assert!(decode(&shards, PaperBackupEncoding::Base32).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    procedures::{FatalProcedureError, ProcedureError, StrongholdProcedure},
+    Location,
+};
+use stronghold_utils::random as rand;
+
+const SCRATCH_VAULT_PREFIX: &[u8] = b"stronghold.pipeline.";
+
+/// A sequence of procedures, that is executed with [`crate::Client::execute_pipeline`].
+///
+/// Each step can bind the output of the previous step as its input with [`Pipeline::then_with`]. Outputs, that
+/// are only needed by following steps, can be written to [`Pipeline::intermediate`] locations. These are in a
+/// vault, that is unique to the pipeline, and are deleted once the pipeline has finished, whether it succeeded or
+/// not. If a step fails, the outputs of all previous steps are revoked, like with
+/// [`crate::Client::execute_procedure_chained`].
+///
+/// # Example
+/// ```
+/// use iota_stronghold::{
+///     procedures::{
+///         Chain, Curve, KeyType, Pipeline, PublicKey, Slip10Derive, Slip10DeriveInput,
+///         Slip10Generate,
+///     },
+///     Location, Stronghold,
+/// };
+///
+/// let stronghold = Stronghold::default();
+/// let client = stronghold.create_client(b"client").unwrap();
+///
+/// let mut pipeline = Pipeline::new();
+/// let seed = pipeline.intermediate("seed");
+/// let key = Location::const_generic(b"keys".to_vec(), b"account-0".to_vec());
+/// let pipeline = pipeline
+///     .then(Slip10Generate {
+///         size_bytes: None,
+///         output: seed,
+///     })
+///     .then_with(|seed| Slip10Derive {
+///         curve: Curve::Ed25519,
+///         chain: Chain::from_u32_hardened(vec![44, 4218, 0]),
+///         input: Slip10DeriveInput::Seed(seed.clone()),
+///         output: key.clone(),
+///     })
+///     .then_with(|key| PublicKey {
+///         ty: KeyType::Ed25519,
+///         private_key: key.clone(),
+///     });
+///
+/// let outputs = client.execute_pipeline(pipeline).unwrap();
+/// assert_eq!(outputs.len(), 3);
+/// ```
+pub struct Pipeline {
+    steps: Vec<StrongholdProcedure>,
+
+    // Records, that are deleted after the execution
+    intermediates: Vec<Location>,
+
+    // The path of the vault, that holds the intermediate records. The whole vault is deleted after the execution
+    scratch_vault: Vec<u8>,
+
+    // The first step, that could not be bound to its predecessor
+    error: Option<String>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        let mut scratch_vault = SCRATCH_VAULT_PREFIX.to_vec();
+        scratch_vault.extend(rand::fixed_bytestring(16));
+        Self {
+            steps: Vec::new(),
+            intermediates: Vec::new(),
+            scratch_vault,
+            error: None,
+        }
+    }
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a location for an output, that is only needed within the pipeline. The record at the location is
+    /// deleted, once the pipeline has finished.
+    pub fn intermediate<R: Into<Vec<u8>>>(&mut self, record_path: R) -> Location {
+        let location = Location::generic(self.scratch_vault.clone(), record_path);
+        self.intermediates.push(location.clone());
+        location
+    }
+
+    /// Appends `procedure` as the next step.
+    pub fn then<P: Into<StrongholdProcedure>>(mut self, procedure: P) -> Self {
+        self.steps.push(procedure.into());
+        self
+    }
+
+    /// Appends the procedure, that `f` creates from the output location of the previous step. Executing the
+    /// pipeline fails, if the previous step doesn't write a record.
+    pub fn then_with<P, F>(mut self, f: F) -> Self
+    where
+        P: Into<StrongholdProcedure>,
+        F: FnOnce(&Location) -> P,
+    {
+        // a step without output is only reported on execution, so that the builder can be chained
+        let previous = self.steps.last().and_then(StrongholdProcedure::output);
+        match previous {
+            Some(location) => self.steps.push(f(&location).into()),
+            None => {
+                let step = self.steps.len() + 1;
+                self.error
+                    .get_or_insert_with(|| format!("Step {} can not be bound, the previous step has no output", step));
+            }
+        }
+        self
+    }
+
+    /// Returns the number of steps
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Returns the steps, and the path of the vault with the intermediate records, if there are any
+    pub(crate) fn into_parts(self) -> Result<(Vec<StrongholdProcedure>, Option<Vec<u8>>), ProcedureError> {
+        if let Some(e) = self.error {
+            return Err(ProcedureError::Procedure(FatalProcedureError::from(e)));
+        }
+        let scratch_vault = (!self.intermediates.is_empty()).then_some(self.scratch_vault);
+        Ok((self.steps, scratch_vault))
+    }
+}
@@ -3,7 +3,20 @@
 
 use std::str::FromStr;
 
-use super::types::*;
+#[cfg(feature = "bls")]
+use super::bls::{BlsAggregate, BlsGenerateKey, BlsProofOfPossession, BlsPublicKey, BlsSign};
+#[cfg(feature = "ml-dsa")]
+use super::ml_dsa::{MlDsaGenerateKey, MlDsaPublicKey, MlDsaSign};
+use super::{
+    bip32,
+    custom::CustomProcedure,
+    ed25519ph,
+    paper_backup::{self, PaperBackupEncoding, PaperBackupShards},
+    shamir,
+    types::*,
+    wrapped_key,
+    x509::{self, CertificateEncoding, DistinguishedName, SignatureAlgorithm},
+};
 use crate::{derive_record_id, derive_vault_id, Client, ClientError, Location, UseKey};
 pub use crypto::keys::slip10::{Chain, ChainCode};
 use crypto::{
@@ -27,9 +40,15 @@ use crypto::{
     utils::rand::fill,
 };
 
-use engine::runtime::memories::buffer::{Buffer, Ref};
+use engine::runtime::{
+    memories::buffer::{Buffer, Ref},
+    ZeroizingString, ZeroizingVec,
+};
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+use p256::elliptic_curve::{self, sec1::ToEncodedPoint};
 use serde::{Deserialize, Serialize};
 use stronghold_utils::GuardDebug;
+use thiserror::Error as DeriveError;
 use zeroize::Zeroize;
 
 /// Enum that wraps all cryptographic procedures that are supported by Stronghold.
@@ -46,9 +65,12 @@ pub enum StrongholdProcedure {
     Slip10Derive(Slip10Derive),
     BIP39Generate(BIP39Generate),
     BIP39Recover(BIP39Recover),
+    BIP39Validate(BIP39Validate),
     PublicKey(PublicKey),
     GenerateKey(GenerateKey),
     Ed25519Sign(Ed25519Sign),
+    Ed25519SignPrehashed(Ed25519SignPrehashed),
+    EcdsaSignPrehashed(EcdsaSignPrehashed),
     X25519DiffieHellman(X25519DiffieHellman),
     Hmac(Hmac),
     Hkdf(Hkdf),
@@ -59,6 +81,37 @@ pub enum StrongholdProcedure {
     AeadEncrypt(AeadEncrypt),
     AeadDecrypt(AeadDecrypt),
     ConcatSecret(ConcatSecret),
+    PaperBackupExport(PaperBackupExport),
+    PaperBackupImport(PaperBackupImport),
+    SssSplit(SssSplit),
+    SssRecover(SssRecover),
+    ExportWrappedKey(ExportWrappedKey),
+    ImportWrappedKey(ImportWrappedKey),
+    GenerateCsr(GenerateCsr),
+    SelfSignedCertificate(SelfSignedCertificate),
+
+    /// A procedure of another crate, see [`DynProcedure`](super::DynProcedure). It is skipped by serde, as
+    /// trait objects can't be deserialized.
+    #[serde(skip)]
+    CustomProcedure(CustomProcedure),
+
+    #[cfg(feature = "bls")]
+    BlsGenerateKey(BlsGenerateKey),
+    #[cfg(feature = "bls")]
+    BlsPublicKey(BlsPublicKey),
+    #[cfg(feature = "bls")]
+    BlsSign(BlsSign),
+    #[cfg(feature = "bls")]
+    BlsProofOfPossession(BlsProofOfPossession),
+    #[cfg(feature = "bls")]
+    BlsAggregate(BlsAggregate),
+
+    #[cfg(feature = "ml-dsa")]
+    MlDsaGenerateKey(MlDsaGenerateKey),
+    #[cfg(feature = "ml-dsa")]
+    MlDsaPublicKey(MlDsaPublicKey),
+    #[cfg(feature = "ml-dsa")]
+    MlDsaSign(MlDsaSign),
 
     #[cfg(feature = "insecure")]
     CompareSecret(CompareSecret),
@@ -78,9 +131,12 @@ impl Procedure for StrongholdProcedure {
             Slip10Derive(proc) => proc.execute(runner).map(|o| o.into()),
             BIP39Generate(proc) => proc.execute(runner).map(|o| o.into()),
             BIP39Recover(proc) => proc.execute(runner).map(|o| o.into()),
+            BIP39Validate(proc) => proc.execute(runner).map(|o| o.into()),
             GenerateKey(proc) => proc.execute(runner).map(|o| o.into()),
             PublicKey(proc) => proc.execute(runner).map(|o| o.into()),
             Ed25519Sign(proc) => proc.execute(runner).map(|o| o.into()),
+            Ed25519SignPrehashed(proc) => proc.execute(runner).map(|o| o.into()),
+            EcdsaSignPrehashed(proc) => proc.execute(runner).map(|o| o.into()),
             X25519DiffieHellman(proc) => proc.execute(runner).map(|o| o.into()),
             Hmac(proc) => proc.execute(runner).map(|o| o.into()),
             Hkdf(proc) => proc.execute(runner).map(|o| o.into()),
@@ -91,6 +147,33 @@ impl Procedure for StrongholdProcedure {
             AeadEncrypt(proc) => proc.execute(runner).map(|o| o.into()),
             AeadDecrypt(proc) => proc.execute(runner).map(|o| o.into()),
             ConcatSecret(proc) => proc.exec(runner).map(|o| o.into()),
+            PaperBackupExport(proc) => proc.execute(runner).map(|o| o.into()),
+            PaperBackupImport(proc) => proc.execute(runner).map(|o| o.into()),
+            SssSplit(proc) => proc.execute(runner).map(|o| o.into()),
+            SssRecover(proc) => proc.execute(runner).map(|o| o.into()),
+            ExportWrappedKey(proc) => proc.execute(runner).map(|o| o.into()),
+            ImportWrappedKey(proc) => proc.execute(runner).map(|o| o.into()),
+            GenerateCsr(proc) => proc.execute(runner).map(|o| o.into()),
+            SelfSignedCertificate(proc) => proc.execute(runner).map(|o| o.into()),
+            CustomProcedure(proc) => proc.execute(runner).map(|o| o.into()),
+
+            #[cfg(feature = "bls")]
+            BlsGenerateKey(proc) => proc.execute(runner).map(|o| o.into()),
+            #[cfg(feature = "bls")]
+            BlsPublicKey(proc) => proc.execute(runner).map(|o| o.into()),
+            #[cfg(feature = "bls")]
+            BlsSign(proc) => proc.execute(runner).map(|o| o.into()),
+            #[cfg(feature = "bls")]
+            BlsProofOfPossession(proc) => proc.execute(runner).map(|o| o.into()),
+            #[cfg(feature = "bls")]
+            BlsAggregate(proc) => proc.execute(runner).map(|o| o.into()),
+
+            #[cfg(feature = "ml-dsa")]
+            MlDsaGenerateKey(proc) => proc.execute(runner).map(|o| o.into()),
+            #[cfg(feature = "ml-dsa")]
+            MlDsaPublicKey(proc) => proc.execute(runner).map(|o| o.into()),
+            #[cfg(feature = "ml-dsa")]
+            MlDsaSign(proc) => proc.execute(runner).map(|o| o.into()),
 
             #[cfg(feature = "insecure")]
             CompareSecret(proc) => proc.exec(runner).map(|o| o.into()),
@@ -112,6 +195,8 @@ impl StrongholdProcedure {
             })
             | StrongholdProcedure::PublicKey(PublicKey { private_key: input, .. })
             | StrongholdProcedure::Ed25519Sign(Ed25519Sign { private_key: input, .. })
+            | StrongholdProcedure::Ed25519SignPrehashed(Ed25519SignPrehashed { private_key: input, .. })
+            | StrongholdProcedure::EcdsaSignPrehashed(EcdsaSignPrehashed { private_key: input, .. })
             | StrongholdProcedure::X25519DiffieHellman(X25519DiffieHellman { private_key: input, .. })
             | StrongholdProcedure::Hkdf(Hkdf { ikm: input, .. })
             | StrongholdProcedure::ConcatKdf(ConcatKdf {
@@ -119,7 +204,24 @@ impl StrongholdProcedure {
             })
             | StrongholdProcedure::Hmac(Hmac { key: input, .. })
             | StrongholdProcedure::AeadEncrypt(AeadEncrypt { key: input, .. })
-            | StrongholdProcedure::AeadDecrypt(AeadDecrypt { key: input, .. }) => Some(input.clone()),
+            | StrongholdProcedure::AeadDecrypt(AeadDecrypt { key: input, .. })
+            | StrongholdProcedure::PaperBackupExport(PaperBackupExport { secret: input, .. })
+            | StrongholdProcedure::SssSplit(SssSplit { secret: input, .. })
+            | StrongholdProcedure::ExportWrappedKey(ExportWrappedKey { source: input, .. })
+            | StrongholdProcedure::GenerateCsr(GenerateCsr { private_key: input, .. })
+            | StrongholdProcedure::SelfSignedCertificate(SelfSignedCertificate { private_key: input, .. }) => {
+                Some(input.clone())
+            }
+            StrongholdProcedure::CustomProcedure(proc) => proc.source().into_iter().next(),
+            #[cfg(feature = "bls")]
+            StrongholdProcedure::BlsPublicKey(BlsPublicKey { private_key: input })
+            | StrongholdProcedure::BlsSign(BlsSign { private_key: input, .. })
+            | StrongholdProcedure::BlsProofOfPossession(BlsProofOfPossession { private_key: input }) => {
+                Some(input.clone())
+            }
+            #[cfg(feature = "ml-dsa")]
+            StrongholdProcedure::MlDsaPublicKey(MlDsaPublicKey { private_key: input, .. })
+            | StrongholdProcedure::MlDsaSign(MlDsaSign { private_key: input, .. }) => Some(input.clone()),
             _ => None,
         }
     }
@@ -135,10 +237,122 @@ impl StrongholdProcedure {
             | StrongholdProcedure::X25519DiffieHellman(X25519DiffieHellman { shared_key: output, .. })
             | StrongholdProcedure::Hkdf(Hkdf { okm: output, .. })
             | StrongholdProcedure::ConcatKdf(ConcatKdf { output, .. })
-            | StrongholdProcedure::Pbkdf2Hmac(Pbkdf2Hmac { output, .. }) => Some(output.clone()),
+            | StrongholdProcedure::Pbkdf2Hmac(Pbkdf2Hmac { output, .. })
+            | StrongholdProcedure::PaperBackupImport(PaperBackupImport { output, .. })
+            | StrongholdProcedure::SssRecover(SssRecover { output, .. })
+            | StrongholdProcedure::ImportWrappedKey(ImportWrappedKey { output, .. }) => Some(output.clone()),
+            StrongholdProcedure::CustomProcedure(proc) => proc.target(),
+            #[cfg(feature = "bls")]
+            StrongholdProcedure::BlsGenerateKey(BlsGenerateKey { output }) => Some(output.clone()),
+            #[cfg(feature = "ml-dsa")]
+            StrongholdProcedure::MlDsaGenerateKey(MlDsaGenerateKey { output, .. }) => Some(output.clone()),
             _ => None,
         }
     }
+
+    /// Returns the name of the procedure type.
+    pub(crate) fn name(&self) -> &'static str {
+        use StrongholdProcedure::*;
+        match self {
+            WriteVault(_) => "WriteVault",
+            RevokeData(_) => "RevokeData",
+            GarbageCollect(_) => "GarbageCollect",
+            CopyRecord(_) => "CopyRecord",
+            Slip10Generate(_) => "Slip10Generate",
+            Slip10Derive(_) => "Slip10Derive",
+            BIP39Generate(_) => "BIP39Generate",
+            BIP39Recover(_) => "BIP39Recover",
+            BIP39Validate(_) => "BIP39Validate",
+            PublicKey(_) => "PublicKey",
+            GenerateKey(_) => "GenerateKey",
+            Ed25519Sign(_) => "Ed25519Sign",
+            Ed25519SignPrehashed(_) => "Ed25519SignPrehashed",
+            EcdsaSignPrehashed(_) => "EcdsaSignPrehashed",
+            X25519DiffieHellman(_) => "X25519DiffieHellman",
+            Hmac(_) => "Hmac",
+            Hkdf(_) => "Hkdf",
+            ConcatKdf(_) => "ConcatKdf",
+            AesKeyWrapEncrypt(_) => "AesKeyWrapEncrypt",
+            AesKeyWrapDecrypt(_) => "AesKeyWrapDecrypt",
+            Pbkdf2Hmac(_) => "Pbkdf2Hmac",
+            AeadEncrypt(_) => "AeadEncrypt",
+            AeadDecrypt(_) => "AeadDecrypt",
+            ConcatSecret(_) => "ConcatSecret",
+            PaperBackupExport(_) => "PaperBackupExport",
+            PaperBackupImport(_) => "PaperBackupImport",
+            SssSplit(_) => "SssSplit",
+            SssRecover(_) => "SssRecover",
+            ExportWrappedKey(_) => "ExportWrappedKey",
+            ImportWrappedKey(_) => "ImportWrappedKey",
+            GenerateCsr(_) => "GenerateCsr",
+            SelfSignedCertificate(_) => "SelfSignedCertificate",
+            CustomProcedure(proc) => proc.name(),
+
+            #[cfg(feature = "bls")]
+            BlsGenerateKey(_) => "BlsGenerateKey",
+            #[cfg(feature = "bls")]
+            BlsPublicKey(_) => "BlsPublicKey",
+            #[cfg(feature = "bls")]
+            BlsSign(_) => "BlsSign",
+            #[cfg(feature = "bls")]
+            BlsProofOfPossession(_) => "BlsProofOfPossession",
+            #[cfg(feature = "bls")]
+            BlsAggregate(_) => "BlsAggregate",
+
+            #[cfg(feature = "ml-dsa")]
+            MlDsaGenerateKey(_) => "MlDsaGenerateKey",
+            #[cfg(feature = "ml-dsa")]
+            MlDsaPublicKey(_) => "MlDsaPublicKey",
+            #[cfg(feature = "ml-dsa")]
+            MlDsaSign(_) => "MlDsaSign",
+
+            #[cfg(feature = "insecure")]
+            CompareSecret(_) => "CompareSecret",
+        }
+    }
+
+    /// Returns the location of the new key, if the procedure generates a fresh key or seed.
+    pub(crate) fn generated_key(&self) -> Option<Location> {
+        match self {
+            StrongholdProcedure::GenerateKey(GenerateKey { output, .. })
+            | StrongholdProcedure::Slip10Generate(Slip10Generate { output, .. })
+            | StrongholdProcedure::BIP39Generate(BIP39Generate { output, .. }) => Some(output.clone()),
+            #[cfg(feature = "bls")]
+            StrongholdProcedure::BlsGenerateKey(BlsGenerateKey { output }) => Some(output.clone()),
+            #[cfg(feature = "ml-dsa")]
+            StrongholdProcedure::MlDsaGenerateKey(MlDsaGenerateKey { output, .. }) => Some(output.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the paths of all vaults that are accessed by the procedure.
+    pub(crate) fn vault_paths(&self) -> Vec<Vec<u8>> {
+        let mut locations: Vec<Location> = self.input().into_iter().chain(self.output()).collect();
+        match self {
+            StrongholdProcedure::RevokeData(RevokeData { location, .. }) => locations.push(location.clone()),
+            StrongholdProcedure::GarbageCollect(GarbageCollect { vault_path }) => return vec![vault_path.clone()],
+            StrongholdProcedure::AesKeyWrapEncrypt(AesKeyWrapEncrypt {
+                encryption_key,
+                wrap_key,
+                ..
+            }) => locations.extend([encryption_key.clone(), wrap_key.clone()]),
+            StrongholdProcedure::AesKeyWrapDecrypt(AesKeyWrapDecrypt {
+                decryption_key, output, ..
+            }) => locations.extend([decryption_key.clone(), output.clone()]),
+            StrongholdProcedure::ConcatSecret(ConcatSecret {
+                location_a,
+                location_b,
+                output_location,
+            }) => locations.extend([location_a.clone(), location_b.clone(), output_location.clone()]),
+            StrongholdProcedure::SssSplit(SssSplit { shares, .. }) => locations.extend(shares.iter().cloned()),
+            StrongholdProcedure::SssRecover(SssRecover { shares, .. }) => locations.extend(shares.iter().cloned()),
+            StrongholdProcedure::CustomProcedure(proc) => locations.extend(proc.source()),
+            #[cfg(feature = "insecure")]
+            StrongholdProcedure::CompareSecret(CompareSecret { location, .. }) => locations.push(location.clone()),
+            _ => {}
+        }
+        locations.into_iter().map(|l| l.vault_path().to_vec()).collect()
+    }
 }
 
 /// Implement `StrongholdProcedure: From<T>` for all.
@@ -199,9 +413,33 @@ generic_procedures! {
     UseSecret<1> => { CompareSecret }
 }
 
+#[cfg(feature = "bls")]
+generic_procedures! {
+    UseSecret<1> => { BlsPublicKey, BlsSign, BlsProofOfPossession }
+}
+
+#[cfg(feature = "bls")]
+procedures! {
+    GenerateSecret => { BlsGenerateKey },
+    _ => { BlsAggregate }
+}
+
+#[cfg(feature = "ml-dsa")]
+generic_procedures! {
+    UseSecret<1> => { MlDsaPublicKey, MlDsaSign }
+}
+
+#[cfg(feature = "ml-dsa")]
+procedures! {
+    GenerateSecret => { MlDsaGenerateKey }
+}
+
 generic_procedures! {
     // Stronghold procedures that implement the `UseSecret` trait.
-    UseSecret<1> => { PublicKey, Ed25519Sign, Hmac, AeadEncrypt, AeadDecrypt },
+    UseSecret<1> => {
+        PublicKey, Ed25519Sign, Ed25519SignPrehashed, EcdsaSignPrehashed, Hmac, AeadEncrypt, AeadDecrypt,
+        PaperBackupExport, GenerateCsr
+    },
     UseSecret<2> => { AesKeyWrapEncrypt },
     // Stronghold procedures that implement the `DeriveSecret` trait.
     DeriveSecret<1> => { CopyRecord, Slip10Derive, X25519DiffieHellman, Hkdf, ConcatKdf, AesKeyWrapDecrypt },
@@ -210,9 +448,13 @@ generic_procedures! {
 
 procedures! {
     // Stronghold procedures that implement the `GenerateSecret` trait.
-    GenerateSecret => { WriteVault, BIP39Generate, BIP39Recover, Slip10Generate, GenerateKey, Pbkdf2Hmac },
+    GenerateSecret => {
+        WriteVault, BIP39Generate, BIP39Recover, Slip10Generate, GenerateKey, Pbkdf2Hmac, PaperBackupImport,
+        ImportWrappedKey
+    },
     // Stronghold procedures that directly implement the `Procedure` trait.
-    _ => { RevokeData, GarbageCollect }
+    _ => { RevokeData, GarbageCollect, SssSplit, SssRecover, BIP39Validate, ExportWrappedKey, CustomProcedure,
+        SelfSignedCertificate }
 }
 
 /// Write data to the specified [`Location`].
@@ -257,7 +499,7 @@ impl Procedure for RevokeData {
     }
 }
 
-/// Garbage collects any revokes in a Vault based on the given `vault_path`.
+/// Garbage collects any revokes and expired records in a Vault based on the given `vault_path`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GarbageCollect {
     pub vault_path: Vec<u8>,
@@ -309,6 +551,61 @@ pub enum MnemonicLanguage {
     Japanese,
 }
 
+impl MnemonicLanguage {
+    fn wordlist(&self) -> bip39::wordlist::Wordlist<'static> {
+        match self {
+            MnemonicLanguage::English => bip39::wordlist::ENGLISH,
+            MnemonicLanguage::Japanese => bip39::wordlist::JAPANESE,
+        }
+    }
+}
+
+/// The number of words of a mnemonic sentence of [`BIP39Generate`]. Each three words encode 32 bits of entropy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MnemonicLength {
+    Words12,
+    Words15,
+    Words18,
+    Words21,
+    #[default]
+    Words24,
+}
+
+impl MnemonicLength {
+    /// Returns the number of bytes of entropy, that are encoded in a mnemonic of this length
+    pub fn entropy_len(&self) -> usize {
+        match self {
+            MnemonicLength::Words12 => 16,
+            MnemonicLength::Words15 => 20,
+            MnemonicLength::Words18 => 24,
+            MnemonicLength::Words21 => 28,
+            MnemonicLength::Words24 => 32,
+        }
+    }
+
+    pub fn word_count(&self) -> usize {
+        self.entropy_len() * 3 / 4
+    }
+}
+
+impl TryFrom<usize> for MnemonicLength {
+    type Error = ClientError;
+
+    fn try_from(word_count: usize) -> Result<Self, Self::Error> {
+        match word_count {
+            12 => Ok(MnemonicLength::Words12),
+            15 => Ok(MnemonicLength::Words15),
+            18 => Ok(MnemonicLength::Words18),
+            21 => Ok(MnemonicLength::Words21),
+            24 => Ok(MnemonicLength::Words24),
+            _ => Err(ClientError::Inner(format!(
+                "Invalid mnemonic length of {} words",
+                word_count
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum AeadCipher {
     Aes256Gcm,
@@ -319,6 +616,10 @@ pub enum AeadCipher {
 pub enum KeyType {
     Ed25519,
     X25519,
+    /// ECDSA key on secp256k1, compatible with the keys of [`Slip10Derive`] on [`Curve::Secp256k1`]
+    Secp256k1Ecdsa,
+    /// ECDSA key on NIST P-256, compatible with the keys of [`Slip10Derive`] on [`Curve::NistP256`]
+    P256Ecdsa,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -344,8 +645,12 @@ impl FromStr for MnemonicLanguage {
 /// passphrase). Store the seed and return the mnemonic sentence as data output.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BIP39Generate {
-    pub passphrase: Option<String>,
+    pub passphrase: Option<ZeroizingString>,
     pub language: MnemonicLanguage,
+
+    #[serde(default)]
+    pub length: MnemonicLength,
+
     pub output: Location,
 }
 
@@ -353,21 +658,20 @@ impl GenerateSecret for BIP39Generate {
     type Output = String;
 
     fn generate(self) -> Result<Products<Self::Output>, FatalProcedureError> {
-        let mut entropy = [0u8; 32];
-        fill(&mut entropy)?;
+        self.generate_with_rng(&OsRng)
+    }
 
-        let wordlist = match self.language {
-            MnemonicLanguage::English => bip39::wordlist::ENGLISH,
-            MnemonicLanguage::Japanese => bip39::wordlist::JAPANESE,
-        };
+    fn generate_with_rng(self, rng: &dyn SecureRng) -> Result<Products<Self::Output>, FatalProcedureError> {
+        let mut entropy = vec![0u8; self.length.entropy_len()];
+        rng.fill(&mut entropy)?;
 
-        let mnemonic = bip39::wordlist::encode(&entropy, &wordlist).unwrap();
+        let mnemonic = bip39::wordlist::encode(&entropy, &self.language.wordlist());
+        entropy.zeroize();
+        let mnemonic = mnemonic.map_err(|e| FatalProcedureError::from(format!("{:?}", e)))?;
 
         let mut seed = [0u8; 64];
-        let mut passphrase = self.passphrase.clone().unwrap_or_else(|| "".into());
-        bip39::mnemonic_to_seed(&mnemonic, &passphrase, &mut seed);
-
-        passphrase.zeroize();
+        let passphrase = self.passphrase.as_deref().unwrap_or_default();
+        bip39::mnemonic_to_seed(&mnemonic, passphrase, &mut seed);
 
         Ok(Products {
             secret: seed.to_vec(),
@@ -380,9 +684,26 @@ impl GenerateSecret for BIP39Generate {
     }
 }
 
-impl Drop for BIP39Generate {
-    fn drop(&mut self) {
-        self.passphrase.zeroize();
+/// Check that `mnemonic` is a valid BIP39 mnemonic sentence in `language`, i.e. that all words are in the
+/// wordlist and that the checksum matches. The procedure fails, if the mnemonic is invalid. The error doesn't
+/// tell which word is wrong, so that no part of the mnemonic ends up in error messages or logs.
+///
+/// Unlike [`BIP39Recover`], no seed is derived, so a mistyped mnemonic can be rejected before it is imported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BIP39Validate {
+    pub mnemonic: ZeroizingString,
+    pub language: MnemonicLanguage,
+}
+
+impl Procedure for BIP39Validate {
+    type Output = ();
+
+    fn execute<R: Runner>(self, _runner: &R) -> Result<Self::Output, ProcedureError> {
+        let mut entropy = bip39::wordlist::decode(&self.mnemonic, &self.language.wordlist())
+            // the error of the decoding may contain words of the mnemonic, so it must not be part of the message
+            .map_err(|_| FatalProcedureError::from("Invalid mnemonic".to_string()))?;
+        entropy.zeroize();
+        Ok(())
     }
 }
 
@@ -390,8 +711,8 @@ impl Drop for BIP39Generate {
 /// a BIP39 seed and store it in the `output` location
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BIP39Recover {
-    pub passphrase: Option<String>,
-    pub mnemonic: String,
+    pub passphrase: Option<ZeroizingString>,
+    pub mnemonic: ZeroizingString,
     pub output: Location,
 }
 
@@ -400,11 +721,8 @@ impl GenerateSecret for BIP39Recover {
 
     fn generate(self) -> Result<Products<Self::Output>, FatalProcedureError> {
         let mut seed = [0u8; 64];
-        let mut passphrase = self.passphrase.clone().unwrap_or_else(|| "".into());
-        bip39::mnemonic_to_seed(&self.mnemonic, &passphrase, &mut seed);
-
-        // explicitly clear passphrase
-        passphrase.zeroize();
+        let passphrase = self.passphrase.as_deref().unwrap_or_default();
+        bip39::mnemonic_to_seed(&self.mnemonic, passphrase, &mut seed);
 
         Ok(Products {
             secret: seed.to_vec(),
@@ -417,12 +735,189 @@ impl GenerateSecret for BIP39Recover {
     }
 }
 
-impl Drop for BIP39Recover {
+/// Export the secret at `secret`, e.g. the snapshot key, as shards for an offline paper backup. Each shard is
+/// either a BIP39 mnemonic or a base32 string, that can be printed as QR code.
+///
+/// Anyone who holds all shards can restore the secret with [`PaperBackupImport`], so the shards must be kept
+/// as safe as the secret itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperBackupExport {
+    pub secret: Location,
+    pub encoding: PaperBackupEncoding,
+}
+
+impl UseSecret<1> for PaperBackupExport {
+    type Output = PaperBackupShards;
+
+    fn use_secret(self, guards: [Buffer<u8>; 1]) -> Result<Self::Output, FatalProcedureError> {
+        let secret = guards[0].borrow();
+        paper_backup::encode(&secret, self.encoding)
+    }
+
+    fn source(&self) -> [Location; 1] {
+        [self.secret.clone()]
+    }
+}
+
+/// Restore a secret from all shards of a [`PaperBackupExport`] in any order, and store it in the `output`
+/// location.
+#[derive(Clone, GuardDebug, Serialize, Deserialize)]
+pub struct PaperBackupImport {
+    pub shards: Vec<String>,
+    pub encoding: PaperBackupEncoding,
+    pub output: Location,
+}
+
+impl GenerateSecret for PaperBackupImport {
+    type Output = ();
+
+    fn generate(self) -> Result<Products<Self::Output>, FatalProcedureError> {
+        let secret = paper_backup::decode(&self.shards, self.encoding)?;
+        Ok(Products { secret, output: () })
+    }
+
+    fn target(&self) -> &Location {
+        &self.output
+    }
+}
+
+impl Drop for PaperBackupImport {
     fn drop(&mut self) {
-        self.mnemonic.zeroize();
-        if let Some(ref mut p) = &mut self.passphrase {
-            p.zeroize();
+        self.shards.iter_mut().for_each(|shard| shard.zeroize());
+    }
+}
+
+/// Export the secret at `source` encrypted under `passphrase`, so that it can be moved to another Stronghold or
+/// to an HSM. The key is derived from the passphrase with Argon2id, the secret is encrypted with
+/// XChaCha20-Poly1305.
+///
+/// The export is restored with [`ImportWrappedKey`] and the same passphrase. Unlike writing the secret into the
+/// [`Store`](crate::Store), it never leaves the guarded memory unencrypted.
+#[derive(Clone, GuardDebug, Serialize, Deserialize)]
+pub struct ExportWrappedKey {
+    pub source: Location,
+    pub passphrase: ZeroizingString,
+}
+
+impl Procedure for ExportWrappedKey {
+    type Output = Vec<u8>;
+
+    fn execute<R: Runner>(self, runner: &R) -> Result<Self::Output, ProcedureError> {
+        let mut random = [0u8; wrapped_key::RANDOM_LENGTH];
+        runner.rng()?.fill(&mut random)?;
+        let wrapped = runner.get_guards([self.source.clone()], |guards| {
+            wrapped_key::wrap(&guards[0].borrow(), self.passphrase.as_bytes(), &random)
+        })?;
+        Ok(wrapped)
+    }
+}
+
+/// Decrypt an export of [`ExportWrappedKey`] with `passphrase`, and store the secret in the `output` location.
+#[derive(Clone, GuardDebug, Serialize, Deserialize)]
+pub struct ImportWrappedKey {
+    pub wrapped: Vec<u8>,
+    pub passphrase: ZeroizingString,
+    pub output: Location,
+}
+
+impl GenerateSecret for ImportWrappedKey {
+    type Output = ();
+
+    fn generate(self) -> Result<Products<Self::Output>, FatalProcedureError> {
+        let secret = wrapped_key::unwrap(&self.wrapped, self.passphrase.as_bytes())?;
+        Ok(Products { secret, output: () })
+    }
+
+    fn target(&self) -> &Location {
+        &self.output
+    }
+}
+
+/// Split the secret at `secret`, e.g. the snapshot key, into one share per location in `shares` with
+/// Shamir's secret sharing. Any `threshold` of the shares can recover the secret with [`SssRecover`], fewer
+/// shares reveal nothing about it.
+///
+/// Each share is stored as a separate record, so the shares can be exported and handed to different
+/// custodians. At most 255 shares are supported.
+///
+/// The shares are binary records of the form `| threshold: u8 | x: u8 | y |`, not SLIP-39 mnemonics, and can
+/// only be recovered with [`SssRecover`]. Groups and passphrases of SLIP-39 are not supported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SssSplit {
+    pub secret: Location,
+    pub threshold: u8,
+    pub shares: Vec<Location>,
+}
+
+impl Procedure for SssSplit {
+    type Output = ();
+
+    fn execute<R: Runner>(self, runner: &R) -> Result<Self::Output, ProcedureError> {
+        if self.threshold == 0 || self.shares.len() < self.threshold as usize || self.shares.len() > u8::MAX as usize {
+            return Err(ProcedureError::Procedure(
+                format!("Invalid threshold {} for {} shares", self.threshold, self.shares.len()).into(),
+            ));
+        }
+        let len = runner.get_guards([self.secret.clone()], |guards| match guards[0].borrow().len() {
+            0 => Err(FatalProcedureError::from("Secret is empty".to_string())),
+            len => Ok(len),
+        })?;
+
+        // the same polynomials are evaluated for all shares
+        let mut coefficients = vec![0u8; len * (self.threshold as usize - 1)];
+        runner.rng()?.fill(&mut coefficients)?;
+
+        let mut written = Ok(());
+        for (i, location) in self.shares.iter().enumerate() {
+            written = runner.exec_proc([self.secret.clone()], location, |guards| {
+                let secret = guards[0].borrow();
+                Ok(Products {
+                    secret: shamir::share(&secret, &coefficients, self.threshold, i as u8 + 1),
+                    output: (),
+                })
+            });
+            if written.is_err() {
+                break;
+            }
+        }
+        coefficients.zeroize();
+        Ok(written?)
+    }
+}
+
+/// Recover a secret from the shares of an [`SssSplit`] and store it in the `output` location. At least as
+/// many shares as the threshold of the split are required, in any order.
+///
+/// The shares are read one after another, and the secret is reconstructed in guarded memory, until it is
+/// written to the `output` location.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SssRecover {
+    pub shares: Vec<Location>,
+    pub output: Location,
+}
+
+impl Procedure for SssRecover {
+    type Output = ();
+
+    fn execute<R: Runner>(self, runner: &R) -> Result<Self::Output, ProcedureError> {
+        // the threshold and the coordinates of the shares are not secret
+        let mut headers = Vec::with_capacity(self.shares.len());
+        for location in &self.shares {
+            headers.push(runner.get_guards([location.clone()], |guards| shamir::header(&guards[0].borrow()))?);
+        }
+        let len = shamir::check_headers(&headers)?;
+        let xs: Vec<u8> = headers.iter().map(|(_, x, _)| *x).collect();
+
+        let mut secret = Buffer::alloc(&vec![0u8; len], len);
+        for (i, location) in self.shares.iter().enumerate() {
+            let weight = shamir::lagrange_weight(&xs, i);
+            runner.get_guards([location.clone()], |guards| {
+                shamir::accumulate(&mut secret.borrow_mut(), &guards[0].borrow(), weight);
+                Ok(())
+            })?;
         }
+        runner.write_to_vault(&self.output, secret.borrow().to_vec())?;
+        Ok(())
     }
 }
 
@@ -442,9 +937,13 @@ impl GenerateSecret for Slip10Generate {
     type Output = ();
 
     fn generate(self) -> Result<Products<Self::Output>, FatalProcedureError> {
+        self.generate_with_rng(&OsRng)
+    }
+
+    fn generate_with_rng(self, rng: &dyn SecureRng) -> Result<Products<Self::Output>, FatalProcedureError> {
         let size_bytes = self.size_bytes.unwrap_or(64);
         let mut seed = vec![0u8; size_bytes];
-        fill(&mut seed)?;
+        rng.fill(&mut seed)?;
         Ok(Products {
             secret: seed,
             output: (),
@@ -463,10 +962,133 @@ pub enum Slip10DeriveInput {
     Key(Location),
 }
 
+/// The curve of the keys, that are derived by [`Slip10Derive`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Curve {
+    /// Only supports hardened derivation
+    #[default]
+    Ed25519,
+
+    Secp256k1,
+
+    NistP256,
+}
+
+/// A BIP-44 derivation path `m / 44' / coin_type' / account' / change / address_index`
+///
+/// The purpose, the coin type and the account are always hardened. The change and the address index are hardened
+/// for [`Curve::Ed25519`], that doesn't support anything else, and not hardened for the other curves, as specified
+/// by BIP-44.
+///
+/// # Example
+/// ```
+/// use iota_stronghold::procedures::Bip44Path;
+///
+/// let path: Bip44Path = "m/44'/4218'/0'/0'/7'".parse().unwrap();
+/// assert_eq!(path, Bip44Path::new(4218, 0, 0, 7));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bip44Path {
+    pub coin_type: u32,
+    pub account: u32,
+    pub change: u32,
+    pub address_index: u32,
+}
+
+impl Bip44Path {
+    pub const PURPOSE: u32 = 44;
+
+    pub fn new(coin_type: u32, account: u32, change: u32, address_index: u32) -> Self {
+        Self {
+            coin_type,
+            account,
+            change,
+            address_index,
+        }
+    }
+
+    /// Returns the chain of the path for keys on `curve`.
+    pub fn to_chain(&self, curve: Curve) -> Chain {
+        let hardened = 1 << 31;
+        let tail = match curve {
+            Curve::Ed25519 => hardened,
+            Curve::Secp256k1 | Curve::NistP256 => 0,
+        };
+        Chain::from_u32(vec![
+            Self::PURPOSE | hardened,
+            self.coin_type | hardened,
+            self.account | hardened,
+            self.change | tail,
+            self.address_index | tail,
+        ])
+    }
+}
+
+impl FromStr for Bip44Path {
+    type Err = Bip44PathError;
+
+    /// Parses paths like `m/44'/4218'/0'/0'/0'`. Hardened segments are marked with `'` or `h`. The markers of the
+    /// change and the address index are optional, as their hardening depends on the curve.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| Bip44PathError(format!("{}: {}", s, reason));
+        let mut segments = s.split('/');
+        if segments.next() != Some("m") {
+            return Err(invalid("path has to start with m"));
+        }
+
+        let mut indices = Vec::with_capacity(5);
+        for (position, segment) in segments.enumerate() {
+            let (index, hardened) = match segment.strip_suffix('\'').or_else(|| segment.strip_suffix('h')) {
+                Some(index) => (index, true),
+                None => (segment, false),
+            };
+            if position < 3 && !hardened {
+                return Err(invalid("purpose, coin type and account have to be hardened"));
+            }
+            let index: u32 = index.parse().map_err(|_| invalid("invalid index"))?;
+            if index >= 1 << 31 {
+                return Err(invalid("index is out of range"));
+            }
+            indices.push(index);
+        }
+
+        match indices[..] {
+            [Self::PURPOSE, coin_type, account, change, address_index] => {
+                Ok(Self::new(coin_type, account, change, address_index))
+            }
+            [_, _, _, _, _] => Err(invalid("purpose has to be 44")),
+            _ => Err(invalid("path has to have 5 segments")),
+        }
+    }
+}
+
+impl std::fmt::Display for Bip44Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "m/{}'/{}'/{}'/{}/{}",
+            Self::PURPOSE,
+            self.coin_type,
+            self.account,
+            self.change,
+            self.address_index
+        )
+    }
+}
+
+/// A string could not be parsed into a [`Bip44Path`]
+#[derive(Debug, Clone, PartialEq, Eq, DeriveError)]
+#[error("invalid BIP-44 path {0}")]
+pub struct Bip44PathError(String);
+
 /// Derive a SLIP10 child key from a seed or a parent key, store it in output location and
 /// return the corresponding chain code
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Slip10Derive {
+    /// The curve of the seed or the parent key. Snapshots of earlier versions only contain Ed25519 keys.
+    #[serde(default)]
+    pub curve: Curve,
+
     pub chain: Chain,
 
     pub input: Slip10DeriveInput,
@@ -474,21 +1096,57 @@ pub struct Slip10Derive {
     pub output: Location,
 }
 
+impl Slip10Derive {
+    /// Derives the key at the BIP-44 `path` on `curve`.
+    pub fn bip44(curve: Curve, path: &Bip44Path, input: Slip10DeriveInput, output: Location) -> Self {
+        Self {
+            curve,
+            chain: path.to_chain(curve),
+            input,
+            output,
+        }
+    }
+}
+
 impl DeriveSecret<1> for Slip10Derive {
     type Output = ChainCode;
 
     fn derive(self, guards: [Buffer<u8>; 1]) -> Result<Products<ChainCode>, FatalProcedureError> {
-        let dk = match self.input {
-            Slip10DeriveInput::Key(_) => {
-                slip10::Key::try_from(&*guards[0].borrow()).and_then(|parent| parent.derive(&self.chain))
-            }
-            Slip10DeriveInput::Seed(_) => {
-                slip10::Seed::from_bytes(&guards[0].borrow()).derive(slip10::Curve::Ed25519, &self.chain)
+        let child_key = match self.curve {
+            Curve::Ed25519 => {
+                let dk = match self.input {
+                    Slip10DeriveInput::Key(_) => {
+                        slip10::Key::try_from(&*guards[0].borrow()).and_then(|parent| parent.derive(&self.chain))
+                    }
+                    Slip10DeriveInput::Seed(_) => {
+                        slip10::Seed::from_bytes(&guards[0].borrow()).derive(slip10::Curve::Ed25519, &self.chain)
+                    }
+                }?;
+                return Ok(Products {
+                    secret: dk.into(),
+                    output: dk.chain_code(),
+                });
             }
-        }?;
+            Curve::Secp256k1 => bip32::secp256k1::child_key,
+            Curve::NistP256 => bip32::nist_p256::child_key,
+        };
+
+        let mut parent = match self.input {
+            Slip10DeriveInput::Key(_) => guards[0].borrow().to_vec(),
+            Slip10DeriveInput::Seed(_) => match self.curve {
+                Curve::Secp256k1 => bip32::secp256k1::master_key(&guards[0].borrow()).to_vec(),
+                _ => bip32::nist_p256::master_key(&guards[0].borrow()).to_vec(),
+            },
+        };
+        let derived = bip32::derive(&parent, &self.chain, child_key);
+        parent.zeroize();
+        let secret = derived?;
+
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&secret[32..]);
         Ok(Products {
-            secret: dk.into(),
-            output: dk.chain_code(),
+            secret,
+            output: chain_code,
         })
     }
 
@@ -534,6 +1192,15 @@ fn ed25519_secret_key(raw: Ref<u8>) -> Result<ed25519::SecretKey, crypto::Error>
     Ok(ed25519::SecretKey::from_bytes(bs))
 }
 
+/// Reads the ECDSA private key from the first 32 bytes of `raw`.
+fn ecdsa_secret_key<C: elliptic_curve::Curve>(raw: &[u8]) -> Result<elliptic_curve::SecretKey<C>, FatalProcedureError> {
+    if raw.len() < 32 {
+        return Err(format!("Invalid length {} of the ECDSA key", raw.len()).into());
+    }
+    elliptic_curve::SecretKey::from_be_bytes(&raw[..32])
+        .map_err(|_| FatalProcedureError::from("Invalid ECDSA private key".to_string()))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenerateKey {
     pub ty: KeyType,
@@ -544,10 +1211,19 @@ impl GenerateSecret for GenerateKey {
     type Output = ();
 
     fn generate(self) -> Result<Products<Self::Output>, FatalProcedureError> {
+        self.generate_with_rng(&OsRng)
+    }
+
+    fn generate_with_rng(self, rng: &dyn SecureRng) -> Result<Products<Self::Output>, FatalProcedureError> {
+        let mut bytes = [0u8; 32];
+        rng.fill(&mut bytes)?;
         let secret = match self.ty {
-            KeyType::Ed25519 => ed25519::SecretKey::generate().map(|sk| sk.to_bytes().to_vec())?,
-            KeyType::X25519 => x25519::SecretKey::generate().map(|sk| sk.to_bytes().to_vec())?,
+            KeyType::Ed25519 => ed25519::SecretKey::from_bytes(bytes).to_bytes().to_vec(),
+            KeyType::X25519 => x25519::SecretKey::from_bytes(bytes).to_bytes().to_vec(),
+            KeyType::Secp256k1Ecdsa => ecdsa_secret_key::<k256::Secp256k1>(&bytes)?.to_be_bytes().to_vec(),
+            KeyType::P256Ecdsa => ecdsa_secret_key::<p256::NistP256>(&bytes)?.to_be_bytes().to_vec(),
         };
+        bytes.zeroize();
         Ok(Products { secret, output: () })
     }
 
@@ -556,8 +1232,10 @@ impl GenerateSecret for GenerateKey {
     }
 }
 
-/// Derive an Ed25519 public key from the corresponding private key stored at the specified
-/// location
+/// Derive the public key from the corresponding private key stored at the specified location
+///
+/// Ed25519 and X25519 public keys are 32 bytes long, ECDSA public keys are returned as 33 bytes compressed SEC1
+/// points. Like with [`EcdsaSignPrehashed`], an ECDSA key is read from the first 32 bytes of the record.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PublicKey {
     pub ty: KeyType,
@@ -566,17 +1244,25 @@ pub struct PublicKey {
 }
 
 impl UseSecret<1> for PublicKey {
-    type Output = [u8; 32];
+    type Output = Vec<u8>;
 
     fn use_secret(self, guards: [Buffer<u8>; 1]) -> Result<Self::Output, FatalProcedureError> {
         match self.ty {
             KeyType::Ed25519 => {
                 let sk = ed25519_secret_key(guards[0].borrow())?;
-                Ok(sk.public_key().to_bytes())
+                Ok(sk.public_key().to_bytes().to_vec())
             }
             KeyType::X25519 => {
                 let sk = x25519_secret_key(guards[0].borrow())?;
-                Ok(sk.public_key().to_bytes())
+                Ok(sk.public_key().to_bytes().to_vec())
+            }
+            KeyType::Secp256k1Ecdsa => {
+                let sk: k256::SecretKey = ecdsa_secret_key(&guards[0].borrow())?;
+                Ok(sk.public_key().to_encoded_point(true).as_bytes().to_vec())
+            }
+            KeyType::P256Ecdsa => {
+                let sk: p256::SecretKey = ecdsa_secret_key(&guards[0].borrow())?;
+                Ok(sk.public_key().to_encoded_point(true).as_bytes().to_vec())
             }
         }
     }
@@ -611,6 +1297,204 @@ impl UseSecret<1> for Ed25519Sign {
     }
 }
 
+/// Use the specified Ed25519 compatible key to sign the SHA-512 hash of a message with Ed25519ph
+///
+/// Only the hash is passed into the procedure, so that large messages don't have to be copied. The signature is
+/// verified with the Ed25519ph algorithm of RFC 8032, and not as an Ed25519 signature of the hash. The optional
+/// `context` of at most 255 bytes is part of the signature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ed25519SignPrehashed {
+    pub prehash: Vec<u8>,
+
+    #[serde(default)]
+    pub context: Vec<u8>,
+
+    pub private_key: Location,
+}
+
+impl UseSecret<1> for Ed25519SignPrehashed {
+    type Output = [u8; ed25519::SIGNATURE_LENGTH];
+
+    fn use_secret(self, guards: [Buffer<u8>; 1]) -> Result<Self::Output, FatalProcedureError> {
+        let raw = guards[0].borrow();
+        if raw.len() < ed25519::SECRET_KEY_LENGTH {
+            return Err(format!("Invalid length {} of the Ed25519 key", raw.len()).into());
+        }
+        let mut bs = [0; ed25519::SECRET_KEY_LENGTH];
+        bs.copy_from_slice(&raw[..ed25519::SECRET_KEY_LENGTH]);
+        let sig = ed25519ph::sign(&bs, &self.prehash, &self.context);
+        bs.zeroize();
+        sig
+    }
+
+    fn source(&self) -> [Location; 1] {
+        [self.private_key.clone()]
+    }
+}
+
+/// Sign the SHA-256 digest of a message with the ECDSA key at `private_key`
+///
+/// Like with [`Ed25519Sign`], the key is read from the first 32 bytes of the record, so keys of
+/// [`Slip10Derive`] on the same `curve` are compatible. The signature is encoded as `r || s`, for secp256k1 with
+/// the low `s` that is required by Bitcoin and Ethereum.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EcdsaSignPrehashed {
+    pub curve: Curve,
+
+    pub prehash: [u8; 32],
+
+    pub private_key: Location,
+}
+
+impl UseSecret<1> for EcdsaSignPrehashed {
+    type Output = [u8; 64];
+
+    fn use_secret(self, guards: [Buffer<u8>; 1]) -> Result<Self::Output, FatalProcedureError> {
+        let raw = guards[0].borrow();
+        if raw.len() < 32 {
+            return Err(format!("Invalid length {} of the ECDSA key", raw.len()).into());
+        }
+        let key = &raw[..32];
+        let invalid_key = |_| FatalProcedureError::from("Invalid ECDSA private key".to_string());
+        let signing_error = |e| FatalProcedureError::from(format!("ECDSA signing failed: {}", e));
+
+        let mut sig = [0u8; 64];
+        match self.curve {
+            Curve::Secp256k1 => {
+                let sk = k256::ecdsa::SigningKey::from_bytes(key).map_err(invalid_key)?;
+                let signature: k256::ecdsa::Signature = sk.sign_prehash(&self.prehash).map_err(signing_error)?;
+                sig.copy_from_slice(signature.as_ref());
+            }
+            Curve::NistP256 => {
+                let sk = p256::ecdsa::SigningKey::from_bytes(key).map_err(invalid_key)?;
+                let signature: p256::ecdsa::Signature = sk.sign_prehash(&self.prehash).map_err(signing_error)?;
+                sig.copy_from_slice(signature.as_ref());
+            }
+            Curve::Ed25519 => {
+                return Err("Ed25519 keys can't sign with ECDSA, use Ed25519SignPrehashed"
+                    .to_string()
+                    .into())
+            }
+        }
+        Ok(sig)
+    }
+
+    fn source(&self) -> [Location; 1] {
+        [self.private_key.clone()]
+    }
+}
+
+/// Signs the DER encoding of `tbs` with the key in `raw`, and returns the signed request or certificate
+fn x509_sign(
+    curve: Curve,
+    raw: Ref<u8>,
+    tbs: &x509::Tbs,
+    encoding: CertificateEncoding,
+) -> Result<Vec<u8>, FatalProcedureError> {
+    match curve {
+        Curve::Ed25519 => {
+            let sk = ed25519_secret_key(raw)?;
+            let tbs_der = tbs.to_der(SignatureAlgorithm::Ed25519, &sk.public_key().to_bytes());
+            let signature = sk.sign(&tbs_der).to_bytes();
+            Ok(tbs.sign(SignatureAlgorithm::Ed25519, tbs_der, &signature, encoding))
+        }
+        Curve::NistP256 => {
+            if raw.len() < 32 {
+                return Err(format!("Invalid length {} of the ECDSA key", raw.len()).into());
+            }
+            let invalid_key = || FatalProcedureError::from("Invalid ECDSA private key".to_string());
+            let public_key = p256::SecretKey::from_be_bytes(&raw[..32])
+                .map_err(|_| invalid_key())?
+                .public_key()
+                .to_encoded_point(false);
+            let sk = p256::ecdsa::SigningKey::from_bytes(&raw[..32]).map_err(|_| invalid_key())?;
+            let tbs_der = tbs.to_der(SignatureAlgorithm::EcdsaP256, public_key.as_bytes());
+            let signature: p256::ecdsa::Signature = sk
+                .sign_prehash(&Sha256::digest(&tbs_der))
+                .map_err(|e| FatalProcedureError::from(format!("ECDSA signing failed: {}", e)))?;
+            let signature = x509::ecdsa_signature(signature.as_ref());
+            Ok(tbs.sign(SignatureAlgorithm::EcdsaP256, tbs_der, &signature, encoding))
+        }
+        Curve::Secp256k1 => Err("secp256k1 keys are not supported in certificates".to_string().into()),
+    }
+}
+
+/// Create a PKCS#10 certificate signing request for the Ed25519 or P-256 key at `private_key`, e.g. to
+/// provision it for mTLS. The request is signed with the key, which never leaves the vault.
+///
+/// The key is read from the first 32 bytes of the record, like with [`Ed25519Sign`] and
+/// [`EcdsaSignPrehashed`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerateCsr {
+    pub curve: Curve,
+
+    pub private_key: Location,
+
+    pub subject: DistinguishedName,
+
+    pub encoding: CertificateEncoding,
+}
+
+impl UseSecret<1> for GenerateCsr {
+    type Output = Vec<u8>;
+
+    fn use_secret(self, guards: [Buffer<u8>; 1]) -> Result<Self::Output, FatalProcedureError> {
+        let tbs = x509::Tbs::Csr { subject: &self.subject };
+        x509_sign(self.curve, guards[0].borrow(), &tbs, self.encoding)
+    }
+
+    fn source(&self) -> [Location; 1] {
+        [self.private_key.clone()]
+    }
+}
+
+/// Create a self-signed X.509 v3 certificate for the Ed25519 or P-256 key at `private_key`, that is valid from
+/// `not_before` until `not_after`, both in seconds since the unix epoch. The serial number is random.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfSignedCertificate {
+    pub curve: Curve,
+
+    pub private_key: Location,
+
+    pub subject: DistinguishedName,
+
+    pub not_before: u64,
+
+    pub not_after: u64,
+
+    pub encoding: CertificateEncoding,
+}
+
+impl Procedure for SelfSignedCertificate {
+    type Output = Vec<u8>;
+
+    fn execute<R: Runner>(self, runner: &R) -> Result<Self::Output, ProcedureError> {
+        if self.not_after < self.not_before {
+            return Err(ProcedureError::Procedure(
+                "Certificate expires before it becomes valid".to_string().into(),
+            ));
+        }
+        // positive, and at most 20 bytes as required by RFC 5280
+        let mut serial = [0u8; 16];
+        runner.rng()?.fill(&mut serial)?;
+        serial[0] &= 0x7f;
+        let tbs = x509::Tbs::Certificate {
+            subject: &self.subject,
+            serial: &serial,
+            not_before: self.not_before,
+            not_after: self.not_after,
+        };
+        let certificate = runner.get_guards([self.private_key.clone()], |guards| {
+            x509_sign(self.curve, guards[0].borrow(), &tbs, self.encoding)
+        })?;
+        Ok(certificate)
+    }
+}
+
+/// Agree on a shared secret with the owner of `public_key`, and store it at `shared_key`. The shared secret
+/// never leaves the vault, derive keys from it with e.g. [`Hkdf`].
+///
+/// Public keys of low order, that would lead to an all-zero shared secret, are rejected.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct X25519DiffieHellman {
     pub public_key: [u8; x25519::PUBLIC_KEY_LENGTH],
@@ -626,12 +1510,15 @@ impl DeriveSecret<1> for X25519DiffieHellman {
     fn derive(self, guards: [Buffer<u8>; 1]) -> Result<Products<()>, FatalProcedureError> {
         let sk = x25519_secret_key(guards[0].borrow())?;
         let public = x25519::PublicKey::from_bytes(self.public_key);
-        let shared_key = sk.diffie_hellman(&public);
+        let secret = sk.diffie_hellman(&public).to_bytes().to_vec();
 
-        Ok(Products {
-            secret: shared_key.to_bytes().to_vec(),
-            output: (),
-        })
+        // the check is not constant time, but it only leaks that the result is invalid
+        if secret.iter().all(|b| *b == 0) {
+            return Err(FatalProcedureError::from(
+                "Public key has a low order, the shared secret would be zero".to_string(),
+            ));
+        }
+        Ok(Products { secret, output: () })
     }
 
     fn source(&self) -> [Location; 1] {
@@ -732,7 +1619,7 @@ impl DeriveSecret<1> for Hkdf {
 pub struct Pbkdf2Hmac {
     pub hash_type: Sha2Hash,
 
-    pub password: Vec<u8>,
+    pub password: ZeroizingVec,
 
     pub salt: Vec<u8>,
 
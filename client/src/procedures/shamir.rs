@@ -0,0 +1,164 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Shamir's secret sharing over GF(2^8).
+//!
+//! Each byte of the secret is the constant term of a random polynomial of degree `threshold - 1`. A share
+//! holds the evaluation of all polynomials at its x coordinate, prefixed by the threshold and the
+//! coordinate:
+//!
+//! ```text
+//! | threshold: u8 | x: u8 | y |
+//! ```
+//!
+//! Any `threshold` shares determine the polynomials, and with them the secret. Fewer shares reveal nothing
+//! about it.
+
+use super::FatalProcedureError;
+
+const HEADER_SIZE: usize = 2;
+
+/// Multiplication in GF(2^8) with the reduction polynomial of AES. Does not branch on its inputs.
+fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// Multiplicative inverse of a non-zero element, computed as `a^254`
+fn inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut power = a;
+    let mut exponent = 254u8;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul(result, power);
+        }
+        power = mul(power, power);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// Computes the share with coordinate `x` of `secret`. `coefficients` holds the `threshold - 1` random
+/// coefficients of each polynomial, as consecutive blocks of `secret.len()` bytes.
+pub(crate) fn share(secret: &[u8], coefficients: &[u8], threshold: u8, x: u8) -> Vec<u8> {
+    let len = secret.len();
+    let mut share = Vec::with_capacity(HEADER_SIZE + len);
+    share.extend_from_slice(&[threshold, x]);
+    for (j, s) in secret.iter().enumerate() {
+        // Horner's method, starting with the coefficient of the highest degree
+        let mut y = 0u8;
+        for k in (1..threshold as usize).rev() {
+            y = mul(y ^ coefficients[(k - 1) * len + j], x);
+        }
+        share.push(y ^ s);
+    }
+    share
+}
+
+/// Returns the threshold, the x coordinate and the length of the secret of `share`
+pub(crate) fn header(share: &[u8]) -> Result<(u8, u8, usize), FatalProcedureError> {
+    if share.len() <= HEADER_SIZE || share[0] == 0 || share[1] == 0 {
+        return Err(FatalProcedureError::from("Invalid secret share".to_string()));
+    }
+    Ok((share[0], share[1], share.len() - HEADER_SIZE))
+}
+
+/// Checks that the shares with `headers` belong together and are enough to recover the secret. Returns
+/// the length of the secret.
+pub(crate) fn check_headers(headers: &[(u8, u8, usize)]) -> Result<usize, FatalProcedureError> {
+    let (threshold, _, len) = *headers
+        .first()
+        .ok_or_else(|| FatalProcedureError::from("No secret shares".to_string()))?;
+    if headers.iter().any(|(t, _, l)| *t != threshold || *l != len) {
+        return Err(FatalProcedureError::from(
+            "Secret shares don't belong to the same secret".to_string(),
+        ));
+    }
+    for (i, (_, x, _)) in headers.iter().enumerate() {
+        if headers[..i].iter().any(|(_, other, _)| other == x) {
+            return Err(FatalProcedureError::from(format!("Duplicate secret share {}", x)));
+        }
+    }
+    if headers.len() < threshold as usize {
+        return Err(FatalProcedureError::from(format!(
+            "{} of {} required secret shares",
+            headers.len(),
+            threshold
+        )));
+    }
+    Ok(len)
+}
+
+/// Lagrange basis polynomial of the share at `index` evaluated at zero
+pub(crate) fn lagrange_weight(xs: &[u8], index: usize) -> u8 {
+    let x_i = xs[index];
+    xs.iter()
+        .enumerate()
+        .filter(|(j, _)| *j != index)
+        .fold(1, |weight, (_, x_j)| mul(weight, mul(*x_j, inv(x_j ^ x_i))))
+}
+
+/// Adds the contribution of `share` with the Lagrange `weight` to the secret
+pub(crate) fn accumulate(secret: &mut [u8], share: &[u8], weight: u8) {
+    secret
+        .iter_mut()
+        .zip(&share[HEADER_SIZE..])
+        .for_each(|(s, y)| *s ^= mul(*y, weight));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use stronghold_utils::random;
+
+    fn recover(shares: &[Vec<u8>]) -> Result<Vec<u8>, FatalProcedureError> {
+        let headers = shares
+            .iter()
+            .map(|share| header(share))
+            .collect::<Result<Vec<_>, _>>()?;
+        let len = check_headers(&headers)?;
+        let xs: Vec<u8> = headers.iter().map(|(_, x, _)| *x).collect();
+        let mut secret = vec![0u8; len];
+        for (i, share) in shares.iter().enumerate() {
+            accumulate(&mut secret, share, lagrange_weight(&xs, i));
+        }
+        Ok(secret)
+    }
+
+    #[test]
+    fn test_field() {
+        // the example of FIPS 197, section 4.2
+        assert_eq!(mul(0x57, 0x83), 0xc1);
+        for a in 1..=255u8 {
+            assert_eq!(mul(a, inv(a)), 1);
+        }
+    }
+
+    #[test]
+    fn test_split_recover() {
+        let secret = random::fixed_bytestring(32);
+        let threshold = 3;
+        let coefficients = random::fixed_bytestring(secret.len() * (threshold as usize - 1));
+        let shares: Vec<Vec<u8>> = (1..=5).map(|x| share(&secret, &coefficients, threshold, x)).collect();
+
+        assert_eq!(recover(&shares).unwrap(), secret);
+        assert_eq!(recover(&shares[2..]).unwrap(), secret);
+        assert_eq!(
+            recover(&[shares[4].clone(), shares[0].clone(), shares[2].clone()]).unwrap(),
+            secret
+        );
+        assert!(recover(&shares[..2]).is_err());
+        assert!(recover(&[shares[0].clone(), shares[0].clone(), shares[1].clone()]).is_err());
+
+        // a share of another secret
+        let other = share(&random::fixed_bytestring(16), &coefficients, threshold, 6);
+        assert!(recover(&[shares[0].clone(), shares[1].clone(), other]).is_err());
+    }
+}
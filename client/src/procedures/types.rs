@@ -7,8 +7,9 @@ use engine::{
     vault::{BoxProvider, VaultId},
 };
 use serde::{Deserialize, Serialize};
-use std::{fmt::Debug, string::FromUtf8Error};
+use std::{fmt::Debug, string::FromUtf8Error, sync::Arc, time::Duration};
 use thiserror::Error as DeriveError;
+use zeroize::Zeroize;
 
 /// Bridge to the engine that is required for using / writing / revoking secrets in the vault.
 pub trait Runner {
@@ -32,11 +33,47 @@ pub trait Runner {
     where
         F: FnOnce([Buffer<u8>; N]) -> Result<Products<T>, FatalProcedureError>;
 
-    fn write_to_vault(&self, location1: &Location, value: Vec<u8>) -> Result<(), RecordError>;
+    fn write_to_vault(&self, location1: &Location, value: Vec<u8>) -> Result<(), VaultError<FatalProcedureError>>;
 
     fn revoke_data(&self, location: &Location) -> Result<(), RecordError>;
 
     fn garbage_collect(&self, vault_id: VaultId) -> Result<bool, VaultError<FatalProcedureError>>;
+
+    /// Returns the random number generator for procedures that generate new secrets.
+    fn rng(&self) -> Result<Arc<dyn SecureRng>, FatalProcedureError> {
+        Ok(Arc::new(OsRng))
+    }
+}
+
+/// A source of randomness for procedures that generate new secrets, e.g. a hardware TRNG.
+pub trait SecureRng: Send + Sync {
+    /// Fills `buf` entirely with random bytes.
+    fn fill(&self, buf: &mut [u8]) -> Result<(), FatalProcedureError>;
+}
+
+/// The random number generator of the operating system.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OsRng;
+
+impl SecureRng for OsRng {
+    fn fill(&self, buf: &mut [u8]) -> Result<(), FatalProcedureError> {
+        crypto::utils::rand::fill(buf).map_err(FatalProcedureError::from)
+    }
+}
+
+/// Mixes a custom entropy source into the output of [`OsRng`]. Both outputs are xor-ed, so the
+/// result is at least as unpredictable as the stronger of the two sources.
+pub(crate) struct MixedRng(pub(crate) Arc<dyn SecureRng>);
+
+impl SecureRng for MixedRng {
+    fn fill(&self, buf: &mut [u8]) -> Result<(), FatalProcedureError> {
+        OsRng.fill(buf)?;
+        let mut entropy = vec![0u8; buf.len()];
+        self.0.fill(&mut entropy)?;
+        buf.iter_mut().zip(entropy.iter()).for_each(|(b, e)| *b ^= e);
+        entropy.zeroize();
+        Ok(())
+    }
 }
 
 /// Products of a procedure.
@@ -64,12 +101,19 @@ pub trait GenerateSecret: Sized {
 
     fn generate(self) -> Result<Products<Self::Output>, FatalProcedureError>;
 
+    /// Generates the secret with all randomness drawn from `rng`. Procedures that do not
+    /// need any randomness can rely on the default implementation.
+    fn generate_with_rng(self, rng: &dyn SecureRng) -> Result<Products<Self::Output>, FatalProcedureError> {
+        self.generate()
+    }
+
     fn target(&self) -> &Location;
 
     fn exec<R: Runner>(self, runner: &R) -> Result<Self::Output, ProcedureError> {
         let target = self.target();
         let target = target.clone();
-        let Products { output, secret } = self.generate()?;
+        let rng = runner.rng()?;
+        let Products { output, secret } = self.generate_with_rng(&*rng)?;
         runner.write_to_vault(&target, secret)?;
         Ok(output)
     }
@@ -111,6 +155,18 @@ pub trait UseSecret<const N: usize>: Sized {
     }
 }
 
+/// Where [`crate::Client::execute_procedure_with_target`] puts the non-secret output of a procedure
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputTarget {
+    /// Only return the output to the caller
+    #[default]
+    Return,
+
+    /// Also insert the output into the [`Store`](crate::Store) of the client at `key`, e.g. to cache a public key.
+    /// The entry expires after `ttl`, if it is set.
+    Store { key: Vec<u8>, ttl: Option<Duration> },
+}
+
 /// Output of a [`StrongholdProcedure`][super::StrongholdProcedure].
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ProcedureOutput(Vec<u8>);
@@ -174,6 +230,22 @@ pub enum ProcedureError {
     /// Operation on the vault failed.
     #[error("procedure: {0}")]
     Procedure(#[from] FatalProcedureError),
+
+    /// The procedure accesses a guarded vault and its execution has been denied.
+    #[error("procedure execution has not been approved")]
+    NotApproved,
+
+    /// The operation has been aborted with [`crate::Client::abort`].
+    #[error("procedure execution has been aborted")]
+    Aborted,
+
+    /// The client has been locked, see [`crate::Client::set_auto_lock`].
+    #[error("client is locked")]
+    Locked,
+
+    /// The Stronghold of the client shuts down, see [`crate::Stronghold::shutdown`].
+    #[error("stronghold is shutting down")]
+    ShuttingDown,
 }
 
 impl<T> From<VaultError<T>> for ProcedureError
@@ -0,0 +1,115 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Encryption of single secrets under a transfer passphrase, so that they can be moved to another Stronghold or
+//! to an HSM.
+//!
+//! The key is derived from the passphrase with Argon2id. The parameters of Argon2id are stored in the header, so
+//! that they can be raised later on without breaking older exports:
+//!
+//! ```text
+//! | version: u8 | mem_cost: u32 | time_cost: u32 | salt: [u8; 16] | nonce: [u8; 24] | tag: [u8; 16] | ciphertext |
+//! ```
+//!
+//! The integers are little endian. The header is the associated data of XChaCha20-Poly1305, so the parameters
+//! can't be lowered by an attacker.
+
+use crypto::ciphers::{
+    chacha::XChaCha20Poly1305,
+    traits::{Aead, Tag},
+};
+use engine::snapshot::kdf::Argon2Params;
+use zeroize::Zeroize;
+
+use super::FatalProcedureError;
+
+const VERSION: u8 = 1;
+
+const SALT_LENGTH: usize = 16;
+
+const HEADER_LENGTH: usize = 1 + 4 + 4 + SALT_LENGTH + XChaCha20Poly1305::NONCE_LENGTH;
+
+// the parameters, that are recommended for Argon2id by RFC 9106 for memory constrained environments
+const MEM_COST: u32 = 64 * 1024;
+const TIME_COST: u32 = 3;
+
+/// The random inputs of [`wrap`]
+pub(crate) const RANDOM_LENGTH: usize = SALT_LENGTH + XChaCha20Poly1305::NONCE_LENGTH;
+
+fn derive_key(passphrase: &[u8], salt: &[u8], mem_cost: u32, time_cost: u32) -> Result<Vec<u8>, FatalProcedureError> {
+    let config = argon2::Config {
+        variant: argon2::Variant::Argon2id,
+        version: argon2::Version::Version13,
+        mem_cost,
+        time_cost,
+        lanes: 1,
+        hash_length: XChaCha20Poly1305::KEY_LENGTH as u32,
+        ..Default::default()
+    };
+    argon2::hash_raw(passphrase, salt, &config).map_err(|e| FatalProcedureError::from(e.to_string()))
+}
+
+/// Encrypts `secret` with a key from `passphrase`. `random` provides the salt and the nonce.
+pub(crate) fn wrap(
+    secret: &[u8],
+    passphrase: &[u8],
+    random: &[u8; RANDOM_LENGTH],
+) -> Result<Vec<u8>, FatalProcedureError> {
+    let (salt, nonce) = random.split_at(SALT_LENGTH);
+
+    let mut header = Vec::with_capacity(HEADER_LENGTH);
+    header.push(VERSION);
+    header.extend_from_slice(&MEM_COST.to_le_bytes());
+    header.extend_from_slice(&TIME_COST.to_le_bytes());
+    header.extend_from_slice(salt);
+    header.extend_from_slice(nonce);
+
+    let mut key = derive_key(passphrase, salt, MEM_COST, TIME_COST)?;
+    let mut tag = Tag::<XChaCha20Poly1305>::default();
+    let mut ciphertext = vec![0; secret.len()];
+    let encrypted = XChaCha20Poly1305::try_encrypt(&key, nonce, &header, secret, &mut ciphertext, &mut tag);
+    key.zeroize();
+    encrypted?;
+
+    let mut wrapped = header;
+    wrapped.extend(tag);
+    wrapped.extend(ciphertext);
+    Ok(wrapped)
+}
+
+/// Decrypts the output of [`wrap`]. A wrong passphrase or a modified export fail alike.
+pub(crate) fn unwrap(wrapped: &[u8], passphrase: &[u8]) -> Result<Vec<u8>, FatalProcedureError> {
+    if wrapped.len() < HEADER_LENGTH + XChaCha20Poly1305::TAG_LENGTH {
+        return Err("Wrapped key is too short".to_string().into());
+    }
+    if wrapped[0] != VERSION {
+        return Err(format!("Unsupported version {} of the wrapped key", wrapped[0]).into());
+    }
+    let (header, body) = wrapped.split_at(HEADER_LENGTH);
+    let mem_cost = u32::from_le_bytes([header[1], header[2], header[3], header[4]]);
+    let time_cost = u32::from_le_bytes([header[5], header[6], header[7], header[8]]);
+    // the same limits as for snapshot files, so that a crafted header can't exhaust the memory
+    let params = Argon2Params {
+        memory_kib: mem_cost,
+        iterations: time_cost,
+        parallelism: 1,
+    };
+    if params.check_limits().is_err() {
+        return Err("Argon2id parameters of the wrapped key exceed the limits"
+            .to_string()
+            .into());
+    }
+    let salt = &header[9..9 + SALT_LENGTH];
+    let nonce = &header[9 + SALT_LENGTH..];
+    let (tag, ciphertext) = body.split_at(XChaCha20Poly1305::TAG_LENGTH);
+
+    let mut key = derive_key(passphrase, salt, mem_cost, time_cost)?;
+    let mut secret = vec![0; ciphertext.len()];
+    let decrypted = XChaCha20Poly1305::try_decrypt(&key, nonce, header, &mut secret, ciphertext, tag);
+    key.zeroize();
+    if decrypted.is_err() {
+        secret.zeroize();
+        return Err("Wrong passphrase or corrupted wrapped key".to_string().into());
+    }
+    Ok(secret)
+}
@@ -0,0 +1,309 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! DER encoding of PKCS#10 certificate signing requests (RFC 2986) and self-signed X.509 v3 certificates
+//! (RFC 5280), for Ed25519 (RFC 8410) and ECDSA P-256 keys.
+//!
+//! Only the structures, that are needed for the two procedures, are encoded. The signature is created by the
+//! caller over the bytes of [`Tbs::to_der`].
+
+use serde::{Deserialize, Serialize};
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OID: u8 = 0x06;
+const TAG_UTF8_STRING: u8 = 0x0c;
+const TAG_PRINTABLE_STRING: u8 = 0x13;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+
+const OID_COMMON_NAME: &[u64] = &[2, 5, 4, 3];
+const OID_COUNTRY: &[u64] = &[2, 5, 4, 6];
+const OID_ORGANIZATION: &[u64] = &[2, 5, 4, 10];
+const OID_ED25519: &[u64] = &[1, 3, 101, 112];
+const OID_EC_PUBLIC_KEY: &[u64] = &[1, 2, 840, 10045, 2, 1];
+const OID_PRIME256V1: &[u64] = &[1, 2, 840, 10045, 3, 1, 7];
+const OID_ECDSA_WITH_SHA256: &[u64] = &[1, 2, 840, 10045, 4, 3, 2];
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The subject of a certificate signing request or certificate. Empty optional attributes are omitted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DistinguishedName {
+    pub common_name: String,
+
+    #[serde(default)]
+    pub organization: Option<String>,
+
+    /// Two letter ISO 3166 country code
+    #[serde(default)]
+    pub country: Option<String>,
+}
+
+/// The encoding of a certificate signing request or certificate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CertificateEncoding {
+    Der,
+
+    /// Base64 of the DER encoding, wrapped in `-----BEGIN ...-----` lines
+    Pem,
+}
+
+/// The algorithm of the key, that signs a request or certificate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SignatureAlgorithm {
+    Ed25519,
+    EcdsaP256,
+}
+
+fn length(len: usize) -> Vec<u8> {
+    match len {
+        0..=0x7f => vec![len as u8],
+        _ => {
+            let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+            let mut encoded = vec![0x80 | bytes.len() as u8];
+            encoded.extend(bytes);
+            encoded
+        }
+    }
+}
+
+fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![tag];
+    encoded.extend(length(value.len()));
+    encoded.extend_from_slice(value);
+    encoded
+}
+
+fn constructed(tag: u8, parts: &[Vec<u8>]) -> Vec<u8> {
+    tlv(tag, &parts.concat())
+}
+
+fn oid(arcs: &[u64]) -> Vec<u8> {
+    let mut value = vec![(arcs[0] * 40 + arcs[1]) as u8];
+    for arc in &arcs[2..] {
+        let mut base128 = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            base128.push((rest & 0x7f) as u8 | 0x80);
+            rest >>= 7;
+        }
+        value.extend(base128.into_iter().rev());
+    }
+    tlv(TAG_OID, &value)
+}
+
+/// Encodes `bytes` as a positive big endian integer
+fn integer(bytes: &[u8]) -> Vec<u8> {
+    let trimmed: Vec<u8> = bytes.iter().copied().skip_while(|b| *b == 0).collect();
+    let mut value = Vec::with_capacity(trimmed.len() + 1);
+    if trimmed.first().is_none_or(|b| b & 0x80 != 0) {
+        value.push(0);
+    }
+    value.extend(trimmed);
+    tlv(TAG_INTEGER, &value)
+}
+
+fn bit_string(bytes: &[u8]) -> Vec<u8> {
+    let mut value = vec![0];
+    value.extend_from_slice(bytes);
+    tlv(TAG_BIT_STRING, &value)
+}
+
+fn attribute(arcs: &[u64], tag: u8, value: &str) -> Vec<u8> {
+    let pair = constructed(TAG_SEQUENCE, &[oid(arcs), tlv(tag, value.as_bytes())]);
+    tlv(TAG_SET, &pair)
+}
+
+fn name(dn: &DistinguishedName) -> Vec<u8> {
+    let mut rdns = Vec::new();
+    if let Some(country) = &dn.country {
+        rdns.push(attribute(OID_COUNTRY, TAG_PRINTABLE_STRING, country));
+    }
+    if let Some(organization) = &dn.organization {
+        rdns.push(attribute(OID_ORGANIZATION, TAG_UTF8_STRING, organization));
+    }
+    rdns.push(attribute(OID_COMMON_NAME, TAG_UTF8_STRING, &dn.common_name));
+    constructed(TAG_SEQUENCE, &rdns)
+}
+
+/// Converts days since the unix epoch into the civil date `(year, month, day)`
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Encodes seconds since the unix epoch as UTCTime until 2049, and as GeneralizedTime afterwards
+fn time(unix_seconds: u64) -> Vec<u8> {
+    let (year, month, day) = civil_from_days(unix_seconds / 86_400);
+    let secs = unix_seconds % 86_400;
+    let (hour, minute, second) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if year < 2050 {
+        let s = format!(
+            "{:02}{:02}{:02}{:02}{:02}{:02}Z",
+            year % 100,
+            month,
+            day,
+            hour,
+            minute,
+            second
+        );
+        tlv(TAG_UTC_TIME, s.as_bytes())
+    } else {
+        let s = format!(
+            "{:04}{:02}{:02}{:02}{:02}{:02}Z",
+            year, month, day, hour, minute, second
+        );
+        tlv(TAG_GENERALIZED_TIME, s.as_bytes())
+    }
+}
+
+impl SignatureAlgorithm {
+    fn identifier(&self) -> Vec<u8> {
+        match self {
+            SignatureAlgorithm::Ed25519 => constructed(TAG_SEQUENCE, &[oid(OID_ED25519)]),
+            SignatureAlgorithm::EcdsaP256 => constructed(TAG_SEQUENCE, &[oid(OID_ECDSA_WITH_SHA256)]),
+        }
+    }
+
+    /// `public_key` is the raw Ed25519 key, or the uncompressed SEC1 point of P-256
+    fn subject_public_key_info(&self, public_key: &[u8]) -> Vec<u8> {
+        let algorithm = match self {
+            SignatureAlgorithm::Ed25519 => constructed(TAG_SEQUENCE, &[oid(OID_ED25519)]),
+            SignatureAlgorithm::EcdsaP256 => constructed(TAG_SEQUENCE, &[oid(OID_EC_PUBLIC_KEY), oid(OID_PRIME256V1)]),
+        };
+        constructed(TAG_SEQUENCE, &[algorithm, bit_string(public_key)])
+    }
+}
+
+/// The signed part of a request or certificate
+pub(crate) enum Tbs<'a> {
+    Csr {
+        subject: &'a DistinguishedName,
+    },
+    Certificate {
+        subject: &'a DistinguishedName,
+        serial: &'a [u8],
+        not_before: u64,
+        not_after: u64,
+    },
+}
+
+impl<'a> Tbs<'a> {
+    pub(crate) fn to_der(&self, algorithm: SignatureAlgorithm, public_key: &[u8]) -> Vec<u8> {
+        let spki = algorithm.subject_public_key_info(public_key);
+        match self {
+            Tbs::Csr { subject } => {
+                // version 1, and no attributes
+                constructed(TAG_SEQUENCE, &[integer(&[0]), name(subject), spki, vec![0xa0, 0x00]])
+            }
+            Tbs::Certificate {
+                subject,
+                serial,
+                not_before,
+                not_after,
+            } => {
+                // version 3, the issuer is the subject
+                let version = tlv(0xa0, &integer(&[2]));
+                let validity = constructed(TAG_SEQUENCE, &[time(*not_before), time(*not_after)]);
+                constructed(
+                    TAG_SEQUENCE,
+                    &[
+                        version,
+                        integer(serial),
+                        algorithm.identifier(),
+                        name(subject),
+                        validity,
+                        name(subject),
+                        spki,
+                    ],
+                )
+            }
+        }
+    }
+
+    fn pem_label(&self) -> &'static str {
+        match self {
+            Tbs::Csr { .. } => "CERTIFICATE REQUEST",
+            Tbs::Certificate { .. } => "CERTIFICATE",
+        }
+    }
+
+    /// Appends the `signature` over `tbs`, which is the raw Ed25519 signature or the DER encoded ECDSA signature
+    pub(crate) fn sign(
+        &self,
+        algorithm: SignatureAlgorithm,
+        tbs: Vec<u8>,
+        signature: &[u8],
+        encoding: CertificateEncoding,
+    ) -> Vec<u8> {
+        let der = constructed(TAG_SEQUENCE, &[tbs, algorithm.identifier(), bit_string(signature)]);
+        match encoding {
+            CertificateEncoding::Der => der,
+            CertificateEncoding::Pem => pem(self.pem_label(), &der).into_bytes(),
+        }
+    }
+}
+
+/// Encodes the fixed size ECDSA signature `r || s` as `Ecdsa-Sig-Value` of RFC 3279
+pub(crate) fn ecdsa_signature(signature: &[u8]) -> Vec<u8> {
+    let (r, s) = signature.split_at(signature.len() / 2);
+    constructed(TAG_SEQUENCE, &[integer(r), integer(s)])
+}
+
+fn pem(label: &str, der: &[u8]) -> String {
+    let mut base64 = Vec::with_capacity(der.len().div_ceil(3) * 4);
+    for chunk in der.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                base64.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize]);
+            } else {
+                base64.push(b'=');
+            }
+        }
+    }
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in base64.chunks(64) {
+        pem.push_str(&String::from_utf8_lossy(line));
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encodings() {
+        assert_eq!(oid(OID_EC_PUBLIC_KEY), hex::decode("06072a8648ce3d0201").unwrap());
+        assert_eq!(integer(&[0, 0, 0x80]), vec![0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(integer(&[0]), vec![0x02, 0x01, 0x00]);
+        assert_eq!(length(0x7f), vec![0x7f]);
+        assert_eq!(length(0x100), vec![0x82, 0x01, 0x00]);
+
+        assert_eq!(time(0), tlv(TAG_UTC_TIME, b"700101000000Z"));
+        assert_eq!(time(951_827_696), tlv(TAG_UTC_TIME, b"000229123456Z"));
+        assert_eq!(time(2_524_608_000), tlv(TAG_GENERALIZED_TIME, b"20500101000000Z"));
+
+        assert_eq!(
+            pem("TEST", b"foobar!"),
+            "-----BEGIN TEST-----\nZm9vYmFyIQ==\n-----END TEST-----\n"
+        );
+    }
+}
@@ -1,9 +1,13 @@
 // Copyright 2020-2022 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
+mod authenticator;
+mod backend;
 mod keyprovider;
 mod keystore;
 
 // re-export modules
+pub use authenticator::*;
+pub use backend::*;
 pub use keyprovider::KeyProvider;
 pub use keystore::KeyStore;
@@ -0,0 +1,27 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hardware authenticators, that answer the challenge in the header of a snapshot file with its key.
+//!
+//! Unlike a [`KeyBackend`](crate::KeyBackend), an authenticator doesn't unwrap a stored key, but derives
+//! the key from the challenge, so every unlock requires the authenticator, e.g. a touch of a security key.
+//! See [`Stronghold::commit_with_authenticator`](crate::Stronghold::commit_with_authenticator).
+//!
+//! With the `fido2` feature, [`Fido2`] derives the key with the `hmac-secret` extension of a FIDO2 security key.
+
+#[cfg(feature = "fido2")]
+mod fido2;
+
+#[cfg(feature = "fido2")]
+pub use fido2::Fido2;
+
+use engine::snapshot::challenge::SALT_SIZE;
+
+use crate::ClientError;
+
+/// An authenticator, that computes a secret from a salt with a credential, that can't be exported from it
+pub trait HmacSecret: Send + Sync {
+    /// Returns the secret for `salt` of the credential with `credential_id`. The caller zeroizes the returned
+    /// secret.
+    fn hmac_secret(&self, credential_id: &[u8], salt: &[u8; SALT_SIZE]) -> Result<[u8; 32], ClientError>;
+}
@@ -0,0 +1,82 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use ctap_hid_fido2::{
+    fidokey::{
+        get_assertion::Extension as AssertionExtension, make_credential::Extension as CredentialExtension,
+        GetAssertionArgsBuilder, MakeCredentialArgsBuilder,
+    },
+    Cfg, FidoKeyHid, FidoKeyHidFactory,
+};
+use engine::{snapshot::challenge::SALT_SIZE, vault::BoxProvider};
+
+use super::HmacSecret;
+use crate::{internal::Provider, ClientError};
+
+fn authenticator_error(e: impl ToString) -> ClientError {
+    ClientError::KeyBackend(e.to_string())
+}
+
+/// A FIDO2 security key, that derives the snapshot key with the `hmac-secret` extension.
+///
+/// The secret is bound to a credential of the relying party `rp_id`, that has been created with
+/// [`Fido2::make_credential`]. Every derivation requires the user to touch the security key.
+pub struct Fido2 {
+    device: FidoKeyHid,
+    rp_id: String,
+    pin: Option<String>,
+}
+
+impl Fido2 {
+    /// Connects to the first FIDO2 security key. `pin` is the PIN of the security key, if it has one.
+    pub fn new(rp_id: impl Into<String>, pin: Option<String>) -> Result<Self, ClientError> {
+        let device = FidoKeyHidFactory::create(&Cfg::init()).map_err(authenticator_error)?;
+        Ok(Self {
+            device,
+            rp_id: rp_id.into(),
+            pin,
+        })
+    }
+
+    /// Creates a new credential with the `hmac-secret` extension on the security key, and returns its id
+    pub fn make_credential(&self) -> Result<Vec<u8>, ClientError> {
+        let challenge = Provider::random_vec(32).map_err(|e| ClientError::Provider(format!("{:?}", e)))?;
+        let extensions = [CredentialExtension::HmacSecret(Some(true))];
+        let mut args = MakeCredentialArgsBuilder::new(&self.rp_id, &challenge).extensions(&extensions);
+        if let Some(pin) = &self.pin {
+            args = args.pin(pin);
+        }
+        let attestation = self
+            .device
+            .make_credential_with_args(&args.build())
+            .map_err(authenticator_error)?;
+        Ok(attestation.credential_descriptor.id)
+    }
+}
+
+impl HmacSecret for Fido2 {
+    fn hmac_secret(&self, credential_id: &[u8], salt: &[u8; SALT_SIZE]) -> Result<[u8; 32], ClientError> {
+        // the assertion itself is not verified, only the secret is used
+        let challenge = Provider::random_vec(32).map_err(|e| ClientError::Provider(format!("{:?}", e)))?;
+        let extensions = [AssertionExtension::HmacSecret(Some(*salt))];
+        let mut args = GetAssertionArgsBuilder::new(&self.rp_id, &challenge)
+            .credential_id(credential_id)
+            .extensions(&extensions);
+        if let Some(pin) = &self.pin {
+            args = args.pin(pin);
+        }
+        let assertions = self
+            .device
+            .get_assertion_with_args(&args.build())
+            .map_err(authenticator_error)?;
+
+        assertions
+            .into_iter()
+            .flat_map(|assertion| assertion.extensions)
+            .find_map(|extension| match extension {
+                AssertionExtension::HmacSecret(Some(secret)) => Some(secret),
+                _ => None,
+            })
+            .ok_or_else(|| ClientError::KeyBackend("Security key returned no hmac-secret".to_string()))
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hardware backends for a [`KeyProvider`](crate::KeyProvider).
+//!
+//! Instead of deriving the snapshot key from a password, a random snapshot key is wrapped, i.e. encrypted, with a
+//! key that never leaves a hardware key store of the device. Only the wrapped key is stored by the application,
+//! and the snapshot can only be opened on the device, that holds the hardware key. See
+//! [`KeyProvider::generate_with_backend`](crate::KeyProvider::generate_with_backend).
+//!
+//! The backends of the platforms are gated behind features:
+//! - `secure-enclave`: [`SecureEnclave`] on macOS and iOS
+//! - `android-keystore`: [`AndroidKeystore`] on Android, backed by StrongBox if the device has one
+//! - `pkcs11`: [`Pkcs11`] for hardware security modules and smart cards with a PKCS#11 module
+//! - `tpm`: [`Tpm`] on Linux and Windows, binds keys to the measured boot state of the machine
+
+#[cfg(all(feature = "android-keystore", target_os = "android"))]
+mod android;
+#[cfg(feature = "pkcs11")]
+mod pkcs11;
+#[cfg(all(feature = "secure-enclave", any(target_os = "macos", target_os = "ios")))]
+mod secure_enclave;
+#[cfg(all(feature = "tpm", any(target_os = "linux", target_os = "windows")))]
+mod tpm;
+
+#[cfg(all(feature = "android-keystore", target_os = "android"))]
+pub use android::AndroidKeystore;
+#[cfg(feature = "pkcs11")]
+pub use pkcs11::Pkcs11;
+#[cfg(all(feature = "secure-enclave", any(target_os = "macos", target_os = "ios")))]
+pub use secure_enclave::SecureEnclave;
+#[cfg(all(feature = "tpm", any(target_os = "linux", target_os = "windows")))]
+pub use tpm::Tpm;
+
+use crate::ClientError;
+
+/// A key store, that wraps and unwraps keys with a key, that can't be exported from it
+pub trait KeyBackend: Send + Sync {
+    /// Encrypts `key` with the key of the backend
+    fn wrap(&self, key: &[u8]) -> Result<Vec<u8>, ClientError>;
+
+    /// Decrypts a key, that has been encrypted with [`KeyBackend::wrap`]. The caller zeroizes the returned key.
+    fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, ClientError>;
+}
+
+impl<B: KeyBackend + ?Sized> KeyBackend for Box<B> {
+    fn wrap(&self, key: &[u8]) -> Result<Vec<u8>, ClientError> {
+        (**self).wrap(key)
+    }
+
+    fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, ClientError> {
+        (**self).unwrap(wrapped)
+    }
+}
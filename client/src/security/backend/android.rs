@@ -0,0 +1,235 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use jni::{
+    objects::{JByteArray, JObject, JValue},
+    JNIEnv, JavaVM,
+};
+
+use super::KeyBackend;
+use crate::ClientError;
+
+const ANDROID_KEYSTORE: &str = "AndroidKeyStore";
+const TRANSFORMATION: &str = "AES/GCM/NoPadding";
+
+// constants of `KeyProperties` and `Cipher`
+const PURPOSE_ENCRYPT: i32 = 1;
+const PURPOSE_DECRYPT: i32 = 2;
+const ENCRYPT_MODE: i32 = 1;
+const DECRYPT_MODE: i32 = 2;
+
+/// The length of the IV, that is prepended to a wrapped key
+const IV_LEN: usize = 12;
+/// The length of the authentication tag in bits
+const TAG_LEN: i32 = 128;
+
+const BUILDER: &str = "android/security/keystore/KeyGenParameterSpec$Builder";
+const BUILDER_RETURN: &str = "Landroid/security/keystore/KeyGenParameterSpec$Builder;";
+
+/// A [`KeyBackend`], that wraps keys with an AES-256-GCM key in the Android Keystore.
+///
+/// The key is created under `alias` on first use, and can't be exported from the keystore. With `strongbox`,
+/// the key is created in the StrongBox secure element of the device, and creating it fails on devices without
+/// one.
+pub struct AndroidKeystore {
+    vm: JavaVM,
+    alias: String,
+    strongbox: bool,
+}
+
+impl AndroidKeystore {
+    /// Creates a backend, that uses the key with `alias` in the Android Keystore. `vm` is the Java VM of the
+    /// application, e.g. as obtained in `JNI_OnLoad`.
+    pub fn new(vm: JavaVM, alias: impl Into<String>, strongbox: bool) -> Self {
+        Self {
+            vm,
+            alias: alias.into(),
+            strongbox,
+        }
+    }
+
+    /// Runs `f` on the current thread, that is attached to the Java VM
+    fn with_env<R, F>(&self, f: F) -> Result<R, ClientError>
+    where
+        F: FnOnce(&mut JNIEnv) -> jni::errors::Result<R>,
+    {
+        let mut env = self
+            .vm
+            .attach_current_thread()
+            .map_err(|e| ClientError::KeyBackend(e.to_string()))?;
+        let result = f(&mut env);
+        if env.exception_check().unwrap_or(false) {
+            let _ = env.exception_clear();
+        }
+        result.map_err(|e| ClientError::KeyBackend(e.to_string()))
+    }
+
+    /// Returns the key with the alias of this backend, and creates it, if it doesn't exist yet
+    fn key<'local>(&self, env: &mut JNIEnv<'local>) -> jni::errors::Result<JObject<'local>> {
+        let provider = env.new_string(ANDROID_KEYSTORE)?;
+        let keystore = env
+            .call_static_method(
+                "java/security/KeyStore",
+                "getInstance",
+                "(Ljava/lang/String;)Ljava/security/KeyStore;",
+                &[JValue::Object(&provider)],
+            )?
+            .l()?;
+        env.call_method(
+            &keystore,
+            "load",
+            "(Ljava/security/KeyStore$LoadStoreParameter;)V",
+            &[JValue::Object(&JObject::null())],
+        )?;
+
+        let alias = env.new_string(&self.alias)?;
+        let key = env
+            .call_method(
+                &keystore,
+                "getKey",
+                "(Ljava/lang/String;[C)Ljava/security/Key;",
+                &[JValue::Object(&alias), JValue::Object(&JObject::null())],
+            )?
+            .l()?;
+        if !key.is_null() {
+            return Ok(key);
+        }
+
+        let builder = env.new_object(
+            BUILDER,
+            "(Ljava/lang/String;I)V",
+            &[JValue::Object(&alias), JValue::Int(PURPOSE_ENCRYPT | PURPOSE_DECRYPT)],
+        )?;
+        let mode = env.new_string("GCM")?;
+        let modes = env.new_object_array(1, "java/lang/String", &mode)?;
+        env.call_method(
+            &builder,
+            "setBlockModes",
+            format!("([Ljava/lang/String;){}", BUILDER_RETURN),
+            &[JValue::Object(&modes)],
+        )?;
+        let padding = env.new_string("NoPadding")?;
+        let paddings = env.new_object_array(1, "java/lang/String", &padding)?;
+        env.call_method(
+            &builder,
+            "setEncryptionPaddings",
+            format!("([Ljava/lang/String;){}", BUILDER_RETURN),
+            &[JValue::Object(&paddings)],
+        )?;
+        env.call_method(
+            &builder,
+            "setKeySize",
+            format!("(I){}", BUILDER_RETURN),
+            &[JValue::Int(256)],
+        )?;
+        if self.strongbox {
+            env.call_method(
+                &builder,
+                "setIsStrongBoxBacked",
+                format!("(Z){}", BUILDER_RETURN),
+                &[JValue::Bool(1)],
+            )?;
+        }
+        let spec = env
+            .call_method(
+                &builder,
+                "build",
+                "()Landroid/security/keystore/KeyGenParameterSpec;",
+                &[],
+            )?
+            .l()?;
+
+        let algorithm = env.new_string("AES")?;
+        let generator = env
+            .call_static_method(
+                "javax/crypto/KeyGenerator",
+                "getInstance",
+                "(Ljava/lang/String;Ljava/lang/String;)Ljavax/crypto/KeyGenerator;",
+                &[JValue::Object(&algorithm), JValue::Object(&provider)],
+            )?
+            .l()?;
+        env.call_method(
+            &generator,
+            "init",
+            "(Ljava/security/spec/AlgorithmParameterSpec;)V",
+            &[JValue::Object(&spec)],
+        )?;
+        env.call_method(&generator, "generateKey", "()Ljavax/crypto/SecretKey;", &[])?
+            .l()
+    }
+
+    fn cipher<'local>(&self, env: &mut JNIEnv<'local>) -> jni::errors::Result<JObject<'local>> {
+        let transformation = env.new_string(TRANSFORMATION)?;
+        env.call_static_method(
+            "javax/crypto/Cipher",
+            "getInstance",
+            "(Ljava/lang/String;)Ljavax/crypto/Cipher;",
+            &[JValue::Object(&transformation)],
+        )?
+        .l()
+    }
+}
+
+impl KeyBackend for AndroidKeystore {
+    fn wrap(&self, key: &[u8]) -> Result<Vec<u8>, ClientError> {
+        self.with_env(|env| {
+            let secret_key = self.key(env)?;
+            let cipher = self.cipher(env)?;
+            env.call_method(
+                &cipher,
+                "init",
+                "(ILjava/security/Key;)V",
+                &[JValue::Int(ENCRYPT_MODE), JValue::Object(&secret_key)],
+            )?;
+            // the keystore chooses a random IV, it is stored in front of the ciphertext
+            let iv = JByteArray::from(env.call_method(&cipher, "getIV", "()[B", &[])?.l()?);
+            let input = env.byte_array_from_slice(key)?;
+            let ciphertext = JByteArray::from(
+                env.call_method(&cipher, "doFinal", "([B)[B", &[JValue::Object(&input)])?
+                    .l()?,
+            );
+
+            // the copy of the key on the Java heap is wiped, the garbage collector may have moved it before
+            env.set_byte_array_region(&input, 0, &vec![0; key.len()])?;
+
+            let mut wrapped = env.convert_byte_array(&iv)?;
+            wrapped.extend(env.convert_byte_array(&ciphertext)?);
+            Ok(wrapped)
+        })
+    }
+
+    fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, ClientError> {
+        if wrapped.len() <= IV_LEN {
+            return Err(ClientError::KeyBackend("Wrapped key is too short".to_string()));
+        }
+        let (iv, ciphertext) = wrapped.split_at(IV_LEN);
+        self.with_env(|env| {
+            let secret_key = self.key(env)?;
+            let cipher = self.cipher(env)?;
+            let iv = env.byte_array_from_slice(iv)?;
+            let spec = env.new_object(
+                "javax/crypto/spec/GCMParameterSpec",
+                "(I[B)V",
+                &[JValue::Int(TAG_LEN), JValue::Object(&iv)],
+            )?;
+            env.call_method(
+                &cipher,
+                "init",
+                "(ILjava/security/Key;Ljava/security/spec/AlgorithmParameterSpec;)V",
+                &[
+                    JValue::Int(DECRYPT_MODE),
+                    JValue::Object(&secret_key),
+                    JValue::Object(&spec),
+                ],
+            )?;
+            let input = env.byte_array_from_slice(ciphertext)?;
+            let key = JByteArray::from(
+                env.call_method(&cipher, "doFinal", "([B)[B", &[JValue::Object(&input)])?
+                    .l()?,
+            );
+            let unwrapped = env.convert_byte_array(&key)?;
+            env.set_byte_array_region(&key, 0, &vec![0; unwrapped.len()])?;
+            Ok(unwrapped)
+        })
+    }
+}
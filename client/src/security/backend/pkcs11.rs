@@ -0,0 +1,202 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use std::path::Path;
+
+use cryptoki::{
+    context::{CInitializeArgs, Pkcs11 as Context},
+    error::{Error, RvError},
+    mechanism::{aead::GcmParams, Mechanism},
+    object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle},
+    session::{Session, UserType},
+    slot::Slot,
+    types::AuthPin,
+};
+use zeroize::Zeroize;
+
+use super::KeyBackend;
+use crate::ClientError;
+
+/// The label of the AES key on the token, that wraps the snapshot keys
+const WRAPPING_KEY_LABEL: &str = "stronghold wrapping key";
+
+/// The label of the data object on the token, that holds the wrapped snapshot key of
+/// [`KeyProvider::pkcs11`](crate::KeyProvider::pkcs11)
+pub(crate) const SNAPSHOT_KEY_LABEL: &str = "stronghold snapshot key";
+
+const IV_LEN: usize = 12;
+const TAG_BITS: u64 = 128;
+
+/// Obtains the user PIN of the token, e.g. by asking the user for it
+type PinCallback = dyn Fn() -> Result<String, ClientError> + Send + Sync;
+
+/// A [`KeyBackend`], that wraps keys with an AES-256 key on a PKCS#11 token, e.g. a hardware security module.
+///
+/// The wrapping key is created on the token on first use. It is sensitive and not extractable, so keys can only
+/// be unwrapped by the token. Every operation logs into the token with the PIN, that `pin_callback` returns.
+pub struct Pkcs11 {
+    context: Context,
+    slot: Slot,
+    pin_callback: Box<PinCallback>,
+}
+
+fn backend_error(e: Error) -> ClientError {
+    ClientError::KeyBackend(e.to_string())
+}
+
+impl Pkcs11 {
+    /// Loads the PKCS#11 module at `module_path`, and uses the token in the slot with the id `slot`
+    pub fn new<P, F>(module_path: P, slot: u64, pin_callback: F) -> Result<Self, ClientError>
+    where
+        P: AsRef<Path>,
+        F: Fn() -> Result<String, ClientError> + Send + Sync + 'static,
+    {
+        let context = Context::new(module_path).map_err(backend_error)?;
+        match context.initialize(CInitializeArgs::OsThreads) {
+            // the module may already have been initialized by another backend in this process
+            Ok(()) | Err(Error::AlreadyInitialized) => {}
+            Err(e) => return Err(backend_error(e)),
+        }
+        let slot = context
+            .get_slots_with_token()
+            .map_err(backend_error)?
+            .into_iter()
+            .find(|candidate| candidate.id() == slot)
+            .ok_or_else(|| ClientError::KeyBackend(format!("No token in slot {}", slot)))?;
+
+        Ok(Self {
+            context,
+            slot,
+            pin_callback: Box::new(pin_callback),
+        })
+    }
+
+    /// Opens a session and logs into the token
+    pub(crate) fn session(&self) -> Result<Session, ClientError> {
+        let session = self.context.open_rw_session(self.slot).map_err(backend_error)?;
+        let pin = AuthPin::new((self.pin_callback)()?);
+        match session.login(UserType::User, Some(&pin)) {
+            Ok(()) | Err(Error::Pkcs11(RvError::UserAlreadyLoggedIn)) => Ok(session),
+            Err(e) => Err(backend_error(e)),
+        }
+    }
+
+    /// Returns the wrapping key, and creates it, if it doesn't exist yet
+    fn wrapping_key(&self, session: &Session) -> Result<ObjectHandle, ClientError> {
+        let template = [
+            Attribute::Class(ObjectClass::SECRET_KEY),
+            Attribute::Label(WRAPPING_KEY_LABEL.as_bytes().to_vec()),
+        ];
+        if let Some(key) = session.find_objects(&template).map_err(backend_error)?.first() {
+            return Ok(*key);
+        }
+
+        session
+            .generate_key(
+                &Mechanism::AesKeyGen,
+                &[
+                    Attribute::Class(ObjectClass::SECRET_KEY),
+                    Attribute::KeyType(KeyType::AES),
+                    Attribute::ValueLen(32.into()),
+                    Attribute::Label(WRAPPING_KEY_LABEL.as_bytes().to_vec()),
+                    Attribute::Token(true),
+                    Attribute::Private(true),
+                    Attribute::Sensitive(true),
+                    Attribute::Extractable(false),
+                    Attribute::Encrypt(true),
+                    Attribute::Decrypt(true),
+                ],
+            )
+            .map_err(backend_error)
+    }
+
+    /// Returns `len` random bytes from the random number generator of the token
+    pub(crate) fn random(&self, session: &Session, len: usize) -> Result<Vec<u8>, ClientError> {
+        session.generate_random_vec(len as u32).map_err(backend_error)
+    }
+
+    /// Returns the value of the data object with `label`, or [`None`] if there is none
+    pub(crate) fn find_data(&self, session: &Session, label: &str) -> Result<Option<Vec<u8>>, ClientError> {
+        let template = [
+            Attribute::Class(ObjectClass::DATA),
+            Attribute::Label(label.as_bytes().to_vec()),
+        ];
+        let object = match session.find_objects(&template).map_err(backend_error)?.first() {
+            Some(object) => *object,
+            None => return Ok(None),
+        };
+        let attributes = session
+            .get_attributes(object, &[AttributeType::Value])
+            .map_err(backend_error)?;
+        Ok(attributes.into_iter().find_map(|attribute| match attribute {
+            Attribute::Value(value) => Some(value),
+            _ => None,
+        }))
+    }
+
+    /// Stores `value` in a new private data object with `label` on the token
+    pub(crate) fn store_data(&self, session: &Session, label: &str, value: Vec<u8>) -> Result<(), ClientError> {
+        session
+            .create_object(&[
+                Attribute::Class(ObjectClass::DATA),
+                Attribute::Label(label.as_bytes().to_vec()),
+                Attribute::Token(true),
+                Attribute::Private(true),
+                Attribute::Value(value),
+            ])
+            .map(|_| ())
+            .map_err(backend_error)
+    }
+
+    pub(crate) fn wrap_with(&self, session: &Session, key: &[u8]) -> Result<Vec<u8>, ClientError> {
+        let wrapping_key = self.wrapping_key(session)?;
+        let mut iv = self.random(session, IV_LEN)?;
+        let mut wrapped = iv.clone();
+        let mechanism = Mechanism::AesGcm(GcmParams::new(&mut iv, &[], TAG_BITS.into()));
+        wrapped.extend(session.encrypt(&mechanism, wrapping_key, key).map_err(backend_error)?);
+        Ok(wrapped)
+    }
+
+    pub(crate) fn unwrap_with(&self, session: &Session, wrapped: &[u8]) -> Result<Vec<u8>, ClientError> {
+        if wrapped.len() <= IV_LEN {
+            return Err(ClientError::KeyBackend("Wrapped key is too short".to_string()));
+        }
+        let wrapping_key = self.wrapping_key(session)?;
+        let mut iv = wrapped[..IV_LEN].to_vec();
+        let mechanism = Mechanism::AesGcm(GcmParams::new(&mut iv, &[], TAG_BITS.into()));
+        session
+            .decrypt(&mechanism, wrapping_key, &wrapped[IV_LEN..])
+            .map_err(backend_error)
+    }
+
+    /// Returns the snapshot key, that is stored wrapped on the token, and generates it with the random number
+    /// generator of the token, if there is none yet
+    pub(crate) fn snapshot_key(&self) -> Result<Vec<u8>, ClientError> {
+        let session = self.session()?;
+        if let Some(wrapped) = self.find_data(&session, SNAPSHOT_KEY_LABEL)? {
+            return self.unwrap_with(&session, &wrapped);
+        }
+
+        let mut key = self.random(&session, 32)?;
+        let stored = self
+            .wrap_with(&session, &key)
+            .and_then(|wrapped| self.store_data(&session, SNAPSHOT_KEY_LABEL, wrapped));
+        if let Err(e) = stored {
+            key.zeroize();
+            return Err(e);
+        }
+        Ok(key)
+    }
+}
+
+impl KeyBackend for Pkcs11 {
+    fn wrap(&self, key: &[u8]) -> Result<Vec<u8>, ClientError> {
+        let session = self.session()?;
+        self.wrap_with(&session, key)
+    }
+
+    fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, ClientError> {
+        let session = self.session()?;
+        self.unwrap_with(&session, wrapped)
+    }
+}
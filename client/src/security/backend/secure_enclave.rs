@@ -0,0 +1,161 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use core_foundation::{
+    base::{CFType, CFTypeRef, TCFType},
+    boolean::CFBoolean,
+    data::CFData,
+    dictionary::CFDictionary,
+    number::CFNumber,
+    string::{CFString, CFStringRef},
+};
+use security_framework::key::{Algorithm, SecKey};
+use security_framework_sys::{
+    base::errSecItemNotFound,
+    item::{
+        kSecAttrIsPermanent, kSecAttrKeyClass, kSecAttrKeyClassPrivate, kSecAttrKeySizeInBits, kSecAttrKeyType,
+        kSecAttrKeyTypeECSECPrimeRandom, kSecAttrLabel, kSecAttrTokenID, kSecAttrTokenIDSecureEnclave, kSecClass,
+        kSecClassKey, kSecPrivateKeyAttrs, kSecReturnRef, kSecUseDataProtectionKeychain,
+    },
+    keychain_item::SecItemCopyMatching,
+};
+
+use super::KeyBackend;
+use crate::ClientError;
+
+#[link(name = "Security", kind = "framework")]
+extern "C" {
+    // not exported by security-framework-sys
+    static kSecAttrApplicationTag: CFStringRef;
+}
+
+/// The algorithm, that keys are wrapped with: ECIES with the P-256 key of the Secure Enclave and AES-GCM
+const ALGORITHM: Algorithm = Algorithm::ECIESEncryptionCofactorVariableIVX963SHA256AESGCM;
+
+/// A [`KeyBackend`], that wraps keys with a P-256 key in the Secure Enclave of Apple devices.
+///
+/// The private key is created in the Secure Enclave on first use and stored permanently in the data
+/// protection keychain, with `label` as its application tag. It can't be exported from the Secure Enclave.
+/// Wrapping only needs the public key, unwrapping is performed by the Secure Enclave.
+pub struct SecureEnclave {
+    label: String,
+}
+
+impl SecureEnclave {
+    /// Creates a backend, that uses the key with the application tag `label` in the Secure Enclave
+    pub fn new(label: impl Into<String>) -> Self {
+        Self { label: label.into() }
+    }
+
+    fn tag(&self) -> CFData {
+        CFData::from_buffer(self.label.as_bytes())
+    }
+
+    /// Returns the private key with the tag of this backend, and creates it, if it doesn't exist yet
+    fn private_key(&self) -> Result<SecKey, ClientError> {
+        match self.find_key()? {
+            Some(key) => Ok(key),
+            None => self.create_key(),
+        }
+    }
+
+    /// Looks up the private key by its application tag in the keychain
+    fn find_key(&self) -> Result<Option<SecKey>, ClientError> {
+        let query = unsafe {
+            CFDictionary::from_CFType_pairs(&[
+                (
+                    CFString::wrap_under_get_rule(kSecClass),
+                    CFType::wrap_under_get_rule(kSecClassKey as CFTypeRef),
+                ),
+                (
+                    CFString::wrap_under_get_rule(kSecAttrKeyClass),
+                    CFType::wrap_under_get_rule(kSecAttrKeyClassPrivate as CFTypeRef),
+                ),
+                (
+                    CFString::wrap_under_get_rule(kSecAttrApplicationTag),
+                    self.tag().as_CFType(),
+                ),
+                (
+                    CFString::wrap_under_get_rule(kSecUseDataProtectionKeychain),
+                    CFBoolean::true_value().as_CFType(),
+                ),
+                (
+                    CFString::wrap_under_get_rule(kSecReturnRef),
+                    CFBoolean::true_value().as_CFType(),
+                ),
+            ])
+        };
+
+        let mut result: CFTypeRef = std::ptr::null();
+        let status = unsafe { SecItemCopyMatching(query.as_concrete_TypeRef(), &mut result) };
+        match status {
+            0 if !result.is_null() => Ok(Some(unsafe { SecKey::wrap_under_create_rule(result as _) })),
+            0 | errSecItemNotFound => Ok(None),
+            status => Err(ClientError::KeyBackend(format!(
+                "Keychain lookup failed with status {}",
+                status
+            ))),
+        }
+    }
+
+    /// Creates the private key in the Secure Enclave and stores it permanently in the keychain
+    fn create_key(&self) -> Result<SecKey, ClientError> {
+        let attributes = unsafe {
+            let private_attributes = CFDictionary::from_CFType_pairs(&[
+                (
+                    CFString::wrap_under_get_rule(kSecAttrIsPermanent),
+                    CFBoolean::true_value().as_CFType(),
+                ),
+                (
+                    CFString::wrap_under_get_rule(kSecAttrApplicationTag),
+                    self.tag().as_CFType(),
+                ),
+            ]);
+            CFDictionary::from_CFType_pairs(&[
+                (
+                    CFString::wrap_under_get_rule(kSecAttrKeyType),
+                    CFType::wrap_under_get_rule(kSecAttrKeyTypeECSECPrimeRandom as CFTypeRef),
+                ),
+                (
+                    CFString::wrap_under_get_rule(kSecAttrKeySizeInBits),
+                    CFNumber::from(256).as_CFType(),
+                ),
+                (
+                    CFString::wrap_under_get_rule(kSecAttrTokenID),
+                    CFType::wrap_under_get_rule(kSecAttrTokenIDSecureEnclave as CFTypeRef),
+                ),
+                (
+                    CFString::wrap_under_get_rule(kSecAttrLabel),
+                    CFString::new(&self.label).as_CFType(),
+                ),
+                (
+                    CFString::wrap_under_get_rule(kSecUseDataProtectionKeychain),
+                    CFBoolean::true_value().as_CFType(),
+                ),
+                (
+                    CFString::wrap_under_get_rule(kSecPrivateKeyAttrs),
+                    private_attributes.as_CFType(),
+                ),
+            ])
+        };
+        SecKey::generate(attributes).map_err(|e| ClientError::KeyBackend(e.to_string()))
+    }
+}
+
+impl KeyBackend for SecureEnclave {
+    fn wrap(&self, key: &[u8]) -> Result<Vec<u8>, ClientError> {
+        let public_key = self
+            .private_key()?
+            .public_key()
+            .ok_or_else(|| ClientError::KeyBackend("Secure Enclave key has no public key".to_string()))?;
+        public_key
+            .encrypt_data(ALGORITHM, key)
+            .map_err(|e| ClientError::KeyBackend(e.to_string()))
+    }
+
+    fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, ClientError> {
+        self.private_key()?
+            .decrypt_data(ALGORITHM, wrapped)
+            .map_err(|e| ClientError::KeyBackend(e.to_string()))
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{convert::TryFrom, str::FromStr};
+
+use tss_esapi::{
+    attributes::ObjectAttributesBuilder,
+    constants::{response_code::Tss2ResponseCodeKind, SessionType},
+    handles::{KeyHandle, ObjectHandle},
+    interface_types::{
+        algorithm::{HashingAlgorithm, PublicAlgorithm},
+        key_bits::RsaKeyBits,
+        resource_handles::Hierarchy,
+        session_handles::{AuthSession, PolicySession},
+    },
+    structures::{
+        Digest, KeyedHashScheme, PcrSelectionList, PcrSelectionListBuilder, PcrSlot, Private, Public, PublicBuilder,
+        PublicKeyedHashParameters, RsaExponent, SensitiveData, SymmetricDefinition, SymmetricDefinitionObject,
+    },
+    tcti_ldr::TctiNameConf,
+    traits::{Marshall, UnMarshall},
+    utils::create_restricted_decryption_rsa_public,
+    Context, Error,
+};
+
+use super::KeyBackend;
+use crate::ClientError;
+
+fn backend_error(e: Error) -> ClientError {
+    ClientError::KeyBackend(e.to_string())
+}
+
+/// A [`KeyBackend`], that seals keys with the TPM 2.0 of the machine against the values of a set of PCRs.
+///
+/// A sealed key is bound to the TPM and can only be unsealed, while the selected PCRs hold the same values as
+/// when the key was sealed, i.e. after the same measured boot. Once they changed, e.g. after an update of the
+/// firmware or the boot loader, unsealing fails with [`ClientError::PcrMismatch`] and the key has to be
+/// recovered by other means, see [`KeyProvider::with_backend_fallback`](crate::KeyProvider::with_backend_fallback).
+///
+/// The keys are sealed under a primary key in the owner hierarchy, that the TPM derives again for every
+/// operation, so nothing has to be persisted in the TPM.
+pub struct Tpm {
+    tcti: TctiNameConf,
+    pcrs: PcrSelectionList,
+}
+
+impl Tpm {
+    /// Creates a backend, that accesses the TPM through `tcti`, e.g. `device:/dev/tpmrm0` or `tbs` on Windows,
+    /// and binds keys to the SHA-256 bank of the PCRs with the indices `pcrs`
+    pub fn new(tcti: &str, pcrs: &[u8]) -> Result<Self, ClientError> {
+        let tcti = TctiNameConf::from_str(tcti).map_err(backend_error)?;
+        Self::with_tcti(tcti, pcrs)
+    }
+
+    /// Same as [`Tpm::new`], but the TCTI is taken from the `TPM2TOOLS_TCTI` environment variable
+    pub fn from_environment(pcrs: &[u8]) -> Result<Self, ClientError> {
+        let tcti = TctiNameConf::from_environment_variable().map_err(backend_error)?;
+        Self::with_tcti(tcti, pcrs)
+    }
+
+    fn with_tcti(tcti: TctiNameConf, pcrs: &[u8]) -> Result<Self, ClientError> {
+        let slots = pcrs
+            .iter()
+            .map(|index| {
+                1u32.checked_shl(*index as u32)
+                    .and_then(|mask| PcrSlot::try_from(mask).ok())
+                    .ok_or_else(|| ClientError::KeyBackend(format!("Invalid PCR index {}", index)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let pcrs = PcrSelectionListBuilder::new()
+            .with_selection(HashingAlgorithm::Sha256, &slots)
+            .build()
+            .map_err(backend_error)?;
+        Ok(Self { tcti, pcrs })
+    }
+
+    fn context(&self) -> Result<Context, ClientError> {
+        Context::new(self.tcti.clone()).map_err(backend_error)
+    }
+
+    fn primary_key(&self, context: &mut Context) -> Result<KeyHandle, ClientError> {
+        let public = create_restricted_decryption_rsa_public(
+            SymmetricDefinitionObject::AES_128_CFB,
+            RsaKeyBits::Rsa2048,
+            RsaExponent::default(),
+        )
+        .map_err(backend_error)?;
+        context
+            .execute_with_nullauth_session(|context| {
+                context.create_primary(Hierarchy::Owner, public, None, None, None, None)
+            })
+            .map(|primary| primary.key_handle)
+            .map_err(backend_error)
+    }
+
+    /// Starts a session, whose policy requires the current values of the selected PCRs. A trial session only
+    /// computes the digest of the policy.
+    fn pcr_policy(&self, context: &mut Context, session_type: SessionType) -> Result<AuthSession, ClientError> {
+        let session = context
+            .start_auth_session(
+                None,
+                None,
+                None,
+                session_type,
+                SymmetricDefinition::AES_128_CFB,
+                HashingAlgorithm::Sha256,
+            )
+            .map_err(backend_error)?
+            .ok_or_else(|| ClientError::KeyBackend("TPM returned no session".to_string()))?;
+        let policy = PolicySession::try_from(session).map_err(backend_error)?;
+        context
+            .policy_pcr(policy, Digest::default(), self.pcrs.clone())
+            .map_err(backend_error)?;
+        Ok(session)
+    }
+
+    fn seal(&self, context: &mut Context, primary: KeyHandle, key: &[u8]) -> Result<Vec<u8>, ClientError> {
+        let trial = self.pcr_policy(context, SessionType::Trial)?;
+        let digest = context.policy_get_digest(PolicySession::try_from(trial).map_err(backend_error)?);
+        let _ = context.flush_context(ObjectHandle::from(trial));
+        let digest = digest.map_err(backend_error)?;
+
+        // without `user_with_auth`, the object can only be unsealed with the policy
+        let attributes = ObjectAttributesBuilder::new()
+            .with_fixed_tpm(true)
+            .with_fixed_parent(true)
+            .with_no_da(true)
+            .build()
+            .map_err(backend_error)?;
+        let public = PublicBuilder::new()
+            .with_public_algorithm(PublicAlgorithm::KeyedHash)
+            .with_name_hashing_algorithm(HashingAlgorithm::Sha256)
+            .with_object_attributes(attributes)
+            .with_auth_policy(digest)
+            .with_keyed_hash_parameters(PublicKeyedHashParameters::new(KeyedHashScheme::Null))
+            .with_keyed_hash_unique_identifier(Digest::default())
+            .build()
+            .map_err(backend_error)?;
+        let sensitive = SensitiveData::try_from(key.to_vec()).map_err(backend_error)?;
+        let sealed = context
+            .execute_with_nullauth_session(|context| context.create(primary, public, None, Some(sensitive), None, None))
+            .map_err(backend_error)?;
+
+        // the public and private area of the sealed object, the public area is prefixed with its length
+        let public = sealed.out_public.marshall().map_err(backend_error)?;
+        let mut wrapped = (public.len() as u16).to_be_bytes().to_vec();
+        wrapped.extend(public);
+        wrapped.extend(sealed.out_private.value());
+        Ok(wrapped)
+    }
+
+    fn unseal(&self, context: &mut Context, primary: KeyHandle, wrapped: &[u8]) -> Result<Vec<u8>, ClientError> {
+        let malformed = || ClientError::KeyBackend("Sealed key is malformed".to_string());
+        if wrapped.len() < 2 {
+            return Err(malformed());
+        }
+        let public_len = u16::from_be_bytes([wrapped[0], wrapped[1]]) as usize;
+        if wrapped.len() < 2 + public_len {
+            return Err(malformed());
+        }
+        let public = Public::unmarshall(&wrapped[2..2 + public_len]).map_err(|_| malformed())?;
+        let private = Private::try_from(wrapped[2 + public_len..].to_vec()).map_err(|_| malformed())?;
+
+        let object = context
+            .execute_with_nullauth_session(|context| context.load(primary, private, public))
+            .map_err(backend_error)?;
+        let session = self.pcr_policy(context, SessionType::Policy);
+        let unsealed = session.and_then(|session| {
+            let unsealed = context.execute_with_session(Some(session), |context| context.unseal(object.into()));
+            let _ = context.flush_context(ObjectHandle::from(session));
+            unsealed.map_err(|e| match e {
+                Error::Tss2Error(code) if code.kind() == Some(Tss2ResponseCodeKind::PolicyFail) => {
+                    ClientError::PcrMismatch
+                }
+                e => backend_error(e),
+            })
+        });
+        let _ = context.flush_context(object.into());
+
+        Ok(unsealed?.value().to_vec())
+    }
+}
+
+impl KeyBackend for Tpm {
+    fn wrap(&self, key: &[u8]) -> Result<Vec<u8>, ClientError> {
+        let mut context = self.context()?;
+        let primary = self.primary_key(&mut context)?;
+        let sealed = self.seal(&mut context, primary, key);
+        let _ = context.flush_context(primary.into());
+        sealed
+    }
+
+    fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, ClientError> {
+        let mut context = self.context()?;
+        let primary = self.primary_key(&mut context)?;
+        let unsealed = self.unseal(&mut context, primary, wrapped);
+        let _ = context.flush_context(primary.into());
+        unsealed
+    }
+}
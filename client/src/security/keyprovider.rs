@@ -8,23 +8,62 @@ use engine::{
         memories::buffer::{Buffer, Ref},
         Bytes, MemoryError,
     },
-    vault::NCKey,
+    snapshot::{Phase, Progress},
+    time::{Duration, Instant},
+    vault::{BoxProvider, NCKey},
 };
-use std::ops::Deref;
+use std::{ops::Deref, sync::Mutex};
 use stronghold_utils::GuardDebug;
 use zeroize::Zeroize;
 
-use crate::{internal::Provider, ClientError};
+use crate::{internal::Provider, ClientError, KeyBackend};
 
 /// This constant will be used to truncate a supplied passphrase
 const KEY_SIZE_HASHED: usize = 32;
 
+/// Obtains the key of an interactive [`KeyProvider`] on demand, e.g. by asking the user for the password
+type Prompt = dyn Fn() -> Result<KeyProvider, ClientError> + Send + Sync;
+
 /// The [`KeyProvider`] keeps secrets in [`NCKey`] at rest,
 /// such that no key can be directly read out from memory. The memory fragments
 /// of the key provider will be rotated continuously while not in use.
 #[derive(GuardDebug)]
 pub struct KeyProvider {
-    inner: engine::vault::NCKey<Provider>,
+    inner: Inner,
+    associated_data: Vec<u8>,
+}
+
+enum Inner {
+    Static(NCKey<Provider>),
+    Interactive(Interactive),
+}
+
+/// A key that is obtained from the application and cached for a limited time
+struct Interactive {
+    prompt: Box<Prompt>,
+    timeout: Duration,
+    cached: Mutex<Option<(Box<KeyProvider>, Instant)>>,
+}
+
+impl Interactive {
+    fn unlock(&self) -> Result<Buffer<u8>, MemoryError> {
+        let mut cached = self.cached.lock().map_err(|_| MemoryError::LockNotAvailable)?;
+        let (provider, obtained_at) = match cached.take() {
+            Some((provider, obtained_at)) if obtained_at.elapsed() < self.timeout => (provider, obtained_at),
+            _ => {
+                let provider = (self.prompt)().map_err(|e| MemoryError::Operation(e.to_string()))?;
+                if matches!(provider.inner, Inner::Interactive(_)) {
+                    return Err(MemoryError::Operation(
+                        "Prompt must return a non-interactive key provider".to_string(),
+                    ));
+                }
+                (Box::new(provider), Instant::now())
+            }
+        };
+        let buffer = provider.try_unlock();
+        *cached = Some((provider, obtained_at));
+        buffer
+    }
 }
 
 impl TryFrom<Vec<u8>> for KeyProvider {
@@ -32,7 +71,10 @@ impl TryFrom<Vec<u8>> for KeyProvider {
 
     fn try_from(data: Vec<u8>) -> Result<Self, MemoryError> {
         match NCKey::load(data) {
-            Some(inner) => Ok(Self { inner }),
+            Some(inner) => Ok(Self {
+                inner: Inner::Static(inner),
+                associated_data: Vec::new(),
+            }),
             None => Err(MemoryError::NCSizeNotAllowed),
         }
     }
@@ -273,11 +315,249 @@ impl KeyProvider {
 
         result
     }
+
+    /// Same as [`Self::with_passphrase_hashed_argon2`], but reports the start and end of the key
+    /// derivation as [`Phase::Kdf`] to `progress`. This is intended to be used together with
+    /// [`crate::Stronghold::load_snapshot_with_progress`].
+    pub fn with_passphrase_hashed_argon2_progress<P>(
+        passphrase: P,
+        salt: P,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<Self, ClientError>
+    where
+        P: AsRef<[u8]> + Zeroize,
+    {
+        let total = passphrase.as_ref().len() as u64;
+        Progress::bracket(Phase::Kdf, total, progress, || {
+            Self::with_passphrase_hashed_argon2(passphrase, salt)
+        })
+    }
 }
 
 impl KeyProvider {
+    /// Creates an interactive [`KeyProvider`], that calls `prompt` to obtain the key whenever it is needed
+    /// and the previously obtained key is older than `timeout`. Instead of failing an operation once the
+    /// cached key has expired, the application is asked for the password again.
+    ///
+    /// `prompt` returns the key as another [`KeyProvider`], so the password can be hashed with any of the
+    /// constructors above. It is called from the thread that needs the key and may block until the user
+    /// has entered the password.
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::KeyProvider;
+    /// use std::time::Duration;
+    ///
+    /// let keyprovider = KeyProvider::interactive(Duration::from_secs(300), || {
+    ///     // ask the user for the password
+    ///     let password = b"password".to_vec();
+    ///     KeyProvider::with_passphrase_hashed_blake2b(password)
+    /// });
+    /// assert!(keyprovider.try_unlock().is_ok());
+    /// ```
+    pub fn interactive<F>(timeout: Duration, prompt: F) -> Self
+    where
+        F: Fn() -> Result<KeyProvider, ClientError> + Send + Sync + 'static,
+    {
+        Self {
+            inner: Inner::Interactive(Interactive {
+                prompt: Box::new(prompt),
+                timeout,
+                cached: Mutex::new(None),
+            }),
+            associated_data: Vec::new(),
+        }
+    }
+
+    /// Creates a [`KeyProvider`] with a new random key, that is wrapped by the hardware key store `backend`.
+    /// Returns the key provider and the wrapped key, that the application has to store, e.g. next to the
+    /// snapshot file, to create the key provider again with [`Self::with_backend`].
+    ///
+    /// The unwrapped key is cached for `timeout`, like the key of an [`Self::interactive`] key provider.
+    /// The wrapped key is unwrapped once before it is returned, so that a backend, that can't restore the
+    /// key, fails here and not when the snapshot is opened the next time.
+    pub fn generate_with_backend<B>(backend: B, timeout: Duration) -> Result<(Self, Vec<u8>), ClientError>
+    where
+        B: KeyBackend + 'static,
+    {
+        let mut key = Provider::random_vec(KEY_SIZE_HASHED).map_err(|e| ClientError::Provider(format!("{:?}", e)))?;
+        let wrapped = backend.wrap(&key).and_then(|wrapped| {
+            let mut unwrapped = backend.unwrap(&wrapped)?;
+            let restored = unwrapped == key;
+            unwrapped.zeroize();
+            if !restored {
+                return Err(ClientError::KeyBackend(
+                    "The wrapped key can't be restored by the backend".to_string(),
+                ));
+            }
+            Ok(wrapped)
+        });
+        key.zeroize();
+        let wrapped = wrapped?;
+        Ok((Self::with_backend(backend, wrapped.clone(), timeout), wrapped))
+    }
+
+    /// Creates a [`KeyProvider`], whose key is unwrapped by the hardware key store `backend` from `wrapped`
+    /// whenever it is needed and the previously unwrapped key is older than `timeout`. See
+    /// [`Self::generate_with_backend`].
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::{ClientError, KeyBackend, KeyProvider};
+    /// use std::time::Duration;
+    ///
+    /// // a stand-in for a hardware key store
+    /// struct Xor;
+    ///
+    /// impl KeyBackend for Xor {
+    ///     fn wrap(&self, key: &[u8]) -> Result<Vec<u8>, ClientError> {
+    ///         Ok(key.iter().map(|b| b ^ 0x5c).collect())
+    ///     }
+    ///
+    ///     fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, ClientError> {
+    ///         self.wrap(wrapped)
+    ///     }
+    /// }
+    ///
+    /// let (keyprovider, wrapped) = KeyProvider::generate_with_backend(Xor, Duration::ZERO).unwrap();
+    /// let restored = KeyProvider::with_backend(Xor, wrapped, Duration::ZERO);
+    /// assert_eq!(
+    ///     *keyprovider.try_unlock().unwrap().borrow(),
+    ///     *restored.try_unlock().unwrap().borrow()
+    /// );
+    /// ```
+    pub fn with_backend<B>(backend: B, wrapped: Vec<u8>, timeout: Duration) -> Self
+    where
+        B: KeyBackend + 'static,
+    {
+        Self::interactive(timeout, move || Self::unwrap_with(&backend, &wrapped))
+    }
+
+    /// Same as [`Self::with_backend`], but `fallback` is called with the error, if the backend fails to unwrap
+    /// the key, e.g. with [`ClientError::PcrMismatch`] after the PCRs of a TPM changed. The fallback may
+    /// recover the key by other means, e.g. from a copy that is wrapped with a recovery password, and should
+    /// wrap it again for the current state of the backend.
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::{ClientError, KeyBackend, KeyProvider};
+    /// use std::time::Duration;
+    ///
+    /// // a stand-in for a hardware key store, whose key is gone
+    /// struct Broken;
+    ///
+    /// impl KeyBackend for Broken {
+    ///     fn wrap(&self, key: &[u8]) -> Result<Vec<u8>, ClientError> {
+    ///         Ok(key.to_vec())
+    ///     }
+    ///
+    ///     fn unwrap(&self, _: &[u8]) -> Result<Vec<u8>, ClientError> {
+    ///         Err(ClientError::PcrMismatch)
+    ///     }
+    /// }
+    ///
+    /// let keyprovider =
+    ///     KeyProvider::with_backend_fallback(Broken, vec![0; 32], Duration::ZERO, |e| {
+    ///         assert!(matches!(e, ClientError::PcrMismatch));
+    ///         // ask the user for the recovery password
+    ///         KeyProvider::with_passphrase_hashed_blake2b(b"recovery".to_vec())
+    ///     });
+    /// assert!(keyprovider.try_unlock().is_ok());
+    /// ```
+    pub fn with_backend_fallback<B, F>(backend: B, wrapped: Vec<u8>, timeout: Duration, fallback: F) -> Self
+    where
+        B: KeyBackend + 'static,
+        F: Fn(ClientError) -> Result<KeyProvider, ClientError> + Send + Sync + 'static,
+    {
+        Self::interactive(timeout, move || {
+            Self::unwrap_with(&backend, &wrapped).or_else(&fallback)
+        })
+    }
+
+    fn unwrap_with<B: KeyBackend>(backend: &B, wrapped: &[u8]) -> Result<Self, ClientError> {
+        let key = backend.unwrap(wrapped)?;
+        if key.len() != KEY_SIZE_HASHED {
+            let mut key = key;
+            key.zeroize();
+            return Err(ClientError::IllegalKeySize(KEY_SIZE_HASHED));
+        }
+        Self::with_passphrase_truncated(key)
+    }
+
+    /// Creates a [`KeyProvider`], whose key is generated and wrapped by the PKCS#11 token in `slot`, e.g. a
+    /// hardware security module. `module_path` is the PKCS#11 module of the token, and `pin_callback` returns
+    /// the user PIN whenever the token is accessed.
+    ///
+    /// Unlike [`Self::generate_with_backend`], the wrapped key is stored in a private data object on the token,
+    /// so the same key provider is returned for every call with the same token. The key is unwrapped by the
+    /// token for every operation and not cached, use [`Self::with_backend`] with a [`Pkcs11`](crate::Pkcs11)
+    /// backend to cache it.
+    #[cfg(feature = "pkcs11")]
+    pub fn pkcs11<P, F>(module_path: P, slot: u64, pin_callback: F) -> Result<Self, ClientError>
+    where
+        P: AsRef<std::path::Path>,
+        F: Fn() -> Result<String, ClientError> + Send + Sync + 'static,
+    {
+        let backend = crate::Pkcs11::new(module_path, slot, pin_callback)?;
+        // create the snapshot key right away, so that a misconfigured token fails here
+        backend.snapshot_key()?.zeroize();
+        Ok(Self::interactive(Duration::ZERO, move || {
+            let key = backend.snapshot_key()?;
+            Self::with_passphrase_truncated(key)
+        }))
+    }
+
+    /// Same as [`Self::interactive`], but with an async `prompt`. The returned future is driven to
+    /// completion on the thread that needs the key, which is a background thread for the async API.
+    #[cfg(feature = "async")]
+    pub fn interactive_async<F, Fut>(timeout: Duration, prompt: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<KeyProvider, ClientError>>,
+    {
+        Self::interactive(timeout, move || futures::executor::block_on(prompt()))
+    }
+
+    /// Binds snapshots, that are written with this [`KeyProvider`], to `associated_data`, e.g. an
+    /// identifier of the device or a secondary secret that is unwrapped by a hardware security module.
+    ///
+    /// The associated data is authenticated together with the snapshot, but not stored in it. Loading
+    /// the snapshot fails, unless the same associated data is supplied again, so a snapshot that has been
+    /// copied to another device can not be opened there with the password alone.
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::KeyProvider;
+    ///
+    /// let keyprovider = KeyProvider::try_from(vec![6u8; 32])
+    ///     .expect("Fail to create keyprovider")
+    ///     .with_associated_data(b"device-id".to_vec());
+    /// assert_eq!(keyprovider.associated_data(), b"device-id");
+    /// ```
+    pub fn with_associated_data(mut self, associated_data: Vec<u8>) -> Self {
+        self.associated_data = associated_data;
+        self
+    }
+
+    /// Returns the associated data, that snapshots are bound to. Empty by default.
+    pub fn associated_data(&self) -> &[u8] {
+        &self.associated_data
+    }
+
+    /// Drops the cached key of an interactive [`KeyProvider`], so that the application is asked for
+    /// the password on the next use, e.g. after a wrong password has been entered. Has no effect on
+    /// other key providers.
+    pub fn expire(&self) {
+        if let Inner::Interactive(interactive) = &self.inner {
+            if let Ok(mut cached) = interactive.cached.lock() {
+                cached.take();
+            }
+        }
+    }
+
     /// Tries to unlock the inner key and returns it.
-    /// If unlocking fails, a [`MemoryError`] will be returned
+    /// If unlocking fails, a [`MemoryError`] will be returned. An interactive key provider
+    /// asks the application for the key, if no key is cached or the cached key has expired.
     /// This operations ensures, that the unlocked key will be fragmented,
     /// when it goes out of scope.
     ///
@@ -307,9 +587,24 @@ impl KeyProvider {
     /// assert_eq!(keydata, inner_key.to_vec());
     /// ```
     pub fn try_unlock(&self) -> Result<Buffer<u8>, MemoryError> {
-        match self.inner.key.unlock() {
-            Ok(inner) => Ok(inner),
-            Err(memerror) => Err(memerror),
+        match &self.inner {
+            Inner::Static(key) => key.key.unlock(),
+            Inner::Interactive(interactive) => interactive.unlock(),
+        }
+    }
+
+    /// Re-splits the key in memory with fresh randomness, so that fragments of it, that have been scraped from
+    /// memory before, become useless. An interactive [`KeyProvider`] refreshes the key, that it has cached.
+    pub fn refresh(&self) -> Result<(), MemoryError> {
+        match &self.inner {
+            Inner::Static(key) => key.refresh(),
+            Inner::Interactive(interactive) => {
+                let cached = interactive.cached.lock().map_err(|_| MemoryError::LockNotAvailable)?;
+                match cached.as_ref() {
+                    Some((provider, _)) => provider.refresh(),
+                    None => Ok(()),
+                }
+            }
         }
     }
 }
@@ -339,4 +634,44 @@ mod tests {
 
         assert_eq!(keydata, inner_key.to_vec());
     }
+
+    #[test]
+    fn test_keyprovider_interactive() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let keydata = vec![6; 32];
+        let prompts = Arc::new(AtomicUsize::new(0));
+
+        let prompt = {
+            let keydata = keydata.clone();
+            let prompts = prompts.clone();
+            move || {
+                prompts.fetch_add(1, Ordering::SeqCst);
+                KeyProvider::try_from(keydata.clone()).map_err(|e| ClientError::Inner(e.to_string()))
+            }
+        };
+
+        let keyprovider = KeyProvider::interactive(Duration::from_secs(3600), prompt.clone());
+        for _ in 0..3 {
+            let buffer = keyprovider.try_unlock().expect("Failed to unlock");
+            assert_eq!(keydata, buffer.borrow().deref().to_vec());
+        }
+        assert_eq!(prompts.load(Ordering::SeqCst), 1);
+
+        keyprovider.expire();
+        assert!(keyprovider.try_unlock().is_ok());
+        assert_eq!(prompts.load(Ordering::SeqCst), 2);
+
+        // the key expires immediately
+        let keyprovider = KeyProvider::interactive(Duration::ZERO, prompt);
+        assert!(keyprovider.try_unlock().is_ok());
+        assert!(keyprovider.try_unlock().is_ok());
+        assert_eq!(prompts.load(Ordering::SeqCst), 4);
+
+        let failing = KeyProvider::interactive(Duration::ZERO, || Err(ClientError::Inner("cancelled".to_string())));
+        assert!(failing.try_unlock().is_err());
+    }
 }
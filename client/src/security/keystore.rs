@@ -94,6 +94,12 @@ where
         Ok(())
     }
 
+    /// Re-encrypts all keys with a new `master_key`, that is split in memory with fresh randomness.
+    pub fn refresh(&mut self) -> Result<(), P::Error> {
+        let keys = self.get_data();
+        self.rebuild_keystore(keys)
+    }
+
     /// Gets the state data in a hashmap format for the snapshot.
     pub fn get_data(&mut self) -> HashMap<VaultId, Key<P>> {
         let mut key_store: HashMap<VaultId, Key<P>> = HashMap::new();
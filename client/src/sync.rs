@@ -9,8 +9,8 @@ use std::{
 };
 
 use crate::{
-    derive_record_id, derive_vault_id, Client, ClientError, ClientState, KeyStore, LoadFromPath, Provider, RecordError,
-    SnapshotError, SnapshotState, VaultError,
+    derive_record_id, derive_vault_id, usage_vault_id, Client, ClientError, ClientState, KeyStore, LoadFromPath,
+    Provider, RecordError, SnapshotError, SnapshotState, VaultError,
 };
 
 /// Policy for conflicts when merging two vaults.
@@ -97,7 +97,11 @@ pub(crate) trait SyncClients<'a> {
     ) -> Result<ClientHierarchy<(RecordId, BlobId)>, ClientError> {
         let key_provider = self.get_key_provider()?;
         let db = self.get_db()?;
-        let vaults = vaults.unwrap_or_else(|| db.list_vaults());
+        let vaults = vaults.unwrap_or_else(|| {
+            let mut vaults = db.list_vaults();
+            vaults.retain(|vid| *vid != usage_vault_id());
+            vaults
+        });
         let mut hierarchy = HashMap::new();
         for vid in vaults {
             let list = match &key_provider {
@@ -193,7 +197,7 @@ pub(crate) trait SyncClients<'a> {
     }
 }
 
-pub(crate) type SnapshotHierarchy<T> = HashMap<ClientId, HashMap<VaultId, Vec<T>>>;
+pub type SnapshotHierarchy<T> = HashMap<ClientId, HashMap<VaultId, Vec<T>>>;
 
 /// Config for synching two snapshots.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
@@ -249,6 +253,84 @@ impl SyncSnapshotsConfig {
     }
 }
 
+/// Record-by-record difference between two snapshots, see [`crate::Snapshot::diff`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SnapshotDiff {
+    /// Records that only exist in the other snapshot
+    pub added: SnapshotHierarchy<RecordId>,
+
+    /// Records that only exist in this snapshot
+    pub removed: SnapshotHierarchy<RecordId>,
+
+    /// Records that exist in both snapshots with different content
+    pub changed: SnapshotHierarchy<RecordId>,
+}
+
+impl SnapshotDiff {
+    /// Compares the records of two hierarchies by their [`BlobId`]s
+    pub(crate) fn between(
+        ours: SnapshotHierarchy<(RecordId, BlobId)>,
+        mut theirs: SnapshotHierarchy<(RecordId, BlobId)>,
+    ) -> Self {
+        let mut diff = SnapshotDiff::default();
+        for (cid, our_vaults) in ours {
+            let mut their_vaults = theirs.remove(&cid).unwrap_or_default();
+            for (vid, our_records) in our_vaults {
+                let mut their_records: HashMap<RecordId, BlobId> =
+                    their_vaults.remove(&vid).unwrap_or_default().into_iter().collect();
+                for (rid, bid) in our_records {
+                    match their_records.remove(&rid) {
+                        None => diff.removed.insert_record(cid, vid, rid),
+                        Some(their_bid) if their_bid != bid => diff.changed.insert_record(cid, vid, rid),
+                        Some(_) => {}
+                    }
+                }
+                for rid in their_records.into_keys() {
+                    diff.added.insert_record(cid, vid, rid);
+                }
+            }
+            theirs.insert(cid, their_vaults);
+        }
+        for (cid, vaults) in theirs {
+            for (vid, records) in vaults {
+                for (rid, _) in records {
+                    diff.added.insert_record(cid, vid, rid);
+                }
+            }
+        }
+        diff
+    }
+
+    /// Returns `true`, if both snapshots contain the same records
+    pub fn is_empty(&self) -> bool {
+        let empty = |h: &SnapshotHierarchy<RecordId>| h.values().flat_map(|v| v.values()).all(|r| r.is_empty());
+        empty(&self.added) && empty(&self.removed) && empty(&self.changed)
+    }
+}
+
+/// A record that exists in both snapshots of a merge with different content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordConflict {
+    /// The client that contains the record
+    pub client_id: ClientId,
+
+    /// The vault that contains the record
+    pub vault_id: VaultId,
+
+    /// The conflicting record
+    pub record_id: RecordId,
+}
+
+trait InsertRecord {
+    fn insert_record(&mut self, cid: ClientId, vid: VaultId, rid: RecordId);
+}
+
+impl InsertRecord for SnapshotHierarchy<RecordId> {
+    fn insert_record(&mut self, cid: ClientId, vid: VaultId, rid: RecordId) {
+        self.entry(cid).or_default().entry(vid).or_default().push(rid);
+    }
+}
+
 pub(crate) trait SyncSnapshots {
     fn clients(&self) -> Vec<ClientId>;
     fn get_from_state<F, T>(&self, cid: ClientId, f: F) -> Result<T, SnapshotError>
@@ -391,11 +473,13 @@ pub(crate) trait SyncSnapshots {
 
 #[cfg(test)]
 mod test {
-    use std::convert::Infallible;
-
     use super::*;
 
-    use crate::{derive_record_id, derive_record_id_from_counter, derive_vault_id, procedures::Runner, Location};
+    use crate::{
+        derive_record_id, derive_record_id_from_counter, derive_vault_id,
+        procedures::{FatalProcedureError, Runner},
+        Location,
+    };
     use engine::vault::RecordHint;
     use stronghold_utils::random;
 
@@ -605,7 +689,7 @@ mod test {
             .map(|(_, bid)| *bid)
             .expect("Record does not exist.");
 
-        let set_up_target = || -> Result<Client, VaultError<Infallible>> {
+        let set_up_target = || -> Result<Client, VaultError<FatalProcedureError>> {
             let target = Client::default();
             for i in 2..4usize {
                 for j in 2..4usize {
@@ -9,10 +9,12 @@ use std::{
 };
 
 use crate::{
-    procedures::{GenerateKey, KeyType, StrongholdProcedure},
-    Client, ClientError, ClientVault, KeyProvider, Location, Snapshot, SnapshotPath, Store, Stronghold,
+    procedures::{GarbageCollect, GenerateKey, KeyType, StrongholdProcedure},
+    Argon2Params, AuditEvent, Client, ClientError, ClientVault, Compression, Event, GcPolicy, HmacSecret, KeyProvider,
+    ListOptions, Location, PasswordKey, Quota, ShutdownConfig, Snapshot, SnapshotFormat, SnapshotPath, Store,
+    StoreLimits, Stronghold, ThrottlePolicy,
 };
-use engine::vault::RecordHint;
+use engine::vault::{RecordHint, RecordId};
 use regex::Replacer;
 use stronghold_utils::random as rand;
 use zeroize::Zeroize;
@@ -711,3 +713,2180 @@ fn test_load_unload_client() {
     assert!(stronghold.unload_client(client).is_ok());
     assert!(stronghold.load_client(client_path).is_ok());
 }
+
+#[test]
+fn test_stronghold_metrics() {
+    use crate::procedures::{GenerateKey, KeyType, PublicKey};
+
+    let stronghold = Stronghold::default();
+    let client = stronghold.create_client(b"client").unwrap();
+    let vault_path = b"vault".to_vec();
+    let key = Location::const_generic(vault_path.clone(), b"key".to_vec());
+
+    client
+        .vault(&vault_path)
+        .write_secret(Location::const_generic(vault_path, b"secret".to_vec()), vec![0; 32])
+        .unwrap();
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: key.clone(),
+        })
+        .unwrap();
+    for _ in 0..2 {
+        client
+            .execute_procedure(PublicKey {
+                ty: KeyType::Ed25519,
+                private_key: key.clone(),
+            })
+            .unwrap();
+    }
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let keyprovider = KeyProvider::try_from(fixed_random_bytes(32)).unwrap();
+    stronghold
+        .commit_with_keyprovider(&SnapshotPath::from_path(&*defer), &keyprovider)
+        .unwrap();
+
+    let metrics = stronghold.metrics().unwrap();
+    assert_eq!(metrics.procedures.get("GenerateKey"), Some(&1));
+    assert_eq!(metrics.procedures.get("PublicKey"), Some(&2));
+    assert_eq!(metrics.vault_writes.count, 2);
+    assert_eq!(metrics.vault_reads.count, 2);
+    assert_eq!(metrics.snapshot_commits.count, 1);
+    assert!(metrics.locked_memory_pages > 0);
+}
+
+#[test]
+fn test_key_refresh() {
+    use crate::procedures::{GenerateKey, KeyType, PublicKey};
+    use std::time::Duration;
+
+    let stronghold = Stronghold::default();
+    let client = stronghold.create_client(b"client").unwrap();
+    let key = Location::const_generic(b"vault".to_vec(), b"key".to_vec());
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: key.clone(),
+        })
+        .unwrap();
+    let public_key = || {
+        client
+            .execute_procedure(PublicKey {
+                ty: KeyType::Ed25519,
+                private_key: key.clone(),
+            })
+            .unwrap()
+    };
+    let before = public_key();
+
+    // the vault keys are still usable after they have been re-encrypted with a new master key
+    client.refresh_keys().unwrap();
+    assert_eq!(public_key(), before);
+    assert_eq!(stronghold.metrics().unwrap().key_refreshes.count, 1);
+
+    client.set_key_refresh(Some(Duration::from_millis(10)));
+    std::thread::sleep(Duration::from_millis(200));
+    client.set_key_refresh(None);
+    // a cycle, that has already started, may still finish
+    std::thread::sleep(Duration::from_millis(50));
+    let refreshes = stronghold.metrics().unwrap().key_refreshes.count;
+    assert!(refreshes > 1);
+    assert_eq!(public_key(), before);
+    assert_eq!(stronghold.metrics().unwrap().key_refresh_failures, 0);
+
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(stronghold.metrics().unwrap().key_refreshes.count, refreshes);
+}
+
+#[test]
+fn test_stronghold_health() {
+    let stronghold = Stronghold::default();
+
+    let health = stronghold.health().expect("Failed to query health");
+    assert!(!health.snapshot_loaded);
+    assert_eq!(health.clients, 0);
+    assert_eq!(health.vaults, 0);
+    assert!(health.last_persist.is_none());
+
+    let client_path = fixed_random_bytes(32);
+    let vault_path = fixed_random_bytes(32);
+    let record_path = fixed_random_bytes(32);
+    let client = stronghold.create_client(&client_path).expect("Failed to create client");
+    let vault = client.vault(&vault_path);
+    vault
+        .write_secret(
+            Location::const_generic(vault_path, record_path.clone()),
+            fixed_random_bytes(32),
+        )
+        .expect("Failed to write secret");
+    vault.revoke_secret(record_path).expect("Failed to revoke secret");
+
+    let health = stronghold.health().expect("Failed to query health");
+    assert_eq!(health.clients, 1);
+    assert_eq!(health.vaults, 1);
+    assert_eq!(health.pending_gc, 1);
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+    let keyprovider = KeyProvider::try_from(fixed_random_bytes(32)).expect("Failed to create keyprovider");
+
+    stronghold
+        .commit_with_keyprovider(&snapshot, &keyprovider)
+        .expect("Failed to commit");
+    assert!(stronghold.health().unwrap().last_persist.is_some());
+
+    stronghold
+        .load_snapshot(&keyprovider, &snapshot)
+        .expect("Failed to load snapshot");
+    assert!(stronghold.health().unwrap().snapshot_loaded);
+}
+
+#[test]
+fn test_vault_fingerprint() {
+    let stronghold = Stronghold::default();
+    let vault_path = fixed_random_bytes(32);
+    let shared_key = fixed_random_bytes(32);
+
+    let source = stronghold.create_client(b"source").expect("Failed to create client");
+    let target = stronghold.create_client(b"target").expect("Failed to create client");
+
+    let vault = source.vault(&vault_path);
+    for _ in 0..5 {
+        vault
+            .write_secret(
+                Location::const_generic(vault_path.clone(), fixed_random_bytes(32)),
+                fixed_random_bytes(64),
+            )
+            .expect("Failed to write secret");
+    }
+    let initial = source.vault_fingerprint(&vault_path, &shared_key).unwrap();
+
+    target
+        .sync_with(&source, crate::sync::SyncClientsConfig::default())
+        .expect("Failed to sync clients");
+    assert_eq!(target.vault_fingerprint(&vault_path, &shared_key).unwrap(), initial);
+    assert_ne!(
+        target.vault_fingerprint(&vault_path, fixed_random_bytes(32)).unwrap(),
+        initial
+    );
+
+    // every mutation changes the fingerprint
+    let record_path = fixed_random_bytes(32);
+    vault
+        .write_secret(
+            Location::const_generic(vault_path.clone(), record_path.clone()),
+            fixed_random_bytes(64),
+        )
+        .unwrap();
+    let updated = source.vault_fingerprint(&vault_path, &shared_key).unwrap();
+    assert_ne!(updated, initial);
+
+    // revoking the new record restores the previous content
+    vault.revoke_secret(record_path.clone()).unwrap();
+    assert_eq!(source.vault_fingerprint(&vault_path, &shared_key).unwrap(), initial);
+
+    // a failed write into the revoked record doesn't change the fingerprint
+    assert!(vault
+        .write_secret(
+            Location::const_generic(vault_path.clone(), record_path),
+            fixed_random_bytes(64),
+        )
+        .is_err());
+    assert_eq!(source.vault_fingerprint(&vault_path, &shared_key).unwrap(), initial);
+}
+
+#[test]
+fn test_snapshot_progress() {
+    use engine::snapshot::Phase;
+
+    let client_path = fixed_random_bytes(32);
+    let vault_path = fixed_random_bytes(32);
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+
+    let mut phases = Vec::new();
+    let keyprovider =
+        KeyProvider::with_passphrase_hashed_argon2_progress(fixed_random_bytes(32), fixed_random_bytes(32), &mut |p| {
+            phases.push(p.phase)
+        })
+        .expect("Failed to create keyprovider");
+
+    let stronghold = Stronghold::default();
+    let client = stronghold.create_client(&client_path).unwrap();
+    client
+        .vault(&vault_path)
+        .write_secret(
+            Location::const_generic(vault_path.clone(), fixed_random_bytes(32)),
+            fixed_random_bytes(1024),
+        )
+        .unwrap();
+
+    stronghold
+        .commit_with_keyprovider_and_progress(&snapshot, &keyprovider, |p| phases.push(p.phase))
+        .expect("Failed to commit");
+
+    let stronghold = stronghold.reset();
+    stronghold
+        .load_snapshot_with_progress(&keyprovider, &snapshot, |p| phases.push(p.phase))
+        .expect("Failed to load snapshot");
+    assert!(stronghold.load_client(&client_path).is_ok());
+
+    phases.dedup();
+    assert_eq!(
+        phases,
+        vec![
+            Phase::Kdf,
+            Phase::Serialize,
+            Phase::Compress,
+            Phase::Encrypt,
+            Phase::Write,
+            Phase::Read,
+            Phase::Decrypt,
+            Phase::Decompress,
+            Phase::Deserialize,
+        ]
+    );
+}
+
+#[test]
+fn test_restore_revoked_secret() {
+    let stronghold = Stronghold::default();
+    let client = stronghold
+        .create_client(fixed_random_bytes(32))
+        .expect("Failed to create client");
+    client
+        .set_trash_retention(std::time::Duration::from_secs(3600))
+        .expect("Failed to set retention");
+
+    let vault_path = fixed_random_bytes(32);
+    let record_path = fixed_random_bytes(32);
+    let secret = fixed_random_bytes(32);
+    let location = Location::const_generic(vault_path.clone(), record_path.clone());
+    let vault = client.vault(&vault_path);
+    vault
+        .write_secret(location.clone(), secret.clone())
+        .expect("Failed to write secret");
+
+    assert!(vault.delete_secret(&record_path).expect("Failed to delete secret"));
+    assert!(!client.record_exists(&location).unwrap());
+    assert_eq!(vault.list_revoked().unwrap().len(), 1);
+
+    assert!(vault.restore_secret(&record_path).expect("Failed to restore secret"));
+    assert!(client.record_exists(&location).unwrap());
+    assert_eq!(vault.read_secret(&record_path).unwrap(), secret);
+    assert!(vault.list_revoked().unwrap().is_empty());
+
+    // without retention, deleted secrets are gone for good
+    client.set_trash_retention(std::time::Duration::ZERO).unwrap();
+    vault.delete_secret(&record_path).expect("Failed to delete secret");
+    assert!(!vault.restore_secret(&record_path).unwrap());
+    assert!(!client.record_exists(&location).unwrap());
+}
+
+#[test]
+fn test_list_records_paged() {
+    let stronghold = Stronghold::default();
+    let client = stronghold
+        .create_client(fixed_random_bytes(32))
+        .expect("Failed to create client");
+
+    let vault_path = fixed_random_bytes(32);
+    let vault = client.vault(&vault_path);
+    assert_eq!(vault.list_records(&ListOptions::default()).unwrap().total, 0);
+
+    for _ in 0..25 {
+        let location = Location::const_generic(vault_path.clone(), fixed_random_bytes(32));
+        vault
+            .write_secret(location, fixed_random_bytes(32))
+            .expect("Failed to write secret");
+    }
+
+    let mut listed = Vec::new();
+    let mut options = ListOptions {
+        limit: Some(10),
+        ..Default::default()
+    };
+    loop {
+        let page = vault.list_records(&options).expect("Failed to list records");
+        assert_eq!(page.total, 25);
+        if page.entries.is_empty() {
+            break;
+        }
+        options.offset += page.entries.len();
+        listed.extend(page.entries.into_iter().map(|entry| entry.id));
+    }
+    assert_eq!(listed.len(), 25);
+    listed.dedup();
+    assert_eq!(listed.len(), 25);
+}
+
+#[test]
+fn test_change_snapshot_password() {
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    let location = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    let client = stronghold.create_client(&client_path).expect("Failed to create client");
+    client
+        .vault(location.vault_path())
+        .write_secret(location.clone(), fixed_random_bytes(32))
+        .expect("Failed to write secret");
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+
+    let old_key = fixed_random_bytes(32);
+    let new_key = fixed_random_bytes(32);
+    let old = KeyProvider::try_from(old_key.clone()).unwrap();
+    let new = KeyProvider::try_from(new_key.clone()).unwrap();
+
+    stronghold
+        .commit_with_keyprovider(&snapshot, &old)
+        .expect("Failed to commit");
+
+    // the new password can not be used to change the password
+    assert!(stronghold.change_snapshot_password(&snapshot, &new, &old).is_err());
+
+    stronghold
+        .change_snapshot_password(&snapshot, &old, &new)
+        .expect("Failed to change password");
+
+    let stronghold = stronghold.reset();
+    assert!(stronghold
+        .load_client_from_snapshot(&client_path, &KeyProvider::try_from(old_key).unwrap(), &snapshot)
+        .is_err());
+
+    let client = stronghold
+        .load_client_from_snapshot(&client_path, &KeyProvider::try_from(new_key).unwrap(), &snapshot)
+        .expect("Failed to load client with new password");
+    assert!(client.record_exists(&location).unwrap());
+}
+
+#[test]
+fn test_password_protected_snapshot() {
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    let location = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    let client = stronghold.create_client(&client_path).expect("Failed to create client");
+    client
+        .vault(location.vault_path())
+        .write_secret(location.clone(), fixed_random_bytes(32))
+        .expect("Failed to write secret");
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+
+    // keep the key derivation cheap for the test
+    let params = Argon2Params {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    // a snapshot with a key from the legacy KDF is upgraded
+    let legacy = KeyProvider::with_passphrase_hashed_blake2b(b"password".to_vec()).unwrap();
+    stronghold
+        .commit_with_keyprovider(&snapshot, &legacy)
+        .expect("Failed to commit");
+    stronghold
+        .upgrade_snapshot_kdf(&snapshot, &legacy, b"password".to_vec(), &params)
+        .expect("Failed to upgrade snapshot");
+
+    let stronghold = stronghold.reset();
+    assert!(stronghold.load_snapshot(&legacy, &snapshot).is_err());
+    assert!(stronghold
+        .load_snapshot_with_password(b"wrong password".to_vec(), &snapshot)
+        .is_err());
+    stronghold
+        .load_snapshot_with_password(b"password".to_vec(), &snapshot)
+        .expect("Failed to load snapshot");
+    let client = stronghold.load_client(&client_path).expect("Failed to load client");
+    assert!(client.record_exists(&location).unwrap());
+
+    stronghold
+        .commit_with_password(&snapshot, b"password".to_vec(), &params)
+        .expect("Failed to commit");
+    let stronghold = stronghold.reset();
+    stronghold
+        .load_snapshot_with_password(b"password".to_vec(), &snapshot)
+        .expect("Failed to load snapshot");
+    assert!(stronghold.load_client(&client_path).is_ok());
+
+    // the key is derived once, and reused for loading and committing
+    let key = PasswordKey::from_header(snapshot.as_path(), b"password").expect("Failed to derive key");
+    let stronghold = stronghold.reset();
+    stronghold
+        .load_snapshot_with_password_key(&key, &snapshot)
+        .expect("Failed to load snapshot");
+    stronghold.load_client(&client_path).expect("Failed to load client");
+    stronghold
+        .commit_with_password_key(&snapshot, &key)
+        .expect("Failed to commit");
+    let wrong_key = PasswordKey::from_header(snapshot.as_path(), b"wrong password").unwrap();
+    assert!(stronghold
+        .reset()
+        .load_snapshot_with_password_key(&wrong_key, &snapshot)
+        .is_err());
+    let stronghold = Stronghold::default();
+    stronghold
+        .load_snapshot_with_password(b"password".to_vec(), &snapshot)
+        .expect("Failed to load snapshot");
+    let client = stronghold.load_client(&client_path).expect("Failed to load client");
+    assert!(client.record_exists(&location).unwrap());
+}
+
+#[test]
+fn test_snapshot_authenticator() {
+    use crypto::macs::hmac::HMAC_SHA256;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // a stand-in for a security key, that counts the touches
+    struct SoftKey {
+        secret: Vec<u8>,
+        touches: AtomicUsize,
+    }
+
+    impl HmacSecret for SoftKey {
+        fn hmac_secret(&self, credential_id: &[u8], salt: &[u8; 32]) -> Result<[u8; 32], ClientError> {
+            if credential_id != b"credential" {
+                return Err(ClientError::KeyBackend("Unknown credential".to_string()));
+            }
+            self.touches.fetch_add(1, Ordering::SeqCst);
+            let mut secret = [0u8; 32];
+            HMAC_SHA256(salt, &self.secret, &mut secret);
+            Ok(secret)
+        }
+    }
+
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    let location = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    let client = stronghold.create_client(&client_path).expect("Failed to create client");
+    client
+        .vault(location.vault_path())
+        .write_secret(location.clone(), fixed_random_bytes(32))
+        .expect("Failed to write secret");
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+
+    let authenticator = SoftKey {
+        secret: fixed_random_bytes(32),
+        touches: AtomicUsize::new(0),
+    };
+    assert!(stronghold
+        .commit_with_authenticator(&snapshot, &authenticator, b"unknown")
+        .is_err());
+    stronghold
+        .commit_with_authenticator(&snapshot, &authenticator, b"credential")
+        .expect("Failed to commit");
+    assert_eq!(
+        Snapshot::peek_metadata(&snapshot).unwrap().format,
+        SnapshotFormat::Challenge
+    );
+
+    let stronghold = stronghold.reset();
+    let other = SoftKey {
+        secret: fixed_random_bytes(32),
+        touches: AtomicUsize::new(0),
+    };
+    assert!(stronghold.load_snapshot_with_authenticator(&other, &snapshot).is_err());
+    stronghold
+        .load_snapshot_with_authenticator(&authenticator, &snapshot)
+        .expect("Failed to load snapshot");
+    let client = stronghold.load_client(&client_path).expect("Failed to load client");
+    assert!(client.record_exists(&location).unwrap());
+    assert_eq!(authenticator.touches.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_snapshot_associated_data() {
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    let location = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    let client = stronghold.create_client(&client_path).expect("Failed to create client");
+    client
+        .vault(location.vault_path())
+        .write_secret(location.clone(), fixed_random_bytes(32))
+        .expect("Failed to write secret");
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+
+    let key = fixed_random_bytes(32);
+    let keyprovider = |associated_data: &[u8]| {
+        KeyProvider::try_from(key.clone())
+            .unwrap()
+            .with_associated_data(associated_data.to_vec())
+    };
+
+    stronghold
+        .commit_with_keyprovider(&snapshot, &keyprovider(b"device-a"))
+        .expect("Failed to commit");
+
+    // the snapshot has been copied to another device
+    let stronghold = stronghold.reset();
+    assert!(stronghold.load_snapshot(&keyprovider(b""), &snapshot).is_err());
+    assert!(stronghold.load_snapshot(&keyprovider(b"device-b"), &snapshot).is_err());
+
+    stronghold
+        .load_snapshot(&keyprovider(b"device-a"), &snapshot)
+        .expect("Failed to load snapshot");
+    let client = stronghold.load_client(&client_path).expect("Failed to load client");
+    assert!(client.record_exists(&location).unwrap());
+
+    // re-bind the snapshot to another device
+    stronghold
+        .change_snapshot_password(&snapshot, &keyprovider(b"device-a"), &keyprovider(b"device-b"))
+        .expect("Failed to change associated data");
+    assert!(stronghold.load_snapshot(&keyprovider(b"device-a"), &snapshot).is_err());
+    assert!(Stronghold::default()
+        .load_client_from_snapshot(&client_path, &keyprovider(b"device-b"), &snapshot)
+        .is_ok());
+}
+
+#[test]
+fn test_password_snapshot_associated_data() {
+    use engine::snapshot::{kdf::KDF_VERSION, MAGIC};
+
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    let location = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    let client = stronghold.create_client(&client_path).expect("Failed to create client");
+    client
+        .vault(location.vault_path())
+        .write_secret(location.clone(), fixed_random_bytes(32))
+        .expect("Failed to write secret");
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+
+    // keep the key derivation cheap for the test
+    let params = Argon2Params {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    stronghold.set_snapshot_associated_data(b"device-a".to_vec()).unwrap();
+    stronghold
+        .commit_with_password(&snapshot, b"password".to_vec(), &params)
+        .expect("Failed to commit");
+    let content = stronghold
+        .commit_to_bytes(b"password".to_vec(), &params)
+        .expect("Failed to commit");
+
+    // the snapshot has been copied to another device
+    let stronghold = stronghold.reset();
+    assert!(stronghold
+        .load_snapshot_with_password(b"password".to_vec(), &snapshot)
+        .is_err());
+    assert!(stronghold
+        .load_snapshot_from_bytes(&content, b"password".to_vec())
+        .is_err());
+    stronghold.set_snapshot_associated_data(b"device-b".to_vec()).unwrap();
+    assert!(stronghold
+        .load_snapshot_with_password(b"password".to_vec(), &snapshot)
+        .is_err());
+
+    // a modified header fails to load, here the memory cost is raised by one KiB
+    stronghold.set_snapshot_associated_data(b"device-a".to_vec()).unwrap();
+    let mut tampered = content.clone();
+    tampered[MAGIC.len() + KDF_VERSION.len() + 4] ^= 1;
+    assert!(stronghold
+        .load_snapshot_from_bytes(&tampered, b"password".to_vec())
+        .is_err());
+
+    stronghold
+        .load_snapshot_from_bytes(&content, b"password".to_vec())
+        .expect("Failed to load snapshot");
+    stronghold
+        .load_snapshot_with_password(b"password".to_vec(), &snapshot)
+        .expect("Failed to load snapshot");
+    let client = stronghold.load_client(&client_path).expect("Failed to load client");
+    assert!(client.record_exists(&location).unwrap());
+}
+
+#[test]
+fn test_snapshot_diff_merge() {
+    use crate::{
+        sync::{MergePolicy, SyncClientsConfig},
+        LoadFromPath,
+    };
+    use engine::vault::ClientId;
+
+    let client_path = fixed_random_bytes(32);
+    let vault_path = fixed_random_bytes(32);
+    let location = |record_path: &[u8]| Location::const_generic(vault_path.clone(), record_path.to_vec());
+    let write = |client: &Client, record_path: &[u8]| {
+        client
+            .vault(&vault_path)
+            .write_secret(location(record_path), fixed_random_bytes(32))
+            .expect("Failed to write secret")
+    };
+
+    let stronghold_a = Stronghold::default();
+    let client_a = stronghold_a.create_client(&client_path).unwrap();
+    write(&client_a, b"r1");
+    write(&client_a, b"r2");
+
+    let stronghold_b = Stronghold::default();
+    let client_b = stronghold_b.create_client(&client_path).unwrap();
+    client_b
+        .sync_with(&client_a, SyncClientsConfig::new(MergePolicy::Replace))
+        .expect("Failed to sync clients");
+
+    // both devices continue independently
+    write(&client_a, b"r3");
+    write(&client_b, b"r4");
+    write(&client_b, b"r2");
+
+    let key = fixed_random_bytes(32);
+    let read_snapshot = |stronghold: &Stronghold| {
+        let mut snapshot_path = std::env::temp_dir();
+        snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+        let snapshot_path = SnapshotPath::from_path(snapshot_path);
+        stronghold
+            .commit_with_keyprovider(&snapshot_path, &KeyProvider::try_from(key.clone()).unwrap())
+            .expect("Failed to commit");
+        let snapshot = Snapshot::read_from_snapshot(&snapshot_path, key.clone().try_into().unwrap(), None)
+            .expect("Failed to read snapshot");
+        let _ = std::fs::remove_file(snapshot_path.as_path());
+        snapshot
+    };
+    let mut snapshot_a = read_snapshot(&stronghold_a);
+    let snapshot_b = read_snapshot(&stronghold_b);
+
+    let cid = ClientId::load_from_path(&client_path, &client_path);
+    let records = |hierarchy: &crate::sync::SnapshotHierarchy<engine::vault::RecordId>| {
+        hierarchy
+            .get(&cid)
+            .into_iter()
+            .flat_map(|vaults| vaults.values().flatten().cloned())
+            .collect::<Vec<_>>()
+    };
+    let rid = |record_path: &[u8]| location(record_path).resolve().1;
+
+    let diff = snapshot_a.diff(&snapshot_b).expect("Failed to diff snapshots");
+    assert_eq!(records(&diff.added), vec![rid(b"r4")]);
+    assert_eq!(records(&diff.removed), vec![rid(b"r3")]);
+    assert_eq!(records(&diff.changed), vec![rid(b"r2")]);
+
+    // keeping the own record of a conflict only adds the new records
+    let mut conflicts = Vec::new();
+    snapshot_a
+        .merge_with(&snapshot_b, |conflict| {
+            conflicts.push(conflict.record_id);
+            MergePolicy::KeepOld
+        })
+        .expect("Failed to merge snapshots");
+    assert_eq!(conflicts, vec![rid(b"r2")]);
+    let diff = snapshot_a.diff(&snapshot_b).unwrap();
+    assert!(records(&diff.added).is_empty());
+    assert_eq!(records(&diff.changed), vec![rid(b"r2")]);
+
+    snapshot_a
+        .merge(&snapshot_b, MergePolicy::Replace)
+        .expect("Failed to merge snapshots");
+    let diff = snapshot_a.diff(&snapshot_b).unwrap();
+    assert!(records(&diff.added).is_empty());
+    assert!(records(&diff.changed).is_empty());
+    assert_eq!(records(&diff.removed), vec![rid(b"r3")]);
+}
+
+#[test]
+fn test_shutdown() {
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    let location = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    let client = stronghold.create_client(&client_path).expect("Failed to create client");
+    client
+        .vault(location.vault_path())
+        .write_secret(location.clone(), fixed_random_bytes(32))
+        .expect("Failed to write secret");
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+    let key = fixed_random_bytes(32);
+
+    // without a stored snapshot key nothing can be persisted, so the state is kept
+    assert!(matches!(
+        stronghold.shutdown(ShutdownConfig::default().commit(snapshot.clone())),
+        Err(ClientError::SnapshotKeyLocationMissing)
+    ));
+    assert_eq!(stronghold.health().unwrap().clients, 1);
+    assert!(client.record_exists(&location).unwrap());
+
+    stronghold
+        .shutdown(
+            ShutdownConfig::default()
+                .commit_with_keyprovider(snapshot.clone(), KeyProvider::try_from(key.clone()).unwrap())
+                .abort_operations(true),
+        )
+        .expect("Failed to shut down");
+    assert_eq!(stronghold.health().unwrap().clients, 0);
+    assert!(!client.record_exists(&location).unwrap());
+
+    let client = Stronghold::default()
+        .load_client_from_snapshot(&client_path, &KeyProvider::try_from(key).unwrap(), &snapshot)
+        .expect("Failed to load client");
+    assert!(client.record_exists(&location).unwrap());
+}
+
+#[test]
+fn test_shutdown_awaits_operations() {
+    use crate::procedures::ProcedureError;
+    use std::time::Duration;
+
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    let location = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+    let mut default_path = std::env::temp_dir();
+    default_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let default_defer = Defer::from((default_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let default = SnapshotPath::from_path(&*default_defer);
+    let key = fixed_random_bytes(32);
+    stronghold
+        .open_named_snapshot("named", KeyProvider::try_from(key.clone()).unwrap(), &snapshot)
+        .unwrap();
+    let client = stronghold.create_named_client("named", &client_path).unwrap();
+    client
+        .vault(location.vault_path())
+        .write_secret(location.clone(), fixed_random_bytes(32))
+        .expect("Failed to write secret");
+
+    // an operation, that is still running after the timeout, keeps the state
+    let operation = crate::Operations::begin(&client.operations, *client.id(), &[]).unwrap();
+    assert!(matches!(
+        stronghold.shutdown(
+            ShutdownConfig::default()
+                .commit(default.clone())
+                .timeout(Duration::from_millis(50))
+        ),
+        Err(ClientError::ShutdownTimeout(_))
+    ));
+    assert!(client.record_exists(&location).unwrap());
+    let retried = crate::Operations::begin(&client.operations, *client.id(), &[]);
+    assert!(retried.is_ok());
+    drop(retried);
+
+    // no new operations are started, while the running one is awaited
+    let shutting_down = client.clone();
+    let finish = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        let rejected = crate::Operations::begin(&shutting_down.operations, *shutting_down.id(), &[]);
+        drop(operation);
+        rejected
+    });
+    stronghold
+        .shutdown(
+            ShutdownConfig::default().commit_with_keyprovider(default, KeyProvider::try_from(key.clone()).unwrap()),
+        )
+        .expect("Failed to shut down");
+    assert!(matches!(finish.join().unwrap(), Err(ProcedureError::ShuttingDown)));
+    assert_eq!(stronghold.health().unwrap().clients, 0);
+    assert!(matches!(
+        client.execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32)),
+        }),
+        Err(ProcedureError::ShuttingDown)
+    ));
+
+    // the named snapshot has been committed as well
+    let stronghold = Stronghold::default();
+    stronghold
+        .open_named_snapshot("named", KeyProvider::try_from(key).unwrap(), &snapshot)
+        .unwrap();
+    let client = stronghold.load_named_client("named", &client_path).unwrap();
+    assert!(client.record_exists(&location).unwrap());
+}
+
+#[test]
+fn test_export_partial() {
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    let shared = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    let private = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    let client = stronghold.create_client(&client_path).expect("Failed to create client");
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: shared.clone(),
+        })
+        .expect("Failed to generate key");
+    client
+        .vault(private.vault_path())
+        .write_secret(private.clone(), fixed_random_bytes(32))
+        .expect("Failed to write secret");
+    client.store().insert(b"key".to_vec(), b"value".to_vec(), None).unwrap();
+
+    let params = Argon2Params {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+    let exported = stronghold
+        .export_partial(&[&client_path], &[shared.vault_path()], b"transfer", &params)
+        .expect("Failed to export vault");
+    assert!(stronghold
+        .export_partial(&[fixed_random_bytes(32)], &[shared.vault_path()], b"transfer", &params)
+        .is_err());
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    std::fs::write(&*defer, exported).unwrap();
+    let snapshot = SnapshotPath::from_path(&*defer);
+
+    // the other device only receives the selected vault
+    assert!(Snapshot::read_from_snapshot_with_password(&snapshot, b"wrong", &[], None).is_err());
+    let other = Stronghold::default();
+    other
+        .load_snapshot_with_password(b"transfer".to_vec(), &snapshot)
+        .expect("Failed to load exported snapshot");
+    let received = other.load_client(&client_path).expect("Failed to load client");
+    assert!(received.record_exists(&shared).unwrap());
+    assert!(!received.record_exists(&private).unwrap());
+    assert_eq!(received.store().get(b"key").unwrap(), None);
+
+    let public_key = |client: &Client| {
+        client
+            .execute_procedure(crate::procedures::PublicKey {
+                ty: KeyType::Ed25519,
+                private_key: shared.clone(),
+            })
+            .unwrap()
+    };
+    assert_eq!(public_key(&received), public_key(&client));
+}
+
+#[test]
+fn test_snapshot_compression() {
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    let client = stronghold.create_client(&client_path).expect("Failed to create client");
+    let location = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    client
+        .vault(location.vault_path())
+        .write_secret(location.clone(), fixed_random_bytes(32))
+        .expect("Failed to write secret");
+    client
+        .store()
+        .insert(b"key".to_vec(), vec![0u8; 64 * 1024], None)
+        .unwrap();
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+    let keyprovider = KeyProvider::try_from(fixed_random_bytes(32)).unwrap();
+
+    for compression in [Compression::None, Compression::Zstd { level: 3 }, Compression::Lz4] {
+        stronghold.set_snapshot_compression(compression).unwrap();
+        stronghold
+            .commit_with_keyprovider(&snapshot, &keyprovider)
+            .expect("Failed to commit");
+
+        let stronghold = Stronghold::default();
+        let client = stronghold
+            .load_client_from_snapshot(&client_path, &keyprovider, &snapshot)
+            .expect("Failed to load client");
+        assert!(client.record_exists(&location).unwrap());
+        assert_eq!(client.store().get(b"key").unwrap(), Some(vec![0u8; 64 * 1024]));
+    }
+}
+
+#[test]
+fn test_named_snapshots() {
+    let temp_path = || {
+        let mut path = std::env::temp_dir();
+        path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+        Defer::from((path, |path: &'_ PathBuf| {
+            let _ = std::fs::remove_file(path);
+        }))
+    };
+    let (hot_file, cold_file, default_file) = (temp_path(), temp_path(), temp_path());
+    let hot = SnapshotPath::from_path(&*hot_file);
+    let cold = SnapshotPath::from_path(&*cold_file);
+    let default = SnapshotPath::from_path(&*default_file);
+    let (hot_key, cold_key, default_key) = (fixed_random_bytes(32), fixed_random_bytes(32), fixed_random_bytes(32));
+
+    let hot_client_path = fixed_random_bytes(32);
+    let cold_client_path = fixed_random_bytes(32);
+    let default_client_path = fixed_random_bytes(32);
+    let location = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+
+    let stronghold = Stronghold::default();
+    stronghold
+        .open_named_snapshot("hot", KeyProvider::try_from(hot_key.clone()).unwrap(), &hot)
+        .expect("Failed to open snapshot");
+    stronghold
+        .open_named_snapshot("cold", KeyProvider::try_from(cold_key.clone()).unwrap(), &cold)
+        .expect("Failed to open snapshot");
+    assert!(matches!(
+        stronghold.open_named_snapshot("hot", KeyProvider::try_from(hot_key.clone()).unwrap(), &hot),
+        Err(ClientError::SnapshotAlreadyOpen(_))
+    ));
+    let mut names = stronghold.named_snapshots().unwrap();
+    names.sort();
+    assert_eq!(names, vec!["cold".to_string(), "hot".to_string()]);
+
+    for client in [
+        stronghold.create_named_client("hot", &hot_client_path).unwrap(),
+        stronghold.create_named_client("cold", &cold_client_path).unwrap(),
+        stronghold.create_client(&default_client_path).unwrap(),
+    ] {
+        client
+            .vault(location.vault_path())
+            .write_secret(location.clone(), fixed_random_bytes(32))
+            .expect("Failed to write secret");
+    }
+    assert!(matches!(
+        stronghold.create_named_client("cold", &hot_client_path),
+        Err(ClientError::ClientAlreadyLoaded(_))
+    ));
+
+    // a named client is written into its own snapshot, and can't be exported together with other snapshots
+    stronghold.write_client(&hot_client_path).unwrap();
+    assert!(stronghold
+        .export_partial(
+            &[&hot_client_path, &default_client_path],
+            &[location.vault_path()],
+            b"password",
+            &Argon2Params::default(),
+        )
+        .is_err());
+
+    stronghold.commit_named("hot").expect("Failed to commit snapshot");
+    stronghold.commit_named("cold").expect("Failed to commit snapshot");
+    stronghold
+        .commit_with_keyprovider(&default, &KeyProvider::try_from(default_key.clone()).unwrap())
+        .expect("Failed to commit snapshot");
+    assert!(matches!(
+        stronghold.commit_named("warm"),
+        Err(ClientError::SnapshotNotOpen(_))
+    ));
+
+    // the clients have only been persisted into the snapshot they are routed to
+    let stronghold = Stronghold::default();
+    stronghold
+        .open_named_snapshot("hot", KeyProvider::try_from(hot_key).unwrap(), &hot)
+        .unwrap();
+    stronghold
+        .open_named_snapshot("cold", KeyProvider::try_from(cold_key).unwrap(), &cold)
+        .unwrap();
+    stronghold
+        .load_snapshot(&KeyProvider::try_from(default_key).unwrap(), &default)
+        .unwrap();
+
+    assert!(matches!(
+        stronghold.load_named_client("hot", &cold_client_path),
+        Err(ClientError::ClientDataNotPresent)
+    ));
+    assert!(matches!(
+        stronghold.load_client(&hot_client_path),
+        Err(ClientError::ClientDataNotPresent)
+    ));
+    let hot_client = stronghold.load_named_client("hot", &hot_client_path).unwrap();
+    let cold_client = stronghold.load_named_client("cold", &cold_client_path).unwrap();
+    let default_client = stronghold.load_client(&default_client_path).unwrap();
+    for client in [&hot_client, &cold_client, &default_client] {
+        assert!(client.record_exists(&location).unwrap());
+    }
+
+    // closing a snapshot unloads its clients
+    stronghold.close_named_snapshot("cold").unwrap();
+    assert!(stronghold.get_client(&cold_client_path).is_err());
+    assert!(stronghold.get_client(&hot_client_path).is_ok());
+}
+
+#[test]
+fn test_snapshot_peek_metadata() {
+    let stronghold = Stronghold::default();
+    stronghold
+        .create_client(fixed_random_bytes(32))
+        .expect("Failed to create client");
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+
+    stronghold
+        .commit_with_keyprovider(&snapshot, &KeyProvider::try_from(fixed_random_bytes(32)).unwrap())
+        .expect("Failed to commit");
+    let info = Snapshot::peek_metadata(&snapshot).expect("Failed to read metadata");
+    assert_eq!(info.format, SnapshotFormat::Key);
+    assert_eq!(info.kdf_params, None);
+
+    let params = Argon2Params {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+    stronghold
+        .commit_with_password(&snapshot, b"password".to_vec(), &params)
+        .expect("Failed to commit");
+    let info = Snapshot::peek_metadata(&snapshot).expect("Failed to read metadata");
+    assert_eq!(info.format, SnapshotFormat::Password);
+    assert_eq!(info.kdf_params, Some(params));
+    assert_eq!(info.version, Snapshot::version(&snapshot).unwrap());
+}
+
+#[test]
+fn test_unlock_throttle() {
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    stronghold.create_client(&client_path).expect("Failed to create client");
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+    let params = Argon2Params {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+    stronghold
+        .commit_with_password(&snapshot, b"password".to_vec(), &params)
+        .expect("Failed to commit");
+
+    let stronghold = Stronghold::default();
+    stronghold
+        .set_unlock_throttle(Some(ThrottlePolicy {
+            base_delay: std::time::Duration::from_secs(60),
+            escalate_after: 1,
+            ..Default::default()
+        }))
+        .unwrap();
+    assert!(matches!(
+        stronghold.load_snapshot_with_password(b"wrong".to_vec(), &snapshot),
+        Err(ClientError::UnlockFailed { failed_attempts: 1, .. })
+    ));
+    // even the right password is rejected until the delay has passed
+    assert!(matches!(
+        stronghold.load_snapshot_with_password(b"password".to_vec(), &snapshot),
+        Err(ClientError::UnlockThrottled { failed_attempts: 1, .. })
+    ));
+    assert_eq!(stronghold.unlock_attempts(&snapshot).unwrap().failed, 1);
+
+    stronghold
+        .set_unlock_throttle(Some(ThrottlePolicy {
+            base_delay: std::time::Duration::ZERO,
+            escalate_after: 1,
+            ..Default::default()
+        }))
+        .unwrap();
+    stronghold
+        .load_snapshot_with_password(b"password".to_vec(), &snapshot)
+        .expect("Failed to load snapshot");
+    assert_eq!(stronghold.unlock_attempts(&snapshot).unwrap().failed, 0);
+    assert!(stronghold.load_client(&client_path).is_ok());
+
+    // the key derivation has been raised after the failed attempt
+    let info = Snapshot::peek_metadata(&snapshot).unwrap();
+    assert_eq!(info.kdf_params.unwrap().iterations, 2);
+
+    // a file, that isn't a snapshot, doesn't count as a failed attempt
+    let mut invalid_path = std::env::temp_dir();
+    invalid_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let invalid_defer = Defer::from((invalid_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    std::fs::write(&*invalid_defer, fixed_random_bytes(128)).unwrap();
+    let invalid = SnapshotPath::from_path(&*invalid_defer);
+    let result = stronghold.load_snapshot_with_password(b"password".to_vec(), &invalid);
+    assert!(result.is_err());
+    assert!(!matches!(result, Err(ClientError::UnlockFailed { .. })));
+    assert_eq!(stronghold.unlock_attempts(&invalid).unwrap().failed, 0);
+}
+
+#[test]
+fn test_rotate_vault_key() {
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    let client = stronghold.create_client(&client_path).expect("Failed to create client");
+    let vault_path = fixed_random_bytes(32);
+    let key_location = Location::const_generic(vault_path.clone(), fixed_random_bytes(32));
+    let revoked = Location::const_generic(vault_path.clone(), fixed_random_bytes(32));
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: key_location.clone(),
+        })
+        .expect("Failed to generate key");
+    let vault = client.vault(&vault_path);
+    vault.write_secret(revoked.clone(), fixed_random_bytes(32)).unwrap();
+    vault.revoke_secret(revoked.record_path()).unwrap();
+
+    let public_key = |client: &Client| {
+        client
+            .execute_procedure(crate::procedures::PublicKey {
+                ty: KeyType::Ed25519,
+                private_key: key_location.clone(),
+            })
+            .expect("Failed to get public key")
+    };
+    let expected = public_key(&client);
+
+    assert_eq!(client.rotate_vault_key(&vault_path).expect("Failed to rotate key"), 1);
+    assert_eq!(public_key(&client), expected);
+    assert!(client.rotate_vault_key(fixed_random_bytes(32)).is_err());
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+    let keyprovider = KeyProvider::try_from(fixed_random_bytes(32)).unwrap();
+    stronghold
+        .commit_with_keyprovider(&snapshot, &keyprovider)
+        .expect("Failed to commit");
+
+    let client = Stronghold::default()
+        .load_client_from_snapshot(&client_path, &keyprovider, &snapshot)
+        .expect("Failed to load client");
+    assert_eq!(public_key(&client), expected);
+}
+
+#[test]
+fn test_write_with_ttl() {
+    let stronghold = Stronghold::default();
+    let client = stronghold
+        .create_client(fixed_random_bytes(32))
+        .expect("Failed to create client");
+    let vault_path = fixed_random_bytes(32);
+    let expired = Location::const_generic(vault_path.clone(), fixed_random_bytes(32));
+    let live = Location::const_generic(vault_path.clone(), fixed_random_bytes(32));
+    let hint = RecordHint::new(b"ttl").unwrap();
+
+    client
+        .write_to_vault_with_ttl(&expired, fixed_random_bytes(32), hint, std::time::Duration::ZERO)
+        .expect("Failed to write record");
+    let secret = fixed_random_bytes(32);
+    client
+        .write_to_vault_with_ttl(&live, secret.clone(), hint, std::time::Duration::from_secs(60 * 60))
+        .expect("Failed to write record");
+
+    let vault = client.vault(&vault_path);
+    assert!(vault.read_secret(expired.record_path()).is_err());
+    assert_eq!(vault.read_secret(live.record_path()).unwrap(), secret);
+
+    client
+        .execute_procedure(GarbageCollect {
+            vault_path: vault_path.clone(),
+        })
+        .expect("Failed to collect garbage");
+    assert!(!client.record_exists(&expired).unwrap());
+    assert!(client.record_exists(&live).unwrap());
+}
+
+#[test]
+fn test_list_records_matching() {
+    let stronghold = Stronghold::default();
+    let client = stronghold
+        .create_client(fixed_random_bytes(32))
+        .expect("Failed to create client");
+    let vault_path = fixed_random_bytes(32);
+
+    let mut expected = Vec::new();
+    for (i, label) in ["wallet/0", "wallet/1", "seed", "wallet/2", "backup"]
+        .iter()
+        .enumerate()
+    {
+        let location = Location::const_generic(vault_path.clone(), fixed_random_bytes(32));
+        let hint = RecordHint::new(label).unwrap();
+        client
+            .write_to_vault_with_hint(&location, vec![i as u8], hint)
+            .expect("Failed to write record");
+        if label.starts_with("wallet/") {
+            expected.push(location.resolve().1);
+        }
+    }
+
+    let mut matching: Vec<_> = client
+        .list_records_matching(&vault_path, b"wallet/")
+        .expect("Failed to list records")
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    matching.sort();
+    expected.sort();
+    assert_eq!(matching, expected);
+    assert!(client.list_records_matching(&vault_path, b"none").unwrap().is_empty());
+    assert!(client
+        .list_records_matching(fixed_random_bytes(32), b"")
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_record_history() {
+    let stronghold = Stronghold::default();
+    let client = stronghold
+        .create_client(fixed_random_bytes(32))
+        .expect("Failed to create client");
+    client.set_history_depth(3).unwrap();
+    let location = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    let vault = client.vault(location.vault_path());
+
+    let versions: Vec<Vec<u8>> = (0..3).map(|_| fixed_random_bytes(32)).collect();
+    for version in versions.iter() {
+        vault.write_secret(location.clone(), version.clone()).unwrap();
+    }
+
+    let read = |n: usize| client.read_version(&location, n, |guard| Ok(guard.borrow().to_vec()));
+    assert_eq!(read(0).unwrap(), versions[2]);
+    assert_eq!(read(2).unwrap(), versions[0]);
+    assert!(read(3).is_err());
+
+    // undo the accidental overwrite
+    client.revert(&location, 1).expect("Failed to revert");
+    assert_eq!(vault.read_secret(location.record_path()).unwrap(), versions[1]);
+    assert_eq!(read(1).unwrap(), versions[0]);
+    assert!(client.revert(&location, 2).is_err());
+}
+
+#[test]
+fn test_write_batch() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    let stronghold = Stronghold::default();
+    let client = stronghold
+        .create_client(fixed_random_bytes(32))
+        .expect("Failed to create client");
+    let vault_paths = [fixed_random_bytes(32), fixed_random_bytes(32)];
+
+    let records: Vec<(Location, Vec<u8>, RecordHint)> = (0..50)
+        .map(|i| {
+            let location = Location::const_generic(vault_paths[i % 2].clone(), fixed_random_bytes(32));
+            (location, fixed_random_bytes(32), RecordHint::new(b"imported").unwrap())
+        })
+        .collect();
+
+    // the subscribers are notified after the locks have been released, and can read the written records
+    let readable = Arc::new(AtomicUsize::new(0));
+    let (observer, counter) = (client.clone(), readable.clone());
+    client.events().subscribe(move |event| {
+        if let Event::RecordWritten { location, .. } = event {
+            if observer.record_exists(location).unwrap() {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    });
+    client.write_batch(records.clone()).expect("Failed to write batch");
+    assert_eq!(readable.load(Ordering::SeqCst), records.len());
+
+    for (location, data, _) in records.iter() {
+        let vault = client.vault(location.vault_path());
+        assert_eq!(&vault.read_secret(location.record_path()).unwrap(), data);
+    }
+    for vault_path in vault_paths.iter() {
+        assert_eq!(client.list_records_matching(vault_path, b"imported").unwrap().len(), 25);
+    }
+}
+
+#[test]
+fn test_record_tags() {
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    let client = stronghold.create_client(&client_path).expect("Failed to create client");
+    let vault_path = fixed_random_bytes(32);
+    let vault = client.vault(&vault_path);
+
+    let signing = Location::const_generic(vault_path.clone(), fixed_random_bytes(32));
+    let encryption = Location::const_generic(vault_path.clone(), fixed_random_bytes(32));
+    for location in [&signing, &encryption] {
+        vault.write_secret(location.clone(), fixed_random_bytes(32)).unwrap();
+    }
+    client.set_tag(&signing, "purpose", "signing").unwrap();
+    client.set_tag(&signing, "algorithm", "ed25519").unwrap();
+    client.set_tag(&encryption, "purpose", "encryption").unwrap();
+    assert!(client
+        .set_tag(
+            &Location::const_generic(vault_path.clone(), fixed_random_bytes(32)),
+            "purpose",
+            "signing"
+        )
+        .is_err());
+
+    assert_eq!(
+        client.find_by_tag(&vault_path, "purpose", "signing").unwrap(),
+        vec![signing.resolve().1]
+    );
+    assert_eq!(client.tags(&signing).unwrap().len(), 2);
+    assert!(client.remove_tag(&signing, "algorithm").unwrap());
+    assert!(!client.remove_tag(&signing, "algorithm").unwrap());
+
+    // the tags are persisted with the vault
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+    let keyprovider = KeyProvider::try_from(fixed_random_bytes(32)).unwrap();
+    stronghold
+        .commit_with_keyprovider(&snapshot, &keyprovider)
+        .expect("Failed to commit");
+
+    let client = Stronghold::default()
+        .load_client_from_snapshot(&client_path, &keyprovider, &snapshot)
+        .expect("Failed to load client");
+    assert_eq!(
+        client.find_by_tag(&vault_path, "purpose", "encryption").unwrap(),
+        vec![encryption.resolve().1]
+    );
+    assert_eq!(client.tags(&signing).unwrap().get("purpose").unwrap(), "signing");
+    assert_eq!(
+        client
+            .vault(&vault_path)
+            .list_records(&ListOptions::default())
+            .unwrap()
+            .total,
+        2
+    );
+
+    // revoked records are not found anymore
+    client.vault(&vault_path).revoke_secret(signing.record_path()).unwrap();
+    assert!(client
+        .find_by_tag(&vault_path, "purpose", "signing")
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_read_unversioned_vaults() {
+    use crate::Provider;
+    use engine::vault::{view::Record, ChainId, DbView, Key, VaultId};
+    use std::collections::HashMap;
+
+    // the format of vaults before their entries have been versioned
+    #[derive(serde::Serialize)]
+    struct UnversionedVault<'a> {
+        key: &'a Key<Provider>,
+        entries: HashMap<ChainId, Record>,
+    }
+
+    let key = Key::random();
+    let vid = VaultId::random::<Provider>().unwrap();
+    let rid = RecordId::random::<Provider>().unwrap();
+    let mut view: DbView<Provider> = DbView::new();
+    view.write(&key, vid, rid, b"secret", RecordHint::new(b"hint").unwrap())
+        .unwrap();
+
+    let entries = view
+        .export_all()
+        .remove(&vid)
+        .unwrap()
+        .into_iter()
+        .map(|(rid, record)| (rid.into(), record))
+        .collect();
+    let mut vaults = HashMap::new();
+    vaults.insert(vid, UnversionedVault { key: &key, entries });
+    let bytes = bincode::serialize(&(vaults,)).unwrap();
+
+    let view: DbView<Provider> = bincode::deserialize(&bytes).unwrap();
+    view.get_guard::<std::convert::Infallible, _>(&key, vid, rid, |guard| {
+        assert_eq!(&*guard.borrow(), b"secret");
+        Ok(())
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_revocation_history() {
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    let client = stronghold.create_client(&client_path).expect("Failed to create client");
+    let vault_path = fixed_random_bytes(32);
+    let vault = client.vault(&vault_path);
+
+    let restored = Location::const_generic(vault_path.clone(), fixed_random_bytes(32));
+    let deleted = Location::const_generic(vault_path.clone(), fixed_random_bytes(32));
+    for location in [&restored, &deleted] {
+        vault.write_secret(location.clone(), fixed_random_bytes(32)).unwrap();
+        vault.revoke_secret(location.record_path()).unwrap();
+    }
+    assert!(vault.restore_secret(restored.record_path()).unwrap());
+    vault.cleanup().unwrap();
+
+    let events = |client: &Client| -> Vec<(AuditEvent, RecordId)> {
+        client
+            .revocation_history(&vault_path)
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.event, entry.record))
+            .collect()
+    };
+    let (restored_id, deleted_id) = (restored.resolve().1, deleted.resolve().1);
+    let expected = vec![
+        (AuditEvent::Revoked, restored_id),
+        (AuditEvent::Revoked, deleted_id),
+        (AuditEvent::Restored, restored_id),
+        (AuditEvent::Deleted, deleted_id),
+    ];
+    assert_eq!(events(&client), expected);
+
+    // the log is persisted with the vault
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+    let keyprovider = KeyProvider::try_from(fixed_random_bytes(32)).unwrap();
+    stronghold
+        .commit_with_keyprovider(&snapshot, &keyprovider)
+        .expect("Failed to commit");
+
+    let client = Stronghold::default()
+        .load_client_from_snapshot(&client_path, &keyprovider, &snapshot)
+        .expect("Failed to load client");
+    assert_eq!(events(&client), expected);
+}
+
+#[test]
+fn test_copy_record() {
+    let stronghold = Stronghold::default();
+    let source = stronghold
+        .create_client(fixed_random_bytes(32))
+        .expect("Failed to create client");
+    let destination = stronghold
+        .create_client(fixed_random_bytes(32))
+        .expect("Failed to create client");
+
+    let key_location = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    source
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: key_location.clone(),
+        })
+        .expect("Failed to generate key");
+    let public_key = |client: &Client, location: &Location| {
+        client.execute_procedure(crate::procedures::PublicKey {
+            ty: KeyType::Ed25519,
+            private_key: location.clone(),
+        })
+    };
+    let expected = public_key(&source, &key_location).unwrap();
+
+    // into another vault of the same client
+    let copy = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    source.copy_record(&key_location, &copy, None).expect("Failed to copy");
+    assert_eq!(public_key(&source, &copy).unwrap(), expected);
+
+    // onto itself, with the same client as destination
+    source.move_record(&copy, &copy, Some(&source)).expect("Failed to move");
+    assert_eq!(public_key(&source, &copy).unwrap(), expected);
+
+    // into another client
+    let moved = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    source
+        .move_record(&key_location, &moved, Some(&destination))
+        .expect("Failed to move");
+    assert_eq!(public_key(&destination, &moved).unwrap(), expected);
+    assert!(public_key(&source, &key_location).is_err());
+    assert!(source.copy_record(&key_location, &copy, Some(&destination)).is_err());
+}
+
+#[test]
+fn test_copy_record_policies() {
+    use crate::UsagePolicy;
+    use std::time::Duration;
+
+    let stronghold = Stronghold::default();
+    let client = stronghold
+        .create_client(fixed_random_bytes(32))
+        .expect("Failed to create client");
+    let vault_path = fixed_random_bytes(32);
+    let key_location = Location::const_generic(vault_path.clone(), fixed_random_bytes(32));
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: key_location.clone(),
+        })
+        .expect("Failed to generate key");
+
+    // copying out of a guarded vault has to be approved
+    client
+        .require_approval(&vault_path, Duration::from_secs(1), |_, responder| responder.deny())
+        .unwrap();
+    let copy = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    assert!(client.copy_record(&key_location, &copy, None).is_err());
+    assert!(!client.record_exists(&copy).unwrap());
+    client
+        .require_approval(&vault_path, Duration::from_secs(1), |_, responder| responder.approve())
+        .unwrap();
+    client.copy_record(&key_location, &copy, None).expect("Failed to copy");
+    client.remove_approval(&vault_path).unwrap();
+
+    // the copy counts as a use of the record
+    assert_eq!(client.usage(&key_location).unwrap().count, 1);
+    client
+        .set_usage_policy(
+            &key_location,
+            UsagePolicy {
+                max_uses: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let other = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    client.copy_record(&key_location, &other, None).expect("Failed to copy");
+    let exhausted = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    assert!(client.copy_record(&key_location, &exhausted, None).is_err());
+    assert!(!client.record_exists(&exhausted).unwrap());
+    assert_eq!(client.usage(&key_location).unwrap().count, 2);
+}
+
+#[test]
+fn test_gc_policy() {
+    let stronghold = Stronghold::default();
+    let client = stronghold.create_client(b"client_path").unwrap();
+    let vault_path = b"vault_path".to_vec();
+    let vault = client.vault(&vault_path);
+    assert_eq!(client.gc_stats(&vault_path).unwrap(), None);
+
+    let locations: Vec<Location> = (0..3)
+        .map(|i| Location::generic(vault_path.clone(), format!("record {}", i)))
+        .collect();
+    for location in locations.iter() {
+        vault.write_secret(location.clone(), fixed_random_bytes(32)).unwrap();
+    }
+
+    // manual garbage collection is the default
+    vault.revoke_secret(locations[0].record_path()).unwrap();
+    assert_eq!(client.gc_stats(&vault_path).unwrap().unwrap().revoked, 1);
+
+    client.set_gc_policy(GcPolicy::AfterNRevocations(2)).unwrap();
+    vault.revoke_secret(locations[1].record_path()).unwrap();
+    let stats = client.gc_stats(&vault_path).unwrap().unwrap();
+    assert_eq!(stats.revoked, 0);
+    assert_eq!(stats.reclaimable_bytes, 0);
+    assert!(!client.record_exists(&locations[0]).unwrap());
+    assert!(!client.record_exists(&locations[1]).unwrap());
+    assert!(client.record_exists(&locations[2]).unwrap());
+}
+
+#[test]
+fn test_vault_quota() {
+    let stronghold = Stronghold::default();
+    let client = stronghold.create_client(b"client_path").unwrap();
+    let vault_path = b"vault_path".to_vec();
+    let vault = client.vault(&vault_path);
+
+    client
+        .set_vault_quota(
+            &vault_path,
+            Quota {
+                max_records: None,
+                max_bytes: Some(64),
+            },
+        )
+        .unwrap();
+    vault
+        .write_secret(
+            Location::generic(vault_path.clone(), b"a".to_vec()),
+            fixed_random_bytes(32),
+        )
+        .unwrap();
+    vault
+        .write_secret(
+            Location::generic(vault_path.clone(), b"b".to_vec()),
+            fixed_random_bytes(32),
+        )
+        .unwrap();
+    assert!(matches!(
+        vault.write_secret(
+            Location::generic(vault_path.clone(), b"c".to_vec()),
+            fixed_random_bytes(1)
+        ),
+        Err(ClientError::VaultQuotaExceeded(_))
+    ));
+
+    // the quota of the client applies to all vaults
+    client
+        .set_quota(Quota {
+            max_records: Some(3),
+            max_bytes: None,
+        })
+        .unwrap();
+    let other = client.vault(b"other_vault_path");
+    other
+        .write_secret(
+            Location::generic(b"other_vault_path".to_vec(), b"a".to_vec()),
+            fixed_random_bytes(1),
+        )
+        .unwrap();
+    assert!(matches!(
+        other.write_secret(
+            Location::generic(b"other_vault_path".to_vec(), b"b".to_vec()),
+            fixed_random_bytes(1)
+        ),
+        Err(ClientError::VaultQuotaExceeded(_))
+    ));
+}
+
+#[test]
+fn test_auto_lock() {
+    let stronghold = Stronghold::default();
+    let client = stronghold.create_client(b"client_path").unwrap();
+    let vault_path = b"vault_path".to_vec();
+    let vault = client.vault(&vault_path);
+    let location = Location::generic(vault_path.clone(), b"record_path".to_vec());
+    let key = fixed_random_bytes(32);
+
+    client
+        .set_auto_lock(
+            std::time::Duration::from_millis(200),
+            KeyProvider::try_from(key.clone()).unwrap(),
+        )
+        .unwrap();
+    vault.write_secret(location.clone(), b"secret".to_vec()).unwrap();
+    assert!(!client.is_locked().unwrap());
+
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    assert!(client.is_locked().unwrap());
+    assert!(matches!(
+        vault.write_secret(location.clone(), b"other".to_vec()),
+        Err(ClientError::Locked)
+    ));
+    assert!(matches!(client.record_exists(&location), Ok(true)));
+    assert!(matches!(
+        client.unlock(KeyProvider::try_from(fixed_random_bytes(32)).unwrap()),
+        Err(ClientError::WrongUnlockKey)
+    ));
+
+    client.unlock(KeyProvider::try_from(key).unwrap()).unwrap();
+    assert!(!client.is_locked().unwrap());
+    assert_eq!(vault.read_secret(b"record_path").unwrap(), b"secret".to_vec());
+
+    client.lock().unwrap();
+    assert!(client.is_locked().unwrap());
+    assert!(matches!(vault.read_secret(b"record_path"), Err(ClientError::Locked)));
+}
+
+#[test]
+fn test_commit_skips_locked_clients() {
+    let filename = base64::encode(fixed_random_bytes(8)).replace('/', "n");
+    let mut pb = std::env::temp_dir();
+    pb.push(filename);
+    let snapshot_path = SnapshotPath::from_path(&pb);
+    let keyprovider = KeyProvider::try_from(fixed_random_bytes(32)).unwrap();
+    let location = Location::generic(b"vault_path".to_vec(), b"record_path".to_vec());
+
+    let stronghold = Stronghold::default();
+    let client = stronghold.create_client(b"client_path").unwrap();
+    client
+        .set_auto_lock(
+            std::time::Duration::from_secs(60),
+            KeyProvider::try_from(fixed_random_bytes(32)).unwrap(),
+        )
+        .unwrap();
+    client
+        .vault(b"vault_path")
+        .write_secret(location.clone(), b"secret".to_vec())
+        .unwrap();
+    stronghold
+        .commit_with_keyprovider(&snapshot_path, &keyprovider)
+        .unwrap();
+
+    // the locked client keeps the state of the last commit
+    client.lock().unwrap();
+    stronghold
+        .commit_with_keyprovider(&snapshot_path, &keyprovider)
+        .unwrap();
+
+    let stronghold = stronghold.reset();
+    let client = stronghold
+        .load_client_from_snapshot(b"client_path", &keyprovider, &snapshot_path)
+        .unwrap();
+    assert_eq!(
+        client.vault(b"vault_path").read_secret(b"record_path").unwrap(),
+        b"secret".to_vec()
+    );
+    std::fs::remove_file(pb).unwrap();
+}
+
+#[test]
+fn test_events() {
+    let filename = base64::encode(fixed_random_bytes(8)).replace('/', "n");
+    let mut pb = std::env::temp_dir();
+    pb.push(filename);
+    let snapshot_path = SnapshotPath::from_path(&pb);
+    let keyprovider = KeyProvider::try_from(fixed_random_bytes(32)).unwrap();
+
+    let stronghold = Stronghold::default();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    stronghold.events().subscribe_channel(sender);
+
+    let client = stronghold.create_client(b"client_path").unwrap();
+    let vault_path = b"vault_path".to_vec();
+    let location = Location::generic(vault_path.clone(), b"record_path".to_vec());
+    client
+        .vault(&vault_path)
+        .write_secret(location.clone(), fixed_random_bytes(32))
+        .unwrap();
+    assert!(matches!(
+        receiver.try_recv().unwrap(),
+        Event::RecordWritten { location: written, .. } if written.record_path() == location.record_path()
+    ));
+
+    let key = Location::generic(vault_path, b"key".to_vec());
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: key.clone(),
+        })
+        .unwrap();
+    assert!(matches!(receiver.try_recv().unwrap(), Event::RecordWritten { .. }));
+    assert!(matches!(
+        receiver.try_recv().unwrap(),
+        Event::ProcedureExecuted {
+            kind: "GenerateKey",
+            location: Some(_),
+            ..
+        }
+    ));
+
+    stronghold
+        .commit_with_keyprovider(&snapshot_path, &keyprovider)
+        .unwrap();
+    assert!(matches!(
+        receiver.try_recv().unwrap(),
+        Event::SnapshotCommitted { path } if path == pb
+    ));
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn test_transaction() {
+    let stronghold = Stronghold::default();
+    let client = stronghold.create_client(b"client").unwrap();
+    let location = Location::generic(b"vault".to_vec(), b"record".to_vec());
+    let existing = Location::generic(b"vault".to_vec(), b"existing".to_vec());
+    client
+        .vault(b"vault")
+        .write_secret(existing.clone(), b"existing".to_vec())
+        .unwrap();
+    client
+        .store()
+        .insert(b"kept".to_vec(), b"value".to_vec(), None)
+        .unwrap();
+    client
+        .store()
+        .set_limits(StoreLimits {
+            max_entry_size: Some(16),
+            ..Default::default()
+        })
+        .unwrap();
+
+    // the oversized store entry fails the transaction after the record has been written
+    let result = client.transaction(|tx| {
+        tx.write_vault(location.clone(), b"secret".to_vec())
+            .delete(existing.clone())
+            .delete_store(b"kept".to_vec())
+            .write_store(b"large".to_vec(), vec![0; 32], None);
+        Ok(())
+    });
+    assert!(matches!(result, Err(ClientError::StoreEntryTooLarge { .. })));
+    assert!(!client.record_exists(&location).unwrap());
+    assert!(client.vault(b"vault").list_revoked().unwrap().is_empty());
+    assert!(client.store().contains_key(b"kept").unwrap());
+
+    // a failing closure doesn't apply anything
+    let result: Result<(), ClientError> = client.transaction(|tx| {
+        tx.write_vault(location.clone(), b"secret".to_vec());
+        Err(ClientError::Inner("aborted".into()))
+    });
+    assert!(result.is_err());
+    assert!(!client.record_exists(&location).unwrap());
+
+    let written = client
+        .transaction(|tx| {
+            tx.write_vault(location.clone(), b"secret".to_vec())
+                .delete(existing.clone())
+                .write_store(b"small".to_vec(), b"value".to_vec(), None);
+            Ok(tx.len())
+        })
+        .unwrap();
+    assert_eq!(written, 3);
+    assert!(client.record_exists(&location).unwrap());
+    assert_eq!(client.vault(b"vault").list_revoked().unwrap().len(), 1);
+    assert_eq!(client.store().get(b"small").unwrap(), Some(b"value".to_vec()));
+
+    // records, that don't exist or have already been revoked, and unknown vaults are ignored by deletes
+    client
+        .transaction(|tx| {
+            tx.delete(Location::generic(b"vault".to_vec(), b"missing".to_vec()))
+                .delete(existing.clone())
+                .delete(Location::generic(b"unknown".to_vec(), b"record".to_vec()));
+            Ok(())
+        })
+        .unwrap();
+    assert!(client.record_exists(&location).unwrap());
+    assert_eq!(client.vault(b"vault").list_revoked().unwrap().len(), 1);
+}
+
+#[test]
+fn test_store_iteration() {
+    let stronghold = Stronghold::default();
+    let client = stronghold.create_client(b"client").unwrap();
+    let store = client.store();
+    assert!(store.is_empty().unwrap());
+
+    store.insert(b"cache/a".to_vec(), b"1".to_vec(), None).unwrap();
+    store.insert(b"cache/b".to_vec(), b"2".to_vec(), None).unwrap();
+    store
+        .insert(
+            b"cache/expired".to_vec(),
+            b"3".to_vec(),
+            Some(std::time::Duration::ZERO),
+        )
+        .unwrap();
+    store.insert(b"other".to_vec(), b"4".to_vec(), None).unwrap();
+
+    assert_eq!(store.len().unwrap(), 3);
+    let mut keys = store.keys_with_prefix(b"cache/").unwrap();
+    keys.sort();
+    assert_eq!(keys, vec![b"cache/a".to_vec(), b"cache/b".to_vec()]);
+    let mut entries = store.iter().unwrap();
+    entries.sort();
+    assert_eq!(
+        entries,
+        vec![
+            (b"cache/a".to_vec(), b"1".to_vec()),
+            (b"cache/b".to_vec(), b"2".to_vec()),
+            (b"other".to_vec(), b"4".to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn test_store_prune_expired() {
+    use std::time::Duration;
+
+    let stronghold = Stronghold::default();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    stronghold.events().subscribe_channel(sender);
+    let client = stronghold.create_client(b"client").unwrap();
+    let store = client.store();
+
+    store
+        .insert(b"session".to_vec(), b"token".to_vec(), Some(Duration::from_millis(50)))
+        .unwrap();
+    store.insert(b"settings".to_vec(), b"dark".to_vec(), None).unwrap();
+    assert!(client.store_remaining_ttl(b"session").unwrap().unwrap().unwrap() <= Duration::from_millis(50));
+    assert_eq!(client.store_remaining_ttl(b"settings").unwrap(), Some(None));
+    assert!(client.store_prune_expired().unwrap().is_empty());
+
+    std::thread::sleep(Duration::from_millis(60));
+    assert_eq!(client.store_remaining_ttl(b"session").unwrap(), None);
+    assert_eq!(client.store_prune_expired().unwrap(), vec![b"session".to_vec()]);
+    assert_eq!(store.keys().unwrap(), vec![b"settings".to_vec()]);
+    assert!(matches!(
+        receiver.try_recv().unwrap(),
+        Event::StoreEntryExpired { key, .. } if key == b"session"
+    ));
+
+    // the background sweep prunes without explicit calls
+    store
+        .insert(b"session".to_vec(), b"token".to_vec(), Some(Duration::ZERO))
+        .unwrap();
+    client.set_store_sweep(Some(Duration::from_millis(10)));
+    assert!(matches!(
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap(),
+        Event::StoreEntryExpired { key, .. } if key == b"session"
+    ));
+    client.set_store_sweep(None);
+    assert_eq!(store.keys().unwrap(), vec![b"settings".to_vec()]);
+}
+
+#[test]
+fn test_store_atomic_update() {
+    let stronghold = Stronghold::default();
+    let client = stronghold.create_client(b"client").unwrap();
+
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let store = client.store();
+            std::thread::spawn(move || {
+                for _ in 0..100 {
+                    store
+                        .update_with(b"nonce", |value| {
+                            let nonce = value.map_or(0, |bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+                            Some((nonce + 1).to_le_bytes().to_vec())
+                        })
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+    handles.into_iter().for_each(|handle| handle.join().unwrap());
+
+    let store = client.store();
+    let expected = 800u64.to_le_bytes();
+    assert_eq!(store.get(b"nonce").unwrap(), Some(expected.to_vec()));
+    assert!(!store.compare_and_swap(b"nonce", Some(&[0; 8]), None).unwrap());
+    assert!(store.compare_and_swap(b"nonce", Some(&expected), None).unwrap());
+    assert!(!store.contains_key(b"nonce").unwrap());
+    assert_eq!(store.update_with(b"nonce", |_| None).unwrap(), None);
+}
+
+#[test]
+fn test_store_namespaces() {
+    let stronghold = Stronghold::default();
+    let client = stronghold.create_client(b"client").unwrap();
+    let wallet = client.store_namespace("wallet");
+    let nested = client.store_namespace("wallet/accounts");
+    client.store().insert(b"plain".to_vec(), vec![0], None).unwrap();
+
+    wallet.insert(b"a", vec![1], None).unwrap();
+    wallet.insert(b"b", vec![2], None).unwrap();
+    nested.insert(b"a", vec![3], None).unwrap();
+    assert_eq!(wallet.get(b"a").unwrap(), Some(vec![1]));
+    assert_eq!(nested.get(b"a").unwrap(), Some(vec![3]));
+
+    let mut keys = wallet.keys().unwrap();
+    keys.sort();
+    assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec()]);
+    assert_eq!(nested.len().unwrap(), 1);
+
+    wallet.clear().unwrap();
+    assert!(wallet.is_empty().unwrap());
+    assert_eq!(nested.get(b"a").unwrap(), Some(vec![3]));
+    assert!(client.store().contains_key(b"plain").unwrap());
+}
+
+#[test]
+fn test_snapshot_migrate() {
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    stronghold.create_client(&client_path).expect("Failed to create client");
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+    let key = fixed_random_bytes(32);
+
+    stronghold
+        .commit_with_keyprovider(&snapshot, &KeyProvider::try_from(key.clone()).unwrap())
+        .expect("Failed to commit");
+
+    let version = Snapshot::version(&snapshot).expect("Failed to detect version");
+    assert_eq!(version, engine::snapshot::VERSION);
+
+    let key: [u8; 32] = key.try_into().unwrap();
+    Snapshot::migrate(&snapshot, version, version, key).expect("Migrating to the same version failed");
+    assert!(Snapshot::migrate(&snapshot, [0, 1], version, key).is_err());
+    assert!(Snapshot::migrate(&snapshot, version, [0xff, 0xff], key).is_err());
+
+    let stronghold = stronghold.reset();
+    assert!(stronghold
+        .load_client_from_snapshot(&client_path, &KeyProvider::try_from(key.to_vec()).unwrap(), &snapshot)
+        .is_ok());
+}
+
+#[test]
+fn test_commit_incremental() {
+    let stronghold = Stronghold::default();
+    let client_path_a = fixed_random_bytes(32);
+    let client_path_b = fixed_random_bytes(32);
+    let location = Location::const_generic(fixed_random_bytes(32), fixed_random_bytes(32));
+    let client_a = stronghold
+        .create_client(&client_path_a)
+        .expect("Failed to create client");
+    let client_b = stronghold
+        .create_client(&client_path_b)
+        .expect("Failed to create client");
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+    let key = fixed_random_bytes(32);
+    let keyprovider = KeyProvider::try_from(key.clone()).unwrap();
+
+    // the first commit writes all clients
+    assert_eq!(stronghold.commit_incremental(&snapshot, &keyprovider).unwrap(), 2);
+
+    // nothing has changed
+    assert_eq!(stronghold.commit_incremental(&snapshot, &keyprovider).unwrap(), 0);
+    let len = std::fs::metadata(snapshot.as_path()).unwrap().len();
+    assert_eq!(stronghold.commit_incremental(&snapshot, &keyprovider).unwrap(), 0);
+    assert_eq!(std::fs::metadata(snapshot.as_path()).unwrap().len(), len);
+
+    // only the modified client is written
+    client_a
+        .vault(location.vault_path())
+        .write_secret(location.clone(), fixed_random_bytes(32))
+        .expect("Failed to write secret");
+    client_b
+        .store()
+        .insert(b"key".to_vec(), b"value".to_vec(), None)
+        .unwrap();
+    assert_eq!(stronghold.commit_incremental(&snapshot, &keyprovider).unwrap(), 2);
+    client_b.store().get(b"key").unwrap();
+    assert_eq!(stronghold.commit_incremental(&snapshot, &keyprovider).unwrap(), 0);
+    client_b.store().delete(b"key").unwrap();
+    assert_eq!(stronghold.commit_incremental(&snapshot, &keyprovider).unwrap(), 1);
+
+    let stronghold = stronghold.reset();
+    assert!(stronghold
+        .load_incremental_snapshot(&KeyProvider::try_from(fixed_random_bytes(32)).unwrap(), &snapshot)
+        .is_err());
+    stronghold
+        .load_incremental_snapshot(&keyprovider, &snapshot)
+        .expect("Failed to load incremental snapshot");
+
+    let client_a = stronghold.load_client(&client_path_a).expect("Failed to load client");
+    assert!(client_a.record_exists(&location).unwrap());
+    let client_b = stronghold.load_client(&client_path_b).expect("Failed to load client");
+    assert!(!client_b.store().contains_key(b"key").unwrap());
+
+    // a purged client is removed from the file
+    stronghold.purge_client(client_b).unwrap();
+    assert_eq!(stronghold.commit_incremental(&snapshot, &keyprovider).unwrap(), 1);
+    let stronghold = stronghold.reset();
+    stronghold.load_incremental_snapshot(&keyprovider, &snapshot).unwrap();
+    assert!(stronghold.load_client(&client_path_b).is_err());
+    assert!(stronghold.load_client(&client_path_a).is_ok());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_api() {
+    let stronghold = Stronghold::default();
+    let client_path = fixed_random_bytes(32);
+    let vault_path = fixed_random_bytes(32);
+    let secret = fixed_random_bytes(32);
+    let location = Location::const_generic(vault_path.clone(), fixed_random_bytes(32));
+
+    let client = stronghold.create_client(&client_path).expect("Failed to create client");
+    client
+        .write_to_vault_async(location.clone(), secret.clone())
+        .await
+        .expect("Failed to write secret");
+
+    let key_location = Location::const_generic(vault_path, fixed_random_bytes(32));
+    let output = client
+        .execute_procedure_async(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: key_location.clone(),
+        })
+        .await;
+    assert!(output.is_ok());
+    assert!(client.record_exists(&key_location).unwrap());
+
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push(base64::encode(fixed_random_bytes(16)).replace('/', "n"));
+    let defer = Defer::from((snapshot_path, |path: &'_ PathBuf| {
+        let _ = std::fs::remove_file(path);
+    }));
+    let snapshot = SnapshotPath::from_path(&*defer);
+    let key = fixed_random_bytes(32);
+
+    stronghold
+        .commit_with_keyprovider_async(snapshot.clone(), KeyProvider::try_from(key.clone()).unwrap())
+        .await
+        .expect("Failed to commit");
+
+    let stronghold = stronghold.reset();
+    let client = stronghold
+        .load_client_from_snapshot_async(client_path, KeyProvider::try_from(key).unwrap(), snapshot)
+        .await
+        .expect("Failed to load client");
+    assert!(client.record_exists(&location).unwrap());
+    assert!(client.record_exists(&key_location).unwrap());
+}
+
+#[test]
+fn test_generate_with_backend_checks_unwrap() {
+    use crate::KeyBackend;
+    use std::time::Duration;
+
+    // wraps keys, but can't restore them, e.g. because the hardware key has not been persisted
+    struct Forgetful;
+
+    impl KeyBackend for Forgetful {
+        fn wrap(&self, key: &[u8]) -> Result<Vec<u8>, ClientError> {
+            Ok(key.to_vec())
+        }
+
+        fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, ClientError> {
+            Ok(vec![0; wrapped.len()])
+        }
+    }
+
+    assert!(matches!(
+        KeyProvider::generate_with_backend(Forgetful, Duration::ZERO),
+        Err(ClientError::KeyBackend(_))
+    ));
+}
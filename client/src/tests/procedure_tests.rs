@@ -6,19 +6,33 @@ use crypto::ciphers::aes_kw::AesKeyWrap;
 #[cfg(feature = "insecure")]
 use crate::procedures::CompareSecret;
 
+#[cfg(feature = "ml-dsa")]
+use crate::procedures::{ml_dsa, MlDsaGenerateKey, MlDsaLevel, MlDsaPublicKey, MlDsaSign};
+
+#[cfg(feature = "bls")]
+use crate::procedures::{bls, BlsAggregate, BlsGenerateKey, BlsProofOfPossession, BlsPublicKey, BlsSign};
+
 use crate::{
     procedures::{
         AeadCipher, AeadDecrypt, AeadEncrypt, AesKeyWrapCipher, AesKeyWrapDecrypt, AesKeyWrapEncrypt, BIP39Generate,
-        BIP39Recover, ConcatKdf, CopyRecord, DeriveSecret, Ed25519Sign, GenerateKey, GenerateSecret, Hkdf, KeyType,
-        MnemonicLanguage, PublicKey, Sha2Hash, Slip10Derive, Slip10DeriveInput, Slip10Generate, StrongholdProcedure,
+        BIP39Recover, BIP39Validate, Bip44Path, CertificateEncoding, Chain, ConcatKdf, CopyRecord, Curve,
+        CustomProcedure, DeriveSecret, DistinguishedName, DynProcedure, EcdsaSignPrehashed, Ed25519Sign,
+        Ed25519SignPrehashed, ExportWrappedKey, FatalProcedureError, GenerateCsr, GenerateKey, GenerateSecret, Hkdf,
+        ImportWrappedKey, KeyType, MnemonicLanguage, MnemonicLength, OutputTarget, PaperBackupEncoding,
+        PaperBackupExport, PaperBackupImport, Pipeline, Products, PublicKey, SecureRng, SelfSignedCertificate,
+        Sha2Hash, Slip10Derive, Slip10DeriveInput, Slip10Generate, SssRecover, SssSplit, StrongholdProcedure,
         WriteVault, X25519DiffieHellman,
     },
     tests::fresh,
-    Client, Location, Stronghold,
+    Client, Location, Stronghold, UsagePolicy,
 };
 
 use crypto::{
     ciphers::{aes_gcm::Aes256Gcm, chacha::XChaCha20Poly1305},
+    hashes::{
+        sha::{Sha256, Sha512},
+        Digest,
+    },
     keys::slip10::ChainCode,
     signatures::ed25519,
 };
@@ -249,9 +263,10 @@ async fn usecase_ed25519() -> Result<(), Box<dyn std::error::Error>> {
         assert!(client.execute_procedure(slip10_generate).is_ok());
     } else {
         let bip32_gen = BIP39Generate {
-            passphrase: random::passphrase(),
+            passphrase: random::passphrase().map(Into::into),
             output: seed.clone(),
             language: MnemonicLanguage::English,
+            length: MnemonicLength::Words24,
         };
         assert!(client.execute_procedure(bip32_gen).is_ok());
     }
@@ -260,6 +275,7 @@ async fn usecase_ed25519() -> Result<(), Box<dyn std::error::Error>> {
     let key = Location::generic(vault_path, random::variable_bytestring(1024));
 
     let slip10_derive = Slip10Derive {
+        curve: Curve::Ed25519,
         chain,
         input: Slip10DeriveInput::Seed(seed),
         output: key.clone(),
@@ -270,7 +286,7 @@ async fn usecase_ed25519() -> Result<(), Box<dyn std::error::Error>> {
         private_key: key.clone(),
         ty: KeyType::Ed25519,
     };
-    let pk: [u8; ed25519::PUBLIC_KEY_LENGTH] = client.execute_procedure(ed25519_pk).unwrap();
+    let pk: Vec<u8> = client.execute_procedure(ed25519_pk).unwrap();
 
     let msg = fresh::variable_bytestring(4096);
 
@@ -280,7 +296,7 @@ async fn usecase_ed25519() -> Result<(), Box<dyn std::error::Error>> {
     };
     let sig: [u8; ed25519::SIGNATURE_LENGTH] = client.execute_procedure(ed25519_sign).unwrap();
 
-    let pk = ed25519::PublicKey::try_from_bytes(pk).unwrap();
+    let pk = ed25519::PublicKey::try_from_bytes(pk.try_into().unwrap()).unwrap();
     let sig = ed25519::Signature::from_bytes(sig);
     assert!(pk.verify(&sig, &msg));
 
@@ -305,6 +321,7 @@ async fn usecase_slip10derive_intermediate_keys() -> Result<(), Box<dyn std::err
 
     let cc0: ChainCode = {
         let slip10_derive = Slip10Derive {
+            curve: Curve::Ed25519,
             input: Slip10DeriveInput::Seed(seed.clone()),
             chain: chain0.join(&chain1),
             output: fresh::location(),
@@ -317,6 +334,7 @@ async fn usecase_slip10derive_intermediate_keys() -> Result<(), Box<dyn std::err
         let intermediate = fresh::location();
 
         let slip10_derive_intermediate = Slip10Derive {
+            curve: Curve::Ed25519,
             input: Slip10DeriveInput::Seed(seed),
             chain: chain0,
             output: intermediate.clone(),
@@ -325,6 +343,7 @@ async fn usecase_slip10derive_intermediate_keys() -> Result<(), Box<dyn std::err
         assert!(client.execute_procedure(slip10_derive_intermediate).is_ok());
 
         let slip10_derive_child = Slip10Derive {
+            curve: Curve::Ed25519,
             input: Slip10DeriveInput::Key(intermediate),
             chain: chain1,
             output: fresh::location(),
@@ -349,6 +368,7 @@ async fn usecase_ed25519_as_complex() -> Result<(), Box<dyn std::error::Error>>
         output: fresh::location(),
     };
     let derive = Slip10Derive {
+        curve: Curve::Ed25519,
         input: Slip10DeriveInput::Seed(generate.target().clone()),
         output: fresh::location(),
         chain: fresh::hd_path().1,
@@ -662,10 +682,12 @@ async fn usecase_recover_bip39() -> Result<(), Box<dyn std::error::Error>> {
 
     let generate_bip39 = BIP39Generate {
         language: MnemonicLanguage::English,
-        passphrase: Some(passphrase.clone()),
+        length: MnemonicLength::Words24,
+        passphrase: Some(passphrase.clone().into()),
         output: fresh::location(),
     };
     let derive_from_original = Slip10Derive {
+        curve: Curve::Ed25519,
         input: Slip10DeriveInput::Seed(generate_bip39.target().clone()),
         chain: chain.clone(),
         output: fresh::location(),
@@ -682,16 +704,17 @@ async fn usecase_recover_bip39() -> Result<(), Box<dyn std::error::Error>> {
     ];
     let output = client.execute_procedure_chained(procedures).unwrap();
 
-    let mnemonic = output[0].clone().try_into().unwrap(); // ?
+    let mnemonic: String = output[0].clone().try_into().unwrap();
     let signed_with_original = output[2].clone();
 
     let recover_bip39 = BIP39Recover {
-        mnemonic,
-        passphrase: Some(passphrase),
+        mnemonic: mnemonic.into(),
+        passphrase: Some(passphrase.into()),
         output: fresh::location(),
     };
 
     let derive_from_recovered = Slip10Derive {
+        curve: Curve::Ed25519,
         input: Slip10DeriveInput::Seed(recover_bip39.target().clone()),
         chain,
         output: fresh::location(),
@@ -785,15 +808,16 @@ async fn test_bip39_recover_zeroize() -> Result<(), Box<dyn std::error::Error>>
 
     let bip39_generate = BIP39Generate {
         language: MnemonicLanguage::English,
+        length: MnemonicLength::Words24,
         output: location_a,
-        passphrase: Some(passphrase.clone()),
+        passphrase: Some(passphrase.clone().into()),
     };
 
     let mnemonic = client.execute_procedure(bip39_generate)?;
 
     let bip39_recover = BIP39Recover {
-        passphrase: Some(passphrase),
-        mnemonic,
+        passphrase: Some(passphrase.into()),
+        mnemonic: mnemonic.into(),
         output: location_b,
     };
 
@@ -852,3 +876,1482 @@ fn test_usecase_concatkdf() {
     let result = result.unwrap();
     assert!(result[0] == 1, "failed: ({:?})", result);
 }
+
+#[test]
+fn usecase_procedure_approval() {
+    use crate::procedures::ProcedureError;
+    use std::{sync::Mutex, time::Duration};
+
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    let guarded: Location = fresh::location();
+    let unguarded: Location = fresh::location();
+
+    client
+        .require_approval(guarded.vault_path(), Duration::from_secs(5), |request, responder| {
+            assert!(matches!(request.procedure, StrongholdProcedure::GenerateKey(_)));
+            std::thread::spawn(move || responder.approve());
+        })
+        .unwrap();
+
+    let generate = |output: &Location| GenerateKey {
+        ty: KeyType::Ed25519,
+        output: output.clone(),
+    };
+
+    assert!(client.execute_procedure(generate(&guarded)).is_ok());
+    assert!(client.execute_procedure(generate(&unguarded)).is_ok());
+
+    // explicit denial
+    client
+        .require_approval(guarded.vault_path(), Duration::from_secs(5), |_, responder| {
+            responder.deny()
+        })
+        .unwrap();
+    let result = client.execute_procedure(PublicKey {
+        ty: KeyType::Ed25519,
+        private_key: guarded.clone(),
+    });
+    assert!(matches!(result, Err(ProcedureError::NotApproved)));
+
+    // a denied procedure doesn't remove the record at its output
+    let result = client.execute_procedure(generate(&guarded));
+    assert!(matches!(result, Err(ProcedureError::NotApproved)));
+    assert!(client.record_exists(&guarded).unwrap());
+
+    // no answer within the timeout
+    let pending = std::sync::Arc::new(Mutex::new(Vec::new()));
+    let pending_ref = pending.clone();
+    client
+        .require_approval(guarded.vault_path(), Duration::from_millis(10), move |_, responder| {
+            pending_ref.lock().unwrap().push(responder)
+        })
+        .unwrap();
+    let result = client.execute_procedure(PublicKey {
+        ty: KeyType::Ed25519,
+        private_key: guarded.clone(),
+    });
+    assert!(matches!(result, Err(ProcedureError::NotApproved)));
+    assert_eq!(pending.lock().unwrap().len(), 1);
+
+    assert!(client.remove_approval(guarded.vault_path()).unwrap());
+    assert!(client
+        .execute_procedure(PublicKey {
+            ty: KeyType::Ed25519,
+            private_key: guarded,
+        })
+        .is_ok());
+}
+
+#[test]
+fn usecase_abort_operation() {
+    use crate::{procedures::ProcedureError, OperationStep};
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+    let location: Location = fresh::location();
+
+    // keep the request pending, until the operation is aborted
+    let pending = Arc::new(Mutex::new(Vec::new()));
+    let pending_ref = pending.clone();
+    client
+        .require_approval(location.vault_path(), Duration::from_secs(60), move |_, responder| {
+            pending_ref.lock().unwrap().push(responder)
+        })
+        .unwrap();
+
+    let executing = client.clone();
+    let output = location.clone();
+    let handle = std::thread::spawn(move || {
+        executing.execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output,
+        })
+    });
+
+    let operation = loop {
+        if let Some(operation) = client.list_operations().unwrap().pop() {
+            break operation;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    assert_eq!(operation.client_id, *client.id());
+    assert!(matches!(
+        operation.procedures.as_slice(),
+        [OperationStep {
+            name: "GenerateKey",
+            target: Some(_),
+        }]
+    ));
+
+    assert!(client.abort(operation.id).unwrap());
+    assert!(matches!(handle.join().unwrap(), Err(ProcedureError::Aborted)));
+    assert!(!client.record_exists(&location).unwrap());
+
+    assert!(client.list_operations().unwrap().is_empty());
+    assert!(!client.abort(operation.id).unwrap());
+}
+
+#[test]
+fn usecase_abort_keeps_existing_records() {
+    use crate::procedures::ProcedureError;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+    let first: Location = fresh::location();
+    let existing: Location = fresh::location();
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: existing.clone(),
+        })
+        .unwrap();
+
+    // the second step waits for an approval, until the operation is aborted
+    let pending = Arc::new(Mutex::new(Vec::new()));
+    let pending_ref = pending.clone();
+    client
+        .require_approval(existing.vault_path(), Duration::from_secs(60), move |_, responder| {
+            pending_ref.lock().unwrap().push(responder)
+        })
+        .unwrap();
+
+    let executing = client.clone();
+    let (first_output, existing_output) = (first.clone(), existing.clone());
+    let handle = std::thread::spawn(move || {
+        executing.execute_procedure_chained(vec![
+            GenerateKey {
+                ty: KeyType::Ed25519,
+                output: first_output,
+            }
+            .into(),
+            GenerateKey {
+                ty: KeyType::Ed25519,
+                output: existing_output,
+            }
+            .into(),
+        ])
+    });
+
+    while pending.lock().unwrap().is_empty() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let operation = client.list_operations().unwrap().pop().unwrap();
+    assert!(client.abort(operation.id).unwrap());
+    assert!(matches!(handle.join().unwrap(), Err(ProcedureError::Aborted)));
+
+    // the record of the first step is rolled back, the existing one at the output of the aborted step is kept
+    assert!(!client.record_exists(&first).unwrap());
+    assert!(client.record_exists(&existing).unwrap());
+}
+
+#[test]
+fn usecase_inherited_approval() {
+    use crate::{procedures::ProcedureError, VaultPath};
+    use std::time::Duration;
+
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    let team = VaultPath::new("org/team");
+    let generate = |vault_path: VaultPath| GenerateKey {
+        ty: KeyType::Ed25519,
+        output: Location::generic(vault_path, b"key".to_vec()),
+    };
+
+    // the whole subtree of the team is guarded, but a single service is explicitly approved
+    client
+        .require_approval(&team, Duration::from_secs(5), |_, responder| responder.deny())
+        .unwrap();
+    client
+        .require_approval(team.join("service"), Duration::from_secs(5), |_, responder| {
+            responder.approve()
+        })
+        .unwrap();
+
+    assert!(matches!(
+        client.execute_procedure(generate(team.join("wallet"))),
+        Err(ProcedureError::NotApproved)
+    ));
+    assert!(matches!(
+        client.execute_procedure(generate(team.join("wallet").join("cold"))),
+        Err(ProcedureError::NotApproved)
+    ));
+    assert!(client.execute_procedure(generate(team.join("service"))).is_ok());
+    assert!(client.execute_procedure(generate(VaultPath::new("org/other"))).is_ok());
+
+    // patterns guard matching paths across subtrees
+    client
+        .require_approval("org/*/backup", Duration::from_secs(5), |_, responder| responder.deny())
+        .unwrap();
+    assert!(matches!(
+        client.execute_procedure(generate(VaultPath::new("org/other/backup"))),
+        Err(ProcedureError::NotApproved)
+    ));
+    assert!(client
+        .execute_procedure(generate(VaultPath::new("org/other/backup/old")))
+        .is_ok());
+}
+
+#[test]
+fn usecase_key_escrow() {
+    use crate::{EscrowConfig, EscrowEntry};
+    use crypto::keys::x25519;
+
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    let recovery_key = x25519::SecretKey::generate().unwrap();
+    let store_key = b"escrow".to_vec();
+    client
+        .set_escrow(Some(EscrowConfig {
+            recipient: recovery_key.public_key(),
+            store_key: store_key.clone(),
+        }))
+        .unwrap();
+
+    let location = fresh::location();
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: location.clone(),
+        })
+        .unwrap();
+    let public_key: Vec<u8> = client
+        .execute_procedure(PublicKey {
+            ty: KeyType::Ed25519,
+            private_key: location.clone(),
+        })
+        .unwrap();
+
+    // only generated keys are escrowed
+    client
+        .execute_procedure(WriteVault {
+            location: fresh::location(),
+            data: random::fixed_bytestring(32),
+        })
+        .unwrap();
+
+    let entries = EscrowEntry::decode_all(&client.store().get(&store_key).unwrap().unwrap()).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].location.vault_path(), location.vault_path());
+
+    let associated_data = bincode::serialize(&location).unwrap();
+    let secret = engine::snapshot::read(
+        &mut entries[0].sealed.as_slice(),
+        &recovery_key.to_bytes(),
+        &associated_data,
+    )
+    .unwrap();
+    let secret_key = ed25519::SecretKey::from_bytes(secret.try_into().unwrap());
+    assert_eq!(secret_key.public_key().to_bytes().to_vec(), public_key);
+
+    client.set_escrow(None).unwrap();
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: fresh::location(),
+        })
+        .unwrap();
+    let entries = EscrowEntry::decode_all(&client.store().get(&store_key).unwrap().unwrap()).unwrap();
+    assert_eq!(entries.len(), 1);
+}
+
+#[test]
+fn usecase_key_escrow_store_limits() {
+    use crate::{EscrowConfig, StoreLimits};
+    use crypto::keys::x25519;
+
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+    client
+        .set_escrow(Some(EscrowConfig {
+            recipient: x25519::SecretKey::generate().unwrap().public_key(),
+            store_key: b"escrow".to_vec(),
+        }))
+        .unwrap();
+    client
+        .store()
+        .set_limits(StoreLimits {
+            max_entry_size: Some(16),
+            ..Default::default()
+        })
+        .unwrap();
+
+    // the escrowed key doesn't fit into the store, so the generated key is revoked again
+    let location = fresh::location();
+    assert!(client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: location.clone(),
+        })
+        .is_err());
+    assert!(client.store().get(b"escrow").unwrap().is_none());
+    assert!(!client.record_exists(&location).unwrap());
+}
+
+#[test]
+fn usecase_custom_entropy_source() {
+    use crate::procedures::{FatalProcedureError, SecureRng};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    // a broken source that always returns zeros must not weaken the generated keys
+    struct ZeroSource(Arc<AtomicUsize>);
+
+    impl SecureRng for ZeroSource {
+        fn fill(&self, buf: &mut [u8]) -> Result<(), FatalProcedureError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            buf.iter_mut().for_each(|b| *b = 0);
+            Ok(())
+        }
+    }
+
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+    let calls = Arc::new(AtomicUsize::new(0));
+    client.set_entropy_source(ZeroSource(calls.clone())).unwrap();
+
+    let mut public_keys = Vec::new();
+    for _ in 0..2 {
+        let location = fresh::location();
+        client
+            .execute_procedure(GenerateKey {
+                ty: KeyType::Ed25519,
+                output: location.clone(),
+            })
+            .unwrap();
+        let public_key: Vec<u8> = client
+            .execute_procedure(PublicKey {
+                ty: KeyType::Ed25519,
+                private_key: location,
+            })
+            .unwrap();
+        public_keys.push(public_key);
+    }
+    client
+        .execute_procedure(Slip10Generate {
+            size_bytes: None,
+            output: fresh::location(),
+        })
+        .unwrap();
+
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+    assert_ne!(public_keys[0], public_keys[1]);
+}
+
+#[test]
+fn usecase_procedure_history() {
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+    let location = fresh::location();
+    let generate = GenerateKey {
+        ty: KeyType::Ed25519,
+        output: location.clone(),
+    };
+    let sign = Ed25519Sign {
+        private_key: location.clone(),
+        msg: b"message".to_vec(),
+    };
+
+    // disabled by default
+    client.execute_procedure(generate.clone()).unwrap();
+    assert!(client.procedure_history().unwrap().is_empty());
+
+    client.set_procedure_history(2).unwrap();
+    client.execute_procedure(generate).unwrap();
+    client.execute_procedure(sign.clone()).unwrap();
+    assert!(client
+        .execute_procedure(PublicKey {
+            ty: KeyType::Ed25519,
+            private_key: fresh::location(),
+        })
+        .is_err());
+
+    let history = client.procedure_history().unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].procedure, "Ed25519Sign");
+    assert_eq!(history[0].vault_paths, vec![location.vault_path().to_vec()]);
+    assert!(history[0].success);
+    assert_eq!(history[1].procedure, "PublicKey");
+    assert!(!history[1].success);
+    assert!(history[0].started_at <= history[1].started_at);
+
+    client.set_procedure_history(0).unwrap();
+    client.execute_procedure(sign).unwrap();
+    assert!(client.procedure_history().unwrap().is_empty());
+}
+
+#[cfg(feature = "insecure")]
+#[test]
+fn usecase_deterministic_rng() {
+    use crate::procedures::{FatalProcedureError, SecureRng};
+
+    struct FixedRng;
+
+    impl SecureRng for FixedRng {
+        fn fill(&self, buf: &mut [u8]) -> Result<(), FatalProcedureError> {
+            buf.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+            Ok(())
+        }
+    }
+
+    let stronghold: Stronghold = Stronghold::default();
+    let mut public_keys = Vec::new();
+    for path in [b"client_a", b"client_b"] {
+        let client: Client = stronghold.create_client(path).unwrap();
+        client.set_rng(FixedRng).unwrap();
+        let location = fresh::location();
+        client
+            .execute_procedure(GenerateKey {
+                ty: KeyType::Ed25519,
+                output: location.clone(),
+            })
+            .unwrap();
+        let public_key: [u8; 32] = client
+            .execute_procedure(PublicKey {
+                ty: KeyType::Ed25519,
+                private_key: location,
+            })
+            .unwrap();
+        public_keys.push(public_key);
+    }
+    assert_eq!(public_keys[0], public_keys[1]);
+}
+
+#[test]
+fn usecase_paper_backup() {
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+    let key = fresh::location();
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: key.clone(),
+        })
+        .unwrap();
+    let public_key = |location: &Location| {
+        client
+            .execute_procedure(PublicKey {
+                ty: KeyType::Ed25519,
+                private_key: location.clone(),
+            })
+            .unwrap()
+    };
+
+    for encoding in [PaperBackupEncoding::Mnemonic, PaperBackupEncoding::Base32] {
+        let shards = client
+            .execute_procedure(PaperBackupExport {
+                secret: key.clone(),
+                encoding,
+            })
+            .unwrap();
+        assert_eq!(shards.0.len(), 2);
+        if encoding == PaperBackupEncoding::Mnemonic {
+            assert!(shards.0.iter().all(|shard| shard.split_whitespace().count() == 24));
+        }
+
+        let mut reversed = shards.0.clone();
+        reversed.reverse();
+        let restored = fresh::location();
+        client
+            .execute_procedure(PaperBackupImport {
+                shards: reversed,
+                encoding,
+                output: restored.clone(),
+            })
+            .unwrap();
+        assert_eq!(public_key(&restored), public_key(&key));
+
+        // all shards are required
+        assert!(client
+            .execute_procedure(PaperBackupImport {
+                shards: shards.0[..1].to_vec(),
+                encoding,
+                output: fresh::location(),
+            })
+            .is_err());
+    }
+}
+
+#[test]
+fn usecase_shamir_secret_sharing() {
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+    let key = fresh::location();
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: key.clone(),
+        })
+        .unwrap();
+    let public_key = |location: &Location| {
+        client
+            .execute_procedure(PublicKey {
+                ty: KeyType::Ed25519,
+                private_key: location.clone(),
+            })
+            .unwrap()
+    };
+
+    let shares: Vec<Location> = (0..5).map(|_| fresh::location()).collect();
+    client
+        .execute_procedure(SssSplit {
+            secret: key.clone(),
+            threshold: 3,
+            shares: shares.clone(),
+        })
+        .unwrap();
+    assert!(shares.iter().all(|share| client.record_exists(share).unwrap()));
+
+    for subset in [
+        vec![shares[0].clone(), shares[1].clone(), shares[2].clone()],
+        vec![shares[4].clone(), shares[1].clone(), shares[3].clone()],
+        shares.clone(),
+    ] {
+        let recovered = fresh::location();
+        client
+            .execute_procedure(SssRecover {
+                shares: subset,
+                output: recovered.clone(),
+            })
+            .unwrap();
+        assert_eq!(public_key(&recovered), public_key(&key));
+    }
+
+    // less shares than the threshold
+    assert!(client
+        .execute_procedure(SssRecover {
+            shares: shares[..2].to_vec(),
+            output: fresh::location(),
+        })
+        .is_err());
+
+    // the threshold can't exceed the number of shares
+    assert!(client
+        .execute_procedure(SssSplit {
+            secret: key,
+            threshold: 3,
+            shares: shares[..2].to_vec(),
+        })
+        .is_err());
+}
+
+#[test]
+fn usecase_concurrent_procedures() {
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    let keys: Vec<Location> = (0..4)
+        .map(|i| Location::generic(format!("vault {}", i), "key"))
+        .collect();
+    for key in keys.iter() {
+        client
+            .execute_procedure(GenerateKey {
+                ty: KeyType::Ed25519,
+                output: key.clone(),
+            })
+            .unwrap();
+    }
+
+    // signing with the keys of different vaults, while new keys are generated into the same vaults
+    let handles: Vec<_> = keys
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(i, key)| {
+            let client = client.clone();
+            std::thread::spawn(move || {
+                let pk: Vec<u8> = client
+                    .execute_procedure(PublicKey {
+                        ty: KeyType::Ed25519,
+                        private_key: key.clone(),
+                    })
+                    .unwrap();
+                let pk = ed25519::PublicKey::try_from_bytes(pk.try_into().unwrap()).unwrap();
+                for n in 0..16 {
+                    let msg = fresh::variable_bytestring(256);
+                    let sig: [u8; ed25519::SIGNATURE_LENGTH] = client
+                        .execute_procedure(Ed25519Sign {
+                            private_key: key.clone(),
+                            msg: msg.clone(),
+                        })
+                        .unwrap();
+                    assert!(pk.verify(&ed25519::Signature::from_bytes(sig), &msg));
+
+                    client
+                        .execute_procedure(GenerateKey {
+                            ty: KeyType::Ed25519,
+                            output: Location::generic(format!("vault {}", i), format!("generated {}", n)),
+                        })
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+    handles.into_iter().for_each(|handle| handle.join().unwrap());
+
+    for i in 0..4 {
+        for n in 0..16 {
+            assert!(client
+                .record_exists(&Location::generic(format!("vault {}", i), format!("generated {}", n)))
+                .unwrap());
+        }
+    }
+}
+
+#[test]
+fn usecase_usage_policy() {
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    let key = fresh::location();
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: key.clone(),
+        })
+        .unwrap();
+    assert_eq!(client.usage(&key).unwrap().count, 0);
+
+    client
+        .set_usage_policy(
+            &key,
+            UsagePolicy {
+                max_uses: Some(2),
+                rate_limit: None,
+            },
+        )
+        .unwrap();
+    let sign = Ed25519Sign {
+        private_key: key.clone(),
+        msg: fresh::variable_bytestring(32),
+    };
+    client.execute_procedure(sign.clone()).unwrap();
+    client.execute_procedure(sign.clone()).unwrap();
+    assert!(client.execute_procedure(sign.clone()).is_err());
+
+    let usage = client.usage(&key).unwrap();
+    assert_eq!(usage.count, 2);
+    assert!(usage.last_used.is_some());
+
+    client.set_usage_policy(&key, UsagePolicy::default()).unwrap();
+    client.execute_procedure(sign).unwrap();
+    assert_eq!(client.usage(&key).unwrap().count, 3);
+
+    // a procedure, that is rejected by the policy, doesn't remove the existing record at its output
+    let target = fresh::location();
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: target.clone(),
+        })
+        .unwrap();
+    client
+        .set_usage_policy(
+            &key,
+            UsagePolicy {
+                max_uses: Some(3),
+                rate_limit: None,
+            },
+        )
+        .unwrap();
+    let copy = CopyRecord {
+        source: key.clone(),
+        target: target.clone(),
+    };
+    assert!(client.execute_procedure(copy).is_err());
+    assert!(client
+        .execute_procedure(PublicKey {
+            ty: KeyType::Ed25519,
+            private_key: target,
+        })
+        .is_ok());
+    client.set_usage_policy(&key, UsagePolicy::default()).unwrap();
+
+    // a procedure, that fails after reading the record, doesn't count as a use
+    let decrypt = AeadDecrypt {
+        cipher: AeadCipher::XChaCha20Poly1305,
+        key: key.clone(),
+        ciphertext: fresh::variable_bytestring(32),
+        associated_data: Vec::new(),
+        tag: vec![0; 16],
+        nonce: vec![0; 24],
+    };
+    assert!(client.execute_procedure(decrypt).is_err());
+    assert_eq!(client.usage(&key).unwrap().count, 3);
+
+    // the counters are persisted with the client
+    stronghold.write_client(b"client_path").unwrap();
+    let client = stronghold.unload_client(client).unwrap();
+    drop(client);
+    let client = stronghold.load_client(b"client_path").unwrap();
+    assert_eq!(client.usage(&key).unwrap().count, 3);
+    assert!(client.record_exists(&key).unwrap());
+}
+
+#[test]
+fn usecase_pipeline() {
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    let mut pipeline = Pipeline::new();
+    let seed = pipeline.intermediate("seed");
+    let key = fresh::location();
+    let (_, chain) = fresh::hd_path();
+    let pipeline = pipeline
+        .then(Slip10Generate {
+            size_bytes: None,
+            output: seed.clone(),
+        })
+        .then_with(|seed| Slip10Derive {
+            curve: Curve::Ed25519,
+            chain,
+            input: Slip10DeriveInput::Seed(seed.clone()),
+            output: key.clone(),
+        })
+        .then_with(|key| PublicKey {
+            ty: KeyType::Ed25519,
+            private_key: key.clone(),
+        });
+    assert_eq!(pipeline.len(), 3);
+
+    let mut outputs = client.execute_pipeline(pipeline).unwrap();
+    let public_key: [u8; 32] = outputs.pop().unwrap().try_into().unwrap();
+    let expected: Vec<u8> = client
+        .execute_procedure(PublicKey {
+            ty: KeyType::Ed25519,
+            private_key: key.clone(),
+        })
+        .unwrap();
+    assert_eq!(public_key.to_vec(), expected);
+
+    // the intermediate seed has been deleted together with its vault
+    assert!(!client.record_exists(&seed).unwrap());
+    assert!(!client.vault_exists(seed.vault_path()).unwrap());
+
+    // a step without output can not be bound
+    let pipeline = Pipeline::new()
+        .then(PublicKey {
+            ty: KeyType::Ed25519,
+            private_key: key,
+        })
+        .then_with(|key| PublicKey {
+            ty: KeyType::Ed25519,
+            private_key: key.clone(),
+        });
+    assert!(client.execute_pipeline(pipeline).is_err());
+}
+
+#[test]
+fn usecase_slip10_derive_ecdsa() {
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    let seed = fresh::location();
+    client
+        .execute_procedure(Slip10Generate {
+            size_bytes: None,
+            output: seed.clone(),
+        })
+        .unwrap();
+
+    let path: Bip44Path = "m/44'/60'/0'/0/3".parse().unwrap();
+    assert_eq!(path, Bip44Path::new(60, 0, 0, 3));
+    assert_eq!(path.to_string(), "m/44'/60'/0'/0/3");
+    assert!("m/44'/60/0'/0/3".parse::<Bip44Path>().is_err());
+    assert!("m/49'/60'/0'/0/3".parse::<Bip44Path>().is_err());
+
+    for curve in [Curve::Secp256k1, Curve::NistP256] {
+        let key = fresh::location();
+        let chain_code = client
+            .execute_procedure(Slip10Derive::bip44(
+                curve,
+                &path,
+                Slip10DeriveInput::Seed(seed.clone()),
+                key.clone(),
+            ))
+            .unwrap();
+
+        // deriving the account first, and the address from the account key, leads to the same key
+        let account = fresh::location();
+        client
+            .execute_procedure(Slip10Derive {
+                curve,
+                chain: Chain::from_u32_hardened(vec![44, 60, 0]),
+                input: Slip10DeriveInput::Seed(seed.clone()),
+                output: account.clone(),
+            })
+            .unwrap();
+        let address = fresh::location();
+        let address_chain_code = client
+            .execute_procedure(Slip10Derive {
+                curve,
+                chain: Chain::from_u32(vec![0, 3]),
+                input: Slip10DeriveInput::Key(account),
+                output: address.clone(),
+            })
+            .unwrap();
+        assert_eq!(chain_code, address_chain_code);
+
+        let vault = client.vault(key.vault_path());
+        let derived = vault.read_secret(key.record_path()).unwrap();
+        let vault = client.vault(address.vault_path());
+        assert_eq!(derived, vault.read_secret(address.record_path()).unwrap());
+    }
+}
+
+#[test]
+fn usecase_ecdsa_public_key() {
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    // test vector 1 of SLIP-10
+    let seed = fresh::location();
+    client
+        .execute_procedure(WriteVault {
+            location: seed.clone(),
+            data: hex::decode("000102030405060708090a0b0c0d0e0f").unwrap(),
+        })
+        .unwrap();
+
+    let vectors = [
+        (
+            Curve::Secp256k1,
+            KeyType::Secp256k1Ecdsa,
+            Chain::empty(),
+            "0339a36013301597daef41fbe593a02cc513d0b55527ec2df1050e2e8ff49c85c2",
+        ),
+        (
+            Curve::Secp256k1,
+            KeyType::Secp256k1Ecdsa,
+            Chain::from_u32(vec![1 << 31, 1]),
+            "03501e454bf00751f24b1b489aa925215d66af2234e3891c3b21a52bedb3cd711c",
+        ),
+        (
+            Curve::NistP256,
+            KeyType::P256Ecdsa,
+            Chain::empty(),
+            "0266874dc6ade47b3ecd096745ca09bcd29638dd52c2c12117b11ed3e458cfa9e8",
+        ),
+        (
+            Curve::NistP256,
+            KeyType::P256Ecdsa,
+            Chain::from_u32_hardened(vec![0]),
+            "0384610f5ecffe8fda089363a41f56a5c7ffc1d81b59a612d0d649b2d22355590c",
+        ),
+    ];
+    for (curve, ty, chain, expected) in vectors {
+        let key = fresh::location();
+        client
+            .execute_procedure(Slip10Derive {
+                curve,
+                chain,
+                input: Slip10DeriveInput::Seed(seed.clone()),
+                output: key.clone(),
+            })
+            .unwrap();
+        let public_key = client.execute_procedure(PublicKey { ty, private_key: key }).unwrap();
+        assert_eq!(hex::encode(public_key), expected);
+    }
+
+    for ty in [KeyType::Secp256k1Ecdsa, KeyType::P256Ecdsa] {
+        let key = fresh::location();
+        client
+            .execute_procedure(GenerateKey {
+                ty: ty.clone(),
+                output: key.clone(),
+            })
+            .unwrap();
+        let public_key = client.execute_procedure(PublicKey { ty, private_key: key }).unwrap();
+        assert_eq!(public_key.len(), 33);
+        assert!(public_key[0] == 0x02 || public_key[0] == 0x03);
+    }
+}
+
+#[test]
+fn usecase_diffie_hellman_low_order_public_key() {
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    let sk = fresh::location();
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::X25519,
+            output: sk.clone(),
+        })
+        .unwrap();
+
+    // the identity and a point of order 8
+    let mut order_8 = [0u8; 32];
+    hex::decode_to_slice(
+        "e0eb7a7c3b41b8ae1656e3faf19fc46ada098deb9c32b1fd866205165f49b800",
+        &mut order_8,
+    )
+    .unwrap();
+    for public_key in [[0u8; 32], order_8] {
+        let shared_key = fresh::location();
+        let result = client.execute_procedure(X25519DiffieHellman {
+            public_key,
+            private_key: sk.clone(),
+            shared_key: shared_key.clone(),
+        });
+        assert!(result.is_err());
+        assert!(!client.record_exists(&shared_key).unwrap());
+    }
+}
+
+#[test]
+fn usecase_sign_prehashed() {
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    // Ed25519ph test vector of RFC 8032
+    let ed25519_key = fresh::location();
+    client
+        .execute_procedure(WriteVault {
+            data: hex::decode("833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42").unwrap(),
+            location: ed25519_key.clone(),
+        })
+        .unwrap();
+    let prehash = Sha512::digest(b"abc");
+    let signature = client
+        .execute_procedure(Ed25519SignPrehashed {
+            prehash: prehash.to_vec(),
+            context: Vec::new(),
+            private_key: ed25519_key.clone(),
+        })
+        .unwrap();
+    assert_eq!(
+        hex::encode(signature),
+        "98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae41\
+         31f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406"
+    );
+    assert!(client
+        .execute_procedure(Ed25519SignPrehashed {
+            prehash: prehash[..32].to_vec(),
+            context: Vec::new(),
+            private_key: ed25519_key.clone(),
+        })
+        .is_err());
+    assert!(client
+        .execute_procedure(EcdsaSignPrehashed {
+            curve: Curve::Ed25519,
+            prehash: [0; 32],
+            private_key: ed25519_key,
+        })
+        .is_err());
+
+    // ECDSA with keys, that are derived with SLIP-10
+    let seed = fresh::location();
+    client
+        .execute_procedure(Slip10Generate {
+            size_bytes: None,
+            output: seed.clone(),
+        })
+        .unwrap();
+    let key = fresh::location();
+    client
+        .execute_procedure(Slip10Derive {
+            curve: Curve::Secp256k1,
+            chain: Chain::from_u32(vec![(1 << 31) | 44, (1 << 31) | 60, 1 << 31, 0, 0]),
+            input: Slip10DeriveInput::Seed(seed),
+            output: key.clone(),
+        })
+        .unwrap();
+    let digest: [u8; 32] = Sha256::digest(b"firmware image").into();
+    let signature = client
+        .execute_procedure(EcdsaSignPrehashed {
+            curve: Curve::Secp256k1,
+            prehash: digest,
+            private_key: key.clone(),
+        })
+        .unwrap();
+
+    use k256::ecdsa::{signature::hazmat::PrehashVerifier, Signature, SigningKey};
+    let secret = client.vault(key.vault_path()).read_secret(key.record_path()).unwrap();
+    let verifying_key = SigningKey::from_bytes(&secret[..32]).unwrap().verifying_key();
+    let signature = Signature::try_from(&signature[..]).unwrap();
+    assert!(verifying_key.verify_prehash(&digest, &signature).is_ok());
+    assert!(verifying_key.verify_prehash(&[0; 32], &signature).is_err());
+}
+
+#[test]
+fn usecase_bip39_length_and_validate() {
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    for words in [12, 15, 18, 21, 24] {
+        for language in [MnemonicLanguage::English, MnemonicLanguage::Japanese] {
+            let length = MnemonicLength::try_from(words).unwrap();
+            assert_eq!(length.word_count(), words);
+            let mnemonic: String = client
+                .execute_procedure(BIP39Generate {
+                    passphrase: None,
+                    language: language.clone(),
+                    length,
+                    output: fresh::location(),
+                })
+                .unwrap();
+            assert_eq!(mnemonic.split_whitespace().count(), words);
+
+            assert!(client
+                .execute_procedure(BIP39Validate {
+                    mnemonic: mnemonic.clone().into(),
+                    language: language.clone(),
+                })
+                .is_ok());
+
+            // the words are not in the wordlist of the other language
+            let wrong_language = match language {
+                MnemonicLanguage::English => MnemonicLanguage::Japanese,
+                MnemonicLanguage::Japanese => MnemonicLanguage::English,
+            };
+            let error = client
+                .execute_procedure(BIP39Validate {
+                    mnemonic: mnemonic.into(),
+                    language: wrong_language,
+                })
+                .unwrap_err();
+            // the error doesn't reveal the words of the mnemonic
+            assert!(error.to_string().ends_with("Invalid mnemonic"), "{}", error);
+        }
+    }
+    assert!(MnemonicLength::try_from(13).is_err());
+
+    // valid words, but the checksum of the last word doesn't match
+    let invalid = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon";
+    let valid = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    for (mnemonic, is_valid) in [(invalid, false), (valid, true), ("abandon zzz", false)] {
+        let result = client.execute_procedure(BIP39Validate {
+            mnemonic: mnemonic.into(),
+            language: MnemonicLanguage::English,
+        });
+        assert_eq!(result.is_ok(), is_valid);
+    }
+}
+
+#[test]
+fn usecase_wrapped_key_transfer() {
+    let stronghold: Stronghold = Stronghold::default();
+    let source_client: Client = stronghold.create_client(b"source").unwrap();
+    let target_client: Client = stronghold.create_client(b"target").unwrap();
+
+    let seed = fresh::location();
+    source_client
+        .execute_procedure(Slip10Generate {
+            size_bytes: None,
+            output: seed.clone(),
+        })
+        .unwrap();
+    let wrapped = source_client
+        .execute_procedure(ExportWrappedKey {
+            source: seed.clone(),
+            passphrase: "transfer passphrase".into(),
+        })
+        .unwrap();
+
+    let wrong_passphrase = fresh::location();
+    let result = target_client.execute_procedure(ImportWrappedKey {
+        wrapped: wrapped.clone(),
+        passphrase: "wrong passphrase".into(),
+        output: wrong_passphrase.clone(),
+    });
+    assert!(result.is_err());
+    assert!(!target_client.record_exists(&wrong_passphrase).unwrap());
+
+    // the memory cost in the header is limited like the one of snapshot files
+    let mut excessive = wrapped.clone();
+    excessive[1..5].copy_from_slice(&(engine::snapshot::kdf::MAX_MEMORY_KIB + 1).to_le_bytes());
+    let result = target_client.execute_procedure(ImportWrappedKey {
+        wrapped: excessive,
+        passphrase: "transfer passphrase".into(),
+        output: fresh::location(),
+    });
+    assert!(result.unwrap_err().to_string().contains("exceed the limits"));
+
+    let mut tampered = wrapped.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert!(target_client
+        .execute_procedure(ImportWrappedKey {
+            wrapped: tampered,
+            passphrase: "transfer passphrase".into(),
+            output: fresh::location(),
+        })
+        .is_err());
+
+    let imported = fresh::location();
+    target_client
+        .execute_procedure(ImportWrappedKey {
+            wrapped,
+            passphrase: "transfer passphrase".into(),
+            output: imported.clone(),
+        })
+        .unwrap();
+    assert_eq!(
+        source_client
+            .vault(seed.vault_path())
+            .read_secret(seed.record_path())
+            .unwrap(),
+        target_client
+            .vault(imported.vault_path())
+            .read_secret(imported.record_path())
+            .unwrap()
+    );
+}
+
+#[test]
+fn usecase_custom_procedure() {
+    // writes the concatenation of all sources into the target, and returns its length
+    struct Concat {
+        sources: Vec<Location>,
+        target: Option<Location>,
+    }
+
+    impl DynProcedure for Concat {
+        fn name(&self) -> &'static str {
+            "Concat"
+        }
+
+        fn source(&self) -> Vec<Location> {
+            self.sources.clone()
+        }
+
+        fn target(&self) -> Option<Location> {
+            self.target.clone()
+        }
+
+        fn execute(&self, secrets: &[&[u8]], _: &dyn SecureRng) -> Result<Products<Vec<u8>>, FatalProcedureError> {
+            let secret = secrets.concat();
+            let output = vec![secret.len() as u8];
+            let secret = if self.target.is_some() { secret } else { Vec::new() };
+            Ok(Products { secret, output })
+        }
+    }
+
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    let sources: Vec<Location> = (0..5).map(|_| fresh::location()).collect();
+    for (i, location) in sources.iter().enumerate() {
+        client
+            .execute_procedure(WriteVault {
+                data: vec![i as u8; i + 1],
+                location: location.clone(),
+            })
+            .unwrap();
+    }
+
+    let target = fresh::location();
+    let output = client
+        .execute_procedure(CustomProcedure::new(Concat {
+            sources: sources[..3].to_vec(),
+            target: Some(target.clone()),
+        }))
+        .unwrap();
+    assert_eq!(output, vec![6]);
+    let vault = client.vault(target.vault_path());
+    assert_eq!(vault.read_secret(target.record_path()).unwrap(), vec![0, 1, 1, 2, 2, 2]);
+
+    let output = client
+        .execute_procedure(CustomProcedure::new(Concat {
+            sources: sources[..1].to_vec(),
+            target: None,
+        }))
+        .unwrap();
+    assert_eq!(output, vec![1]);
+
+    let too_many = client.execute_procedure(CustomProcedure::new(Concat {
+        sources,
+        target: Some(fresh::location()),
+    }));
+    assert!(too_many.is_err());
+}
+
+#[test]
+fn usecase_certificates() {
+    // returns the header length and the content length of the DER element at the start of `der`
+    fn element(der: &[u8]) -> (usize, usize) {
+        match der[1] {
+            len @ 0..=0x7f => (2, len as usize),
+            0x81 => (3, der[2] as usize),
+            _ => (4, u16::from_be_bytes([der[2], der[3]]) as usize),
+        }
+    }
+
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+    let subject = DistinguishedName {
+        common_name: "device-42".into(),
+        organization: Some("IOTA Stiftung".into()),
+        country: Some("DE".into()),
+    };
+
+    // CSR of an Ed25519 key, the signature covers the request info
+    let ed25519_key = fresh::location();
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: ed25519_key.clone(),
+        })
+        .unwrap();
+    let csr = client
+        .execute_procedure(GenerateCsr {
+            curve: Curve::Ed25519,
+            private_key: ed25519_key.clone(),
+            subject: subject.clone(),
+            encoding: CertificateEncoding::Der,
+        })
+        .unwrap();
+    let (outer_header, outer_len) = element(&csr);
+    assert_eq!(outer_header + outer_len, csr.len());
+    let (info_header, info_len) = element(&csr[outer_header..]);
+    let info = &csr[outer_header..outer_header + info_header + info_len];
+
+    let public_key: Vec<u8> = client
+        .execute_procedure(PublicKey {
+            ty: KeyType::Ed25519,
+            private_key: ed25519_key.clone(),
+        })
+        .unwrap();
+    let public_key = ed25519::PublicKey::try_from_bytes(public_key.try_into().unwrap()).unwrap();
+    let mut signature = [0u8; ed25519::SIGNATURE_LENGTH];
+    signature.copy_from_slice(&csr[csr.len() - ed25519::SIGNATURE_LENGTH..]);
+    assert!(public_key.verify(&ed25519::Signature::from_bytes(signature), info));
+    assert!(info.windows(9).any(|w| w == b"device-42"));
+
+    // self-signed certificate of a P-256 key
+    let seed = fresh::location();
+    client
+        .execute_procedure(Slip10Generate {
+            size_bytes: None,
+            output: seed.clone(),
+        })
+        .unwrap();
+    let p256_key = fresh::location();
+    client
+        .execute_procedure(Slip10Derive {
+            curve: Curve::NistP256,
+            chain: Chain::from_u32(vec![1 << 31]),
+            input: Slip10DeriveInput::Seed(seed),
+            output: p256_key.clone(),
+        })
+        .unwrap();
+    let certificate = client
+        .execute_procedure(SelfSignedCertificate {
+            curve: Curve::NistP256,
+            private_key: p256_key.clone(),
+            subject: subject.clone(),
+            not_before: 1_672_531_200,
+            not_after: 1_704_067_200,
+            encoding: CertificateEncoding::Pem,
+        })
+        .unwrap();
+    let certificate = String::from_utf8(certificate).unwrap();
+    assert!(certificate.starts_with("-----BEGIN CERTIFICATE-----\n"));
+    assert!(certificate.ends_with("-----END CERTIFICATE-----\n"));
+
+    let invalid = [
+        SelfSignedCertificate {
+            curve: Curve::NistP256,
+            private_key: p256_key,
+            subject: subject.clone(),
+            not_before: 1_704_067_200,
+            not_after: 1_672_531_200,
+            encoding: CertificateEncoding::Der,
+        },
+        SelfSignedCertificate {
+            curve: Curve::Secp256k1,
+            private_key: ed25519_key,
+            subject,
+            not_before: 1_672_531_200,
+            not_after: 1_704_067_200,
+            encoding: CertificateEncoding::Der,
+        },
+    ];
+    for procedure in invalid {
+        assert!(client.execute_procedure(procedure).is_err());
+    }
+}
+
+#[test]
+fn usecase_procedure_output_into_store() {
+    use std::time::Duration;
+
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    let key = fresh::location();
+    client
+        .execute_procedure(GenerateKey {
+            ty: KeyType::Ed25519,
+            output: key.clone(),
+        })
+        .unwrap();
+
+    let public_key = client
+        .execute_procedure_with_target(
+            PublicKey {
+                ty: KeyType::Ed25519,
+                private_key: key.clone(),
+            },
+            OutputTarget::Store {
+                key: b"public_key".to_vec(),
+                ttl: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(client.store().get(b"public_key").unwrap(), Some(public_key.to_vec()));
+
+    // the default target only returns the output
+    client
+        .execute_procedure_with_target(
+            PublicKey {
+                ty: KeyType::Ed25519,
+                private_key: key.clone(),
+            },
+            OutputTarget::default(),
+        )
+        .unwrap();
+    assert_eq!(client.store().keys().unwrap().len(), 1);
+
+    // nothing is inserted for failed procedures
+    let result = client.execute_procedure_with_target(
+        PublicKey {
+            ty: KeyType::Ed25519,
+            private_key: fresh::location(),
+        },
+        OutputTarget::Store {
+            key: b"missing".to_vec(),
+            ttl: None,
+        },
+    );
+    assert!(result.is_err());
+    assert!(client.store().get(b"missing").unwrap().is_none());
+
+    client
+        .execute_procedure_with_target(
+            PublicKey {
+                ty: KeyType::Ed25519,
+                private_key: key,
+            },
+            OutputTarget::Store {
+                key: b"expiring".to_vec(),
+                ttl: Some(Duration::from_millis(50)),
+            },
+        )
+        .unwrap();
+    assert!(client.store().get(b"expiring").unwrap().is_some());
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(client.store().get(b"expiring").unwrap().is_none());
+}
+
+#[cfg(feature = "bls")]
+#[test]
+fn usecase_bls_aggregate() {
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    let msg = b"block 42".to_vec();
+    let mut public_keys = Vec::new();
+    let mut signatures = Vec::new();
+    for _ in 0..2 {
+        let sk = fresh::location();
+        client.execute_procedure(BlsGenerateKey { output: sk.clone() }).unwrap();
+        let pk = client
+            .execute_procedure(BlsPublicKey {
+                private_key: sk.clone(),
+            })
+            .unwrap();
+
+        let pop = client
+            .execute_procedure(BlsProofOfPossession {
+                private_key: sk.clone(),
+            })
+            .unwrap();
+        assert!(bls::verify(&pop, &pk, &[pk], true));
+        assert!(!bls::verify(&pop, &pk, &[pk], false));
+
+        let signature = client
+            .execute_procedure(BlsSign {
+                msg: msg.clone(),
+                private_key: sk,
+            })
+            .unwrap();
+        assert!(bls::verify(&signature, &msg, &[pk], false));
+
+        public_keys.push(pk);
+        signatures.push(signature.to_vec());
+    }
+
+    let aggregate = client
+        .execute_procedure(BlsAggregate {
+            signatures: signatures.clone(),
+        })
+        .unwrap();
+    assert!(bls::verify(&aggregate, &msg, &public_keys, false));
+    assert!(!bls::verify(&aggregate, b"block 43", &public_keys, false));
+    assert!(!bls::verify(&aggregate, &msg, &public_keys[..1], false));
+
+    signatures.push(vec![0u8; 96]);
+    assert!(client.execute_procedure(BlsAggregate { signatures }).is_err());
+}
+
+#[cfg(feature = "ml-dsa")]
+#[test]
+fn usecase_ml_dsa() {
+    let stronghold: Stronghold = Stronghold::default();
+    let client: Client = stronghold.create_client(b"client_path").unwrap();
+
+    let msg = b"hybrid signature".to_vec();
+    for level in [MlDsaLevel::Dilithium2, MlDsaLevel::Dilithium3, MlDsaLevel::Dilithium5] {
+        let key = fresh::location();
+        client
+            .execute_procedure(MlDsaGenerateKey {
+                level,
+                output: key.clone(),
+            })
+            .unwrap();
+        let public_key = client
+            .execute_procedure(MlDsaPublicKey {
+                level,
+                private_key: key.clone(),
+            })
+            .unwrap();
+        let signature = client
+            .execute_procedure(MlDsaSign {
+                level,
+                msg: msg.clone(),
+                private_key: key.clone(),
+            })
+            .unwrap();
+        assert!(ml_dsa::verify(level, &signature, &msg, &public_key));
+        assert!(!ml_dsa::verify(level, &signature, b"other message", &public_key));
+    }
+
+    // a key pair of one level can't be used with another
+    let key = fresh::location();
+    client
+        .execute_procedure(MlDsaGenerateKey {
+            level: MlDsaLevel::Dilithium2,
+            output: key.clone(),
+        })
+        .unwrap();
+    let result = client.execute_procedure(MlDsaSign {
+        level: MlDsaLevel::Dilithium5,
+        msg,
+        private_key: key,
+    });
+    assert!(result.is_err());
+}
@@ -1,7 +1,7 @@
 // Copyright 2020-2022 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{ClientError, Store};
+use crate::{ClientError, EvictionPolicy, Store, StoreLimits};
 use stronghold_utils::random as rand;
 
 #[test]
@@ -81,3 +81,73 @@ fn test_keys() {
 
     assert_eq!(actual, keys);
 }
+
+#[test]
+fn test_store_entry_limit() -> Result<(), ClientError> {
+    let store = Store::with_limits(StoreLimits {
+        max_entry_size: Some(16),
+        ..Default::default()
+    });
+
+    assert!(matches!(
+        store.insert(b"key".to_vec(), vec![0; 14], None),
+        Err(ClientError::StoreEntryTooLarge { size: 17, limit: 16 })
+    ));
+    assert!(!store.contains_key(b"key")?);
+
+    store.insert(b"key".to_vec(), vec![0; 13], None)?;
+    assert_eq!(store.size()?, 16);
+
+    Ok(())
+}
+
+#[test]
+fn test_store_total_limit_reject() -> Result<(), ClientError> {
+    let store = Store::with_limits(StoreLimits {
+        max_total_size: Some(20),
+        ..Default::default()
+    });
+
+    store.insert(b"key-1".to_vec(), vec![0; 5], None)?;
+    assert!(matches!(
+        store.insert(b"key-2".to_vec(), vec![0; 11], None),
+        Err(ClientError::StoreQuotaExceeded { size: 26, limit: 20 })
+    ));
+
+    // replacing an entry only accounts for the new value
+    store.insert(b"key-1".to_vec(), vec![0; 15], None)?;
+    assert_eq!(store.size()?, 20);
+
+    Ok(())
+}
+
+#[test]
+fn test_store_total_limit_lru() -> Result<(), ClientError> {
+    let store = Store::with_limits(StoreLimits {
+        max_total_size: Some(30),
+        eviction: EvictionPolicy::Lru,
+        ..Default::default()
+    });
+
+    store.insert(b"key-1".to_vec(), vec![1; 5], None)?;
+    store.insert(b"key-2".to_vec(), vec![2; 5], None)?;
+    store.insert(b"key-3".to_vec(), vec![3; 5], None)?;
+
+    // key-1 is now more recently used than key-2
+    assert!(store.get(b"key-1")?.is_some());
+
+    store.insert(b"key-4".to_vec(), vec![4; 5], None)?;
+    assert!(store.contains_key(b"key-1")?);
+    assert!(!store.contains_key(b"key-2")?);
+    assert!(store.contains_key(b"key-3")?);
+    assert!(store.contains_key(b"key-4")?);
+
+    // entries larger than the store are rejected without evicting anything
+    assert!(matches!(
+        store.insert(b"key-5".to_vec(), vec![5; 30], None),
+        Err(ClientError::StoreQuotaExceeded { .. })
+    ));
+    assert_eq!(store.keys()?.len(), 3);
+
+    Ok(())
+}
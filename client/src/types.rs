@@ -4,19 +4,52 @@
 //! A collection of relevant interface types to interact with a Stronghold
 
 // modules
+mod approval;
+#[cfg(feature = "async")]
+mod asynchronous;
+mod autolock;
 mod client;
 mod error;
+mod escrow;
+mod events;
+mod health;
+mod history;
 mod location;
+mod metrics;
+mod operation;
+mod refresh;
+mod shutdown;
 mod snapshot;
 mod store;
 mod stronghold;
+mod transaction;
+mod typed;
+mod usage;
 mod vault;
+mod vault_path;
 
 // re-export imports
+pub use approval::*;
+pub use autolock::*;
 pub use client::*;
 pub use error::*;
+pub use escrow::*;
+pub use events::*;
+pub use health::*;
+pub use history::*;
 pub use location::*;
+pub use metrics::*;
+pub use operation::*;
+pub use shutdown::*;
 pub use snapshot::*;
 pub use store::*;
 pub use stronghold::*;
+pub use transaction::*;
+#[cfg(feature = "cbor")]
+pub use typed::Cbor;
+#[cfg(feature = "json")]
+pub use typed::Json;
+pub use typed::{Bincode, Codec, TypedValue};
+pub use usage::*;
 pub use vault::*;
+pub use vault_path::*;
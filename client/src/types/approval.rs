@@ -0,0 +1,139 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    procedures::{ProcedureError, StrongholdProcedure},
+    OperationGuard, VaultPath,
+};
+use engine::{
+    time::{Duration, Instant},
+    vault::ClientId,
+};
+use std::{
+    collections::HashMap,
+    sync::{mpsc, Arc},
+};
+use stronghold_utils::GuardDebug;
+
+/// Interval in which a pending approval checks, if its operation has been aborted
+const ABORT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Callback that is invoked for procedures on a guarded vault. The callback receives the
+/// [`ApprovalRequest`] and an [`ApprovalResponder`] to answer it. The responder may be moved
+/// to another thread, e.g. to prompt the user, and answered from there.
+pub type ApprovalHandler = Arc<dyn Fn(ApprovalRequest, ApprovalResponder) + Send + Sync>;
+
+/// A request to approve the execution of a procedure that accesses a guarded vault.
+#[derive(Clone, GuardDebug)]
+pub struct ApprovalRequest {
+    /// The id of the client the procedure is executed on
+    pub client_id: ClientId,
+
+    /// The path of the guarded vault that is accessed by the procedure
+    pub vault_path: Vec<u8>,
+
+    /// The procedure that waits for approval
+    pub procedure: StrongholdProcedure,
+}
+
+/// Answers an [`ApprovalRequest`]. Dropping the responder without answering denies the request.
+pub struct ApprovalResponder(mpsc::SyncSender<bool>);
+
+impl ApprovalResponder {
+    /// Approves the execution of the procedure
+    pub fn approve(self) {
+        let _ = self.0.send(true);
+    }
+
+    /// Denies the execution of the procedure
+    pub fn deny(self) {
+        let _ = self.0.send(false);
+    }
+}
+
+#[derive(Clone)]
+struct ApprovalHook {
+    handler: ApprovalHandler,
+    timeout: Duration,
+}
+
+/// All registered approval hooks of a client, mapped by vault path or [`VaultPath`] pattern
+#[derive(Clone, Default)]
+pub(crate) struct Approvals {
+    hooks: HashMap<Vec<u8>, ApprovalHook>,
+}
+
+impl Approvals {
+    pub(crate) fn insert(&mut self, vault_path: Vec<u8>, timeout: Duration, handler: ApprovalHandler) {
+        self.hooks.insert(vault_path, ApprovalHook { handler, timeout });
+    }
+
+    pub(crate) fn remove(&mut self, vault_path: &[u8]) -> bool {
+        self.hooks.remove(vault_path).is_some()
+    }
+
+    /// Returns the hook that guards `vault_path`. A hook registered for the path itself or its closest
+    /// ancestor takes precedence over hooks registered for matching patterns.
+    fn hook_for(&self, vault_path: &[u8]) -> Option<&ApprovalHook> {
+        let path = VaultPath::new(vault_path);
+        path.ancestors()
+            .iter()
+            .find_map(|ancestor| self.hooks.get(ancestor.as_bytes()))
+            .or_else(|| {
+                let mut patterns: Vec<_> = self
+                    .hooks
+                    .iter()
+                    .filter(|(pattern, _)| VaultPath::is_pattern(pattern) && path.matches(pattern))
+                    .collect();
+                // pick a deterministic hook, if several patterns match
+                patterns.sort_by_key(|(pattern, _)| *pattern);
+                patterns.first().map(|(_, hook)| *hook)
+            })
+    }
+
+    /// Asks the hooks of all guarded vaults that are accessed by `procedure` for approval.
+    /// Execution is denied, if any hook denies it or does not answer within its timeout.
+    /// Waiting for an answer stops early, if `operation` is aborted.
+    pub(crate) fn check(
+        &self,
+        client_id: ClientId,
+        procedure: &StrongholdProcedure,
+        operation: &OperationGuard,
+    ) -> Result<(), ProcedureError> {
+        if self.hooks.is_empty() {
+            return Ok(());
+        }
+
+        let mut vault_paths = procedure.vault_paths();
+        vault_paths.sort();
+        vault_paths.dedup();
+
+        for vault_path in vault_paths {
+            let hook = match self.hook_for(&vault_path) {
+                Some(hook) => hook,
+                None => continue,
+            };
+
+            let (tx, rx) = mpsc::sync_channel(1);
+            let request = ApprovalRequest {
+                client_id,
+                vault_path,
+                procedure: procedure.clone(),
+            };
+            (hook.handler)(request, ApprovalResponder(tx));
+
+            let deadline = Instant::now() + hook.timeout;
+            loop {
+                operation.check()?;
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                match rx.recv_timeout(remaining.min(ABORT_POLL_INTERVAL)) {
+                    Ok(true) => break,
+                    Err(mpsc::RecvTimeoutError::Timeout) if !remaining.is_zero() => continue,
+                    _ => return Err(ProcedureError::NotApproved),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
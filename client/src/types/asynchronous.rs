@@ -0,0 +1,128 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Async variants of the blocking [`Stronghold`] and [`Client`] API.
+//!
+//! The async functions do not depend on a specific executor and can be awaited from tokio,
+//! async-std or any other runtime. Operations that may block for a longer time, like deriving
+//! keys, file I/O or waiting for the approval of a procedure, are moved onto a background thread,
+//! so that the executor is never blocked by them.
+
+use crate::{
+    procedures::{Procedure, ProcedureError, ProcedureOutput, StrongholdProcedure},
+    Client, ClientError, KeyProvider, Location, ShutdownConfig, SnapshotPath, Stronghold,
+};
+use futures::channel::oneshot;
+use std::thread;
+
+/// Runs `f` on a background thread and resolves with its return value. Fails, if the thread
+/// could not be spawned or panicked.
+async fn unblock<F, R>(f: F) -> Result<R, String>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    thread::Builder::new()
+        .name("stronghold-blocking".to_string())
+        .spawn(move || {
+            let _ = tx.send(f());
+        })
+        .map_err(|e| e.to_string())?;
+
+    rx.await.map_err(|_| "Background task has been cancelled".to_string())
+}
+
+impl Stronghold {
+    /// Async variant of [`Stronghold::load_snapshot`]
+    pub async fn load_snapshot_async(
+        &self,
+        keyprovider: KeyProvider,
+        snapshot_path: SnapshotPath,
+    ) -> Result<(), ClientError> {
+        let stronghold = self.clone();
+        unblock(move || stronghold.load_snapshot(&keyprovider, &snapshot_path))
+            .await
+            .map_err(ClientError::Inner)?
+    }
+
+    /// Async variant of [`Stronghold::load_client_from_snapshot`]
+    pub async fn load_client_from_snapshot_async<P>(
+        &self,
+        client_path: P,
+        keyprovider: KeyProvider,
+        snapshot_path: SnapshotPath,
+    ) -> Result<Client, ClientError>
+    where
+        P: AsRef<[u8]> + Send + 'static,
+    {
+        let stronghold = self.clone();
+        unblock(move || stronghold.load_client_from_snapshot(client_path, &keyprovider, &snapshot_path))
+            .await
+            .map_err(ClientError::Inner)?
+    }
+
+    /// Async variant of [`Stronghold::commit_with_keyprovider`]
+    pub async fn commit_with_keyprovider_async(
+        &self,
+        snapshot_path: SnapshotPath,
+        keyprovider: KeyProvider,
+    ) -> Result<(), ClientError> {
+        let stronghold = self.clone();
+        unblock(move || stronghold.commit_with_keyprovider(&snapshot_path, &keyprovider))
+            .await
+            .map_err(ClientError::Inner)?
+    }
+
+    /// Async variant of [`Stronghold::commit`]
+    pub async fn commit_async(&self, snapshot_path: SnapshotPath) -> Result<(), ClientError> {
+        let stronghold = self.clone();
+        unblock(move || stronghold.commit(&snapshot_path))
+            .await
+            .map_err(ClientError::Inner)?
+    }
+
+    /// Async variant of [`Stronghold::shutdown`]. Resolves once the state has been persisted and cleared.
+    pub async fn shutdown_async(&self, config: ShutdownConfig) -> Result<(), ClientError> {
+        let stronghold = self.clone();
+        unblock(move || stronghold.shutdown(config))
+            .await
+            .map_err(ClientError::Inner)?
+    }
+}
+
+impl Client {
+    /// Async variant of writing a secret with [`crate::ClientVault::write_secret`]
+    pub async fn write_to_vault_async(&self, location: Location, payload: Vec<u8>) -> Result<(), ClientError> {
+        let client = self.clone();
+        unblock(move || {
+            let vault_path = location.vault_path().to_vec();
+            client.vault(vault_path).write_secret(location, payload)
+        })
+        .await
+        .map_err(ClientError::Inner)?
+    }
+
+    /// Async variant of [`Client::execute_procedure`]
+    pub async fn execute_procedure_async<P>(&self, procedure: P) -> Result<P::Output, ProcedureError>
+    where
+        P: Procedure + Into<StrongholdProcedure> + Send + 'static,
+        P::Output: Send + 'static,
+    {
+        let client = self.clone();
+        unblock(move || client.execute_procedure(procedure))
+            .await
+            .map_err(|e| ProcedureError::Engine(e.into()))?
+    }
+
+    /// Async variant of [`Client::execute_procedure_chained`]
+    pub async fn execute_procedure_chained_async(
+        &self,
+        procedures: Vec<StrongholdProcedure>,
+    ) -> Result<Vec<ProcedureOutput>, ProcedureError> {
+        let client = self.clone();
+        unblock(move || client.execute_procedure_chained(procedures))
+            .await
+            .map_err(|e| ProcedureError::Engine(e.into()))?
+    }
+}
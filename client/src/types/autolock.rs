@@ -0,0 +1,183 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Locking of a [`Client`](crate::Client) after a period of inactivity.
+//!
+//! Locking seals the vault keys of the client with the key of its [`KeyProvider`], removes them from the
+//! keystore, and drops the key provider. Until the client is unlocked with the same key, operations on its
+//! secrets fail with [`ClientError::Locked`]. The sealed keys are kept in memory, so changes that have not been
+//! committed into a snapshot are not lost.
+
+use engine::time::{Duration, Instant};
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, RwLock, Weak,
+    },
+    thread,
+};
+
+use engine::{
+    snapshot::{read, write, Key},
+    vault::{Key as PKey, VaultId},
+};
+use zeroize::Zeroize;
+
+use crate::{ClientError, KeyProvider, KeyStore, Provider};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) struct AutoLock {
+    // Distinguishes the configurations of a client, so that the watcher of a replaced one stops
+    id: u64,
+
+    // Inactivity after which the client is locked
+    idle_timeout: Duration,
+
+    last_activity: Instant,
+
+    // The key provider, that the vault keys are sealed with. `None` while the client is locked
+    keyprovider: Option<KeyProvider>,
+
+    // The sealed vault keys. `Some` while the client is locked
+    sealed: Option<Vec<u8>>,
+}
+
+impl AutoLock {
+    pub(crate) fn new(idle_timeout: Duration, keyprovider: KeyProvider) -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            idle_timeout,
+            last_activity: Instant::now(),
+            keyprovider: Some(keyprovider),
+            sealed: None,
+        }
+    }
+
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub(crate) fn is_locked(&self) -> bool {
+        self.sealed.is_some()
+    }
+
+    pub(crate) fn is_idle(&self) -> bool {
+        self.last_activity.elapsed() >= self.idle_timeout
+    }
+
+    /// Refreshes the key provider, that the vault keys are sealed with, see [`KeyProvider::refresh`]
+    pub(crate) fn refresh(&self) -> Result<(), ClientError> {
+        match self.keyprovider.as_ref() {
+            Some(keyprovider) => keyprovider
+                .refresh()
+                .map_err(|e| ClientError::Inner(format!("{:?}", e))),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    /// Seals the vault keys of `keystore` and replaces it with an empty one. Does nothing, if the client is
+    /// already locked.
+    pub(crate) fn lock(&mut self, keystore: &mut KeyStore<Provider>) -> Result<(), ClientError> {
+        let keyprovider = match self.keyprovider.as_ref() {
+            Some(keyprovider) => keyprovider,
+            None => return Ok(()),
+        };
+        let key = unlock_key(keyprovider)?;
+
+        let mut bytes = bincode::serialize(&keystore.get_data()).map_err(|e| ClientError::Inner(e.to_string()))?;
+        let mut sealed = Vec::new();
+        let written = write(&bytes, &mut sealed, &key, &[]);
+        bytes.zeroize();
+        written.map_err(|e| ClientError::Inner(e.to_string()))?;
+
+        // a new keystore also replaces the master key, that the vault keys have been encrypted with
+        *keystore = KeyStore::default();
+        self.keyprovider = None;
+        self.sealed = Some(sealed);
+        Ok(())
+    }
+
+    /// Restores the sealed vault keys into `keystore`. Fails with [`ClientError::WrongUnlockKey`], if
+    /// `keyprovider` does not hold the key, that the client has been locked with. Does nothing, if the client
+    /// is not locked.
+    pub(crate) fn unlock(
+        &mut self,
+        keyprovider: KeyProvider,
+        keystore: &mut KeyStore<Provider>,
+    ) -> Result<(), ClientError> {
+        let sealed = match self.sealed.as_ref() {
+            Some(sealed) => sealed,
+            None => return Ok(()),
+        };
+        let key = unlock_key(&keyprovider)?;
+
+        let mut bytes = read(&mut sealed.as_slice(), &key, &[]).map_err(|_| ClientError::WrongUnlockKey)?;
+        let keys: Result<HashMap<VaultId, PKey<Provider>>, _> = bincode::deserialize(&bytes);
+        bytes.zeroize();
+        keystore
+            .rebuild_keystore(keys.map_err(|e| ClientError::Inner(e.to_string()))?)
+            .map_err(|e| ClientError::Inner(e.to_string()))?;
+
+        self.keyprovider = Some(keyprovider);
+        self.sealed = None;
+        self.touch();
+        Ok(())
+    }
+}
+
+fn unlock_key(keyprovider: &KeyProvider) -> Result<Key, ClientError> {
+    let buffer = keyprovider
+        .try_unlock()
+        .map_err(|e| ClientError::Inner(format!("{:?}", e)))?;
+    let key = buffer
+        .borrow()
+        .deref()
+        .try_into()
+        .map_err(|_| ClientError::IllegalKeySize(32))?;
+    Ok(key)
+}
+
+/// Locks the client, that `state` and `keystore` belong to, once it has been idle for its timeout. Returns, when
+/// the client has been dropped, or its auto-lock has been disabled or replaced.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn watch(state: Weak<Mutex<Option<AutoLock>>>, keystore: Weak<RwLock<KeyStore<Provider>>>, id: u64) {
+    loop {
+        let sleep = {
+            let (state, keystore) = match (state.upgrade(), keystore.upgrade()) {
+                (Some(state), Some(keystore)) => (state, keystore),
+                _ => return,
+            };
+            let mut guard = match state.lock() {
+                Ok(guard) => guard,
+                Err(_) => return,
+            };
+            let auto_lock = match guard.as_mut() {
+                Some(auto_lock) if auto_lock.id == id => auto_lock,
+                _ => return,
+            };
+
+            if !auto_lock.is_locked() && auto_lock.is_idle() {
+                let locked = keystore
+                    .write()
+                    .map_err(ClientError::from)
+                    .and_then(|mut keystore| auto_lock.lock(&mut keystore));
+                if locked.is_err() {
+                    return;
+                }
+            }
+            if auto_lock.is_locked() {
+                auto_lock.idle_timeout
+            } else {
+                auto_lock.idle_timeout.saturating_sub(auto_lock.last_activity.elapsed())
+            }
+        };
+        thread::sleep(sleep);
+    }
+}
@@ -1,29 +1,51 @@
 // Copyright 2020-2022 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
-use super::{location, snapshot};
+use super::{autolock, location, refresh, snapshot};
 
 use crate::{
     derive_vault_id,
     procedures::{
-        FatalProcedureError, Procedure, ProcedureError, ProcedureOutput, Products, Runner, StrongholdProcedure,
+        CopyRecord, FatalProcedureError, MixedRng, OsRng, OutputTarget, Pipeline, Procedure, ProcedureError,
+        ProcedureOutput, Products, Runner, SecureRng, StrongholdProcedure,
     },
     sync::{KeyProvider, MergePolicy, SyncClients, SyncClientsConfig, SyncSnapshots, SyncSnapshotsConfig},
-    ClientError, ClientState, ClientVault, KeyStore, Location, Provider, RecordError, SnapshotError, Store, Stronghold,
+    usage_record_id, usage_vault_id, ApprovalRequest, ApprovalResponder, Approvals, AutoLock, ClientError, ClientState,
+    ClientVault, DenyReason, EscrowConfig, Event, EventBus, History, KeyStore, Location, MetricsRecorder, Mutation,
+    Operation, OperationGuard, OperationId, Operations, ProcedureSummary, Provider, RecordError, SnapshotError, Store,
+    StoreHandle, Stronghold, Transaction, Usage, UsagePolicy, UsageReservation, UsageTracker, DEFAULT_RANDOM_HINT_SIZE,
 };
-use crypto::keys::x25519;
+use crypto::{keys::x25519, macs::hmac::HMAC_SHA256};
 use engine::{
     runtime::memories::buffer::Buffer,
-    vault::{view::Record, BoxProvider, ClientId, DbView, Id, Key, RecordHint, RecordId, VaultId},
+    time::{Duration, Instant, SystemTime},
+    vault::{
+        view::Record, AuditEntry, BoxProvider, ClientId, DbView, GcStats, HintFilter, Id, Key, Quota, RecordHint,
+        RecordId, Tags, VaultId, WriteRequest,
+    },
 };
 use std::{
     collections::HashMap,
     error::Error,
-    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
-    time::Duration,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
 };
-use stronghold_utils::GuardDebug;
+use stronghold_utils::{random as rand, GuardDebug};
 use zeroize::Zeroize;
 
+/// Decides, when the revoked records of a vault are garbage collected, see [`Client::set_gc_policy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GcPolicy {
+    /// Revoked records are only deleted by explicit garbage collections, e.g. with [`ClientVault::cleanup`]
+    #[default]
+    Manual,
+
+    /// A vault is garbage collected, as soon as a revocation leaves it with at least this many revoked
+    /// records
+    AfterNRevocations(usize),
+}
+
 #[derive(Clone, GuardDebug)]
 pub struct Client {
     // A keystore
@@ -32,20 +54,97 @@ pub struct Client {
     // A view on the vault entries
     pub(crate) db: Arc<RwLock<DbView<Provider>>>,
 
+    // Serializes the procedures, that write into the same vault
+    pub(crate) vault_locks: Arc<Mutex<HashMap<VaultId, Arc<Mutex<()>>>>>,
+
     // The id of this client
     pub id: ClientId,
 
     // Contains the Record Ids for the most recent Record in each vault.
     pub store: Store,
+
+    // Hooks that need to approve procedures on guarded vaults
+    pub(crate) approvals: Arc<RwLock<Approvals>>,
+
+    // Optional escrow of newly generated keys
+    pub(crate) escrow: Arc<RwLock<Option<EscrowConfig>>>,
+
+    // The random number generator for procedures that generate new secrets
+    pub(crate) rng: Arc<RwLock<Arc<dyn SecureRng>>>,
+
+    // How long revoked records are kept before garbage collection deletes them
+    pub(crate) trash_retention: Arc<RwLock<Duration>>,
+
+    // When revoked records are garbage collected
+    pub(crate) gc_policy: Arc<RwLock<GcPolicy>>,
+
+    // Procedures that are currently executed
+    pub(crate) operations: Arc<RwLock<Operations>>,
+
+    // Increased with every modification of the keystore or the vaults
+    pub(crate) revision: Arc<AtomicU64>,
+
+    // Summaries of the most recent procedure executions
+    pub(crate) history: Arc<RwLock<History>>,
+
+    // Locks the client after a period of inactivity
+    pub(crate) auto_lock: Arc<Mutex<Option<AutoLock>>>,
+
+    // How often procedures have used each record, and the limits of their use
+    pub(crate) usage: Arc<Mutex<UsageTracker>>,
+
+    // Receives the events of this client, shared with the Stronghold that has created or loaded it
+    pub(crate) events: EventBus,
+
+    // Changed with every configuration of the store sweeper, so that the sweeper of a replaced one stops
+    pub(crate) store_sweep: Arc<AtomicU64>,
+
+    // Changed with every configuration of the key refresh, so that the refresher of a replaced one stops
+    pub(crate) key_refresh: Arc<AtomicU64>,
+
+    // Collects the metrics of this client, shared with the Stronghold that has created or loaded it
+    pub(crate) metrics: MetricsRecorder,
+}
+
+/// Reserved id of the key in the [`KeyStore`], that authenticates the audit logs of the vaults
+fn audit_key_id() -> VaultId {
+    VaultId::load(&[0xff; 24]).expect("VaultId of 24 bytes is valid")
+}
+
+/// Stores a new audit key in `keystore` and `db`.
+fn init_audit_key(keystore: &mut KeyStore<Provider>, db: &mut DbView<Provider>) -> Result<(), ClientError> {
+    let audit_key = Key::random();
+    keystore.insert_key(audit_key_id(), audit_key.clone())?;
+    db.set_audit_key(audit_key);
+    Ok(())
 }
 
 impl Default for Client {
     fn default() -> Self {
+        let mut keystore = KeyStore::default();
+        let mut db = DbView::new();
+        init_audit_key(&mut keystore, &mut db).expect("Failed to store the audit key");
+
         Self {
-            keystore: Arc::new(RwLock::new(KeyStore::default())),
-            db: Arc::new(RwLock::new(DbView::new())),
+            keystore: Arc::new(RwLock::new(keystore)),
+            db: Arc::new(RwLock::new(db)),
+            vault_locks: Arc::new(Mutex::new(HashMap::new())),
             id: ClientId::default(),
             store: Store::default(),
+            approvals: Arc::new(RwLock::new(Approvals::default())),
+            escrow: Arc::new(RwLock::new(None)),
+            rng: Arc::new(RwLock::new(Arc::new(OsRng))),
+            trash_retention: Arc::new(RwLock::new(Duration::ZERO)),
+            gc_policy: Arc::new(RwLock::new(GcPolicy::default())),
+            operations: Arc::new(RwLock::new(Operations::default())),
+            revision: Arc::new(AtomicU64::new(0)),
+            history: Arc::new(RwLock::new(History::default())),
+            auto_lock: Arc::new(Mutex::new(None)),
+            usage: Arc::new(Mutex::new(UsageTracker::default())),
+            events: EventBus::default(),
+            store_sweep: Arc::new(AtomicU64::new(0)),
+            key_refresh: Arc::new(AtomicU64::new(0)),
+            metrics: MetricsRecorder::default(),
         }
     }
 }
@@ -58,6 +157,86 @@ impl Client {
         self.store.clone()
     }
 
+    /// Returns a [`StoreHandle`] on the [`Store`] of this client, whose keys are isolated in the namespace
+    /// `name`. Libraries sharing one client should each use their own namespace.
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::Stronghold;
+    ///
+    /// let stronghold = Stronghold::default();
+    /// let client = stronghold.create_client(b"client").unwrap();
+    /// let wallet = client.store_namespace("wallet");
+    /// let identity = client.store_namespace("identity");
+    /// wallet.insert(b"account", b"main".to_vec(), None).unwrap();
+    ///
+    /// assert_eq!(wallet.get(b"account").unwrap(), Some(b"main".to_vec()));
+    /// assert_eq!(identity.get(b"account").unwrap(), None);
+    /// assert_eq!(client.store().get(b"account").unwrap(), None);
+    /// ```
+    pub fn store_namespace(&self, name: &str) -> StoreHandle {
+        StoreHandle::new(self.store.clone(), name)
+    }
+
+    /// Removes all expired entries from the [`Store`], and publishes an [`Event::StoreEntryExpired`] for each
+    /// of them. Returns the keys of the removed entries.
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::Stronghold;
+    /// use std::time::Duration;
+    ///
+    /// let stronghold = Stronghold::default();
+    /// let client = stronghold.create_client(b"client").unwrap();
+    /// client
+    ///     .store()
+    ///     .insert(b"session".to_vec(), vec![], Some(Duration::ZERO))
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     client.store_prune_expired().unwrap(),
+    ///     vec![b"session".to_vec()]
+    /// );
+    /// ```
+    pub fn store_prune_expired(&self) -> Result<Vec<Vec<u8>>, ClientError> {
+        let expired = self.store.prune_expired()?;
+        for key in expired.iter() {
+            self.events.emit(Event::StoreEntryExpired {
+                client: self.id,
+                key: key.clone(),
+            });
+        }
+        Ok(expired)
+    }
+
+    /// Returns the lifetime, that is left for the entry with `key` in the [`Store`]. See
+    /// [`Store::remaining_ttl`].
+    ///
+    /// # Example
+    pub fn store_remaining_ttl(&self, key: &[u8]) -> Result<Option<Option<Duration>>, ClientError> {
+        self.store.remaining_ttl(key)
+    }
+
+    /// Prunes the expired entries of the [`Store`] in the background every `interval`, like
+    /// [`Self::store_prune_expired`]. A previous interval is replaced, `None` stops the background sweep.
+    /// The sweep also stops, once the client is dropped.
+    ///
+    /// Not available on WebAssembly, which has no threads to run it in the background.
+    ///
+    /// # Example
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_store_sweep(&self, interval: Option<Duration>) {
+        let id = self.store_sweep.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(interval) = interval {
+            let (store, generation, events, client) = (
+                self.store.downgrade(),
+                Arc::downgrade(&self.store_sweep),
+                self.events.clone(),
+                self.id,
+            );
+            std::thread::spawn(move || super::store::sweep(store, generation, id, interval, events, client));
+        }
+    }
+
     /// Returns a [`ClientVault`] according to path
     ///
     /// # Example
@@ -79,7 +258,7 @@ impl Client {
         P: AsRef<[u8]>,
     {
         let vault_id = derive_vault_id(vault_path);
-        let keystore = self.keystore.read()?;
+        let keystore = self.read_keystore()?;
 
         Ok(keystore.vault_exists(vault_id))
     }
@@ -95,6 +274,477 @@ impl Client {
         Ok(contains_record)
     }
 
+    /// Lists the ids and hints of the records in the vault at `vault_path`, whose hint starts with
+    /// `prefix`. The filter is applied inside the vault, so only the matching records are returned.
+    ///
+    /// # Example
+    pub fn list_records_matching<P>(
+        &self,
+        vault_path: P,
+        prefix: &[u8],
+    ) -> Result<Vec<(RecordId, RecordHint)>, ClientError>
+    where
+        P: AsRef<[u8]>,
+    {
+        let vault_id = derive_vault_id(vault_path);
+        let keystore = self.read_keystore()?;
+        let key = match keystore.get_key(vault_id) {
+            Some(key) => key,
+            None => return Ok(Vec::new()),
+        };
+        let db = self.db.read()?;
+        let records = db
+            .iter_records(&key, vault_id, HintFilter::Prefix(prefix.to_vec()))
+            .collect();
+        Ok(records)
+    }
+
+    /// Sets the tag `name` of the record at `location` to `value`, e.g. `purpose = signing`. Tags are
+    /// stored encrypted in the vault, and can be queried with [`Self::find_by_tag`].
+    ///
+    /// # Example
+    pub fn set_tag(&self, location: &Location, name: &str, value: &str) -> Result<(), ClientError> {
+        let (vault_id, record_id) = location.resolve();
+        let keystore = self.read_keystore()?;
+        let key = keystore
+            .get_key(vault_id)
+            .ok_or_else(|| ClientError::Inner(format!("Missing Key for vault {:?}", vault_id)))?;
+
+        self.db.write()?.set_tag(&key, vault_id, record_id, name, value)?;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Removes the tag `name` of the record at `location`. Returns `false`, if the record didn't have
+    /// the tag.
+    ///
+    /// # Example
+    pub fn remove_tag(&self, location: &Location, name: &str) -> Result<bool, ClientError> {
+        let (vault_id, record_id) = location.resolve();
+        let keystore = self.read_keystore()?;
+        let key = match keystore.get_key(vault_id) {
+            Some(key) => key,
+            None => return Ok(false),
+        };
+
+        let removed = self.db.write()?.remove_tag(&key, vault_id, record_id, name)?;
+        if removed {
+            self.mark_dirty();
+        }
+        Ok(removed)
+    }
+
+    /// Returns the tags of the record at `location`.
+    ///
+    /// # Example
+    pub fn tags(&self, location: &Location) -> Result<Tags, ClientError> {
+        let (vault_id, record_id) = location.resolve();
+        let keystore = self.read_keystore()?;
+        let key = match keystore.get_key(vault_id) {
+            Some(key) => key,
+            None => return Ok(Tags::new()),
+        };
+        let tags = self.db.read()?.tags(&key, vault_id, record_id)?;
+        Ok(tags)
+    }
+
+    /// Finds the records in the vault at `vault_path`, whose tag `name` is `value`.
+    ///
+    /// # Example
+    pub fn find_by_tag<P>(&self, vault_path: P, name: &str, value: &str) -> Result<Vec<RecordId>, ClientError>
+    where
+        P: AsRef<[u8]>,
+    {
+        let vault_id = derive_vault_id(vault_path);
+        let keystore = self.read_keystore()?;
+        let key = match keystore.get_key(vault_id) {
+            Some(key) => key,
+            None => return Ok(Vec::new()),
+        };
+        let records = self.db.read()?.find_by_tag(&key, vault_id, name, value)?;
+        Ok(records)
+    }
+
+    /// Returns the log of the revocations and deletions of records in the vault at `vault_path`, the oldest
+    /// entry first. The log is stored encrypted in the vault, and each entry is authenticated together with the
+    /// entries before it by a key of the client, that is separate from the vault keys, so it can serve as evidence
+    /// of when secrets have been destroyed.
+    ///
+    /// # Example
+    pub fn revocation_history<P>(&self, vault_path: P) -> Result<Vec<AuditEntry>, ClientError>
+    where
+        P: AsRef<[u8]>,
+    {
+        let vault_id = derive_vault_id(vault_path);
+        let keystore = self.read_keystore()?;
+        let key = match keystore.get_key(vault_id) {
+            Some(key) => key,
+            None => return Ok(Vec::new()),
+        };
+        let history = self.db.read()?.revocation_history(&key, vault_id)?;
+        Ok(history)
+    }
+
+    /// Returns a keyed fingerprint of the logical content of a vault.
+    ///
+    /// The fingerprint is maintained incrementally on every mutation of the vault. Two clients that hold
+    /// the same records, e.g. after synchronizing with each other, yield the same fingerprint for
+    /// the same `key`, so their state can be compared without transferring any records. The `key`
+    /// should be a secret shared between both parties, as it prevents others from
+    /// testing guesses about the content against a published fingerprint.
+    ///
+    /// # Example
+    pub fn vault_fingerprint<P, K>(&self, vault_path: P, key: K) -> Result<[u8; 32], ClientError>
+    where
+        P: AsRef<[u8]>,
+        K: AsRef<[u8]>,
+    {
+        let vault_id = derive_vault_id(vault_path);
+        let vault_key = self
+            .keystore
+            .read()?
+            .get_key(vault_id)
+            .ok_or_else(|| ClientError::Inner(format!("Missing Key for vault {:?}", vault_id)))?;
+
+        let digest = self.db.write()?.vault_digest(&vault_key, vault_id)?;
+
+        let mut data = vault_id.as_ref().to_vec();
+        data.extend_from_slice(&digest);
+
+        let mut mac = [0u8; 32];
+        HMAC_SHA256(&data, key.as_ref(), &mut mac);
+        Ok(mac)
+    }
+
+    /// Re-encrypts all records of the vault at `vault_path` with a freshly generated key, that replaces
+    /// the old key of the vault. Revoked records, that have not been garbage collected yet, are deleted
+    /// together with their transactions. Returns the number of re-encrypted records.
+    ///
+    /// Only the vault itself is rewritten. The rotated key is persisted with the next commit of the
+    /// [`Snapshot`][crate::Snapshot].
+    ///
+    /// # Example
+    pub fn rotate_vault_key<P>(&self, vault_path: P) -> Result<usize, ClientError>
+    where
+        P: AsRef<[u8]>,
+    {
+        let vault_id = derive_vault_id(vault_path);
+        let mut keystore = self.write_keystore()?;
+        let mut db = self.db.write()?;
+
+        let old_key = keystore
+            .get_key(vault_id)
+            .ok_or_else(|| ClientError::Inner(format!("Missing Key for vault {:?}", vault_id)))?;
+        let new_key = Key::random();
+
+        let rotated = db.rekey_vault(&old_key, &new_key, vault_id)?;
+        keystore.insert_key(vault_id, new_key)?;
+        self.mark_dirty();
+
+        Ok(rotated)
+    }
+
+    /// Writes `data` with `hint` into the vault at `location`. Unlike the writes through
+    /// [`ClientVault::write_secret`], the hint is not random, so the record can be found with
+    /// [`Self::list_records_matching`].
+    pub fn write_to_vault_with_hint(
+        &self,
+        location: &Location,
+        data: Vec<u8>,
+        hint: RecordHint,
+    ) -> Result<(), ClientError> {
+        self.write_record(location, data, hint, None)
+    }
+
+    /// Writes `data` with `hint` into the vault at `location`, and lets the record expire after `ttl`.
+    /// Expired records can't be read anymore, and are deleted by the next garbage collection of the
+    /// vault. Overwriting the record replaces its expiry.
+    pub fn write_to_vault_with_ttl(
+        &self,
+        location: &Location,
+        data: Vec<u8>,
+        hint: RecordHint,
+        ttl: Duration,
+    ) -> Result<(), ClientError> {
+        let expires_at = SystemTime::now()
+            .checked_add(ttl)
+            .ok_or_else(|| ClientError::Inner(format!("Invalid ttl {:?}", ttl)))?;
+        self.write_record(location, data, hint, Some(expires_at))
+    }
+
+    /// Writes all `records` with their hints. The key of each vault is only looked up once, and the
+    /// vaults are locked once for the whole batch, which speeds up importing many records at once.
+    /// Writing stops at the first failure, the records that have been written before it are kept.
+    ///
+    /// # Example
+    pub fn write_batch(&self, records: Vec<(Location, Vec<u8>, RecordHint)>) -> Result<(), ClientError> {
+        let mut batches: HashMap<VaultId, (Vec<WriteRequest>, Vec<Location>)> = HashMap::new();
+        for (location, data, hint) in records {
+            let (vault_id, record_id) = location.resolve();
+            let (requests, locations) = batches.entry(vault_id).or_default();
+            requests.push(WriteRequest { record_id, data, hint });
+            locations.push(location);
+        }
+
+        let mut written = Vec::new();
+        let res = {
+            let mut keystore = self.write_keystore()?;
+            let mut db = self.db.write()?;
+            self.mark_dirty();
+
+            batches
+                .into_iter()
+                .try_for_each(|(vault_id, (requests, locations))| -> Result<(), ClientError> {
+                    let key = match keystore.get_key(vault_id) {
+                        Some(key) => key,
+                        None => keystore.create_key(vault_id)?,
+                    };
+                    db.write_batch(&key, vault_id, requests)?;
+                    written.extend(locations);
+                    Ok(())
+                })
+        };
+
+        // the events are emitted once the locks have been released, so that subscribers can access the client
+        written.into_iter().for_each(|location| {
+            self.events.emit(Event::RecordWritten {
+                client: self.id,
+                location,
+            })
+        });
+        res
+    }
+
+    /// Collects the vault and store mutations of `f` in a [`Transaction`], and applies them all at once. If `f`
+    /// or one of the mutations fails, none of the mutations becomes visible, and the error is returned. The
+    /// vaults and the store are copied for the duration of the transaction, so that they can be restored.
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::{Location, Stronghold};
+    ///
+    /// let stronghold = Stronghold::default();
+    /// let client = stronghold.create_client(b"client").unwrap();
+    /// let location = Location::const_generic(b"vault".to_vec(), b"seed".to_vec());
+    ///
+    /// client
+    ///     .transaction(|tx| {
+    ///         tx.write_vault(location.clone(), b"secret".to_vec())
+    ///             .write_store(b"account".to_vec(), b"main".to_vec(), None);
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// assert!(client.record_exists(&location).unwrap());
+    /// ```
+    pub fn transaction<F, T>(&self, f: F) -> Result<T, ClientError>
+    where
+        F: FnOnce(&mut Transaction) -> Result<T, ClientError>,
+    {
+        self.ensure_unlocked()?;
+        let mut transaction = Transaction::default();
+        let value = f(&mut transaction)?;
+        if transaction.is_empty() {
+            return Ok(value);
+        }
+
+        let mut keystore = self.write_keystore()?;
+        let mut db = self.db.write()?;
+        let mut store = self.store.write()?;
+
+        let db_backup = db.clone();
+        let store_backup = store.backup();
+        let mut created = Vec::new();
+        let mut written = Vec::new();
+
+        let mut apply = |mutation: &mut Mutation| -> Result<(), ClientError> {
+            match mutation {
+                Mutation::WriteVault { location, data } => {
+                    let (vault_id, record_id) = location.resolve();
+                    let key = match keystore.get_key(vault_id) {
+                        Some(key) => key,
+                        None => {
+                            let key = keystore.create_key(vault_id)?;
+                            db.init_vault(&key, vault_id);
+                            created.push(vault_id);
+                            key
+                        }
+                    };
+                    let hint = RecordHint::new(rand::variable_bytestring(DEFAULT_RANDOM_HINT_SIZE))
+                        .expect("Random hint has a valid size");
+                    let res = db.write(&key, vault_id, record_id, data, hint);
+                    data.zeroize();
+                    res?;
+                    written.push(location.clone());
+                }
+                Mutation::Delete(location) => {
+                    // records, that don't exist, are ignored
+                    let (vault_id, record_id) = location.resolve();
+                    match keystore.get_key(vault_id) {
+                        Some(key) if db.contains_record(vault_id, record_id) => {
+                            db.revoke_record(&key, vault_id, record_id)?
+                        }
+                        _ => {}
+                    }
+                }
+                Mutation::WriteStore { key, value, lifetime } => {
+                    store.insert(std::mem::take(key), std::mem::take(value), *lifetime)?;
+                }
+                Mutation::DeleteStore(key) => {
+                    store.delete(key);
+                }
+            }
+            Ok(())
+        };
+        let res = transaction.mutations.iter_mut().try_for_each(&mut apply);
+
+        if let Err(e) = res {
+            *db = db_backup;
+            store.restore(store_backup);
+            created.into_iter().for_each(|vault_id| {
+                keystore.take_key(vault_id);
+            });
+            return Err(e);
+        }
+        self.mark_dirty();
+        drop((keystore, db, store));
+
+        written.into_iter().for_each(|location| {
+            self.events.emit(Event::RecordWritten {
+                client: self.id,
+                location,
+            })
+        });
+        Ok(value)
+    }
+
+    fn write_record(
+        &self,
+        location: &Location,
+        mut data: Vec<u8>,
+        hint: RecordHint,
+        expires_at: Option<SystemTime>,
+    ) -> Result<(), ClientError> {
+        let (vault_id, record_id) = location.resolve();
+        let mut keystore = self.write_keystore()?;
+        let mut db = self.db.write()?;
+
+        let key = match keystore.get_key(vault_id) {
+            Some(key) => key,
+            None => {
+                let key = keystore.create_key(vault_id)?;
+                db.init_vault(&key, vault_id);
+                key
+            }
+        };
+        let start = Instant::now();
+        let res = db.write_with_expiry(&key, vault_id, record_id, &data, hint, expires_at);
+        self.metrics.vault_write(start.elapsed());
+        data.zeroize();
+        res?;
+        self.mark_dirty();
+        drop((keystore, db));
+
+        self.events.emit(Event::RecordWritten {
+            client: self.id,
+            location: location.clone(),
+        });
+        Ok(())
+    }
+
+    /// Copies the record at `from` to `to`, either in this client, or in the `destination` client. The record is
+    /// re-encrypted with the key of the destination vault inside the engine, so the secret is never exposed.
+    /// An existing record at `to` is replaced.
+    ///
+    /// Like a [`CopyRecord`] procedure, the copy has to be approved, if the
+    /// vault at `from` is guarded, and counts as a use of the record at `from`, see [`Self::set_usage_policy`].
+    ///
+    /// # Example
+    pub fn copy_record(&self, from: &Location, to: &Location, destination: Option<&Client>) -> Result<(), ClientError> {
+        let (vault_id, record_id) = from.resolve();
+        let (target_vault_id, target_record_id) = to.resolve();
+        let target = destination.unwrap_or(self);
+
+        let procedure = StrongholdProcedure::CopyRecord(CopyRecord {
+            source: from.clone(),
+            target: to.clone(),
+        });
+        let operation = Operations::begin(&self.operations, self.id, std::slice::from_ref(&procedure))
+            .map_err(|e| ClientError::Inner(e.to_string()))?;
+        if let Err(e) = self.approve(&procedure, &operation) {
+            if matches!(e, ProcedureError::NotApproved) {
+                self.events.emit(Event::AccessDenied {
+                    client: self.id,
+                    kind: Some(procedure.name()),
+                    reason: DenyReason::NotApproved,
+                });
+            }
+            return Err(ClientError::Inner(e.to_string()));
+        }
+        let mut reservation = UsageReservation::new(&self.usage);
+        reservation.reserve(vault_id, record_id).map_err(|e| {
+            self.events.emit(Event::AccessDenied {
+                client: self.id,
+                kind: Some(procedure.name()),
+                reason: DenyReason::UsageLimit,
+            });
+            ClientError::Inner(e)
+        })?;
+
+        // the locks of both clients are never held at the same time, so that copying within the same
+        // client and concurrent copies in opposite directions don't deadlock
+        let existing_key = target.read_keystore()?.get_key(target_vault_id);
+        let target_key = existing_key.clone().unwrap_or_else(Key::random);
+
+        let record = {
+            let keystore = self.read_keystore()?;
+            let key = keystore
+                .get_key(vault_id)
+                .ok_or_else(|| ClientError::Inner(format!("Missing Key for vault {:?}", vault_id)))?;
+            let db = self.db.read()?;
+            db.reencrypt_record(&key, vault_id, record_id, &target_key, target_record_id)?
+        };
+
+        let mut keystore = target.write_keystore()?;
+        let mut db = target.db.write()?;
+        if existing_key.is_none() {
+            let key = keystore.get_or_insert_key(target_vault_id, target_key.clone())?;
+            if key != target_key {
+                return Err(ClientError::Inner(format!(
+                    "Vault {:?} has been created concurrently",
+                    target_vault_id
+                )));
+            }
+        }
+        db.insert_record(&target_key, target_vault_id, target_record_id, record)?;
+        target.mark_dirty();
+        drop((keystore, db));
+
+        // the use is counted with the other state of this client
+        reservation.complete();
+        self.mark_dirty();
+
+        target.events.emit(Event::RecordWritten {
+            client: target.id,
+            location: to.clone(),
+        });
+        Ok(())
+    }
+
+    /// Moves the record at `from` to `to`, like [`Self::copy_record`], and revokes the record at `from`
+    /// afterwards. Moving a record onto itself, e.g. with `destination` being this client, does nothing.
+    ///
+    /// # Example
+    pub fn move_record(&self, from: &Location, to: &Location, destination: Option<&Client>) -> Result<(), ClientError> {
+        let same_client = destination.is_none_or(|destination| destination.id == self.id);
+        if same_client && from.resolve() == to.resolve() {
+            return Ok(());
+        }
+        self.copy_record(from, to, destination)?;
+        self.ensure_unlocked()?;
+        self.revoke_data(from)?;
+        Ok(())
+    }
+
     /// Synchronize two vaults of the client so that records are copied from `source` to `target`.
     /// If `select_records` is `Some` only the specified records are copied, else a full sync
     /// is performed. If a record already exists at the target, the [`MergePolicy`] applies.
@@ -125,8 +775,9 @@ impl Client {
         let diff = self.get_diff(hierarchy, &config)?;
         let exported = self.export_entries(diff)?;
 
-        let mut key_store = self.keystore.write()?;
+        let mut key_store = self.write_keystore()?;
         let mut db = self.db.write()?;
+        self.mark_dirty();
 
         for (vid, records) in exported {
             let mapped_vid = config.map_vaults.remove(&vid).unwrap_or(vid);
@@ -160,19 +811,367 @@ impl Client {
             }
             let mapped_vid = config.map_vaults.get(&vid).copied().unwrap_or(vid);
             let old_key = other
-                .keystore
-                .read()?
+                .read_keystore()?
                 .get_key(vid)
                 .ok_or_else(|| ClientError::Inner(format!("Missing Key for vault {:?}", vid)))?;
 
-            let mut keystore = self.keystore.write()?;
+            let mut keystore = self.write_keystore()?;
             let mut db = self.db.write()?;
+            self.mark_dirty();
             let new_key = keystore.get_or_insert_key(mapped_vid, Key::random())?;
             db.import_records(&old_key, &new_key, mapped_vid, records)?
         }
         Ok(())
     }
 
+    /// Requires approval for all procedures that access the vault at `vault_path`.
+    ///
+    /// Before such a procedure is executed, `handler` is called with the [`ApprovalRequest`] and an
+    /// [`ApprovalResponder`]. Execution only proceeds if the request is approved within `timeout`,
+    /// otherwise it fails with [`ProcedureError::NotApproved`]. A previously registered handler
+    /// for the same vault is replaced.
+    ///
+    /// `vault_path` is treated as [`crate::VaultPath`]: the handler also guards all vaults in its subtree,
+    /// unless a descendant has a handler of its own. `vault_path` may also be a pattern like `org/*/keys`
+    /// to guard all matching vaults, or `org/*/keys/**` to include their subtrees.
+    ///
+    /// # Example
+    pub fn require_approval<P, F>(&self, vault_path: P, timeout: Duration, handler: F) -> Result<(), ClientError>
+    where
+        P: AsRef<[u8]>,
+        F: Fn(ApprovalRequest, ApprovalResponder) + Send + Sync + 'static,
+    {
+        let mut approvals = self.approvals.write()?;
+        approvals.insert(vault_path.as_ref().to_vec(), timeout, Arc::new(handler));
+        Ok(())
+    }
+
+    /// Removes the approval handler for the vault at `vault_path`. Returns `true`, if a
+    /// handler was present.
+    ///
+    /// # Example
+    pub fn remove_approval<P>(&self, vault_path: P) -> Result<bool, ClientError>
+    where
+        P: AsRef<[u8]>,
+    {
+        let mut approvals = self.approvals.write()?;
+        Ok(approvals.remove(vault_path.as_ref()))
+    }
+
+    /// Enables the escrow of newly generated keys. Each key created by [`crate::procedures::GenerateKey`],
+    /// [`crate::procedures::Slip10Generate`] or [`crate::procedures::BIP39Generate`] is additionally sealed
+    /// for the organizational recovery key and appended to the escrow entry in the client's [`Store`].
+    /// Passing `None` disables escrow.
+    ///
+    /// If escrowing a key fails, the procedure fails and the new key is revoked.
+    ///
+    /// # Example
+    pub fn set_escrow(&self, config: Option<EscrowConfig>) -> Result<(), ClientError> {
+        *self.escrow.write()? = config;
+        Ok(())
+    }
+
+    /// Sets a custom entropy source, e.g. a hardware TRNG, for all procedures that generate keys or seeds.
+    /// The output of `source` is mixed with the random number generator of the operating system.
+    ///
+    /// # Example
+    pub fn set_entropy_source<R>(&self, source: R) -> Result<(), ClientError>
+    where
+        R: SecureRng + 'static,
+    {
+        *self.rng.write()? = Arc::new(MixedRng(Arc::new(source)));
+        Ok(())
+    }
+
+    /// Replaces the random number generator for all procedures that generate keys or seeds with `rng`.
+    /// The random number generator of the operating system is not used anymore. This is intended
+    /// for deterministic tests.
+    ///
+    /// # Security
+    ///
+    /// Secrets generated this way are only as unpredictable as `rng`.
+    #[cfg(feature = "insecure")]
+    pub fn set_rng<R>(&self, rng: R) -> Result<(), ClientError>
+    where
+        R: SecureRng + 'static,
+    {
+        *self.rng.write()? = Arc::new(rng);
+        Ok(())
+    }
+
+    /// Sets how long revoked records are kept, before garbage collection deletes them. Until then,
+    /// they can be restored with [`ClientVault::restore_secret`]. A retention of zero, which is the
+    /// default, deletes revoked records on the next garbage collection.
+    ///
+    /// # Example
+    pub fn set_trash_retention(&self, retention: Duration) -> Result<(), ClientError> {
+        *self.trash_retention.write()? = retention;
+        Ok(())
+    }
+
+    /// Sets the limits of all vaults of this client together. Writes, that would exceed them, fail with
+    /// [`ClientError::VaultQuotaExceeded`]. The quotas are not persisted in snapshots.
+    ///
+    /// # Example
+    pub fn set_quota(&self, quota: Quota) -> Result<(), ClientError> {
+        self.db.write()?.set_quota(quota);
+        Ok(())
+    }
+
+    /// Sets the limits of a single vault, in addition to the limits of [`Self::set_quota`].
+    ///
+    /// # Example
+    pub fn set_vault_quota<P>(&self, vault_path: P, quota: Quota) -> Result<(), ClientError>
+    where
+        P: AsRef<[u8]>,
+    {
+        let vault_id = derive_vault_id(vault_path);
+        self.db.write()?.set_vault_quota(vault_id, quota);
+        Ok(())
+    }
+
+    /// Sets the [`GcPolicy`] of all vaults of this client. The retention of [`Self::set_trash_retention`]
+    /// also applies to the garbage collections of the policy. The default is [`GcPolicy::Manual`].
+    ///
+    /// # Example
+    pub fn set_gc_policy(&self, policy: GcPolicy) -> Result<(), ClientError> {
+        *self.gc_policy.write()? = policy;
+        Ok(())
+    }
+
+    /// Returns the revoked records of a vault, that are waiting for garbage collection. Returns `None`,
+    /// if the vault does not exist.
+    ///
+    /// # Example
+    pub fn gc_stats<P>(&self, vault_path: P) -> Result<Option<GcStats>, ClientError>
+    where
+        P: AsRef<[u8]>,
+    {
+        let vault_id = derive_vault_id(vault_path);
+        Ok(self.db.read()?.vault_gc_stats(vault_id))
+    }
+
+    /// Sets the number of previous versions, that are kept for each record when it is overwritten.
+    /// Until the next garbage collection of the vault, they can be read with [`Self::read_version`]
+    /// and restored with [`Self::revert`]. The history is not persisted in snapshots. A depth of zero,
+    /// which is the default, disables it.
+    ///
+    /// # Example
+    pub fn set_history_depth(&self, depth: usize) -> Result<(), ClientError> {
+        self.db.write()?.set_history_depth(depth);
+        Ok(())
+    }
+
+    /// Applies `f` to the `n`-th previous version of the record at `location`. Version `0` is the
+    /// current one.
+    ///
+    /// # Example
+    pub fn read_version<F, T>(&self, location: &Location, n: usize, f: F) -> Result<T, ClientError>
+    where
+        F: FnOnce(Buffer<u8>) -> Result<T, FatalProcedureError>,
+    {
+        let (vault_id, record_id) = location.resolve();
+        let keystore = self.read_keystore()?;
+        let key = keystore
+            .get_key(vault_id)
+            .ok_or_else(|| ClientError::Inner(format!("Missing Key for vault {:?}", vault_id)))?;
+
+        let mut ret = None;
+        self.db
+            .read()?
+            .get_version_guard(&key, vault_id, record_id, n, |guard| {
+                ret = Some(f(guard)?);
+                Ok::<_, FatalProcedureError>(())
+            })?;
+        Ok(ret.expect("Output has been set"))
+    }
+
+    /// Makes the `n`-th previous version of the record at `location` the current one. The newer
+    /// versions, including the current one, are discarded.
+    ///
+    /// # Example
+    pub fn revert(&self, location: &Location, n: usize) -> Result<(), ClientError> {
+        let (vault_id, record_id) = location.resolve();
+        let keystore = self.read_keystore()?;
+        let key = keystore
+            .get_key(vault_id)
+            .ok_or_else(|| ClientError::Inner(format!("Missing Key for vault {:?}", vault_id)))?;
+
+        self.db.write()?.revert_record(&key, vault_id, record_id, n)?;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Lists the operations that are currently executed on this client, ordered by their start.
+    ///
+    /// # Example
+    pub fn list_operations(&self) -> Result<Vec<Operation>, ClientError> {
+        Ok(self.operations.read()?.list())
+    }
+
+    /// Aborts the in-flight operation with `id`. The operation fails with [`ProcedureError::Aborted`]
+    /// before its next procedure is executed, or while it waits for approval. Returns `false`, if no
+    /// such operation is running.
+    ///
+    /// # Example
+    pub fn abort(&self, id: OperationId) -> Result<bool, ClientError> {
+        Ok(self.operations.read()?.abort(id))
+    }
+
+    /// Keeps summaries of the last `capacity` procedure executions in memory, see [`ProcedureSummary`].
+    /// Inputs and outputs of the procedures are never recorded. A capacity of zero, which is the
+    /// default, disables the history and drops all summaries.
+    ///
+    /// # Example
+    pub fn set_procedure_history(&self, capacity: usize) -> Result<(), ClientError> {
+        self.history.write()?.set_capacity(capacity);
+        Ok(())
+    }
+
+    /// Returns the summaries of the most recent procedure executions, the most recent one last.
+    ///
+    /// # Example
+    pub fn procedure_history(&self) -> Result<Vec<ProcedureSummary>, ClientError> {
+        Ok(self.history.read()?.list())
+    }
+
+    /// Returns how often procedures have successfully used the record at `location` as a source. The counters
+    /// are persisted with the client in snapshots.
+    ///
+    /// # Example
+    pub fn usage(&self, location: &Location) -> Result<Usage, ClientError> {
+        let (vault_id, record_id) = location.resolve();
+        Ok(self.usage.lock()?.usage(vault_id, record_id))
+    }
+
+    /// Limits the use of the record at `location` by procedures. Procedures, that would exceed the limits, fail
+    /// before they read the record. [`UsagePolicy::default`] removes all limits. Policies are not persisted in
+    /// snapshots.
+    ///
+    /// # Example
+    pub fn set_usage_policy(&self, location: &Location, policy: UsagePolicy) -> Result<(), ClientError> {
+        let (vault_id, record_id) = location.resolve();
+        self.usage.lock()?.set_policy(vault_id, record_id, policy);
+        Ok(())
+    }
+
+    /// Locks the client, once no operation on its secrets has been executed for `idle_timeout`. Locking
+    /// seals the keys of all vaults with the key of `keyprovider`, removes them from memory, and drops
+    /// `keyprovider`. Until [`Self::unlock`] is called with the same key, operations on the secrets fail
+    /// with [`ClientError::Locked`], and procedures with [`ProcedureError::Locked`].
+    ///
+    /// A previous auto-lock is replaced. Fails with [`ClientError::Locked`], if the client is locked.
+    ///
+    /// Not available on WebAssembly, which has no threads to run it in the background.
+    ///
+    /// # Example
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_auto_lock(&self, idle_timeout: Duration, keyprovider: crate::KeyProvider) -> Result<(), ClientError> {
+        let mut auto_lock = self.auto_lock.lock()?;
+        if auto_lock.as_ref().is_some_and(AutoLock::is_locked) {
+            return Err(ClientError::Locked);
+        }
+        let new = AutoLock::new(idle_timeout, keyprovider);
+        let (state, keystore, id) = (
+            Arc::downgrade(&self.auto_lock),
+            Arc::downgrade(&self.keystore),
+            new.id(),
+        );
+        *auto_lock = Some(new);
+        std::thread::spawn(move || autolock::watch(state, keystore, id));
+        Ok(())
+    }
+
+    /// Refreshes the long-lived keys of this client in memory: the vault keys are re-encrypted with a new
+    /// master key, and the key provider of the auto-lock is re-split with fresh randomness. This limits the
+    /// window, in which fragments of the keys, that have been scraped from memory, can be combined.
+    ///
+    /// The duration of each cycle is recorded in [`Metrics::key_refreshes`](crate::Metrics::key_refreshes).
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::Stronghold;
+    ///
+    /// let stronghold = Stronghold::default();
+    /// let client = stronghold.create_client(b"client").unwrap();
+    /// client.refresh_keys().unwrap();
+    /// assert_eq!(stronghold.metrics().unwrap().key_refreshes.count, 1);
+    /// ```
+    pub fn refresh_keys(&self) -> Result<(), ClientError> {
+        refresh::refresh(&self.keystore, &self.auto_lock, &self.metrics)
+    }
+
+    /// Refreshes the keys of this client in the background every `interval`, like [`Self::refresh_keys`]. A
+    /// previous interval is replaced, `None` stops the background refresh. The refresh also stops, once the
+    /// client is dropped.
+    ///
+    /// Not available on WebAssembly, which has no threads to run it in the background.
+    ///
+    /// # Example
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_key_refresh(&self, interval: Option<Duration>) {
+        let id = self.key_refresh.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(interval) = interval {
+            let (keystore, auto_lock, generation, metrics) = (
+                Arc::downgrade(&self.keystore),
+                Arc::downgrade(&self.auto_lock),
+                Arc::downgrade(&self.key_refresh),
+                self.metrics.clone(),
+            );
+            std::thread::spawn(move || refresh::watch(keystore, auto_lock, generation, id, interval, metrics));
+        }
+    }
+
+    /// Disables the auto-lock of [`Self::set_auto_lock`]. Fails with [`ClientError::Locked`], if the client
+    /// is locked.
+    ///
+    /// # Example
+    pub fn disable_auto_lock(&self) -> Result<(), ClientError> {
+        let mut auto_lock = self.auto_lock.lock()?;
+        if auto_lock.as_ref().is_some_and(AutoLock::is_locked) {
+            return Err(ClientError::Locked);
+        }
+        *auto_lock = None;
+        Ok(())
+    }
+
+    /// Locks the client immediately, without waiting for the idle timeout. Has no effect, if no auto-lock
+    /// has been set with [`Self::set_auto_lock`].
+    ///
+    /// # Example
+    pub fn lock(&self) -> Result<(), ClientError> {
+        if let Some(auto_lock) = self.auto_lock.lock()?.as_mut() {
+            auto_lock.lock(&mut *self.keystore.write()?)?;
+        }
+        Ok(())
+    }
+
+    /// Unlocks the client with the key, that it has been locked with. Fails with
+    /// [`ClientError::WrongUnlockKey`] otherwise. Has no effect, if the client is not locked.
+    ///
+    /// # Example
+    pub fn unlock(&self, keyprovider: crate::KeyProvider) -> Result<(), ClientError> {
+        if let Some(auto_lock) = self.auto_lock.lock()?.as_mut() {
+            auto_lock.unlock(keyprovider, &mut *self.keystore.write()?)?;
+        }
+        Ok(())
+    }
+
+    /// Returns `true`, if the client has been locked and not unlocked since.
+    ///
+    /// # Example
+    pub fn is_locked(&self) -> Result<bool, ClientError> {
+        Ok(self.auto_lock.lock()?.as_ref().is_some_and(AutoLock::is_locked))
+    }
+
+    /// Returns the [`EventBus`], that the events of this client are published on. The bus of a client, that has
+    /// been created or loaded by a [`Stronghold`], is the bus of the Stronghold.
+    ///
+    /// # Example
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
     /// Returns the [`ClientId`] of the client
     ///
     /// # Example
@@ -180,11 +1179,75 @@ impl Client {
         &self.id
     }
 
+    /// Returns a counter, that changes with every modification of the state that is persisted in a snapshot
+    pub(crate) fn revision(&self) -> u64 {
+        self.revision.load(Ordering::SeqCst) + self.store.revision()
+    }
+
+    /// Records a modification of the keystore or the vaults
+    pub(crate) fn mark_dirty(&self) {
+        self.revision.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Fails with [`ClientError::Locked`], if the client is locked, or if it has been idle for longer than
+    /// the timeout of its auto-lock, in which case it is locked now. Otherwise the call counts as activity.
+    ///
+    /// Must not be called while holding a lock on the keystore.
+    pub(crate) fn ensure_unlocked(&self) -> Result<(), ClientError> {
+        self.unlocked().map(|_| ())
+    }
+
+    /// Same as [`Self::ensure_unlocked`], but returns the lock of the auto-lock, so that the client can't be
+    /// locked until it is released
+    fn unlocked(&self) -> Result<MutexGuard<'_, Option<AutoLock>>, ClientError> {
+        let mut guard = self.auto_lock.lock()?;
+        if let Some(auto_lock) = guard.as_mut() {
+            if !auto_lock.is_locked() && auto_lock.is_idle() {
+                auto_lock.lock(&mut *self.keystore.write()?)?;
+            }
+            if auto_lock.is_locked() {
+                self.events.emit(Event::AccessDenied {
+                    client: self.id,
+                    kind: None,
+                    reason: DenyReason::Locked,
+                });
+                return Err(ClientError::Locked);
+            }
+            auto_lock.touch();
+        }
+        Ok(guard)
+    }
+
+    /// Read access to the keystore, if the client is unlocked. The keystore is acquired before the auto-lock is
+    /// released, and locking needs write access to it, so the client stays unlocked while the guard is held.
+    pub(crate) fn read_keystore(&self) -> Result<RwLockReadGuard<'_, KeyStore<Provider>>, ClientError> {
+        let _unlocked = self.unlocked()?;
+        Ok(self.keystore.read()?)
+    }
+
+    /// Write access to the keystore, if the client is unlocked, see [`Self::read_keystore`]
+    pub(crate) fn write_keystore(&self) -> Result<RwLockWriteGuard<'_, KeyStore<Provider>>, ClientError> {
+        let _unlocked = self.unlocked()?;
+        Ok(self.keystore.write()?)
+    }
+
+    /// Write access to the keystore for persisting the client, or `None`, if the client is locked and its
+    /// keystore is empty. Unlike [`Self::write_keystore`], it doesn't count as activity.
+    pub(crate) fn keystore_unless_locked(
+        &self,
+    ) -> Result<Option<RwLockWriteGuard<'_, KeyStore<Provider>>>, ClientError> {
+        let auto_lock = self.auto_lock.lock()?;
+        if auto_lock.as_ref().is_some_and(AutoLock::is_locked) {
+            return Ok(None);
+        }
+        Ok(Some(self.keystore.write()?))
+    }
+
     /// Loads the state of [`Self`] from a [`ClientState`]. Replaces all previous data.
     ///
     /// # Example
     pub(crate) fn restore(&mut self, state: ClientState, id: ClientId) -> Result<(), ClientError> {
-        let (keys, db, st) = state;
+        let (mut keys, mut db, st) = state;
 
         self.id = id;
 
@@ -193,18 +1256,69 @@ impl Client {
         let mut view = self.db.write()?;
         let mut store = self.store.cache.write()?;
 
+        let audit_key = keys.get(&audit_key_id()).cloned();
+        let usage_key = keys.remove(&usage_vault_id());
+        let usage_vault = db.vaults.remove(&usage_vault_id());
         let mut new_keystore = KeyStore::<Provider>::default();
         new_keystore
             .rebuild_keystore(keys)
             .map_err(|e| ClientError::Inner(e.to_string()))?;
 
+        // the history depth and the quotas are settings of the client, and not part of the state
         *keystore = new_keystore;
-        *view = db;
+        view.vaults = db.vaults;
+        match audit_key {
+            Some(audit_key) => view.set_audit_key(audit_key),
+            None => init_audit_key(&mut keystore, &mut view)?,
+        }
         *store = st;
+        self.mark_dirty();
+        self.store.mark_dirty();
+
+        let mut usage = self.usage.lock()?;
+        match (usage_key, usage_vault) {
+            (Some(key), Some(vault)) => {
+                let mut usage_view = DbView::<Provider>::new();
+                usage_view.vaults.insert(usage_vault_id(), vault);
+                usage_view
+                    .get_guard(&key, usage_vault_id(), usage_record_id(), |counters| {
+                        usage.restore(&counters.borrow())
+                    })
+                    .map_err(|e| ClientError::Inner(e.to_string()))?;
+            }
+            _ => usage.clear(),
+        }
 
         Ok(())
     }
 
+    /// Adds the usage counters of the records to `keys` and `db` of the state of this client, that is persisted
+    /// in a snapshot. They are stored in a vault with a reserved id, that is removed again by [`Self::restore`].
+    pub(crate) fn persist_usage(
+        &self,
+        keys: &mut HashMap<VaultId, Key<Provider>>,
+        db: &mut DbView<Provider>,
+    ) -> Result<(), ClientError> {
+        let counters = self
+            .usage
+            .lock()?
+            .serialize()
+            .map_err(|e| ClientError::Inner(e.to_string()))?;
+        let key = Key::random();
+        let hint = RecordHint::new(rand::variable_bytestring(DEFAULT_RANDOM_HINT_SIZE)).expect("Hint is not too long");
+        let mut usage_view = DbView::<Provider>::new();
+        usage_view
+            .write(&key, usage_vault_id(), usage_record_id(), &counters, hint)
+            .map_err(|e| ClientError::Inner(e.to_string()))?;
+        let vault = usage_view
+            .vaults
+            .remove(&usage_vault_id())
+            .expect("Vault has been initiated by the write");
+        db.vaults.insert(usage_vault_id(), vault);
+        keys.insert(usage_vault_id(), key);
+        Ok(())
+    }
+
     /// Clears the inner [`Client`] state. This functions should not be called directly
     /// but by calling the function of same name on [`Stronghold`]
     pub(crate) fn clear(&self) -> Result<(), ClientError> {
@@ -215,6 +1329,10 @@ impl Client {
         view.clear();
         store.clear();
         ks.clear_keys();
+        init_audit_key(&mut ks, &mut view)?;
+        self.usage.lock()?.clear();
+        self.mark_dirty();
+        self.store.mark_dirty();
 
         Ok(())
     }
@@ -232,22 +1350,128 @@ impl Client {
         Ok(mapped)
     }
 
+    /// Executes a cryptographic [`Procedure`] like [`Self::execute_procedure`], and inserts its non-secret output
+    /// into the [`Store`] of the client, if `target` is [`OutputTarget::Store`]. Derived public material, e.g.
+    /// addresses or public keys, can be cached that way, with its expiry managed by the store.
+    ///
+    /// The output is only inserted, if the procedure has succeeded.
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::{
+    ///     procedures::{GenerateKey, KeyType, OutputTarget, PublicKey},
+    ///     Location, Stronghold,
+    /// };
+    /// use std::time::Duration;
+    ///
+    /// let stronghold = Stronghold::default();
+    /// let client = stronghold.create_client(b"client").unwrap();
+    /// let key = Location::const_generic(b"keys".to_vec(), b"identity".to_vec());
+    /// client
+    ///     .execute_procedure(GenerateKey {
+    ///         ty: KeyType::Ed25519,
+    ///         output: key.clone(),
+    ///     })
+    ///     .unwrap();
+    ///
+    /// let target = OutputTarget::Store {
+    ///     key: b"identity.pub".to_vec(),
+    ///     ttl: Some(Duration::from_secs(3600)),
+    /// };
+    /// let public_key = client
+    ///     .execute_procedure_with_target(
+    ///         PublicKey {
+    ///             ty: KeyType::Ed25519,
+    ///             private_key: key,
+    ///         },
+    ///         target,
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     client.store().get(b"identity.pub").unwrap(),
+    ///     Some(public_key)
+    /// );
+    /// ```
+    pub fn execute_procedure_with_target<P>(
+        &self,
+        procedure: P,
+        target: OutputTarget,
+    ) -> Result<P::Output, ProcedureError>
+    where
+        P: Procedure + Into<StrongholdProcedure>,
+    {
+        let output = self.execute_procedure_chained(vec![procedure.into()])?.pop().unwrap();
+        if let OutputTarget::Store { key, ttl } = target {
+            self.store
+                .insert(key, output.clone().into(), ttl)
+                .map_err(|e| ProcedureError::Engine(e.to_string().into()))?;
+        }
+        Ok(output.try_into().ok().unwrap())
+    }
+
     /// Executes a list of cryptographic [`crate::procedures::Procedure`]s sequentially and returns a collected output
     ///
     /// # Example
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(client = ?self.id, procedures = procedures.len())))]
     pub fn execute_procedure_chained(
         &self,
         procedures: Vec<StrongholdProcedure>,
     ) -> core::result::Result<Vec<ProcedureOutput>, ProcedureError> {
+        self.ensure_unlocked().map_err(|e| match e {
+            ClientError::Locked => ProcedureError::Locked,
+            e => ProcedureError::Engine(e.to_string().into()),
+        })?;
+        let operation = Operations::begin(&self.operations, self.id, &procedures)?;
+        let record_history = self
+            .history
+            .read()
+            .map_err(|_| ProcedureError::Engine("Lock is poisoned".to_string().into()))?
+            .is_enabled();
         let mut out = Vec::new();
         let mut log = Vec::new();
         // Execute the procedures sequentially.
         for proc in procedures {
-            if let Some(output) = proc.output() {
-                log.push(output);
+            let written = proc.output();
+            let summary = record_history.then(|| (proc.name(), proc.vault_paths(), SystemTime::now(), Instant::now()));
+            let (kind, location) = (proc.name(), proc.output().or_else(|| proc.input()));
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!("procedure", kind).entered();
+            let result = operation
+                .check()
+                .and_then(|_| self.approve(&proc, &operation))
+                .and_then(|_| self.execute_with_escrow(proc));
+            if let Some((procedure, vault_paths, started_at, start)) = summary {
+                self.record_history(ProcedureSummary {
+                    procedure,
+                    vault_paths,
+                    started_at,
+                    duration: start.elapsed(),
+                    success: result.is_ok(),
+                });
             }
-            let output = match proc.execute(self) {
-                Ok(o) => o,
+            match &result {
+                Ok(_) => {
+                    self.metrics.procedure_executed(kind);
+                    self.events.emit(Event::ProcedureExecuted {
+                        client: self.id,
+                        kind,
+                        location,
+                    })
+                }
+                Err(ProcedureError::NotApproved) => self.events.emit(Event::AccessDenied {
+                    client: self.id,
+                    kind: Some(kind),
+                    reason: DenyReason::NotApproved,
+                }),
+                Err(_) => {}
+            }
+            // only the records, that this chain has written, are removed on failure, and not the ones at the
+            // output of a procedure, that has been denied or aborted before it has been executed
+            let output = match result {
+                Ok(o) => {
+                    log.extend(written);
+                    o
+                }
                 Err(e) => {
                     for location in log {
                         let _ = self.revoke_data(&location);
@@ -259,6 +1483,71 @@ impl Client {
         }
         Ok(out)
     }
+
+    /// Executes the steps of `pipeline` like [`Self::execute_procedure_chained`], and deletes its intermediate
+    /// records afterwards, whether the execution succeeded or not. Returns the outputs of all steps.
+    pub fn execute_pipeline(&self, pipeline: Pipeline) -> Result<Vec<ProcedureOutput>, ProcedureError> {
+        let (steps, scratch_vault) = pipeline.into_parts()?;
+        let result = self.execute_procedure_chained(steps);
+        let removed = match scratch_vault {
+            Some(vault_path) => self.remove_vault(vault_path),
+            None => Ok(()),
+        };
+        let outputs = result?;
+        removed.map_err(|e| ProcedureError::Engine(e.to_string().into()))?;
+        Ok(outputs)
+    }
+
+    /// Deletes the vault at `vault_path` together with its key, regardless of the trash retention.
+    fn remove_vault<P: AsRef<[u8]>>(&self, vault_path: P) -> Result<(), ClientError> {
+        let vault_id = derive_vault_id(vault_path);
+        let mut keystore = self.write_keystore()?;
+        let mut db = self.db.write()?;
+        keystore.take_key(vault_id);
+        if db.vaults.remove(&vault_id).is_some() {
+            self.mark_dirty();
+        }
+        Ok(())
+    }
+
+    /// Appends a summary to the procedure history. A poisoned lock only loses the summary.
+    fn record_history(&self, summary: ProcedureSummary) {
+        if let Ok(mut history) = self.history.write() {
+            history.push(summary);
+        }
+    }
+
+    /// Executes the procedure and escrows the generated key, if escrow is enabled.
+    fn execute_with_escrow(&self, procedure: StrongholdProcedure) -> Result<ProcedureOutput, ProcedureError> {
+        let generated_key = procedure.generated_key();
+        let output = procedure.execute(self)?;
+
+        let config = self
+            .escrow
+            .read()
+            .map_err(|_| ProcedureError::Engine("Lock is poisoned".to_string().into()))?
+            .clone();
+
+        if let (Some(config), Some(location)) = (config, generated_key) {
+            let result = self.get_guard(&location, |key| config.escrow(&self.store, &location, &key.borrow()));
+            if let Err(e) = result {
+                let _ = self.revoke_data(&location);
+                return Err(e.into());
+            }
+        }
+        Ok(output)
+    }
+
+    /// Asks for approval, if the procedure accesses a guarded vault. No lock is held
+    /// while waiting for the answer.
+    fn approve(&self, procedure: &StrongholdProcedure, operation: &OperationGuard) -> Result<(), ProcedureError> {
+        let approvals = self
+            .approvals
+            .read()
+            .map_err(|_| ProcedureError::Engine("Lock is poisoned".to_string().into()))?
+            .clone();
+        approvals.check(self.id, procedure, operation)
+    }
 }
 
 impl<'a> SyncClients<'a> for Client {
@@ -270,7 +1559,7 @@ impl<'a> SyncClients<'a> for Client {
     }
 
     fn get_key_provider(&'a self) -> Result<KeyProvider<'a>, ClientError> {
-        let ks = self.keystore.read()?;
+        let ks = self.read_keystore()?;
         Ok(KeyProvider::KeyStore(ks))
     }
 }
@@ -6,10 +6,14 @@ use std::{
     convert::Infallible,
     fmt::Debug,
     sync::{PoisonError, TryLockError},
+    time::Duration,
 };
 
 use engine::{
-    snapshot::{ReadError as EngineReadError, WriteError as EngineWriteError},
+    snapshot::{
+        migration::MigrationError as EngineMigrationError, throttle::ThrottleError as EngineThrottleError,
+        ReadError as EngineReadError, RekeyError as EngineRekeyError, WriteError as EngineWriteError,
+    },
     vault::{
         BoxProvider, ClientId, RecordError as EngineRecordError, RecordId, VaultError as EngineVaultError, VaultId,
     },
@@ -61,6 +65,54 @@ pub enum ClientError {
 
     #[error("Client with id {0:?} has already been loaded before. Can not be loaded twice.")]
     ClientAlreadyLoaded(ClientId),
+
+    #[error("Store entry of {size} bytes exceeds the limit of {limit} bytes per entry")]
+    StoreEntryTooLarge { size: usize, limit: usize },
+
+    #[error("Store would grow to {size} bytes, exceeding its limit of {limit} bytes")]
+    StoreQuotaExceeded { size: usize, limit: usize },
+
+    #[error("Write into vault {0:?} exceeds its quota, or the quota of the client")]
+    VaultQuotaExceeded(VaultId),
+
+    #[error("Encoding or decoding a typed value failed ({0})")]
+    TypedEncoding(String),
+
+    #[error("Expected a value of type {expected}, found {found}")]
+    TypeMismatch { expected: String, found: String },
+
+    #[error("No snapshot named ({0}) is open")]
+    SnapshotNotOpen(String),
+
+    #[error("A snapshot named ({0}) is already open")]
+    SnapshotAlreadyOpen(String),
+
+    #[error("Unlocking the snapshot is throttled after {failed_attempts} failed attempts, retry in {retry_after:?}")]
+    UnlockThrottled {
+        failed_attempts: u32,
+        retry_after: Duration,
+    },
+
+    #[error("Unlocking the snapshot failed, {failed_attempts} failed attempts ({reason})")]
+    UnlockFailed { failed_attempts: u32, reason: String },
+
+    #[error("Failed to decrypt the snapshot: ({0})")]
+    SnapshotDecryption(String),
+
+    #[error("Client is locked, it has to be unlocked first")]
+    Locked,
+
+    #[error("Unlocking the client failed, the key is wrong")]
+    WrongUnlockKey,
+
+    #[error("Hardware key backend failed ({0})")]
+    KeyBackend(String),
+
+    #[error("The PCR values have changed since the key was sealed")]
+    PcrMismatch,
+
+    #[error("Procedures are still executed after the shutdown timeout of {0:?}")]
+    ShutdownTimeout(Duration),
 }
 
 impl<T> From<TryLockError<T>> for ClientError {
@@ -77,7 +129,10 @@ impl<T> From<PoisonError<T>> for ClientError {
 
 impl<E: Debug> From<VaultError<E>> for ClientError {
     fn from(e: VaultError<E>) -> Self {
-        ClientError::Engine(format!("{:?}", e))
+        match e {
+            VaultError::QuotaExceeded(vault_id) => ClientError::VaultQuotaExceeded(vault_id),
+            e => ClientError::Engine(format!("{:?}", e)),
+        }
     }
 }
 
@@ -99,12 +154,25 @@ impl From<Box<dyn Any>> for ClientError {
     }
 }
 
+impl From<EngineThrottleError> for ClientError {
+    fn from(e: EngineThrottleError) -> Self {
+        match e {
+            EngineThrottleError::Throttled { failed, retry_after } => ClientError::UnlockThrottled {
+                failed_attempts: failed,
+                retry_after,
+            },
+            e => ClientError::Inner(e.to_string()),
+        }
+    }
+}
+
 impl From<SnapshotError> for ClientError {
     fn from(se: SnapshotError) -> Self {
         match se {
             SnapshotError::MissingFile(path) => ClientError::SnapshotFileMissing(path),
             SnapshotError::Io(inner) => ClientError::Inner(inner.to_string()),
             SnapshotError::CorruptedContent(inner) => ClientError::Inner(inner),
+            SnapshotError::Decryption(inner) => ClientError::SnapshotDecryption(inner),
             SnapshotError::InvalidFile(inner) => ClientError::Inner(inner),
             SnapshotError::SnapshotKey(vault_id, record_id) => ClientError::Inner(format!(
                 "Missing or invalid snapshot key vaultid: {:?}, recordid: {:?}",
@@ -144,6 +212,9 @@ pub enum SnapshotError {
     #[error("corrupted file: {0}")]
     CorruptedContent(String),
 
+    #[error("decryption failed: {0}")]
+    Decryption(String),
+
     #[error("invalid file {0}")]
     InvalidFile(String),
 
@@ -220,6 +291,7 @@ impl From<EngineReadError> for SnapshotError {
     fn from(e: EngineReadError) -> Self {
         match e {
             EngineReadError::CorruptedContent(reason) => SnapshotError::CorruptedContent(reason),
+            EngineReadError::Decryption(reason) => SnapshotError::Decryption(reason),
             EngineReadError::InvalidFile => SnapshotError::InvalidFile("Not a Snapshot.".into()),
             EngineReadError::Io(io) => SnapshotError::Io(io),
             EngineReadError::UnsupportedVersion { expected, found } => SnapshotError::InvalidFile(format!(
@@ -239,3 +311,25 @@ impl From<EngineWriteError> for SnapshotError {
         }
     }
 }
+
+impl From<EngineRekeyError> for SnapshotError {
+    fn from(e: EngineRekeyError) -> Self {
+        match e {
+            EngineRekeyError::Read(e) => e.into(),
+            EngineRekeyError::Write(e) => e.into(),
+        }
+    }
+}
+
+impl From<EngineMigrationError> for SnapshotError {
+    fn from(e: EngineMigrationError) -> Self {
+        match e {
+            EngineMigrationError::Read(e) => e.into(),
+            EngineMigrationError::Write(e) => e.into(),
+            EngineMigrationError::Failed(reason) => SnapshotError::CorruptedContent(reason),
+            e @ EngineMigrationError::VersionMismatch { .. } | e @ EngineMigrationError::Unsupported { .. } => {
+                SnapshotError::InvalidFile(e.to_string())
+            }
+        }
+    }
+}
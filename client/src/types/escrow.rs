@@ -0,0 +1,67 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{procedures::FatalProcedureError, Location, Store};
+use crypto::keys::x25519;
+use serde::{Deserialize, Serialize};
+
+/// Configuration for escrowing newly generated keys.
+///
+/// If set on a [`crate::Client`], each key created by a key generating procedure is sealed for the
+/// organizational recovery key `recipient` and appended to the [`Store`] entry at `store_key`.
+/// The plaintext key never leaves the vault unencrypted.
+#[derive(Clone)]
+pub struct EscrowConfig {
+    /// The `x25519` public key of the recovery authority
+    pub recipient: x25519::PublicKey,
+
+    /// The key of the [`Store`] entry the escrowed keys are appended to
+    pub store_key: Vec<u8>,
+}
+
+/// A single escrowed key, as appended to the escrow [`Store`] entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscrowEntry {
+    /// The location of the key inside the client
+    pub location: Location,
+
+    /// The key, sealed for the recovery authority. The serialized `location` is used as associated data.
+    ///
+    /// The recovery authority can decrypt it with `engine::snapshot::read` and its `x25519` secret key.
+    pub sealed: Vec<u8>,
+}
+
+impl EscrowEntry {
+    /// Decodes all [`EscrowEntry`]s from the raw value of the escrow [`Store`] entry.
+    pub fn decode_all(bytes: &[u8]) -> Result<Vec<EscrowEntry>, FatalProcedureError> {
+        bincode::deserialize(bytes).map_err(|e| FatalProcedureError::from(e.to_string()))
+    }
+}
+
+impl EscrowConfig {
+    /// Seals `secret` for the recovery authority and appends it to the escrow entry in `store`.
+    pub(crate) fn escrow(&self, store: &Store, location: &Location, secret: &[u8]) -> Result<(), FatalProcedureError> {
+        let associated_data = bincode::serialize(location).map_err(|e| FatalProcedureError::from(e.to_string()))?;
+        let mut sealed = Vec::new();
+        engine::snapshot::seal(secret, &mut sealed, &self.recipient, &associated_data)
+            .map_err(|e| FatalProcedureError::from(e.to_string()))?;
+
+        // the entries are written through the store, so that its size limits apply to them
+        let mut guard = store.write().map_err(|e| FatalProcedureError::from(e.to_string()))?;
+
+        let mut entries = match guard.get(&self.store_key) {
+            Some(bytes) => EscrowEntry::decode_all(bytes)?,
+            None => Vec::new(),
+        };
+        entries.push(EscrowEntry {
+            location: location.clone(),
+            sealed,
+        });
+        let bytes = bincode::serialize(&entries).map_err(|e| FatalProcedureError::from(e.to_string()))?;
+        guard
+            .insert(self.store_key.clone(), bytes, None)
+            .map_err(|e| FatalProcedureError::from(e.to_string()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Events of a [`Stronghold`](crate::Stronghold) and its [`Client`](crate::Client)s, e.g. for the audit log of
+//! the application.
+//!
+//! Events only carry identifiers, locations and names of procedures. Secrets, and the inputs and outputs of
+//! procedures, are never part of an event.
+
+use std::{
+    path::PathBuf,
+    sync::{mpsc::Sender, Arc, Mutex, RwLock},
+};
+
+use engine::vault::ClientId;
+
+use crate::Location;
+
+/// An event, that has been published on an [`EventBus`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Event {
+    /// A record has been written into a vault, either directly or as the output of a procedure
+    RecordWritten { client: ClientId, location: Location },
+
+    /// A procedure has been executed successfully. `location` is its output, or its first input for procedures
+    /// without output.
+    ProcedureExecuted {
+        client: ClientId,
+        kind: &'static str,
+        location: Option<Location>,
+    },
+
+    /// The state of the clients has been written into the snapshot file at `path`
+    SnapshotCommitted { path: PathBuf },
+
+    /// An operation on the secrets of a client has been denied
+    AccessDenied {
+        client: ClientId,
+        kind: Option<&'static str>,
+        reason: DenyReason,
+    },
+
+    /// An expired entry has been pruned from the [`Store`](crate::Store) of a client
+    StoreEntryExpired { client: ClientId, key: Vec<u8> },
+}
+
+/// Why an operation has been denied, see [`Event::AccessDenied`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DenyReason {
+    /// The procedure has not been approved, see [`Client::require_approval`](crate::Client::require_approval)
+    NotApproved,
+
+    /// The client is locked, see [`Client::set_auto_lock`](crate::Client::set_auto_lock)
+    Locked,
+
+    /// A record has reached the limit of its [`UsagePolicy`](crate::UsagePolicy)
+    UsageLimit,
+}
+
+type Callback = Arc<dyn Fn(&Event) + Send + Sync>;
+
+/// Publishes [`Event`]s to callbacks and channels. A [`Stronghold`](crate::Stronghold) shares its bus with all
+/// clients, that it creates or loads.
+///
+/// Subscribers are called on the thread that causes the event, while the operation is still in progress. They
+/// should return quickly, and must not execute operations on the same client.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    callbacks: Arc<RwLock<Vec<Callback>>>,
+    senders: Arc<Mutex<Vec<Sender<Event>>>>,
+}
+
+impl EventBus {
+    /// Calls `callback` with all following events.
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::{Event, Stronghold};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let stronghold = Stronghold::default();
+    /// let events = Arc::new(Mutex::new(Vec::new()));
+    /// let collected = events.clone();
+    /// stronghold
+    ///     .events()
+    ///     .subscribe(move |event: &Event| collected.lock().unwrap().push(event.clone()));
+    /// ```
+    pub fn subscribe<F>(&self, callback: F)
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        if let Ok(mut callbacks) = self.callbacks.write() {
+            callbacks.push(Arc::new(callback));
+        }
+    }
+
+    /// Sends all following events into `sender`, until its receiver is dropped.
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::Stronghold;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let stronghold = Stronghold::default();
+    /// let (sender, receiver) = channel();
+    /// stronghold.events().subscribe_channel(sender);
+    ///
+    /// stronghold.create_client(b"client").unwrap();
+    /// assert!(receiver.try_recv().is_err());
+    /// ```
+    pub fn subscribe_channel(&self, sender: Sender<Event>) {
+        if let Ok(mut senders) = self.senders.lock() {
+            senders.push(sender);
+        }
+    }
+
+    /// Publishes `event` to all subscribers. Channels, whose receiver has been dropped, are removed.
+    pub(crate) fn emit(&self, event: Event) {
+        // the callbacks are called without holding the lock, so that they can subscribe further callbacks
+        let callbacks: Vec<Callback> = match self.callbacks.read() {
+            Ok(callbacks) => callbacks.clone(),
+            Err(_) => Vec::new(),
+        };
+        callbacks.iter().for_each(|callback| callback(&event));
+
+        if let Ok(mut senders) = self.senders.lock() {
+            senders.retain(|sender| sender.send(event.clone()).is_ok());
+        }
+    }
+}
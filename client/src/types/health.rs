@@ -0,0 +1,36 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use engine::time::SystemTime;
+
+/// A snapshot of the runtime status of a [`crate::Stronghold`] instance.
+///
+/// Services embedding Stronghold can use this to back liveness and readiness probes.
+/// Acquiring the report at all already implies that none of the internal locks are poisoned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Health {
+    /// `true`, if [`Snapshot`](crate::Snapshot) state has been loaded from a file in this session.
+    pub snapshot_loaded: bool,
+
+    /// The number of [`Client`](crate::Client)s currently loaded.
+    pub clients: usize,
+
+    /// The number of vaults over all loaded clients.
+    pub vaults: usize,
+
+    /// `true`, if the process is able to lock memory pages, so that secrets won't be swapped to disk.
+    pub memory_lock_available: bool,
+
+    /// The last time the state has successfully been committed to a snapshot file.
+    pub last_persist: Option<SystemTime>,
+
+    /// The number of revoked records that are still waiting for garbage collection.
+    pub pending_gc: usize,
+}
+
+impl Health {
+    /// Returns `true`, if the instance is able to safely hold secrets.
+    pub fn is_ready(&self) -> bool {
+        self.memory_lock_available
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use engine::time::{Duration, SystemTime};
+use std::collections::VecDeque;
+
+/// Summary of a single procedure execution.
+///
+/// Neither the inputs nor the outputs of the procedure are recorded, only which kind of procedure has
+/// been executed on which vaults, so the history can be shown to users e.g. as recent key activity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcedureSummary {
+    /// The name of the procedure type, e.g. `Ed25519Sign`
+    pub procedure: &'static str,
+
+    /// The paths of the vaults that have been accessed
+    pub vault_paths: Vec<Vec<u8>>,
+
+    /// The time the execution has started
+    pub started_at: SystemTime,
+
+    /// How long the execution took
+    pub duration: Duration,
+
+    /// `true`, if the procedure has been executed successfully
+    pub success: bool,
+}
+
+/// Ring buffer of the most recent procedure executions. Disabled with a capacity of zero.
+#[derive(Default)]
+pub(crate) struct History {
+    capacity: usize,
+    entries: VecDeque<ProcedureSummary>,
+}
+
+impl History {
+    /// Sets the number of kept entries and drops the oldest entries, that exceed it
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub(crate) fn push(&mut self, summary: ProcedureSummary) {
+        if !self.is_enabled() {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(summary);
+    }
+
+    /// Returns the entries, the most recent one last
+    pub(crate) fn list(&self) -> Vec<ProcedureSummary> {
+        self.entries.iter().cloned().collect()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
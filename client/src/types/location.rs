@@ -102,17 +102,16 @@ pub fn derive_vault_id<P>(path: P) -> VaultId
 where
     P: AsRef<[u8]>,
 {
-    VaultId::load_from_path(path.as_ref(), path.as_ref())
+    VaultId::deterministic(path.as_ref())
 }
 
-// Derives the counter [`RecordId`] from the given vault path and the counter value.
+// Derives the [`RecordId`] from the given vault path and record path, see [`RecordId::deterministic`].
 pub fn derive_record_id<V, R>(vault_path: V, record_path: R) -> RecordId
 where
     V: AsRef<[u8]>,
     R: AsRef<[u8]>,
 {
-    let vid = derive_vault_id(vault_path);
-    RecordId::load_from_path(vid.as_ref(), record_path.as_ref())
+    RecordId::deterministic(vault_path.as_ref(), record_path.as_ref())
 }
 
 // Derives the counter [`RecordId`] from the given vault path and the counter value.
@@ -0,0 +1,266 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Counters and latency histograms of a [`Stronghold`](crate::Stronghold) and its clients, so that operators can
+//! monitor an embedded Stronghold. See [`Stronghold::metrics`](crate::Stronghold::metrics).
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// The upper bounds of the buckets of a [`Histogram`]. Larger values only count into [`Histogram::count`].
+const BUCKETS: [Duration; 8] = [
+    Duration::from_micros(10),
+    Duration::from_micros(100),
+    Duration::from_millis(1),
+    Duration::from_millis(10),
+    Duration::from_millis(100),
+    Duration::from_secs(1),
+    Duration::from_secs(10),
+    Duration::from_secs(60),
+];
+
+/// A distribution of durations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    /// The number of observed durations
+    pub count: u64,
+
+    /// The sum of all observed durations
+    pub sum: Duration,
+
+    /// The upper bound of each bucket, and the number of durations, that are less than or equal to it
+    pub buckets: Vec<(Duration, u64)>,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            sum: Duration::ZERO,
+            buckets: BUCKETS.iter().map(|bound| (*bound, 0)).collect(),
+        }
+    }
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        self.count += 1;
+        self.sum += duration;
+        self.buckets
+            .iter_mut()
+            .filter(|(bound, _)| duration <= *bound)
+            .for_each(|(_, count)| *count += 1);
+    }
+
+    /// Returns the mean of the observed durations, or [`None`] if nothing has been observed
+    pub fn mean(&self) -> Option<Duration> {
+        u32::try_from(self.count)
+            .ok()
+            .filter(|count| *count > 0)
+            .map(|count| self.sum / count)
+    }
+}
+
+/// The metrics of a [`Stronghold`](crate::Stronghold) and all of its clients, since the Stronghold has been
+/// created.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// The number of successful executions of each procedure, by the name of the procedure
+    pub procedures: BTreeMap<String, u64>,
+
+    /// The time to decrypt the records, that procedures read their secrets from
+    pub vault_reads: Histogram,
+
+    /// The time to encrypt and write a record into a vault
+    pub vault_writes: Histogram,
+
+    /// The duration of commits into snapshot files
+    pub snapshot_commits: Histogram,
+
+    /// The duration of the refresh cycles of the keys in memory, see
+    /// [`Client::refresh_keys`](crate::Client::refresh_keys)
+    pub key_refreshes: Histogram,
+
+    /// The number of refresh cycles of the keys in memory, that have failed
+    pub key_refresh_failures: u64,
+
+    /// The number of locked memory pages, that are held by guarded memory of the whole process
+    pub locked_memory_pages: usize,
+}
+
+#[cfg(feature = "prometheus")]
+impl Metrics {
+    /// Formats the metrics in the text exposition format of Prometheus, e.g. to be served on a `/metrics`
+    /// endpoint.
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::Stronghold;
+    ///
+    /// let stronghold = Stronghold::default();
+    /// let exposition = stronghold.metrics().unwrap().to_prometheus();
+    /// assert!(exposition.contains("stronghold_locked_memory_pages"));
+    /// ```
+    pub fn to_prometheus(&self) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        out.push_str("# HELP stronghold_procedures_total Successfully executed procedures.\n");
+        out.push_str("# TYPE stronghold_procedures_total counter\n");
+        for (procedure, count) in self.procedures.iter() {
+            let _ = writeln!(
+                out,
+                "stronghold_procedures_total{{procedure=\"{}\"}} {}",
+                escape_label_value(procedure),
+                count
+            );
+        }
+        write_histogram(
+            &mut out,
+            "stronghold_vault_read_seconds",
+            "Time to decrypt the records read by procedures.",
+            &self.vault_reads,
+        );
+        write_histogram(
+            &mut out,
+            "stronghold_vault_write_seconds",
+            "Time to encrypt and write a record.",
+            &self.vault_writes,
+        );
+        write_histogram(
+            &mut out,
+            "stronghold_snapshot_commit_seconds",
+            "Duration of snapshot commits.",
+            &self.snapshot_commits,
+        );
+        write_histogram(
+            &mut out,
+            "stronghold_key_refresh_seconds",
+            "Duration of the refresh cycles of the keys in memory.",
+            &self.key_refreshes,
+        );
+        out.push_str("# HELP stronghold_key_refresh_failures_total Failed refresh cycles of the keys in memory.\n");
+        out.push_str("# TYPE stronghold_key_refresh_failures_total counter\n");
+        let _ = writeln!(
+            out,
+            "stronghold_key_refresh_failures_total {}",
+            self.key_refresh_failures
+        );
+        out.push_str("# HELP stronghold_locked_memory_pages Locked memory pages held by guarded memory.\n");
+        out.push_str("# TYPE stronghold_locked_memory_pages gauge\n");
+        let _ = writeln!(out, "stronghold_locked_memory_pages {}", self.locked_memory_pages);
+        out
+    }
+}
+
+/// Escapes `value` for a label of the text exposition format. Names of custom procedures are chosen by the
+/// application, and may contain any character.
+#[cfg(feature = "prometheus")]
+fn escape_label_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(feature = "prometheus")]
+fn write_histogram(out: &mut String, name: &str, help: &str, histogram: &Histogram) {
+    use std::fmt::Write;
+
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} histogram", name);
+    for (bound, count) in histogram.buckets.iter() {
+        let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound.as_secs_f64(), count);
+    }
+    let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count);
+    let _ = writeln!(out, "{}_sum {}", name, histogram.sum.as_secs_f64());
+    let _ = writeln!(out, "{}_count {}", name, histogram.count);
+}
+
+/// Collects the [`Metrics`] of a [`Stronghold`](crate::Stronghold). It is shared with all clients, that the
+/// Stronghold creates or loads.
+#[derive(Clone, Default)]
+pub(crate) struct MetricsRecorder {
+    inner: Arc<Mutex<Metrics>>,
+}
+
+impl MetricsRecorder {
+    fn update<F: FnOnce(&mut Metrics)>(&self, f: F) {
+        // metrics are best effort, a poisoned lock must not fail the operation that is measured
+        if let Ok(mut metrics) = self.inner.lock() {
+            f(&mut metrics);
+        }
+    }
+
+    pub(crate) fn procedure_executed(&self, name: &str) {
+        self.update(|metrics| *metrics.procedures.entry(name.to_string()).or_default() += 1);
+    }
+
+    pub(crate) fn vault_read(&self, duration: Duration) {
+        self.update(|metrics| metrics.vault_reads.observe(duration));
+    }
+
+    pub(crate) fn vault_write(&self, duration: Duration) {
+        self.update(|metrics| metrics.vault_writes.observe(duration));
+    }
+
+    pub(crate) fn snapshot_commit(&self, duration: Duration) {
+        self.update(|metrics| metrics.snapshot_commits.observe(duration));
+    }
+
+    pub(crate) fn key_refresh(&self, duration: Duration) {
+        self.update(|metrics| metrics.key_refreshes.observe(duration));
+    }
+
+    pub(crate) fn key_refresh_failed(&self) {
+        self.update(|metrics| metrics.key_refresh_failures += 1);
+    }
+
+    pub(crate) fn snapshot(&self) -> Metrics {
+        let mut metrics = self.inner.lock().map(|metrics| metrics.clone()).unwrap_or_default();
+        metrics.locked_memory_pages = engine::runtime::utils::locked_pages();
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets() {
+        let mut histogram = Histogram::default();
+        assert_eq!(histogram.mean(), None);
+
+        histogram.observe(Duration::from_micros(50));
+        histogram.observe(Duration::from_millis(5));
+        histogram.observe(Duration::from_secs(120));
+
+        assert_eq!(histogram.count, 3);
+        let counts: Vec<u64> = histogram.buckets.iter().map(|(_, count)| *count).collect();
+        assert_eq!(counts, vec![0, 1, 1, 2, 2, 2, 2, 2]);
+        assert_eq!(
+            histogram.mean(),
+            Some((Duration::from_micros(50) + Duration::from_millis(5) + Duration::from_secs(120)) / 3)
+        );
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[test]
+    fn test_label_escaping() {
+        let mut metrics = Metrics::default();
+        metrics.procedures.insert("custom \"sign\"\\\n".to_string(), 1);
+        assert!(metrics
+            .to_prometheus()
+            .contains("stronghold_procedures_total{procedure=\"custom \\\"sign\\\"\\\\\\n\"} 1\n"));
+    }
+}
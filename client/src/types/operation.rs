@@ -0,0 +1,167 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    procedures::{ProcedureError, StrongholdProcedure},
+    Location,
+};
+use engine::{time::SystemTime, vault::ClientId};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+};
+
+/// Identifies an in-flight operation of a [`crate::Client`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OperationId(u64);
+
+impl Display for OperationId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A procedure of an [`Operation`]. Only the type of the procedure and its target are kept, its inputs, e.g.
+/// the data of a [`crate::procedures::WriteVault`], are not.
+#[derive(Debug, Clone)]
+pub struct OperationStep {
+    /// The name of the procedure type, e.g. `"GenerateKey"`
+    pub name: &'static str,
+
+    /// The location the procedure writes its output to, if any
+    pub target: Option<Location>,
+}
+
+impl From<&StrongholdProcedure> for OperationStep {
+    fn from(procedure: &StrongholdProcedure) -> Self {
+        OperationStep {
+            name: procedure.name(),
+            target: procedure.output(),
+        }
+    }
+}
+
+/// An in-flight execution of one or more chained procedures
+#[derive(Debug, Clone)]
+pub struct Operation {
+    /// The id to abort the operation with
+    pub id: OperationId,
+
+    /// The id of the client the operation is executed on
+    pub client_id: ClientId,
+
+    /// The procedures of the operation, in the order of their execution
+    pub procedures: Vec<OperationStep>,
+
+    /// The time the execution has started
+    pub started_at: SystemTime,
+}
+
+struct Entry {
+    operation: Operation,
+    aborted: Arc<AtomicBool>,
+}
+
+/// All in-flight operations of a client
+#[derive(Default)]
+pub(crate) struct Operations {
+    next_id: u64,
+    running: HashMap<OperationId, Entry>,
+
+    // set while the Stronghold of the client shuts down, so that no new operations are started
+    shutting_down: bool,
+}
+
+impl Operations {
+    /// Registers a new operation. The operation is removed again, once the returned guard is dropped. Fails with
+    /// [`ProcedureError::ShuttingDown`], while the Stronghold of the client shuts down.
+    pub(crate) fn begin(
+        operations: &Arc<RwLock<Operations>>,
+        client_id: ClientId,
+        procedures: &[StrongholdProcedure],
+    ) -> Result<OperationGuard, ProcedureError> {
+        let mut ops = operations
+            .write()
+            .map_err(|_| ProcedureError::Engine("Lock is poisoned".to_string().into()))?;
+        if ops.shutting_down {
+            return Err(ProcedureError::ShuttingDown);
+        }
+
+        let id = OperationId(ops.next_id);
+        ops.next_id += 1;
+
+        let aborted = Arc::new(AtomicBool::new(false));
+        let operation = Operation {
+            id,
+            client_id,
+            procedures: procedures.iter().map(OperationStep::from).collect(),
+            started_at: SystemTime::now(),
+        };
+        ops.running.insert(
+            id,
+            Entry {
+                operation,
+                aborted: aborted.clone(),
+            },
+        );
+
+        Ok(OperationGuard {
+            operations: operations.clone(),
+            id,
+            aborted,
+        })
+    }
+
+    pub(crate) fn list(&self) -> Vec<Operation> {
+        let mut operations: Vec<Operation> = self.running.values().map(|e| e.operation.clone()).collect();
+        operations.sort_by_key(|op| op.id);
+        operations
+    }
+
+    pub(crate) fn set_shutting_down(&mut self, shutting_down: bool) {
+        self.shutting_down = shutting_down;
+    }
+
+    pub(crate) fn abort(&self, id: OperationId) -> bool {
+        match self.running.get(&id) {
+            Some(entry) => {
+                entry.aborted.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Keeps an operation registered while it is in-flight
+pub(crate) struct OperationGuard {
+    operations: Arc<RwLock<Operations>>,
+    id: OperationId,
+    aborted: Arc<AtomicBool>,
+}
+
+impl OperationGuard {
+    /// Fails with [`ProcedureError::Aborted`], if the operation has been aborted
+    pub(crate) fn check(&self) -> Result<(), ProcedureError> {
+        if self.is_aborted() {
+            return Err(ProcedureError::Aborted);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        if let Ok(mut operations) = self.operations.write() {
+            operations.running.remove(&self.id);
+        }
+    }
+}
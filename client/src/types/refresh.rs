@@ -0,0 +1,72 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Periodic refresh of the long-lived keys of a [`Client`](crate::Client) in memory.
+//!
+//! The master key of the keystore and the key provider of the auto-lock are kept in non-contiguous memory, that
+//! is split into two shards. A refresh cycle replaces the master key with a new one, re-encrypts all vault keys
+//! with it, and re-splits the shards of the key provider with fresh randomness. Memory, that has been scraped
+//! before a refresh, e.g. after a cold-boot attack, can't be combined with memory scraped after it.
+
+use engine::time::{Duration, Instant};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, RwLock, Weak,
+    },
+    thread,
+};
+
+use crate::{AutoLock, ClientError, KeyStore, MetricsRecorder, Provider};
+
+/// Runs one refresh cycle, and records its duration or failure in `metrics`
+pub(crate) fn refresh(
+    keystore: &RwLock<KeyStore<Provider>>,
+    auto_lock: &Mutex<Option<AutoLock>>,
+    metrics: &MetricsRecorder,
+) -> Result<(), ClientError> {
+    let start = Instant::now();
+    let refreshed = keystore
+        .write()
+        .map_err(ClientError::from)
+        .and_then(|mut keystore| {
+            keystore
+                .refresh()
+                .map_err(|e| ClientError::Provider(format!("{:?}", e)))
+        })
+        .and_then(|_| match auto_lock.lock()?.as_ref() {
+            Some(auto_lock) => auto_lock.refresh(),
+            None => Ok(()),
+        });
+    match refreshed {
+        Ok(()) => metrics.key_refresh(start.elapsed()),
+        Err(_) => metrics.key_refresh_failed(),
+    }
+    refreshed
+}
+
+/// Refreshes the keys of the client, that `keystore` and `auto_lock` belong to, every `interval`. Returns, when
+/// the client has been dropped, or `generation` no longer equals `id`.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn watch(
+    keystore: Weak<RwLock<KeyStore<Provider>>>,
+    auto_lock: Weak<Mutex<Option<AutoLock>>>,
+    generation: Weak<AtomicU64>,
+    id: u64,
+    interval: Duration,
+    metrics: MetricsRecorder,
+) {
+    loop {
+        thread::sleep(interval);
+        match generation.upgrade() {
+            Some(generation) if generation.load(Ordering::SeqCst) == id => {}
+            _ => return,
+        }
+        let (keystore, auto_lock) = match (keystore.upgrade(), auto_lock.upgrade()) {
+            (Some(keystore), Some(auto_lock)) => (keystore, auto_lock),
+            _ => return,
+        };
+        // a failed cycle is counted in the metrics, the next one is tried nevertheless
+        let _ = refresh(&keystore, &auto_lock, &metrics);
+    }
+}
@@ -0,0 +1,73 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{KeyProvider, SnapshotPath};
+use engine::time::Duration;
+
+/// Default time [`crate::Stronghold::shutdown`] waits for in-flight procedures
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where the state of a [`crate::Stronghold`] is written to on shutdown
+pub(crate) enum Persist {
+    None,
+    KeyProvider(SnapshotPath, KeyProvider),
+    StoredKey(SnapshotPath),
+    Incremental(SnapshotPath, KeyProvider),
+}
+
+/// Configures [`crate::Stronghold::shutdown`].
+///
+/// By default nothing is persisted, and in-flight procedures are allowed to finish for up to 30 seconds
+/// before the state is cleared.
+pub struct ShutdownConfig {
+    pub(crate) persist: Persist,
+    pub(crate) abort_operations: bool,
+    pub(crate) timeout: Duration,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        ShutdownConfig {
+            persist: Persist::None,
+            abort_operations: false,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl ShutdownConfig {
+    /// Commits all clients into the snapshot file at `snapshot_path` with the key of `keyprovider`, see
+    /// [`crate::Stronghold::commit_with_keyprovider`].
+    pub fn commit_with_keyprovider(mut self, snapshot_path: SnapshotPath, keyprovider: KeyProvider) -> Self {
+        self.persist = Persist::KeyProvider(snapshot_path, keyprovider);
+        self
+    }
+
+    /// Commits all clients into the snapshot file at `snapshot_path` with the stored snapshot key, see
+    /// [`crate::Stronghold::commit`].
+    pub fn commit(mut self, snapshot_path: SnapshotPath) -> Self {
+        self.persist = Persist::StoredKey(snapshot_path);
+        self
+    }
+
+    /// Writes all modified clients into the incremental snapshot file at `snapshot_path`, see
+    /// [`crate::Stronghold::commit_incremental`].
+    pub fn commit_incremental(mut self, snapshot_path: SnapshotPath, keyprovider: KeyProvider) -> Self {
+        self.persist = Persist::Incremental(snapshot_path, keyprovider);
+        self
+    }
+
+    /// If `true`, procedures that are still executed are aborted instead of waiting for them to finish.
+    /// Aborted procedures stop at their next step, which is still awaited.
+    pub fn abort_operations(mut self, abort: bool) -> Self {
+        self.abort_operations = abort;
+        self
+    }
+
+    /// Sets how long to wait for in-flight procedures, before shutting down fails with
+    /// [`crate::ClientError::ShutdownTimeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
@@ -8,7 +8,10 @@
 
 use crypto::keys::x25519;
 use engine::{
-    snapshot::{self, read, read_from as read_from_file, write, write_to as write_to_file, Key},
+    snapshot::{
+        self, challenge::Challenge, info::SnapshotInfo, read, read_from_with_progress as read_from_file, write,
+        write_to_with_options as write_to_file, Compression, Key, Phase, Progress, WriteOptions,
+    },
     store::Cache,
     vault::{view::Record, BlobId, BoxProvider, ClientId, DbView, Key as PKey, RecordHint, RecordId, VaultId},
 };
@@ -25,8 +28,11 @@ use zeroize::Zeroize;
 
 use crate::{
     procedures::{DeriveSecret, X25519DiffieHellman},
-    sync::{self, KeyProvider, SnapshotHierarchy, SyncClients, SyncClientsConfig, SyncSnapshots, SyncSnapshotsConfig},
-    ClientError, KeyStore, Location, Provider, SnapshotError,
+    sync::{
+        self, KeyProvider, MergePolicy, RecordConflict, SnapshotDiff, SnapshotHierarchy, SyncClients,
+        SyncClientsConfig, SyncSnapshots, SyncSnapshotsConfig,
+    },
+    Argon2Params, ClientError, KeyStore, LoadFromPath, Location, PasswordKey, Provider, SnapshotError,
 };
 
 type EncryptedClientState = (Vec<u8>, Cache<Vec<u8>, Vec<u8>>);
@@ -58,6 +64,10 @@ pub struct Snapshot {
     db: DbView<Provider>,
     // Loaded snapshot states with each client state separately encrypted.
     states: HashMap<ClientId, EncryptedClientState>,
+    // Associated data, that the snapshot file is bound to.
+    associated_data: Vec<u8>,
+    // Compression of the snapshot file.
+    compression: Compression,
 }
 
 /// Data structure that is written to the snapshot.
@@ -190,29 +200,156 @@ impl Snapshot {
         Ok(())
     }
 
+    /// Returns the ids of all clients in the snapshot hashmap.
+    pub(crate) fn client_ids(&self) -> Vec<ClientId> {
+        self.states.keys().cloned().collect()
+    }
+
     /// Checks to see if the [`ClientId`] exists in the snapshot hashmap.
     pub fn has_data(&self, cid: ClientId) -> bool {
         self.states.contains_key(&cid)
     }
 
     /// Reads state from the specified named snapshot or the specified path
-    /// TODO: Add associated data.
     pub fn read_from_snapshot(
         snapshot_path: &SnapshotPath,
         key: Key,
         write_key: Option<(VaultId, RecordId)>,
     ) -> Result<Self, SnapshotError> {
-        let data = read_from_file(snapshot_path.as_path(), &key, &[])?;
+        Self::read_from_snapshot_with_progress(snapshot_path, key, write_key, &mut |_: Progress| {})
+    }
+
+    /// Same as [`Self::read_from_snapshot`], but reports the [`Progress`] of each phase to `progress`.
+    pub fn read_from_snapshot_with_progress(
+        snapshot_path: &SnapshotPath,
+        key: Key,
+        write_key: Option<(VaultId, RecordId)>,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<Self, SnapshotError> {
+        Self::read_from_snapshot_with_associated_data(snapshot_path, key, &[], write_key, progress)
+    }
+
+    /// Same as [`Self::read_from_snapshot_with_progress`], for a snapshot file that has been bound to
+    /// `associated_data`. Reading fails, if the file has been written with other associated data.
+    ///
+    /// The associated data is kept, so that the snapshot is written with the same associated data again.
+    pub fn read_from_snapshot_with_associated_data(
+        snapshot_path: &SnapshotPath,
+        key: Key,
+        associated_data: &[u8],
+        write_key: Option<(VaultId, RecordId)>,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<Self, SnapshotError> {
+        let data = read_from_file(snapshot_path.as_path(), &key, associated_data, progress)?;
+
+        let state = Progress::bracket(Phase::Deserialize, data.len() as u64, progress, || {
+            bincode::deserialize(&data)
+        })?;
+        let mut snapshot = Snapshot::from_state(state, key, write_key)?;
+        snapshot.set_associated_data(associated_data.to_vec());
+        Ok(snapshot)
+    }
+
+    /// Binds the snapshot file to `associated_data`, e.g. an identifier of the device or a secret that is
+    /// kept in a hardware security module. The associated data is authenticated together with the
+    /// encrypted content, so the snapshot can only be read with the same associated data.
+    pub fn set_associated_data(&mut self, associated_data: Vec<u8>) {
+        self.associated_data = associated_data;
+    }
+
+    /// Returns the associated data, that the snapshot file is bound to
+    pub fn associated_data(&self) -> &[u8] {
+        &self.associated_data
+    }
+
+    /// Sets the algorithm, that the snapshot file is compressed with. Reading detects the algorithm from
+    /// the file, so it only applies to writing.
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// Reads state from a password protected snapshot file, that has been bound to `associated_data`. The key
+    /// is derived from `password` with the parameters that are stored in the header of the file.
+    ///
+    /// Like with [`Self::read_from_snapshot_with_associated_data`], the associated data is kept.
+    pub fn read_from_snapshot_with_password(
+        snapshot_path: &SnapshotPath,
+        password: &[u8],
+        associated_data: &[u8],
+        write_key: Option<(VaultId, RecordId)>,
+    ) -> Result<Self, SnapshotError> {
+        let (mut data, key) = snapshot::kdf::read_from(snapshot_path.as_path(), password, associated_data)?;
+        Self::from_decrypted(&mut data, key, associated_data, write_key)
+    }
+
+    /// Same as [`Self::read_from_snapshot_with_password`], but decrypts the file with `key`, that has been
+    /// derived before, e.g. with [`PasswordKey::from_header`]
+    pub fn read_from_snapshot_with_password_key(
+        snapshot_path: &SnapshotPath,
+        key: &PasswordKey,
+        associated_data: &[u8],
+        write_key: Option<(VaultId, RecordId)>,
+    ) -> Result<Self, SnapshotError> {
+        let mut data = snapshot::kdf::read_with_key(snapshot_path.as_path(), key, associated_data)?;
+        Self::from_decrypted(&mut data, *key.key(), associated_data, write_key)
+    }
+
+    /// Same as [`Self::read_from_snapshot_with_password`], but reads the state from the `content` of a snapshot
+    /// file instead of the file system, e.g. from the storage of a browser
+    pub fn read_from_bytes_with_password(
+        content: &[u8],
+        password: &[u8],
+        associated_data: &[u8],
+        write_key: Option<(VaultId, RecordId)>,
+    ) -> Result<Self, SnapshotError> {
+        let (mut data, key) = snapshot::kdf::decrypt(content, password, associated_data)?;
+        Self::from_decrypted(&mut data, key, associated_data, write_key)
+    }
+
+    /// Reads state from a snapshot file, whose key is the response of an authenticator to the challenge in
+    /// its header, see [`snapshot::challenge`], and that has been bound to `associated_data`
+    pub fn read_from_snapshot_with_challenge(
+        snapshot_path: &SnapshotPath,
+        key: Key,
+        associated_data: &[u8],
+        write_key: Option<(VaultId, RecordId)>,
+    ) -> Result<Self, SnapshotError> {
+        let mut data = snapshot::challenge::read_from(snapshot_path.as_path(), &key, associated_data)?;
+        Self::from_decrypted(&mut data, key, associated_data, write_key)
+    }
 
-        let state = bincode::deserialize(&data)?;
-        Snapshot::from_state(state, key, write_key)
+    /// Deserializes the decrypted content of a snapshot file, zeroizes it, and keeps the associated data of the
+    /// file
+    fn from_decrypted(
+        data: &mut Vec<u8>,
+        key: Key,
+        associated_data: &[u8],
+        write_key: Option<(VaultId, RecordId)>,
+    ) -> Result<Self, SnapshotError> {
+        let state = bincode::deserialize(data);
+        data.zeroize();
+        let mut snapshot = Snapshot::from_state(state?, key, write_key)?;
+        snapshot.set_associated_data(associated_data.to_vec());
+        Ok(snapshot)
     }
 
-    /// Writes state to the specified named snapshot or the specified path
-    /// TODO: Add associated data.
+    /// Writes state to the specified named snapshot or the specified path, bound to the associated data
+    /// of the snapshot
     pub fn write_to_snapshot(&self, snapshot_path: &SnapshotPath, use_key: UseKey) -> Result<(), SnapshotError> {
+        self.write_to_snapshot_with_progress(snapshot_path, use_key, &mut |_: Progress| {})
+    }
+
+    /// Same as [`Self::write_to_snapshot`], but reports the [`Progress`] of each phase to `progress`.
+    pub fn write_to_snapshot_with_progress(
+        &self,
+        snapshot_path: &SnapshotPath,
+        use_key: UseKey,
+        progress: &mut dyn FnMut(Progress),
+    ) -> Result<(), SnapshotError> {
         let state = self.get_snapshot_state()?;
+        progress(Progress::new(Phase::Serialize, 0, 0));
         let data = bincode::serialize(&state)?;
+        progress(Progress::new(Phase::Serialize, data.len() as u64, data.len() as u64));
 
         let key = match use_key {
             UseKey::Key(k) => k,
@@ -229,7 +366,78 @@ impl Snapshot {
             }
         };
 
-        write_to_file(&data, snapshot_path.as_path(), &key, &[]).map_err(|e| e.into())
+        let options = WriteOptions {
+            compression: self.compression,
+        };
+        write_to_file(
+            &data,
+            snapshot_path.as_path(),
+            &key,
+            &self.associated_data,
+            &options,
+            progress,
+        )
+        .map_err(|e| e.into())
+    }
+
+    /// Writes state into a password protected snapshot file, whose key is derived from `password` with `params`.
+    /// The file is bound to the associated data of the snapshot.
+    pub fn write_to_snapshot_with_password(
+        &self,
+        snapshot_path: &SnapshotPath,
+        password: &[u8],
+        params: &Argon2Params,
+    ) -> Result<(), SnapshotError> {
+        let state = self.get_snapshot_state()?;
+        let mut data = bincode::serialize(&state)?;
+        let written = snapshot::kdf::write_to(&data, snapshot_path.as_path(), password, params, &self.associated_data);
+        data.zeroize();
+        written.map_err(|e| e.into())
+    }
+
+    /// Same as [`Self::write_to_snapshot_with_password`], but encrypts the file with `key` instead of deriving
+    /// a new key
+    pub fn write_to_snapshot_with_password_key(
+        &self,
+        snapshot_path: &SnapshotPath,
+        key: &PasswordKey,
+    ) -> Result<(), SnapshotError> {
+        let state = self.get_snapshot_state()?;
+        let mut data = bincode::serialize(&state)?;
+        let written = snapshot::kdf::write_with_key(&data, snapshot_path.as_path(), key, &self.associated_data);
+        data.zeroize();
+        written.map_err(|e| e.into())
+    }
+
+    /// Same as [`Self::write_to_snapshot_with_password`], but returns the content of the snapshot file instead
+    /// of writing it
+    pub fn write_to_bytes_with_password(
+        &self,
+        password: &[u8],
+        params: &Argon2Params,
+    ) -> Result<Vec<u8>, SnapshotError> {
+        let state = self.get_snapshot_state()?;
+        let mut data = bincode::serialize(&state)?;
+        let encrypted = snapshot::kdf::encrypt(&data, password, params, &self.associated_data);
+        data.zeroize();
+        encrypted.map_err(|e| e.into())
+    }
+
+    /// Writes state into a snapshot file, that is encrypted with `key`, the response of an authenticator to
+    /// `challenge`. The challenge is stored in the header of the file, and the file is bound to the associated
+    /// data of the snapshot.
+    pub fn write_to_snapshot_with_challenge(
+        &self,
+        snapshot_path: &SnapshotPath,
+        challenge: &Challenge,
+        key: &Key,
+    ) -> Result<(), SnapshotError> {
+        let state = self.get_snapshot_state()?;
+        let mut data = bincode::serialize(&state)?;
+        let written =
+            snapshot::challenge::write_to(&data, snapshot_path.as_path(), challenge, key, &self.associated_data);
+        data.zeroize();
+        written.map_err(|e| e.into())
     }
 
     /// Adds data to the snapshot state hashmap.
@@ -253,6 +461,68 @@ impl Snapshot {
         Ok(())
     }
 
+    /// Re-encrypts the snapshot file at `snapshot_path` from `old_key` to `new_key`, without
+    /// deserializing its state.
+    pub fn change_key(snapshot_path: &SnapshotPath, old_key: Key, new_key: Key) -> Result<(), SnapshotError> {
+        Self::change_key_with_associated_data(snapshot_path, (old_key, &[]), (new_key, &[]))
+    }
+
+    /// Same as [`Self::change_key`], but also re-binds the snapshot file from the old to the new
+    /// associated data.
+    pub fn change_key_with_associated_data(
+        snapshot_path: &SnapshotPath,
+        (mut old_key, old_associated_data): (Key, &[u8]),
+        (mut new_key, new_associated_data): (Key, &[u8]),
+    ) -> Result<(), SnapshotError> {
+        let result = snapshot::rekey_with_associated_data(
+            snapshot_path.as_path(),
+            (&old_key, old_associated_data),
+            (&new_key, new_associated_data),
+        );
+        old_key.zeroize();
+        new_key.zeroize();
+        result.map_err(|e| e.into())
+    }
+
+    /// Re-encrypts the snapshot file at `snapshot_path`, that has been written with `old_key` and
+    /// `associated_data`, with a key that is derived from `password` with `params`. Afterwards the snapshot can
+    /// only be read with [`Self::read_from_snapshot_with_password`] and the same associated data.
+    pub fn upgrade_kdf(
+        snapshot_path: &SnapshotPath,
+        (mut old_key, associated_data): (Key, &[u8]),
+        password: &[u8],
+        params: &Argon2Params,
+    ) -> Result<(), SnapshotError> {
+        let result = snapshot::kdf::upgrade(snapshot_path.as_path(), &old_key, password, params, associated_data);
+        old_key.zeroize();
+        result.map_err(|e| e.into())
+    }
+
+    /// Returns the format version of the snapshot file at `snapshot_path`
+    pub fn version(snapshot_path: &SnapshotPath) -> Result<[u8; 2], SnapshotError> {
+        Ok(snapshot::migration::detect_version(snapshot_path.as_path())?)
+    }
+
+    /// Returns the metadata in the unencrypted header of the snapshot file at `snapshot_path`, e.g. to show
+    /// its format before asking for the password. No key is needed.
+    pub fn peek_metadata(snapshot_path: &SnapshotPath) -> Result<SnapshotInfo, SnapshotError> {
+        Ok(snapshot::info::read_info(snapshot_path.as_path())?)
+    }
+
+    /// Upgrades the snapshot file at `snapshot_path`, that has been written with format version
+    /// `from_version`, in place to `to_version`. Use [`snapshot::VERSION`] as `to_version` to make an old
+    /// snapshot readable by [`Self::read_from_snapshot`].
+    pub fn migrate(
+        snapshot_path: &SnapshotPath,
+        from_version: [u8; 2],
+        to_version: [u8; 2],
+        mut key: Key,
+    ) -> Result<(), SnapshotError> {
+        let result = snapshot::migration::migrate(snapshot_path.as_path(), from_version, to_version, &key, &[]);
+        key.zeroize();
+        result.map_err(|e| e.into())
+    }
+
     /// Adds data to the snapshot state hashmap.
     pub fn store_snapshot_key(
         &mut self,
@@ -320,6 +590,66 @@ impl Snapshot {
         Ok(())
     }
 
+    /// Compares the records of all clients with the records in `other`, e.g. the backup of the same
+    /// Stronghold from another device.
+    pub fn diff(&self, other: &Snapshot) -> Result<SnapshotDiff, SnapshotError> {
+        let ours = self.get_hierarchy(None)?;
+        let theirs = other.get_hierarchy(None)?;
+        Ok(SnapshotDiff::between(ours, theirs))
+    }
+
+    /// Merges all records of `other` into this snapshot. Records that only exist in `other` are added,
+    /// records that only exist here are kept. For records with different content in both snapshots
+    /// [`MergePolicy::Replace`] takes the record of `other` and [`MergePolicy::KeepOld`] keeps the local one,
+    /// which results in the union of both snapshots.
+    ///
+    /// Records carry no modification time, so a newest-wins merge is done by merging the older snapshot
+    /// into the newer one with [`MergePolicy::KeepOld`], or the newer into the older one with
+    /// [`MergePolicy::Replace`].
+    pub fn merge(&mut self, other: &Snapshot, policy: MergePolicy) -> Result<(), SnapshotError> {
+        self.merge_with(other, |_| policy)
+    }
+
+    /// Same as [`Self::merge`], but calls `resolve` for each record with different content in both
+    /// snapshots to decide, which record is kept.
+    pub fn merge_with<F>(&mut self, other: &Snapshot, mut resolve: F) -> Result<(), SnapshotError>
+    where
+        F: FnMut(&RecordConflict) -> MergePolicy,
+    {
+        let SnapshotDiff { mut added, changed, .. } = self.diff(other)?;
+        for (client_id, vaults) in changed {
+            for (vault_id, records) in vaults {
+                let replaced = records.into_iter().filter(|&record_id| {
+                    let conflict = RecordConflict {
+                        client_id,
+                        vault_id,
+                        record_id,
+                    };
+                    resolve(&conflict) == MergePolicy::Replace
+                });
+                added
+                    .entry(client_id)
+                    .or_default()
+                    .entry(vault_id)
+                    .or_default()
+                    .extend(replaced);
+            }
+        }
+
+        let mut state = other.get_snapshot_state()?;
+        let exported = state.export_entries(added)?;
+        let mut old_keys = HashMap::new();
+        for cid in exported.keys() {
+            let ks = state
+                .0
+                .remove(cid)
+                .ok_or_else(|| SnapshotError::Inner(format!("Missing KeyStore for client {:?}", cid)))?
+                .0;
+            old_keys.insert(*cid, ks);
+        }
+        self.import_records(exported, &old_keys, &SyncSnapshotsConfig::default())
+    }
+
     /// Deserialize, decompress and decrypt a state received from a remote peer and merge
     /// it into the local state.
     ///
@@ -389,6 +719,58 @@ impl Snapshot {
         Ok((pk, buffer))
     }
 
+    /// Exports the vaults at `vault_paths` of the clients at `client_paths` into a new password protected
+    /// snapshot, and returns the content of the snapshot file. Vaults that don't exist in a client are
+    /// skipped. The store of the clients is not exported.
+    ///
+    /// The records are re-encrypted with new vault keys, and the snapshot with a transfer key that is
+    /// derived from `password` with `params`. The receiving device can write the content to a file and
+    /// read it with [`Self::read_from_snapshot_with_password`].
+    pub fn export_partial<C, V>(
+        &self,
+        client_paths: &[C],
+        vault_paths: &[V],
+        password: &[u8],
+        params: &Argon2Params,
+    ) -> Result<Vec<u8>, SnapshotError>
+    where
+        C: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let clients: Vec<ClientId> = client_paths
+            .iter()
+            .map(|path| ClientId::load_from_path(path.as_ref(), path.as_ref()))
+            .collect();
+        let vaults: Vec<VaultId> = vault_paths
+            .iter()
+            .map(|path| VaultId::load_from_path(path.as_ref(), path.as_ref()))
+            .collect();
+
+        let mut old_keys = HashMap::new();
+        let mut export = HashMap::new();
+        for cid in clients {
+            if !self.has_data(cid) {
+                return Err(SnapshotError::Inner(format!("Missing state for client {:?}", cid)));
+            }
+            let state = self.get_state(cid)?;
+            let select = state
+                .get_hierarchy(Some(vaults.clone()))?
+                .into_iter()
+                .map(|(vid, records)| (vid, records.into_iter().map(|(rid, _)| rid).collect()))
+                .collect();
+            let exported = state.export_entries(select)?;
+            old_keys.insert(cid, state.0);
+            export.insert(cid, exported);
+        }
+
+        let mut partial = SnapshotState::default();
+        partial.import_records(export, &old_keys, &SyncSnapshotsConfig::default())?;
+        let mut data = bincode::serialize(&partial)?;
+        let encrypted = snapshot::kdf::encrypt(&data, password, params, &self.associated_data);
+        data.zeroize();
+        encrypted.map_err(|e| e.into())
+    }
+
     /// Clears the state from the [`Snapshot`]. This function shouldn't be called directly,
     /// but from [`crate::Stronghold::clear()`]
     pub(crate) fn clear(&mut self) -> Result<(), SnapshotError> {
@@ -2,17 +2,26 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
+    collections::HashMap,
     error::Error,
     marker::PhantomData,
     ops::Deref,
-    sync::{Arc, RwLock, RwLockReadGuard},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard, Weak,
+    },
+    thread,
     time::Duration,
 };
 
-use crate::ClientError;
-use engine::store::Cache;
+use super::typed;
+use crate::{ClientError, Codec, Event, EventBus, TypedValue};
+use engine::{store::Cache, vault::ClientId};
 use serde::{de::DeserializeSeed, Deserialize, Serialize};
 
+/// A key of the store together with its value
+type Entry = (Vec<u8>, Vec<u8>);
+
 // The [`StoreGuard`] wraps the [`RwLocKReadGuard`] with an associated key. The
 // inner value can simply be accessed by a custom `deref` function
 // pub struct StoreGuard<'a> {
@@ -33,12 +42,109 @@ use serde::{de::DeserializeSeed, Deserialize, Serialize};
 //     }
 // }
 
+/// What happens, if an insert into a [`Store`] would exceed [`StoreLimits::max_total_size`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// The insert fails with [`ClientError::StoreQuotaExceeded`]
+    #[default]
+    Reject,
+
+    /// The least recently used entries are evicted, until the new entry fits into the store
+    Lru,
+}
+
+/// Size limits of a [`Store`]. The size of an entry is the length of its key plus the length of its value.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StoreLimits {
+    /// The maximum size of a single entry. Larger entries are rejected with [`ClientError::StoreEntryTooLarge`]
+    pub max_entry_size: Option<usize>,
+
+    /// The maximum size of all entries together
+    pub max_total_size: Option<usize>,
+
+    /// The policy to apply, if `max_total_size` would be exceeded
+    pub eviction: EvictionPolicy,
+}
+
+/// Tracks the order in which the entries of a [`Store`] have been accessed
+#[derive(Clone, Default)]
+pub(crate) struct AccessLog {
+    tick: u64,
+    last_access: HashMap<Vec<u8>, u64>,
+}
+
+impl AccessLog {
+    fn touch(&mut self, key: &[u8]) {
+        self.tick += 1;
+        self.last_access.insert(key.to_vec(), self.tick);
+    }
+
+    /// Entries without a recorded access, e.g. loaded from a snapshot, are considered the oldest.
+    fn last_access(&self, key: &[u8]) -> u64 {
+        self.last_access.get(key).copied().unwrap_or_default()
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct Store {
     pub(crate) cache: Arc<RwLock<Cache<Vec<u8>, Vec<u8>>>>,
+
+    limits: Arc<RwLock<StoreLimits>>,
+
+    access: Arc<Mutex<AccessLog>>,
+
+    revision: Arc<AtomicU64>,
 }
 
 impl Store {
+    /// Creates a new, empty [`Store`] with the given size `limits`
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::{ClientError, Store, StoreLimits};
+    ///
+    /// let store = Store::with_limits(StoreLimits {
+    ///     max_entry_size: Some(16),
+    ///     ..Default::default()
+    /// });
+    /// assert!(matches!(
+    ///     store.insert(b"key".to_vec(), vec![0; 32], None),
+    ///     Err(ClientError::StoreEntryTooLarge { .. })
+    /// ));
+    /// ```
+    pub fn with_limits(limits: StoreLimits) -> Self {
+        let store = Self::default();
+        *store.limits.write().expect("Lock of a new store can not be poisoned") = limits;
+        store
+    }
+
+    /// Replaces the size limits of the [`Store`]. Existing entries are kept, even if they exceed the new limits.
+    pub fn set_limits(&self, limits: StoreLimits) -> Result<(), ClientError> {
+        *self.limits.write()? = limits;
+        Ok(())
+    }
+
+    /// Returns the current size limits of the [`Store`]
+    pub fn limits(&self) -> Result<StoreLimits, ClientError> {
+        Ok(*self.limits.read()?)
+    }
+
+    /// Returns the size of all entries in the [`Store`]
+    pub fn size(&self) -> Result<usize, ClientError> {
+        let guard = self.cache.read()?;
+        Ok(entry_sizes(&guard).into_iter().map(|(_, size)| size).sum())
+    }
+
+    /// Returns a counter, that is increased with every modification of the [`Store`]
+    pub(crate) fn revision(&self) -> u64 {
+        self.revision.load(Ordering::SeqCst)
+    }
+
+    /// Records a modification of the [`Store`], that has bypassed its API
+    pub(crate) fn mark_dirty(&self) {
+        self.revision.fetch_add(1, Ordering::SeqCst);
+    }
+
     /// Inserts a `value` into the store with `key`
     ///
     /// # Example
@@ -56,8 +162,72 @@ impl Store {
         value: Vec<u8>,
         lifetime: Option<Duration>,
     ) -> Result<Option<Vec<u8>>, ClientError> {
-        let mut guard = self.cache.write()?;
-        Ok(guard.insert(key.to_vec(), value, lifetime))
+        self.write()?.insert(key, value, lifetime)
+    }
+
+    /// Inserts `value` encoded with [`Bincode`](crate::Bincode), together with its type tag and version.
+    /// See [`Self::insert_typed_with`] for other encodings.
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::{Store, TypedValue};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    /// struct Account {
+    ///     name: String,
+    ///     index: u32,
+    /// }
+    ///
+    /// impl TypedValue for Account {
+    ///     const TAG: &'static str = "wallet.account";
+    ///     const VERSION: u32 = 1;
+    /// }
+    ///
+    /// let store = Store::default();
+    /// let account = Account {
+    ///     name: "main".into(),
+    ///     index: 0,
+    /// };
+    /// store.insert_typed(b"account", &account, None).unwrap();
+    /// assert_eq!(
+    ///     store.get_typed::<Account>(b"account").unwrap(),
+    ///     Some(account)
+    /// );
+    /// ```
+    pub fn insert_typed<T: TypedValue>(
+        &self,
+        key: &[u8],
+        value: &T,
+        lifetime: Option<Duration>,
+    ) -> Result<(), ClientError> {
+        self.insert_typed_with::<crate::Bincode, T>(key, value, lifetime)
+    }
+
+    /// Inserts `value` encoded with the codec `C`, together with its type tag and version.
+    pub fn insert_typed_with<C: Codec, T: TypedValue>(
+        &self,
+        key: &[u8],
+        value: &T,
+        lifetime: Option<Duration>,
+    ) -> Result<(), ClientError> {
+        let encoded = typed::encode::<C, T>(value)?;
+        self.insert(key.to_vec(), encoded, lifetime)?;
+        Ok(())
+    }
+
+    /// Gets the value via `key` and decodes it as `T`, with the codec, that it has been inserted with. Fails
+    /// with [`ClientError::TypeMismatch`], if the stored value has another type tag or version.
+    pub fn get_typed<T: TypedValue>(&self, key: &[u8]) -> Result<Option<T>, ClientError> {
+        self.get(key)?.map(|bytes| typed::decode(&bytes)).transpose()
+    }
+
+    /// Gets the value via `key` and decodes it as `T` with the codec `C`. Fails, if the value has been inserted
+    /// with another codec.
+    pub fn get_typed_with<C: Codec, T: TypedValue>(&self, key: &[u8]) -> Result<Option<T>, ClientError> {
+        self.get(key)?
+            .map(|bytes| typed::decode_with::<C, T>(&bytes))
+            .transpose()
     }
 
     /// Tries to get the stored value via `key`
@@ -79,7 +249,13 @@ impl Store {
         // Problem: The returned rwread guard is local to this function, hence we can't return a borrowed ref
         // to the inner value. we could return the guard itself, but would rely on the user to deref the rwguard
         // and then access the value again
-        Ok(guard.get(&key.to_vec()).cloned())
+        let value = guard.get(&key.to_vec()).cloned();
+
+        // only accesses of existing entries are recorded, so that lookups of missing keys don't grow the log
+        if value.is_some() {
+            self.access.lock()?.touch(key);
+        }
+        Ok(value)
     }
 
     /// Tries to delete the inner vale with `key`
@@ -100,8 +276,72 @@ impl Store {
     ///     .is_none());
     /// ```
     pub fn delete(&self, key: &[u8]) -> Result<Option<Vec<u8>>, ClientError> {
-        let mut guard = self.cache.write()?;
-        Ok(guard.remove(&key.to_vec()))
+        Ok(self.write()?.delete(key))
+    }
+
+    /// Replaces the value of `key` with `new`, if its current value equals `expected`. `None` stands for a
+    /// missing entry on both sides, so `expected: None` inserts only if the key is absent, and `new: None`
+    /// deletes the entry. An existing entry keeps its remaining lifetime.
+    ///
+    /// Returns `true`, if the value has been replaced. The comparison and the replacement are atomic with respect
+    /// to all other operations on the store.
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::Store;
+    ///
+    /// let store = Store::default();
+    /// assert!(store
+    ///     .compare_and_swap(b"nonce", None, Some(vec![0]))
+    ///     .unwrap());
+    /// assert!(!store
+    ///     .compare_and_swap(b"nonce", None, Some(vec![1]))
+    ///     .unwrap());
+    /// assert!(store
+    ///     .compare_and_swap(b"nonce", Some(&[0]), Some(vec![1]))
+    ///     .unwrap());
+    /// assert_eq!(store.get(b"nonce").unwrap(), Some(vec![1]));
+    /// ```
+    pub fn compare_and_swap(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<Vec<u8>>,
+    ) -> Result<bool, ClientError> {
+        let mut guard = self.write()?;
+        if guard.get(key).map(Vec::as_slice) != expected {
+            return Ok(false);
+        }
+        guard.replace(key, new)?;
+        Ok(true)
+    }
+
+    /// Replaces the value of `key` with the result of `f`, which is called with the current value. If `f` returns
+    /// `None`, the entry is deleted. An existing entry keeps its remaining lifetime. Returns the new value.
+    ///
+    /// The store is locked while `f` runs, so `f` must not access the store itself.
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::Store;
+    ///
+    /// let store = Store::default();
+    /// let increment = |value: Option<Vec<u8>>| {
+    ///     let nonce = value.map_or(0, |bytes| u64::from_le_bytes(bytes.try_into().unwrap()) + 1);
+    ///     Some(nonce.to_le_bytes().to_vec())
+    /// };
+    /// store.update_with(b"nonce", increment).unwrap();
+    /// let updated = store.update_with(b"nonce", increment).unwrap();
+    /// assert_eq!(updated, Some(1u64.to_le_bytes().to_vec()));
+    /// ```
+    pub fn update_with<F>(&self, key: &[u8], f: F) -> Result<Option<Vec<u8>>, ClientError>
+    where
+        F: FnOnce(Option<Vec<u8>>) -> Option<Vec<u8>>,
+    {
+        let mut guard = self.write()?;
+        let new = f(guard.get(key).cloned());
+        guard.replace(key, new.clone())?;
+        Ok(new)
     }
 
     /// Checks the [`Store`], if the provided key exists
@@ -134,6 +374,8 @@ impl Store {
     pub fn reload(&self, cache: Cache<Vec<u8>, Vec<u8>>) -> Result<(), ClientError> {
         let mut inner = self.cache.write()?;
         *inner = cache;
+        *self.access.lock()? = AccessLog::default();
+        self.mark_dirty();
         Ok(())
     }
 
@@ -159,13 +401,380 @@ impl Store {
         Ok(inner.keys())
     }
 
+    /// Returns a copy of all entries inside the store, that have not expired yet. The order is unspecified.
+    ///
+    /// # Examples
+    /// ```
+    /// use iota_stronghold::Store;
+    ///
+    /// let store = Store::default();
+    /// store
+    ///     .insert(b"key-1".to_vec(), b"val-1".to_vec(), None)
+    ///     .unwrap();
+    /// store
+    ///     .insert(b"key-2".to_vec(), b"val-2".to_vec(), None)
+    ///     .unwrap();
+    /// let mut entries = store.iter().unwrap();
+    /// entries.sort();
+    /// assert_eq!(entries[0], (b"key-1".to_vec(), b"val-1".to_vec()));
+    /// ```
+    pub fn iter(&self) -> Result<Vec<Entry>, ClientError> {
+        let inner = self.cache.read()?;
+        Ok(inner.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+    }
+
+    /// Returns all keys starting with `prefix`, that have not expired yet
+    ///
+    /// # Examples
+    /// ```
+    /// use iota_stronghold::Store;
+    ///
+    /// let store = Store::default();
+    /// store.insert(b"session/1".to_vec(), vec![], None).unwrap();
+    /// store.insert(b"session/2".to_vec(), vec![], None).unwrap();
+    /// store.insert(b"settings".to_vec(), vec![], None).unwrap();
+    /// let mut keys = store.keys_with_prefix(b"session/").unwrap();
+    /// keys.sort();
+    /// assert_eq!(keys, vec![b"session/1".to_vec(), b"session/2".to_vec()]);
+    /// ```
+    pub fn keys_with_prefix(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>, ClientError> {
+        let inner = self.cache.read()?;
+        Ok(inner
+            .iter()
+            .filter(|(k, _)| k.starts_with(prefix))
+            .map(|(k, _)| k.clone())
+            .collect())
+    }
+
+    /// Returns the number of entries inside the store, that have not expired yet
+    pub fn len(&self) -> Result<usize, ClientError> {
+        Ok(self.cache.read()?.len())
+    }
+
+    /// Checks, if the store has no entries, that have not expired yet
+    pub fn is_empty(&self) -> Result<bool, ClientError> {
+        Ok(self.cache.read()?.is_empty())
+    }
+
+    /// Returns the lifetime, that is left for the entry with `key`. Returns [`None`], if there is no live entry
+    /// with `key`, and `Some(None)`, if the entry has been inserted without lifetime.
+    ///
+    /// # Examples
+    /// ```
+    /// use iota_stronghold::Store;
+    /// use std::time::Duration;
+    ///
+    /// let store = Store::default();
+    /// store
+    ///     .insert(b"session".to_vec(), vec![], Some(Duration::from_secs(60)))
+    ///     .unwrap();
+    /// let remaining = store.remaining_ttl(b"session").unwrap().unwrap().unwrap();
+    /// assert!(remaining <= Duration::from_secs(60));
+    /// assert_eq!(store.remaining_ttl(b"missing").unwrap(), None);
+    /// ```
+    pub fn remaining_ttl(&self, key: &[u8]) -> Result<Option<Option<Duration>>, ClientError> {
+        let inner = self.cache.read()?;
+        Ok(inner.remaining_lifetime(&key.to_vec()))
+    }
+
+    /// Removes all expired entries from the store, and returns their keys. Expired entries can't be read, but
+    /// they still take up memory and space in the snapshot, until they are pruned.
+    pub fn prune_expired(&self) -> Result<Vec<Vec<u8>>, ClientError> {
+        let mut inner = self.cache.write()?;
+        let expired = inner.remove_expired();
+        if !expired.is_empty() {
+            let mut access = self.access.lock()?;
+            expired.iter().for_each(|key| {
+                access.last_access.remove(key);
+            });
+            self.mark_dirty();
+        }
+        Ok(expired)
+    }
+
+    /// Returns a reference to the entries of the [`Store`], that doesn't keep them alive
+    pub(crate) fn downgrade(&self) -> WeakStore {
+        WeakStore {
+            cache: Arc::downgrade(&self.cache),
+            limits: Arc::downgrade(&self.limits),
+            access: Arc::downgrade(&self.access),
+            revision: Arc::downgrade(&self.revision),
+        }
+    }
+
+    /// Locks the entries of the [`Store`] for writing. Reads of other threads block, until the guard is dropped.
+    pub(crate) fn write(&self) -> Result<StoreWriteGuard<'_>, ClientError> {
+        let limits = *self.limits.read()?;
+        Ok(StoreWriteGuard {
+            store: self,
+            limits,
+            cache: self.cache.write()?,
+            access: self.access.lock()?,
+        })
+    }
+
     /// Clear the [`Store`]
     pub fn clear(&self) -> Result<(), ClientError> {
-        self.cache.write()?.clear();
+        let mut guard = self.cache.write()?;
+        guard.clear();
+        *self.access.lock()? = AccessLog::default();
+        self.mark_dirty();
         Ok(())
     }
 }
 
+/// A view on the entries of a [`Store`], whose keys start with the prefix of a namespace, see
+/// [`Client::store_namespace`](crate::Client::store_namespace). Keys passed to and returned by the handle don't
+/// contain the prefix, so components sharing a client can't read or overwrite each other's entries by accident.
+///
+/// The prefix of namespace `name` is `b"ns\0"`, followed by the length of `name` as big endian `u32` and `name`
+/// itself. Different names therefore never yield overlapping prefixes.
+#[derive(Clone)]
+pub struct StoreHandle {
+    store: Store,
+    prefix: Vec<u8>,
+}
+
+impl StoreHandle {
+    pub(crate) fn new(store: Store, name: &str) -> Self {
+        let mut prefix = b"ns\0".to_vec();
+        prefix.extend_from_slice(&(name.len() as u32).to_be_bytes());
+        prefix.extend_from_slice(name.as_bytes());
+        Self { store, prefix }
+    }
+
+    fn key(&self, key: &[u8]) -> Vec<u8> {
+        [&self.prefix, key].concat()
+    }
+
+    /// Inserts `value` with `key` into the namespace, see [`Store::insert`]
+    pub fn insert(
+        &self,
+        key: &[u8],
+        value: Vec<u8>,
+        lifetime: Option<Duration>,
+    ) -> Result<Option<Vec<u8>>, ClientError> {
+        self.store.insert(self.key(key), value, lifetime)
+    }
+
+    /// Gets the value of `key` in the namespace
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, ClientError> {
+        self.store.get(&self.key(key))
+    }
+
+    /// Deletes `key` from the namespace
+    pub fn delete(&self, key: &[u8]) -> Result<Option<Vec<u8>>, ClientError> {
+        self.store.delete(&self.key(key))
+    }
+
+    /// Checks, if `key` exists in the namespace
+    pub fn contains_key(&self, key: &[u8]) -> Result<bool, ClientError> {
+        self.store.contains_key(&self.key(key))
+    }
+
+    /// See [`Store::compare_and_swap`]
+    pub fn compare_and_swap(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<Vec<u8>>,
+    ) -> Result<bool, ClientError> {
+        self.store.compare_and_swap(&self.key(key), expected, new)
+    }
+
+    /// See [`Store::update_with`]
+    pub fn update_with<F>(&self, key: &[u8], f: F) -> Result<Option<Vec<u8>>, ClientError>
+    where
+        F: FnOnce(Option<Vec<u8>>) -> Option<Vec<u8>>,
+    {
+        self.store.update_with(&self.key(key), f)
+    }
+
+    /// Returns the keys of all live entries in the namespace, without the prefix of the namespace
+    pub fn keys(&self) -> Result<Vec<Vec<u8>>, ClientError> {
+        Ok(self
+            .store
+            .keys_with_prefix(&self.prefix)?
+            .into_iter()
+            .map(|key| key[self.prefix.len()..].to_vec())
+            .collect())
+    }
+
+    /// Returns the number of live entries in the namespace
+    pub fn len(&self) -> Result<usize, ClientError> {
+        Ok(self.store.keys_with_prefix(&self.prefix)?.len())
+    }
+
+    /// Checks, if the namespace has no live entries
+    pub fn is_empty(&self) -> Result<bool, ClientError> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Deletes all entries of the namespace. Entries of other namespaces and of the plain [`Store`] are kept.
+    pub fn clear(&self) -> Result<(), ClientError> {
+        let mut guard = self.store.write()?;
+        let keys: Vec<Vec<u8>> = guard
+            .cache
+            .keys()
+            .into_iter()
+            .filter(|key| key.starts_with(&self.prefix))
+            .collect();
+        keys.iter().for_each(|key| {
+            guard.delete(key);
+        });
+        Ok(())
+    }
+}
+
+/// A [`Store`], that is only accessible as long as another reference to it exists, see [`Store::downgrade`]
+pub(crate) struct WeakStore {
+    cache: Weak<RwLock<Cache<Vec<u8>, Vec<u8>>>>,
+    limits: Weak<RwLock<StoreLimits>>,
+    access: Weak<Mutex<AccessLog>>,
+    revision: Weak<AtomicU64>,
+}
+
+impl WeakStore {
+    pub(crate) fn upgrade(&self) -> Option<Store> {
+        Some(Store {
+            cache: self.cache.upgrade()?,
+            limits: self.limits.upgrade()?,
+            access: self.access.upgrade()?,
+            revision: self.revision.upgrade()?,
+        })
+    }
+}
+
+/// Prunes the expired entries of `store` every `interval`, and publishes an [`Event::StoreEntryExpired`] for each
+/// of them. Returns, when the store has been dropped, or `generation` no longer equals `id`.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn sweep(
+    store: WeakStore,
+    generation: Weak<AtomicU64>,
+    id: u64,
+    interval: Duration,
+    events: EventBus,
+    client: ClientId,
+) {
+    loop {
+        thread::sleep(interval);
+        match generation.upgrade() {
+            Some(generation) if generation.load(Ordering::SeqCst) == id => {}
+            _ => return,
+        }
+        let expired = match store.upgrade().map(|store| store.prune_expired()) {
+            Some(Ok(expired)) => expired,
+            _ => return,
+        };
+        expired
+            .into_iter()
+            .for_each(|key| events.emit(Event::StoreEntryExpired { client, key }));
+    }
+}
+
+/// Exclusive access to the entries of a [`Store`], see [`Store::write`]
+pub(crate) struct StoreWriteGuard<'a> {
+    store: &'a Store,
+    limits: StoreLimits,
+    cache: RwLockWriteGuard<'a, Cache<Vec<u8>, Vec<u8>>>,
+    access: MutexGuard<'a, AccessLog>,
+}
+
+impl<'a> StoreWriteGuard<'a> {
+    pub(crate) fn insert(
+        &mut self,
+        key: Vec<u8>,
+        value: Vec<u8>,
+        lifetime: Option<Duration>,
+    ) -> Result<Option<Vec<u8>>, ClientError> {
+        let size = key.len() + value.len();
+        if let Some(limit) = self.limits.max_entry_size {
+            if size > limit {
+                return Err(ClientError::StoreEntryTooLarge { size, limit });
+            }
+        }
+
+        if let Some(limit) = self.limits.max_total_size {
+            let mut others: Vec<(Vec<u8>, usize)> = entry_sizes(&self.cache)
+                .into_iter()
+                .filter(|(k, _)| *k != key)
+                .collect();
+            let mut total = others.iter().map(|(_, size)| size).sum::<usize>() + size;
+
+            if total > limit {
+                if self.limits.eviction == EvictionPolicy::Reject || size > limit {
+                    return Err(ClientError::StoreQuotaExceeded { size: total, limit });
+                }
+
+                others.sort_by_key(|(k, _)| self.access.last_access(k));
+                for (k, entry_size) in others {
+                    if total <= limit {
+                        break;
+                    }
+                    self.cache.remove(&k);
+                    self.access.last_access.remove(&k);
+                    total -= entry_size;
+                }
+            }
+        }
+
+        self.access.touch(&key);
+        self.store.mark_dirty();
+        Ok(self.cache.insert(key, value, lifetime))
+    }
+
+    pub(crate) fn get(&self, key: &[u8]) -> Option<&Vec<u8>> {
+        self.cache.get(&key.to_vec())
+    }
+
+    /// Sets the value of `key` to `value`, or deletes it for `None`. An existing entry keeps its remaining
+    /// lifetime.
+    pub(crate) fn replace(&mut self, key: &[u8], value: Option<Vec<u8>>) -> Result<(), ClientError> {
+        match value {
+            Some(value) => {
+                let lifetime = self.cache.remaining_lifetime(&key.to_vec()).flatten();
+                self.insert(key.to_vec(), value, lifetime)?;
+            }
+            None => {
+                self.delete(key);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn delete(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        self.access.last_access.remove(key);
+        let removed = self.cache.remove(&key.to_vec());
+        if removed.is_some() {
+            self.store.mark_dirty();
+        }
+        removed
+    }
+
+    /// Returns a copy of the entries, that can be restored with [`Self::restore`]
+    pub(crate) fn backup(&self) -> (Cache<Vec<u8>, Vec<u8>>, AccessLog) {
+        ((*self.cache).clone(), (*self.access).clone())
+    }
+
+    pub(crate) fn restore(&mut self, (cache, access): (Cache<Vec<u8>, Vec<u8>>, AccessLog)) {
+        *self.cache = cache;
+        *self.access = access;
+        self.store.mark_dirty();
+    }
+}
+
+/// Returns the key and size of all live entries in `cache`
+fn entry_sizes(cache: &Cache<Vec<u8>, Vec<u8>>) -> Vec<(Vec<u8>, usize)> {
+    cache
+        .keys()
+        .into_iter()
+        .filter_map(|key| {
+            let size = cache.get(&key)?.len() + key.len();
+            Some((key, size))
+        })
+        .collect()
+}
+
 // compatibility implementation
 
 impl Serialize for Store {
@@ -185,6 +794,7 @@ impl<'a> Deserialize<'a> for Store {
         let cache = Cache::deserialize(deserializer)?;
         Ok(Store {
             cache: Arc::new(RwLock::new(cache)),
+            ..Default::default()
         })
     }
 }
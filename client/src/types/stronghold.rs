@@ -3,20 +3,33 @@
 use crate::{
     procedures::Runner,
     sync::{SnapshotHierarchy, SyncSnapshots, SyncSnapshotsConfig},
-    Client, ClientError, ClientState, KeyProvider, LoadFromPath, Location, RemoteMergeError, RemoteVaultError,
-    Snapshot, SnapshotPath, Store, UseKey,
+    Argon2Params, Client, ClientError, ClientState, Event, EventBus, Health, HmacSecret, KeyProvider, LoadFromPath,
+    Location, Metrics, MetricsRecorder, PasswordKey, Persist, RemoteMergeError, RemoteVaultError, ShutdownConfig,
+    Snapshot, SnapshotError, SnapshotPath, Store, UseKey,
 };
 use crypto::keys::x25519;
-use engine::vault::ClientId;
+use engine::{
+    snapshot::{
+        self,
+        challenge::Challenge,
+        incremental::IncrementalSnapshot,
+        throttle::{self, Attempts, ThrottlePolicy},
+        Compression, Progress,
+    },
+    time::{Duration, Instant, SystemTime},
+    vault::ClientId,
+};
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::{hash_map::Entry, HashMap, HashSet},
     ops::Deref,
-    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 use stronghold_utils::GuardDebug;
 use zeroize::Zeroize;
 
-/// Writes a single [`Client`] into snapshot
+/// Writes a single [`Client`] into snapshot. A locked client is skipped, its keystore is empty, and the
+/// snapshot keeps the state it has been written with last.
 /// We use a macro instead of a function due to locks lifetime
 /// ending at the end of a function
 /// # Example
@@ -27,20 +40,23 @@ macro_rules! write_with_clientid {
             None => return Err(ClientError::ClientDataNotPresent),
         };
 
-        let mut keystore_guard = client.keystore.write()?;
-        let view = client.db.read()?;
-        let store = client.store.cache.read()?;
+        if let Some(mut keystore_guard) = client.keystore_unless_locked()? {
+            let view = client.db.read()?;
+            let store = client.store.cache.read()?;
 
-        // we need some compatibility code here. Keyprovider stores encrypted vec
-        // by snapshot requires a mapping to Key<Provider>
+            // we need some compatibility code here. Keyprovider stores encrypted vec
+            // by snapshot requires a mapping to Key<Provider>
 
-        let keystore = keystore_guard.get_data();
+            let mut keystore = keystore_guard.get_data();
+            let mut view = (*view).clone();
+            client.persist_usage(&mut keystore, &mut view)?;
 
-        // This might be critical, as keystore gets copied into Boxed types, but still safe
-        // we also use cloned data, which might not be ideal.
-        ($snapshot)
-            .add_data(($client_id), (keystore, (*view).clone(), (*store).clone()))
-            .map_err(|e| ClientError::Inner(e.to_string()))?;
+            // This might be critical, as keystore gets copied into Boxed types, but still safe
+            // we also use cloned data, which might not be ideal.
+            ($snapshot)
+                .add_data(($client_id), (keystore, view, (*store).clone()))
+                .map_err(|e| ClientError::Inner(e.to_string()))?;
+        }
     }};
 }
 
@@ -50,6 +66,9 @@ macro_rules! write_with_clientid {
 /// # Example
 macro_rules! load_snapshot {
     ($snapshot:expr, $snapshot_path:expr, $keyprovider:expr) => {{
+        load_snapshot!($snapshot, $snapshot_path, $keyprovider, &mut |_: Progress| {})
+    }};
+    ($snapshot:expr, $snapshot_path:expr, $keyprovider:expr, $progress:expr) => {{
         {
             if !($snapshot_path).exists() {
                 let path = ($snapshot_path)
@@ -64,10 +83,20 @@ macro_rules! load_snapshot {
             let buffer = ($keyprovider)
                 .try_unlock()
                 .map_err(|e| ClientError::Inner(format!("{:?}", e)))?;
-            let buffer_ref = buffer.borrow().deref().try_into().unwrap();
+            let buffer_ref = buffer
+                .borrow()
+                .deref()
+                .try_into()
+                .map_err(|_| ClientError::IllegalKeySize(32))?;
 
-            *($snapshot) = Snapshot::read_from_snapshot(($snapshot_path), buffer_ref, None)
-                .map_err(|e| ClientError::Inner(e.to_string()))?;
+            *($snapshot) = Snapshot::read_from_snapshot_with_associated_data(
+                ($snapshot_path),
+                buffer_ref,
+                ($keyprovider).associated_data(),
+                None,
+                ($progress),
+            )
+            .map_err(|e| ClientError::Inner(e.to_string()))?;
             // END CRITICAL SECTION
         }
     }};
@@ -92,6 +121,53 @@ pub struct Stronghold {
 
     /// Optional key location for writing to [`Snapshot`]
     key_location: Arc<RwLock<Option<Location>>>,
+
+    /// `true`, if the [`Snapshot`] state has been loaded from a file
+    snapshot_loaded: Arc<RwLock<bool>>,
+
+    /// The time of the last successful commit into a snapshot file
+    last_persist: Arc<RwLock<Option<SystemTime>>>,
+
+    /// The incremental snapshot file, that has been written by [`Stronghold::commit_incremental`]
+    incremental: Arc<Mutex<Option<IncrementalState>>>,
+
+    /// The compression of snapshot files written by [`Stronghold::commit`]
+    compression: Arc<RwLock<Compression>>,
+
+    /// The associated data of snapshot files, that are protected by a password or an authenticator, see
+    /// [`Stronghold::set_snapshot_associated_data`]
+    associated_data: Arc<RwLock<Vec<u8>>>,
+
+    /// Snapshots, that have been opened by name with [`Stronghold::open_named_snapshot`]
+    named_snapshots: Arc<RwLock<HashMap<String, NamedSnapshot>>>,
+
+    /// Limits the rate of failed attempts to load a snapshot file, see [`Stronghold::set_unlock_throttle`]
+    unlock_throttle: Arc<RwLock<Option<ThrottlePolicy>>>,
+
+    /// The events of the Stronghold and all of its clients
+    events: EventBus,
+
+    /// The metrics of the Stronghold and all of its clients
+    metrics: MetricsRecorder,
+}
+
+/// A snapshot, that is held next to the default [`Snapshot`] together with its file and key
+struct NamedSnapshot {
+    snapshot: Snapshot,
+    path: SnapshotPath,
+    keyprovider: KeyProvider,
+
+    /// The clients, that are persisted into this snapshot instead of the default one
+    clients: HashSet<ClientId>,
+}
+
+/// Keeps track of the client states, that have been written into an incremental snapshot file
+struct IncrementalState {
+    path: PathBuf,
+    snapshot: IncrementalSnapshot,
+
+    /// The revision of each loaded client at the time it has been written
+    persisted: HashMap<ClientId, u64>,
 }
 
 impl Stronghold {
@@ -107,6 +183,11 @@ impl Stronghold {
         self.store.clone()
     }
 
+    /// Returns the [`EventBus`], that the events of the Stronghold and all of its clients are published on
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
     /// Load the state of a [`Snapshot`] at given `snapshot_path`.
     ///
     /// The [`Snapshot`] is secured in memory and may be used to load further
@@ -123,13 +204,21 @@ impl Stronghold {
     where
         P: AsRef<[u8]>,
     {
-        let mut client = Client::default();
+        let mut client = Client {
+            events: self.events.clone(),
+            metrics: self.metrics.clone(),
+            ..Default::default()
+        };
         let client_id = ClientId::load_from_path(client_path.as_ref(), client_path.as_ref());
 
         let mut snapshot = self.snapshot.write()?;
         let mut clients = self.clients.write()?;
 
-        load_snapshot!(snapshot, snapshot_path, keyprovider);
+        self.throttled(snapshot_path, || {
+            load_snapshot!(snapshot, snapshot_path, keyprovider);
+            Ok(())
+        })?;
+        *self.snapshot_loaded.write()? = true;
 
         // If a client has already been loaded returns an error
         if clients.contains_key(&client_id) {
@@ -158,7 +247,11 @@ impl Stronghold {
         P: AsRef<[u8]>,
     {
         let client_id = ClientId::load_from_path(client_path.as_ref(), client_path.as_ref());
-        let mut client = Client::default();
+        let mut client = Client {
+            events: self.events.clone(),
+            metrics: self.metrics.clone(),
+            ..Default::default()
+        };
 
         let snapshot = self.snapshot.read()?;
         let mut clients = self.clients.write()?;
@@ -218,6 +311,15 @@ impl Stronghold {
         let mut clients = self.clients.write()?;
         clients.remove(client.id());
 
+        for named in self.named_snapshots.write()?.values_mut() {
+            if named.clients.remove(client.id()) {
+                return named
+                    .snapshot
+                    .purge_client(*client.id())
+                    .map_err(|e| ClientError::Inner(e.to_string()));
+            }
+        }
+
         snapshot
             .purge_client(*client.id())
             .map_err(|e| ClientError::Inner(e.to_string()))
@@ -227,9 +329,37 @@ impl Stronghold {
     /// is secured in memory.
     ///
     /// # Example
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %snapshot_path.as_path().display())))]
     pub fn load_snapshot(&self, keyprovider: &KeyProvider, snapshot_path: &SnapshotPath) -> Result<(), ClientError> {
         let mut snapshot = self.snapshot.write()?;
-        load_snapshot!(snapshot, snapshot_path, keyprovider);
+        self.throttled(snapshot_path, || {
+            load_snapshot!(snapshot, snapshot_path, keyprovider);
+            Ok(())
+        })?;
+        *self.snapshot_loaded.write()? = true;
+        Ok(())
+    }
+
+    /// Same as [`Self::load_snapshot`], but reports the [`Progress`] of reading, decrypting,
+    /// decompressing and deserializing the [`Snapshot`] to `progress`.
+    ///
+    /// # Example
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %snapshot_path.as_path().display())))]
+    pub fn load_snapshot_with_progress<F>(
+        &self,
+        keyprovider: &KeyProvider,
+        snapshot_path: &SnapshotPath,
+        mut progress: F,
+    ) -> Result<(), ClientError>
+    where
+        F: FnMut(Progress),
+    {
+        let mut snapshot = self.snapshot.write()?;
+        self.throttled(snapshot_path, || {
+            load_snapshot!(snapshot, snapshot_path, keyprovider, &mut progress);
+            Ok(())
+        })?;
+        *self.snapshot_loaded.write()? = true;
         Ok(())
     }
 
@@ -252,6 +382,364 @@ impl Stronghold {
         Ok(())
     }
 
+    /// Changes the password of the snapshot file at `snapshot_path`. The file is decrypted with the key of `old`,
+    /// re-encrypted with the key of `new` and atomically replaced. Neither the loaded [`Client`]s nor the
+    /// in-memory [`Snapshot`] state are affected.
+    ///
+    /// If a snapshot key has been stored with [`Self::store_snapshot_key_at_location`], it is replaced
+    /// with the new key, so that subsequent calls to [`Self::commit`] keep using the new password.
+    /// The snapshot is bound to the [`KeyProvider::associated_data`] of `new` from now on.
+    pub fn change_snapshot_password(
+        &self,
+        snapshot_path: &SnapshotPath,
+        old: &KeyProvider,
+        new: &KeyProvider,
+    ) -> Result<(), ClientError> {
+        if !snapshot_path.exists() {
+            let path = snapshot_path
+                .as_path()
+                .to_str()
+                .ok_or_else(|| ClientError::Inner("Cannot display path as string".to_string()))?;
+
+            return Err(ClientError::SnapshotFileMissing(path.to_string()));
+        }
+
+        // hold the lock, so that no commit interferes with rewriting the file
+        let mut snapshot = self.snapshot.write()?;
+
+        // CRITICAL SECTION
+        let old_buffer = old.try_unlock().map_err(|e| ClientError::Inner(format!("{:?}", e)))?;
+        let new_buffer = new.try_unlock().map_err(|e| ClientError::Inner(format!("{:?}", e)))?;
+        let old_key = old_buffer
+            .borrow()
+            .deref()
+            .try_into()
+            .map_err(|_| ClientError::IllegalKeySize(32))?;
+        let new_key: [u8; 32] = new_buffer
+            .borrow()
+            .deref()
+            .try_into()
+            .map_err(|_| ClientError::IllegalKeySize(32))?;
+
+        Snapshot::change_key_with_associated_data(
+            snapshot_path,
+            (old_key, old.associated_data()),
+            (new_key, new.associated_data()),
+        )?;
+
+        if let Some(location) = self.key_location.read()?.clone() {
+            snapshot.store_secret_key(new_key, location)?;
+        }
+        snapshot.set_associated_data(new.associated_data().to_vec());
+        // END CRITICAL SECTION
+
+        Ok(())
+    }
+
+    /// Loads the state of a password protected [`Snapshot`] at `snapshot_path`, that has been written with
+    /// [`Self::commit_with_password`]. The key is derived from `password` with the Argon2id parameters
+    /// that are stored in the header of the file.
+    ///
+    /// If an unlock throttle is set and too many attempts have failed since the last successful load, the
+    /// file is re-encrypted with raised parameters, see [`ThrottlePolicy::escalate`].
+    pub fn load_snapshot_with_password<P>(
+        &self,
+        mut password: P,
+        snapshot_path: &SnapshotPath,
+    ) -> Result<(), ClientError>
+    where
+        P: AsRef<[u8]> + Zeroize,
+    {
+        if !snapshot_path.exists() {
+            let path = snapshot_path
+                .as_path()
+                .to_str()
+                .ok_or_else(|| ClientError::Inner("Cannot display path as string".to_string()))?;
+
+            return Err(ClientError::SnapshotFileMissing(path.to_string()));
+        }
+
+        let mut snapshot = self.snapshot.write()?;
+        let associated_data = self.associated_data.read()?;
+        let loaded = self.throttled(snapshot_path, || {
+            Ok(Snapshot::read_from_snapshot_with_password(
+                snapshot_path,
+                password.as_ref(),
+                &associated_data,
+                None,
+            )?)
+        });
+        let escalated = match loaded {
+            Ok((loaded, attempts)) => {
+                *snapshot = loaded;
+                self.escalate_kdf(&snapshot, snapshot_path, password.as_ref(), &attempts)
+            }
+            Err(e) => Err(e),
+        };
+        password.zeroize();
+        escalated?;
+        *self.snapshot_loaded.write()? = true;
+
+        Ok(())
+    }
+
+    /// Writes all client states into a password protected [`Snapshot`] file. The key is derived from
+    /// `password` with Argon2id and `params`, which are stored in the header of the file together with a
+    /// fresh salt.
+    pub fn commit_with_password<P>(
+        &self,
+        snapshot_path: &SnapshotPath,
+        mut password: P,
+        params: &Argon2Params,
+    ) -> Result<(), ClientError>
+    where
+        P: AsRef<[u8]> + Zeroize,
+    {
+        let start = Instant::now();
+        if !snapshot_path.exists() {
+            let path = snapshot_path.as_path().parent().ok_or_else(|| {
+                ClientError::SnapshotFileMissing("Parent directory of snapshot file does not exist".to_string())
+            })?;
+            if let Err(io_error) = std::fs::create_dir_all(path) {
+                return Err(ClientError::SnapshotFileMissing(
+                    "Could not create snapshot file".to_string(),
+                ));
+            }
+        }
+
+        let mut snapshot = self.snapshot.write()?;
+        let clients = self.clients.read()?;
+        let ids = self.default_client_ids(&clients)?;
+
+        for client_id in ids {
+            write_with_clientid!(client_id, snapshot, clients);
+        }
+
+        snapshot.set_associated_data(self.associated_data.read()?.clone());
+        let written = snapshot.write_to_snapshot_with_password(snapshot_path, password.as_ref(), params);
+        password.zeroize();
+        written?;
+
+        self.persisted(snapshot_path.as_path(), start)
+    }
+
+    /// Same as [`Self::load_snapshot_with_password`], but decrypts the file with `key`, that has been derived
+    /// from the password before with [`PasswordKey::from_header`]. Together with
+    /// [`Self::commit_with_password_key`], the key only has to be derived once, instead of on every load and
+    /// commit. The unlock throttle doesn't apply, as the key has already been derived.
+    pub fn load_snapshot_with_password_key(
+        &self,
+        key: &PasswordKey,
+        snapshot_path: &SnapshotPath,
+    ) -> Result<(), ClientError> {
+        if !snapshot_path.exists() {
+            let path = snapshot_path
+                .as_path()
+                .to_str()
+                .ok_or_else(|| ClientError::Inner("Cannot display path as string".to_string()))?;
+
+            return Err(ClientError::SnapshotFileMissing(path.to_string()));
+        }
+
+        let mut snapshot = self.snapshot.write()?;
+        let associated_data = self.associated_data.read()?;
+        *snapshot = Snapshot::read_from_snapshot_with_password_key(snapshot_path, key, &associated_data, None)?;
+        *self.snapshot_loaded.write()? = true;
+
+        Ok(())
+    }
+
+    /// Same as [`Self::commit_with_password`], but encrypts the file with `key` instead of deriving a new key
+    /// from the password. The file can be loaded with the password of `key`.
+    pub fn commit_with_password_key(&self, snapshot_path: &SnapshotPath, key: &PasswordKey) -> Result<(), ClientError> {
+        let start = Instant::now();
+        if !snapshot_path.exists() {
+            let path = snapshot_path.as_path().parent().ok_or_else(|| {
+                ClientError::SnapshotFileMissing("Parent directory of snapshot file does not exist".to_string())
+            })?;
+            if let Err(io_error) = std::fs::create_dir_all(path) {
+                return Err(ClientError::SnapshotFileMissing(
+                    "Could not create snapshot file".to_string(),
+                ));
+            }
+        }
+
+        let mut snapshot = self.snapshot.write()?;
+        let clients = self.clients.read()?;
+        let ids = self.default_client_ids(&clients)?;
+
+        for client_id in ids {
+            write_with_clientid!(client_id, snapshot, clients);
+        }
+
+        snapshot.set_associated_data(self.associated_data.read()?.clone());
+        snapshot.write_to_snapshot_with_password_key(snapshot_path, key)?;
+
+        self.persisted(snapshot_path.as_path(), start)
+    }
+
+    /// Same as [`Self::load_snapshot_with_password`], but loads the state from the `content` of a password
+    /// protected snapshot file, e.g. from the IndexedDB of a browser, where there is no file system.
+    pub fn load_snapshot_from_bytes<P>(&self, content: &[u8], mut password: P) -> Result<(), ClientError>
+    where
+        P: AsRef<[u8]> + Zeroize,
+    {
+        let mut snapshot = self.snapshot.write()?;
+        let associated_data = self.associated_data.read()?;
+        let loaded = Snapshot::read_from_bytes_with_password(content, password.as_ref(), &associated_data, None);
+        password.zeroize();
+        *snapshot = loaded?;
+        *self.snapshot_loaded.write()? = true;
+
+        Ok(())
+    }
+
+    /// Same as [`Self::commit_with_password`], but returns the content of the snapshot file instead of writing
+    /// it, so that the application can store it elsewhere, e.g. in the IndexedDB of a browser. Load it again
+    /// with [`Self::load_snapshot_from_bytes`].
+    pub fn commit_to_bytes<P>(&self, mut password: P, params: &Argon2Params) -> Result<Vec<u8>, ClientError>
+    where
+        P: AsRef<[u8]> + Zeroize,
+    {
+        let start = Instant::now();
+        let mut snapshot = self.snapshot.write()?;
+        let clients = self.clients.read()?;
+        let ids = self.default_client_ids(&clients)?;
+
+        for client_id in ids {
+            write_with_clientid!(client_id, snapshot, clients);
+        }
+
+        snapshot.set_associated_data(self.associated_data.read()?.clone());
+        let encrypted = snapshot.write_to_bytes_with_password(password.as_ref(), params);
+        password.zeroize();
+        let encrypted = encrypted?;
+
+        self.metrics.snapshot_commit(start.elapsed());
+        self.last_persist.write()?.replace(SystemTime::now());
+        Ok(encrypted)
+    }
+
+    /// Upgrades the snapshot file at `snapshot_path`, whose key has been provided by `old`, to a key that is
+    /// derived from `password` with Argon2id and `params`. Use this to move snapshots, whose key has been
+    /// derived with [`KeyProvider::with_passphrase_hashed_blake2b`] or another KDF, to the recommended
+    /// password based format. Afterwards the snapshot must be loaded with [`Self::load_snapshot_with_password`].
+    ///
+    /// The same function raises the parameters of a password protected snapshot, if `old` provides
+    /// its derived key.
+    ///
+    /// The file stays bound to the associated data of `old`, so the same associated data has to be set with
+    /// [`Self::set_snapshot_associated_data`] to load it.
+    pub fn upgrade_snapshot_kdf<P>(
+        &self,
+        snapshot_path: &SnapshotPath,
+        old: &KeyProvider,
+        mut password: P,
+        params: &Argon2Params,
+    ) -> Result<(), ClientError>
+    where
+        P: AsRef<[u8]> + Zeroize,
+    {
+        // hold the lock, so that no commit interferes with rewriting the file
+        let snapshot = self.snapshot.write()?;
+
+        // CRITICAL SECTION
+        let old_buffer = old.try_unlock().map_err(|e| ClientError::Inner(format!("{:?}", e)))?;
+        let old_key = old_buffer
+            .borrow()
+            .deref()
+            .try_into()
+            .map_err(|_| ClientError::IllegalKeySize(32))?;
+
+        let upgraded = Snapshot::upgrade_kdf(
+            snapshot_path,
+            (old_key, old.associated_data()),
+            password.as_ref(),
+            params,
+        );
+        password.zeroize();
+        upgraded?;
+        // END CRITICAL SECTION
+
+        Ok(())
+    }
+
+    /// Loads the state of a [`Snapshot`] at `snapshot_path`, that has been written with
+    /// [`Self::commit_with_authenticator`]. The challenge is read from the header of the file, and
+    /// `authenticator` is asked for the key, e.g. by requiring a touch of a FIDO2 security key.
+    pub fn load_snapshot_with_authenticator<A>(
+        &self,
+        authenticator: &A,
+        snapshot_path: &SnapshotPath,
+    ) -> Result<(), ClientError>
+    where
+        A: HmacSecret + ?Sized,
+    {
+        if !snapshot_path.exists() {
+            let path = snapshot_path
+                .as_path()
+                .to_str()
+                .ok_or_else(|| ClientError::Inner("Cannot display path as string".to_string()))?;
+
+            return Err(ClientError::SnapshotFileMissing(path.to_string()));
+        }
+
+        let mut snapshot = self.snapshot.write()?;
+        let challenge = snapshot::challenge::read_challenge(snapshot_path.as_path()).map_err(SnapshotError::from)?;
+        let key = authenticator.hmac_secret(&challenge.credential_id, &challenge.salt)?;
+        let associated_data = self.associated_data.read()?;
+        *snapshot = Snapshot::read_from_snapshot_with_challenge(snapshot_path, key, &associated_data, None)?;
+        *self.snapshot_loaded.write()? = true;
+
+        Ok(())
+    }
+
+    /// Writes all client states into a [`Snapshot`] file, whose key is derived by `authenticator` from the
+    /// credential with `credential_id`, e.g. with the `hmac-secret` extension of a FIDO2 security key. A new
+    /// random salt is used for each commit, so `authenticator` is asked for the key every time.
+    ///
+    /// The credential id and the salt are stored in the header of the file, so the snapshot can be loaded
+    /// with [`Self::load_snapshot_with_authenticator`] and the same authenticator alone.
+    pub fn commit_with_authenticator<A>(
+        &self,
+        snapshot_path: &SnapshotPath,
+        authenticator: &A,
+        credential_id: &[u8],
+    ) -> Result<(), ClientError>
+    where
+        A: HmacSecret + ?Sized,
+    {
+        let start = Instant::now();
+        if !snapshot_path.exists() {
+            let path = snapshot_path.as_path().parent().ok_or_else(|| {
+                ClientError::SnapshotFileMissing("Parent directory of snapshot file does not exist".to_string())
+            })?;
+            if std::fs::create_dir_all(path).is_err() {
+                return Err(ClientError::SnapshotFileMissing(
+                    "Could not create snapshot file".to_string(),
+                ));
+            }
+        }
+
+        let challenge = Challenge::new(credential_id.to_vec()).map_err(SnapshotError::from)?;
+        let mut key = authenticator.hmac_secret(&challenge.credential_id, &challenge.salt)?;
+
+        let mut snapshot = self.snapshot.write()?;
+        let clients = self.clients.read()?;
+        let ids = self.default_client_ids(&clients)?;
+
+        for client_id in ids {
+            write_with_clientid!(client_id, snapshot, clients);
+        }
+
+        snapshot.set_associated_data(self.associated_data.read()?.clone());
+        let written = snapshot.write_to_snapshot_with_challenge(snapshot_path, &challenge, &key);
+        key.zeroize();
+        written?;
+
+        self.persisted(snapshot_path.as_path(), start)
+    }
+
     /// Creates a new, empty [`Client`]
     ///
     /// # Example
@@ -262,6 +750,8 @@ impl Stronghold {
         let client_id = ClientId::load_from_path(client_path.as_ref(), client_path.as_ref());
         let client = Client {
             id: client_id,
+            events: self.events.clone(),
+            metrics: self.metrics.clone(),
             ..Default::default()
         };
 
@@ -279,6 +769,22 @@ impl Stronghold {
         snapshot_path: &SnapshotPath,
         keyprovider: &KeyProvider,
     ) -> Result<(), ClientError> {
+        self.commit_with_keyprovider_and_progress(snapshot_path, keyprovider, |_| {})
+    }
+
+    /// Same as [`Self::commit_with_keyprovider`], but reports the [`Progress`] of serializing,
+    /// compressing, encrypting and writing the [`Snapshot`] to `progress`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %snapshot_path.as_path().display())))]
+    pub fn commit_with_keyprovider_and_progress<F>(
+        &self,
+        snapshot_path: &SnapshotPath,
+        keyprovider: &KeyProvider,
+        mut progress: F,
+    ) -> Result<(), ClientError>
+    where
+        F: FnMut(Progress),
+    {
+        let start = Instant::now();
         if !snapshot_path.exists() {
             let path = snapshot_path.as_path().parent().ok_or_else(|| {
                 ClientError::SnapshotFileMissing("Parent directory of snapshot file does not exist".to_string())
@@ -293,7 +799,7 @@ impl Stronghold {
         let mut snapshot = self.snapshot.write()?;
         let clients = self.clients.read()?;
 
-        let ids: Vec<ClientId> = clients.iter().map(|(id, _)| *id).collect();
+        let ids = self.default_client_ids(&clients)?;
 
         for client_id in ids {
             write_with_clientid!(client_id, snapshot, clients);
@@ -303,20 +809,45 @@ impl Stronghold {
         let buffer = keyprovider
             .try_unlock()
             .map_err(|e| ClientError::Inner(format!("{:?}", e)))?;
-        let buffer_ref = buffer.borrow();
-        let key = buffer_ref.deref();
+        let key = buffer
+            .borrow()
+            .deref()
+            .try_into()
+            .map_err(|_| ClientError::IllegalKeySize(32))?;
 
+        snapshot.set_associated_data(keyprovider.associated_data().to_vec());
+        snapshot.set_compression(*self.compression.read()?);
         snapshot
-            .write_to_snapshot(snapshot_path, UseKey::Key(key.try_into().unwrap()))
+            .write_to_snapshot_with_progress(snapshot_path, UseKey::Key(key), &mut progress)
             .map_err(|e| ClientError::Inner(e.to_string()))?;
 
+        self.persisted(snapshot_path.as_path(), start)
+    }
+
+    /// Sets the algorithm, that snapshot files are compressed with by [`Self::commit`] and
+    /// [`Self::commit_with_keyprovider`]. Defaults to [`Compression::Lz4`]. Snapshot files are always
+    /// loaded with the algorithm they have been written with.
+    pub fn set_snapshot_compression(&self, compression: Compression) -> Result<(), ClientError> {
+        *self.compression.write()? = compression;
+        Ok(())
+    }
+
+    /// Binds the snapshot files, that are protected by a password or an authenticator, to `associated_data`,
+    /// like [`KeyProvider::with_associated_data`] does for the snapshot files of a [`KeyProvider`]. It applies to
+    /// loading and committing with [`Self::commit_with_password`], [`Self::commit_with_password_key`],
+    /// [`Self::commit_to_bytes`] and [`Self::commit_with_authenticator`], and to the corresponding loads. Defaults
+    /// to no associated data.
+    pub fn set_snapshot_associated_data(&self, associated_data: Vec<u8>) -> Result<(), ClientError> {
+        *self.associated_data.write()? = associated_data;
         Ok(())
     }
 
     /// Writes all client states into the [`Snapshot`] file
     ///
     /// # Example
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %snapshot_path.as_path().display())))]
     pub fn commit(&self, snapshot_path: &SnapshotPath) -> Result<(), ClientError> {
+        let start = Instant::now();
         if !snapshot_path.exists() {
             let path = snapshot_path.as_path().parent().ok_or_else(|| {
                 ClientError::SnapshotFileMissing("Parent directory of snapshot file does not exist".to_string())
@@ -330,7 +861,7 @@ impl Stronghold {
 
         let mut snapshot = self.snapshot.write()?;
         let clients = self.clients.read()?;
-        let ids: Vec<ClientId> = clients.iter().map(|(id, _)| *id).collect();
+        let ids = self.default_client_ids(&clients)?;
 
         for client_id in ids {
             write_with_clientid!(client_id, snapshot, clients);
@@ -344,10 +875,176 @@ impl Stronghold {
             None => return Err(ClientError::SnapshotKeyLocationMissing),
         };
 
+        snapshot.set_compression(*self.compression.read()?);
         snapshot
             .write_to_snapshot(snapshot_path, UseKey::Stored(key_location.clone()))
             .map_err(|e| ClientError::Inner(e.to_string()))?;
 
+        self.persisted(snapshot_path.as_path(), start)
+    }
+
+    /// Writes all client states into the incremental snapshot file at `snapshot_path`, that is encrypted
+    /// with the key of `keyprovider`. Returns the number of client states that have been written.
+    ///
+    /// The first commit writes the state of all clients. Further commits to the same file with the same
+    /// key and associated data only serialize and append the state of the clients that have been modified
+    /// since the previous commit, and skip writing altogether, if nothing has changed. Once enough outdated
+    /// states have accumulated, the file is compacted.
+    ///
+    /// The incremental file format can not be read with [`Self::load_snapshot`], use
+    /// [`Self::load_incremental_snapshot`] instead.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %snapshot_path.as_path().display())))]
+    pub fn commit_incremental(
+        &self,
+        snapshot_path: &SnapshotPath,
+        keyprovider: &KeyProvider,
+    ) -> Result<usize, ClientError> {
+        let start = Instant::now();
+        if !snapshot_path.exists() {
+            let path = snapshot_path.as_path().parent().ok_or_else(|| {
+                ClientError::SnapshotFileMissing("Parent directory of snapshot file does not exist".to_string())
+            })?;
+            if let Err(io_error) = std::fs::create_dir_all(path) {
+                return Err(ClientError::SnapshotFileMissing(
+                    "Could not create snapshot file".to_string(),
+                ));
+            }
+        }
+
+        // CRITICAL SECTION
+        let buffer = keyprovider
+            .try_unlock()
+            .map_err(|e| ClientError::Inner(format!("{:?}", e)))?;
+        let buffer_ref = buffer.borrow();
+        let key: snapshot::Key = buffer_ref
+            .deref()
+            .try_into()
+            .map_err(|_| ClientError::Inner("Invalid snapshot key length".to_string()))?;
+
+        let mut incremental = self.incremental.lock()?;
+        let snapshot = self.snapshot.read()?;
+        let clients = self.clients.read()?;
+
+        let ids = self.default_client_ids(&clients)?;
+        let mut revisions: HashMap<ClientId, u64> = ids.iter().map(|id| (*id, clients[id].revision())).collect();
+        let mut live: Vec<ClientId> = snapshot.client_ids();
+        live.extend(ids.iter().filter(|id| !snapshot.has_data(**id)));
+
+        let mut state = match incremental.take() {
+            Some(state)
+                if state.path == snapshot_path.as_path()
+                    && state.snapshot.key_matches(&key)
+                    && state.snapshot.associated_data() == keyprovider.associated_data() =>
+            {
+                state
+            }
+            _ => {
+                let mut partitions = serialize_client_states(&live, &snapshot, &clients, &mut revisions)?;
+                let created = IncrementalSnapshot::create(
+                    snapshot_path.as_path(),
+                    &key,
+                    keyprovider.associated_data(),
+                    &partitions,
+                );
+                partitions.values_mut().for_each(|partition| partition.zeroize());
+                let created = created.map_err(|e| ClientError::Inner(e.to_string()))?;
+
+                *incremental = Some(IncrementalState {
+                    path: snapshot_path.as_path().to_path_buf(),
+                    snapshot: created,
+                    persisted: revisions,
+                });
+                self.persisted(snapshot_path.as_path(), start)?;
+                return Ok(partitions.len());
+            }
+        };
+
+        let changed: Vec<ClientId> = revisions
+            .iter()
+            .filter(|(id, revision)| state.persisted.get(*id) != Some(*revision))
+            .map(|(id, _)| *id)
+            .collect();
+        let live_ids = live.iter().map(partition_id).collect::<Result<HashSet<_>, _>>()?;
+        let removed: Vec<Vec<u8>> = state
+            .snapshot
+            .partitions()
+            .into_iter()
+            .filter(|id| !live_ids.contains(id))
+            .collect();
+
+        if changed.is_empty() && removed.is_empty() {
+            *incremental = Some(state);
+            return Ok(0);
+        }
+
+        let mut partitions = serialize_client_states(&changed, &snapshot, &clients, &mut revisions)?;
+        let written = state.snapshot.update(&partitions, &removed);
+        partitions.values_mut().for_each(|partition| partition.zeroize());
+        written.map_err(|e| ClientError::Inner(e.to_string()))?;
+
+        if state.snapshot.needs_compaction() {
+            let mut partitions = serialize_client_states(&live, &snapshot, &clients, &mut revisions)?;
+            let compacted = state.snapshot.compact(&partitions);
+            partitions.values_mut().for_each(|partition| partition.zeroize());
+            compacted.map_err(|e| ClientError::Inner(e.to_string()))?;
+        }
+
+        state.persisted = revisions;
+        *incremental = Some(state);
+        self.persisted(snapshot_path.as_path(), start)?;
+
+        Ok(changed.len())
+    }
+
+    /// Loads the client states of an incremental snapshot file, that has been written with
+    /// [`Self::commit_incremental`]. The clients can then be loaded with [`Self::load_client`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %snapshot_path.as_path().display())))]
+    pub fn load_incremental_snapshot(
+        &self,
+        keyprovider: &KeyProvider,
+        snapshot_path: &SnapshotPath,
+    ) -> Result<(), ClientError> {
+        if !snapshot_path.exists() {
+            let path = snapshot_path
+                .as_path()
+                .to_str()
+                .ok_or_else(|| ClientError::Inner("Cannot display path as string".to_string()))?;
+
+            return Err(ClientError::SnapshotFileMissing(path.to_string()));
+        }
+
+        // CRITICAL SECTION
+        let buffer = keyprovider
+            .try_unlock()
+            .map_err(|e| ClientError::Inner(format!("{:?}", e)))?;
+        let buffer_ref = buffer.borrow();
+        let key: snapshot::Key = buffer_ref
+            .deref()
+            .try_into()
+            .map_err(|_| ClientError::Inner("Invalid snapshot key length".to_string()))?;
+
+        let (opened, mut partitions) =
+            IncrementalSnapshot::open(snapshot_path.as_path(), &key, keyprovider.associated_data())
+                .map_err(|e| ClientError::Inner(e.to_string()))?;
+
+        let mut loaded = Snapshot::default();
+        let result = partitions.iter().try_for_each(|(id, partition)| {
+            let client_id: ClientId = bincode::deserialize(id).map_err(|e| ClientError::Inner(e.to_string()))?;
+            let client_state: ClientState =
+                bincode::deserialize(partition).map_err(|e| ClientError::Inner(e.to_string()))?;
+            loaded.add_data(client_id, client_state).map_err(ClientError::from)
+        });
+        partitions.values_mut().for_each(|partition| partition.zeroize());
+        result?;
+
+        *self.snapshot.write()? = loaded;
+        *self.snapshot_loaded.write()? = true;
+        *self.incremental.lock()? = Some(IncrementalState {
+            path: snapshot_path.as_path().to_path_buf(),
+            snapshot: opened,
+            persisted: HashMap::new(),
+        });
+
         Ok(())
     }
 
@@ -360,12 +1057,336 @@ impl Stronghold {
     {
         let mut snapshot = self.snapshot.write()?;
         let clients = self.clients.read()?;
+        let mut named_snapshots = self.named_snapshots.write()?;
+
+        let client_id = ClientId::load_from_path(client_path.as_ref(), client_path.as_ref());
+        match named_snapshots
+            .values_mut()
+            .find(|named| named.clients.contains(&client_id))
+        {
+            Some(named) => write_with_clientid!(client_id, named.snapshot, clients),
+            None => write_with_clientid!(client_id, snapshot, clients),
+        }
+        Ok(())
+    }
+
+    /// Exports the vaults at `vault_paths` of the clients at `client_paths` into a new snapshot, that is
+    /// protected by `password`, and returns the content of the snapshot file. The state of loaded clients
+    /// is written into the [`Snapshot`] first, or into the named snapshot, that they are routed to. All clients
+    /// have to belong to the same snapshot. See [`Snapshot::export_partial`].
+    pub fn export_partial<C, V>(
+        &self,
+        client_paths: &[C],
+        vault_paths: &[V],
+        password: &[u8],
+        params: &Argon2Params,
+    ) -> Result<Vec<u8>, ClientError>
+    where
+        C: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let mut snapshot = self.snapshot.write()?;
+        let clients = self.clients.read()?;
+        let mut named_snapshots = self.named_snapshots.write()?;
+
+        let ids: Vec<ClientId> = client_paths
+            .iter()
+            .map(|path| ClientId::load_from_path(path.as_ref(), path.as_ref()))
+            .collect();
+        let routes: Vec<Option<String>> = ids
+            .iter()
+            .map(|id| {
+                named_snapshots
+                    .iter()
+                    .find(|(_, named)| named.clients.contains(id))
+                    .map(|(name, _)| name.clone())
+            })
+            .collect();
+        if routes.windows(2).any(|pair| pair[0] != pair[1]) {
+            return Err(ClientError::Inner(
+                "Clients of different snapshots can't be exported together".to_string(),
+            ));
+        }
+        let target = match routes.first().cloned().flatten() {
+            Some(name) => &mut named_snapshots.get_mut(&name).expect("Named snapshot is open").snapshot,
+            None => &mut *snapshot,
+        };
+
+        for client_id in ids {
+            if clients.contains_key(&client_id) {
+                write_with_clientid!(client_id, target, clients);
+            }
+        }
+
+        target
+            .export_partial(client_paths, vault_paths, password, params)
+            .map_err(|e| ClientError::Inner(e.to_string()))
+    }
+
+    /// Opens the snapshot file at `snapshot_path` under `name`, next to the default [`Snapshot`]. The file
+    /// is decrypted with the key of `keyprovider`, which is kept to encrypt the file again in
+    /// [`Self::commit_named`]. If the file does not exist yet, the named snapshot starts out empty.
+    ///
+    /// Clients are routed to a named snapshot with [`Self::load_named_client`] or
+    /// [`Self::create_named_client`]. Those clients are only persisted into their named snapshot, and never
+    /// into the default one.
+    pub fn open_named_snapshot(
+        &self,
+        name: &str,
+        keyprovider: KeyProvider,
+        snapshot_path: &SnapshotPath,
+    ) -> Result<(), ClientError> {
+        let mut named_snapshots = self.named_snapshots.write()?;
+        if named_snapshots.contains_key(name) {
+            return Err(ClientError::SnapshotAlreadyOpen(name.to_string()));
+        }
+
+        let mut snapshot = Snapshot::default();
+        if snapshot_path.exists() {
+            load_snapshot!(&mut snapshot, snapshot_path, &keyprovider);
+        }
+        snapshot.set_associated_data(keyprovider.associated_data().to_vec());
+
+        named_snapshots.insert(
+            name.to_string(),
+            NamedSnapshot {
+                snapshot,
+                path: snapshot_path.clone(),
+                keyprovider,
+                clients: HashSet::new(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Closes the snapshot `name` without persisting it, and unloads all [`Client`]s, that have been
+    /// routed to it. Use [`Self::commit_named`] first to keep their state.
+    pub fn close_named_snapshot(&self, name: &str) -> Result<(), ClientError> {
+        let mut clients = self.clients.write()?;
+        let named = self
+            .named_snapshots
+            .write()?
+            .remove(name)
+            .ok_or_else(|| ClientError::SnapshotNotOpen(name.to_string()))?;
+        for client_id in &named.clients {
+            if let Some(client) = clients.remove(client_id) {
+                client.clear()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the names of all open named snapshots
+    pub fn named_snapshots(&self) -> Result<Vec<String>, ClientError> {
+        Ok(self.named_snapshots.read()?.keys().cloned().collect())
+    }
+
+    /// Loads the [`Client`] at `client_path` from the snapshot `name`, and routes it to that snapshot.
+    ///
+    /// The function returns an error if the client path is not in the snapshot or a client with the same id
+    /// has already been loaded before.
+    pub fn load_named_client<P>(&self, name: &str, client_path: P) -> Result<Client, ClientError>
+    where
+        P: AsRef<[u8]>,
+    {
+        let client_id = ClientId::load_from_path(client_path.as_ref(), client_path.as_ref());
+        let mut client = Client {
+            events: self.events.clone(),
+            metrics: self.metrics.clone(),
+            ..Default::default()
+        };
+
+        let mut clients = self.clients.write()?;
+        let mut named_snapshots = self.named_snapshots.write()?;
+        let named = named_snapshots
+            .get_mut(name)
+            .ok_or_else(|| ClientError::SnapshotNotOpen(name.to_string()))?;
+
+        if clients.contains_key(&client_id) {
+            return Err(ClientError::ClientAlreadyLoaded(client_id));
+        }
+        if !named.snapshot.has_data(client_id) {
+            return Err(ClientError::ClientDataNotPresent);
+        }
+
+        let client_state: ClientState = named
+            .snapshot
+            .get_state(client_id)
+            .map_err(|e| ClientError::Inner(e.to_string()))?;
+        client.restore(client_state, client_id)?;
+
+        named.clients.insert(client_id);
+        clients.insert(client_id, client.clone());
+
+        Ok(client)
+    }
 
+    /// Creates a new, empty [`Client`], that is routed to the snapshot `name`. Fails with
+    /// [`ClientError::ClientAlreadyLoaded`], if a client with the same id has been loaded or created before.
+    pub fn create_named_client<P>(&self, name: &str, client_path: P) -> Result<Client, ClientError>
+    where
+        P: AsRef<[u8]>,
+    {
         let client_id = ClientId::load_from_path(client_path.as_ref(), client_path.as_ref());
-        write_with_clientid!(client_id, snapshot, clients);
+        let client = Client {
+            id: client_id,
+            events: self.events.clone(),
+            metrics: self.metrics.clone(),
+            ..Default::default()
+        };
+
+        let mut clients = self.clients.write()?;
+        let mut named_snapshots = self.named_snapshots.write()?;
+        let named = named_snapshots
+            .get_mut(name)
+            .ok_or_else(|| ClientError::SnapshotNotOpen(name.to_string()))?;
+
+        if clients.contains_key(&client_id) {
+            return Err(ClientError::ClientAlreadyLoaded(client_id));
+        }
+        named.clients.insert(client_id);
+        clients.insert(client_id, client.clone());
+
+        Ok(client)
+    }
+
+    /// Writes the state of all [`Client`]s, that are routed to the snapshot `name`, into it and persists
+    /// it into its file with the key it has been opened with.
+    pub fn commit_named(&self, name: &str) -> Result<(), ClientError> {
+        let start = Instant::now();
+        let clients = self.clients.read()?;
+        let mut named_snapshots = self.named_snapshots.write()?;
+        let named = named_snapshots
+            .get_mut(name)
+            .ok_or_else(|| ClientError::SnapshotNotOpen(name.to_string()))?;
+
+        if !named.path.exists() {
+            let path = named.path.as_path().parent().ok_or_else(|| {
+                ClientError::SnapshotFileMissing("Parent directory of snapshot file does not exist".to_string())
+            })?;
+            if let Err(io_error) = std::fs::create_dir_all(path) {
+                return Err(ClientError::SnapshotFileMissing(
+                    "Could not create snapshot file".to_string(),
+                ));
+            }
+        }
+
+        let ids: Vec<ClientId> = named
+            .clients
+            .iter()
+            .filter(|id| clients.contains_key(id))
+            .copied()
+            .collect();
+        for client_id in ids {
+            write_with_clientid!(client_id, named.snapshot, clients);
+        }
+
+        // CRITICAL SECTION
+        let buffer = named
+            .keyprovider
+            .try_unlock()
+            .map_err(|e| ClientError::Inner(format!("{:?}", e)))?;
+        let key = buffer
+            .borrow()
+            .deref()
+            .try_into()
+            .map_err(|_| ClientError::IllegalKeySize(32))?;
+
+        named.snapshot.set_compression(*self.compression.read()?);
+        named
+            .snapshot
+            .write_to_snapshot(&named.path, UseKey::Key(key))
+            .map_err(|e| ClientError::Inner(e.to_string()))?;
+
+        self.persisted(named.path.as_path(), start)
+    }
+
+    /// Records a successful commit into the snapshot file at `path`, that has been started at `start`
+    fn persisted(&self, path: &Path, start: Instant) -> Result<(), ClientError> {
+        self.metrics.snapshot_commit(start.elapsed());
+        self.last_persist.write()?.replace(SystemTime::now());
+        self.events.emit(Event::SnapshotCommitted {
+            path: path.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    /// Sets the policy, that limits the rate of failed attempts to load a snapshot file with
+    /// [`Self::load_snapshot`], [`Self::load_client_from_snapshot`] or [`Self::load_snapshot_with_password`].
+    /// Disabled with `None`, which is the default.
+    ///
+    /// The failed attempts are counted in a file next to the snapshot. While throttled, loading fails with
+    /// [`ClientError::UnlockThrottled`], and each failed attempt with [`ClientError::UnlockFailed`]. Only a key,
+    /// that fails to decrypt the snapshot, counts as a failed attempt; other errors are returned unchanged.
+    pub fn set_unlock_throttle(&self, policy: Option<ThrottlePolicy>) -> Result<(), ClientError> {
+        *self.unlock_throttle.write()? = policy;
+        Ok(())
+    }
+
+    /// Returns the failed attempts to load the snapshot file at `snapshot_path` since it has last been loaded
+    pub fn unlock_attempts(&self, snapshot_path: &SnapshotPath) -> Result<Attempts, ClientError> {
+        Ok(throttle::read_attempts(snapshot_path.as_path()).map_err(SnapshotError::from)?)
+    }
+
+    /// Runs `unlock` on the snapshot file at `snapshot_path` under the unlock throttle, if one is set.
+    /// Returns the result together with the failed attempts before it.
+    fn throttled<T, F>(&self, snapshot_path: &SnapshotPath, unlock: F) -> Result<(T, Attempts), ClientError>
+    where
+        F: FnOnce() -> Result<T, ClientError>,
+    {
+        let policy = match *self.unlock_throttle.read()? {
+            Some(policy) => policy,
+            None => return unlock().map(|unlocked| (unlocked, Attempts::default())),
+        };
+        let path = snapshot_path.as_path();
+        let attempts = policy.check(path)?;
+        match unlock() {
+            Ok(unlocked) => {
+                throttle::reset(path).map_err(SnapshotError::from)?;
+                Ok((unlocked, attempts))
+            }
+            // only a key, that doesn't decrypt the snapshot, counts as a failed attempt, and not e.g. I/O errors
+            Err(e @ ClientError::SnapshotDecryption(_)) => {
+                let failed = throttle::record_failure(path)?;
+                Err(ClientError::UnlockFailed {
+                    failed_attempts: failed.failed,
+                    reason: e.to_string(),
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Re-encrypts the password protected snapshot file at `snapshot_path` with raised key derivation
+    /// parameters, if the unlock throttle asks for it after `attempts`
+    fn escalate_kdf(
+        &self,
+        snapshot: &Snapshot,
+        snapshot_path: &SnapshotPath,
+        password: &[u8],
+        attempts: &Attempts,
+    ) -> Result<(), ClientError> {
+        let policy = match *self.unlock_throttle.read()? {
+            Some(policy) if policy.should_escalate(attempts) => policy,
+            _ => return Ok(()),
+        };
+        let params = snapshot::kdf::read_params(snapshot_path.as_path()).map_err(SnapshotError::from)?;
+        if let Some(params) = policy.escalate(&params) {
+            snapshot.write_to_snapshot_with_password(snapshot_path, password, &params)?;
+        }
         Ok(())
     }
 
+    /// Returns the ids of the loaded clients, that are not routed to a named snapshot
+    fn default_client_ids(&self, clients: &HashMap<ClientId, Client>) -> Result<Vec<ClientId>, ClientError> {
+        let named_snapshots = self.named_snapshots.read()?;
+        Ok(clients
+            .keys()
+            .filter(|id| !named_snapshots.values().any(|named| named.clients.contains(id)))
+            .copied()
+            .collect())
+    }
+
     /// Calling this function clears the runtime state of all [`Client`]s and the in-memory
     /// [`Snapshot`] state. This does not affect the persisted [`Client`] state inside a
     /// snapshot file. Use [`Self::load_client_from_snapshot`] to reload any [`Client`] and
@@ -375,9 +1396,190 @@ impl Stronghold {
         let mut clients = self.clients.write()?;
         self.store.clear()?;
         self.key_location.write()?.take();
+        *self.snapshot_loaded.write()? = false;
+        self.incremental.lock()?.take();
+        self.named_snapshots.write()?.clear();
         for (_, client) in clients.drain() {
             client.clear()?;
         }
         Ok(())
     }
+
+    /// Shuts this instance down: persists the state of all [`Client`]s as configured by `config`, and
+    /// afterwards clears all runtime state like [`Self::clear`], which zeroizes the guarded memory of
+    /// all keys and secrets. Returns once both steps have completed.
+    ///
+    /// Procedures that are still executed are either awaited or aborted, depending on `config`, and new
+    /// procedures fail with [`ProcedureError::ShuttingDown`] from now on. Open named snapshots are committed as
+    /// well, unless nothing is persisted. If procedures are still executed after the timeout of `config`, or
+    /// persisting fails, the state is kept and procedures can be executed again, so that shutting down can be
+    /// retried.
+    ///
+    /// Embedding services should call this from their termination handlers instead of relying on the
+    /// order in which the instance and its clients are dropped.
+    pub fn shutdown(&self, config: ShutdownConfig) -> Result<(), ClientError> {
+        // no new procedures are started, so that the awaited ones are the last ones before persisting
+        self.set_shutting_down(true)?;
+        let result = self.await_and_persist(&config);
+        if result.is_err() {
+            self.set_shutting_down(false)?;
+        }
+        result?;
+
+        self.clear()
+    }
+
+    /// Sets the flag of all loaded clients, that rejects new procedures while shutting down
+    fn set_shutting_down(&self, shutting_down: bool) -> Result<(), ClientError> {
+        for client in self.clients.read()?.values() {
+            client.operations.write()?.set_shutting_down(shutting_down);
+        }
+        Ok(())
+    }
+
+    /// Awaits or aborts the running procedures and persists the state, as configured by `config`
+    fn await_and_persist(&self, config: &ShutdownConfig) -> Result<(), ClientError> {
+        if config.abort_operations {
+            for client in self.clients.read()?.values() {
+                for operation in client.list_operations()? {
+                    client.abort(operation.id)?;
+                }
+            }
+        }
+        self.await_operations(config.timeout)?;
+
+        match &config.persist {
+            Persist::None => {}
+            Persist::KeyProvider(snapshot_path, keyprovider) => {
+                self.commit_with_keyprovider(snapshot_path, keyprovider)?
+            }
+            Persist::StoredKey(snapshot_path) => self.commit(snapshot_path)?,
+            Persist::Incremental(snapshot_path, keyprovider) => {
+                self.commit_incremental(snapshot_path, keyprovider)?;
+            }
+        }
+        if !matches!(config.persist, Persist::None) {
+            for name in self.named_snapshots()? {
+                self.commit_named(&name)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits until none of the loaded clients executes a procedure anymore. Fails with
+    /// [`ClientError::ShutdownTimeout`], if procedures are still executed after `timeout`.
+    fn await_operations(&self, timeout: Duration) -> Result<(), ClientError> {
+        let start = Instant::now();
+        loop {
+            let mut running = false;
+            for client in self.clients.read()?.values() {
+                running |= !client.list_operations()?.is_empty();
+            }
+            if !running {
+                return Ok(());
+            }
+            if start.elapsed() >= timeout {
+                return Err(ClientError::ShutdownTimeout(timeout));
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Returns the current [`Health`] status of this instance
+    ///
+    /// The call fails, if any of the internal locks has been poisoned.
+    pub fn health(&self) -> Result<Health, ClientError> {
+        let clients = self.clients.read()?;
+
+        let mut vaults = 0;
+        let mut pending_gc = 0;
+        for client in clients.values() {
+            let db = client.db.read()?;
+            vaults += db.list_vaults().len();
+            pending_gc += db.pending_garbage();
+        }
+
+        Ok(Health {
+            snapshot_loaded: *self.snapshot_loaded.read()?,
+            clients: clients.len(),
+            vaults,
+            memory_lock_available: engine::runtime::utils::memory_lock_available(),
+            last_persist: *self.last_persist.read()?,
+            pending_gc,
+        })
+    }
+
+    /// Returns the [`Metrics`] of the Stronghold and all of its clients, that have been collected since the
+    /// Stronghold has been created. Enable the `prometheus` feature to export them with
+    /// [`Metrics::to_prometheus`].
+    ///
+    /// # Example
+    /// ```
+    /// use iota_stronghold::{
+    ///     procedures::{GenerateKey, KeyType},
+    ///     Location, Stronghold,
+    /// };
+    ///
+    /// let stronghold = Stronghold::default();
+    /// let client = stronghold.create_client(b"client").unwrap();
+    /// let output = Location::const_generic(b"vault".to_vec(), b"key".to_vec());
+    /// client
+    ///     .execute_procedure(GenerateKey {
+    ///         ty: KeyType::Ed25519,
+    ///         output,
+    ///     })
+    ///     .unwrap();
+    ///
+    /// let metrics = stronghold.metrics().unwrap();
+    /// assert_eq!(metrics.procedures.get("GenerateKey"), Some(&1));
+    /// assert_eq!(metrics.vault_writes.count, 1);
+    /// ```
+    pub fn metrics(&self) -> Result<Metrics, ClientError> {
+        Ok(self.metrics.snapshot())
+    }
+}
+
+/// Returns the id of the partition in an incremental snapshot, that holds the state of client `id`
+fn partition_id(id: &ClientId) -> Result<Vec<u8>, ClientError> {
+    bincode::serialize(id).map_err(|e| ClientError::Inner(e.to_string()))
+}
+
+/// Serializes the states of the clients with `ids` into partitions of an incremental snapshot. The state of a
+/// loaded [`Client`] takes precedence over the state in the [`Snapshot`]. For a locked client the state in the
+/// [`Snapshot`] is written, if there is one, and its revision is removed from `revisions`, so that it is
+/// written again after it has been unlocked.
+fn serialize_client_states(
+    ids: &[ClientId],
+    snapshot: &Snapshot,
+    clients: &HashMap<ClientId, Client>,
+    revisions: &mut HashMap<ClientId, u64>,
+) -> Result<HashMap<Vec<u8>, Vec<u8>>, ClientError> {
+    let mut partitions = HashMap::new();
+    for id in ids {
+        let keystore = match clients.get(id) {
+            Some(client) => client.keystore_unless_locked()?.map(|keystore| (client, keystore)),
+            None => None,
+        };
+        let state: ClientState = match keystore {
+            Some((client, mut keystore)) => {
+                let mut keys = keystore.get_data();
+                let mut db = client.db.read()?.clone();
+                client.persist_usage(&mut keys, &mut db)?;
+                let store = client.store.cache.read()?.clone();
+                (keys, db, store)
+            }
+            None => {
+                if clients.contains_key(id) {
+                    revisions.remove(id);
+                }
+                if !snapshot.has_data(*id) {
+                    continue;
+                }
+                snapshot.get_state(*id)?
+            }
+        };
+        let serialized = bincode::serialize(&state).map_err(|e| ClientError::Inner(e.to_string()))?;
+        partitions.insert(partition_id(id)?, serialized);
+    }
+    Ok(partitions)
 }
@@ -0,0 +1,81 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Groups of vault and store mutations, that are applied all at once, see
+//! [`Client::transaction`](crate::Client::transaction).
+//!
+//! The mutations are only collected while the closure of the transaction runs. They are applied afterwards with
+//! the keystore, the vaults and the store of the client locked, so other threads and snapshot commits either see
+//! all of them, or none.
+
+use std::time::Duration;
+
+use zeroize::Zeroize;
+
+use crate::Location;
+
+pub(crate) enum Mutation {
+    WriteVault {
+        location: Location,
+        data: Vec<u8>,
+    },
+    Delete(Location),
+    WriteStore {
+        key: Vec<u8>,
+        value: Vec<u8>,
+        lifetime: Option<Duration>,
+    },
+    DeleteStore(Vec<u8>),
+}
+
+/// The mutations of a [`Client::transaction`](crate::Client::transaction), in the order in which they are
+/// applied
+#[derive(Default)]
+pub struct Transaction {
+    pub(crate) mutations: Vec<Mutation>,
+}
+
+impl Transaction {
+    /// Writes `data` into the vault at `location`, like [`ClientVault::write_secret`](crate::ClientVault::write_secret)
+    pub fn write_vault(&mut self, location: Location, data: Vec<u8>) -> &mut Self {
+        self.mutations.push(Mutation::WriteVault { location, data });
+        self
+    }
+
+    /// Revokes the record at `location`. A record, that doesn't exist, is ignored.
+    pub fn delete(&mut self, location: Location) -> &mut Self {
+        self.mutations.push(Mutation::Delete(location));
+        self
+    }
+
+    /// Inserts `value` into the [`Store`](crate::Store) of the client, like [`Store::insert`](crate::Store::insert)
+    pub fn write_store(&mut self, key: Vec<u8>, value: Vec<u8>, lifetime: Option<Duration>) -> &mut Self {
+        self.mutations.push(Mutation::WriteStore { key, value, lifetime });
+        self
+    }
+
+    /// Deletes the entry with `key` from the [`Store`](crate::Store) of the client
+    pub fn delete_store(&mut self, key: Vec<u8>) -> &mut Self {
+        self.mutations.push(Mutation::DeleteStore(key));
+        self
+    }
+
+    /// Returns the number of collected mutations
+    pub fn len(&self) -> usize {
+        self.mutations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mutations.is_empty()
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        for mutation in self.mutations.iter_mut() {
+            if let Mutation::WriteVault { data, .. } = mutation {
+                data.zeroize();
+            }
+        }
+    }
+}
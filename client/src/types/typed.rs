@@ -0,0 +1,201 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Typed values in the [`Store`](crate::Store).
+//!
+//! A value is encoded with a [`Codec`] and prefixed with a header, that names the codec and the type of the
+//! value:
+//!
+//! ```text
+//! | codec: u8 | tag length: u8 | tag | version: u32 | encoded value |
+//! ```
+//!
+//! The version is big endian. Reading a value checks the tag and the version against the expected
+//! [`TypedValue`], so values written by incompatible versions of an application are reported as
+//! [`ClientError::TypeMismatch`] instead of being decoded into garbage.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::ClientError;
+
+/// A type, that can be stored with [`Store::insert_typed`](crate::Store::insert_typed)
+pub trait TypedValue: Serialize + DeserializeOwned {
+    /// Name of the type, that is stored with each value. It should stay the same across versions of the
+    /// application, e.g. `wallet.account`. At most 255 bytes long.
+    const TAG: &'static str;
+
+    /// Version of the type. It has to be increased with each incompatible change of the type.
+    const VERSION: u32;
+}
+
+/// Encoding of [`TypedValue`]s
+pub trait Codec {
+    /// Identifier of the codec in the header of the encoded values. Identifiers below 128 are reserved for the
+    /// codecs of this crate.
+    const ID: u8;
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, ClientError>;
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ClientError>;
+}
+
+/// The encoding of [`bincode`]. Compact, but not self-describing.
+pub struct Bincode;
+
+impl Codec for Bincode {
+    const ID: u8 = 1;
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, ClientError> {
+        bincode::serialize(value).map_err(|e| ClientError::TypedEncoding(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ClientError> {
+        bincode::deserialize(bytes).map_err(|e| ClientError::TypedEncoding(e.to_string()))
+    }
+}
+
+/// JSON, readable by other languages and tools
+#[cfg(feature = "json")]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl Codec for Json {
+    const ID: u8 = 2;
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, ClientError> {
+        serde_json::to_vec(value).map_err(|e| ClientError::TypedEncoding(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ClientError> {
+        serde_json::from_slice(bytes).map_err(|e| ClientError::TypedEncoding(e.to_string()))
+    }
+}
+
+/// CBOR (RFC 8949), a compact and self-describing binary encoding
+#[cfg(feature = "cbor")]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Codec for Cbor {
+    const ID: u8 = 3;
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, ClientError> {
+        let mut buf = Vec::new();
+        ciborium::ser::into_writer(value, &mut buf).map_err(|e| ClientError::TypedEncoding(e.to_string()))?;
+        Ok(buf)
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ClientError> {
+        ciborium::de::from_reader(bytes).map_err(|e| ClientError::TypedEncoding(e.to_string()))
+    }
+}
+
+/// Encodes `value` with `C`, and prefixes it with the header.
+pub(crate) fn encode<C: Codec, T: TypedValue>(value: &T) -> Result<Vec<u8>, ClientError> {
+    let tag = T::TAG.as_bytes();
+    if tag.len() > u8::MAX as usize {
+        return Err(ClientError::TypedEncoding(format!("Type tag {} is too long", T::TAG)));
+    }
+    let encoded = C::encode(value)?;
+
+    let mut buf = Vec::with_capacity(2 + tag.len() + 4 + encoded.len());
+    buf.push(C::ID);
+    buf.push(tag.len() as u8);
+    buf.extend_from_slice(tag);
+    buf.extend_from_slice(&T::VERSION.to_be_bytes());
+    buf.extend_from_slice(&encoded);
+    Ok(buf)
+}
+
+/// Checks the header of `bytes` and decodes the value with `C`. Fails, if the value has been encoded with
+/// another codec.
+pub(crate) fn decode_with<C: Codec, T: TypedValue>(bytes: &[u8]) -> Result<T, ClientError> {
+    let (codec, value) = split_header::<T>(bytes)?;
+    if codec != C::ID {
+        return Err(ClientError::TypedEncoding(format!(
+            "Value has been encoded with codec {}, expected codec {}",
+            codec,
+            C::ID
+        )));
+    }
+    C::decode(value)
+}
+
+/// Checks the header of `bytes` and decodes the value with the codec of this crate, that it names.
+pub(crate) fn decode<T: TypedValue>(bytes: &[u8]) -> Result<T, ClientError> {
+    let (codec, value) = split_header::<T>(bytes)?;
+    match codec {
+        codec if codec == Bincode::ID => Bincode::decode(value),
+        #[cfg(feature = "json")]
+        codec if codec == Json::ID => Json::decode(value),
+        #[cfg(feature = "cbor")]
+        codec if codec == Cbor::ID => Cbor::decode(value),
+        codec => Err(ClientError::TypedEncoding(format!("Unsupported codec {}", codec))),
+    }
+}
+
+/// Returns the codec and the encoded value, after checking the type tag and the version of the header
+fn split_header<T: TypedValue>(bytes: &[u8]) -> Result<(u8, &[u8]), ClientError> {
+    let truncated = || ClientError::TypedEncoding("Typed value is truncated".into());
+    let (&codec, rest) = bytes.split_first().ok_or_else(truncated)?;
+    let (&tag_len, rest) = rest.split_first().ok_or_else(truncated)?;
+    if rest.len() < tag_len as usize + 4 {
+        return Err(truncated());
+    }
+    let (tag, rest) = rest.split_at(tag_len as usize);
+    let (version, value) = rest.split_at(4);
+    let version = u32::from_be_bytes(version.try_into().expect("slice with incorrect length"));
+
+    if tag != T::TAG.as_bytes() || version != T::VERSION {
+        return Err(ClientError::TypeMismatch {
+            expected: format!("{} v{}", T::TAG, T::VERSION),
+            found: format!("{} v{}", String::from_utf8_lossy(tag), version),
+        });
+    }
+    Ok((codec, value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Account {
+        name: String,
+        index: u32,
+    }
+
+    impl TypedValue for Account {
+        const TAG: &'static str = "test.account";
+        const VERSION: u32 = 1;
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct AccountV2 {
+        name: String,
+        index: u64,
+    }
+
+    impl TypedValue for AccountV2 {
+        const TAG: &'static str = "test.account";
+        const VERSION: u32 = 2;
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let account = Account {
+            name: "main".into(),
+            index: 3,
+        };
+        let encoded = encode::<Bincode, _>(&account).unwrap();
+        assert_eq!(decode::<Account>(&encoded).unwrap(), account);
+        assert_eq!(decode_with::<Bincode, Account>(&encoded).unwrap(), account);
+
+        assert!(matches!(
+            decode::<AccountV2>(&encoded),
+            Err(ClientError::TypeMismatch { .. })
+        ));
+        assert!(decode::<Account>(&encoded[..3]).is_err());
+    }
+}
@@ -0,0 +1,318 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Counts how often procedures use the records of a client, and limits the use of single records.
+//!
+//! A record is used, whenever a procedure reads it as a source, e.g. to sign with a private key, and the
+//! procedure succeeds. The counters are persisted with the state of the client, the policies are settings of
+//! the client and not persisted in snapshots.
+
+use engine::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use engine::vault::{RecordId, VaultId};
+
+/// How often a record has been used by procedures, see [`Client::usage`](crate::Client::usage)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// Number of successful uses
+    pub count: u64,
+
+    /// Time of the last use
+    pub last_used: Option<SystemTime>,
+}
+
+/// Limits the use of a record, see [`Client::set_usage_policy`](crate::Client::set_usage_policy). The default
+/// policy doesn't limit anything.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UsagePolicy {
+    /// Maximum number of uses
+    pub max_uses: Option<u64>,
+
+    /// Maximum number of uses within any window of the given duration
+    pub rate_limit: Option<(u32, Duration)>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct UsageTracker {
+    usage: HashMap<(VaultId, RecordId), Usage>,
+    policies: HashMap<(VaultId, RecordId), UsagePolicy>,
+
+    // The uses, that have been reserved by procedures, that are still executed
+    pending: HashMap<(VaultId, RecordId), u64>,
+
+    // The times of the uses within the window of the rate limit
+    recent: HashMap<(VaultId, RecordId), VecDeque<Instant>>,
+}
+
+/// Reserved id of the key and the vault in the persisted state of a client, that hold the usage counters
+/// of the records, see [`Client::persist_usage`](crate::Client::persist_usage). The vault is not part of
+/// the synchronization of snapshots.
+pub(crate) fn usage_vault_id() -> VaultId {
+    VaultId::load(&[0xfe; 24]).expect("VaultId of 24 bytes is valid")
+}
+
+/// Reserved id of the record with the usage counters in the vault with [`usage_vault_id`]
+pub(crate) fn usage_record_id() -> RecordId {
+    RecordId::load(&[0xfe; 24]).expect("RecordId of 24 bytes is valid")
+}
+
+/// The counter of a record in the state of a client, see [`UsageTracker::serialize`]
+#[derive(Serialize, Deserialize)]
+struct PersistedUsage {
+    vault_id: VaultId,
+    record_id: RecordId,
+    count: u64,
+    // seconds since the unix epoch
+    last_used: Option<u64>,
+}
+
+impl UsageTracker {
+    pub(crate) fn usage(&self, vault_id: VaultId, record_id: RecordId) -> Usage {
+        self.usage.get(&(vault_id, record_id)).copied().unwrap_or_default()
+    }
+
+    pub(crate) fn set_policy(&mut self, vault_id: VaultId, record_id: RecordId, policy: UsagePolicy) {
+        let id = (vault_id, record_id);
+        self.recent.remove(&id);
+        if policy == UsagePolicy::default() {
+            self.policies.remove(&id);
+        } else {
+            self.policies.insert(id, policy);
+        }
+    }
+
+    /// Reserves a use of the record, unless it would exceed the policy of the record together with the uses,
+    /// that are already reserved. Returns the time, at which the use has been added to the rate limit.
+    fn reserve(&mut self, vault_id: VaultId, record_id: RecordId) -> Result<Option<Instant>, String> {
+        let id = (vault_id, record_id);
+        let pending = self.pending.get(&id).copied().unwrap_or_default();
+        let mut reserved = None;
+
+        if let Some(policy) = self.policies.get(&id) {
+            if let Some(max_uses) = policy.max_uses {
+                if self.usage(vault_id, record_id).count + pending >= max_uses {
+                    return Err(format!(
+                        "Record {:?} has reached its limit of {} uses",
+                        record_id, max_uses
+                    ));
+                }
+            }
+            if let Some((max_uses, window)) = policy.rate_limit {
+                let now = Instant::now();
+                let recent = self.recent.entry(id).or_default();
+                while recent.front().is_some_and(|used| now.duration_since(*used) >= window) {
+                    recent.pop_front();
+                }
+                if recent.len() >= max_uses as usize {
+                    return Err(format!(
+                        "Record {:?} has reached its limit of {} uses per {:?}",
+                        record_id, max_uses, window
+                    ));
+                }
+                recent.push_back(now);
+                reserved = Some(now);
+            }
+        }
+
+        self.pending.insert(id, pending + 1);
+        Ok(reserved)
+    }
+
+    /// Releases a reserved use. It is counted, if the procedure has succeeded, otherwise it is also removed
+    /// from the rate limit.
+    fn release(&mut self, id: (VaultId, RecordId), reserved: Option<Instant>, used: bool) {
+        if let Some(pending) = self.pending.get_mut(&id) {
+            *pending -= 1;
+            if *pending == 0 {
+                self.pending.remove(&id);
+            }
+        }
+        if used {
+            let usage = self.usage.entry(id).or_default();
+            usage.count += 1;
+            usage.last_used = Some(SystemTime::now());
+        } else if let (Some(reserved), Some(recent)) = (reserved, self.recent.get_mut(&id)) {
+            if let Some(position) = recent.iter().rposition(|used| *used == reserved) {
+                recent.remove(position);
+            }
+        }
+    }
+
+    /// Serializes the counters, so that they can be persisted with the state of the client
+    pub(crate) fn serialize(&self) -> Result<Vec<u8>, bincode::Error> {
+        let persisted: Vec<PersistedUsage> = self
+            .usage
+            .iter()
+            .map(|((vault_id, record_id), usage)| PersistedUsage {
+                vault_id: *vault_id,
+                record_id: *record_id,
+                count: usage.count,
+                last_used: usage
+                    .last_used
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map(|since| since.as_secs()),
+            })
+            .collect();
+        bincode::serialize(&persisted)
+    }
+
+    /// Replaces the counters with the ones, that have been serialized with [`Self::serialize`]. The policies
+    /// are kept.
+    pub(crate) fn restore(&mut self, bytes: &[u8]) -> Result<(), bincode::Error> {
+        let persisted: Vec<PersistedUsage> = bincode::deserialize(bytes)?;
+        self.usage = persisted
+            .into_iter()
+            .map(|usage| {
+                let last_used = usage.last_used.map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+                (
+                    (usage.vault_id, usage.record_id),
+                    Usage {
+                        count: usage.count,
+                        last_used,
+                    },
+                )
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Removes all counters. The policies are kept.
+    pub(crate) fn clear(&mut self) {
+        self.usage.clear();
+        self.recent.clear();
+    }
+}
+
+/// The uses of records, that a procedure has reserved before reading them. They are counted, once the procedure
+/// has succeeded with [`Self::complete`], and released without being counted, if it is dropped before.
+pub(crate) struct UsageReservation {
+    tracker: Arc<Mutex<UsageTracker>>,
+    reserved: Vec<((VaultId, RecordId), Option<Instant>)>,
+    used: bool,
+}
+
+impl UsageReservation {
+    pub(crate) fn new(tracker: &Arc<Mutex<UsageTracker>>) -> Self {
+        UsageReservation {
+            tracker: tracker.clone(),
+            reserved: Vec::new(),
+            used: false,
+        }
+    }
+
+    /// Reserves a use of the record, see [`UsageTracker::reserve`]. Fails with the reason, if the policy of the
+    /// record denies it.
+    pub(crate) fn reserve(&mut self, vault_id: VaultId, record_id: RecordId) -> Result<(), String> {
+        let mut tracker = self.tracker.lock().map_err(|e| e.to_string())?;
+        let reserved = tracker.reserve(vault_id, record_id)?;
+        self.reserved.push(((vault_id, record_id), reserved));
+        Ok(())
+    }
+
+    /// Counts the reserved uses
+    pub(crate) fn complete(mut self) {
+        self.used = true;
+    }
+}
+
+impl Drop for UsageReservation {
+    fn drop(&mut self) {
+        if let Ok(mut tracker) = self.tracker.lock() {
+            for (id, reserved) in self.reserved.drain(..) {
+                tracker.release(id, reserved, self.used);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn use_record(tracker: &Arc<Mutex<UsageTracker>>, vault_id: VaultId, record_id: RecordId) -> Result<(), String> {
+        let mut reservation = UsageReservation::new(tracker);
+        reservation.reserve(vault_id, record_id)?;
+        reservation.complete();
+        Ok(())
+    }
+
+    #[test]
+    fn test_policy() {
+        let vault_id = VaultId::load(&[1; 24]).unwrap();
+        let record_id = RecordId::load(&[2; 24]).unwrap();
+        let tracker = Arc::new(Mutex::new(UsageTracker::default()));
+
+        use_record(&tracker, vault_id, record_id).unwrap();
+        tracker.lock().unwrap().set_policy(
+            vault_id,
+            record_id,
+            UsagePolicy {
+                max_uses: Some(3),
+                rate_limit: Some((1, Duration::from_secs(3600))),
+            },
+        );
+        use_record(&tracker, vault_id, record_id).unwrap();
+        assert!(use_record(&tracker, vault_id, record_id).is_err());
+        assert_eq!(tracker.lock().unwrap().usage(vault_id, record_id).count, 2);
+
+        tracker.lock().unwrap().set_policy(
+            vault_id,
+            record_id,
+            UsagePolicy {
+                max_uses: Some(3),
+                rate_limit: None,
+            },
+        );
+        use_record(&tracker, vault_id, record_id).unwrap();
+        assert!(use_record(&tracker, vault_id, record_id).is_err());
+        assert_eq!(tracker.lock().unwrap().usage(vault_id, record_id).count, 3);
+    }
+
+    #[test]
+    fn test_reservation() {
+        let vault_id = VaultId::load(&[1; 24]).unwrap();
+        let record_id = RecordId::load(&[2; 24]).unwrap();
+        let tracker = Arc::new(Mutex::new(UsageTracker::default()));
+        tracker.lock().unwrap().set_policy(
+            vault_id,
+            record_id,
+            UsagePolicy {
+                max_uses: Some(2),
+                rate_limit: Some((1, Duration::from_secs(3600))),
+            },
+        );
+
+        // a failed procedure doesn't count, and doesn't use up the rate limit
+        let mut failed = UsageReservation::new(&tracker);
+        failed.reserve(vault_id, record_id).unwrap();
+        drop(failed);
+        assert_eq!(tracker.lock().unwrap().usage(vault_id, record_id), Usage::default());
+
+        // uses, that are reserved by concurrent procedures, count against the limits
+        let mut pending = UsageReservation::new(&tracker);
+        pending.reserve(vault_id, record_id).unwrap();
+        assert!(use_record(&tracker, vault_id, record_id).is_err());
+        pending.complete();
+        assert_eq!(tracker.lock().unwrap().usage(vault_id, record_id).count, 1);
+    }
+
+    #[test]
+    fn test_serialize() {
+        let vault_id = VaultId::load(&[1; 24]).unwrap();
+        let record_id = RecordId::load(&[2; 24]).unwrap();
+        let tracker = Arc::new(Mutex::new(UsageTracker::default()));
+        use_record(&tracker, vault_id, record_id).unwrap();
+        let bytes = tracker.lock().unwrap().serialize().unwrap();
+
+        let mut restored = UsageTracker::default();
+        restored.restore(&bytes).unwrap();
+        let usage = restored.usage(vault_id, record_id);
+        assert_eq!(usage.count, 1);
+        assert!(usage.last_used.is_some());
+    }
+}
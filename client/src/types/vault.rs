@@ -2,7 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{derive_vault_id, procedures::Runner, Client, ClientError, Location};
-use engine::vault::VaultId;
+use engine::{
+    time::SystemTime,
+    vault::{ListOptions, RecordId, RecordPage, VaultId},
+};
 
 pub const DEFAULT_RANDOM_HINT_SIZE: usize = 24;
 
@@ -23,6 +26,7 @@ impl ClientVault {
     ///
     /// # Example
     pub fn write_secret(&self, location: Location, payload: Vec<u8>) -> Result<(), ClientError> {
+        self.client.ensure_unlocked()?;
         self.client.write_to_vault(&location, payload)?;
         Ok(())
     }
@@ -54,19 +58,77 @@ impl ClientVault {
             record_path: record_path.as_ref().to_vec(),
             vault_path: self.vault_path.clone(),
         };
+        self.client.ensure_unlocked()?;
         self.client.revoke_data(&location)?;
         Ok(())
     }
 
-    /// Collects revoked records and deletes them
+    /// Collects revoked records and deletes them. Records that have been revoked within the
+    /// retention window of [`Client::set_trash_retention`] are kept.
     ///
     /// # Example
     pub fn cleanup(&self) -> Result<bool, ClientError> {
+        self.client.ensure_unlocked()?;
         let result = self.client.garbage_collect(self.id())?;
 
         Ok(result)
     }
 
+    /// Restores a revoked secret, that has not been deleted by [`Self::cleanup`] yet.
+    /// Returns `false`, if there is no revoked secret at `record_path`.
+    ///
+    /// # Example
+    pub fn restore_secret<P>(&self, record_path: P) -> Result<bool, ClientError>
+    where
+        P: AsRef<[u8]>,
+    {
+        let location = Location::generic(self.vault_path.clone(), record_path.as_ref().to_vec());
+        let (vault_id, record_id) = location.resolve();
+
+        let keystore = self.client.read_keystore()?;
+        let key = match keystore.get_key(vault_id) {
+            Some(key) => key,
+            None => return Ok(false),
+        };
+        let restored = self.client.db.write()?.restore_record(&key, vault_id, record_id)?;
+        if restored {
+            self.client.mark_dirty();
+        }
+        Ok(restored)
+    }
+
+    /// Lists the revoked secrets, that have not been deleted by [`Self::cleanup`] yet, together with
+    /// the time of their revocation.
+    ///
+    /// # Example
+    pub fn list_revoked(&self) -> Result<Vec<(RecordId, SystemTime)>, ClientError> {
+        let vault_id = self.id();
+
+        let keystore = self.client.read_keystore()?;
+        let key = match keystore.get_key(vault_id) {
+            Some(key) => key,
+            None => return Ok(Vec::new()),
+        };
+        let revoked = self.client.db.read()?.list_revoked_records(&key, vault_id)?;
+        Ok(revoked)
+    }
+
+    /// Lists the page of records in the vault, that is selected by `options`, together with their
+    /// hints. Use [`ListOptions::offset`] and [`ListOptions::limit`] to list large vaults in multiple calls.
+    ///
+    /// # Example
+    pub fn list_records(&self, options: &ListOptions) -> Result<RecordPage, ClientError> {
+        let vault_id = self.id();
+
+        let keystore = self.client.read_keystore()?;
+        let key = match keystore.get_key(vault_id) {
+            Some(key) => key,
+            None => return Ok(RecordPage::default()),
+        };
+        let page = self.client.db.read()?.list_hints_and_ids_paged(&key, vault_id, options);
+        Ok(page)
+    }
+
     pub fn id(&self) -> VaultId {
         derive_vault_id(self.vault_path.clone())
     }
@@ -85,6 +147,7 @@ impl ClientVault {
 
         let mut data = Vec::new();
 
+        self.client.ensure_unlocked()?;
         self.client.get_guard(&location, |guarded_data| {
             let guarded_data = guarded_data.borrow();
             data.extend_from_slice(&guarded_data);
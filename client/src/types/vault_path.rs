@@ -0,0 +1,151 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Separates the segments of a [`VaultPath`]
+pub const VAULT_PATH_SEPARATOR: u8 = b'/';
+
+/// A hierarchical vault path, like `org/team/service`.
+///
+/// The bytes of the path are used as-is to derive the [`engine::vault::VaultId`], so a [`VaultPath`] can be
+/// passed wherever a vault path is expected. Policies, like approval hooks, that are registered for a path
+/// are inherited by all of its children. Patterns can be used to address several subtrees at once:
+/// `*` matches exactly one segment and `**` matches any number of segments.
+///
+/// # Example
+/// ```
+/// use iota_stronghold::VaultPath;
+///
+/// let path = VaultPath::new("org/team/service");
+/// assert_eq!(path.parent(), Some(VaultPath::new("org/team")));
+/// assert!(VaultPath::new("org").is_ancestor_of(&path));
+/// assert!(path.matches("org/*/service"));
+/// assert!(path.matches("org/**"));
+/// assert!(!path.matches("org/*"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct VaultPath(Vec<u8>);
+
+impl VaultPath {
+    /// Creates a new [`VaultPath`] from raw bytes
+    pub fn new<P>(path: P) -> Self
+    where
+        P: AsRef<[u8]>,
+    {
+        Self(path.as_ref().to_vec())
+    }
+
+    /// Returns a new path with `segment` appended as child of `self`
+    pub fn join<S>(&self, segment: S) -> Self
+    where
+        S: AsRef<[u8]>,
+    {
+        if self.0.is_empty() {
+            return Self::new(segment);
+        }
+        let mut path = self.0.clone();
+        path.push(VAULT_PATH_SEPARATOR);
+        path.extend_from_slice(segment.as_ref());
+        Self(path)
+    }
+
+    /// Returns an iterator over the segments of the path
+    pub fn segments(&self) -> impl Iterator<Item = &[u8]> {
+        self.0.split(|b| *b == VAULT_PATH_SEPARATOR)
+    }
+
+    /// Returns the parent of the path, or `None` for a top level path
+    pub fn parent(&self) -> Option<Self> {
+        let position = self.0.iter().rposition(|b| *b == VAULT_PATH_SEPARATOR)?;
+        Some(Self(self.0[..position].to_vec()))
+    }
+
+    /// Returns the path itself followed by all of its ancestors, the most specific path first
+    pub fn ancestors(&self) -> Vec<Self> {
+        let mut ancestors = vec![self.clone()];
+        while let Some(parent) = ancestors.last().and_then(|p| p.parent()) {
+            ancestors.push(parent);
+        }
+        ancestors
+    }
+
+    /// Returns `true`, if `other` is the same path as `self` or lies in the subtree of `self`.
+    /// The empty path is the ancestor of all paths.
+    pub fn is_ancestor_of(&self, other: &VaultPath) -> bool {
+        self.0.is_empty()
+            || other.0.starts_with(&self.0)
+                && (other.0.len() == self.0.len() || other.0[self.0.len()] == VAULT_PATH_SEPARATOR)
+    }
+
+    /// Returns `true`, if the path matches `pattern`. A `*` segment in the pattern matches exactly
+    /// one segment of the path, a `**` segment matches any number of segments, including none.
+    pub fn matches<P>(&self, pattern: P) -> bool
+    where
+        P: AsRef<[u8]>,
+    {
+        let pattern: Vec<&[u8]> = pattern.as_ref().split(|b| *b == VAULT_PATH_SEPARATOR).collect();
+        let segments: Vec<&[u8]> = self.segments().collect();
+        matches_segments(&pattern, &segments)
+    }
+
+    /// Returns `true`, if `pattern` contains wildcard segments
+    pub fn is_pattern<P>(pattern: P) -> bool
+    where
+        P: AsRef<[u8]>,
+    {
+        pattern
+            .as_ref()
+            .split(|b| *b == VAULT_PATH_SEPARATOR)
+            .any(|segment| segment == b"*" || segment == b"**")
+    }
+
+    /// Returns the raw bytes of the path
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+fn matches_segments(pattern: &[&[u8]], segments: &[&[u8]]) -> bool {
+    match pattern.split_first() {
+        None => segments.is_empty(),
+        Some((p, rest)) if *p == b"**" => {
+            matches_segments(rest, segments) || (!segments.is_empty() && matches_segments(pattern, &segments[1..]))
+        }
+        Some((p, rest)) => match segments.split_first() {
+            Some((s, segments)) if *p == b"*" || p == s => matches_segments(rest, segments),
+            _ => false,
+        },
+    }
+}
+
+impl AsRef<[u8]> for VaultPath {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<&str> for VaultPath {
+    fn from(path: &str) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<Vec<u8>> for VaultPath {
+    fn from(path: Vec<u8>) -> Self {
+        Self(path)
+    }
+}
+
+impl From<VaultPath> for Vec<u8> {
+    fn from(path: VaultPath) -> Self {
+        path.0
+    }
+}
+
+impl Display for VaultPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
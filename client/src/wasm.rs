@@ -0,0 +1,147 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Bindings of the core client API for JavaScript, built with `wasm-bindgen` for `wasm32-unknown-unknown`.
+//!
+//! There is no file system in the browser, so snapshots are committed to and loaded from bytes, that
+//! JavaScript persists itself, e.g. in IndexedDB:
+//!
+//! ```text
+//! const stronghold = new Stronghold();
+//! const client = stronghold.createClient(clientPath);
+//! client.generateEd25519(vaultPath, recordPath);
+//! await db.put("snapshot", stronghold.commitToBytes(password));
+//! ```
+//!
+//! WebAssembly can't lock memory or change its protection, so secrets are only zeroized after use, see
+//! [`memory_protection_available`](engine::runtime::utils::memory_protection_available).
+
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroize;
+
+use crate::{
+    procedures::{Ed25519Sign, GenerateKey, KeyType, PublicKey},
+    Argon2Params, Client, Location, Stronghold,
+};
+
+fn js_error(e: impl std::fmt::Display) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+/// A Stronghold instance, see [`Stronghold`]
+#[wasm_bindgen(js_name = Stronghold)]
+#[derive(Default)]
+pub struct WasmStronghold {
+    inner: Stronghold,
+}
+
+#[wasm_bindgen(js_class = Stronghold)]
+impl WasmStronghold {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new client with `client_path`
+    #[wasm_bindgen(js_name = createClient)]
+    pub fn create_client(&self, client_path: &[u8]) -> Result<WasmClient, JsError> {
+        self.inner
+            .create_client(client_path)
+            .map(|inner| WasmClient { inner })
+            .map_err(js_error)
+    }
+
+    /// Loads the client with `client_path` from the loaded snapshot
+    #[wasm_bindgen(js_name = loadClient)]
+    pub fn load_client(&self, client_path: &[u8]) -> Result<WasmClient, JsError> {
+        self.inner
+            .load_client(client_path)
+            .map(|inner| WasmClient { inner })
+            .map_err(js_error)
+    }
+
+    /// Returns the encrypted snapshot of all clients, whose key is derived from `password`
+    #[wasm_bindgen(js_name = commitToBytes)]
+    pub fn commit_to_bytes(&self, password: String) -> Result<Vec<u8>, JsError> {
+        self.inner
+            .commit_to_bytes(password, &Argon2Params::default())
+            .map_err(js_error)
+    }
+
+    /// Loads the snapshot `content`, that has been returned by `commitToBytes`
+    #[wasm_bindgen(js_name = loadFromBytes)]
+    pub fn load_from_bytes(&self, content: &[u8], password: String) -> Result<(), JsError> {
+        self.inner.load_snapshot_from_bytes(content, password).map_err(js_error)
+    }
+}
+
+/// A client of a Stronghold instance, see [`Client`]
+#[wasm_bindgen(js_name = Client)]
+pub struct WasmClient {
+    inner: Client,
+}
+
+#[wasm_bindgen(js_class = Client)]
+impl WasmClient {
+    /// Writes `secret` to the record at `record_path` in the vault at `vault_path`. The buffer is zeroized
+    /// afterwards.
+    #[wasm_bindgen(js_name = writeSecret)]
+    pub fn write_secret(&self, vault_path: &[u8], record_path: &[u8], secret: &mut [u8]) -> Result<(), JsError> {
+        let written = self
+            .inner
+            .vault(vault_path)
+            .write_secret(Location::generic(vault_path, record_path), secret.to_vec());
+        secret.zeroize();
+        written.map_err(js_error)
+    }
+
+    /// Generates an Ed25519 key in the record at `record_path` in the vault at `vault_path`
+    #[wasm_bindgen(js_name = generateEd25519)]
+    pub fn generate_ed25519(&self, vault_path: &[u8], record_path: &[u8]) -> Result<(), JsError> {
+        self.inner
+            .execute_procedure(GenerateKey {
+                ty: KeyType::Ed25519,
+                output: Location::generic(vault_path, record_path),
+            })
+            .map_err(js_error)
+    }
+
+    /// Returns the public key of the Ed25519 key in the record at `record_path`
+    #[wasm_bindgen(js_name = ed25519PublicKey)]
+    pub fn ed25519_public_key(&self, vault_path: &[u8], record_path: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.inner
+            .execute_procedure(PublicKey {
+                ty: KeyType::Ed25519,
+                private_key: Location::generic(vault_path, record_path),
+            })
+            .map_err(js_error)
+    }
+
+    /// Signs `msg` with the Ed25519 key in the record at `record_path`
+    #[wasm_bindgen(js_name = ed25519Sign)]
+    pub fn ed25519_sign(&self, vault_path: &[u8], record_path: &[u8], msg: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.inner
+            .execute_procedure(Ed25519Sign {
+                msg: msg.to_vec(),
+                private_key: Location::generic(vault_path, record_path),
+            })
+            .map(|signature| signature.to_vec())
+            .map_err(js_error)
+    }
+
+    /// Inserts `value` under `key` into the store of the client
+    #[wasm_bindgen(js_name = storeInsert)]
+    pub fn store_insert(&self, key: &[u8], value: &[u8]) -> Result<(), JsError> {
+        self.inner
+            .store()
+            .insert(key.to_vec(), value.to_vec(), None)
+            .map(|_| ())
+            .map_err(js_error)
+    }
+
+    /// Returns the value under `key` in the store of the client
+    #[wasm_bindgen(js_name = storeGet)]
+    pub fn store_get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, JsError> {
+        self.inner.store().get(key).map_err(js_error)
+    }
+}
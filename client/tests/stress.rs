@@ -0,0 +1,268 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Long running stress test with a mixed workload.
+//!
+//! Several threads concurrently write secrets, execute procedures, use the store, synchronize clients,
+//! persist snapshots and simulate crashes by loading truncated or corrupted copies of the snapshot. The
+//! observable state is checked against a model during the run and once more after reloading the final
+//! snapshot.
+//!
+//! The test only runs with the `stress` feature, and is meant to be run in release mode:
+//!
+//! ```sh
+//! cargo test --release --features stress --test stress -- --nocapture
+//! ```
+//!
+//! The duration and the number of threads are read from `STRONGHOLD_STRESS_SECS` (default: 60) and
+//! `STRONGHOLD_STRESS_THREADS` (default: 8).
+
+use iota_stronghold::{
+    procedures::{GenerateKey, KeyType, PublicKey},
+    sync::{MergePolicy, SyncClientsConfig},
+    Client, KeyProvider, Location, SnapshotPath, Stronghold,
+};
+use rand::{seq::IteratorRandom, Rng};
+use std::{
+    collections::HashMap,
+    env,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+use stronghold_utils::{random::fixed_bytestring, test_utils::corrupt_file_at};
+
+const SNAPSHOT_KEY: &[u8; 32] = b"stress-test-snapshot-key-0123456";
+const AGGREGATE_CLIENT: &[u8] = b"stress-aggregate";
+
+struct Config {
+    duration: Duration,
+    threads: usize,
+}
+
+impl Config {
+    fn from_env() -> Self {
+        let var = |name: &str, default: u64| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+        Config {
+            duration: Duration::from_secs(var("STRONGHOLD_STRESS_SECS", 60)),
+            threads: var("STRONGHOLD_STRESS_THREADS", 8) as usize,
+        }
+    }
+}
+
+/// The state a worker expects its client to have
+#[derive(Default)]
+struct Model {
+    client_path: Vec<u8>,
+    vault_path: Vec<u8>,
+
+    /// Records with an opaque secret
+    secrets: Vec<Vec<u8>>,
+
+    /// Records with a generated Ed25519 key, and its public key
+    keys: HashMap<Vec<u8>, [u8; 32]>,
+
+    /// Entries of the client store
+    store: HashMap<Vec<u8>, Vec<u8>>,
+
+    /// Number of executed operations
+    operations: usize,
+}
+
+impl Model {
+    fn location(&self, record_path: &[u8]) -> Location {
+        Location::generic(self.vault_path.clone(), record_path.to_vec())
+    }
+
+    fn records(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.secrets.iter().chain(self.keys.keys())
+    }
+}
+
+fn keyprovider() -> KeyProvider {
+    KeyProvider::try_from(SNAPSHOT_KEY.to_vec()).expect("Failed to create keyprovider")
+}
+
+fn public_key(client: &Client, location: Location) -> [u8; 32] {
+    client
+        .execute_procedure(PublicKey {
+            ty: KeyType::Ed25519,
+            private_key: location,
+        })
+        .expect("Failed to derive public key")
+}
+
+/// Runs the mixed workload of a single worker until `deadline`
+fn worker(id: usize, stronghold: Stronghold, snapshot: SnapshotPath, deadline: Instant) -> Model {
+    let mut rng = rand::thread_rng();
+    let mut model = Model {
+        client_path: format!("stress-client-{}", id).into_bytes(),
+        vault_path: format!("stress-vault-{}", id).into_bytes(),
+        ..Default::default()
+    };
+    let client = stronghold
+        .create_client(&model.client_path)
+        .expect("Failed to create client");
+    let aggregate = stronghold
+        .get_client(AGGREGATE_CLIENT)
+        .expect("Failed to get aggregate client");
+    let crash_path = PathBuf::from(format!("{}.crash-{}", snapshot.as_path().display(), id));
+
+    while Instant::now() < deadline {
+        match rng.gen_range(0..7) {
+            // write an opaque secret
+            0 => {
+                let record_path = fixed_bytestring(16);
+                let location = model.location(&record_path);
+                client
+                    .vault(&model.vault_path)
+                    .write_secret(location.clone(), fixed_bytestring(32))
+                    .expect("Failed to write secret");
+                assert!(client.record_exists(&location).unwrap());
+                model.secrets.push(record_path);
+            }
+            // generate a key and derive its public key
+            1 => {
+                let record_path = fixed_bytestring(16);
+                let location = model.location(&record_path);
+                client
+                    .execute_procedure(GenerateKey {
+                        ty: KeyType::Ed25519,
+                        output: location.clone(),
+                    })
+                    .expect("Failed to generate key");
+                model.keys.insert(record_path, public_key(&client, location));
+            }
+            // procedures on an existing key are deterministic
+            2 => {
+                if let Some((record_path, expected)) = model.keys.iter().choose(&mut rng) {
+                    assert_eq!(&public_key(&client, model.location(record_path)), expected);
+                }
+            }
+            // store entries
+            3 => {
+                let key = fixed_bytestring(8);
+                let value = fixed_bytestring(64);
+                client.store().insert(key.clone(), value.clone(), None).unwrap();
+                assert_eq!(client.store().get(&key).unwrap(), Some(value.clone()));
+                model.store.insert(key, value);
+            }
+            // synchronize into the shared client
+            4 => {
+                aggregate
+                    .sync_with(&client, SyncClientsConfig::new(MergePolicy::Replace))
+                    .expect("Failed to sync clients");
+                if let Some(record_path) = model.records().choose(&mut rng) {
+                    assert!(aggregate.record_exists(&model.location(record_path)).unwrap());
+                }
+            }
+            // persist all clients
+            5 => {
+                stronghold
+                    .commit_with_keyprovider(&snapshot, &keyprovider())
+                    .expect("Failed to commit");
+            }
+            // simulate a crash, that left a damaged snapshot behind
+            _ => {
+                let content = match std::fs::read(snapshot.as_path()) {
+                    Ok(content) => content,
+                    Err(_) => continue,
+                };
+
+                // the snapshot is replaced atomically, so the latest file is always complete
+                Stronghold::default()
+                    .load_snapshot(&keyprovider(), &snapshot)
+                    .expect("Failed to load a committed snapshot");
+
+                let damaged = SnapshotPath::from_path(&crash_path);
+                if rng.gen_bool(0.5) {
+                    std::fs::write(&crash_path, &content[..rng.gen_range(0..content.len())]).unwrap();
+                } else {
+                    std::fs::write(&crash_path, &content).unwrap();
+                    corrupt_file_at(&crash_path);
+                }
+                assert!(Stronghold::default().load_snapshot(&keyprovider(), &damaged).is_err());
+                let _ = std::fs::remove_file(&crash_path);
+            }
+        }
+        model.operations += 1;
+    }
+
+    model
+}
+
+#[test]
+fn stress_mixed_workload() {
+    let config = Config::from_env();
+    let mut path = env::temp_dir();
+    path.push(format!("stronghold-stress-{}.snapshot", std::process::id()));
+    let snapshot = SnapshotPath::from_path(&path);
+
+    let stronghold = Stronghold::default();
+    stronghold
+        .create_client(AGGREGATE_CLIENT)
+        .expect("Failed to create aggregate client");
+
+    let started = Instant::now();
+    let deadline = started + config.duration;
+    let workers: Vec<_> = (0..config.threads)
+        .map(|id| {
+            let stronghold = stronghold.clone();
+            let snapshot = snapshot.clone();
+            thread::spawn(move || worker(id, stronghold, snapshot, deadline))
+        })
+        .collect();
+    let models: Vec<Model> = workers
+        .into_iter()
+        .map(|worker| worker.join().expect("Worker panicked"))
+        .collect();
+
+    let operations: usize = models.iter().map(|model| model.operations).sum();
+    println!(
+        "{} operations on {} threads in {:?}",
+        operations,
+        config.threads,
+        started.elapsed()
+    );
+
+    // make sure the aggregate client has seen the final state of each worker
+    let aggregate = stronghold.get_client(AGGREGATE_CLIENT).unwrap();
+    for model in &models {
+        let client = stronghold.get_client(&model.client_path).unwrap();
+        aggregate
+            .sync_with(&client, SyncClientsConfig::new(MergePolicy::Replace))
+            .unwrap();
+    }
+    stronghold
+        .commit_with_keyprovider(&snapshot, &keyprovider())
+        .expect("Failed to commit");
+
+    // reload everything from the snapshot and check it against the models
+    let stronghold = stronghold.reset();
+    stronghold
+        .load_snapshot(&keyprovider(), &snapshot)
+        .expect("Failed to load snapshot");
+    let aggregate = stronghold.load_client(AGGREGATE_CLIENT).unwrap();
+
+    for model in &models {
+        let client = stronghold.load_client(&model.client_path).unwrap();
+        for record_path in model.records() {
+            assert!(client.record_exists(&model.location(record_path)).unwrap());
+            assert!(aggregate.record_exists(&model.location(record_path)).unwrap());
+        }
+        for (record_path, expected) in &model.keys {
+            assert_eq!(&public_key(&client, model.location(record_path)), expected);
+            assert_eq!(&public_key(&aggregate, model.location(record_path)), expected);
+        }
+        for (key, value) in &model.store {
+            assert_eq!(client.store().get(key).unwrap().as_ref(), Some(value));
+        }
+    }
+
+    let _ = std::fs::remove_file(&path);
+}
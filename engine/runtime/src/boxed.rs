@@ -1,7 +1,10 @@
 // Copyright 2020-2021 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::types::*;
+use crate::{
+    pool::{self, Allocation, Prot},
+    types::*,
+};
 use zeroize::Zeroize;
 
 use core::{
@@ -12,25 +15,18 @@ use core::{
     slice,
 };
 
-use libsodium_sys::{
-    sodium_allocarray, sodium_free, sodium_init, sodium_mlock, sodium_mprotect_noaccess, sodium_mprotect_readonly,
-    sodium_mprotect_readwrite,
-};
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Prot {
-    NoAccess,
-    ReadOnly,
-    ReadWrite,
-}
+#[cfg(not(target_arch = "wasm32"))]
+use libsodium_sys::sodium_init;
 
 type RefCount = u8;
 
-/// A protected piece of memory.
+/// A protected piece of memory, that is allocated from the [`pool`] of locked memory.
 #[derive(Eq)]
 pub(crate) struct Boxed<T: Bytes> {
     // the pointer to the underlying protected memory
     ptr: NonNull<T>,
+    // the slot of the pool, that holds the memory
+    allocation: Allocation,
     // The number of elements of type `T` that can be stored in the pointer.
     len: usize,
     // the current protection level of the data.
@@ -45,7 +41,6 @@ impl<T: Bytes> Boxed<T> {
         F: FnOnce(&mut Self),
     {
         let mut boxed = Self::new_unlocked(len);
-        unsafe { lock_memory(boxed.ptr.as_mut(), len) };
 
         assert!(
             boxed.ptr != core::ptr::NonNull::dangling(),
@@ -142,15 +137,17 @@ impl<T: Bytes> Boxed<T> {
     }
 
     fn new_unlocked(len: usize) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
         if unsafe { sodium_init() == -1 } {
             panic!("Failed to initialize libsodium")
         }
 
-        let ptr = NonNull::new(unsafe { sodium_allocarray(len, mem::size_of::<T>()) as *mut _ })
-            .expect("Failed to allocate memory");
+        let size = len.checked_mul(mem::size_of::<T>()).expect("Failed to allocate memory");
+        let allocation = pool::allocate(size, mem::align_of::<T>());
 
         Self {
-            ptr,
+            ptr: allocation.as_ptr().cast(),
+            allocation,
             len,
             prot: Cell::new(Prot::ReadWrite),
             refs: Cell::new(1),
@@ -164,7 +161,7 @@ impl<T: Bytes> Boxed<T> {
             assert!(prot != Prot::NoAccess, "Must retain readably or writably");
 
             self.prot.set(prot);
-            mprotect(self.ptr.as_ptr(), prot);
+            pool::protect(&self.allocation, prot);
         } else {
             assert!(
                 Prot::NoAccess != self.prot.get(),
@@ -197,7 +194,7 @@ impl<T: Bytes> Boxed<T> {
         self.refs.set(refs);
 
         if refs == 0 {
-            mprotect(self.ptr.as_ptr(), Prot::NoAccess);
+            pool::protect(&self.allocation, Prot::NoAccess);
             self.prot.set(Prot::NoAccess);
         }
     }
@@ -253,7 +250,7 @@ impl<T: Bytes> Drop for Boxed<T> {
             assert!(self.prot.get() == Prot::NoAccess, "Dropped secret was still accessible");
         }
 
-        unsafe { free(self.ptr.as_mut()) }
+        pool::release(&self.allocation);
     }
 }
 
@@ -305,24 +302,6 @@ impl<T: Bytes + Zeroed> From<&mut [T]> for Boxed<T> {
 unsafe impl<T: Bytes + Send> Send for Boxed<T> {}
 unsafe impl<T: Bytes + Sync> Sync for Boxed<T> {}
 
-fn mprotect<T>(ptr: *mut T, prot: Prot) {
-    if !match prot {
-        Prot::NoAccess => unsafe { sodium_mprotect_noaccess(ptr as *mut _) == 0 },
-        Prot::ReadOnly => unsafe { sodium_mprotect_readonly(ptr as *mut _) == 0 },
-        Prot::ReadWrite => unsafe { sodium_mprotect_readwrite(ptr as *mut _) == 0 },
-    } {
-        panic!("Error setting memory protection to {:?}", prot);
-    }
-}
-
-pub(crate) unsafe fn free<T>(ptr: *mut T) {
-    sodium_free(ptr as *mut _)
-}
-
-pub(crate) unsafe fn lock_memory<T>(ptr: *mut T, len: usize) {
-    sodium_mlock(ptr as *mut _, len);
-}
-
 #[cfg(test)]
 mod test {
     extern crate alloc;
@@ -330,7 +309,7 @@ mod test {
     use alloc::vec;
 
     use super::*;
-    use libsodium_sys::randombytes_buf;
+    use libsodium_sys::{randombytes_buf, sodium_allocarray, sodium_free};
 
     #[test]
     fn boxed_zeroize() {
@@ -356,7 +335,7 @@ mod test {
             let garb_ptr = sodium_allocarray(1, mem::size_of::<u8>()) as *mut u8;
             let garb_byte = *garb_ptr;
 
-            free(garb_ptr);
+            sodium_free(garb_ptr as *mut _);
 
             vec![garb_byte; unboxed.len()]
         };
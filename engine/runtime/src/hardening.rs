@@ -0,0 +1,84 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hardening of the whole process, that complements the protection of single memory regions: secrets must not be
+//! written into core dumps, and other processes must not be able to read them by attaching a debugger.
+
+/// The mitigations, that [`harden_process`] has applied to the current process. Mitigations, that are not
+/// available on the platform or could not be applied, are `false`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Mitigations {
+    /// The size limit of core files is set to zero (`RLIMIT_CORE`).
+    pub core_dumps_disabled: bool,
+
+    /// The process is marked as not dumpable (`PR_SET_DUMPABLE` on Linux), so that `/proc/<pid>/mem` of the
+    /// process can't be read by other processes of the same user.
+    pub not_dumpable: bool,
+
+    /// Unprivileged processes can't attach to the process with a debugger, e.g. with `ptrace`.
+    pub not_traceable: bool,
+
+    /// The mitigation policies of the process are set (`SetProcessMitigationPolicy` on Windows), see
+    /// `memories::windows_memory::set_mitigation_policies`.
+    pub mitigation_policies: bool,
+}
+
+/// Applies all mitigations of the platform, that protect the memory of the current process from being dumped or
+/// traced, and reports which of them have been applied. Mitigations, that fail, are logged and skipped.
+///
+/// The mitigations can't be reverted, and affect the whole process, i.e. also debuggers and crash reporters of the
+/// embedding application. Call it once early in `main`.
+///
+/// # Example
+/// ```no_run
+/// let mitigations = runtime::harden_process();
+/// if !mitigations.not_traceable {
+///     eprintln!("Secrets of this process may be read by a debugger");
+/// }
+/// ```
+pub fn harden_process() -> Mitigations {
+    let mut mitigations = Mitigations::default();
+
+    #[cfg(unix)]
+    {
+        let limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        mitigations.core_dumps_disabled = unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } == 0;
+        if !mitigations.core_dumps_disabled {
+            log::warn!("Failed to disable core dumps: {}", std::io::Error::last_os_error());
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        // a process that is not dumpable can only be traced by processes with CAP_SYS_PTRACE
+        mitigations.not_dumpable = unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) } == 0;
+        mitigations.not_traceable = mitigations.not_dumpable;
+        if !mitigations.not_dumpable {
+            log::warn!(
+                "Failed to mark the process as not dumpable: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        mitigations.not_traceable = unsafe { libc::ptrace(libc::PT_DENY_ATTACH, 0, core::ptr::null_mut(), 0) } == 0;
+        if !mitigations.not_traceable {
+            log::warn!("Failed to deny debuggers: {}", std::io::Error::last_os_error());
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        match crate::memories::windows_memory::set_mitigation_policies() {
+            Ok(()) => mitigations.mitigation_policies = true,
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+
+    mitigations
+}
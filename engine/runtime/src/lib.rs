@@ -5,13 +5,16 @@
 // #![no_std]
 
 mod boxed;
+mod hardening;
 pub mod locked_memory;
 pub mod memories;
+mod pool;
 mod types;
 pub mod utils;
 
+pub use hardening::{harden_process, Mitigations};
 pub use thiserror::Error as DeriveError;
-pub use types::Bytes;
+pub use types::{Bytes, ZeroizingString, ZeroizingVec};
 
 /// The memory types of this crate shall return this message when trying to debug them
 pub const DEBUG_MSG: &str = "Content of Locked Memory is hidden";
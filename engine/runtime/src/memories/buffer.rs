@@ -64,6 +64,57 @@ impl<T: Bytes> Buffer<T> {
         RefMut::new(&mut self.boxed)
     }
 
+    /// Appends `value` to the end of the buffer, see [`Self::extend_from_slice`]
+    pub fn push(&mut self, value: T) {
+        self.extend_from_slice(&[value]);
+    }
+
+    /// Appends `values` to the end of the buffer.
+    ///
+    /// The buffer doesn't reserve spare capacity, so every growth moves the data into new guarded memory, and the
+    /// memory it has been held in before is zeroed and released. Appending many values at once is cheaper than
+    /// pushing them one by one.
+    pub fn extend_from_slice(&mut self, values: &[T]) {
+        if values.is_empty() {
+            return;
+        }
+        let len = self.len();
+        self.reallocate(len + values.len(), |data| data[len..].copy_from_slice(values));
+    }
+
+    /// Resizes the buffer to `new_len` elements, like [`Vec::resize`]. New elements are set to `value`, and
+    /// elements past `new_len` are dropped. The data is moved into new guarded memory as in
+    /// [`Self::extend_from_slice`].
+    pub fn resize(&mut self, new_len: usize, value: T) {
+        if new_len == self.len() {
+            return;
+        }
+        let len = self.len();
+        self.reallocate(new_len, |data| {
+            if new_len > len {
+                data[len..].fill(value);
+            }
+        });
+    }
+
+    /// Copies the data into new guarded memory of `new_len` elements, and initializes the new elements with
+    /// `init`. Dropping the previous memory zeroes it.
+    fn reallocate<F>(&mut self, new_len: usize, init: F)
+    where
+        F: FnOnce(&mut [T]),
+    {
+        let old = &self.boxed;
+        let keep = old.len().min(new_len);
+        let boxed = Boxed::new(new_len, |b| {
+            if keep > 0 {
+                b.as_mut_slice()[..keep].copy_from_slice(&old.unlock().as_slice()[..keep]);
+                old.lock();
+            }
+            init(b.as_mut_slice());
+        });
+        self.boxed = boxed;
+    }
+
     #[cfg(test)]
     #[allow(dead_code)]
     /// Returns the address of the pointer to the data
@@ -372,6 +423,31 @@ mod tests {
         v.copy_from_slice(&[7, 1][..]);
     }
 
+    #[test]
+    fn buffer_growth() {
+        let mut buf = Buffer::<u8>::alloc(&[1, 2], 2);
+        buf.push(3);
+        buf.extend_from_slice(&[4, 5]);
+        buf.extend_from_slice(&[]);
+        assert_eq!(*buf.borrow(), [1, 2, 3, 4, 5]);
+
+        buf.resize(7, 0xff);
+        assert_eq!(*buf.borrow(), [1, 2, 3, 4, 5, 0xff, 0xff]);
+        buf.resize(2, 0);
+        assert_eq!(*buf.borrow(), [1, 2]);
+        assert_eq!(buf.len(), 2);
+
+        // growing into a new page copies all elements
+        let mut buf = Buffer::<u64>::zero(0);
+        (0..1024).for_each(|i| buf.push(i));
+        assert_eq!(*buf.borrow(), (0..1024).collect::<alloc::vec::Vec<u64>>()[..]);
+
+        let mut buf = Buffer::<u8>::from(&mut [1, 2, 3][..]);
+        buf.zeroize();
+        buf.push(4);
+        assert_eq!(*buf.borrow(), [4]);
+    }
+
     // We should not be able to access memory without borrow/borrow_mut
     #[test]
     #[should_panic]
@@ -70,6 +70,9 @@ pub struct Frag<T: Default + Clone> {
 
     #[cfg(target_os = "windows")]
     info: Option<(windows::Win32::Foundation::HANDLE, *const libc::c_void)>,
+
+    #[cfg(target_arch = "wasm32")]
+    info: (*mut u8, std::alloc::Layout),
 }
 
 impl<T: Default + Clone> Zeroize for Frag<T> {
@@ -293,6 +296,16 @@ where
         dealloc_map(frag.info.0, frag.info.1 as libc::size_t)
     }
 
+    #[cfg(target_arch = "wasm32")]
+    fn alloc(_config: Option<FragConfig>) -> Result<Frag<T>, Self::Error> {
+        alloc_padded(FragStrategy::Map)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn dealloc(frag: &mut Frag<T>) -> Result<(), Self::Error> {
+        dealloc_padded(frag)
+    }
+
     #[cfg(target_os = "windows")]
     fn alloc(_config: Option<FragConfig>) -> Result<Frag<T>, Self::Error> {
         let handle = windows::Win32::Foundation::INVALID_HANDLE_VALUE;
@@ -423,6 +436,16 @@ where
         dealloc_direct(frag.info.0 as *mut libc::c_void)
     }
 
+    #[cfg(target_arch = "wasm32")]
+    fn alloc(_config: Option<FragConfig>) -> Result<Frag<T>, Self::Error> {
+        alloc_padded(FragStrategy::Direct)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn dealloc(frag: &mut Frag<T>) -> Result<(), Self::Error> {
+        dealloc_padded(frag)
+    }
+
     #[cfg(target_os = "windows")]
     fn alloc(config: Option<FragConfig>) -> Result<Frag<T>, Self::Error> {
         use windows::Win32::System::Memory::{VirtualAlloc, MEM_COMMIT, MEM_RESERVE, PAGE_READWRITE};
@@ -520,6 +543,43 @@ fn dealloc_direct(ptr: *mut libc::c_void) -> Result<(), MemoryError> {
     Ok(())
 }
 
+/// WebAssembly has neither memory mappings nor control over the addresses of allocations. The object is
+/// placed at a random offset into a chunk of the heap, with at least [`FRAG_MIN_DISTANCE`] bytes of padding
+/// on both sides, so that two fragments are never closer to each other.
+#[cfg(target_arch = "wasm32")]
+fn alloc_padded<T: Default + Clone>(strategy: FragStrategy) -> Result<Frag<T>, MemoryError> {
+    use random::{thread_rng, Rng};
+    use std::alloc::{alloc_zeroed, Layout};
+
+    let size = std::mem::size_of::<T>();
+    let align = std::mem::align_of::<T>();
+    let padding = FRAG_MIN_DISTANCE + thread_rng().gen_range(0..FRAG_MIN_DISTANCE);
+    let layout = Layout::from_size_align(2 * padding + size + align, align)
+        .map_err(|e| MemoryError::Allocation(e.to_string()))?;
+
+    unsafe {
+        let chunk = alloc_zeroed(layout);
+        if chunk.is_null() {
+            return Err(MemoryError::Allocation("Received a null pointer".to_string()));
+        }
+        let ptr = chunk.add(round_up(padding, align)) as *mut T;
+        ptr.write(T::default());
+
+        Ok(Frag {
+            ptr: NonNull::new_unchecked(ptr),
+            strategy,
+            live: true,
+            info: (chunk, layout),
+        })
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn dealloc_padded<T: Default + Clone>(frag: &mut Frag<T>) -> Result<(), MemoryError> {
+    unsafe { std::alloc::dealloc(frag.info.0, frag.info.1) };
+    Ok(())
+}
+
 // -----------------------------------------------------------------------------
 
 /// Rounds `value` up to a multiple of `base`
@@ -7,3 +7,7 @@ pub mod file_memory;
 pub mod frag;
 pub mod noncontiguous_memory;
 pub mod ram_memory;
+#[cfg(target_os = "linux")]
+pub mod secret_memory;
+#[cfg(target_os = "windows")]
+pub mod windows_memory;
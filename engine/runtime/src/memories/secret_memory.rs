@@ -0,0 +1,353 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Locked memory on Linux, that is removed from the direct map of the kernel with `memfd_secret(2)`. Pages of a
+//! secret memory area can't be read by other processes, not even through `ptrace` or `/proc/<pid>/mem`, and are
+//! never written into a hibernation image.
+//!
+//! `memfd_secret` is available since Linux 5.14, on some kernels it has to be enabled with `secretmem.enable=1`.
+//! Where it is unavailable, [`SecretMemoryConfig::Auto`] falls back to an anonymous mapping, that is locked with
+//! `mlock`, and excluded from core dumps and forked children with `madvise`.
+
+use crate::{
+    locked_memory::LockedMemory,
+    memories::buffer::Buffer,
+    utils::page_size,
+    MemoryError::{self, *},
+    ZeroizeOnDrop, DEBUG_MSG,
+};
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+};
+use std::sync::Mutex;
+use zeroize::Zeroize;
+
+use serde::{
+    de::{Deserialize, Deserializer, SeqAccess, Visitor},
+    ser::{Serialize, Serializer},
+};
+
+// the number of the syscall is the same on all architectures, that support it
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "riscv64"))]
+const SYS_MEMFD_SECRET: libc::c_long = 447;
+
+/// Selects the kernel mechanism, that protects the pages of a [`SecretMemory`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SecretMemoryConfig {
+    /// `memfd_secret`, if the kernel supports it, otherwise [`SecretMemoryConfig::Mlock`]
+    #[default]
+    Auto,
+
+    /// `memfd_secret` only, allocations fail on kernels without support for it
+    MemfdSecret,
+
+    /// An anonymous mapping, that is locked into RAM and excluded from core dumps and forks
+    Mlock,
+}
+
+/// Long lived secrets in memory, that is protected by the kernel, see the [module documentation](self).
+///
+/// The pages are inaccessible, while the memory is not unlocked.
+pub struct SecretMemory {
+    // the address of the mapping, 0 once zeroized
+    addr: usize,
+    // the length of the mapping, a multiple of the page size
+    len: usize,
+    // the size of the data
+    size: usize,
+    // `true`, if the mapping is backed by `memfd_secret`
+    secret: bool,
+    config: SecretMemoryConfig,
+    // serializes the changes of the protection of the pages
+    access: Mutex<()>,
+}
+
+impl SecretMemory {
+    /// Copies `payload` into new secret memory of `size` bytes
+    pub fn alloc(payload: &[u8], size: usize, config: SecretMemoryConfig) -> Result<Self, MemoryError> {
+        if size == 0 {
+            return Err(ZeroSizedNotAllowed);
+        }
+        if payload.len() < size {
+            return Err(Allocation(format!("Payload is shorter than {} bytes", size)));
+        }
+        let len = size.div_ceil(page_size()) * page_size();
+
+        let (addr, secret) = match config {
+            SecretMemoryConfig::MemfdSecret => (map_secret(len)?, true),
+            SecretMemoryConfig::Mlock => (map_locked(len)?, false),
+            SecretMemoryConfig::Auto => match map_secret(len) {
+                Ok(addr) => (addr, true),
+                Err(_) => (map_locked(len)?, false),
+            },
+        };
+
+        unsafe { core::ptr::copy_nonoverlapping(payload.as_ptr(), addr as *mut u8, size) };
+        if let Err(e) = protect(addr, len, libc::PROT_NONE) {
+            // the mapping is still writable, so wipe the copied secret before releasing it
+            unsafe {
+                core::slice::from_raw_parts_mut(addr as *mut u8, len).zeroize();
+                libc::munmap(addr as *mut _, len);
+            }
+            return Err(e);
+        }
+
+        Ok(SecretMemory {
+            addr,
+            len,
+            size,
+            secret,
+            config,
+            access: Mutex::new(()),
+        })
+    }
+
+    /// Returns `true`, if the memory is backed by `memfd_secret`, and `false` if it is only locked
+    pub fn is_memfd_secret(&self) -> bool {
+        self.secret
+    }
+
+    /// Returns the configuration, that the memory has been allocated with
+    pub fn config(&self) -> SecretMemoryConfig {
+        self.config
+    }
+}
+
+/// Maps `len` bytes of memory, that is removed from the direct map of the kernel
+fn map_secret(len: usize) -> Result<usize, MemoryError> {
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "riscv64"))]
+    {
+        let fd = unsafe { libc::syscall(SYS_MEMFD_SECRET, libc::O_CLOEXEC) } as libc::c_int;
+        if fd < 0 {
+            return Err(Allocation("memfd_secret is not available".to_string()));
+        }
+
+        let addr = unsafe {
+            if libc::ftruncate(fd, len as libc::off_t) != 0 {
+                libc::close(fd);
+                return Err(Allocation("Failed to size the memfd_secret area".to_string()));
+            }
+            let addr = libc::mmap(
+                core::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            );
+            // the mapping keeps the area alive
+            libc::close(fd);
+            addr
+        };
+        if addr == libc::MAP_FAILED {
+            return Err(Allocation("Failed to map the memfd_secret area".to_string()));
+        }
+        Ok(addr as usize)
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "riscv64")))]
+    {
+        let _ = len;
+        Err(Allocation(
+            "memfd_secret is not supported on this architecture".to_string(),
+        ))
+    }
+}
+
+/// Maps `len` bytes of anonymous memory, that is locked into RAM and excluded from core dumps and forks
+fn map_locked(len: usize) -> Result<usize, MemoryError> {
+    let addr = unsafe {
+        libc::mmap(
+            core::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    if addr == libc::MAP_FAILED {
+        return Err(Allocation("Failed to map memory".to_string()));
+    }
+
+    unsafe {
+        if libc::mlock(addr, len) != 0 {
+            libc::munmap(addr, len);
+            return Err(LockNotAvailable);
+        }
+        libc::madvise(addr, len, libc::MADV_DONTDUMP);
+        libc::madvise(addr, len, libc::MADV_WIPEONFORK);
+    }
+    Ok(addr as usize)
+}
+
+fn protect(addr: usize, len: usize, prot: libc::c_int) -> Result<(), MemoryError> {
+    match unsafe { libc::mprotect(addr as *mut _, len, prot) } {
+        0 => Ok(()),
+        _ => Err(Operation(
+            "Failed to change the protection of secret memory".to_string(),
+        )),
+    }
+}
+
+impl LockedMemory for SecretMemory {
+    /// Locks the memory and possibly reallocates
+    fn update(self, payload: Buffer<u8>, size: usize) -> Result<Self, MemoryError> {
+        SecretMemory::alloc(&payload.borrow(), size, self.config)
+    }
+
+    /// Unlocks the memory
+    fn unlock(&self) -> Result<Buffer<u8>, MemoryError> {
+        if self.size == 0 {
+            return Err(ZeroSizedNotAllowed);
+        }
+
+        let _access = self.access.lock().map_err(|_| LockNotAvailable)?;
+        protect(self.addr, self.len, libc::PROT_READ)?;
+        let data = unsafe { core::slice::from_raw_parts(self.addr as *const u8, self.size) };
+        let buf = Buffer::alloc(data, self.size);
+        protect(self.addr, self.len, libc::PROT_NONE)?;
+        Ok(buf)
+    }
+}
+
+impl Clone for SecretMemory {
+    fn clone(&self) -> Self {
+        let buf = self.unlock().expect("Failed to unlock SecretMemory for cloning");
+        let clone = SecretMemory::alloc(&buf.borrow(), self.size, self.config);
+        clone.expect("Failed to allocate SecretMemory for cloning")
+    }
+}
+
+impl Zeroize for SecretMemory {
+    fn zeroize(&mut self) {
+        if self.addr != 0 {
+            // the pages are unmapped anyway, when the protection can't be changed
+            if protect(self.addr, self.len, libc::PROT_READ | libc::PROT_WRITE).is_ok() {
+                unsafe { core::slice::from_raw_parts_mut(self.addr as *mut u8, self.len) }.zeroize();
+            }
+            unsafe { libc::munmap(self.addr as *mut _, self.len) };
+        }
+        self.addr = 0;
+        self.len = 0;
+        self.size = 0;
+    }
+}
+
+impl ZeroizeOnDrop for SecretMemory {}
+
+impl Drop for SecretMemory {
+    fn drop(&mut self) {
+        self.zeroize()
+    }
+}
+
+impl Debug for SecretMemory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", DEBUG_MSG)
+    }
+}
+
+unsafe impl Send for SecretMemory {}
+unsafe impl Sync for SecretMemory {}
+
+impl Serialize for SecretMemory {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let buf = self.unlock().expect("Failed to unlock SecretMemory for serialization");
+        buf.serialize(serializer)
+    }
+}
+
+struct SecretMemoryVisitor {
+    marker: PhantomData<fn() -> SecretMemory>,
+}
+
+impl SecretMemoryVisitor {
+    fn new() -> Self {
+        SecretMemoryVisitor { marker: PhantomData }
+    }
+}
+
+impl<'de> Visitor<'de> for SecretMemoryVisitor {
+    type Value = SecretMemory;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str("SecretMemory not found")
+    }
+
+    fn visit_seq<E>(self, mut access: E) -> Result<Self::Value, E::Error>
+    where
+        E: SeqAccess<'de>,
+    {
+        let mut seq = Vec::<u8>::with_capacity(access.size_hint().unwrap_or(0));
+
+        while let Some(e) = access.next_element()? {
+            seq.push(e);
+        }
+
+        let mem = SecretMemory::alloc(seq.as_slice(), seq.len(), SecretMemoryConfig::default());
+        seq.zeroize();
+
+        mem.map_err(|e| serde::de::Error::custom(e.to_string()))
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretMemory {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(SecretMemoryVisitor::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_memory_mlock() {
+        let mem = SecretMemory::alloc(&[1, 2, 3, 4, 5, 6][..], 6, SecretMemoryConfig::Mlock);
+        let mut mem = match mem {
+            Ok(mem) => mem,
+            // the limit of locked memory of the test environment may be exhausted
+            Err(LockNotAvailable) => return,
+            Err(e) => panic!("{}", e),
+        };
+        assert!(!mem.is_memfd_secret());
+        assert_eq!(*mem.unlock().unwrap().borrow(), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(*mem.clone().unlock().unwrap().borrow(), [1, 2, 3, 4, 5, 6]);
+
+        mem.zeroize();
+        assert_eq!(mem.size, 0);
+        assert!(mem.unlock().is_err());
+    }
+
+    #[test]
+    fn secret_memory_memfd_secret() {
+        // kernels without memfd_secret must fail explicitly, instead of silently falling back
+        let mem = match SecretMemory::alloc(&[7; 32], 32, SecretMemoryConfig::MemfdSecret) {
+            Ok(mem) => mem,
+            Err(Allocation(_)) | Err(LockNotAvailable) => return,
+            Err(e) => panic!("{}", e),
+        };
+        assert!(mem.is_memfd_secret());
+        assert_eq!(*mem.unlock().unwrap().borrow(), [7; 32]);
+
+        let serialized = serde_json::to_string(&mem).unwrap();
+        let deserialized: SecretMemory = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(*deserialized.unlock().unwrap().borrow(), [7; 32]);
+    }
+
+    #[test]
+    fn secret_memory_zero_size() {
+        assert!(matches!(
+            SecretMemory::alloc(&[], 0, SecretMemoryConfig::Auto),
+            Err(ZeroSizedNotAllowed)
+        ));
+    }
+}
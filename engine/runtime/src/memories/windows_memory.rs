@@ -0,0 +1,297 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Locked memory on Windows.
+//!
+//! The pages of a [`WindowsMemory`] are locked into the working set with
+//! [`VirtualLock`](https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-virtuallock), so they are
+//! not written into the page file, and are inaccessible while the memory is not unlocked. At rest the data is
+//! encrypted with [`CryptProtectMemory`](https://learn.microsoft.com/en-us/windows/win32/api/dpapi/nf-dpapi-cryptprotectmemory),
+//! with a key that the kernel keeps outside of the address space of the process. Memory dumps of the process, e.g.
+//! crash reports or hibernation files, only contain the ciphertext.
+//!
+//! [`set_mitigation_policies`] additionally hardens the process against code, that is injected to scrape its memory.
+
+use crate::{
+    locked_memory::LockedMemory,
+    memories::buffer::Buffer,
+    utils::page_size,
+    MemoryError::{self, *},
+    ZeroizeOnDrop, DEBUG_MSG,
+};
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+};
+use std::sync::Mutex;
+use zeroize::Zeroize;
+
+use serde::{
+    de::{Deserialize, Deserializer, SeqAccess, Visitor},
+    ser::{Serialize, Serializer},
+};
+
+use windows::Win32::{
+    Security::Cryptography::{
+        CryptProtectMemory, CryptUnprotectMemory, CRYPTPROTECTMEMORY_BLOCK_SIZE, CRYPTPROTECTMEMORY_SAME_PROCESS,
+    },
+    System::{
+        Memory::{
+            VirtualAlloc, VirtualFree, VirtualLock, VirtualProtect, VirtualUnlock, MEM_COMMIT, MEM_RELEASE,
+            MEM_RESERVE, PAGE_NOACCESS, PAGE_PROTECTION_FLAGS, PAGE_READWRITE,
+        },
+        SystemServices::PROCESS_MITIGATION_EXTENSION_POINT_DISABLE_POLICY,
+        Threading::{ProcessExtensionPointDisablePolicy, SetProcessMitigationPolicy},
+    },
+};
+
+/// Long lived secrets in locked and encrypted memory, see the [module documentation](self).
+pub struct WindowsMemory {
+    // the address of the allocation, 0 once zeroized
+    addr: usize,
+    // the length of the allocation, a multiple of the page size
+    len: usize,
+    // the length of the encrypted region, a multiple of the block size of `CryptProtectMemory`
+    encrypted: usize,
+    // the size of the data
+    size: usize,
+    // serializes the changes of the protection of the pages
+    access: Mutex<()>,
+}
+
+impl WindowsMemory {
+    /// Copies `payload` into new locked memory of `size` bytes, and encrypts it
+    pub fn alloc(payload: &[u8], size: usize) -> Result<Self, MemoryError> {
+        if size == 0 {
+            return Err(ZeroSizedNotAllowed);
+        }
+        if payload.len() < size {
+            return Err(Allocation(format!("Payload is shorter than {} bytes", size)));
+        }
+        let block = CRYPTPROTECTMEMORY_BLOCK_SIZE as usize;
+        let encrypted = size.div_ceil(block) * block;
+        let len = encrypted.div_ceil(page_size()) * page_size();
+
+        let addr = unsafe { VirtualAlloc(core::ptr::null(), len, MEM_COMMIT | MEM_RESERVE, PAGE_READWRITE) };
+        if addr.is_null() {
+            return Err(Allocation("Call to VirtualAlloc failed".to_string()));
+        }
+        if !unsafe { VirtualLock(addr, len) }.as_bool() {
+            unsafe { VirtualFree(addr, 0, MEM_RELEASE) };
+            return Err(LockNotAvailable);
+        }
+
+        let mem = WindowsMemory {
+            addr: addr as usize,
+            len,
+            encrypted,
+            size,
+            access: Mutex::new(()),
+        };
+        unsafe { core::ptr::copy_nonoverlapping(payload.as_ptr(), addr as *mut u8, size) };
+        mem.protect_data()?;
+        mem.set_protection(PAGE_NOACCESS)?;
+        Ok(mem)
+    }
+
+    fn set_protection(&self, protection: PAGE_PROTECTION_FLAGS) -> Result<(), MemoryError> {
+        let mut old = PAGE_PROTECTION_FLAGS::default();
+        match unsafe { VirtualProtect(self.addr as *const _, self.len, protection, &mut old) }.as_bool() {
+            true => Ok(()),
+            false => Err(Operation(
+                "Failed to change the protection of locked memory".to_string(),
+            )),
+        }
+    }
+
+    fn protect_data(&self) -> Result<(), MemoryError> {
+        let encrypted = self.encrypted as u32;
+        match unsafe { CryptProtectMemory(self.addr as *mut _, encrypted, CRYPTPROTECTMEMORY_SAME_PROCESS) }.as_bool() {
+            true => Ok(()),
+            false => Err(Operation("Failed to encrypt locked memory".to_string())),
+        }
+    }
+
+    fn unprotect_data(&self) -> Result<(), MemoryError> {
+        let encrypted = self.encrypted as u32;
+        match unsafe { CryptUnprotectMemory(self.addr as *mut _, encrypted, CRYPTPROTECTMEMORY_SAME_PROCESS) }.as_bool()
+        {
+            true => Ok(()),
+            false => Err(Operation("Failed to decrypt locked memory".to_string())),
+        }
+    }
+}
+
+/// Applies the mitigation policies of the current process, that protect the secrets in its memory:
+/// - legacy extension points, like `AppInit_DLLs` and window hooks, can't inject libraries into the process
+///
+/// The policies can't be reverted for the lifetime of the process.
+pub fn set_mitigation_policies() -> Result<(), MemoryError> {
+    let mut policy = PROCESS_MITIGATION_EXTENSION_POINT_DISABLE_POLICY::default();
+    // DisableExtensionPoints is the first bit of the flags
+    policy.Anonymous.Flags = 1;
+
+    let applied = unsafe {
+        SetProcessMitigationPolicy(
+            ProcessExtensionPointDisablePolicy,
+            &policy as *const _ as *const _,
+            core::mem::size_of_val(&policy),
+        )
+    };
+    match applied.as_bool() {
+        true => Ok(()),
+        false => Err(Operation(
+            "Failed to set the mitigation policies of the process".to_string(),
+        )),
+    }
+}
+
+impl LockedMemory for WindowsMemory {
+    /// Locks the memory and possibly reallocates
+    fn update(self, payload: Buffer<u8>, size: usize) -> Result<Self, MemoryError> {
+        WindowsMemory::alloc(&payload.borrow(), size)
+    }
+
+    /// Unlocks the memory
+    fn unlock(&self) -> Result<Buffer<u8>, MemoryError> {
+        if self.size == 0 {
+            return Err(ZeroSizedNotAllowed);
+        }
+
+        let _access = self.access.lock().map_err(|_| LockNotAvailable)?;
+        self.set_protection(PAGE_READWRITE)?;
+        if let Err(e) = self.unprotect_data() {
+            // the pages must not stay accessible, even if the data couldn't be decrypted
+            let _ = self.set_protection(PAGE_NOACCESS);
+            return Err(e);
+        }
+        let data = unsafe { core::slice::from_raw_parts(self.addr as *const u8, self.size) };
+        let buf = Buffer::alloc(data, self.size);
+        let protected = self.protect_data();
+        self.set_protection(PAGE_NOACCESS)?;
+        protected?;
+        Ok(buf)
+    }
+}
+
+impl Clone for WindowsMemory {
+    fn clone(&self) -> Self {
+        let buf = self.unlock().expect("Failed to unlock WindowsMemory for cloning");
+        let clone = WindowsMemory::alloc(&buf.borrow(), self.size);
+        clone.expect("Failed to allocate WindowsMemory for cloning")
+    }
+}
+
+impl Zeroize for WindowsMemory {
+    fn zeroize(&mut self) {
+        if self.addr != 0 {
+            // the memory is released anyway, when the protection can't be changed
+            if self.set_protection(PAGE_READWRITE).is_ok() {
+                unsafe { core::slice::from_raw_parts_mut(self.addr as *mut u8, self.len) }.zeroize();
+            }
+            unsafe {
+                VirtualUnlock(self.addr as *const _, self.len);
+                VirtualFree(self.addr as *mut _, 0, MEM_RELEASE);
+            }
+        }
+        self.addr = 0;
+        self.len = 0;
+        self.encrypted = 0;
+        self.size = 0;
+    }
+}
+
+impl ZeroizeOnDrop for WindowsMemory {}
+
+impl Drop for WindowsMemory {
+    fn drop(&mut self) {
+        self.zeroize()
+    }
+}
+
+impl Debug for WindowsMemory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", DEBUG_MSG)
+    }
+}
+
+unsafe impl Send for WindowsMemory {}
+unsafe impl Sync for WindowsMemory {}
+
+impl Serialize for WindowsMemory {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let buf = self.unlock().expect("Failed to unlock WindowsMemory for serialization");
+        buf.serialize(serializer)
+    }
+}
+
+struct WindowsMemoryVisitor {
+    marker: PhantomData<fn() -> WindowsMemory>,
+}
+
+impl WindowsMemoryVisitor {
+    fn new() -> Self {
+        WindowsMemoryVisitor { marker: PhantomData }
+    }
+}
+
+impl<'de> Visitor<'de> for WindowsMemoryVisitor {
+    type Value = WindowsMemory;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str("WindowsMemory not found")
+    }
+
+    fn visit_seq<E>(self, mut access: E) -> Result<Self::Value, E::Error>
+    where
+        E: SeqAccess<'de>,
+    {
+        let mut seq = Vec::<u8>::with_capacity(access.size_hint().unwrap_or(0));
+
+        while let Some(e) = access.next_element()? {
+            seq.push(e);
+        }
+
+        let mem = WindowsMemory::alloc(seq.as_slice(), seq.len());
+        seq.zeroize();
+
+        mem.map_err(|e| serde::de::Error::custom(e.to_string()))
+    }
+}
+
+impl<'de> Deserialize<'de> for WindowsMemory {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(WindowsMemoryVisitor::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_memory_roundtrip() {
+        let mut mem = WindowsMemory::alloc(&[1, 2, 3, 4, 5, 6][..], 6).unwrap();
+        assert_eq!(*mem.unlock().unwrap().borrow(), [1, 2, 3, 4, 5, 6]);
+        // the data stays readable after it has been re-encrypted
+        assert_eq!(*mem.clone().unlock().unwrap().borrow(), [1, 2, 3, 4, 5, 6]);
+
+        let serialized = serde_json::to_string(&mem).unwrap();
+        let deserialized: WindowsMemory = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(*deserialized.unlock().unwrap().borrow(), [1, 2, 3, 4, 5, 6]);
+
+        mem.zeroize();
+        assert!(mem.unlock().is_err());
+    }
+
+    #[test]
+    fn windows_memory_zero_size() {
+        assert!(matches!(WindowsMemory::alloc(&[], 0), Err(ZeroSizedNotAllowed)));
+    }
+}
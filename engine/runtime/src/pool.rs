@@ -0,0 +1,432 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! A pool of locked memory, that the guarded allocations of [`Boxed`](crate::boxed::Boxed) are served from.
+//!
+//! Memory is reserved in arenas, that are mapped, locked and excluded from core dumps with a single system call
+//! each, instead of once per allocation. An arena is divided into slots of the same number of pages, which is
+//! rounded up to a power of two, so that allocations of similar sizes share their arenas. Every slot is
+//! surrounded by guard pages:
+//!
+//! ```text
+//! | guard | slot 0 | guard | slot 1 | guard | ... | slot n | guard |
+//! ```
+//!
+//! Free slots and guard pages are never accessible. The data of an allocation is placed at the end of its slot,
+//! so that overflows hit the following guard page, and a canary in front of it detects underflows when the slot is
+//! released. Released slots are zeroed and reused.
+//!
+//! The first arena of a slot size has [`MIN_ARENA_PAGES`] pages, every further one twice as many as the previous,
+//! up to [`MAX_ARENA_PAGES`]. An arena is unmapped, as soon as all of its slots have been released.
+//!
+//! Platforms without `mmap` fall back to an allocation of libsodium per value. On WebAssembly, where memory can be
+//! neither locked nor protected, values are allocated on the heap and only zeroed when they are released.
+
+use core::{
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The size of the canary in front of each allocation
+const CANARY_SIZE: usize = 16;
+
+/// The value, that new allocations are filled with, the same as libsodium uses
+const GARBAGE_VALUE: u8 = 0xdb;
+
+/// The number of pages of the first arena of a slot size
+const MIN_ARENA_PAGES: usize = 16;
+
+/// The number of pages, that an arena is reserved with at most, unless a single slot is larger
+const MAX_ARENA_PAGES: usize = 256;
+
+// The number of data pages of all live allocations
+static PAGES_IN_USE: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the number of data pages, that are held by live allocations
+pub(crate) fn pages_in_use() -> usize {
+    PAGES_IN_USE.load(Ordering::Relaxed)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Prot {
+    NoAccess,
+    ReadOnly,
+    ReadWrite,
+}
+
+/// A guarded allocation. Only [`release`] frees it, dropping it leaks the slot.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Allocation {
+    // The first byte of the data
+    ptr: NonNull<u8>,
+    // The address of the first page of the slot
+    #[cfg_attr(not(unix), allow(dead_code))]
+    region: usize,
+    // The number of pages of the slot
+    pages: usize,
+}
+
+impl Allocation {
+    pub(crate) fn as_ptr(&self) -> NonNull<u8> {
+        self.ptr
+    }
+}
+
+/// Returns the number of pages of a slot for `size` bytes with `align`, including the canary
+fn slot_pages(size: usize, align: usize, page: usize) -> usize {
+    (size + CANARY_SIZE + align - 1).div_ceil(page).max(1)
+}
+
+/// Returns the number of pages of the slots, that an allocation of `pages` pages is served from
+#[cfg(unix)]
+fn size_class(pages: usize) -> usize {
+    pages.next_power_of_two()
+}
+
+#[cfg(unix)]
+mod imp {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    use libsodium_sys::{randombytes_buf, sodium_memzero};
+
+    use crate::utils::page_size;
+
+    static POOL: Mutex<Pool> = Mutex::new(Pool::new());
+
+    struct Arena {
+        // The address of the first page, which is a guard page
+        base: usize,
+        // The number of pages of each slot, without guard page
+        slot_pages: usize,
+        // The number of slots
+        slots: usize,
+        // The indices of the free slots
+        free: Vec<usize>,
+    }
+
+    impl Arena {
+        fn total_pages(slot_pages: usize, slots: usize) -> usize {
+            1 + slots * (slot_pages + 1)
+        }
+
+        fn new(slot_pages: usize, arena_pages: usize, page: usize) -> Self {
+            let slots = ((arena_pages - 1) / (slot_pages + 1)).max(1);
+            let len = Self::total_pages(slot_pages, slots) * page;
+
+            let base = unsafe {
+                libc::mmap(
+                    core::ptr::null_mut(),
+                    len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_PRIVATE | libc::MAP_ANON,
+                    -1,
+                    0,
+                )
+            };
+            if base == libc::MAP_FAILED {
+                panic!("Failed to allocate memory");
+            }
+
+            // locking may fail due to the limit of locked memory, the memory is still guarded then
+            if unsafe { libc::mlock(base, len) } != 0 {
+                log::warn!("Failed to lock {} bytes of guarded memory", len);
+            }
+            #[cfg(target_os = "linux")]
+            unsafe {
+                libc::madvise(base, len, libc::MADV_DONTDUMP);
+            }
+            mprotect(base as usize, len, Prot::NoAccess);
+
+            Self {
+                base: base as usize,
+                slot_pages,
+                slots,
+                free: (0..slots).rev().collect(),
+            }
+        }
+
+        fn len(&self, page: usize) -> usize {
+            Self::total_pages(self.slot_pages, self.slots) * page
+        }
+
+        fn is_empty(&self) -> bool {
+            self.free.len() == self.slots
+        }
+
+        fn slot(&self, index: usize, page: usize) -> usize {
+            self.base + (1 + index * (self.slot_pages + 1)) * page
+        }
+
+        fn index_of(&self, region: usize, page: usize) -> Option<usize> {
+            let offset = region.checked_sub(self.base + page)?;
+            let stride = (self.slot_pages + 1) * page;
+            let index = offset / stride;
+            (offset % stride == 0 && index < self.slots).then_some(index)
+        }
+    }
+
+    struct Pool {
+        arenas: Vec<Arena>,
+        canary: [u8; CANARY_SIZE],
+        canary_set: bool,
+    }
+
+    impl Pool {
+        const fn new() -> Self {
+            Self {
+                arenas: Vec::new(),
+                canary: [0; CANARY_SIZE],
+                canary_set: false,
+            }
+        }
+
+        fn canary(&mut self) -> [u8; CANARY_SIZE] {
+            if !self.canary_set {
+                unsafe { randombytes_buf(self.canary.as_mut_ptr() as *mut _, CANARY_SIZE) };
+                self.canary_set = true;
+            }
+            self.canary
+        }
+    }
+
+    fn mprotect(addr: usize, len: usize, prot: Prot) {
+        let flags = match prot {
+            Prot::NoAccess => libc::PROT_NONE,
+            Prot::ReadOnly => libc::PROT_READ,
+            Prot::ReadWrite => libc::PROT_READ | libc::PROT_WRITE,
+        };
+        if unsafe { libc::mprotect(addr as *mut _, len, flags) } != 0 {
+            panic!("Error setting memory protection to {:?}", prot);
+        }
+    }
+
+    pub(crate) fn allocate(size: usize, align: usize) -> Allocation {
+        let page = page_size();
+        let slot_pages = size_class(slot_pages(size, align, page));
+
+        let mut pool = POOL.lock().expect("Pool of guarded memory is poisoned");
+        let canary = pool.canary();
+        let arena = match pool
+            .arenas
+            .iter()
+            .position(|arena| arena.slot_pages == slot_pages && !arena.free.is_empty())
+        {
+            Some(position) => &mut pool.arenas[position],
+            None => {
+                let arenas = pool
+                    .arenas
+                    .iter()
+                    .filter(|arena| arena.slot_pages == slot_pages)
+                    .count() as u32;
+                let arena_pages = MIN_ARENA_PAGES
+                    .checked_shl(arenas)
+                    .unwrap_or(MAX_ARENA_PAGES)
+                    .min(MAX_ARENA_PAGES);
+                pool.arenas.push(Arena::new(slot_pages, arena_pages, page));
+                pool.arenas.last_mut().unwrap()
+            }
+        };
+        let index = arena.free.pop().expect("Arena has a free slot");
+        let region = arena.slot(index, page);
+        let len = slot_pages * page;
+
+        mprotect(region, len, Prot::ReadWrite);
+        let ptr = (region + len - size) & !(align - 1);
+        unsafe {
+            core::ptr::write_bytes(region as *mut u8, GARBAGE_VALUE, len);
+            core::ptr::copy_nonoverlapping(canary.as_ptr(), (ptr - CANARY_SIZE) as *mut u8, CANARY_SIZE);
+        }
+        PAGES_IN_USE.fetch_add(slot_pages, Ordering::Relaxed);
+
+        Allocation {
+            ptr: NonNull::new(ptr as *mut u8).expect("Slot is not null"),
+            region,
+            pages: slot_pages,
+        }
+    }
+
+    pub(crate) fn protect(allocation: &Allocation, prot: Prot) {
+        mprotect(allocation.region, allocation.pages * page_size(), prot);
+    }
+
+    pub(crate) fn release(allocation: &Allocation) {
+        let page = page_size();
+        let len = allocation.pages * page;
+
+        let mut pool = POOL.lock().expect("Pool of guarded memory is poisoned");
+        let canary = pool.canary();
+        mprotect(allocation.region, len, Prot::ReadWrite);
+
+        let found = unsafe {
+            core::slice::from_raw_parts(
+                (allocation.ptr.as_ptr() as usize - CANARY_SIZE) as *const u8,
+                CANARY_SIZE,
+            )
+        };
+        if found != &canary[..] {
+            // the memory around the secret has been overwritten, the state of the process can't be trusted
+            std::process::abort();
+        }
+
+        unsafe { sodium_memzero(allocation.region as *mut _, len) };
+        mprotect(allocation.region, len, Prot::NoAccess);
+
+        let (position, index) = pool
+            .arenas
+            .iter()
+            .enumerate()
+            .find_map(|(position, arena)| Some((position, arena.index_of(allocation.region, page)?)))
+            .expect("Allocation belongs to the pool");
+        let arena = &mut pool.arenas[position];
+        arena.free.push(index);
+        if arena.is_empty() {
+            let arena = pool.arenas.swap_remove(position);
+            // unmapping the arena also unlocks it, its memory has been zeroed slot by slot
+            if unsafe { libc::munmap(arena.base as *mut _, arena.len(page)) } != 0 {
+                panic!("Failed to release memory");
+            }
+        }
+        PAGES_IN_USE.fetch_sub(allocation.pages, Ordering::Relaxed);
+    }
+
+    /// Returns the number of arenas with slots of `slot_pages` pages
+    #[cfg(test)]
+    pub(crate) fn arenas(slot_pages: usize) -> usize {
+        POOL.lock()
+            .expect("Pool of guarded memory is poisoned")
+            .arenas
+            .iter()
+            .filter(|arena| arena.slot_pages == slot_pages)
+            .count()
+    }
+}
+
+#[cfg(all(not(unix), not(target_arch = "wasm32")))]
+mod imp {
+    use super::*;
+
+    use libsodium_sys::{
+        sodium_free, sodium_malloc, sodium_mprotect_noaccess, sodium_mprotect_readonly, sodium_mprotect_readwrite,
+    };
+
+    use crate::utils::page_size;
+
+    pub(crate) fn allocate(size: usize, align: usize) -> Allocation {
+        let ptr = NonNull::new(unsafe { sodium_malloc(size) as *mut u8 }).expect("Failed to allocate memory");
+        let pages = slot_pages(size, align, page_size());
+        PAGES_IN_USE.fetch_add(pages, Ordering::Relaxed);
+        Allocation {
+            ptr,
+            region: ptr.as_ptr() as usize,
+            pages,
+        }
+    }
+
+    pub(crate) fn protect(allocation: &Allocation, prot: Prot) {
+        let ptr = allocation.ptr.as_ptr() as *mut _;
+        if !match prot {
+            Prot::NoAccess => unsafe { sodium_mprotect_noaccess(ptr) == 0 },
+            Prot::ReadOnly => unsafe { sodium_mprotect_readonly(ptr) == 0 },
+            Prot::ReadWrite => unsafe { sodium_mprotect_readwrite(ptr) == 0 },
+        } {
+            panic!("Error setting memory protection to {:?}", prot);
+        }
+    }
+
+    pub(crate) fn release(allocation: &Allocation) {
+        unsafe { sodium_free(allocation.ptr.as_ptr() as *mut _) };
+        PAGES_IN_USE.fetch_sub(allocation.pages, Ordering::Relaxed);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod imp {
+    use super::*;
+
+    use std::alloc::{alloc, dealloc, Layout};
+
+    use zeroize::Zeroize;
+
+    use crate::utils::page_size;
+
+    /// Allocations span whole pages, so that the layout can be recovered from the allocation on release
+    fn layout(pages: usize) -> Layout {
+        Layout::from_size_align(pages * page_size(), page_size()).expect("Failed to allocate memory")
+    }
+
+    pub(crate) fn allocate(size: usize, align: usize) -> Allocation {
+        let pages = slot_pages(size, align, page_size());
+        let layout = layout(pages);
+        let ptr = NonNull::new(unsafe { alloc(layout) }).expect("Failed to allocate memory");
+        unsafe { core::ptr::write_bytes(ptr.as_ptr(), GARBAGE_VALUE, layout.size()) };
+        PAGES_IN_USE.fetch_add(pages, Ordering::Relaxed);
+        Allocation {
+            ptr,
+            region: ptr.as_ptr() as usize,
+            pages,
+        }
+    }
+
+    pub(crate) fn protect(_allocation: &Allocation, _prot: Prot) {}
+
+    pub(crate) fn release(allocation: &Allocation) {
+        let layout = layout(allocation.pages);
+        unsafe {
+            core::slice::from_raw_parts_mut(allocation.ptr.as_ptr(), layout.size()).zeroize();
+            dealloc(allocation.ptr.as_ptr(), layout);
+        }
+        PAGES_IN_USE.fetch_sub(allocation.pages, Ordering::Relaxed);
+    }
+}
+
+pub(crate) use imp::{allocate, protect, release};
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    use crate::utils::page_size;
+
+    #[test]
+    fn test_slots_are_reused() {
+        let first = allocate(32, 8);
+        let address = first.as_ptr().as_ptr() as usize;
+        assert_eq!(address % 8, 0);
+        assert_eq!((address + 32) % page_size(), 0);
+        release(&first);
+
+        // a released slot is zeroed and filled with garbage again, before it is handed out
+        let allocations: Vec<Allocation> = (0..4).map(|_| allocate(32, 8)).collect();
+        for allocation in allocations.iter() {
+            let data = unsafe { core::slice::from_raw_parts(allocation.as_ptr().as_ptr(), 32) };
+            assert_eq!(data, [GARBAGE_VALUE; 32]);
+            protect(allocation, Prot::NoAccess);
+            protect(allocation, Prot::ReadWrite);
+        }
+        allocations.iter().for_each(release);
+    }
+
+    #[test]
+    fn test_large_allocation() {
+        let size = MAX_ARENA_PAGES * page_size();
+        let allocation = allocate(size, 1);
+        assert!(allocation.pages > MAX_ARENA_PAGES);
+        unsafe { core::ptr::write_bytes(allocation.as_ptr().as_ptr(), 1, size) };
+        release(&allocation);
+    }
+
+    #[test]
+    fn test_empty_arenas_are_released() {
+        // no other test allocates slots of this size
+        let slot_pages = 64;
+        let size = (slot_pages - 1) * page_size();
+
+        let allocations: Vec<Allocation> = (0..3).map(|_| allocate(size, 1)).collect();
+        assert!(allocations.iter().all(|allocation| allocation.pages == slot_pages));
+        assert!(imp::arenas(slot_pages) > 1);
+
+        allocations.iter().for_each(release);
+        assert_eq!(imp::arenas(slot_pages), 0);
+    }
+}
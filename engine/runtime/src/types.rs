@@ -7,11 +7,13 @@ mod bytes;
 mod const_eq;
 mod rand;
 mod zero;
+mod zeroizing;
 
 pub use bytes::{Bytes, ContiguousBytes};
 pub use const_eq::ConstEq;
 pub use rand::Randomized;
 pub use zero::Zeroed;
+pub use zeroizing::{ZeroizingString, ZeroizingVec};
 
 /// Implements the traits [`Bytes`] onto primitive types and slices.
 macro_rules! impls {
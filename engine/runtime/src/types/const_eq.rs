@@ -3,10 +3,12 @@
 
 use crate::types::*;
 
+#[cfg(not(target_arch = "wasm32"))]
 use libsodium_sys::sodium_memcmp;
 
-/// A trait for comparing types in Constant Time using [`sodium_memcmp`].
+/// A trait for comparing types in Constant Time using `sodium_memcmp`.
 pub trait ConstEq: ContiguousBytes {
+    #[cfg(not(target_arch = "wasm32"))]
     fn const_eq(&self, rhs: &Self) -> bool {
         unsafe {
             sodium_memcmp(
@@ -16,6 +18,12 @@ pub trait ConstEq: ContiguousBytes {
             ) == 0
         }
     }
+
+    #[cfg(target_arch = "wasm32")]
+    fn const_eq(&self, rhs: &Self) -> bool {
+        let (lhs, rhs) = (self.as_bytes(), rhs.as_bytes());
+        lhs.len() == rhs.len() && lhs.iter().zip(rhs).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+    }
 }
 
 impl<T: ContiguousBytes> ConstEq for T {}
@@ -3,13 +3,21 @@
 
 use crate::types::*;
 
+#[cfg(not(target_arch = "wasm32"))]
 use libsodium_sys::randombytes_buf;
 
-/// A trait for generating random bytes via [`randombytes_buf`].
+/// A trait for generating random bytes via `randombytes_buf`, or the `crypto.getRandomValues` API of the
+/// browser on WebAssembly.
 pub unsafe trait Randomized: ContiguousBytes {
+    #[cfg(not(target_arch = "wasm32"))]
     fn randomize(&mut self) {
         unsafe { randombytes_buf(self.as_mut_bytes().as_mut_ptr() as *mut _, self.as_bytes().len()) }
     }
+
+    #[cfg(target_arch = "wasm32")]
+    fn randomize(&mut self) {
+        getrandom::getrandom(self.as_mut_bytes()).expect("Failed to generate random bytes")
+    }
 }
 
 unsafe impl<T: ContiguousBytes + ?Sized> Randomized for T {}
@@ -3,14 +3,21 @@
 
 use crate::types::*;
 
+#[cfg(not(target_arch = "wasm32"))]
 use libsodium_sys::sodium_memzero;
 
-/// A trait for zeroing out memory on drop using [`sodium_memzero`].
+/// A trait for zeroing out memory on drop using `sodium_memzero`, or [`zeroize`] on WebAssembly.
 pub unsafe trait Zeroed: ContiguousBytes {
+    #[cfg(not(target_arch = "wasm32"))]
     fn zero(&mut self) {
         unsafe { sodium_memzero(self.as_mut_bytes().as_mut_ptr() as *mut _, self.as_bytes().len()) }
     }
 
+    #[cfg(target_arch = "wasm32")]
+    fn zero(&mut self) {
+        zeroize::Zeroize::zeroize(self.as_mut_bytes())
+    }
+
     unsafe fn copy_and_zero(&mut self, other: &mut Self) {
         assert!(other.size() >= self.size(), "other must be larger than self");
 
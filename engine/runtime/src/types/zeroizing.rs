@@ -0,0 +1,297 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Containers for secrets, that are passed by value, e.g. passwords and mnemonics in the parameters of a
+//! procedure. Unlike a plain [`String`] or [`Vec`], they are zeroized when dropped, and deserialization doesn't
+//! leave copies of the secret behind in memory, that has been returned to the allocator.
+//!
+//! Buffers, that are owned by the deserializer itself, e.g. the input or a scratch buffer for escaped strings,
+//! can't be wiped from here and have to be zeroized by the caller.
+
+use core::{
+    fmt::{self, Debug, Formatter},
+    ops::Deref,
+};
+
+use serde::{
+    de::{Deserialize, Deserializer, Error, SeqAccess, Visitor},
+    ser::{Serialize, Serializer},
+};
+use zeroize::Zeroize;
+
+use crate::{ZeroizeOnDrop, DEBUG_MSG};
+
+/// A [`String`], that is zeroized when dropped
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct ZeroizingString(String);
+
+impl ZeroizingString {
+    /// Takes ownership of `value` without copying it
+    pub fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for ZeroizingString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for ZeroizingString {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+impl From<String> for ZeroizingString {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for ZeroizingString {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Zeroize for ZeroizingString {
+    fn zeroize(&mut self) {
+        self.0.zeroize()
+    }
+}
+
+impl ZeroizeOnDrop for ZeroizingString {}
+
+impl Drop for ZeroizingString {
+    fn drop(&mut self) {
+        self.zeroize()
+    }
+}
+
+impl Debug for ZeroizingString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", DEBUG_MSG)
+    }
+}
+
+impl Serialize for ZeroizingString {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+struct ZeroizingStringVisitor;
+
+impl<'de> Visitor<'de> for ZeroizingStringVisitor {
+    type Value = ZeroizingString;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str("a string")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        // allocated with the exact capacity, so that it is never reallocated
+        let mut string = String::with_capacity(value.len());
+        string.push_str(value);
+        Ok(ZeroizingString(string))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(ZeroizingString(value))
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        let value = core::str::from_utf8(value).map_err(|_| E::custom("invalid UTF-8"))?;
+        self.visit_str(value)
+    }
+
+    fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        match String::from_utf8(value) {
+            Ok(string) => Ok(ZeroizingString(string)),
+            Err(e) => {
+                e.into_bytes().zeroize();
+                Err(E::custom("invalid UTF-8"))
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ZeroizingString {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_string(ZeroizingStringVisitor)
+    }
+}
+
+/// A [`Vec<u8>`], that is zeroized when dropped
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct ZeroizingVec(Vec<u8>);
+
+impl ZeroizingVec {
+    /// Takes ownership of `value` without copying it
+    pub fn new(value: Vec<u8>) -> Self {
+        Self(value)
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Deref for ZeroizingVec {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for ZeroizingVec {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for ZeroizingVec {
+    fn from(value: Vec<u8>) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&[u8]> for ZeroizingVec {
+    fn from(value: &[u8]) -> Self {
+        Self(value.to_vec())
+    }
+}
+
+impl Zeroize for ZeroizingVec {
+    fn zeroize(&mut self) {
+        self.0.zeroize()
+    }
+}
+
+impl ZeroizeOnDrop for ZeroizingVec {}
+
+impl Drop for ZeroizingVec {
+    fn drop(&mut self) {
+        self.zeroize()
+    }
+}
+
+impl Debug for ZeroizingVec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", DEBUG_MSG)
+    }
+}
+
+impl Serialize for ZeroizingVec {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+struct ZeroizingVecVisitor;
+
+impl<'de> Visitor<'de> for ZeroizingVecVisitor {
+    type Value = ZeroizingVec;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of bytes")
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(ZeroizingVec(value.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(ZeroizingVec(value))
+    }
+
+    fn visit_seq<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        // the bytes are collected into a wrapper right away, so that they are zeroized on errors, too
+        let mut bytes = ZeroizingVec(Vec::with_capacity(access.size_hint().unwrap_or(0)));
+        while let Some(byte) = access.next_element()? {
+            // grow manually, a reallocation of the vector would leave the old buffer behind
+            if bytes.0.len() == bytes.0.capacity() {
+                let mut grown = Vec::with_capacity((bytes.0.capacity() * 2).max(32));
+                grown.extend_from_slice(&bytes.0);
+                bytes = ZeroizingVec(grown);
+            }
+            bytes.0.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+impl<'de> Deserialize<'de> for ZeroizingVec {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_byte_buf(ZeroizingVecVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zeroizing_serde() {
+        let string = ZeroizingString::from("correct horse battery staple");
+        let json = serde_json::to_string(&string).unwrap();
+        assert_eq!(json, "\"correct horse battery staple\"");
+        assert_eq!(serde_json::from_str::<ZeroizingString>(&json).unwrap(), string);
+        assert_eq!(format!("{:?}", string), DEBUG_MSG);
+
+        // more bytes than the initial capacity, that the size hint of serde_json suggests
+        let vec = ZeroizingVec::from((0..100).collect::<Vec<u8>>());
+        let json = serde_json::to_string(&vec).unwrap();
+        assert_eq!(serde_json::from_str::<ZeroizingVec>(&json).unwrap(), vec);
+        assert_eq!(format!("{:?}", vec), DEBUG_MSG);
+    }
+
+    #[test]
+    fn test_zeroizing_string_is_wiped() {
+        let mut string = ZeroizingString::from("secret");
+        string.zeroize();
+        assert!(string.is_empty());
+    }
+}
@@ -1,6 +1,8 @@
 // Copyright 2020-2021 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
+#[cfg(not(target_arch = "wasm32"))]
+use libsodium_sys::{sodium_init, sodium_mlock, sodium_munlock};
 use random::{distributions::Alphanumeric, thread_rng, Rng, RngCore};
 
 pub fn xor(payload: &[u8], noise: &[u8], size: usize) -> Vec<u8> {
@@ -28,3 +30,58 @@ pub fn random_fname(size: usize) -> String {
         .collect();
     fname
 }
+
+/// Checks if the process is allowed to lock memory pages into RAM.
+///
+/// A small probe buffer is locked and immediately unlocked again. This returns `false`
+/// if the platform does not support it or the limit for locked memory has been reached.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn memory_lock_available() -> bool {
+    let mut probe = [0u8; 32];
+    unsafe {
+        if sodium_init() == -1 {
+            return false;
+        }
+        let ptr = probe.as_mut_ptr() as *mut _;
+        if sodium_mlock(ptr, probe.len()) != 0 {
+            return false;
+        }
+        sodium_munlock(ptr, probe.len());
+    }
+    true
+}
+
+/// WebAssembly has a single linear memory without paging, that can be neither locked nor protected
+#[cfg(target_arch = "wasm32")]
+pub fn memory_lock_available() -> bool {
+    false
+}
+
+/// Returns `true`, if guarded memory is protected by the platform, i.e. it is inaccessible while locked
+/// and surrounded by guard pages. Without it, e.g. on WebAssembly, the memory types of this crate still
+/// zeroize their content and keep the non-contiguous layout, but a locked value can be read by anyone
+/// with access to the memory of the process.
+pub fn memory_protection_available() -> bool {
+    !cfg!(target_arch = "wasm32")
+}
+
+/// Returns the size of a memory page of the platform
+pub fn page_size() -> usize {
+    #[cfg(unix)]
+    {
+        match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+            size if size > 0 => size as usize,
+            _ => 4096,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        4096
+    }
+}
+
+/// Returns the number of locked memory pages, that are currently held by the guarded memory of this crate. The
+/// guard pages around each allocation are not included.
+pub fn locked_pages() -> usize {
+    crate::pool::pages_in_use()
+}
@@ -0,0 +1,28 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+// The mitigations apply to the whole process, so they are tested in their own binary, where they can't affect
+// other tests.
+
+#[cfg(target_os = "linux")]
+use runtime::harden_process;
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_harden_process() {
+    let mitigations = harden_process();
+    assert!(mitigations.core_dumps_disabled);
+    assert!(mitigations.not_dumpable);
+    assert!(!mitigations.mitigation_policies);
+
+    let mut limit = libc::rlimit {
+        rlim_cur: 1,
+        rlim_max: 1,
+    };
+    assert_eq!(unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut limit) }, 0);
+    assert_eq!(limit.rlim_cur, 0);
+    assert_eq!(unsafe { libc::prctl(libc::PR_GET_DUMPABLE, 0, 0, 0, 0) }, 0);
+
+    // applying the mitigations again must not fail
+    assert_eq!(harden_process(), mitigations);
+}
@@ -13,6 +13,7 @@
 //! - `vault`: logic and abstractions for the storage layer
 //! - `snapshot`: method for storing the state of the vault in a file
 //! - `store`: a simple unencrypted storage protocol
+//! - `time`: clocks, that also work in the browser
 //!
 //! ## WARNING
 //!
@@ -22,5 +23,6 @@
 
 pub mod snapshot;
 pub mod store;
+pub mod time;
 pub mod vault;
 pub use runtime;
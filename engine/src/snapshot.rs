@@ -11,8 +11,9 @@
 //!
 //! The data stored within a snapshot is considered opaque and uses 256 bit keys.
 //! It provides recommended ways to derive the snapshot encryption key from a user
-//! provided password. The format also allows using an authenticated data
-//! bytestring to further protect the offline snapshot files (one might consider
+//! provided password, see [`kdf`] for snapshots whose Argon2id parameters are kept
+//! in the file header, or to obtain it from a hardware authenticator, see [`challenge`]. The format also allows using
+//! an authenticated data bytestring to further protect the offline snapshot files (one might consider
 //! using a secondary user password strengthened by an HSM).
 //!
 //! The current version of the format is using X25519 together with an ephemeral
@@ -22,10 +23,23 @@
 //! Future versions, when the demands for larger snapshot sizes and/or random
 //! access is desired, might consider encrypting smaller chunks (B-trees?) or
 //! similar using per chunk derived ephemeral keys.
+//! The [`incremental`] format is a first step in this direction: it encrypts
+//! partitions separately and only appends the partitions that have changed.
+//!
+//! Files written with an older version of the format can be upgraded in place
+//! with the [`migration`] module.
 
+pub mod challenge;
 mod compression;
 pub mod files;
+pub mod incremental;
+pub mod info;
+pub mod kdf;
+pub mod migration;
+pub mod throttle;
 
 mod logic;
-pub use compression::{compress, decompress, Lz4DecodeError};
+mod progress;
+pub use compression::{compress, decompress, Compression, Lz4DecodeError};
 pub use logic::*;
+pub use progress::{Phase, Progress};
@@ -0,0 +1,173 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Snapshot files, whose key is the response of a hardware authenticator to a challenge.
+//!
+//! The challenge, i.e. the id of the credential on the authenticator and a random salt, is stored in the
+//! header of the snapshot file. To open the file, the challenge is read from the header and passed to the
+//! authenticator, e.g. as the salt of the FIDO2 `hmac-secret` extension, whose response is the snapshot key:
+//!
+//! ```text
+//! | MAGIC | CHALLENGE_VERSION | credential id length: u16 | credential id | salt | body |
+//! ```
+//!
+//! The body is identical to the body of a [`VERSION`][crate::snapshot::VERSION] snapshot. The complete
+//! header is part of the associated data of the body, so the challenge can not be altered.
+
+use std::{fs::File, io::Read, path::Path};
+
+use crypto::utils::rand;
+use zeroize::Zeroize;
+
+use crate::snapshot::{
+    compress, decompress,
+    logic::{read, write, write_atomically, Key, ReadError, WriteError, MAGIC},
+    Progress,
+};
+
+/// Version bytes of a snapshot file, whose key is the response to the challenge in its header
+pub const CHALLENGE_VERSION: [u8; 2] = [0x2, 0x3];
+
+/// The size of the salt in the challenge
+pub const SALT_SIZE: usize = 32;
+
+const PREFIX_LEN: usize = MAGIC.len() + CHALLENGE_VERSION.len() + 2;
+
+/// The challenge, that an authenticator answers with the snapshot key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    /// The id of the credential on the authenticator
+    pub credential_id: Vec<u8>,
+
+    /// A random salt, that the response is computed for
+    pub salt: [u8; SALT_SIZE],
+}
+
+impl Challenge {
+    /// Creates a challenge for the credential with `credential_id` and a new random salt
+    pub fn new(credential_id: Vec<u8>) -> Result<Self, WriteError> {
+        if credential_id.len() > u16::MAX as usize {
+            return Err(WriteError::CorruptedData("Credential id is too long".to_string()));
+        }
+        let mut salt = [0u8; SALT_SIZE];
+        rand::fill(&mut salt).map_err(|e| WriteError::GenerateRandom(format!("{}", e)))?;
+        Ok(Self { credential_id, salt })
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(PREFIX_LEN + self.credential_id.len() + SALT_SIZE);
+        header.extend_from_slice(&MAGIC);
+        header.extend_from_slice(&CHALLENGE_VERSION);
+        header.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        header.extend_from_slice(&self.credential_id);
+        header.extend_from_slice(&self.salt);
+        header
+    }
+}
+
+/// Compresses and encrypts `plain` with `key`, the response to `challenge`, and atomically writes it
+/// together with the challenge to `path`
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %path.display(), len = plain.len())))]
+pub fn write_to(
+    plain: &[u8],
+    path: &Path,
+    challenge: &Challenge,
+    key: &Key,
+    associated_data: &[u8],
+) -> Result<(), WriteError> {
+    let mut ciphertext = challenge.header();
+    let ad = header_ad(&ciphertext, associated_data);
+    let mut compressed = compress(plain);
+    let written = write(&compressed, &mut ciphertext, key, &ad);
+    compressed.zeroize();
+    written?;
+    write_atomically(&ciphertext, path, &mut |_: Progress| {})
+}
+
+/// Reads the challenge from the header of the snapshot file at `path`, that the key has to be obtained for
+pub fn read_challenge(path: &Path) -> Result<Challenge, ReadError> {
+    let mut content = Vec::new();
+    File::open(path)?.read_to_end(&mut content)?;
+    parse_header(&content).map(|(challenge, _)| challenge)
+}
+
+/// Reads and decrypts the snapshot file at `path` with `key`, the response to its challenge
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %path.display())))]
+pub fn read_from(path: &Path, key: &Key, associated_data: &[u8]) -> Result<Vec<u8>, ReadError> {
+    let mut content = Vec::new();
+    File::open(path)?.read_to_end(&mut content)?;
+
+    let (_, header_len) = parse_header(&content)?;
+    let (header, mut body) = content.split_at(header_len);
+    let mut compressed = read(&mut body, key, &header_ad(header, associated_data))?;
+    let plain = decompress(&compressed);
+    compressed.zeroize();
+
+    plain.map_err(|e| ReadError::CorruptedContent(format!("Decompression failed: {}", e)))
+}
+
+/// Checks the header and returns the challenge and the length of the header
+fn parse_header(content: &[u8]) -> Result<(Challenge, usize), ReadError> {
+    if content.len() < PREFIX_LEN || content[..MAGIC.len()] != MAGIC {
+        return Err(ReadError::InvalidFile);
+    }
+    let version = [content[MAGIC.len()], content[MAGIC.len() + 1]];
+    if version != CHALLENGE_VERSION {
+        return Err(ReadError::UnsupportedVersion {
+            expected: CHALLENGE_VERSION,
+            found: version,
+        });
+    }
+
+    let id_len = u16::from_be_bytes([content[PREFIX_LEN - 2], content[PREFIX_LEN - 1]]) as usize;
+    let header_len = PREFIX_LEN + id_len + SALT_SIZE;
+    if content.len() < header_len {
+        return Err(ReadError::InvalidFile);
+    }
+    let mut salt = [0u8; SALT_SIZE];
+    salt.copy_from_slice(&content[PREFIX_LEN + id_len..header_len]);
+
+    Ok((
+        Challenge {
+            credential_id: content[PREFIX_LEN..PREFIX_LEN + id_len].to_vec(),
+            salt,
+        },
+        header_len,
+    ))
+}
+
+/// The header is authenticated together with the associated data of the caller
+fn header_ad(header: &[u8], associated_data: &[u8]) -> Vec<u8> {
+    let mut ad = header.to_vec();
+    ad.extend_from_slice(associated_data);
+    ad
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use stronghold_utils::random;
+
+    #[test]
+    fn test_challenge_write_read() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let challenge = Challenge::new(random::variable_bytestring(64)).unwrap();
+        let key: Key = random::random();
+        let data = random::variable_bytestring(4096);
+        write_to(&data, &pb, &challenge, &key, &[]).unwrap();
+
+        assert_eq!(read_challenge(&pb).unwrap(), challenge);
+        assert_eq!(read_from(&pb, &key, &[]).unwrap(), data);
+        assert!(read_from(&pb, &random::random(), &[]).is_err());
+
+        // the challenge is authenticated
+        let mut content = std::fs::read(&pb).unwrap();
+        let last_salt_byte = PREFIX_LEN + challenge.credential_id.len() + SALT_SIZE - 1;
+        content[last_salt_byte] ^= 1;
+        std::fs::write(&pb, content).unwrap();
+        assert!(read_from(&pb, &key, &[]).is_err());
+    }
+}
@@ -7,6 +7,55 @@ mod encoder;
 pub use decoder::{decompress, Lz4DecodeError};
 pub use encoder::compress;
 
+/// Compression algorithm of the snapshot content. The algorithm is recorded in the header of the
+/// snapshot file, so files can be read without knowing how they have been written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// The content is stored uncompressed
+    None,
+
+    /// The built-in LZ4 implementation
+    #[default]
+    Lz4,
+
+    /// Zstandard with the given compression level, that gives better ratios for large payloads
+    Zstd { level: i32 },
+}
+
+impl Compression {
+    const ID_NONE: u8 = 0;
+    const ID_LZ4: u8 = 1;
+    const ID_ZSTD: u8 = 2;
+
+    /// Identifies the algorithm in the snapshot header. The level is only needed for compressing, so it
+    /// is not recorded.
+    pub(crate) fn id(&self) -> u8 {
+        match self {
+            Compression::None => Self::ID_NONE,
+            Compression::Lz4 => Self::ID_LZ4,
+            Compression::Zstd { .. } => Self::ID_ZSTD,
+        }
+    }
+
+    pub(crate) fn compress(&self, plain: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            Compression::None => Ok(plain.to_vec()),
+            Compression::Lz4 => Ok(compress(plain)),
+            Compression::Zstd { level } => zstd::encode_all(plain, *level).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Decompresses `compressed` with the algorithm identified by `id`
+    pub(crate) fn decompress(id: u8, compressed: &[u8]) -> Result<Vec<u8>, String> {
+        match id {
+            Self::ID_NONE => Ok(compressed.to_vec()),
+            Self::ID_LZ4 => decompress(compressed).map_err(|e| e.to_string()),
+            Self::ID_ZSTD => zstd::decode_all(compressed).map_err(|e| e.to_string()),
+            id => Err(format!("Unknown compression algorithm {}", id)),
+        }
+    }
+}
+
 /// Block for the LZ4 compression algorithm.
 #[derive(Debug)]
 pub(crate) struct Block {
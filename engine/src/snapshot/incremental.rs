@@ -0,0 +1,469 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Incremental snapshot files.
+//!
+//! An incremental snapshot stores its content in named partitions, e.g. one partition per client.
+//! Each partition is encrypted on its own, using a fresh ephemeral key per record. Writing the
+//! snapshot only appends the partitions that have changed since the last write, and a tombstone
+//! for each partition that has been removed. Once the superseded records take up too much space,
+//! the file is compacted by atomically rewriting it with the live partitions only.
+//!
+//! The file starts with the [`MAGIC`] bytes and [`INCREMENTAL_VERSION`], followed by a sequence of
+//! records:
+//!
+//! ```text
+//! | partition id length: u32 | partition id | sealed length: u64 | sealed record |
+//! ```
+//!
+//! The associated data of each sealed record binds it to the partition id and its position in the
+//! file, so records can neither be moved between partitions nor be replayed at a later position.
+//! Truncating the file to an earlier record boundary can not be detected.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crypto::hashes::{blake2b::Blake2b256, Digest};
+use zeroize::Zeroize;
+
+use crate::snapshot::{
+    compress, decompress,
+    logic::{read, write, write_atomically, Key, ReadError, WriteError, MAGIC},
+    Progress,
+};
+
+/// Version bytes of an incremental snapshot file
+pub const INCREMENTAL_VERSION: [u8; 2] = [0x3, 0x0];
+
+/// The file is compacted, if it is larger than this factor times the size of its live records
+pub const DEFAULT_COMPACTION_FACTOR: u64 = 4;
+
+const HEADER_LEN: usize = MAGIC.len() + INCREMENTAL_VERSION.len();
+
+/// The content of the partitions of an incremental snapshot by their ids
+pub type Partitions = HashMap<Vec<u8>, Vec<u8>>;
+
+const KIND_DATA: u8 = 0;
+const KIND_TOMBSTONE: u8 = 1;
+
+/// A handle to an incremental snapshot file, that keeps track of the partitions written so far.
+pub struct IncrementalSnapshot {
+    path: PathBuf,
+    key: Key,
+    associated_data: Vec<u8>,
+
+    /// Digest of the plaintext and size of the latest record of each live partition
+    partitions: HashMap<Vec<u8>, ([u8; 32], u64)>,
+
+    /// Sequence number of the next record
+    next_seq: u64,
+
+    /// Length of the valid content of the file
+    file_len: u64,
+
+    compaction_factor: u64,
+}
+
+impl IncrementalSnapshot {
+    /// Creates a new incremental snapshot at `path` with the given `partitions`. An existing file is replaced.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %path.display())))]
+    pub fn create(path: &Path, key: &Key, associated_data: &[u8], partitions: &Partitions) -> Result<Self, WriteError> {
+        let mut snapshot = Self {
+            path: path.to_path_buf(),
+            key: *key,
+            associated_data: associated_data.to_vec(),
+            partitions: HashMap::new(),
+            next_seq: 0,
+            file_len: 0,
+            compaction_factor: DEFAULT_COMPACTION_FACTOR,
+        };
+        snapshot.compact(partitions)?;
+        Ok(snapshot)
+    }
+
+    /// Opens the incremental snapshot at `path` and returns it together with the content of all partitions.
+    ///
+    /// An incomplete record at the end of the file, e.g. from an interrupted write, is ignored and
+    /// overwritten by the next write.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %path.display())))]
+    pub fn open(path: &Path, key: &Key, associated_data: &[u8]) -> Result<(Self, Partitions), ReadError> {
+        let mut content = Vec::new();
+        File::open(path)?.read_to_end(&mut content)?;
+
+        if content.len() < HEADER_LEN || content[..MAGIC.len()] != MAGIC {
+            return Err(ReadError::InvalidFile);
+        }
+        let mut version = [0u8; 2];
+        version.copy_from_slice(&content[MAGIC.len()..HEADER_LEN]);
+        if version != INCREMENTAL_VERSION {
+            return Err(ReadError::UnsupportedVersion {
+                expected: INCREMENTAL_VERSION,
+                found: version,
+            });
+        }
+
+        let mut snapshot = Self {
+            path: path.to_path_buf(),
+            key: *key,
+            associated_data: associated_data.to_vec(),
+            partitions: HashMap::new(),
+            next_seq: 0,
+            file_len: HEADER_LEN as u64,
+            compaction_factor: DEFAULT_COMPACTION_FACTOR,
+        };
+
+        let mut data = HashMap::new();
+        let mut offset = HEADER_LEN;
+        while let Some((id, sealed, len)) = next_record(&content[offset..]) {
+            let ad = snapshot.record_ad(snapshot.next_seq, id);
+            let mut plain = read(&mut &*sealed, key, &ad)?;
+            let result = match plain.split_first() {
+                Some((&KIND_DATA, compressed)) => decompress(compressed)
+                    .map_err(|e| ReadError::CorruptedContent(format!("Decompression failed: {}", e)))
+                    .map(|partition| {
+                        snapshot
+                            .partitions
+                            .insert(id.to_vec(), (digest(&partition), len as u64));
+                        data.insert(id.to_vec(), partition);
+                    }),
+                Some((&KIND_TOMBSTONE, [])) => {
+                    snapshot.partitions.remove(id);
+                    data.remove(id);
+                    Ok(())
+                }
+                _ => Err(ReadError::CorruptedContent("Unknown record type".into())),
+            };
+            plain.zeroize();
+            result?;
+
+            snapshot.next_seq += 1;
+            offset += len;
+            snapshot.file_len = offset as u64;
+        }
+
+        Ok((snapshot, data))
+    }
+
+    /// Sets the factor by which the file may exceed the size of its live records, before it is compacted
+    pub fn set_compaction_factor(&mut self, factor: u64) {
+        self.compaction_factor = factor.max(1);
+    }
+
+    /// Writes `partitions` into the snapshot. Only partitions that have changed since the last write are
+    /// encrypted and appended, and partitions that are missing in `partitions` are removed. Returns the
+    /// number of appended records.
+    ///
+    /// The file is compacted afterwards, if it has grown beyond the compaction factor.
+    pub fn write(&mut self, partitions: &Partitions) -> Result<usize, WriteError> {
+        let removed: Vec<Vec<u8>> = self
+            .partitions
+            .keys()
+            .filter(|id| !partitions.contains_key(*id))
+            .cloned()
+            .collect();
+
+        let appended = self.update(partitions, &removed)?;
+        if self.needs_compaction() {
+            self.compact(partitions)?;
+        }
+        Ok(appended)
+    }
+
+    /// Appends the `changed` partitions and a tombstone for each of the `removed` partitions, without
+    /// touching any other partition. Partitions whose content has not changed and removed partitions that
+    /// do not exist are skipped. Returns the number of appended records.
+    ///
+    /// Other than [`Self::write`], this never compacts the file. Use [`Self::needs_compaction`] to check,
+    /// if [`Self::compact`] should be called.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %self.path.display(), changed = changed.len(), removed = removed.len())))]
+    pub fn update(&mut self, changed: &Partitions, removed: &[Vec<u8>]) -> Result<usize, WriteError> {
+        let mut records = Vec::new();
+        let mut updated = Vec::new();
+        let mut seq = self.next_seq;
+
+        for (id, partition) in sorted(changed) {
+            let partition_digest = digest(partition);
+            if matches!(self.partitions.get(id), Some((d, _)) if *d == partition_digest) {
+                continue;
+            }
+            let len = self.append_record(&mut records, seq, id, KIND_DATA, partition)?;
+            updated.push((id.clone(), Some((partition_digest, len))));
+            seq += 1;
+        }
+
+        let mut removed: Vec<&Vec<u8>> = removed
+            .iter()
+            .filter(|id| self.partitions.contains_key(*id) && !changed.contains_key(*id))
+            .collect();
+        removed.sort();
+        removed.dedup();
+        for id in removed {
+            self.append_record(&mut records, seq, id, KIND_TOMBSTONE, &[])?;
+            updated.push((id.clone(), None));
+            seq += 1;
+        }
+
+        if updated.is_empty() {
+            return Ok(0);
+        }
+
+        let mut f = OpenOptions::new().write(true).open(&self.path)?;
+        // drop an incomplete record from an interrupted write
+        f.set_len(self.file_len)?;
+        f.seek(SeekFrom::End(0))?;
+        f.write_all(&records)?;
+        f.sync_all()?;
+
+        self.file_len += records.len() as u64;
+        self.next_seq = seq;
+        let appended = updated.len();
+        for (id, entry) in updated {
+            match entry {
+                Some(entry) => self.partitions.insert(id, entry),
+                None => self.partitions.remove(&id),
+            };
+        }
+
+        Ok(appended)
+    }
+
+    /// Returns `true`, if the file has grown beyond the compaction factor times the size of its live records
+    pub fn needs_compaction(&self) -> bool {
+        let live_len = HEADER_LEN as u64 + self.partitions.values().map(|(_, len)| len).sum::<u64>();
+        self.file_len > self.compaction_factor.saturating_mul(live_len)
+    }
+
+    /// Atomically rewrites the file with a single record per partition in `partitions`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %self.path.display(), partitions = partitions.len())))]
+    pub fn compact(&mut self, partitions: &Partitions) -> Result<(), WriteError> {
+        let mut content = Vec::with_capacity(HEADER_LEN);
+        content.extend_from_slice(&MAGIC);
+        content.extend_from_slice(&INCREMENTAL_VERSION);
+
+        let mut compacted = HashMap::new();
+        for (seq, (id, partition)) in sorted(partitions).into_iter().enumerate() {
+            let len = self.append_record(&mut content, seq as u64, id, KIND_DATA, partition)?;
+            compacted.insert(id.clone(), (digest(partition), len));
+        }
+
+        write_atomically(&content, &self.path, &mut |_: Progress| {})?;
+
+        self.partitions = compacted;
+        self.next_seq = partitions.len() as u64;
+        self.file_len = content.len() as u64;
+        Ok(())
+    }
+
+    /// Returns `true`, if the snapshot is encrypted with `key`
+    pub fn key_matches(&self, key: &Key) -> bool {
+        self.key.iter().zip(key.iter()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+    }
+
+    /// Returns the associated data, that the records are authenticated with
+    pub fn associated_data(&self) -> &[u8] {
+        &self.associated_data
+    }
+
+    /// Returns the ids of all live partitions
+    pub fn partitions(&self) -> Vec<Vec<u8>> {
+        self.partitions.keys().cloned().collect()
+    }
+
+    /// Seals a record and appends it to `output`. Returns the length of the appended record.
+    fn append_record(
+        &self,
+        output: &mut Vec<u8>,
+        seq: u64,
+        id: &[u8],
+        kind: u8,
+        partition: &[u8],
+    ) -> Result<u64, WriteError> {
+        let mut plain = vec![kind];
+        if kind == KIND_DATA {
+            plain.extend_from_slice(&compress(partition));
+        }
+        let mut sealed = Vec::new();
+        let written = write(&plain, &mut sealed, &self.key, &self.record_ad(seq, id));
+        plain.zeroize();
+        written?;
+
+        let start = output.len();
+        output.extend_from_slice(&(id.len() as u32).to_be_bytes());
+        output.extend_from_slice(id);
+        output.extend_from_slice(&(sealed.len() as u64).to_be_bytes());
+        output.extend_from_slice(&sealed);
+        Ok((output.len() - start) as u64)
+    }
+
+    /// Associated data of the record at position `seq`
+    fn record_ad(&self, seq: u64, id: &[u8]) -> Vec<u8> {
+        let mut ad = self.associated_data.clone();
+        ad.extend_from_slice(&seq.to_be_bytes());
+        ad.extend_from_slice(&(id.len() as u32).to_be_bytes());
+        ad.extend_from_slice(id);
+        ad
+    }
+}
+
+impl Drop for IncrementalSnapshot {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+/// Parses the record at the start of `input`. Returns the partition id, the sealed record and the
+/// total length of the record, or `None`, if `input` does not contain a complete record.
+fn next_record(input: &[u8]) -> Option<(&[u8], &[u8], usize)> {
+    let id_len = u32::from_be_bytes(input.get(..4)?.try_into().ok()?) as usize;
+    let id = input.get(4..4 + id_len)?;
+    let offset = 4 + id_len;
+    let sealed_len = u64::from_be_bytes(input.get(offset..offset + 8)?.try_into().ok()?) as usize;
+    let sealed = input.get(offset + 8..offset.checked_add(8)?.checked_add(sealed_len)?)?;
+    Some((id, sealed, offset + 8 + sealed_len))
+}
+
+fn digest(data: &[u8]) -> [u8; 32] {
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&Blake2b256::digest(data));
+    digest
+}
+
+/// Returns the partitions ordered by id, so that the written file does not depend on the iteration order
+fn sorted(partitions: &Partitions) -> Vec<(&Vec<u8>, &Vec<u8>)> {
+    partitions.iter().collect::<BTreeMap<_, _>>().into_iter().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crypto::utils::rand;
+    use stronghold_utils::random;
+
+    fn random_key() -> Key {
+        let mut key: Key = [0u8; 32];
+        rand::fill(&mut key).expect("Unable to fill buffer");
+        key
+    }
+
+    fn random_partitions(n: usize) -> HashMap<Vec<u8>, Vec<u8>> {
+        (0..n)
+            .map(|_| (random::fixed_bytestring(32), random::fixed_bytestring(4096)))
+            .collect()
+    }
+
+    #[test]
+    fn test_incremental_write_read() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let key = random_key();
+        let ad = random::variable_bytestring(64);
+        let mut partitions = random_partitions(4);
+
+        let mut snapshot = IncrementalSnapshot::create(&pb, &key, &ad, &partitions).unwrap();
+        snapshot.set_compaction_factor(u64::MAX);
+
+        // nothing changed
+        assert_eq!(snapshot.write(&partitions).unwrap(), 0);
+        let len = std::fs::metadata(&pb).unwrap().len();
+
+        // change one partition and remove another one
+        let mut ids: Vec<Vec<u8>> = partitions.keys().cloned().collect();
+        ids.sort();
+        partitions.insert(ids[0].clone(), random::fixed_bytestring(4096));
+        partitions.remove(&ids[1]);
+        assert_eq!(snapshot.write(&partitions).unwrap(), 2);
+        assert!(std::fs::metadata(&pb).unwrap().len() > len);
+
+        let (_, read) = IncrementalSnapshot::open(&pb, &key, &ad).unwrap();
+        assert_eq!(read, partitions);
+
+        assert!(IncrementalSnapshot::open(&pb, &random_key(), &ad).is_err());
+        assert!(IncrementalSnapshot::open(&pb, &key, &random::variable_bytestring(64)).is_err());
+    }
+
+    #[test]
+    fn test_incremental_compaction() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let key = random_key();
+        let mut partitions = random_partitions(2);
+        let mut snapshot = IncrementalSnapshot::create(&pb, &key, &[], &partitions).unwrap();
+        snapshot.set_compaction_factor(2);
+
+        let id = partitions.keys().next().unwrap().clone();
+        for _ in 0..16 {
+            partitions.insert(id.clone(), random::fixed_bytestring(4096));
+            assert_eq!(snapshot.write(&partitions).unwrap(), 1);
+        }
+
+        let live: u64 = HEADER_LEN as u64 + snapshot.partitions.values().map(|(_, len)| len).sum::<u64>();
+        assert!(std::fs::metadata(&pb).unwrap().len() <= 2 * live);
+
+        let (_, read) = IncrementalSnapshot::open(&pb, &key, &[]).unwrap();
+        assert_eq!(read, partitions);
+    }
+
+    #[test]
+    fn test_incremental_interrupted_write() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let key = random_key();
+        let mut partitions = random_partitions(2);
+        IncrementalSnapshot::create(&pb, &key, &[], &partitions).unwrap();
+
+        // simulate a record that has only partially been written
+        let mut f = OpenOptions::new().append(true).open(&pb).unwrap();
+        f.write_all(&[0, 0, 0, 32, 1, 2, 3]).unwrap();
+        drop(f);
+
+        let (mut snapshot, read) = IncrementalSnapshot::open(&pb, &key, &[]).unwrap();
+        assert_eq!(read, partitions);
+
+        snapshot.set_compaction_factor(u64::MAX);
+        partitions.insert(random::fixed_bytestring(32), random::fixed_bytestring(4096));
+        assert_eq!(snapshot.write(&partitions).unwrap(), 1);
+
+        let (_, read) = IncrementalSnapshot::open(&pb, &key, &[]).unwrap();
+        assert_eq!(read, partitions);
+    }
+
+    #[test]
+    fn test_incremental_update() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let key = random_key();
+        let mut partitions = random_partitions(3);
+        let mut snapshot = IncrementalSnapshot::create(&pb, &key, &[], &partitions).unwrap();
+
+        let mut ids: Vec<Vec<u8>> = partitions.keys().cloned().collect();
+        ids.sort();
+
+        // only the given partitions are touched
+        let changed: HashMap<Vec<u8>, Vec<u8>> = [(ids[0].clone(), random::fixed_bytestring(4096))].into();
+        assert_eq!(snapshot.update(&changed, &[]).unwrap(), 1);
+        assert_eq!(snapshot.update(&changed, &[]).unwrap(), 0);
+        partitions.extend(changed);
+
+        // removing an unknown partition is a no-op
+        assert_eq!(
+            snapshot.update(&HashMap::new(), &[ids[1].clone(), vec![0; 8]]).unwrap(),
+            1
+        );
+        partitions.remove(&ids[1]);
+
+        let (_, read) = IncrementalSnapshot::open(&pb, &key, &[]).unwrap();
+        assert_eq!(read, partitions);
+    }
+}
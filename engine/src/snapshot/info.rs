@@ -0,0 +1,127 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Inspection of snapshot files without their key.
+//!
+//! Only the unencrypted header and the metadata of the file system are read. The content of the
+//! snapshot, e.g. its clients, stays encrypted.
+
+use std::{fs::File, io::Read, path::Path, time::SystemTime};
+
+use crate::snapshot::{
+    challenge::CHALLENGE_VERSION,
+    incremental::INCREMENTAL_VERSION,
+    kdf::{self, Argon2Params, KDF_VERSION},
+    logic::{ReadError, COMPRESSION_VERSION, MAGIC, VERSION},
+};
+
+/// The layout of a snapshot file, as identified by its version bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    /// The content is encrypted with a key, that is provided by the application
+    Key,
+
+    /// The key is derived from a password with the parameters in the header, see [`kdf`]
+    Password,
+
+    /// The key is the response of a hardware authenticator to the challenge in the header, see
+    /// [`challenge`][crate::snapshot::challenge]
+    Challenge,
+
+    /// The content is stored in separately encrypted partitions, see
+    /// [`incremental`][crate::snapshot::incremental]
+    Incremental,
+
+    /// A version, that is not supported by this release. It may need to be migrated, or it has been
+    /// written by a newer release.
+    Unknown,
+}
+
+/// Metadata of a snapshot file, that can be read without its key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    /// The version bytes of the header
+    pub version: [u8; 2],
+
+    /// The format, that the version stands for
+    pub format: SnapshotFormat,
+
+    /// The key derivation parameters of a [`SnapshotFormat::Password`] snapshot
+    pub kdf_params: Option<Argon2Params>,
+
+    /// The time the file has been written. Snapshot files are replaced as a whole on each write, so this
+    /// is the time of the last write. `None`, if the file system doesn't record it.
+    pub created_at: Option<SystemTime>,
+
+    /// The size of the file in bytes
+    pub file_size: u64,
+}
+
+/// Reads the [`SnapshotInfo`] of the snapshot file at `path`. Fails with [`ReadError::InvalidFile`], if
+/// the file does not start with the snapshot [`MAGIC`] bytes.
+pub fn read_info(path: &Path) -> Result<SnapshotInfo, ReadError> {
+    let mut file = File::open(path)?;
+    let metadata = file.metadata()?;
+
+    let mut header = [0u8; MAGIC.len() + 2];
+    file.read_exact(&mut header).map_err(|_| ReadError::InvalidFile)?;
+    if header[..MAGIC.len()] != MAGIC {
+        return Err(ReadError::InvalidFile);
+    }
+    let version = [header[MAGIC.len()], header[MAGIC.len() + 1]];
+
+    let (format, kdf_params) = match version {
+        VERSION | COMPRESSION_VERSION => (SnapshotFormat::Key, None),
+        KDF_VERSION => (SnapshotFormat::Password, Some(kdf::read_params(path)?)),
+        CHALLENGE_VERSION => (SnapshotFormat::Challenge, None),
+        INCREMENTAL_VERSION => (SnapshotFormat::Incremental, None),
+        _ => (SnapshotFormat::Unknown, None),
+    };
+
+    Ok(SnapshotInfo {
+        version,
+        format,
+        kdf_params,
+        created_at: metadata.created().or_else(|_| metadata.modified()).ok(),
+        file_size: metadata.len(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::snapshot::write_to;
+    use stronghold_utils::random;
+
+    #[test]
+    fn test_read_info() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = [7u8; 32];
+        let data = random::variable_bytestring(1024);
+
+        let path = dir.path().join("key.snapshot");
+        write_to(&data, &path, &key, &[]).unwrap();
+        let info = read_info(&path).unwrap();
+        assert_eq!(info.version, VERSION);
+        assert_eq!(info.format, SnapshotFormat::Key);
+        assert_eq!(info.kdf_params, None);
+        assert_eq!(info.file_size, std::fs::metadata(&path).unwrap().len());
+        assert!(info.created_at.is_some());
+
+        let params = Argon2Params {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let path = dir.path().join("password.snapshot");
+        kdf::write_to(&data, &path, b"password", &params, &[]).unwrap();
+        let info = read_info(&path).unwrap();
+        assert_eq!(info.version, KDF_VERSION);
+        assert_eq!(info.format, SnapshotFormat::Password);
+        assert_eq!(info.kdf_params, Some(params));
+
+        let path = dir.path().join("invalid");
+        std::fs::write(&path, b"not a snapshot").unwrap();
+        assert!(matches!(read_info(&path), Err(ReadError::InvalidFile)));
+    }
+}
@@ -0,0 +1,522 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Password protected snapshot files.
+//!
+//! The snapshot key is derived from a password with Argon2id. The parameters of the derivation and a
+//! random salt are stored in the header of the snapshot file, so that the file can be opened with
+//! the password alone, and the parameters can be raised over time without breaking older files:
+//!
+//! ```text
+//! | MAGIC | KDF_VERSION | algorithm: u8 | memory: u32 | iterations: u32 | parallelism: u32 | salt | body |
+//! ```
+//!
+//! The body is identical to the body of a [`VERSION`][crate::snapshot::VERSION] snapshot. The complete
+//! header is part of the associated data of the body, so the parameters can not be altered.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Read,
+    path::Path,
+};
+
+use crypto::utils::rand;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::snapshot::{
+    compress, decompress,
+    logic::{
+        check_header, check_min_file_len, read, write, write_atomically, Key, ReadError, RekeyError, WriteError, MAGIC,
+    },
+    Progress,
+};
+
+/// Version bytes of a snapshot file, whose key is derived from a password
+pub const KDF_VERSION: [u8; 2] = [0x2, 0x1];
+
+/// Identifies Argon2id in the header
+const ALGORITHM_ARGON2ID: u8 = 1;
+
+const SALT_SIZE: usize = 16;
+
+const PARAMS_LEN: usize = 1 + 3 * 4 + SALT_SIZE;
+
+const HEADER_LEN: usize = MAGIC.len() + KDF_VERSION.len() + PARAMS_LEN;
+
+/// Maximum memory usage in KiB, that is accepted in the header, four times the default
+pub const MAX_MEMORY_KIB: u32 = 256 * 1024;
+
+/// Maximum number of iterations, that is accepted in the header
+pub const MAX_ITERATIONS: u32 = 64;
+
+/// Maximum number of lanes, that is accepted in the header
+pub const MAX_PARALLELISM: u32 = 16;
+
+/// Parameters of the Argon2id key derivation.
+///
+/// The defaults follow the recommendation of RFC 9106 for memory constrained environments. Higher
+/// values make brute forcing the password more expensive, but also slow down opening the snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Params {
+    /// Memory usage in KiB
+    pub memory_kib: u32,
+
+    /// Number of passes over the memory
+    pub iterations: u32,
+
+    /// Number of lanes
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Params {
+    fn default() -> Self {
+        Self {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 4,
+        }
+    }
+}
+
+impl Argon2Params {
+    /// Checks, that the parameters are within [`MAX_MEMORY_KIB`], [`MAX_ITERATIONS`] and [`MAX_PARALLELISM`].
+    ///
+    /// The parameters are read from the header before the password can be checked, so the limits keep a crafted
+    /// file from exhausting the memory or the CPU.
+    pub fn check_limits(&self) -> Result<(), String> {
+        if self.memory_kib > MAX_MEMORY_KIB || self.iterations > MAX_ITERATIONS || self.parallelism > MAX_PARALLELISM {
+            return Err("Argon2id parameters exceed the limits".to_string());
+        }
+        Ok(())
+    }
+
+    /// Derives a snapshot [`Key`] from `password` and `salt`
+    pub fn derive_key(&self, password: &[u8], salt: &[u8]) -> Result<Key, String> {
+        let config = argon2::Config {
+            variant: argon2::Variant::Argon2id,
+            version: argon2::Version::Version13,
+            mem_cost: self.memory_kib,
+            time_cost: self.iterations,
+            lanes: self.parallelism,
+            hash_length: 32,
+            ..Default::default()
+        };
+        let mut hash = argon2::hash_raw(password, salt, &config).map_err(|e| e.to_string())?;
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&hash);
+        hash.zeroize();
+        Ok(key)
+    }
+}
+
+/// A snapshot key, that has been derived from a password, together with the parameters and the salt of
+/// the derivation. Snapshot files can be written with it repeatedly, without deriving the key again for
+/// each write.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct PasswordKey {
+    #[zeroize(skip)]
+    params: Argon2Params,
+    salt: [u8; SALT_SIZE],
+    key: Key,
+}
+
+impl PasswordKey {
+    /// Derives a key from `password` with `params` and a new random salt
+    pub fn new(password: &[u8], params: &Argon2Params) -> Result<Self, WriteError> {
+        params.check_limits().map_err(WriteError::CorruptedData)?;
+        let mut salt = [0u8; SALT_SIZE];
+        rand::fill(&mut salt).map_err(|e| WriteError::GenerateRandom(format!("{}", e)))?;
+        let key = params
+            .derive_key(password, &salt)
+            .map_err(|e| WriteError::CorruptedData(format!("Key derivation failed: {}", e)))?;
+        Ok(Self {
+            params: *params,
+            salt,
+            key,
+        })
+    }
+
+    /// Derives the key of the snapshot file at `path` from `password`, with the parameters and the salt
+    /// in its header. The password is not checked, until the file is read with the key.
+    pub fn from_header(path: &Path, password: &[u8]) -> Result<Self, ReadError> {
+        let mut header = [0u8; HEADER_LEN];
+        File::open(path)?
+            .read_exact(&mut header)
+            .map_err(|_| ReadError::InvalidFile)?;
+        let (params, salt) = parse_header(&header)?;
+        Self::derive(password, params, salt)
+    }
+
+    /// Returns the parameters of the derivation
+    pub fn params(&self) -> Argon2Params {
+        self.params
+    }
+
+    /// Returns the derived key
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    fn derive(password: &[u8], params: Argon2Params, salt: [u8; SALT_SIZE]) -> Result<Self, ReadError> {
+        let key = params
+            .derive_key(password, &salt)
+            .map_err(|e| ReadError::CorruptedContent(format!("Key derivation failed: {}", e)))?;
+        Ok(Self { params, salt, key })
+    }
+}
+
+/// Compresses and encrypts `plain` with a key, that is derived from `password`, and atomically writes
+/// it to `path`. A new random salt is used for each write.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %path.display(), len = plain.len())))]
+pub fn write_to(
+    plain: &[u8],
+    path: &Path,
+    password: &[u8],
+    params: &Argon2Params,
+    associated_data: &[u8],
+) -> Result<(), WriteError> {
+    write_compressed(&compress(plain), path, password, params, associated_data)
+}
+
+/// Same as [`write_to`], but encrypts `plain` with `key` instead of deriving a new key. The file can be
+/// read with the password of `key`.
+pub fn write_with_key(plain: &[u8], path: &Path, key: &PasswordKey, associated_data: &[u8]) -> Result<(), WriteError> {
+    let ciphertext = seal_with_key(&compress(plain), key, associated_data)?;
+    write_atomically(&ciphertext, path, &mut |_: Progress| {})
+}
+
+/// Same as [`write_to`], but returns the content of the snapshot file instead of writing it, e.g. to
+/// transfer it to another device.
+pub fn encrypt(
+    plain: &[u8],
+    password: &[u8],
+    params: &Argon2Params,
+    associated_data: &[u8],
+) -> Result<Vec<u8>, WriteError> {
+    seal(&compress(plain), password, params, associated_data)
+}
+
+/// Reads and decrypts the snapshot file at `path` with `password`. Returns the decompressed content
+/// together with the derived key.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %path.display())))]
+pub fn read_from(path: &Path, password: &[u8], associated_data: &[u8]) -> Result<(Vec<u8>, Key), ReadError> {
+    let mut content = Vec::new();
+    File::open(path)?.read_to_end(&mut content)?;
+    decrypt(&content, password, associated_data)
+}
+
+/// Same as [`read_from`], but decrypts the `content` of a snapshot file, that has been returned by [`encrypt`]
+/// or read by the application, e.g. from the storage of a browser.
+pub fn decrypt(content: &[u8], password: &[u8], associated_data: &[u8]) -> Result<(Vec<u8>, Key), ReadError> {
+    let (params, salt) = parse_header(content)?;
+    let key = PasswordKey::derive(password, params, salt)?;
+    let plain = open(content, &key, associated_data)?;
+    Ok((plain, key.key))
+}
+
+/// Same as [`read_from`], but decrypts the snapshot file at `path` with `key`, e.g. the key that has been
+/// used to write it with [`write_with_key`]. Fails, if the file has been written with a different salt or
+/// different parameters.
+pub fn read_with_key(path: &Path, key: &PasswordKey, associated_data: &[u8]) -> Result<Vec<u8>, ReadError> {
+    let mut content = Vec::new();
+    File::open(path)?.read_to_end(&mut content)?;
+    let (params, salt) = parse_header(&content)?;
+    if params != key.params || salt != key.salt {
+        return Err(ReadError::CorruptedContent(
+            "The key has not been derived for this file".to_string(),
+        ));
+    }
+    open(&content, key, associated_data)
+}
+
+/// Returns the key derivation parameters of the snapshot file at `path`
+pub fn read_params(path: &Path) -> Result<Argon2Params, ReadError> {
+    let mut header = [0u8; HEADER_LEN];
+    File::open(path)?
+        .read_exact(&mut header)
+        .map_err(|_| ReadError::InvalidFile)?;
+    parse_header(&header).map(|(params, _)| params)
+}
+
+/// Atomically re-encrypts the snapshot file at `path`, that has been written with `old_key` in the
+/// [`VERSION`][crate::snapshot::VERSION] format, with a key that is derived from `password`. This
+/// upgrades snapshots whose key has been derived with a different KDF by the application.
+///
+/// Snapshots that are already password protected can be upgraded to new `params` in the same way, by
+/// passing the key that [`read_from`] has returned as `old_key`.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %path.display())))]
+pub fn upgrade(
+    path: &Path,
+    old_key: &Key,
+    password: &[u8],
+    params: &Argon2Params,
+    associated_data: &[u8],
+) -> Result<(), RekeyError> {
+    let mut f: File = OpenOptions::new().read(true).open(path).map_err(ReadError::from)?;
+    check_min_file_len(&mut f)?;
+
+    let mut content = Vec::new();
+    f.read_to_end(&mut content).map_err(ReadError::from)?;
+    drop(f);
+
+    let mut compressed = if content.get(MAGIC.len()..MAGIC.len() + KDF_VERSION.len()) == Some(&KDF_VERSION[..]) {
+        parse_header(&content)?;
+        let (header, mut body) = content.split_at(HEADER_LEN);
+        read(&mut body, old_key, &header_ad(header, associated_data))?
+    } else {
+        let mut input = content.as_slice();
+        check_header(&mut input)?;
+        read(&mut input, old_key, associated_data)?
+    };
+
+    let written = write_compressed(&compressed, path, password, params, associated_data);
+    compressed.zeroize();
+    written?;
+    Ok(())
+}
+
+fn write_compressed(
+    compressed: &[u8],
+    path: &Path,
+    password: &[u8],
+    params: &Argon2Params,
+    associated_data: &[u8],
+) -> Result<(), WriteError> {
+    let ciphertext = seal(compressed, password, params, associated_data)?;
+    write_atomically(&ciphertext, path, &mut |_: Progress| {})
+}
+
+/// Encrypts `compressed` with a key, that is derived from `password` with a new random salt, and
+/// prepends the header
+fn seal(
+    compressed: &[u8],
+    password: &[u8],
+    params: &Argon2Params,
+    associated_data: &[u8],
+) -> Result<Vec<u8>, WriteError> {
+    seal_with_key(compressed, &PasswordKey::new(password, params)?, associated_data)
+}
+
+/// Encrypts `compressed` with `key` and prepends the header with its parameters and salt
+fn seal_with_key(compressed: &[u8], key: &PasswordKey, associated_data: &[u8]) -> Result<Vec<u8>, WriteError> {
+    let mut ciphertext = Vec::with_capacity(HEADER_LEN + compressed.len());
+    ciphertext.extend_from_slice(&MAGIC);
+    ciphertext.extend_from_slice(&KDF_VERSION);
+    ciphertext.push(ALGORITHM_ARGON2ID);
+    ciphertext.extend_from_slice(&key.params.memory_kib.to_be_bytes());
+    ciphertext.extend_from_slice(&key.params.iterations.to_be_bytes());
+    ciphertext.extend_from_slice(&key.params.parallelism.to_be_bytes());
+    ciphertext.extend_from_slice(&key.salt);
+
+    let ad = header_ad(&ciphertext, associated_data);
+    write(compressed, &mut ciphertext, &key.key, &ad)?;
+
+    Ok(ciphertext)
+}
+
+/// Decrypts the body of `content` with `key`, that has been derived with the parameters and the salt in
+/// its header
+fn open(content: &[u8], key: &PasswordKey, associated_data: &[u8]) -> Result<Vec<u8>, ReadError> {
+    let (header, mut body) = content.split_at(HEADER_LEN);
+    let mut compressed = read(&mut body, &key.key, &header_ad(header, associated_data))?;
+    let plain = decompress(&compressed);
+    compressed.zeroize();
+
+    plain.map_err(|e| ReadError::CorruptedContent(format!("Decompression failed: {}", e)))
+}
+
+/// Checks the header and returns the key derivation parameters and the salt
+fn parse_header(content: &[u8]) -> Result<(Argon2Params, [u8; SALT_SIZE]), ReadError> {
+    if content.len() < HEADER_LEN || content[..MAGIC.len()] != MAGIC {
+        return Err(ReadError::InvalidFile);
+    }
+    let version = [content[MAGIC.len()], content[MAGIC.len() + 1]];
+    if version != KDF_VERSION {
+        return Err(ReadError::UnsupportedVersion {
+            expected: KDF_VERSION,
+            found: version,
+        });
+    }
+
+    let params = &content[MAGIC.len() + KDF_VERSION.len()..HEADER_LEN];
+    if params[0] != ALGORITHM_ARGON2ID {
+        return Err(ReadError::CorruptedContent(format!(
+            "Unknown KDF algorithm {}",
+            params[0]
+        )));
+    }
+    let u32_at = |offset: usize| {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&params[offset..offset + 4]);
+        u32::from_be_bytes(bytes)
+    };
+    let mut salt = [0u8; SALT_SIZE];
+    salt.copy_from_slice(&params[13..]);
+
+    let params = Argon2Params {
+        memory_kib: u32_at(1),
+        iterations: u32_at(5),
+        parallelism: u32_at(9),
+    };
+    params.check_limits().map_err(ReadError::CorruptedContent)?;
+    Ok((params, salt))
+}
+
+/// The header is authenticated together with the associated data of the caller
+fn header_ad(header: &[u8], associated_data: &[u8]) -> Vec<u8> {
+    let mut ad = header.to_vec();
+    ad.extend_from_slice(associated_data);
+    ad
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::snapshot::{read_from as read_from_key, write_to as write_to_key};
+    use stronghold_utils::random;
+
+    /// Cheap parameters to keep the tests fast
+    const PARAMS: Argon2Params = Argon2Params {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn test_password_write_read() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let data = random::variable_bytestring(4096);
+        let ad = random::variable_bytestring(64);
+        write_to(&data, &pb, b"password", &PARAMS, &ad).unwrap();
+
+        assert_eq!(read_params(&pb).unwrap(), PARAMS);
+        let (read, key) = read_from(&pb, b"password", &ad).unwrap();
+        assert_eq!(read, data);
+        assert_eq!(
+            key,
+            PARAMS
+                .derive_key(b"password", &parse_header(&std::fs::read(&pb).unwrap()).unwrap().1)
+                .unwrap()
+        );
+
+        assert!(read_from(&pb, b"wrong password", &ad).is_err());
+        assert!(read_from(&pb, b"password", &[]).is_err());
+
+        // the parameters are authenticated
+        let mut content = std::fs::read(&pb).unwrap();
+        content[MAGIC.len() + KDF_VERSION.len() + 4] ^= 1;
+        std::fs::write(&pb, content).unwrap();
+        assert!(read_from(&pb, b"password", &ad).is_err());
+    }
+
+    #[test]
+    fn test_password_encrypt() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let data = random::variable_bytestring(4096);
+        let content = encrypt(&data, b"password", &PARAMS, &[]).unwrap();
+        std::fs::write(&pb, &content).unwrap();
+
+        assert_eq!(read_params(&pb).unwrap(), PARAMS);
+        assert_eq!(read_from(&pb, b"password", &[]).unwrap().0, data);
+        assert_eq!(decrypt(&content, b"password", &[]).unwrap().0, data);
+        assert!(decrypt(&content, b"wrong password", &[]).is_err());
+
+        // each encryption uses a new salt
+        assert_ne!(encrypt(&data, b"password", &PARAMS, &[]).unwrap(), content);
+    }
+
+    #[test]
+    fn test_upgrade_kdf() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let old_key: Key = random::random();
+        let data = random::variable_bytestring(4096);
+        write_to_key(&data, &pb, &old_key, &[]).unwrap();
+
+        assert!(upgrade(&pb, &random::random(), b"password", &PARAMS, &[]).is_err());
+        upgrade(&pb, &old_key, b"password", &PARAMS, &[]).unwrap();
+        assert!(read_from_key(&pb, &old_key, &[]).is_err());
+        let (read, key) = read_from(&pb, b"password", &[]).unwrap();
+        assert_eq!(read, data);
+
+        // raise the parameters of a password protected snapshot
+        let stronger = Argon2Params {
+            iterations: 2,
+            ..PARAMS
+        };
+        upgrade(&pb, &key, b"password", &stronger, &[]).unwrap();
+        assert_eq!(read_params(&pb).unwrap(), stronger);
+        assert_eq!(read_from(&pb, b"password", &[]).unwrap().0, data);
+    }
+
+    #[test]
+    fn test_write_with_key() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let data = random::variable_bytestring(4096);
+        let key = PasswordKey::new(b"password", &PARAMS).unwrap();
+        write_with_key(&data, &pb, &key, &[]).unwrap();
+        assert_eq!(read_with_key(&pb, &key, &[]).unwrap(), data);
+        assert_eq!(read_from(&pb, b"password", &[]).unwrap().0, data);
+
+        let restored = PasswordKey::from_header(&pb, b"password").unwrap();
+        assert_eq!(restored.key(), key.key());
+        write_with_key(&data, &pb, &restored, &[]).unwrap();
+        assert_eq!(read_with_key(&pb, &key, &[]).unwrap(), data);
+
+        // a key of a different password or derivation can't read the file
+        let wrong = PasswordKey::from_header(&pb, b"wrong password").unwrap();
+        assert!(read_with_key(&pb, &wrong, &[]).is_err());
+        let other = PasswordKey::new(b"password", &PARAMS).unwrap();
+        assert!(read_with_key(&pb, &other, &[]).is_err());
+    }
+
+    #[test]
+    fn test_params_limits() {
+        let data = random::variable_bytestring(64);
+        let excessive = [
+            Argon2Params {
+                memory_kib: MAX_MEMORY_KIB + 1,
+                ..PARAMS
+            },
+            Argon2Params {
+                iterations: MAX_ITERATIONS + 1,
+                ..PARAMS
+            },
+            Argon2Params {
+                parallelism: MAX_PARALLELISM + 1,
+                ..PARAMS
+            },
+        ];
+        for params in excessive {
+            assert!(encrypt(&data, b"password", &params, &[]).is_err());
+        }
+
+        // a crafted header is rejected before the key is derived, the memory, the iterations and the
+        // parallelism follow the algorithm in the header
+        let content = encrypt(&data, b"password", &PARAMS, &[]).unwrap();
+        let params_offset = MAGIC.len() + KDF_VERSION.len() + 1;
+        for (field, value) in [MAX_MEMORY_KIB + 1, MAX_ITERATIONS + 1, MAX_PARALLELISM + 1]
+            .into_iter()
+            .enumerate()
+        {
+            let mut crafted = content.clone();
+            let offset = params_offset + 4 * field;
+            crafted[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+            assert!(matches!(
+                decrypt(&crafted, b"password", &[]),
+                Err(ReadError::CorruptedContent(_))
+            ));
+        }
+    }
+}
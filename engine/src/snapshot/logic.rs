@@ -14,8 +14,12 @@ use crypto::{
     utils::rand,
 };
 use thiserror::Error as DeriveError;
+use zeroize::Zeroize;
 
-use crate::snapshot::{compress, decompress};
+use crate::snapshot::{
+    progress::{Phase, Progress, ProgressReader},
+    Compression,
+};
 
 /// Magic bytes (bytes 0-4 in a snapshot file) aka PARTI
 pub const MAGIC: [u8; 5] = [0x50, 0x41, 0x52, 0x54, 0x49];
@@ -24,11 +28,20 @@ pub const MAGIC: [u8; 5] = [0x50, 0x41, 0x52, 0x54, 0x49];
 pub const VERSION: [u8; 2] = [0x2, 0x0];
 // pub const OLD_VERSION: [u8; 2] = [0x2, 0x0];
 
+/// Version bytes of a snapshot file, whose header is followed by the id of the [`Compression`]. Files
+/// compressed with [`Compression::Lz4`] are written with [`VERSION`].
+pub const COMPRESSION_VERSION: [u8; 2] = [0x2, 0x2];
+
+const COMPRESSION_HEADER_LEN: usize = MAGIC.len() + COMPRESSION_VERSION.len() + 1;
+
 /// Key size for the ephemeral key
 const KEY_SIZE: usize = 32;
 /// Key type alias.
 pub type Key = [u8; KEY_SIZE];
 
+/// Number of bytes written at once, between two progress reports
+const WRITE_CHUNK_SIZE: usize = 64 * 1024;
+
 /// Nonce size for XChaCha20Poly1305
 const NONCE_SIZE: usize = XChaCha20Poly1305::NONCE_LENGTH;
 /// Nonce type alias
@@ -42,6 +55,10 @@ pub enum ReadError {
     #[error("corrupted file: {0}")]
     CorruptedContent(String),
 
+    /// The content could not be authenticated, e.g. because it has been encrypted with another key
+    #[error("decryption failed: {0}")]
+    Decryption(String),
+
     #[error("invalid File: not a snapshot")]
     InvalidFile,
 
@@ -61,9 +78,44 @@ pub enum WriteError {
     CorruptedData(String),
 }
 
+/// Options for writing a snapshot file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Algorithm to compress the content with
+    pub compression: Compression,
+}
+
+#[derive(Debug, DeriveError)]
+pub enum RekeyError {
+    #[error("reading snapshot failed: {0}")]
+    Read(#[from] ReadError),
+
+    #[error("writing snapshot failed: {0}")]
+    Write(#[from] WriteError),
+}
+
 /// Encrypt the opaque plaintext bytestring using the specified [`Key`] and optional associated data
 /// and writes the ciphertext to the specifed output
 pub fn write<O: Write>(plain: &[u8], output: &mut O, key: &Key, associated_data: &[u8]) -> Result<(), WriteError> {
+    // secret key now expects an array
+    let mut key_bytes = [0u8; x25519::SECRET_KEY_LENGTH];
+    key_bytes.clone_from_slice(key);
+
+    // get `x25519` secret key from public key.
+    let pk = x25519::SecretKey::from_bytes(key_bytes).public_key();
+
+    seal(plain, output, &pk, associated_data)
+}
+
+/// Encrypt the opaque plaintext bytestring for the owner of the `x25519` public key `pk` and writes the
+/// ciphertext to the specified output. The ciphertext can be decrypted with [`read`] using the
+/// corresponding secret key.
+pub fn seal<O: Write>(
+    plain: &[u8],
+    output: &mut O,
+    pk: &x25519::PublicKey,
+    associated_data: &[u8],
+) -> Result<(), WriteError> {
     // create ephemeral key pair.
     let ephemeral_key = x25519::SecretKey::generate().map_err(|e| WriteError::GenerateRandom(format!("{}", e)))?;
 
@@ -75,17 +127,10 @@ pub fn write<O: Write>(plain: &[u8], output: &mut O, key: &Key, associated_data:
     // write public key into output.
     output.write_all(&ephemeral_pk_bytes)?;
 
-    // secret key now expects an array
-    let mut key_bytes = [0u8; x25519::SECRET_KEY_LENGTH];
-    key_bytes.clone_from_slice(key);
-
-    // get `x25519` secret key from public key.
-    let pk = x25519::SecretKey::from_bytes(key_bytes).public_key();
-
     let pk_bytes = pk.to_bytes();
 
     // do a diffie_hellman exchange to make a shared secret key.
-    let shared = ephemeral_key.diffie_hellman(&pk);
+    let shared = ephemeral_key.diffie_hellman(pk);
 
     // compute the nonce using the ephemeral keys.
     let nonce = {
@@ -157,7 +202,7 @@ pub fn read<I: Read>(input: &mut I, key: &Key, associated_data: &[u8]) -> Result
 
     // decrypt the ciphertext into the plain text buffer.
     XChaCha20Poly1305::try_decrypt(&shared.to_bytes(), &nonce, associated_data, &mut pt, &ct, &tag)
-        .map_err(|e| ReadError::CorruptedContent(format!("Decryption failed: {}", e)))?;
+        .map_err(|e| ReadError::Decryption(e.to_string()))?;
 
     Ok(pt)
 }
@@ -169,11 +214,62 @@ pub fn read<I: Read>(input: &mut I, key: &Key, associated_data: &[u8]) -> Result
 /// filename with a salted suffix). This is currently known to be problematic if the path is a
 /// symlink and/or if the target path resides in a directory without user write permission.
 pub fn write_to(plain: &[u8], path: &Path, key: &Key, associated_data: &[u8]) -> Result<(), WriteError> {
+    write_to_with_progress(plain, path, key, associated_data, &mut |_: Progress| {})
+}
+
+/// Same as [`write_to`], but reports the [`Progress`] of compressing, encrypting and writing
+/// the snapshot to `progress`.
+pub fn write_to_with_progress(
+    plain: &[u8],
+    path: &Path,
+    key: &Key,
+    associated_data: &[u8],
+    progress: &mut dyn FnMut(Progress),
+) -> Result<(), WriteError> {
+    write_to_with_options(plain, path, key, associated_data, &WriteOptions::default(), progress)
+}
+
+/// Same as [`write_to_with_progress`], but compresses the snapshot as configured in `options`.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %path.display(), len = plain.len())))]
+pub fn write_to_with_options(
+    plain: &[u8],
+    path: &Path,
+    key: &Key,
+    associated_data: &[u8],
+    options: &WriteOptions,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<(), WriteError> {
     // TODO: if path exists and is a symlink, resolve it and then append the salt
     // TODO: if the sibling tempfile isn't writeable (e.g. directory permissions), write to
 
-    let compressed_plain = compress(plain);
+    let compressed_plain = Progress::bracket(Phase::Compress, plain.len() as u64, progress, || {
+        options.compression.compress(plain)
+    })
+    .map_err(|e| WriteError::CorruptedData(format!("Compression failed: {}", e)))?;
 
+    let mut ciphertext = Vec::with_capacity(compressed_plain.len() + COMPRESSION_HEADER_LEN);
+    // write magic and version bytes
+    ciphertext.extend_from_slice(&MAGIC);
+    if options.compression == Compression::Lz4 {
+        ciphertext.extend_from_slice(&VERSION);
+    } else {
+        ciphertext.extend_from_slice(&COMPRESSION_VERSION);
+        ciphertext.push(options.compression.id());
+    }
+    let ad = body_ad(&ciphertext, associated_data);
+    Progress::bracket(Phase::Encrypt, compressed_plain.len() as u64, progress, || {
+        write(&compressed_plain, &mut ciphertext, key, &ad)
+    })?;
+
+    write_atomically(&ciphertext, path, progress)
+}
+
+/// Writes `ciphertext` into a sibling temporary file of `path` and renames it to `path` afterwards.
+pub(crate) fn write_atomically(
+    ciphertext: &[u8],
+    path: &Path,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<(), WriteError> {
     let mut salt = [0u8; 6];
     rand::fill(&mut salt).map_err(|e| WriteError::GenerateRandom(format!("{}", e)))?;
 
@@ -183,10 +279,14 @@ pub fn write_to(plain: &[u8], path: &Path, key: &Key, associated_data: &[u8]) ->
     let tmp = Path::new(&s);
 
     let mut f = OpenOptions::new().write(true).create_new(true).open(tmp)?;
-    // write magic and version bytes
-    f.write_all(&MAGIC)?;
-    f.write_all(&VERSION)?;
-    write(&compressed_plain, &mut f, key, associated_data)?;
+    let total = ciphertext.len() as u64;
+    let mut processed = 0;
+    progress(Progress::new(Phase::Write, processed, total));
+    for chunk in ciphertext.chunks(WRITE_CHUNK_SIZE) {
+        f.write_all(chunk)?;
+        processed += chunk.len() as u64;
+        progress(Progress::new(Phase::Write, processed, total));
+    }
     f.sync_all()?;
 
     rename(tmp, path)?;
@@ -194,18 +294,114 @@ pub fn write_to(plain: &[u8], path: &Path, key: &Key, associated_data: &[u8]) ->
     Ok(())
 }
 
+/// Atomically re-encrypts the snapshot at `path`, that has been written with `old_key`, with `new_key`.
+///
+/// The content is only decrypted, but not decompressed, and the decrypted bytes are zeroized before
+/// the function returns. The snapshot file is replaced in the same way as by [`write_to`], so the
+/// file at `path` is either still encrypted with `old_key` or fully re-encrypted with `new_key`.
+pub fn rekey(path: &Path, old_key: &Key, new_key: &Key, associated_data: &[u8]) -> Result<(), RekeyError> {
+    rekey_with_associated_data(path, (old_key, associated_data), (new_key, associated_data))
+}
+
+/// Same as [`rekey`], but also replaces the associated data, that the snapshot is bound to.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %path.display())))]
+pub fn rekey_with_associated_data(
+    path: &Path,
+    (old_key, old_associated_data): (&Key, &[u8]),
+    (new_key, new_associated_data): (&Key, &[u8]),
+) -> Result<(), RekeyError> {
+    let mut f: File = OpenOptions::new().read(true).open(path).map_err(ReadError::from)?;
+    check_min_file_len(&mut f)?;
+
+    let mut content = Vec::new();
+    f.read_to_end(&mut content).map_err(ReadError::from)?;
+    drop(f);
+
+    // the header, and with it the compression, is kept
+    let (header, mut input) = split_header(&content)?;
+    let mut compressed_plain = read(&mut input, old_key, &body_ad(header, old_associated_data))?;
+
+    let mut ciphertext = Vec::with_capacity(compressed_plain.len() + header.len());
+    ciphertext.extend_from_slice(header);
+    let written = write(
+        &compressed_plain,
+        &mut ciphertext,
+        new_key,
+        &body_ad(header, new_associated_data),
+    );
+    compressed_plain.zeroize();
+    written?;
+
+    write_atomically(&ciphertext, path, &mut |_: Progress| {})?;
+
+    Ok(())
+}
+
 /// Check the file header, [`read`][self::read], and decompress the ciphertext from the specified path.
 pub fn read_from(path: &Path, key: &Key, associated_data: &[u8]) -> Result<Vec<u8>, ReadError> {
+    read_from_with_progress(path, key, associated_data, &mut |_: Progress| {})
+}
+
+/// Same as [`read_from`], but reports the [`Progress`] of reading, decrypting and decompressing
+/// the snapshot to `progress`.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(path = %path.display())))]
+pub fn read_from_with_progress(
+    path: &Path,
+    key: &Key,
+    associated_data: &[u8],
+    progress: &mut dyn FnMut(Progress),
+) -> Result<Vec<u8>, ReadError> {
     let mut f: File = OpenOptions::new().read(true).open(path)?;
     check_min_file_len(&mut f)?;
+    let total = f.metadata()?.len();
+
+    let mut content = Vec::with_capacity(total as usize);
+    ProgressReader::new(&mut f, total, progress).read_to_end(&mut content)?;
+
     // check the header for structure.
-    check_header(&mut f)?;
-    let pt = read(&mut f, key, associated_data)?;
+    let (header, mut input) = split_header(&content)?;
+    let ad = body_ad(header, associated_data);
+    let pt = Progress::bracket(Phase::Decrypt, input.len() as u64, progress, || {
+        read(&mut input, key, &ad)
+    })?;
+
+    let compression = match header.len() {
+        COMPRESSION_HEADER_LEN => header[COMPRESSION_HEADER_LEN - 1],
+        _ => Compression::Lz4.id(),
+    };
+    Progress::bracket(Phase::Decompress, pt.len() as u64, progress, || {
+        Compression::decompress(compression, &pt)
+    })
+    .map_err(|e| ReadError::CorruptedContent(format!("Decompression failed: {}", e)))
+}
 
-    decompress(&pt).map_err(|e| ReadError::CorruptedContent(format!("Decompression failed: {}", e)))
+/// Checks the header of a snapshot file with either [`VERSION`] or [`COMPRESSION_VERSION`], and splits
+/// `content` into the header and the body.
+fn split_header(content: &[u8]) -> Result<(&[u8], &[u8]), ReadError> {
+    if content.get(MAGIC.len()..MAGIC.len() + COMPRESSION_VERSION.len()) == Some(&COMPRESSION_VERSION[..]) {
+        if content.len() < COMPRESSION_HEADER_LEN || content[..MAGIC.len()] != MAGIC {
+            return Err(ReadError::InvalidFile);
+        }
+        return Ok(content.split_at(COMPRESSION_HEADER_LEN));
+    }
+
+    let mut input = content;
+    check_header(&mut input)?;
+    Ok(content.split_at(content.len() - input.len()))
+}
+
+/// The header of a [`COMPRESSION_VERSION`] file is authenticated together with the associated data of
+/// the caller. [`VERSION`] files only authenticate the associated data.
+fn body_ad(header: &[u8], associated_data: &[u8]) -> Vec<u8> {
+    let mut ad = Vec::with_capacity(header.len() + associated_data.len());
+    if header.len() == COMPRESSION_HEADER_LEN {
+        ad.extend_from_slice(header);
+    }
+    ad.extend_from_slice(associated_data);
+    ad
 }
 
-fn check_min_file_len(input: &mut File) -> Result<(), ReadError> {
+pub(crate) fn check_min_file_len(input: &mut File) -> Result<(), ReadError> {
     let min = MAGIC.len() + VERSION.len() + x25519::PUBLIC_KEY_LENGTH + XChaCha20Poly1305::TAG_LENGTH;
     if input.metadata()?.len() >= min as u64 {
         Ok(())
@@ -215,7 +411,7 @@ fn check_min_file_len(input: &mut File) -> Result<(), ReadError> {
 }
 
 /// Checks the header for a specific structure; explicitly the magic and version bytes.
-fn check_header<I: Read>(input: &mut I) -> Result<(), ReadError> {
+pub(crate) fn check_header<I: Read>(input: &mut I) -> Result<(), ReadError> {
     // check the magic bytes
     let mut magic = [0u8; 5];
     input.read_exact(&mut magic)?;
@@ -313,6 +509,39 @@ mod test {
         read_from(&pb, &key, &ad).unwrap();
     }
 
+    #[test]
+    fn test_snapshot_progress() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let key: Key = random_key();
+        let bs0 = random_bytestring();
+        let ad = random_bytestring();
+
+        let mut reported = Vec::new();
+        write_to_with_progress(&bs0, &pb, &key, &ad, &mut |p| reported.push(p)).unwrap();
+        let bs1 = read_from_with_progress(&pb, &key, &ad, &mut |p| reported.push(p)).unwrap();
+        assert_eq!(bs0, bs1);
+
+        let mut phases: Vec<Phase> = reported.iter().map(|p| p.phase).collect();
+        phases.dedup();
+        assert_eq!(
+            phases,
+            vec![
+                Phase::Compress,
+                Phase::Encrypt,
+                Phase::Write,
+                Phase::Read,
+                Phase::Decrypt,
+                Phase::Decompress
+            ]
+        );
+        assert!(reported.iter().all(|p| p.processed <= p.total));
+        let last_read = reported.iter().rfind(|p| p.phase == Phase::Read).unwrap();
+        assert_eq!(last_read.processed, last_read.total);
+    }
+
     #[test]
     fn test_snapshot_overwrite() {
         let f = tempfile::tempdir().unwrap();
@@ -329,6 +558,78 @@ mod test {
         assert_eq!(bs0, bs1);
     }
 
+    #[test]
+    fn test_snapshot_rekey() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let old_key: Key = random_key();
+        let new_key: Key = random_key();
+        let bs0 = random_bytestring();
+        let ad = random_bytestring();
+
+        write_to(&bs0, &pb, &old_key, &ad).unwrap();
+
+        // a wrong key must not touch the file
+        assert!(matches!(
+            rekey(&pb, &random_key(), &new_key, &ad),
+            Err(RekeyError::Read(ReadError::Decryption(_)))
+        ));
+        assert_eq!(bs0, read_from(&pb, &old_key, &ad).unwrap());
+
+        rekey(&pb, &old_key, &new_key, &ad).unwrap();
+        assert!(read_from(&pb, &old_key, &ad).is_err());
+        assert_eq!(bs0, read_from(&pb, &new_key, &ad).unwrap());
+
+        // bind the snapshot to other associated data
+        let new_ad = random_bytestring();
+        rekey_with_associated_data(&pb, (&new_key, &ad), (&new_key, &new_ad)).unwrap();
+        assert!(read_from(&pb, &new_key, &ad).is_err());
+        assert_eq!(bs0, read_from(&pb, &new_key, &new_ad).unwrap());
+    }
+
+    #[test]
+    fn test_snapshot_compression() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let key: Key = random_key();
+        let bs0 = random_bytestring();
+        let ad = random_bytestring();
+
+        for compression in [Compression::None, Compression::Lz4, Compression::Zstd { level: 19 }] {
+            let options = WriteOptions { compression };
+            write_to_with_options(&bs0, &pb, &key, &ad, &options, &mut |_| {}).unwrap();
+            assert_eq!(bs0, read_from(&pb, &key, &ad).unwrap());
+
+            let mut version = [0u8; 2];
+            version.copy_from_slice(&std::fs::read(&pb).unwrap()[MAGIC.len()..MAGIC.len() + 2]);
+            let expected = if compression == Compression::Lz4 {
+                VERSION
+            } else {
+                COMPRESSION_VERSION
+            };
+            assert_eq!(version, expected);
+
+            // rekeying keeps the compression
+            let new_key: Key = random_key();
+            rekey(&pb, &key, &new_key, &ad).unwrap();
+            assert_eq!(bs0, read_from(&pb, &new_key, &ad).unwrap());
+        }
+
+        // the compression in the header is authenticated
+        let options = WriteOptions {
+            compression: Compression::None,
+        };
+        write_to_with_options(&bs0, &pb, &key, &ad, &options, &mut |_| {}).unwrap();
+        let mut content = std::fs::read(&pb).unwrap();
+        content[COMPRESSION_HEADER_LEN - 1] = Compression::Lz4.id();
+        std::fs::write(&pb, content).unwrap();
+        assert!(read_from(&pb, &key, &ad).is_err());
+    }
+
     struct TestVector {
         key: &'static str,
         ad: &'static str,
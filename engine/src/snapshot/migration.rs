@@ -0,0 +1,234 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Upgrades snapshot files from older versions of the format.
+//!
+//! Every change of the format registers a [`Migration`] from the previous version to the new one.
+//! [`migrate`] detects the version of a snapshot file from its header, chains all migrations that are
+//! needed to reach the requested version and atomically replaces the file with the upgraded content.
+//! A snapshot is therefore never left half-migrated: it is either still in its old format or fully
+//! upgraded.
+
+use std::{fs::File, io::Read, path::Path};
+
+use thiserror::Error as DeriveError;
+use zeroize::Zeroize;
+
+use crate::snapshot::{
+    logic::{write_atomically, Key, ReadError, WriteError, MAGIC},
+    Progress,
+};
+
+/// Upgrades the content of a snapshot file. Receives the complete file and returns the complete
+/// upgraded file, including the new header.
+pub type MigrationFn = fn(content: &[u8], key: &Key, associated_data: &[u8]) -> Result<Vec<u8>, MigrationError>;
+
+/// A single upgrade of the snapshot format from one version to the next
+#[derive(Clone, Copy)]
+pub struct Migration {
+    /// Version of the files this migration can be applied to
+    pub from: [u8; 2],
+
+    /// Version of the files produced by this migration
+    pub to: [u8; 2],
+
+    /// Converts the file content
+    pub apply: MigrationFn,
+}
+
+/// All known migrations. Each format change appends an entry here.
+const MIGRATIONS: &[Migration] = &[];
+
+#[derive(Debug, DeriveError)]
+pub enum MigrationError {
+    #[error("reading snapshot failed: {0}")]
+    Read(#[from] ReadError),
+
+    #[error("writing snapshot failed: {0}")]
+    Write(#[from] WriteError),
+
+    #[error("snapshot has version `{found:?}`, expected `{expected:?}`")]
+    VersionMismatch { expected: [u8; 2], found: [u8; 2] },
+
+    #[error("no migration from version `{from:?}` to `{to:?}`")]
+    Unsupported { from: [u8; 2], to: [u8; 2] },
+
+    #[error("migration failed: {0}")]
+    Failed(String),
+}
+
+/// Reads the version bytes from the header of the snapshot file at `path`
+pub fn detect_version(path: &Path) -> Result<[u8; 2], ReadError> {
+    let mut header = [0u8; MAGIC.len() + 2];
+    File::open(path)?
+        .read_exact(&mut header)
+        .map_err(|_| ReadError::InvalidFile)?;
+    if header[..MAGIC.len()] != MAGIC {
+        return Err(ReadError::InvalidFile);
+    }
+    Ok([header[MAGIC.len()], header[MAGIC.len() + 1]])
+}
+
+/// Returns `true`, if a snapshot with version `from` can be upgraded to version `to`
+pub fn can_migrate(from: [u8; 2], to: [u8; 2]) -> bool {
+    plan(MIGRATIONS, from, to).is_some()
+}
+
+/// Upgrades the snapshot file at `path` from version `from` to version `to` in place.
+///
+/// Fails with [`MigrationError::VersionMismatch`], if the file does not have version `from`, and with
+/// [`MigrationError::Unsupported`], if there is no chain of migrations between both versions. Migrating
+/// a file to its current version does nothing.
+pub fn migrate(
+    path: &Path,
+    from: [u8; 2],
+    to: [u8; 2],
+    key: &Key,
+    associated_data: &[u8],
+) -> Result<(), MigrationError> {
+    migrate_with(MIGRATIONS, path, from, to, key, associated_data)
+}
+
+fn migrate_with(
+    migrations: &[Migration],
+    path: &Path,
+    from: [u8; 2],
+    to: [u8; 2],
+    key: &Key,
+    associated_data: &[u8],
+) -> Result<(), MigrationError> {
+    let found = detect_version(path)?;
+    if found != from {
+        return Err(MigrationError::VersionMismatch { expected: from, found });
+    }
+    if from == to {
+        return Ok(());
+    }
+    let steps = plan(migrations, from, to).ok_or(MigrationError::Unsupported { from, to })?;
+
+    let mut content = Vec::new();
+    File::open(path)
+        .map_err(ReadError::from)?
+        .read_to_end(&mut content)
+        .map_err(ReadError::from)?;
+
+    for step in steps {
+        let upgraded = (step.apply)(&content, key, associated_data);
+        content.zeroize();
+        content = upgraded?;
+    }
+
+    write_atomically(&content, path, &mut |_: Progress| {})?;
+    Ok(())
+}
+
+/// Finds the shortest chain of migrations from `from` to `to`
+fn plan(migrations: &[Migration], from: [u8; 2], to: [u8; 2]) -> Option<Vec<Migration>> {
+    let mut paths: Vec<(Vec<Migration>, [u8; 2])> = vec![(Vec::new(), from)];
+    let mut visited = vec![from];
+
+    while !paths.is_empty() {
+        let mut next = Vec::new();
+        for (path, version) in paths {
+            if version == to {
+                return Some(path);
+            }
+            for migration in migrations.iter().filter(|m| m.from == version) {
+                if visited.contains(&migration.to) {
+                    continue;
+                }
+                visited.push(migration.to);
+                let mut path = path.clone();
+                path.push(*migration);
+                next.push((path, migration.to));
+            }
+        }
+        paths = next;
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::snapshot::{write_to, VERSION};
+    use stronghold_utils::random;
+
+    /// Replaces the version bytes of the header
+    fn bump(content: &[u8], _: &Key, _: &[u8]) -> Result<Vec<u8>, MigrationError> {
+        let mut upgraded = content.to_vec();
+        upgraded[MAGIC.len() + 1] += 1;
+        Ok(upgraded)
+    }
+
+    fn fail(_: &[u8], _: &Key, _: &[u8]) -> Result<Vec<u8>, MigrationError> {
+        Err(MigrationError::Failed("unsupported content".to_string()))
+    }
+
+    #[test]
+    fn test_detect_version() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let key: Key = random::random();
+        write_to(&random::variable_bytestring(4096), &pb, &key, &[]).unwrap();
+        assert_eq!(detect_version(&pb).unwrap(), VERSION);
+
+        // migrating to the same version is a no-op
+        migrate(&pb, VERSION, VERSION, &key, &[]).unwrap();
+        assert!(matches!(
+            migrate(&pb, [0x1, 0x0], VERSION, &key, &[]),
+            Err(MigrationError::VersionMismatch { .. })
+        ));
+
+        std::fs::write(&pb, b"not a snapshot").unwrap();
+        assert!(matches!(detect_version(&pb), Err(ReadError::InvalidFile)));
+    }
+
+    #[test]
+    fn test_migration_chain() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let key: Key = random::random();
+        let data = random::variable_bytestring(4096);
+        write_to(&data, &pb, &key, &[]).unwrap();
+
+        let v = |minor: u8| [VERSION[0], minor];
+        let migrations = [
+            Migration {
+                from: v(1),
+                to: v(2),
+                apply: bump,
+            },
+            Migration {
+                from: v(0),
+                to: v(1),
+                apply: bump,
+            },
+            Migration {
+                from: v(0),
+                to: v(9),
+                apply: fail,
+            },
+        ];
+        assert_eq!(plan(&migrations, v(0), v(2)).unwrap().len(), 2);
+        assert!(plan(&migrations, v(2), v(0)).is_none());
+
+        // a failing migration leaves the file untouched
+        assert!(matches!(
+            migrate_with(&migrations, &pb, v(0), v(9), &key, &[]),
+            Err(MigrationError::Failed(_))
+        ));
+        assert_eq!(detect_version(&pb).unwrap(), v(0));
+
+        migrate_with(&migrations, &pb, v(0), v(2), &key, &[]).unwrap();
+        assert_eq!(detect_version(&pb).unwrap(), v(2));
+        assert!(matches!(
+            migrate_with(&migrations, &pb, v(2), v(3), &key, &[]),
+            Err(MigrationError::Unsupported { .. })
+        ));
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io::Read;
+
+/// The phase a snapshot operation is currently in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// Deriving the snapshot key from a passphrase
+    Kdf,
+    /// Reading the snapshot file
+    Read,
+    /// Decrypting the snapshot content
+    Decrypt,
+    /// Decompressing the snapshot content
+    Decompress,
+    /// Deserializing the snapshot state
+    Deserialize,
+    /// Serializing the snapshot state
+    Serialize,
+    /// Compressing the snapshot content
+    Compress,
+    /// Encrypting the snapshot content
+    Encrypt,
+    /// Writing the snapshot file
+    Write,
+}
+
+/// Progress of a snapshot operation, reported to a progress callback.
+///
+/// Each phase is reported at least twice: once when it starts with `processed == 0`,
+/// and once when it is finished with `processed == total`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The current phase
+    pub phase: Phase,
+
+    /// The number of bytes processed in the current phase
+    pub processed: u64,
+
+    /// The total number of bytes of the current phase
+    pub total: u64,
+}
+
+impl Progress {
+    pub fn new(phase: Phase, processed: u64, total: u64) -> Self {
+        Self {
+            phase,
+            processed,
+            total,
+        }
+    }
+
+    /// Reports the start of `phase` with `total` bytes, runs `f` and reports the end of the phase.
+    pub fn bracket<T, F>(phase: Phase, total: u64, progress: &mut dyn FnMut(Progress), f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        progress(Progress::new(phase, 0, total));
+        let result = f();
+        progress(Progress::new(phase, total, total));
+        result
+    }
+}
+
+/// Reader that reports the number of bytes read in [`Phase::Read`].
+pub(crate) struct ProgressReader<'a, R> {
+    inner: R,
+    processed: u64,
+    total: u64,
+    progress: &'a mut dyn FnMut(Progress),
+}
+
+impl<'a, R: Read> ProgressReader<'a, R> {
+    pub(crate) fn new(inner: R, total: u64, progress: &'a mut dyn FnMut(Progress)) -> Self {
+        progress(Progress::new(Phase::Read, 0, total));
+        Self {
+            inner,
+            processed: 0,
+            total,
+            progress,
+        }
+    }
+}
+
+impl<'a, R: Read> Read for ProgressReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            self.processed += n as u64;
+            (self.progress)(Progress::new(Phase::Read, self.processed, self.total));
+        }
+        Ok(n)
+    }
+}
@@ -0,0 +1,261 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Throttling of failed attempts to unlock a snapshot file.
+//!
+//! The number of failed attempts and the time of the last failure are kept in a file next to the
+//! snapshot, see [`attempts_path`]:
+//!
+//! ```text
+//! | failed attempts: u32 | last failure, seconds since the unix epoch: u64 |
+//! ```
+//!
+//! After each failure the next attempt is delayed exponentially, up to [`ThrottlePolicy::max_delay`].
+//! Once [`ThrottlePolicy::escalate_after`] attempts have failed, the owner is expected to raise the key
+//! derivation parameters of the snapshot on the next successful unlock, see [`ThrottlePolicy::escalate`].
+//! This makes brute forcing a copy of the file, that has been taken after the escalation, more expensive.
+//!
+//! The attempts file is only a speed bump for the software that honors it. It does not protect a copy
+//! of the snapshot on its own.
+
+use crate::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{
+    fs::File,
+    io::{ErrorKind, Read},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error as DeriveError;
+
+use crate::snapshot::{
+    kdf::Argon2Params,
+    logic::{write_atomically, ReadError, WriteError},
+    Progress,
+};
+
+/// Extension of the file next to the snapshot, that holds the failed attempts
+const ATTEMPTS_EXTENSION: &str = "attempts";
+
+const ATTEMPTS_LEN: usize = 4 + 8;
+
+/// Limits the rate of attempts to unlock a snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThrottlePolicy {
+    /// Delay after the first failed attempt. It is doubled with each further failure.
+    pub base_delay: Duration,
+
+    /// Upper bound of the delay
+    pub max_delay: Duration,
+
+    /// Number of failed attempts, after which the key derivation parameters should be raised
+    pub escalate_after: u32,
+
+    /// Upper bound of the Argon2id iterations, that [`Self::escalate`] raises the parameters to
+    pub max_iterations: u32,
+}
+
+impl Default for ThrottlePolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60 * 60),
+            escalate_after: 5,
+            max_iterations: 64,
+        }
+    }
+}
+
+/// The failed attempts to unlock a snapshot since its last successful unlock
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Attempts {
+    /// Number of failed attempts
+    pub failed: u32,
+
+    /// Time of the last failed attempt
+    pub last_failure: Option<SystemTime>,
+}
+
+#[derive(Debug, DeriveError)]
+pub enum ThrottleError {
+    #[error("unlocking is throttled after {failed} failed attempts, retry in {retry_after:?}")]
+    Throttled { failed: u32, retry_after: Duration },
+
+    #[error("reading failed attempts failed: {0}")]
+    Read(#[from] ReadError),
+
+    #[error("writing failed attempts failed: {0}")]
+    Write(#[from] WriteError),
+}
+
+impl ThrottlePolicy {
+    /// Returns the delay, that has to pass after the last of `failed` attempts
+    pub fn delay(&self, failed: u32) -> Duration {
+        match failed {
+            0 => Duration::ZERO,
+            failed => self
+                .base_delay
+                .checked_mul(1u32.checked_shl(failed - 1).unwrap_or(u32::MAX))
+                .map_or(self.max_delay, |delay| delay.min(self.max_delay)),
+        }
+    }
+
+    /// Checks, that an attempt to unlock the snapshot at `snapshot_path` is allowed at this time, and
+    /// returns the previous failed attempts. Fails with [`ThrottleError::Throttled`] otherwise.
+    pub fn check(&self, snapshot_path: &Path) -> Result<Attempts, ThrottleError> {
+        let attempts = read_attempts(snapshot_path)?;
+        if let Some(last_failure) = attempts.last_failure {
+            // a clock that has been turned back doesn't shorten the delay
+            let elapsed = SystemTime::now().duration_since(last_failure).unwrap_or_default();
+            let delay = self.delay(attempts.failed);
+            if elapsed < delay {
+                return Err(ThrottleError::Throttled {
+                    failed: attempts.failed,
+                    retry_after: delay - elapsed,
+                });
+            }
+        }
+        Ok(attempts)
+    }
+
+    /// Returns `true`, if the key derivation parameters should be raised after `attempts`
+    pub fn should_escalate(&self, attempts: &Attempts) -> bool {
+        self.escalate_after > 0 && attempts.failed >= self.escalate_after
+    }
+
+    /// Returns `params` with twice the iterations, capped at [`Self::max_iterations`], or `None` if they
+    /// can not be raised any further
+    pub fn escalate(&self, params: &Argon2Params) -> Option<Argon2Params> {
+        let iterations = params.iterations.saturating_mul(2).min(self.max_iterations);
+        (iterations > params.iterations).then_some(Argon2Params { iterations, ..*params })
+    }
+}
+
+/// Path of the attempts file of the snapshot at `snapshot_path`
+pub fn attempts_path(snapshot_path: &Path) -> PathBuf {
+    let mut path = snapshot_path.as_os_str().to_os_string();
+    path.push(".");
+    path.push(ATTEMPTS_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Reads the failed attempts to unlock the snapshot at `snapshot_path`
+pub fn read_attempts(snapshot_path: &Path) -> Result<Attempts, ReadError> {
+    let mut content = Vec::new();
+    match File::open(attempts_path(snapshot_path)) {
+        Ok(mut f) => f.read_to_end(&mut content)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Attempts::default()),
+        Err(e) => return Err(e.into()),
+    };
+    if content.len() != ATTEMPTS_LEN {
+        return Err(ReadError::CorruptedContent("Invalid attempts file".into()));
+    }
+
+    let failed = u32::from_be_bytes(content[..4].try_into().expect("slice with incorrect length"));
+    let secs = u64::from_be_bytes(content[4..].try_into().expect("slice with incorrect length"));
+    Ok(Attempts {
+        failed,
+        last_failure: (failed > 0).then(|| UNIX_EPOCH + Duration::from_secs(secs)),
+    })
+}
+
+/// Records a failed attempt to unlock the snapshot at `snapshot_path`, and returns all failed attempts
+pub fn record_failure(snapshot_path: &Path) -> Result<Attempts, ThrottleError> {
+    let previous = read_attempts(snapshot_path)?;
+    let now = SystemTime::now();
+    let secs = now.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    let failed = previous.failed.saturating_add(1);
+
+    let mut content = Vec::with_capacity(ATTEMPTS_LEN);
+    content.extend_from_slice(&failed.to_be_bytes());
+    content.extend_from_slice(&secs.to_be_bytes());
+    write_atomically(&content, &attempts_path(snapshot_path), &mut |_: Progress| {})?;
+
+    Ok(Attempts {
+        failed,
+        last_failure: Some(now),
+    })
+}
+
+/// Resets the failed attempts after the snapshot at `snapshot_path` has been unlocked
+pub fn reset(snapshot_path: &Path) -> Result<(), WriteError> {
+    match std::fs::remove_file(attempts_path(snapshot_path)) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let policy = ThrottlePolicy {
+            base_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(60),
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(0), Duration::ZERO);
+        assert_eq!(policy.delay(1), Duration::from_secs(2));
+        assert_eq!(policy.delay(3), Duration::from_secs(8));
+        assert_eq!(policy.delay(6), Duration::from_secs(60));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_attempts() {
+        let f = tempfile::tempdir().unwrap();
+        let mut pb = f.keep();
+        pb.push("snapshot");
+
+        let policy = ThrottlePolicy {
+            base_delay: Duration::from_secs(60),
+            escalate_after: 2,
+            ..Default::default()
+        };
+        assert_eq!(policy.check(&pb).unwrap(), Attempts::default());
+
+        assert_eq!(record_failure(&pb).unwrap().failed, 1);
+        assert!(matches!(
+            policy.check(&pb),
+            Err(ThrottleError::Throttled { failed: 1, .. })
+        ));
+        let attempts = record_failure(&pb).unwrap();
+        assert_eq!(read_attempts(&pb).unwrap().failed, 2);
+        assert!(policy.should_escalate(&attempts));
+
+        // without a delay the attempt is allowed, and the failures are still reported
+        let unthrottled = ThrottlePolicy {
+            base_delay: Duration::ZERO,
+            ..policy
+        };
+        assert_eq!(unthrottled.check(&pb).unwrap().failed, 2);
+
+        reset(&pb).unwrap();
+        assert_eq!(read_attempts(&pb).unwrap(), Attempts::default());
+        reset(&pb).unwrap();
+    }
+
+    #[test]
+    fn test_escalate() {
+        let policy = ThrottlePolicy {
+            max_iterations: 10,
+            ..Default::default()
+        };
+        let params = Argon2Params {
+            memory_kib: 64,
+            iterations: 3,
+            parallelism: 1,
+        };
+        let escalated = policy.escalate(&params).unwrap();
+        assert_eq!(escalated.iterations, 6);
+        assert_eq!(escalated.memory_kib, params.memory_kib);
+        assert_eq!(policy.escalate(&escalated).unwrap().iterations, 10);
+        assert!(policy
+            .escalate(&Argon2Params {
+                iterations: 10,
+                ..params
+            })
+            .is_none());
+    }
+}
@@ -1,8 +1,8 @@
 // Copyright 2020-2021 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::time::{Duration, SystemTime};
 use serde::{Deserialize, Serialize};
-use std::time::{Duration, SystemTime};
 
 pub mod cache;
 
@@ -28,4 +28,10 @@ impl<T> Value<T> {
     pub fn has_expired(&self, time_now: SystemTime) -> bool {
         self.expiration.map_or(false, |time| time_now >= time)
     }
+
+    /// Returns the time until the [`Value`] expires, or [`None`] if it doesn't expire.
+    pub fn remaining(&self, time_now: SystemTime) -> Option<Duration> {
+        self.expiration
+            .map(|time| time.duration_since(time_now).unwrap_or(Duration::ZERO))
+    }
 }
@@ -5,11 +5,11 @@ use crate::store::storage::Value;
 
 use serde::{Deserialize, Serialize};
 
+use crate::time::{Duration, SystemTime};
 use std::{
     collections::{hash_map::Entry, HashMap},
     fmt::Debug,
     hash::Hash,
-    time::{Duration, SystemTime},
 };
 
 /// The [`Cache`] struct used to store the data in an ordered format.
@@ -245,6 +245,96 @@ impl<K: Hash + Eq + Clone, V: Clone + Debug> Cache<K, V> {
         self.table.keys().cloned().collect()
     }
 
+    /// Returns an iterator over all entries of the [`Cache`], that have not expired yet. The order is unspecified.
+    ///
+    /// # Example
+    /// ```
+    /// use engine::store::Cache;
+    /// use std::time::Duration;
+    ///
+    /// let mut cache = Cache::new();
+    ///
+    /// cache.insert("key", "value", None);
+    /// cache.insert("expired", "value", Some(Duration::ZERO));
+    ///
+    /// assert_eq!(cache.iter().collect::<Vec<_>>(), vec![(&"key", &"value")]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        let now = SystemTime::now();
+
+        self.table
+            .iter()
+            .filter(move |(_, value)| !value.has_expired(now))
+            .map(|(key, value)| (key, &value.val))
+    }
+
+    /// Returns the number of entries in the [`Cache`], that have not expired yet.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Checks, if the [`Cache`] has no entries, that have not expired yet.
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// Returns the lifetime, that is left for the entry with `key`. Returns [`None`], if there is no live entry
+    /// with `key`, and `Some(None)`, if the entry doesn't expire.
+    ///
+    /// # Example
+    /// ```
+    /// use engine::store::Cache;
+    /// use std::time::Duration;
+    ///
+    /// let mut cache = Cache::new();
+    ///
+    /// cache.insert("key", "value", Some(Duration::from_secs(60)));
+    /// cache.insert("forever", "value", None);
+    ///
+    /// assert!(cache.remaining_lifetime(&"key").unwrap().unwrap() <= Duration::from_secs(60));
+    /// assert_eq!(cache.remaining_lifetime(&"forever"), Some(None));
+    /// assert_eq!(cache.remaining_lifetime(&"missing"), None);
+    /// ```
+    pub fn remaining_lifetime(&self, key: &K) -> Option<Option<Duration>> {
+        let now = SystemTime::now();
+
+        self.table
+            .get(key)
+            .filter(|value| !value.has_expired(now))
+            .map(|value| value.remaining(now))
+    }
+
+    /// Removes all expired entries, regardless of the scan frequency, and returns their keys.
+    ///
+    /// # Example
+    /// ```
+    /// use engine::store::Cache;
+    /// use std::time::Duration;
+    ///
+    /// let mut cache = Cache::new();
+    ///
+    /// cache.insert("key", "value", None);
+    /// cache.insert("expired", "value", Some(Duration::ZERO));
+    ///
+    /// assert_eq!(cache.remove_expired(), vec!["expired"]);
+    /// assert_eq!(cache.keys(), vec!["key"]);
+    /// ```
+    pub fn remove_expired(&mut self) -> Vec<K> {
+        let now = SystemTime::now();
+
+        let expired: Vec<K> = self
+            .table
+            .iter()
+            .filter(|(_, value)| value.has_expired(now))
+            .map(|(key, _)| key.clone())
+            .collect();
+        expired.iter().for_each(|key| {
+            self.table.remove(key);
+        });
+        self.last_scan_at = Some(now);
+        expired
+    }
+
     /// attempts to remove expired items based on the current system time provided.
     fn try_remove_expired_items(&mut self, now: SystemTime) {
         if let Some(frequency) = self.scan_freq {
@@ -0,0 +1,13 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Clocks of the platform. The clocks of `std` panic on `wasm32-unknown-unknown`, where the time is taken from
+//! the `Date` and `performance` APIs of the browser instead. On all other platforms these are the types of `std`.
+
+pub use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::{Instant, SystemTime, SystemTimeError, UNIX_EPOCH};
+
+#[cfg(target_arch = "wasm32")]
+pub use web_time::{Instant, SystemTime, SystemTimeError, UNIX_EPOCH};
@@ -24,6 +24,13 @@ pub mod view;
 pub use crate::vault::{
     base64::{Base64Decodable, Base64Encodable},
     crypto_box::{BoxProvider, Decrypt, DecryptError, Encrypt, Key, NCKey},
-    types::utils::{BlobId, ChainId, ClientId, Id, InvalidLength, RecordHint, RecordId, VaultId},
-    view::{DbView, RecordError, VaultError},
+    types::{
+        audit::{AuditEntry, AuditEvent},
+        tags::Tags,
+        utils::{BlobId, ChainId, ClientId, Id, InvalidLength, RecordHint, RecordId, VaultId},
+    },
+    view::{
+        DbView, GcStats, HintFilter, ListOptions, Quota, RecordEntry, RecordError, RecordOrder, RecordPage, VaultError,
+        WriteRequest,
+    },
 };
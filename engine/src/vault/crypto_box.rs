@@ -194,6 +194,11 @@ impl<T: BoxProvider> NCKey<T> {
         }
     }
 
+    /// Re-splits the shards of the key in memory with fresh randomness, see [`NonContiguousMemory::refresh`]
+    pub fn refresh(&self) -> Result<(), runtime::MemoryError> {
+        self.key.refresh()
+    }
+
     pub fn encrypt_key<AD: AsRef<[u8]>>(&self, data: &Key<T>, ad: AD) -> Result<Vec<u8>, T::Error> {
         let key = Key {
             key: self.key.unlock().unwrap_or_else(|e| panic!("{}", e)),
@@ -5,6 +5,8 @@ use std::mem;
 
 pub use transactions::{DataTransaction, SealedBlob, SealedTransaction};
 
+pub mod audit;
+pub mod tags;
 pub mod transactions;
 pub mod utils;
 
@@ -0,0 +1,170 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Append-only log of the revocations and deletions in a vault.
+//!
+//! The log is stored encrypted in the vault. Each entry is authenticated with a MAC under an audit key, that is
+//! separate from the key of the vault, and commits to its predecessor by including the MAC of the previous entry:
+//!
+//! ```text
+//! mac = HMAC-SHA256(audit key, previous mac | event: u8 | record id: [u8; 24] | time, seconds since the unix epoch: u64)
+//! ```
+//!
+//! Entries can neither be forged, removed nor reordered without the audit key, even by someone who holds the
+//! key of the vault.
+//!
+//! The plaintext is encoded as `| entries: u32 | { event | record id | time | mac } |`, with big endian
+//! numbers. The MAC of the first entry commits to 32 zero bytes.
+
+use crate::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crypto::macs::hmac::HMAC_SHA256;
+
+use crate::vault::{crypto_box::BoxProvider, types::utils::RecordId, Key};
+
+const ENTRY_LEN: usize = 1 + 24 + 8 + 32;
+
+/// Kind of an [`AuditEntry`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AuditEvent {
+    /// The record has been revoked
+    Revoked = 1,
+
+    /// The revocation of the record has been undone
+    Restored = 2,
+
+    /// The revoked record has been deleted by garbage collection, or by a rotation of the vault key
+    Deleted = 3,
+
+    /// The record has been deleted after it has expired
+    Expired = 4,
+}
+
+impl TryFrom<u8> for AuditEvent {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(AuditEvent::Revoked),
+            2 => Ok(AuditEvent::Restored),
+            3 => Ok(AuditEvent::Deleted),
+            4 => Ok(AuditEvent::Expired),
+            v => Err(format!("Invalid audit event {}", v)),
+        }
+    }
+}
+
+/// An entry of the [`AuditLog`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub event: AuditEvent,
+    pub record: RecordId,
+
+    /// Time of the event, with a precision of seconds
+    pub time: SystemTime,
+
+    /// MAC over the entry and all entries before it
+    pub mac: [u8; 32],
+}
+
+/// The entries of the log, the oldest one first
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AuditLog(pub Vec<AuditEntry>);
+
+impl AuditLog {
+    /// Appends an entry for `event` on `record` at `time`, authenticated with `audit_key`.
+    pub fn append<P: BoxProvider>(
+        &mut self,
+        audit_key: &Key<P>,
+        event: AuditEvent,
+        record: RecordId,
+        time: SystemTime,
+    ) {
+        let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        let previous = self.0.last().map(|entry| entry.mac).unwrap_or_default();
+        self.0.push(AuditEntry {
+            event,
+            record,
+            time: UNIX_EPOCH + Duration::from_secs(secs),
+            mac: entry_mac(audit_key, &previous, event, &record, secs),
+        });
+    }
+
+    /// Checks the chain of MACs under `audit_key`.
+    pub fn verify<P: BoxProvider>(&self, audit_key: &Key<P>) -> bool {
+        let mut previous = [0u8; 32];
+        for entry in self.0.iter() {
+            let secs = entry
+                .time
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            if entry.mac != entry_mac(audit_key, &previous, entry.event, &entry.record, secs) {
+                return false;
+            }
+            previous = entry.mac;
+        }
+        true
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(4 + self.0.len() * ENTRY_LEN);
+        buf.extend_from_slice(&(self.0.len() as u32).to_be_bytes());
+        for entry in self.0.iter() {
+            let secs = entry
+                .time
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            buf.push(entry.event as u8);
+            buf.extend_from_slice(entry.record.0.as_ref());
+            buf.extend_from_slice(&secs.to_be_bytes());
+            buf.extend_from_slice(&entry.mac);
+        }
+        buf
+    }
+
+    /// Decodes the log. The chain of MACs is checked separately with [`Self::verify`].
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 4 {
+            return Err("Audit log is truncated".into());
+        }
+        let (count, entries) = bytes.split_at(4);
+        let count = u32::from_be_bytes(count.try_into().expect("slice with incorrect length")) as usize;
+        if entries.len() != count * ENTRY_LEN {
+            return Err("Audit log has an invalid length".into());
+        }
+
+        let mut log = Vec::with_capacity(count);
+        for entry in entries.chunks_exact(ENTRY_LEN) {
+            let secs = u64::from_be_bytes(entry[25..33].try_into().expect("slice with incorrect length"));
+            let mut mac = [0u8; 32];
+            mac.copy_from_slice(&entry[33..]);
+            log.push(AuditEntry {
+                event: AuditEvent::try_from(entry[0])?,
+                record: RecordId::load(&entry[1..25]).map_err(|e| e.to_string())?,
+                time: UNIX_EPOCH + Duration::from_secs(secs),
+                mac,
+            });
+        }
+        Ok(AuditLog(log))
+    }
+}
+
+fn entry_mac<P: BoxProvider>(
+    audit_key: &Key<P>,
+    previous: &[u8; 32],
+    event: AuditEvent,
+    record: &RecordId,
+    secs: u64,
+) -> [u8; 32] {
+    let mut message = Vec::with_capacity(ENTRY_LEN);
+    message.extend_from_slice(previous);
+    message.push(event as u8);
+    message.extend_from_slice(record.0.as_ref());
+    message.extend_from_slice(&secs.to_be_bytes());
+    let mut mac = [0u8; 32];
+    HMAC_SHA256(&message, &audit_key.key.borrow(), &mut mac);
+    mac
+}
@@ -0,0 +1,107 @@
+// Copyright 2020-2022 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Index of the tags of the records in a vault.
+//!
+//! The index is stored encrypted in the vault. Its plaintext is encoded as:
+//!
+//! ```text
+//! | records: u32 | { record id: [u8; 24] | tags: u32 | { name: u32, [u8] | value: u32, [u8] } } |
+//! ```
+//!
+//! All numbers are big endian.
+
+use std::collections::BTreeMap;
+
+use crate::vault::types::utils::RecordId;
+
+/// Key/value tags of a record, e.g. `purpose = signing`.
+pub type Tags = BTreeMap<String, String>;
+
+/// The tags of all records of a vault
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TagIndex(pub BTreeMap<RecordId, Tags>);
+
+impl TagIndex {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&(self.0.len() as u32).to_be_bytes());
+        for (id, tags) in self.0.iter() {
+            buf.extend_from_slice(id.0.as_ref());
+            buf.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+            for (name, value) in tags.iter() {
+                for s in [name, value] {
+                    buf.extend_from_slice(&(s.len() as u32).to_be_bytes());
+                    buf.extend_from_slice(s.as_bytes());
+                }
+            }
+        }
+        buf
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader(bytes);
+        let mut index = BTreeMap::new();
+        for _ in 0..reader.u32()? {
+            let id = RecordId::load(reader.take(24)?).map_err(|e| e.to_string())?;
+            let mut tags = Tags::new();
+            for _ in 0..reader.u32()? {
+                let name = reader.string()?;
+                let value = reader.string()?;
+                tags.insert(name, value);
+            }
+            index.insert(id, tags);
+        }
+        if !reader.0.is_empty() {
+            return Err("Trailing bytes after tag index".into());
+        }
+        Ok(TagIndex(index))
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.0.len() < len {
+            return Err("Tag index is truncated".into());
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes(
+            bytes.try_into().expect("slice with incorrect length"),
+        ))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let mut index = TagIndex::default();
+        assert_eq!(TagIndex::decode(&index.encode()).unwrap(), index);
+
+        let mut tags = Tags::new();
+        tags.insert("purpose".into(), "signing".into());
+        tags.insert("algorithm".into(), "ed25519".into());
+        index.0.insert(RecordId::load(&[1; 24]).unwrap(), tags);
+        index.0.insert(RecordId::load(&[2; 24]).unwrap(), Tags::new());
+
+        let encoded = index.encode();
+        assert_eq!(TagIndex::decode(&encoded).unwrap(), index);
+        assert!(TagIndex::decode(&encoded[..encoded.len() - 1]).is_err());
+    }
+}
@@ -12,6 +12,7 @@ use crate::vault::{
     },
 };
 
+use crate::time::{Duration, SystemTime, UNIX_EPOCH};
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Debug, Formatter},
@@ -78,6 +79,10 @@ pub struct DataTransaction {
 
     /// a record hint
     pub record_hint: RecordHint,
+
+    /// time after which the data must not be read anymore, in seconds since the unix epoch. Data
+    /// without expiry, including data from older versions, has `0`.
+    pub expires_at: Val,
 }
 
 /// a typed transaction
@@ -95,11 +100,22 @@ pub struct RevocationTransaction {
 
     /// id identifer
     pub id: ChainId,
+
+    /// time of the revocation in seconds since the unix epoch. Revocations from older versions
+    /// have no timestamp and are read as `0`.
+    pub revoked_at: Val,
 }
 
 impl DataTransaction {
-    /// Create a new data transaction from a [`ChainId`], a len, a [`BlobId`] and a [`RecordHint`].
-    pub fn new<L: Into<Val>>(id: ChainId, len: L, blob: BlobId, record_hint: RecordHint) -> Transaction {
+    /// Create a new data transaction from a [`ChainId`], a len, a [`BlobId`] and a [`RecordHint`], for data
+    /// that expires at `expires_at`.
+    pub fn with_expiry<L: Into<Val>>(
+        id: ChainId,
+        len: L,
+        blob: BlobId,
+        record_hint: RecordHint,
+        expires_at: Option<SystemTime>,
+    ) -> Transaction {
         let mut transaction = Transaction::default();
         let view: &mut Self = transaction.view_mut();
 
@@ -108,8 +124,27 @@ impl DataTransaction {
         view.id = id;
         view.blob = blob;
         view.record_hint = record_hint;
+        view.expires_at = expires_at
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            // an expiry at the epoch itself would be read as no expiry
+            .map(|d| d.as_secs().max(1))
+            .unwrap_or_default()
+            .into();
         transaction
     }
+
+    /// Returns the time after which the data must not be read anymore, or `None`, if it doesn't expire.
+    pub fn expiry(&self) -> Option<SystemTime> {
+        match self.expires_at.u64() {
+            0 => None,
+            secs => Some(UNIX_EPOCH + Duration::from_secs(secs)),
+        }
+    }
+
+    /// Returns `true`, if the data has an expiry, that has passed.
+    pub fn is_expired(&self) -> bool {
+        self.expiry().is_some_and(|expiry| expiry <= SystemTime::now())
+    }
 }
 
 impl TypedTransaction for DataTransaction {
@@ -126,6 +161,11 @@ impl RevocationTransaction {
 
         view.type_id = (TransactionType::Revocation as u64).into();
         view.id = id;
+        view.revoked_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+            .into();
         transaction
     }
 }
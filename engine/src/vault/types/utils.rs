@@ -3,9 +3,11 @@
 
 use crate::vault::{base64::Base64Encodable, crypto_box::BoxProvider};
 
+use crypto::macs::hmac::HMAC_SHA512;
 use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
+    collections::HashMap,
     fmt::{self, Debug, Display, Formatter},
     hash::Hash,
     ops::{Add, AddAssign},
@@ -115,6 +117,33 @@ impl RecordId {
     pub fn load(data: &[u8]) -> Result<Self, InvalidLength> {
         Ok(RecordId(ChainId::load(data)?))
     }
+
+    /// Derives the [`RecordId`] of `record_path` in the vault at `vault_path`.
+    ///
+    /// The id is the first 24 bytes of `HMAC-SHA512(key = record_path, message = vault id)`, where the vault
+    /// id is derived with [`VaultId::deterministic`]. This is the derivation of the locations of the client,
+    /// so the id can be computed without loading the vault.
+    pub fn deterministic(vault_path: &[u8], record_path: &[u8]) -> Self {
+        let vid = VaultId::deterministic(vault_path);
+        RecordId(ChainId(derive_id(vid.0.as_ref(), record_path)))
+    }
+
+    /// Returns the pairs of indices into `record_paths`, whose paths derive the same [`RecordId`] in the
+    /// vault at `vault_path` with [`RecordId::deterministic`]. Equal paths are reported as well.
+    pub fn collisions<P: AsRef<[u8]>>(vault_path: &[u8], record_paths: &[P]) -> Vec<(usize, usize)> {
+        let mut seen: HashMap<RecordId, usize> = HashMap::with_capacity(record_paths.len());
+        let mut collisions = Vec::new();
+        for (i, path) in record_paths.iter().enumerate() {
+            let id = RecordId::deterministic(vault_path, path.as_ref());
+            match seen.get(&id) {
+                Some(first) => collisions.push((*first, i)),
+                None => {
+                    seen.insert(id, i);
+                }
+            }
+        }
+        collisions
+    }
 }
 
 impl Id {
@@ -142,6 +171,21 @@ impl VaultId {
     pub fn load(data: &[u8]) -> Result<Self, InvalidLength> {
         Ok(VaultId(Id::load(data)?))
     }
+
+    /// Derives the [`VaultId`] of `vault_path`: the first 24 bytes of
+    /// `HMAC-SHA512(key = vault_path, message = vault_path)`.
+    pub fn deterministic(vault_path: &[u8]) -> Self {
+        VaultId(Id(derive_id(vault_path, vault_path)))
+    }
+}
+
+/// First 24 bytes of `HMAC-SHA512(key, message)`
+fn derive_id(message: &[u8], key: &[u8]) -> [u8; 24] {
+    let mut mac = [0; 64];
+    HMAC_SHA512(message, key, &mut mac);
+    let mut id = [0; 24];
+    id.copy_from_slice(&mac[..24]);
+    id
 }
 
 impl ClientId {
@@ -328,6 +372,12 @@ impl TryFrom<&[u8]> for RecordId {
     }
 }
 
+impl AsRef<[u8]> for RecordId {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
 impl AsRef<[u8]> for Id {
     fn as_ref(&self) -> &[u8] {
         &self.0
@@ -444,3 +494,36 @@ impl Into<String> for VaultId {
         self.0.as_ref().base64()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_deterministic_ids() {
+        // fixed vectors, that embedders can check their own derivation against
+        let vid = VaultId::deterministic(b"vault");
+        assert_eq!(
+            vid,
+            VaultId::load(&[
+                0x33, 0x00, 0xab, 0xb9, 0xc1, 0x68, 0x90, 0x55, 0x3f, 0x9d, 0xaa, 0x17, 0x53, 0xbc, 0x04, 0xd0, 0x67,
+                0xb5, 0xff, 0xa7, 0x1e, 0xc8, 0x4d, 0x3d
+            ])
+            .unwrap()
+        );
+        let rid = RecordId::deterministic(b"vault", b"record");
+        assert_eq!(
+            rid,
+            RecordId::load(&[
+                0x37, 0x45, 0x10, 0xa7, 0x2e, 0x3e, 0x31, 0xd8, 0xca, 0x93, 0xbb, 0x93, 0xe6, 0xa8, 0x6c, 0xc0, 0x48,
+                0xd4, 0x9d, 0xcd, 0x4a, 0x1b, 0x63, 0x75
+            ])
+            .unwrap()
+        );
+        assert_ne!(RecordId::deterministic(b"other vault", b"record"), rid);
+
+        let paths: [&[u8]; 4] = [b"a", b"b", b"a", b"c"];
+        assert_eq!(RecordId::collisions(b"vault", &paths), vec![(0, 2)]);
+        assert!(RecordId::collisions(b"vault", &paths[1..]).is_empty());
+    }
+}
@@ -4,17 +4,31 @@
 use crate::vault::{
     crypto_box::{BoxProvider, Decrypt, Encrypt, Key},
     types::{
-        transactions::{DataTransaction, RevocationTransaction, SealedBlob, SealedTransaction},
+        audit::{AuditEntry, AuditEvent, AuditLog},
+        tags::{TagIndex, Tags},
+        transactions::{DataTransaction, RevocationTransaction, SealedBlob, SealedTransaction, Transaction},
         utils::{BlobId, ChainId, RecordHint, RecordId, VaultId},
     },
 };
 
+use crate::time::{Duration, SystemTime, UNIX_EPOCH};
+use crypto::hashes::{blake2b::Blake2b256, Digest};
 use runtime::memories::buffer::Buffer;
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, convert::Infallible, fmt::Debug};
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    ser::SerializeTuple,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    fmt::Debug,
+    ops::{Add, Deref},
+};
 use thiserror::Error as DeriveError;
+use zeroize::Zeroize;
 
-use super::{crypto_box::DecryptError, types::transactions::Transaction};
+use super::crypto_box::DecryptError;
 
 #[derive(DeriveError, Debug)]
 pub enum VaultError<TProvErr: Debug, TProcErr: Debug = Infallible> {
@@ -29,6 +43,22 @@ pub enum VaultError<TProvErr: Debug, TProcErr: Debug = Infallible> {
 
     #[error("Lock is poisoned")]
     LockPoisoned,
+
+    #[error("quota exceeded by a write into vault `{0:?}`")]
+    QuotaExceeded(VaultId),
+}
+
+impl<TProvErr: Debug> VaultError<TProvErr> {
+    /// Converts an error, that can't have been caused by a procedure, into the error type of a procedure.
+    pub fn with_procedure_error<TProcErr: Debug>(self) -> VaultError<TProvErr, TProcErr> {
+        match self {
+            VaultError::VaultNotFound(vid) => VaultError::VaultNotFound(vid),
+            VaultError::Record(e) => VaultError::Record(e),
+            VaultError::Procedure(e) => match e {},
+            VaultError::LockPoisoned => VaultError::LockPoisoned,
+            VaultError::QuotaExceeded(vid) => VaultError::QuotaExceeded(vid),
+        }
+    }
 }
 
 #[derive(DeriveError, Debug)]
@@ -45,22 +75,317 @@ pub enum RecordError<TProvErr: Debug> {
     #[error("no record with `{0:?}`")]
     RecordNotFound(ChainId),
 
+    #[error("record `{0:?}` has expired")]
+    Expired(ChainId),
+
+    #[error("record `{0:?}` has no version {1}")]
+    VersionNotFound(ChainId, usize),
+
     #[error("Lock is poisoned")]
     LockPoisoned,
 }
 
+/// Order of the entries in a [`RecordPage`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RecordOrder {
+    /// Ordered by [`RecordId`]. This order is stable across calls, as long as no records are added.
+    #[default]
+    Id,
+
+    /// Ordered by [`RecordHint`], records with the same hint are ordered by [`RecordId`].
+    Hint,
+}
+
+/// Selects a page of the records of a [`Vault`], see [`DbView::list_hints_and_ids_paged`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ListOptions {
+    /// Number of records to skip
+    pub offset: usize,
+
+    /// Maximum number of records in the page. All remaining records are returned, if `None`.
+    pub limit: Option<usize>,
+
+    /// Order of the records
+    pub order: RecordOrder,
+
+    /// Decode the [`RecordHint`] of each record as label
+    pub with_labels: bool,
+}
+
+/// An entry of a [`RecordPage`]
+#[derive(Debug, Clone)]
+pub struct RecordEntry {
+    pub id: RecordId,
+    pub hint: RecordHint,
+
+    /// The hint as UTF-8 string without trailing zero bytes. Only set, if labels have been requested
+    /// and the hint is valid UTF-8.
+    pub label: Option<String>,
+}
+
+/// A page of the records of a [`Vault`]
+#[derive(Debug, Clone, Default)]
+pub struct RecordPage {
+    pub entries: Vec<RecordEntry>,
+
+    /// Number of records in the vault, independent of the page
+    pub total: usize,
+}
+
+/// Limits of the records in a [`Vault`], or in all vaults of a [`DbView`], see [`DbView::set_quota`].
+/// Revoked records count against the limits, until they are garbage collected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quota {
+    /// Maximum number of records
+    pub max_records: Option<usize>,
+
+    /// Maximum size of the plaintext of all records
+    pub max_bytes: Option<usize>,
+}
+
+impl Quota {
+    /// Returns `true`, if `records` records with `bytes` bytes of plaintext are within the limits.
+    pub fn allows(&self, records: usize, bytes: usize) -> bool {
+        self.max_records.is_none_or(|max| records <= max) && self.max_bytes.is_none_or(|max| bytes <= max)
+    }
+}
+
+/// The revoked records, that the next garbage collection would delete, see [`DbView::gc_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcStats {
+    /// Number of revoked records
+    pub revoked: usize,
+
+    /// Size of the encrypted transactions and blobs of the revoked records
+    pub reclaimable_bytes: usize,
+}
+
+impl Add for GcStats {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        GcStats {
+            revoked: self.revoked + rhs.revoked,
+            reclaimable_bytes: self.reclaimable_bytes + rhs.reclaimable_bytes,
+        }
+    }
+}
+
+/// Selects the records of a [`Vault`] by their [`RecordHint`], see [`DbView::iter_records`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HintFilter {
+    /// All records
+    Any,
+
+    /// Records, whose hint starts with the given bytes
+    Prefix(Vec<u8>),
+
+    /// Records with the given hint
+    Exact(RecordHint),
+}
+
+impl HintFilter {
+    /// Returns `true`, if `hint` is selected by the filter.
+    pub fn matches(&self, hint: &RecordHint) -> bool {
+        match self {
+            HintFilter::Any => true,
+            HintFilter::Prefix(prefix) => hint.as_ref().starts_with(prefix),
+            HintFilter::Exact(expected) => hint == expected,
+        }
+    }
+}
+
+/// A record, that is written by [`DbView::write_batch`]. The data is zeroized on drop.
+pub struct WriteRequest {
+    pub record_id: RecordId,
+    pub data: Vec<u8>,
+    pub hint: RecordHint,
+}
+
+impl Drop for WriteRequest {
+    fn drop(&mut self) {
+        self.data.zeroize();
+    }
+}
+
 /// A view over the data inside of a collection of [`Vault`] types.
 #[derive(Deserialize, Serialize, Clone, Default)]
 pub struct DbView<P: BoxProvider> {
     /// A hashmap of the [`Vault`] types.
     pub vaults: HashMap<VaultId, Vault<P>>,
+
+    /// Number of previous versions, that are kept for each record. Not persisted.
+    #[serde(skip)]
+    history_depth: usize,
+
+    /// Limits of all vaults together. Not persisted.
+    #[serde(skip)]
+    quota: Quota,
+
+    /// Limits of single vaults. Not persisted.
+    #[serde(skip)]
+    vault_quotas: HashMap<VaultId, Quota>,
+
+    /// Key of the audit logs of all vaults, see [`Self::set_audit_key`]. Not persisted.
+    #[serde(skip)]
+    audit_key: Option<Key<P>>,
 }
 
 /// A enclave of data that is encrypted under one [`Key`].
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Vault<P: BoxProvider> {
     key: Key<P>,
-    entries: HashMap<ChainId, Record>,
+    entries: Entries,
+
+    /// Rolling digest over the logical content of the vault. Not persisted, but rebuilt on demand.
+    #[serde(skip)]
+    digest: Option<[u8; 32]>,
+
+    /// Previous versions of the entries, the most recent one first. Not persisted, and dropped by
+    /// garbage collection.
+    #[serde(skip)]
+    history: HashMap<ChainId, VecDeque<Record>>,
+}
+
+/// The entries of a [`Vault`] together with the encrypted index of their tags and the encrypted audit log.
+///
+/// Entries are serialized as `| ENTRIES_MARKER | ENTRIES_VERSION | records | tags | audit |`. The marker can not be
+/// the length of a map of records, so that vaults written before the format has been versioned, which only hold
+/// their records, are still read.
+#[derive(Clone, Default)]
+struct Entries {
+    records: HashMap<ChainId, Record>,
+    tags: Option<Record>,
+    audit: Option<Record>,
+
+    /// Size of the encrypted blobs of all records. It is updated on each change of the records, so that the
+    /// quotas can be checked without iterating over all records on every write.
+    blob_bytes: usize,
+}
+
+/// Marks the versioned serialization of [`Entries`]
+const ENTRIES_MARKER: u64 = u64::MAX;
+
+/// Version of the serialization of [`Entries`]
+const ENTRIES_VERSION: u8 = 1;
+
+/// Id, that the record of the tag index of a [`Vault`] is bound to
+fn tag_index_id() -> ChainId {
+    ChainId::load(&[0xff; 24]).expect("Tag index id has the length of a chain id")
+}
+
+/// Id, that the record of the audit log of a [`Vault`] is bound to
+fn audit_log_id() -> ChainId {
+    ChainId::load(&[0xfe; 24]).expect("Audit log id has the length of a chain id")
+}
+
+impl Deref for Entries {
+    type Target = HashMap<ChainId, Record>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.records
+    }
+}
+
+impl Entries {
+    fn new(records: HashMap<ChainId, Record>, tags: Option<Record>, audit: Option<Record>) -> Self {
+        let blob_bytes = records.values().map(Record::blob_size).sum();
+        Entries {
+            records,
+            tags,
+            audit,
+            blob_bytes,
+        }
+    }
+
+    fn insert(&mut self, id: ChainId, record: Record) -> Option<Record> {
+        self.blob_bytes += record.blob_size();
+        let old = self.records.insert(id, record);
+        if let Some(old) = old.as_ref() {
+            self.blob_bytes -= old.blob_size();
+        }
+        old
+    }
+
+    fn remove(&mut self, id: &ChainId) -> Option<Record> {
+        let old = self.records.remove(id);
+        if let Some(old) = old.as_ref() {
+            self.blob_bytes -= old.blob_size();
+        }
+        old
+    }
+
+    /// Applies `f` to the record with the given id. Returns `None`, if the record doesn't exist.
+    fn update<T, F>(&mut self, id: &ChainId, f: F) -> Option<T>
+    where
+        F: FnOnce(&mut Record) -> T,
+    {
+        let record = self.records.get_mut(id)?;
+        self.blob_bytes -= record.blob_size();
+        let result = f(record);
+        self.blob_bytes += record.blob_size();
+        Some(result)
+    }
+}
+
+impl Serialize for Entries {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(5)?;
+        tuple.serialize_element(&ENTRIES_MARKER)?;
+        tuple.serialize_element(&ENTRIES_VERSION)?;
+        tuple.serialize_element(&self.records)?;
+        tuple.serialize_element(&self.tags)?;
+        tuple.serialize_element(&self.audit)?;
+        tuple.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Entries {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // the length is only known after the first element, that is either the marker or the length of the
+        // unversioned map of records
+        deserializer.deserialize_tuple(usize::MAX, EntriesVisitor)
+    }
+}
+
+struct EntriesVisitor;
+
+impl<'de> Visitor<'de> for EntriesVisitor {
+    type Value = Entries;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("the entries of a vault")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        fn next<'de, T: Deserialize<'de>, A: SeqAccess<'de>>(seq: &mut A, index: usize) -> Result<T, A::Error> {
+            seq.next_element()?
+                .ok_or_else(|| de::Error::invalid_length(index, &EntriesVisitor))
+        }
+
+        let first: u64 = next(&mut seq, 0)?;
+        if first != ENTRIES_MARKER {
+            // unversioned vaults only hold their records
+            let mut records = HashMap::new();
+            for i in 0..first as usize {
+                let (id, record): (ChainId, Record) = next(&mut seq, i + 1)?;
+                records.insert(id, record);
+            }
+            return Ok(Entries::new(records, None, None));
+        }
+
+        let version: u8 = next(&mut seq, 1)?;
+        if version != ENTRIES_VERSION {
+            return Err(de::Error::custom(format!(
+                "unsupported version {} of vault entries",
+                version
+            )));
+        }
+        let records = next(&mut seq, 2)?;
+        let tags = next(&mut seq, 3)?;
+        let audit = next(&mut seq, 4)?;
+        Ok(Entries::new(records, tags, audit))
+    }
 }
 
 /// A bit of data inside of a [`Vault`].
@@ -81,7 +406,89 @@ impl<P: BoxProvider> DbView<P> {
     pub fn new() -> DbView<P> {
         let vaults = HashMap::new();
 
-        Self { vaults }
+        Self {
+            vaults,
+            history_depth: 0,
+            quota: Quota::default(),
+            vault_quotas: HashMap::new(),
+            audit_key: None,
+        }
+    }
+
+    /// Sets the number of previous versions, that are kept for each record when it is overwritten.
+    /// Previous versions can be read with [`Self::get_version_guard`] and restored with
+    /// [`Self::revert_record`] until the record is garbage collected. Lowering the depth drops the oldest
+    /// versions, a depth of zero, which is the default, disables the history.
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history_depth = depth;
+        self.vaults.values_mut().for_each(|vault| vault.truncate_history(depth));
+    }
+
+    /// Returns the number of previous versions, that are kept for each record.
+    pub fn history_depth(&self) -> usize {
+        self.history_depth
+    }
+
+    /// Sets the limits of all vaults together. Writes, that would exceed them, fail with
+    /// [`VaultError::QuotaExceeded`]. Records, that already exceed them, are kept.
+    pub fn set_quota(&mut self, quota: Quota) {
+        self.quota = quota;
+    }
+
+    /// Returns the limits of all vaults together.
+    pub fn quota(&self) -> Quota {
+        self.quota
+    }
+
+    /// Sets the limits of a single [`Vault`], in addition to the limits of [`Self::set_quota`].
+    pub fn set_vault_quota(&mut self, vid: VaultId, quota: Quota) {
+        if quota == Quota::default() {
+            self.vault_quotas.remove(&vid);
+        } else {
+            self.vault_quotas.insert(vid, quota);
+        }
+    }
+
+    /// Returns the limits of a single [`Vault`].
+    pub fn vault_quota(&self, vid: VaultId) -> Quota {
+        self.vault_quotas.get(&vid).copied().unwrap_or_default()
+    }
+
+    /// Checks, that writing records with the given ids and plaintext sizes into the [`Vault`] `vid` stays
+    /// within the quotas. Records that already exist are replaced by the write.
+    fn check_quota<I>(&self, vid: VaultId, writes: I) -> Result<(), VaultError<P::Error>>
+    where
+        I: IntoIterator<Item = (ChainId, usize)>,
+    {
+        let vault_quota = self.vault_quota(vid);
+        if vault_quota == Quota::default() && self.quota == Quota::default() {
+            return Ok(());
+        }
+
+        let vault = self.vaults.get(&vid);
+        let (mut records, mut bytes) = vault.map_or((0, 0), |vault| vault.usage());
+        let writes: HashMap<ChainId, usize> = writes.into_iter().collect();
+        for (id, size) in writes {
+            match vault.and_then(|vault| vault.entries.get(&id)) {
+                Some(record) => bytes = bytes.saturating_sub(record.plaintext_size::<P>()) + size,
+                None => {
+                    records += 1;
+                    bytes += size;
+                }
+            }
+        }
+
+        let (other_records, other_bytes) = self
+            .vaults
+            .iter()
+            .filter(|(id, _)| **id != vid)
+            .map(|(_, vault)| vault.usage())
+            .fold((0, 0), |(r, b), (records, bytes)| (r + records, b + bytes));
+
+        if !vault_quota.allows(records, bytes) || !self.quota.allows(other_records + records, other_bytes + bytes) {
+            return Err(VaultError::QuotaExceeded(vid));
+        }
+        Ok(())
     }
 
     /// Initialize a new [`Vault`] if it doesn't exist.
@@ -97,13 +504,57 @@ impl<P: BoxProvider> DbView<P> {
         rid: RecordId,
         data: &[u8],
         record_hint: RecordHint,
-    ) -> Result<(), RecordError<P::Error>> {
+    ) -> Result<(), VaultError<P::Error>> {
+        self.write_with_expiry(key, vid, rid, data, record_hint, None)
+    }
+
+    /// Same as [`Self::write`], but the record can't be read anymore after `expires_at`, and is deleted
+    /// by the next garbage collection of the [`Vault`] after that.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(vault = ?vid, record = ?rid, len = data.len())))]
+    pub fn write_with_expiry(
+        &mut self,
+        key: &Key<P>,
+        vid: VaultId,
+        rid: RecordId,
+        data: &[u8],
+        record_hint: RecordHint,
+        expires_at: Option<SystemTime>,
+    ) -> Result<(), VaultError<P::Error>> {
+        self.check_quota(vid, [(rid.0, data.len())])?;
         if !self.vaults.contains_key(&vid) {
             self.init_vault(key, vid);
         }
 
         let vault = self.vaults.get_mut(&vid).expect("Vault was initiated");
-        vault.add_or_update_record(key, rid.0, data, record_hint)
+        vault.check_key(key)?;
+        vault.archive(rid.0, self.history_depth);
+        vault.add_or_update_record_with_expiry(key, rid.0, data, record_hint, expires_at)?;
+        Ok(())
+    }
+
+    /// Writes all `requests` into the given [`Vault`], which is created if it doesn't exist yet. The key is
+    /// checked once for the whole batch, and so are the quotas. Writing stops at the first failure, the
+    /// records that have been written before it are kept.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(vault = ?vid, records = requests.len())))]
+    pub fn write_batch(
+        &mut self,
+        key: &Key<P>,
+        vid: VaultId,
+        requests: Vec<WriteRequest>,
+    ) -> Result<(), VaultError<P::Error>> {
+        self.check_quota(
+            vid,
+            requests.iter().map(|request| (request.record_id.0, request.data.len())),
+        )?;
+        self.init_vault(key, vid);
+        let depth = self.history_depth;
+        let vault = self.vaults.get_mut(&vid).expect("Vault was initiated");
+        vault.check_key(key)?;
+        for request in requests.iter() {
+            vault.archive(request.record_id.0, depth);
+            vault.add_or_update_record_with_expiry(key, request.record_id.0, &request.data, request.hint, None)?;
+        }
+        Ok(())
     }
 
     /// Lists all of the [`RecordHint`] values and [`RecordId`] values for the given [`Vault`].
@@ -115,6 +566,115 @@ impl<P: BoxProvider> DbView<P> {
         }
     }
 
+    /// Iterates over the [`RecordId`] and [`RecordHint`] of the records in the given [`Vault`], that are
+    /// selected by `filter`. Only the metadata of each record is decrypted, and nothing is collected, so
+    /// the caller can stop early, e.g. after the first match.
+    pub fn iter_records<'a>(
+        &'a self,
+        key: &'a Key<P>,
+        vid: VaultId,
+        filter: HintFilter,
+    ) -> impl Iterator<Item = (RecordId, RecordHint)> + 'a {
+        self.vaults
+            .get(&vid)
+            .into_iter()
+            .flat_map(move |vault| vault.iter_hints_and_ids(key))
+            .filter(move |(_, hint)| filter.matches(hint))
+    }
+
+    /// Same as [`Self::list_hints_and_ids`], but only returns the page of records that is selected by
+    /// `options`. Large vaults can be listed in multiple calls this way.
+    pub fn list_hints_and_ids_paged(&self, key: &Key<P>, vid: VaultId, options: &ListOptions) -> RecordPage {
+        let mut records = self.list_hints_and_ids(key, vid);
+        let total = records.len();
+        match options.order {
+            RecordOrder::Id => records.sort_by_key(|(id, _)| *id),
+            RecordOrder::Hint => records.sort_by_key(|(id, hint)| (*hint, *id)),
+        }
+
+        let entries = records
+            .into_iter()
+            .skip(options.offset)
+            .take(options.limit.unwrap_or(usize::MAX))
+            .map(|(id, hint)| {
+                let label = if options.with_labels { hint_label(&hint) } else { None };
+                RecordEntry { id, hint, label }
+            })
+            .collect();
+
+        RecordPage { entries, total }
+    }
+
+    /// Sets the tag `name` of a [`Record`] to `value`. Tags are kept in an encrypted index of the
+    /// [`Vault`], and can be queried with [`Self::find_by_tag`].
+    pub fn set_tag(
+        &mut self,
+        key: &Key<P>,
+        vid: VaultId,
+        rid: RecordId,
+        name: &str,
+        value: &str,
+    ) -> Result<(), VaultError<P::Error>> {
+        let vault = self.vaults.get_mut(&vid).ok_or(VaultError::VaultNotFound(vid))?;
+        vault.set_tag(key, rid.0, name, value).map_err(VaultError::Record)
+    }
+
+    /// Removes the tag `name` of a [`Record`]. Returns `false`, if the [`Record`] didn't have the tag.
+    pub fn remove_tag(
+        &mut self,
+        key: &Key<P>,
+        vid: VaultId,
+        rid: RecordId,
+        name: &str,
+    ) -> Result<bool, VaultError<P::Error>> {
+        let vault = self.vaults.get_mut(&vid).ok_or(VaultError::VaultNotFound(vid))?;
+        vault.remove_tag(key, rid.0, name).map_err(VaultError::Record)
+    }
+
+    /// Returns the tags of a [`Record`].
+    pub fn tags(&self, key: &Key<P>, vid: VaultId, rid: RecordId) -> Result<Tags, VaultError<P::Error>> {
+        let vault = self.vaults.get(&vid).ok_or(VaultError::VaultNotFound(vid))?;
+        let mut index = vault.tag_index(key).map_err(VaultError::Record)?;
+        Ok(index.0.remove(&rid).unwrap_or_default())
+    }
+
+    /// Finds the [`Record`]s of a [`Vault`], whose tag `name` is `value`. Revoked records are not returned.
+    pub fn find_by_tag(
+        &self,
+        key: &Key<P>,
+        vid: VaultId,
+        name: &str,
+        value: &str,
+    ) -> Result<Vec<RecordId>, VaultError<P::Error>> {
+        let vault = self.vaults.get(&vid).ok_or(VaultError::VaultNotFound(vid))?;
+        let index = vault.tag_index(key).map_err(VaultError::Record)?;
+        Ok(index
+            .0
+            .into_iter()
+            .filter(|(rid, tags)| tags.get(name).map(String::as_str) == Some(value) && vault.is_live(rid.0))
+            .map(|(rid, _)| rid)
+            .collect())
+    }
+
+    /// Returns the log of the revocations and deletions of records in a [`Vault`], the oldest entry first.
+    ///
+    /// Fails with [`RecordError::CorruptedContent`], if the log has not been written with the audit key of this
+    /// view, or has been tampered with.
+    pub fn revocation_history(&self, key: &Key<P>, vid: VaultId) -> Result<Vec<AuditEntry>, VaultError<P::Error>> {
+        let vault = self.vaults.get(&vid).ok_or(VaultError::VaultNotFound(vid))?;
+        let log = vault.audit_log(key).map_err(VaultError::Record)?;
+        let authentic = match &self.audit_key {
+            Some(audit_key) => log.verify(audit_key),
+            None => log.0.is_empty(),
+        };
+        if !authentic {
+            return Err(VaultError::Record(RecordError::CorruptedContent(
+                "Audit log chain is broken".into(),
+            )));
+        }
+        Ok(log.0)
+    }
+
     /// Check to see if a vault with the given [`VaultId`] is present.
     pub fn contains_vault(&self, vid: &VaultId) -> bool {
         self.vaults.contains_key(vid)
@@ -160,6 +720,7 @@ impl<P: BoxProvider> DbView<P> {
     }
 
     /// Get access the decrypted [`Buffer`] of the specified [`Record`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(vault = ?vid, record = ?rid)))]
     pub fn get_guard<E, F>(
         &self,
         key: &Key<P>,
@@ -176,6 +737,44 @@ impl<P: BoxProvider> DbView<P> {
         f(guard).map_err(VaultError::Procedure)
     }
 
+    /// Same as [`Self::get_guard`], but for the `n`-th previous version of the [`Record`]. Version `0` is
+    /// the current one, see [`Self::set_history_depth`].
+    pub fn get_version_guard<E, F>(
+        &self,
+        key: &Key<P>,
+        vid: VaultId,
+        rid: RecordId,
+        n: usize,
+        f: F,
+    ) -> Result<(), VaultError<P::Error, E>>
+    where
+        F: FnOnce(Buffer<u8>) -> Result<(), E>,
+        E: Debug,
+    {
+        let vault = self.vaults.get(&vid).ok_or(VaultError::VaultNotFound(vid))?;
+        let guard = vault.get_version_guard(key, rid.0, n).map_err(VaultError::Record)?;
+        f(guard).map_err(VaultError::Procedure)
+    }
+
+    /// Returns the number of previous versions of a [`Record`], that are kept.
+    pub fn version_count(&self, vid: VaultId, rid: RecordId) -> usize {
+        self.vaults.get(&vid).map_or(0, |vault| vault.version_count(rid.0))
+    }
+
+    /// Makes the `n`-th previous version of a [`Record`] the current one. The versions after it,
+    /// including the current one, are discarded. Also restores a [`Record`], that has been revoked.
+    pub fn revert_record(
+        &mut self,
+        key: &Key<P>,
+        vid: VaultId,
+        rid: RecordId,
+        n: usize,
+    ) -> Result<(), VaultError<P::Error>> {
+        let vault = self.vaults.get_mut(&vid).ok_or(VaultError::VaultNotFound(vid))?;
+        vault.revert(key, rid.0, n).map_err(VaultError::Record)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(sources = N)))]
     pub fn get_guards<E, F, const N: usize>(
         &self,
         ids: [(Key<P>, VaultId, RecordId); N],
@@ -189,6 +788,28 @@ impl<P: BoxProvider> DbView<P> {
         f(buffers).map_err(VaultError::Procedure)
     }
 
+    /// Access the decrypted [`Buffer`]s of multiple [`Record`]s of the same [`Vault`]. The buffers are passed
+    /// to `f` in the order of `rids`.
+    pub fn read_batch<E, F>(
+        &self,
+        key: &Key<P>,
+        vid: VaultId,
+        rids: &[RecordId],
+        f: F,
+    ) -> Result<(), VaultError<P::Error, E>>
+    where
+        F: FnOnce(Vec<Buffer<u8>>) -> Result<(), E>,
+        E: Debug,
+    {
+        let vault = self.vaults.get(&vid).ok_or(VaultError::VaultNotFound(vid))?;
+        let buffers = rids
+            .iter()
+            .map(|rid| vault.get_guard(key, rid.0))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(VaultError::Record)?;
+        f(buffers).map_err(VaultError::Procedure)
+    }
+
     /// Access the decrypted [`Buffer`]s of the specified [`Record`]s and place the return value
     /// into the target [`Record`].
     pub fn exec_procedure<E, F, const N: usize>(
@@ -204,36 +825,147 @@ impl<P: BoxProvider> DbView<P> {
         F: FnOnce([Buffer<u8>; N]) -> Result<Vec<u8>, E>,
         E: Debug,
     {
-        let buffers: [Buffer<u8>; N] = self.get_buffers(sources)?;
-
-        let data: Vec<u8> = f(buffers).map_err(VaultError::Procedure)?;
+        let data: Vec<u8> = self.run_procedure(sources, f)?;
 
         self.write(target_key, target_vid, target_rid, &data, hint)
-            .map_err(VaultError::Record)
+            .map_err(VaultError::with_procedure_error)
+    }
+
+    /// Applies `f` to the decrypted [`Buffer`]s of the sources, and returns the data, that
+    /// [`Self::exec_procedure`] would write into the target [`Record`].
+    ///
+    /// Only needs shared access to the view, so that expensive procedures can run concurrently. The caller is
+    /// responsible for writing the result, e.g. with [`Self::write`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(sources = N)))]
+    pub fn run_procedure<E, F, const N: usize>(
+        &self,
+        sources: [(Key<P>, VaultId, RecordId); N],
+        f: F,
+    ) -> Result<Vec<u8>, VaultError<P::Error, E>>
+    where
+        F: FnOnce([Buffer<u8>; N]) -> Result<Vec<u8>, E>,
+        E: Debug,
+    {
+        let buffers: [Buffer<u8>; N] = self.get_buffers(sources)?;
+        f(buffers).map_err(VaultError::Procedure)
     }
 
     /// Add a revocation transaction to the [`Record`]
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(vault = ?vid, record = ?rid)))]
     pub fn revoke_record(&mut self, key: &Key<P>, vid: VaultId, rid: RecordId) -> Result<(), RecordError<P::Error>> {
         if let Some(vault) = self.vaults.get_mut(&vid) {
-            vault.revoke(key, rid.0)?;
+            let audit_key = self.audit_key.get_or_insert_with(Key::random);
+            vault.revoke(key, audit_key, rid.0)?;
         }
         Ok(())
     }
 
-    /// Garbage collect a [`Vault`]. Deletes any records that contain revocation transactions.
+    /// Garbage collect a [`Vault`]. Deletes any records that contain revocation transactions, and records
+    /// that have expired.
     pub fn garbage_collect_vault(&mut self, key: &Key<P>, vid: VaultId) {
         if let Some(vault) = self.vaults.get_mut(&vid) {
             if &vault.key == key {
-                vault.garbage_collect();
+                let audit_key = self.audit_key.get_or_insert_with(Key::random);
+                // records, whose transaction can't be decrypted, are left to the reads to report
+                let _ = vault.garbage_collect_expired(key, audit_key, Duration::ZERO);
             }
         }
     }
 
+    /// Garbage collect a [`Vault`], but keep revoked records that have been revoked less than `retention` ago.
+    /// Expired records are always deleted. Returns the number of deleted records.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(vault = ?vid)))]
+    pub fn garbage_collect_vault_expired(
+        &mut self,
+        key: &Key<P>,
+        vid: VaultId,
+        retention: Duration,
+    ) -> Result<usize, RecordError<P::Error>> {
+        match self.vaults.get_mut(&vid) {
+            Some(vault) => {
+                let audit_key = self.audit_key.get_or_insert_with(Key::random);
+                vault.garbage_collect_expired(key, audit_key, retention)
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Removes the revocation of a [`Record`], that has not been garbage collected yet.
+    /// Returns `false`, if there is no revoked record with the given [`RecordId`].
+    pub fn restore_record(&mut self, key: &Key<P>, vid: VaultId, rid: RecordId) -> Result<bool, VaultError<P::Error>> {
+        let vault = self.vaults.get_mut(&vid).ok_or(VaultError::VaultNotFound(vid))?;
+        let audit_key = self.audit_key.get_or_insert_with(Key::random);
+        vault.restore(key, audit_key, rid.0).map_err(VaultError::Record)
+    }
+
+    /// Lists all revoked records of a [`Vault`], that have not been garbage collected yet, with the
+    /// time of their revocation.
+    pub fn list_revoked_records(
+        &self,
+        key: &Key<P>,
+        vid: VaultId,
+    ) -> Result<Vec<(RecordId, SystemTime)>, VaultError<P::Error>> {
+        let vault = self.vaults.get(&vid).ok_or(VaultError::VaultNotFound(vid))?;
+        vault.list_revoked(key).map_err(VaultError::Record)
+    }
+
+    /// Re-encrypts all records of a [`Vault`] with `new_key`, which replaces `old_key` as the key of the
+    /// vault. Revoked records, whose transactions can only be read with the old key, are deleted.
+    /// Returns the number of re-encrypted records.
+    ///
+    /// The vault is left unchanged, if any of its records can not be re-encrypted.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(vault = ?vid)))]
+    pub fn rekey_vault(
+        &mut self,
+        old_key: &Key<P>,
+        new_key: &Key<P>,
+        vid: VaultId,
+    ) -> Result<usize, VaultError<P::Error>> {
+        let vault = self.vaults.get_mut(&vid).ok_or(VaultError::VaultNotFound(vid))?;
+        let audit_key = self.audit_key.get_or_insert_with(Key::random);
+        vault.rekey(old_key, new_key, audit_key).map_err(VaultError::Record)
+    }
+
+    /// Returns the number of revoked records across all vaults that have not been garbage collected yet.
+    pub fn pending_garbage(&self) -> usize {
+        self.vaults.values().map(|vault| vault.pending_garbage()).sum()
+    }
+
+    /// Returns the revoked records across all vaults, that have not been garbage collected yet. Revoked
+    /// records within the retention window of a garbage collection are included.
+    pub fn gc_stats(&self) -> GcStats {
+        self.vaults
+            .values()
+            .fold(GcStats::default(), |stats, vault| stats + vault.gc_stats())
+    }
+
+    /// Same as [`Self::gc_stats`], but for a single [`Vault`].
+    pub fn vault_gc_stats(&self, vid: VaultId) -> Option<GcStats> {
+        self.vaults.get(&vid).map(|vault| vault.gc_stats())
+    }
+
+    /// Returns the digest over the logical content of a [`Vault`]. Two vaults with the same
+    /// records, that have been synchronized from one another, return the same digest, independent of the
+    /// key they are encrypted with.
+    pub fn vault_digest(&mut self, key: &Key<P>, vid: VaultId) -> Result<[u8; 32], VaultError<P::Error>> {
+        let vault = self.vaults.get_mut(&vid).ok_or(VaultError::VaultNotFound(vid))?;
+        vault.digest(key).map_err(VaultError::Record)
+    }
+
     /// Clears the entire [`Vault`] from memory.
     pub fn clear(&mut self) {
         self.vaults.clear();
     }
 
+    /// Sets the key, that authenticates the entries of the audit logs of all vaults. It is separate from the keys
+    /// of the vaults, so that holding the key of a vault does not suffice to rewrite its log. If no key has been
+    /// set, a random one is generated with the first entry.
+    ///
+    /// The key is not persisted with the view, and has to be stored alongside the keys of the vaults.
+    pub fn set_audit_key(&mut self, audit_key: Key<P>) {
+        self.audit_key = Some(audit_key);
+    }
+
     /// List the ids of all vaults.
     pub fn list_vaults(&self) -> Vec<VaultId> {
         self.vaults.keys().cloned().collect()
@@ -286,36 +1018,100 @@ impl<P: BoxProvider> DbView<P> {
         Ok(list)
     }
 
+    /// Returns a copy of a [`Record`], that is re-encrypted with `target_key` for `target_rid`. The data is only
+    /// decrypted into guarded memory. Add the copy to a [`Vault`] with [`Self::insert_record`].
+    pub fn reencrypt_record(
+        &self,
+        key: &Key<P>,
+        vid: VaultId,
+        rid: RecordId,
+        target_key: &Key<P>,
+        target_rid: RecordId,
+    ) -> Result<Record, VaultError<P::Error>> {
+        let vault = self.vaults.get(&vid).ok_or(VaultError::VaultNotFound(vid))?;
+        vault
+            .reencrypt_record(key, rid.0, target_key, target_rid.0)
+            .map_err(VaultError::Record)
+    }
+
+    /// Inserts a [`Record`], that has been encrypted with `key` for `rid`, into a [`Vault`]. An existing
+    /// [`Record`] with the same id is replaced.
+    pub fn insert_record(
+        &mut self,
+        key: &Key<P>,
+        vid: VaultId,
+        rid: RecordId,
+        record: Record,
+    ) -> Result<(), VaultError<P::Error>> {
+        self.check_quota(vid, [(rid.0, record.plaintext_size::<P>())])?;
+        self.init_vault(key, vid);
+        let vault = self.vaults.get_mut(&vid).expect("Vault was initiated");
+        vault.check_key(key)?;
+        vault.archive(rid.0, self.history_depth);
+        vault.extend(key, [(rid.0, record)])?;
+        Ok(())
+    }
+
+    /// Copies a [`Record`] to `target_rid` in the [`Vault`] `target_vid`, which is created if it doesn't exist yet.
+    /// The copy is re-encrypted with `target_key`.
+    pub fn copy_record(
+        &mut self,
+        key: &Key<P>,
+        vid: VaultId,
+        rid: RecordId,
+        target_key: &Key<P>,
+        target_vid: VaultId,
+        target_rid: RecordId,
+    ) -> Result<(), VaultError<P::Error>> {
+        let record = self.reencrypt_record(key, vid, rid, target_key, target_rid)?;
+        self.insert_record(target_key, target_vid, target_rid, record)
+    }
+
     /// Import records to the [`Vault`]. In case of duplicated records, the existing record is dropped in favor of the
-    /// new one. Re-encrypt the records with the new key.
+    /// new one. Re-encrypt the records with the new key. Fails with [`VaultError::QuotaExceeded`], if the imported
+    /// records would exceed the quotas.
     pub fn import_records(
         &mut self,
         old_key: &Key<P>,
         new_key: &Key<P>,
         vid: VaultId,
         mut records: Vec<(RecordId, Record)>,
-    ) -> Result<(), RecordError<P::Error>> {
+    ) -> Result<(), VaultError<P::Error>> {
+        self.check_quota(
+            vid,
+            records
+                .iter()
+                .map(|(rid, record)| (rid.0, record.plaintext_size::<P>())),
+        )?;
         if !self.vaults.contains_key(&vid) {
             self.init_vault(new_key, vid);
         }
         for (rid, record) in &mut records {
-            record
-                .update_meta(old_key, (*rid).into(), new_key, (*rid).into())
-                .unwrap();
+            record.update_meta(old_key, (*rid).into(), new_key, (*rid).into())?;
         }
         let vault = self.vaults.get_mut(&vid).expect("Vault was initiated.");
-        vault.extend(new_key, records.into_iter().map(|(rid, r)| (rid.0, r)))
+        vault.extend(new_key, records.into_iter().map(|(rid, r)| (rid.0, r)))?;
+        Ok(())
     }
 }
 
+/// Decodes a [`RecordHint`] as UTF-8, without the zero bytes it has been padded with
+fn hint_label(hint: &RecordHint) -> Option<String> {
+    let bytes = hint.as_ref();
+    let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    String::from_utf8(bytes[..len].to_vec()).ok()
+}
+
 impl<P: BoxProvider> Vault<P> {
     /// Initialize a new [`Vault`]
     pub fn init_vault(key: &Key<P>) -> Vault<P> {
-        let entries = HashMap::new();
+        let entries = Entries::default();
 
         Self {
             entries,
             key: key.clone(),
+            digest: Some([0u8; 32]),
+            history: HashMap::new(),
         }
     }
 
@@ -327,15 +1123,42 @@ impl<P: BoxProvider> Vault<P> {
         id: ChainId,
         data: &[u8],
         record_hint: RecordHint,
+    ) -> Result<(), RecordError<P::Error>> {
+        self.add_or_update_record_with_expiry(key, id, data, record_hint, None)
+    }
+
+    /// Same as [`Self::add_or_update_record`], but sets the time after which the [`Record`] expires. An
+    /// update replaces the expiry of the existing [`Record`].
+    pub fn add_or_update_record_with_expiry(
+        &mut self,
+        key: &Key<P>,
+        id: ChainId,
+        data: &[u8],
+        record_hint: RecordHint,
+        expires_at: Option<SystemTime>,
     ) -> Result<(), RecordError<P::Error>> {
         self.check_key(key)?;
         let blob_id = BlobId::random::<P>().map_err(RecordError::Provider)?;
-        if let Some(entry) = self.entries.get_mut(&id) {
-            // TODO: double-check that using a new blob-id does not break the old snapshot format.
-            entry.update_data(key, id, data, blob_id)?
-        } else {
-            let entry = Record::new(key, id, blob_id, data, record_hint).map_err(RecordError::Provider)?;
-            self.entries.insert(id, entry);
+        let old_blob_id = self.live_blob_id(key, id)?;
+        // TODO: double-check that using a new blob-id does not break the old snapshot format.
+        match self
+            .entries
+            .update(&id, |entry| entry.update_data(key, id, data, blob_id, expires_at))
+        {
+            Some(updated) => updated?,
+            None => {
+                let entry = Record::with_expiry(key, id, blob_id, data, record_hint, expires_at)
+                    .map_err(RecordError::Provider)?;
+                self.entries.insert(id, entry);
+            }
+        }
+
+        // the digest only covers live entries
+        if let Some(old_blob_id) = old_blob_id {
+            self.roll_digest(id, old_blob_id);
+        }
+        if self.is_live(id) {
+            self.roll_digest(id, blob_id);
         }
 
         Ok(())
@@ -348,10 +1171,44 @@ impl<P: BoxProvider> Vault<P> {
         I: IntoIterator<Item = (ChainId, Record)>,
     {
         self.check_key(key)?;
-        self.entries.extend(entries.into_iter());
+        for (id, record) in entries {
+            let blob_id = match record.revoke {
+                None => Some(record.get_blob_id(key, id)?),
+                Some(_) => None,
+            };
+            if let Some(old_blob_id) = self.live_blob_id(key, id)? {
+                self.roll_digest(id, old_blob_id);
+            }
+            self.entries.insert(id, record);
+            if let Some(blob_id) = blob_id {
+                self.roll_digest(id, blob_id);
+            }
+        }
         Ok(())
     }
 
+    /// Re-encrypts an entry with `target_key` for `target_id`, with a new [`BlobId`].
+    fn reencrypt_record(
+        &self,
+        key: &Key<P>,
+        id: ChainId,
+        target_key: &Key<P>,
+        target_id: ChainId,
+    ) -> Result<Record, RecordError<P::Error>> {
+        self.check_key(key)?;
+        let entry = self.entries.get(&id).ok_or(RecordError::RecordNotFound(id))?;
+        let tx = entry.get_transaction(key)?;
+        let tx = tx.typed::<DataTransaction>().ok_or_else(|| {
+            RecordError::CorruptedContent("Could not type decrypted transaction as data-transaction".into())
+        })?;
+        let (hint, expires_at) = (tx.record_hint, tx.expiry());
+
+        let guard = entry.get_blob(key, id)?;
+        let blob_id = BlobId::random::<P>().map_err(RecordError::Provider)?;
+        let record = Record::with_expiry(target_key, target_id, blob_id, &guard.borrow(), hint, expires_at);
+        record.map_err(RecordError::Provider)
+    }
+
     /// Export record stored in the current vault. This clones the encrypted record without
     /// removing it.
     pub fn export_record(&self, rid: &RecordId) -> Option<Record> {
@@ -360,17 +1217,24 @@ impl<P: BoxProvider> Vault<P> {
 
     /// List the [`RecordHint`] values and [`RecordId`] values of the specified [`Vault`].
     pub(crate) fn list_hints_and_ids(&self, key: &Key<P>) -> Vec<(RecordId, RecordHint)> {
-        let mut buf: Vec<(RecordId, RecordHint)> = Vec::new();
-
-        if key == &self.key {
-            buf = self
-                .entries
-                .values()
-                .filter_map(|entry| entry.get_hint_and_id(key).ok())
-                .collect();
-        }
+        self.iter_hints_and_ids(key).collect()
+    }
 
-        buf
+    /// Iterates over the [`RecordHint`] and [`RecordId`] values of the entries, that can be decrypted with
+    /// `key`. Yields nothing for a wrong key.
+    pub(crate) fn iter_hints_and_ids<'a>(
+        &'a self,
+        key: &'a Key<P>,
+    ) -> impl Iterator<Item = (RecordId, RecordHint)> + 'a {
+        let entries = if key == &self.key {
+            Some(self.entries.values())
+        } else {
+            None
+        };
+        entries
+            .into_iter()
+            .flatten()
+            .filter_map(move |entry| entry.get_hint_and_id(key).ok())
     }
 
     /// List the [`RecordId`]s of the entries stored in this [`Vault`].
@@ -389,10 +1253,13 @@ impl<P: BoxProvider> Vault<P> {
     }
 
     /// Revokes an [`Record`] by its [`ChainId`].  Does nothing if the [`Record`] doesn't exist.
-    pub fn revoke(&mut self, key: &Key<P>, id: ChainId) -> Result<(), RecordError<P::Error>> {
+    pub fn revoke(&mut self, key: &Key<P>, audit_key: &Key<P>, id: ChainId) -> Result<(), RecordError<P::Error>> {
         self.check_key(key)?;
-        if let Some(entry) = self.entries.get_mut(&id) {
-            entry.revoke(key, id)?;
+        let live_blob_id = self.live_blob_id(key, id)?;
+        self.entries.update(&id, |entry| entry.revoke(key, id)).transpose()?;
+        if let Some(blob_id) = live_blob_id {
+            self.roll_digest(id, blob_id);
+            self.log(key, audit_key, [(AuditEvent::Revoked, id)])?;
         }
         Ok(())
     }
@@ -404,7 +1271,8 @@ impl<P: BoxProvider> Vault<P> {
         entry.get_blob(key, id)
     }
 
-    /// Sorts through all of the vault entries and garbage collects any revoked entries.
+    /// Sorts through all of the vault entries and garbage collects any revoked entries. The deletions are not
+    /// recorded in the audit log, see [`Self::garbage_collect_expired`].
     pub fn garbage_collect(&mut self) {
         // get the keys of the entries with the revocation transactions.
         let garbage: Vec<ChainId> = self
@@ -417,9 +1285,328 @@ impl<P: BoxProvider> Vault<P> {
         // remove the garbage entries from the database.
         garbage.iter().for_each(|c| {
             self.entries.remove(c);
+            self.history.remove(c);
         });
     }
 
+    /// Garbage collects the revoked entries, that have been revoked at least `retention` ago, and the expired
+    /// entries. The deletions are recorded in the audit log. Returns the number of removed entries.
+    pub fn garbage_collect_expired(
+        &mut self,
+        key: &Key<P>,
+        audit_key: &Key<P>,
+        retention: Duration,
+    ) -> Result<usize, RecordError<P::Error>> {
+        self.check_key(key)?;
+        let purged = self.purge_expired(key, audit_key)?;
+
+        let now = SystemTime::now();
+        let mut garbage = Vec::new();
+        for (&id, entry) in self.entries.iter().filter(|(_, entry)| entry.revoke.is_some()) {
+            let expired = retention.is_zero()
+                || entry
+                    .revoked_at(key)?
+                    .and_then(|revoked_at| revoked_at.checked_add(retention))
+                    .is_some_and(|expiry| expiry <= now);
+            if expired {
+                garbage.push(id);
+            }
+        }
+
+        garbage.iter().for_each(|c| {
+            self.entries.remove(c);
+            self.history.remove(c);
+        });
+        self.log(key, audit_key, garbage.iter().map(|id| (AuditEvent::Deleted, *id)))?;
+        Ok(purged + garbage.len())
+    }
+
+    /// Returns `true`, if the entry exists and has not been revoked.
+    fn is_live(&self, id: ChainId) -> bool {
+        self.entries.get(&id).is_some_and(|entry| entry.revoke.is_none())
+    }
+
+    /// Decrypts the tag index of the vault.
+    pub(crate) fn tag_index(&self, key: &Key<P>) -> Result<TagIndex, RecordError<P::Error>> {
+        self.check_key(key)?;
+        match &self.entries.tags {
+            Some(record) => {
+                let buffer = record.get_blob(key, tag_index_id())?;
+                let decoded = TagIndex::decode(&buffer.borrow());
+                decoded.map_err(RecordError::CorruptedContent)
+            }
+            None => Ok(TagIndex::default()),
+        }
+    }
+
+    /// Decrypts the audit log of the vault. The chain of MACs is not checked.
+    pub(crate) fn audit_log(&self, key: &Key<P>) -> Result<AuditLog, RecordError<P::Error>> {
+        self.check_key(key)?;
+        match &self.entries.audit {
+            Some(record) => {
+                let buffer = record.get_blob(key, audit_log_id())?;
+                let decoded = AuditLog::decode(&buffer.borrow());
+                decoded.map_err(RecordError::CorruptedContent)
+            }
+            None => Ok(AuditLog::default()),
+        }
+    }
+
+    /// Appends `events` to the audit log, authenticated with `audit_key`.
+    fn log<I>(&mut self, key: &Key<P>, audit_key: &Key<P>, events: I) -> Result<(), RecordError<P::Error>>
+    where
+        I: IntoIterator<Item = (AuditEvent, ChainId)>,
+    {
+        let mut events = events.into_iter().peekable();
+        if events.peek().is_none() {
+            return Ok(());
+        }
+        let mut log = self.audit_log(key)?;
+        let now = SystemTime::now();
+        events.for_each(|(event, id)| log.append(audit_key, event, id.into(), now));
+
+        let blob_id = BlobId::random::<P>().map_err(RecordError::Provider)?;
+        let hint = RecordHint::new(b"").expect("Empty hint is valid");
+        let record = Record::new(key, audit_log_id(), blob_id, &log.encode(), hint).map_err(RecordError::Provider)?;
+        self.entries.audit = Some(record);
+        Ok(())
+    }
+
+    /// Encrypts and stores the tag index. The tags of entries, that don't exist anymore, are dropped.
+    fn store_tag_index(&mut self, key: &Key<P>, mut index: TagIndex) -> Result<(), RecordError<P::Error>> {
+        index
+            .0
+            .retain(|rid, tags| !tags.is_empty() && self.entries.contains_key(&rid.0));
+        if index.0.is_empty() {
+            self.entries.tags = None;
+            return Ok(());
+        }
+
+        let mut encoded = index.encode();
+        let blob_id = BlobId::random::<P>().map_err(RecordError::Provider)?;
+        let hint = RecordHint::new(b"").expect("Empty hint is valid");
+        let record = Record::new(key, tag_index_id(), blob_id, &encoded, hint);
+        encoded.zeroize();
+        self.entries.tags = Some(record.map_err(RecordError::Provider)?);
+        Ok(())
+    }
+
+    /// Sets the tag `name` of an entry to `value`.
+    pub fn set_tag(&mut self, key: &Key<P>, id: ChainId, name: &str, value: &str) -> Result<(), RecordError<P::Error>> {
+        let mut index = self.tag_index(key)?;
+        if !self.is_live(id) {
+            return Err(RecordError::RecordNotFound(id));
+        }
+        index.0.entry(id.into()).or_default().insert(name.into(), value.into());
+        self.store_tag_index(key, index)
+    }
+
+    /// Removes the tag `name` of an entry. Returns `false`, if the entry didn't have the tag.
+    pub fn remove_tag(&mut self, key: &Key<P>, id: ChainId, name: &str) -> Result<bool, RecordError<P::Error>> {
+        let mut index = self.tag_index(key)?;
+        let removed = index
+            .0
+            .get_mut(&RecordId::from(id))
+            .and_then(|tags| tags.remove(name))
+            .is_some();
+        if removed {
+            self.store_tag_index(key, index)?;
+        }
+        Ok(removed)
+    }
+
+    /// Keeps a copy of the current version of an entry, that is about to be overwritten, in the history.
+    fn archive(&mut self, id: ChainId, depth: usize) {
+        if depth == 0 {
+            return;
+        }
+        if let Some(record) = self.entries.get(&id).filter(|r| r.revoke.is_none()) {
+            let versions = self.history.entry(id).or_default();
+            versions.push_front(record.clone());
+            versions.truncate(depth);
+        }
+    }
+
+    fn truncate_history(&mut self, depth: usize) {
+        self.history.values_mut().for_each(|versions| versions.truncate(depth));
+        self.history.retain(|_, versions| !versions.is_empty());
+    }
+
+    /// Returns the number of previous versions of an entry.
+    pub fn version_count(&self, id: ChainId) -> usize {
+        self.history.get(&id).map_or(0, |versions| versions.len())
+    }
+
+    /// Returns the data of the `n`-th previous version of an entry. Version `0` is the current one.
+    pub fn get_version_guard(&self, key: &Key<P>, id: ChainId, n: usize) -> Result<Buffer<u8>, RecordError<P::Error>> {
+        if n == 0 {
+            return self.get_guard(key, id);
+        }
+        self.check_key(key)?;
+        self.history
+            .get(&id)
+            .and_then(|versions| versions.get(n - 1))
+            .ok_or(RecordError::VersionNotFound(id, n))?
+            .get_blob(key, id)
+    }
+
+    /// Makes the `n`-th previous version of an entry the current one, and discards the newer versions.
+    pub fn revert(&mut self, key: &Key<P>, id: ChainId, n: usize) -> Result<(), RecordError<P::Error>> {
+        self.check_key(key)?;
+        if n == 0 {
+            return Ok(());
+        }
+        let versions = self.history.get_mut(&id).filter(|versions| versions.len() >= n);
+        let versions = versions.ok_or(RecordError::VersionNotFound(id, n))?;
+        let record = versions
+            .drain(..n)
+            .next_back()
+            .expect("n > 0 versions have been drained");
+        if versions.is_empty() {
+            self.history.remove(&id);
+        }
+
+        if let Some(old_blob_id) = self.live_blob_id(key, id)? {
+            self.roll_digest(id, old_blob_id);
+        }
+        let blob_id = record.get_blob_id(key, id)?;
+        self.entries.insert(id, record);
+        self.roll_digest(id, blob_id);
+        Ok(())
+    }
+
+    /// Removes the entries, that have not been revoked, but have expired. Returns the number of removed
+    /// entries.
+    pub fn purge_expired(&mut self, key: &Key<P>, audit_key: &Key<P>) -> Result<usize, RecordError<P::Error>> {
+        self.check_key(key)?;
+        let mut expired = Vec::new();
+        for (&id, entry) in self.entries.iter() {
+            if entry.revoke.is_none() && entry.is_expired(key)? {
+                expired.push((id, entry.get_blob_id(key, id)?));
+            }
+        }
+
+        for (id, blob_id) in expired.iter() {
+            self.entries.remove(id);
+            self.roll_digest(*id, *blob_id);
+        }
+        self.log(key, audit_key, expired.iter().map(|(id, _)| (AuditEvent::Expired, *id)))?;
+        Ok(expired.len())
+    }
+
+    /// Removes the revocation transaction of an entry, so that it becomes readable again.
+    /// Returns `false`, if the entry doesn't exist or hasn't been revoked.
+    pub fn restore(&mut self, key: &Key<P>, audit_key: &Key<P>, id: ChainId) -> Result<bool, RecordError<P::Error>> {
+        self.check_key(key)?;
+        let restored = self
+            .entries
+            .update(&id, |entry| entry.revoke.take().is_some())
+            .unwrap_or(false);
+        if restored {
+            if let Some(blob_id) = self.live_blob_id(key, id)? {
+                self.roll_digest(id, blob_id);
+            }
+            self.log(key, audit_key, [(AuditEvent::Restored, id)])?;
+        }
+        Ok(restored)
+    }
+
+    /// Lists the revoked entries with the time of their revocation.
+    pub fn list_revoked(&self, key: &Key<P>) -> Result<Vec<(RecordId, SystemTime)>, RecordError<P::Error>> {
+        self.check_key(key)?;
+        let mut buf = Vec::new();
+        for (&id, entry) in self.entries.iter() {
+            if let Some(revoked_at) = entry.revoked_at(key)? {
+                buf.push((id.into(), revoked_at));
+            }
+        }
+        Ok(buf)
+    }
+
+    /// Re-encrypts all non-revoked entries with `new_key` and replaces the key of the vault. Revoked
+    /// entries are dropped. Returns the number of re-encrypted entries.
+    pub fn rekey(
+        &mut self,
+        old_key: &Key<P>,
+        new_key: &Key<P>,
+        audit_key: &Key<P>,
+    ) -> Result<usize, RecordError<P::Error>> {
+        self.check_key(old_key)?;
+        let dropped: Vec<ChainId> = self
+            .entries
+            .iter()
+            .filter(|(_, r)| r.revoke.is_some())
+            .map(|(id, _)| *id)
+            .collect();
+        let mut entries = HashMap::with_capacity(self.entries.len());
+        for (&id, record) in self.entries.iter().filter(|(_, r)| r.revoke.is_none()) {
+            let mut record = record.clone();
+            record.update_meta(old_key, id, new_key, id)?;
+            entries.insert(id, record);
+        }
+
+        let mut history = HashMap::with_capacity(self.history.len());
+        for (&id, versions) in self.history.iter() {
+            let mut rekeyed = VecDeque::with_capacity(versions.len());
+            for record in versions {
+                let mut record = record.clone();
+                record.update_meta(old_key, id, new_key, id)?;
+                rekeyed.push_back(record);
+            }
+            history.insert(id, rekeyed);
+        }
+
+        let rekey_reserved = |record: &Option<Record>, id: ChainId| match record {
+            Some(record) => {
+                let mut record = record.clone();
+                record.update_meta(old_key, id, new_key, id).map(|_| Some(record))
+            }
+            None => Ok(None),
+        };
+        let tags = rekey_reserved(&self.entries.tags, tag_index_id())?;
+        let audit = rekey_reserved(&self.entries.audit, audit_log_id())?;
+
+        // the digest does not depend on the key, and revoked entries are not part of it
+        let rekeyed = entries.len();
+        self.entries = Entries::new(entries, tags, audit);
+        self.history = history;
+        self.key = new_key.clone();
+        self.log(
+            new_key,
+            audit_key,
+            dropped.into_iter().map(|id| (AuditEvent::Deleted, id)),
+        )?;
+        Ok(rekeyed)
+    }
+
+    /// Returns the number of entries that contain a revocation transaction.
+    pub fn pending_garbage(&self) -> usize {
+        self.entries.values().filter(|entry| entry.revoke.is_some()).count()
+    }
+
+    /// Returns the number of entries and the size of their plaintext.
+    pub fn usage(&self) -> (usize, usize) {
+        let records = self.entries.len();
+        (
+            records,
+            self.entries.blob_bytes.saturating_sub(records * P::box_overhead()),
+        )
+    }
+
+    /// Returns the number and the encrypted size of the entries that contain a revocation transaction.
+    pub fn gc_stats(&self) -> GcStats {
+        self.entries
+            .values()
+            .filter(|entry| entry.revoke.is_some())
+            .fold(GcStats::default(), |stats, entry| {
+                stats
+                    + GcStats {
+                        revoked: 1,
+                        reclaimable_bytes: entry.encrypted_size(),
+                    }
+            })
+    }
+
     /// Gets the [`BlobId`] of the record with the given [`ChainId`].
     pub fn get_blob_id(&self, key: &Key<P>, id: ChainId) -> Result<BlobId, RecordError<P::Error>> {
         self.check_key(key)?;
@@ -429,6 +1616,38 @@ impl<P: BoxProvider> Vault<P> {
             .and_then(|r| r.get_blob_id(key, id))
     }
 
+    /// Returns the digest over all non-revoked entries. The digest is rebuilt, if it is not present,
+    /// e.g. after the vault has been deserialized.
+    pub fn digest(&mut self, key: &Key<P>) -> Result<[u8; 32], RecordError<P::Error>> {
+        self.check_key(key)?;
+        if let Some(digest) = self.digest {
+            return Ok(digest);
+        }
+        let mut digest = [0u8; 32];
+        for (&id, record) in self.entries.iter().filter(|(_, r)| r.revoke.is_none()) {
+            let blob_id = record.get_blob_id(key, id)?;
+            xor_into(&mut digest, &entry_digest(id, blob_id));
+        }
+        self.digest = Some(digest);
+        Ok(digest)
+    }
+
+    /// Returns the [`BlobId`] of an entry, if it exists and has not been revoked.
+    fn live_blob_id(&self, key: &Key<P>, id: ChainId) -> Result<Option<BlobId>, RecordError<P::Error>> {
+        match self.entries.get(&id) {
+            Some(record) if record.revoke.is_none() => record.get_blob_id(key, id).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Adds or removes an entry from the rolling digest. Since the digest is the xor over all entries,
+    /// both operations are the same.
+    fn roll_digest(&mut self, id: ChainId, blob_id: BlobId) {
+        if let Some(digest) = self.digest.as_mut() {
+            xor_into(digest, &entry_digest(id, blob_id));
+        }
+    }
+
     fn check_key(&self, key: &Key<P>) -> Result<(), RecordError<P::Error>> {
         if key == &self.key {
             Ok(())
@@ -438,7 +1657,37 @@ impl<P: BoxProvider> Vault<P> {
     }
 }
 
+fn entry_digest(id: ChainId, blob_id: BlobId) -> [u8; 32] {
+    let mut hasher = Blake2b256::new();
+    hasher.update(id.as_ref());
+    hasher.update(blob_id.as_ref());
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&hasher.finalize());
+    digest
+}
+
+fn xor_into(target: &mut [u8; 32], other: &[u8; 32]) {
+    target.iter_mut().zip(other.iter()).for_each(|(t, o)| *t ^= o);
+}
+
 impl Record {
+    /// Size of the plaintext of the record.
+    pub fn plaintext_size<P: BoxProvider>(&self) -> usize {
+        self.blob_size().saturating_sub(P::box_overhead())
+    }
+
+    /// Size of the encrypted blob of the record.
+    fn blob_size(&self) -> usize {
+        self.blob.as_ref().len()
+    }
+
+    /// Size of the encrypted transactions and the encrypted blob of the record.
+    pub fn encrypted_size(&self) -> usize {
+        self.data.as_ref().len()
+            + self.revoke.as_ref().map_or(0, |revoke| revoke.as_ref().len())
+            + self.blob.as_ref().len()
+    }
+
     // create a new [`Record`].
     pub fn new<P: BoxProvider>(
         key: &Key<P>,
@@ -446,9 +1695,21 @@ impl Record {
         blob: BlobId,
         data: &[u8],
         hint: RecordHint,
+    ) -> Result<Record, P::Error> {
+        Self::with_expiry(key, id, blob, data, hint, None)
+    }
+
+    /// Create a new [`Record`], that expires at `expires_at`.
+    pub fn with_expiry<P: BoxProvider>(
+        key: &Key<P>,
+        id: ChainId,
+        blob: BlobId,
+        data: &[u8],
+        hint: RecordHint,
+        expires_at: Option<SystemTime>,
     ) -> Result<Record, P::Error> {
         let len = data.len() as u64;
-        let dtx = DataTransaction::new(id, len, blob, hint);
+        let dtx = DataTransaction::with_expiry(id, len, blob, hint, expires_at);
 
         let blob: SealedBlob = data.encrypt(key, blob)?;
         let data = dtx.encrypt(key, id)?;
@@ -507,6 +1768,9 @@ impl Record {
         let tx = tx.typed::<DataTransaction>().ok_or_else(|| {
             RecordError::CorruptedContent("Could not type decrypted transaction as data-transaction".into())
         })?;
+        if tx.is_expired() {
+            return Err(RecordError::Expired(id));
+        }
 
         let blob = SealedBlob::from(self.blob.as_ref())
             .decrypt(key, tx.blob)
@@ -539,13 +1803,23 @@ impl Record {
         Ok(tx.blob)
     }
 
-    /// Update the data in an existing [`Record`].
+    /// Returns `true`, if the [`Record`] has an expiry, that has passed.
+    fn is_expired<P: BoxProvider>(&self, key: &Key<P>) -> Result<bool, RecordError<P::Error>> {
+        let tx = self.get_transaction(key)?;
+        let tx = tx.typed::<DataTransaction>().ok_or_else(|| {
+            RecordError::CorruptedContent("Could not type decrypted transaction as data-transaction".into())
+        })?;
+        Ok(tx.is_expired())
+    }
+
+    /// Update the data and the expiry in an existing [`Record`].
     fn update_data<P: BoxProvider>(
         &mut self,
         key: &Key<P>,
         id: ChainId,
         new_data: &[u8],
         new_blob: BlobId,
+        expires_at: Option<SystemTime>,
     ) -> Result<(), RecordError<P::Error>> {
         // check if ids match
         if self.id != id {
@@ -561,7 +1835,7 @@ impl Record {
         let blob: SealedBlob = new_data.encrypt(key, new_blob).map_err(RecordError::Provider)?;
 
         // create a new sealed transaction with the new_data length.
-        let dtx = DataTransaction::new(tx.id, new_data.len() as u64, new_blob, tx.record_hint);
+        let dtx = DataTransaction::with_expiry(tx.id, new_data.len() as u64, new_blob, tx.record_hint, expires_at);
         let data = dtx.encrypt(key, tx.id).map_err(RecordError::Provider)?;
 
         self.blob = blob;
@@ -599,9 +1873,15 @@ impl Record {
             .map_err(RecordError::Provider)?;
 
         // Re-encrypt meta data with new key.
-        let updated_data = DataTransaction::new(new_id, typed_tx.len, typed_tx.blob, typed_tx.record_hint)
-            .encrypt(new_key, new_id)
-            .map_err(RecordError::Provider)?;
+        let updated_data = DataTransaction::with_expiry(
+            new_id,
+            typed_tx.len,
+            typed_tx.blob,
+            typed_tx.record_hint,
+            typed_tx.expiry(),
+        )
+        .encrypt(new_key, new_id)
+        .map_err(RecordError::Provider)?;
 
         self.blob = updated_blob;
         self.data = updated_data;
@@ -610,6 +1890,24 @@ impl Record {
         Ok(())
     }
 
+    /// Returns the time the [`Record`] has been revoked, or `None`, if it has not been revoked.
+    fn revoked_at<P: BoxProvider>(&self, key: &Key<P>) -> Result<Option<SystemTime>, RecordError<P::Error>> {
+        let revoke = match &self.revoke {
+            Some(revoke) => revoke,
+            None => return Ok(None),
+        };
+        let tx: Transaction = revoke.decrypt(key, self.id).map_err(|err| match err {
+            DecryptError::Invalid => {
+                RecordError::CorruptedContent("Could not convert bytes into transaction structure".into())
+            }
+            DecryptError::Provider(e) => RecordError::Provider(e),
+        })?;
+        let tx = tx.typed::<RevocationTransaction>().ok_or_else(|| {
+            RecordError::CorruptedContent("Could not type decrypted transaction as revocation-transaction".into())
+        })?;
+        Ok(Some(UNIX_EPOCH + Duration::from_secs(tx.revoked_at.u64())))
+    }
+
     // add a revocation transaction to the [`Record`].
     fn revoke<P: BoxProvider>(&mut self, key: &Key<P>, id: ChainId) -> Result<(), RecordError<P::Error>> {
         // check if id and id match.
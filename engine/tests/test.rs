@@ -74,3 +74,15 @@ fn test_scanner() {
 
     assert!(scanner.is_some())
 }
+
+#[test]
+fn test_len_skips_expired() {
+    let mut cache = Cache::new();
+
+    cache.insert("a", 1, None);
+    cache.insert("b", 2, Some(Duration::default()));
+
+    assert_eq!(cache.len(), 1);
+    assert!(!cache.is_empty());
+    assert_eq!(cache.iter().map(|(k, _)| *k).collect::<Vec<_>>(), vec!["a"]);
+}
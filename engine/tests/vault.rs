@@ -2,11 +2,17 @@
 // SPDX-License-Identifier: Apache-2.0
 
 mod utils;
-use std::convert::Infallible;
+use std::{
+    convert::Infallible,
+    time::{Duration, SystemTime},
+};
 
 use utils::provider::Provider;
 
-use engine::vault::{DbView, Key, RecordHint, RecordId, VaultId};
+use engine::vault::{
+    AuditEvent, DbView, GcStats, HintFilter, Key, ListOptions, Quota, RecordHint, RecordId, RecordOrder, VaultError,
+    VaultId, WriteRequest,
+};
 
 #[test]
 fn test_vaults() {
@@ -69,10 +75,13 @@ fn test_vaults() {
     let list0 = view.list_hints_and_ids(&key0, vid0);
 
     assert_eq!(list0.len(), 1);
+    assert_eq!(view.pending_garbage(), 2);
 
     // garbage collect vid0.
     view.garbage_collect_vault(&key0, vid0);
 
+    assert_eq!(view.pending_garbage(), 1);
+
     let list0 = view.list_hints_and_ids(&key0, vid0);
 
     assert_eq!(list0.len(), 1);
@@ -140,3 +149,475 @@ fn test_vaults() {
     })
     .unwrap();
 }
+
+#[test]
+fn test_restore_revoked_records() {
+    let mut view: DbView<Provider> = DbView::new();
+
+    let key = Key::random();
+    let vid = VaultId::random::<Provider>().unwrap();
+    let rid0 = RecordId::random::<Provider>().unwrap();
+    let rid1 = RecordId::random::<Provider>().unwrap();
+
+    view.write(&key, vid, rid0, b"test0", RecordHint::new(b"hint").unwrap())
+        .unwrap();
+    view.write(&key, vid, rid1, b"test1", RecordHint::new(b"hint").unwrap())
+        .unwrap();
+    let digest = view.vault_digest(&key, vid).unwrap();
+
+    view.revoke_record(&key, vid, rid0).unwrap();
+    view.revoke_record(&key, vid, rid1).unwrap();
+    assert!(!view.contains_record(vid, rid0));
+
+    let revoked = view.list_revoked_records(&key, vid).unwrap();
+    assert_eq!(revoked.len(), 2);
+    assert!(revoked
+        .iter()
+        .all(|(_, at)| at.elapsed().unwrap() < Duration::from_secs(60)));
+
+    // records within the retention window are kept
+    assert_eq!(
+        view.garbage_collect_vault_expired(&key, vid, Duration::from_secs(3600))
+            .unwrap(),
+        0
+    );
+
+    assert!(view.restore_record(&key, vid, rid0).unwrap());
+    assert!(!view.restore_record(&key, vid, rid0).unwrap());
+    assert!(view.contains_record(vid, rid0));
+    view.get_guard::<Infallible, _>(&key, vid, rid0, |g| {
+        assert_eq!(b"test0", &(*g.borrow()));
+        Ok(())
+    })
+    .unwrap();
+
+    // restoring all records restores the digest
+    assert!(view.restore_record(&key, vid, rid1).unwrap());
+    assert_eq!(view.vault_digest(&key, vid).unwrap(), digest);
+
+    view.revoke_record(&key, vid, rid1).unwrap();
+    assert_eq!(
+        view.garbage_collect_vault_expired(&key, vid, Duration::ZERO).unwrap(),
+        1
+    );
+    assert!(!view.restore_record(&key, vid, rid1).unwrap());
+    assert_eq!(view.pending_garbage(), 0);
+}
+
+#[test]
+fn test_revocation_history() {
+    let mut view: DbView<Provider> = DbView::new();
+    view.set_audit_key(Key::random());
+
+    let key = Key::random();
+    let vid = VaultId::random::<Provider>().unwrap();
+    let rid = RecordId::random::<Provider>().unwrap();
+
+    view.write(&key, vid, rid, b"test", RecordHint::new(b"hint").unwrap())
+        .unwrap();
+    view.revoke_record(&key, vid, rid).unwrap();
+    assert!(view.restore_record(&key, vid, rid).unwrap());
+    view.revoke_record(&key, vid, rid).unwrap();
+    view.garbage_collect_vault(&key, vid);
+
+    let events: Vec<AuditEvent> = view
+        .revocation_history(&key, vid)
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.event)
+        .collect();
+    assert_eq!(
+        events,
+        [
+            AuditEvent::Revoked,
+            AuditEvent::Restored,
+            AuditEvent::Revoked,
+            AuditEvent::Deleted
+        ]
+    );
+
+    // the key of the vault alone does not suffice to authenticate the log
+    let mut other = view.clone();
+    other.set_audit_key(Key::random());
+    assert!(other.revocation_history(&key, vid).is_err());
+}
+
+#[test]
+fn test_list_hints_and_ids_paged() {
+    let mut view: DbView<Provider> = DbView::new();
+
+    let key = Key::random();
+    let vid = VaultId::random::<Provider>().unwrap();
+
+    for i in 0..10u8 {
+        let rid = RecordId::random::<Provider>().unwrap();
+        let hint = format!("record-{}", 9 - i);
+        view.write(&key, vid, rid, &[i], RecordHint::new(hint).unwrap())
+            .unwrap();
+    }
+
+    let options = ListOptions {
+        limit: Some(4),
+        order: RecordOrder::Hint,
+        with_labels: true,
+        ..Default::default()
+    };
+    let first = view.list_hints_and_ids_paged(&key, vid, &options);
+    assert_eq!(first.total, 10);
+    let labels: Vec<_> = first.entries.iter().map(|e| e.label.clone().unwrap()).collect();
+    assert_eq!(labels, ["record-0", "record-1", "record-2", "record-3"]);
+
+    let last = view.list_hints_and_ids_paged(&key, vid, &ListOptions { offset: 8, ..options });
+    assert_eq!(last.entries.len(), 2);
+    assert_eq!(last.entries[1].label.as_deref(), Some("record-9"));
+
+    // pages in id order cover all records exactly once
+    let mut ids = Vec::new();
+    for page in 0..4 {
+        let options = ListOptions {
+            offset: page * 3,
+            limit: Some(3),
+            ..Default::default()
+        };
+        let page = view.list_hints_and_ids_paged(&key, vid, &options);
+        assert!(page.entries.iter().all(|e| e.label.is_none()));
+        ids.extend(page.entries.into_iter().map(|e| e.id));
+    }
+    let mut all: Vec<_> = view
+        .list_hints_and_ids(&key, vid)
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    all.sort();
+    assert_eq!(ids, all);
+}
+
+#[test]
+fn test_rekey_vault() {
+    let mut view: DbView<Provider> = DbView::new();
+
+    let old_key = Key::random();
+    let new_key = Key::random();
+    let vid = VaultId::random::<Provider>().unwrap();
+    let rid0 = RecordId::random::<Provider>().unwrap();
+    let rid1 = RecordId::random::<Provider>().unwrap();
+    let revoked = RecordId::random::<Provider>().unwrap();
+
+    for (rid, data) in [(rid0, b"test0"), (rid1, b"test1"), (revoked, b"test2")] {
+        view.write(&old_key, vid, rid, data, RecordHint::new(b"hint").unwrap())
+            .unwrap();
+    }
+    view.revoke_record(&old_key, vid, revoked).unwrap();
+    let digest = view.vault_digest(&old_key, vid).unwrap();
+
+    assert!(view.rekey_vault(&new_key, &old_key, vid).is_err());
+    assert_eq!(view.rekey_vault(&old_key, &new_key, vid).unwrap(), 2);
+
+    // the old key can't access the vault anymore
+    assert!(view
+        .get_guard::<Infallible, _>(&old_key, vid, rid0, |_| Ok(()))
+        .is_err());
+    for (rid, data) in [(rid0, b"test0"), (rid1, b"test1")] {
+        view.get_guard::<Infallible, _>(&new_key, vid, rid, |g| {
+            assert_eq!(data, &(*g.borrow()));
+            Ok(())
+        })
+        .unwrap();
+    }
+    assert_eq!(view.list_hints_and_ids(&new_key, vid).len(), 2);
+    assert_eq!(view.pending_garbage(), 0);
+    assert_eq!(view.vault_digest(&new_key, vid).unwrap(), digest);
+}
+
+#[test]
+fn test_record_expiry() {
+    let mut view: DbView<Provider> = DbView::new();
+
+    let key = Key::random();
+    let vid = VaultId::random::<Provider>().unwrap();
+    let expired = RecordId::random::<Provider>().unwrap();
+    let live = RecordId::random::<Provider>().unwrap();
+    let persistent = RecordId::random::<Provider>().unwrap();
+    let hint = RecordHint::new(b"hint").unwrap();
+
+    let past = SystemTime::now() - Duration::from_secs(1);
+    let future = SystemTime::now() + Duration::from_secs(60 * 60);
+    view.write_with_expiry(&key, vid, expired, b"expired", hint, Some(past))
+        .unwrap();
+    view.write_with_expiry(&key, vid, live, b"live", hint, Some(future))
+        .unwrap();
+    view.write(&key, vid, persistent, b"persistent", hint).unwrap();
+
+    assert!(view.get_guard::<Infallible, _>(&key, vid, expired, |_| Ok(())).is_err());
+    for (rid, data) in [(live, b"live".as_ref()), (persistent, b"persistent".as_ref())] {
+        view.get_guard::<Infallible, _>(&key, vid, rid, |g| {
+            assert_eq!(data, &(*g.borrow()));
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    // the expiry survives a rotation of the vault key
+    let new_key = Key::random();
+    assert_eq!(view.rekey_vault(&key, &new_key, vid).unwrap(), 3);
+    assert!(view
+        .get_guard::<Infallible, _>(&new_key, vid, expired, |_| Ok(()))
+        .is_err());
+
+    assert_eq!(
+        view.garbage_collect_vault_expired(&new_key, vid, Duration::ZERO)
+            .unwrap(),
+        1
+    );
+    assert!(!view.contains_record(vid, expired));
+    assert!(view.contains_record(vid, live));
+    assert!(view.contains_record(vid, persistent));
+}
+
+#[test]
+fn test_iter_records() {
+    let mut view: DbView<Provider> = DbView::new();
+
+    let key = Key::random();
+    let vid = VaultId::random::<Provider>().unwrap();
+    let mut accounts = Vec::new();
+    for i in 0..10u8 {
+        let rid = RecordId::random::<Provider>().unwrap();
+        let hint = if i % 2 == 0 {
+            accounts.push(rid);
+            RecordHint::new(format!("account/{}", i)).unwrap()
+        } else {
+            RecordHint::new(format!("identity/{}", i)).unwrap()
+        };
+        view.write(&key, vid, rid, &[i], hint).unwrap();
+    }
+
+    let mut matching: Vec<RecordId> = view
+        .iter_records(&key, vid, HintFilter::Prefix(b"account/".to_vec()))
+        .map(|(id, _)| id)
+        .collect();
+    matching.sort();
+    accounts.sort();
+    assert_eq!(matching, accounts);
+
+    let exact = RecordHint::new("identity/3").unwrap();
+    let found: Vec<_> = view.iter_records(&key, vid, HintFilter::Exact(exact)).collect();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].1, exact);
+
+    assert_eq!(view.iter_records(&key, vid, HintFilter::Any).count(), 10);
+    assert_eq!(view.iter_records(&Key::random(), vid, HintFilter::Any).count(), 0);
+}
+
+#[test]
+fn test_record_history() {
+    let mut view: DbView<Provider> = DbView::new();
+    view.set_history_depth(2);
+
+    let key = Key::random();
+    let vid = VaultId::random::<Provider>().unwrap();
+    let rid = RecordId::random::<Provider>().unwrap();
+    let hint = RecordHint::new(b"hint").unwrap();
+
+    for data in [b"v0", b"v1", b"v2", b"v3"] {
+        view.write(&key, vid, rid, data, hint).unwrap();
+    }
+    assert_eq!(view.version_count(vid, rid), 2);
+
+    let read = |view: &DbView<Provider>, n: usize| {
+        let mut data = Vec::new();
+        view.get_version_guard::<Infallible, _>(&key, vid, rid, n, |g| {
+            data = g.borrow().to_vec();
+            Ok(())
+        })
+        .map(|_| data)
+    };
+    assert_eq!(read(&view, 0).unwrap(), b"v3");
+    assert_eq!(read(&view, 1).unwrap(), b"v2");
+    assert_eq!(read(&view, 2).unwrap(), b"v1");
+    assert!(read(&view, 3).is_err());
+
+    assert!(view.revert_record(&key, vid, rid, 3).is_err());
+    view.revert_record(&key, vid, rid, 2).unwrap();
+    assert_eq!(read(&view, 0).unwrap(), b"v1");
+    assert_eq!(view.version_count(vid, rid), 0);
+
+    // garbage collection drops only the history of the collected records
+    let revoked = RecordId::random::<Provider>().unwrap();
+    view.write(&key, vid, revoked, b"r0", hint).unwrap();
+    view.write(&key, vid, revoked, b"r1", hint).unwrap();
+    view.write(&key, vid, rid, b"v4", hint).unwrap();
+    assert_eq!(view.version_count(vid, rid), 1);
+    assert_eq!(view.version_count(vid, revoked), 1);
+    view.revoke_record(&key, vid, revoked).unwrap();
+    view.garbage_collect_vault(&key, vid);
+    assert_eq!(view.version_count(vid, revoked), 0);
+    assert_eq!(view.version_count(vid, rid), 1);
+    assert_eq!(read(&view, 1).unwrap(), b"v1");
+}
+
+#[test]
+fn test_batch() {
+    let mut view: DbView<Provider> = DbView::new();
+
+    let key = Key::random();
+    let vid = VaultId::random::<Provider>().unwrap();
+    let records: Vec<(RecordId, Vec<u8>)> = (0..100u8)
+        .map(|i| (RecordId::random::<Provider>().unwrap(), vec![i; 32]))
+        .collect();
+    let requests = records
+        .iter()
+        .map(|(record_id, data)| WriteRequest {
+            record_id: *record_id,
+            data: data.clone(),
+            hint: RecordHint::new(b"batch").unwrap(),
+        })
+        .collect();
+
+    assert!(view.write_batch(&key, vid, Vec::new()).is_ok());
+    view.write_batch(&key, vid, requests).unwrap();
+    assert_eq!(view.list_hints_and_ids(&key, vid).len(), records.len());
+    assert!(view.write_batch(&Key::random(), vid, Vec::new()).is_err());
+
+    let rids: Vec<RecordId> = records.iter().map(|(id, _)| *id).collect();
+    view.read_batch::<Infallible, _>(&key, vid, &rids, |buffers| {
+        assert_eq!(buffers.len(), records.len());
+        for (buffer, (_, data)) in buffers.iter().zip(records.iter()) {
+            assert_eq!(data.as_slice(), &(*buffer.borrow()));
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    let missing = [rids[0], RecordId::random::<Provider>().unwrap()];
+    assert!(view
+        .read_batch::<Infallible, _>(&key, vid, &missing, |_| Ok(()))
+        .is_err());
+}
+
+#[test]
+fn test_gc_stats() {
+    let mut view: DbView<Provider> = DbView::new();
+    let key = Key::random();
+    let vid = VaultId::random::<Provider>().unwrap();
+    let rids: Vec<RecordId> = (0..3).map(|_| RecordId::random::<Provider>().unwrap()).collect();
+
+    view.init_vault(&key, vid);
+    for rid in rids.iter() {
+        view.write(&key, vid, *rid, &[0u8; 64], RecordHint::new(b"hint").unwrap())
+            .unwrap();
+    }
+    assert_eq!(view.gc_stats(), GcStats::default());
+    assert_eq!(view.vault_gc_stats(vid), Some(GcStats::default()));
+    assert_eq!(view.vault_gc_stats(VaultId::random::<Provider>().unwrap()), None);
+
+    view.revoke_record(&key, vid, rids[0]).unwrap();
+    view.revoke_record(&key, vid, rids[1]).unwrap();
+    let stats = view.gc_stats();
+    assert_eq!(stats.revoked, 2);
+    assert!(stats.reclaimable_bytes > 2 * 64);
+    assert_eq!(view.vault_gc_stats(vid), Some(stats));
+
+    view.garbage_collect_vault(&key, vid);
+    assert_eq!(view.gc_stats(), GcStats::default());
+}
+
+#[test]
+fn test_quota() {
+    let mut view: DbView<Provider> = DbView::new();
+    let key = Key::random();
+    let vid0 = VaultId::random::<Provider>().unwrap();
+    let vid1 = VaultId::random::<Provider>().unwrap();
+    let hint = RecordHint::new(b"hint").unwrap();
+
+    view.set_vault_quota(
+        vid0,
+        Quota {
+            max_records: Some(2),
+            max_bytes: None,
+        },
+    );
+    let rid0 = RecordId::random::<Provider>().unwrap();
+    view.write(&key, vid0, rid0, &[0u8; 32], hint).unwrap();
+    view.write(&key, vid0, RecordId::random::<Provider>().unwrap(), &[0u8; 32], hint)
+        .unwrap();
+    assert!(matches!(
+        view.write(&key, vid0, RecordId::random::<Provider>().unwrap(), &[0u8; 32], hint),
+        Err(VaultError::QuotaExceeded(vid)) if vid == vid0
+    ));
+    // overwriting an existing record doesn't add a record
+    view.write(&key, vid0, rid0, &[1u8; 32], hint).unwrap();
+
+    // the quota of all vaults together
+    view.set_quota(Quota {
+        max_records: None,
+        max_bytes: Some(100),
+    });
+    view.write(&key, vid1, RecordId::random::<Provider>().unwrap(), &[0u8; 36], hint)
+        .unwrap();
+    let requests = vec![
+        WriteRequest {
+            record_id: RecordId::random::<Provider>().unwrap(),
+            data: vec![0u8; 1],
+            hint,
+        },
+        WriteRequest {
+            record_id: RecordId::random::<Provider>().unwrap(),
+            data: vec![0u8; 1],
+            hint,
+        },
+    ];
+    assert!(matches!(
+        view.write_batch(&key, vid1, requests),
+        Err(VaultError::QuotaExceeded(_))
+    ));
+    assert_eq!(view.list_hints_and_ids(&key, vid1).len(), 1);
+
+    view.set_quota(Quota::default());
+    view.set_vault_quota(vid0, Quota::default());
+    view.write(&key, vid0, RecordId::random::<Provider>().unwrap(), &[0u8; 32], hint)
+        .unwrap();
+}
+
+#[test]
+fn test_quota_import() {
+    let mut source: DbView<Provider> = DbView::new();
+    let mut target: DbView<Provider> = DbView::new();
+    let key = Key::random();
+    let vid = VaultId::random::<Provider>().unwrap();
+    let hint = RecordHint::new(b"hint").unwrap();
+
+    let rids: Vec<RecordId> = (0..2).map(|_| RecordId::random::<Provider>().unwrap()).collect();
+    for rid in rids.iter() {
+        source.write(&key, vid, *rid, &[0u8; 32], hint).unwrap();
+    }
+    let records = || {
+        rids.iter()
+            .map(|rid| (*rid, source.vaults[&vid].export_record(rid).unwrap()))
+            .collect::<Vec<_>>()
+    };
+
+    target.set_vault_quota(
+        vid,
+        Quota {
+            max_records: Some(1),
+            max_bytes: None,
+        },
+    );
+    assert!(matches!(
+        target.import_records(&key, &key, vid, records()),
+        Err(VaultError::QuotaExceeded(_))
+    ));
+    assert!(target.list_hints_and_ids(&key, vid).is_empty());
+
+    target.set_vault_quota(vid, Quota::default());
+    target.import_records(&key, &key, vid, records()).unwrap();
+    assert_eq!(target.vaults[&vid].usage(), (2, 64));
+
+    // the usage follows updates and deletions of the records
+    target.write(&key, vid, rids[0], &[0u8; 8], hint).unwrap();
+    assert_eq!(target.vaults[&vid].usage(), (2, 40));
+    target.revoke_record(&key, vid, rids[1]).unwrap();
+    target.garbage_collect_vault(&key, vid);
+    assert_eq!(target.vaults[&vid].usage(), (1, 8));
+}